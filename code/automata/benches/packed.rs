@@ -0,0 +1,103 @@
+//! Packed `Automaton` against the original one-`bool`-per-cell row.
+//!
+//! Run with `cargo bench --bench packed`.
+
+#![allow(clippy::needless_range_loop)]
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::hint::black_box;
+
+use automata::Automaton;
+use automata::inference::hashed_row;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+const WIDTHS: [usize; 2] = [100_000, 1_000_000];
+
+/// The unpacked representation `Automaton` used before bit-packing
+struct BoolRow {
+    cells: Vec<bool>,
+    rule: u8,
+}
+
+impl BoolRow {
+    fn step(&mut self) {
+        let n = self.cells.len();
+        let mut next = vec![false; n];
+        for i in 0..n {
+            let left = self.cells[(i + n - 1) % n];
+            let center = self.cells[i];
+            let right = self.cells[(i + 1) % n];
+            let index = (left as u8) << 2 | (center as u8) << 1 | right as u8;
+            next[i] = (self.rule >> index) & 1 == 1;
+        }
+        self.cells = next;
+    }
+
+    fn population(&self) -> usize {
+        self.cells.iter().filter(|&&c| c).count()
+    }
+
+    fn state_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.cells.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+fn rows(width: usize) -> (Automaton, BoolRow) {
+    let row = hashed_row(12345, width, 50);
+    (
+        Automaton::from_cells(row.clone(), 110),
+        BoolRow {
+            cells: row,
+            rule: 110,
+        },
+    )
+}
+
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    group.sample_size(10);
+    for width in WIDTHS {
+        let (mut packed, mut unpacked) = rows(width);
+        group.bench_function(BenchmarkId::new("packed", width), |b| {
+            b.iter(|| packed.step())
+        });
+        group.bench_function(BenchmarkId::new("bool", width), |b| {
+            b.iter(|| unpacked.step())
+        });
+    }
+    group.finish();
+}
+
+fn population(c: &mut Criterion) {
+    let mut group = c.benchmark_group("population");
+    for width in WIDTHS {
+        let (packed, unpacked) = rows(width);
+        group.bench_function(BenchmarkId::new("packed", width), |b| {
+            b.iter(|| black_box(&packed).population())
+        });
+        group.bench_function(BenchmarkId::new("bool", width), |b| {
+            b.iter(|| black_box(&unpacked).population())
+        });
+    }
+    group.finish();
+}
+
+fn state_hash(c: &mut Criterion) {
+    let mut group = c.benchmark_group("state_hash");
+    for width in WIDTHS {
+        let (packed, unpacked) = rows(width);
+        group.bench_function(BenchmarkId::new("packed", width), |b| {
+            b.iter(|| black_box(&packed).state_hash())
+        });
+        group.bench_function(BenchmarkId::new("bool", width), |b| {
+            b.iter(|| black_box(&unpacked).state_hash())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, step, population, state_hash);
+criterion_main!(benches);
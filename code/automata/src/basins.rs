@@ -0,0 +1,847 @@
+//! Exhaustive state-transition graphs of small rings
+//!
+//! A ring of n cells has 2^n states, and the rule maps each to exactly one
+//! successor, so every trajectory is a "rho": a transient tail running into
+//! a cycle. For n up to `MAX_WIDTH` every state's successor fits in memory,
+//! and one pass over the graph labels each state with its attractor (the
+//! cycle it falls into) and its transient length.
+//!
+//! States are the packed row as an integer: bit i is cell i, as in
+//! `Automaton::words`.
+//!
+//! Wider rings are sampled instead: `sample_attractors` runs random rows
+//! until they cycle, tells the cycles apart by their `CycleFingerprint`, and
+//! estimates how many attractors were never hit from how many were hit only
+//! once or twice (capture-recapture statistics). Coin-flip rows almost all
+//! have density near 1/2, so `sample_attractors_stratified` can instead
+//! spread the rows over bands of exact popcounts, with one sample per band.
+//! For narrow enough rings or few enough live cells, `enumerate_by_popcount`
+//! runs every row of each popcount instead: the exact weights the stratified
+//! estimates are after.
+//!
+//! A fingerprint names a cycle independently of where it was entered and of
+//! the ring's rotation, so the same attractor found by different rows or
+//! commands gets the same ID.
+//!
+//! Counting predecessors alone needs no labels: `in_degrees` tallies how
+//! many states map onto each, so its zeros are the Gardens of Eden and a
+//! rule with every in-degree 1 is a bijection of the ring (reversible on
+//! it); the spread in between is how far from that the rule is.
+//!
+//! Up to `MAX_GRAPH_WIDTH` cells the graph itself can be written out, as
+//! Graphviz DOT or GraphML: a node per state, an edge to its successor, and
+//! each node's attractor and transient, for graph tools to draw the basins.
+
+use crate::metadata::Metadata;
+use crate::{apply_rule, profile, tail_mask, Automaton, History, Recall};
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use std::collections::HashMap;
+
+/// Widest ring whose state graph is enumerated: 2^24 states need a few
+/// hundred megabytes of labels
+pub const MAX_WIDTH: usize = 24;
+
+/// Widest ring whose state graph is written out: 2^16 nodes is already more
+/// than layout tools draw legibly
+pub const MAX_GRAPH_WIDTH: usize = 16;
+
+/// One cycle of the state graph and the states that fall into it
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attractor {
+    /// Cycle states in order, starting from the smallest
+    pub cycle: Vec<u32>,
+    /// States ending on this cycle, cycle states included
+    pub basin: usize,
+    /// States with no predecessor (Garden of Eden) in the basin
+    pub gardens: usize,
+    /// Longest transient in the basin
+    pub max_transient: usize,
+    /// Sum of transients over the basin, for the mean
+    pub total_transient: usize,
+}
+
+impl Attractor {
+    pub fn period(&self) -> usize {
+        self.cycle.len()
+    }
+
+    pub fn mean_transient(&self) -> f64 {
+        self.total_transient as f64 / self.basin as f64
+    }
+}
+
+/// Every state of a ring labeled with its attractor and transient length
+pub struct BasinAnalysis {
+    pub rule: u8,
+    pub width: usize,
+    /// Attractors, largest basin first (ties: smallest cycle state first)
+    pub attractors: Vec<Attractor>,
+    /// Index into `attractors` for each state
+    pub attractor: Vec<u32>,
+    /// Steps from each state to its cycle (0 on the cycle)
+    pub transient: Vec<u32>,
+}
+
+impl BasinAnalysis {
+    /// Number of states, 2^width
+    pub fn states(&self) -> usize {
+        self.attractor.len()
+    }
+
+    /// A state's cells, cell 0 first
+    pub fn cells(&self, state: u32) -> String {
+        (0..self.width).map(|i| if state >> i & 1 == 1 { '1' } else { '0' }).collect()
+    }
+
+    /// Each state's successor, and whether it has a predecessor
+    pub(crate) fn edges(&self) -> (Vec<u32>, Vec<bool>) {
+        let next: Vec<u32> = (0..self.states() as u64).map(|s| successor(self.rule, self.width, s) as u32).collect();
+        let mut has_predecessor = vec![false; self.states()];
+        next.iter().for_each(|&s| has_predecessor[s as usize] = true);
+        (next, has_predecessor)
+    }
+
+    /// The state graph in Graphviz DOT, `metadata` in comments at the top: a
+    /// cluster per attractor labelled with its period and basin, cycle
+    /// states filled, Garden-of-Eden states dashed
+    pub fn to_dot(&self, metadata: &Metadata) -> String {
+        assert!(self.width <= MAX_GRAPH_WIDTH, "state graphs are written up to {MAX_GRAPH_WIDTH} cells");
+        let (next, has_predecessor) = self.edges();
+        let mut clusters = vec![String::new(); self.attractors.len()];
+        for s in 0..self.states() {
+            let style = match (self.transient[s], has_predecessor[s]) {
+                (0, _) => ", style=filled, fillcolor=gray80",
+                (_, false) => ", style=dashed",
+                _ => "",
+            };
+            let cluster = &mut clusters[self.attractor[s] as usize];
+            *cluster += &format!("    s{s} [label=\"{}\"{style}];\n", self.cells(s as u32));
+        }
+        let mut out = metadata.comments("// ");
+        out += &format!("digraph \"rule {} on {} cells\" {{\n", self.rule, self.width);
+        out += "  node [shape=box, fontname=monospace];\n";
+        for (id, (a, nodes)) in self.attractors.iter().zip(clusters).enumerate() {
+            let label = format!("attractor {id}: period {}, basin {}", a.period(), a.basin);
+            out += &format!("  subgraph cluster_{id} {{\n    label=\"{label}\";\n{nodes}  }}\n");
+        }
+        for (s, t) in next.iter().enumerate() {
+            out += &format!("  s{s} -> s{t};\n");
+        }
+        out + "}\n"
+    }
+
+    /// The state graph in GraphML, `metadata` in a comment at the top, each
+    /// node with its cells, attractor, transient and whether it is on its
+    /// cycle or a Garden of Eden
+    pub fn to_graphml(&self, metadata: &Metadata) -> String {
+        assert!(self.width <= MAX_GRAPH_WIDTH, "state graphs are written up to {MAX_GRAPH_WIDTH} cells");
+        let (next, has_predecessor) = self.edges();
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        if !metadata.is_empty() {
+            // "--" can't appear inside an XML comment
+            out += &format!("<!--\n{}-->\n", metadata.comments("  ").replace("--", "- -"));
+        }
+        out += "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n";
+        for (key, kind) in
+            [("cells", "string"), ("attractor", "int"), ("transient", "int"), ("cycle", "boolean"), ("garden", "boolean")]
+        {
+            out += &format!("  <key id=\"{key}\" for=\"node\" attr.name=\"{key}\" attr.type=\"{kind}\"/>\n");
+        }
+        out += &format!("  <graph id=\"rule{}-w{}\" edgedefault=\"directed\">\n", self.rule, self.width);
+        for s in 0..self.states() {
+            let data = [
+                ("cells", self.cells(s as u32)),
+                ("attractor", self.attractor[s].to_string()),
+                ("transient", self.transient[s].to_string()),
+                ("cycle", (self.transient[s] == 0).to_string()),
+                ("garden", (!has_predecessor[s]).to_string()),
+            ];
+            let data: String = data.iter().map(|(key, value)| format!("<data key=\"{key}\">{value}</data>")).collect();
+            out += &format!("    <node id=\"s{s}\">{data}</node>\n");
+        }
+        for (s, t) in next.iter().enumerate() {
+            out += &format!("    <edge source=\"s{s}\" target=\"s{t}\"/>\n");
+        }
+        out + "  </graph>\n</graphml>\n"
+    }
+}
+
+/// Successor of a packed state on a ring of `width` cells (at most 63)
+pub fn successor(rule: u8, width: usize, state: u64) -> u64 {
+    let left = (state << 1) | (state >> (width - 1)) & 1;
+    let right = (state >> 1) | (state & 1) << (width - 1);
+    apply_rule(rule, left, state, right) & tail_mask(width)
+}
+
+/// Label every state of a ring of `width` cells (1..=`MAX_WIDTH`)
+pub fn enumerate_basins(rule: u8, width: usize) -> BasinAnalysis {
+    assert!((1..=MAX_WIDTH).contains(&width), "width must be 1..={MAX_WIDTH}");
+    let states = 1usize << width;
+    profile::add_states(states);
+    let next: Vec<u32> = (0..states as u64).into_par_iter().map(|s| successor(rule, width, s) as u32).collect();
+
+    const UNVISITED: u32 = u32::MAX;
+    const ON_PATH: u32 = u32::MAX - 1;
+    let mut attractor = vec![UNVISITED; states];
+    let mut transient = vec![0u32; states];
+    let mut cycles: Vec<Vec<u32>> = Vec::new();
+    let mut path = Vec::new();
+
+    for start in 0..states as u32 {
+        if attractor[start as usize] != UNVISITED {
+            continue;
+        }
+        // Walk until reaching a labeled state or closing a loop on this walk
+        path.clear();
+        let mut state = start;
+        while attractor[state as usize] == UNVISITED {
+            attractor[state as usize] = ON_PATH;
+            path.push(state);
+            state = next[state as usize];
+        }
+        let (id, mut depth) = if attractor[state as usize] == ON_PATH {
+            // A new cycle: the walk from `state` onward
+            let at = path.iter().position(|&s| s == state).unwrap();
+            let mut cycle = path.split_off(at);
+            let id = cycles.len() as u32;
+            for &s in &cycle {
+                attractor[s as usize] = id;
+            }
+            let smallest = (0..cycle.len()).min_by_key(|&i| cycle[i]).unwrap();
+            cycle.rotate_left(smallest);
+            cycles.push(cycle);
+            (id, 0)
+        } else {
+            (attractor[state as usize], transient[state as usize])
+        };
+        // What's left of the walk is the tail, labeled back from its end
+        for &s in path.iter().rev() {
+            depth += 1;
+            attractor[s as usize] = id;
+            transient[s as usize] = depth;
+        }
+    }
+
+    // Per-attractor statistics, then order by basin size
+    let mut has_predecessor = vec![false; states];
+    next.iter().for_each(|&s| has_predecessor[s as usize] = true);
+    let mut attractors: Vec<Attractor> = cycles
+        .into_iter()
+        .map(|cycle| Attractor { cycle, basin: 0, gardens: 0, max_transient: 0, total_transient: 0 })
+        .collect();
+    for s in 0..states {
+        let a = &mut attractors[attractor[s] as usize];
+        let t = transient[s] as usize;
+        a.basin += 1;
+        a.gardens += !has_predecessor[s] as usize;
+        a.max_transient = a.max_transient.max(t);
+        a.total_transient += t;
+    }
+    let mut order: Vec<usize> = (0..attractors.len()).collect();
+    order.sort_by_key(|&i| (std::cmp::Reverse(attractors[i].basin), attractors[i].cycle[0]));
+    let mut rank = vec![0u32; order.len()];
+    for (r, &i) in order.iter().enumerate() {
+        rank[i] = r as u32;
+    }
+    attractor.iter_mut().for_each(|a| *a = rank[*a as usize]);
+    let attractors = order.into_iter().map(|i| attractors[i].clone()).collect();
+
+    BasinAnalysis { rule, width, attractors, attractor, transient }
+}
+
+/// How many states of a ring have each number of predecessors
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InDegrees {
+    pub width: usize,
+    /// States with in-degree d at index d, up to the largest
+    pub counts: Vec<usize>,
+}
+
+impl InDegrees {
+    /// Garden-of-Eden states: those with no predecessor
+    pub fn gardens(&self) -> usize {
+        self.counts[0]
+    }
+
+    pub fn max(&self) -> usize {
+        self.counts.len() - 1
+    }
+
+    /// Whether every state has exactly one predecessor: the rule permutes the ring's states
+    pub fn bijective(&self) -> bool {
+        self.counts == [0, 1 << self.width]
+    }
+
+    /// The counts as "d:n" pairs, zeros left out, for tables and CSV
+    pub fn histogram(&self) -> String {
+        let pairs = self.counts.iter().enumerate().filter(|&(_, &n)| n > 0).map(|(d, n)| format!("{d}:{n}"));
+        pairs.collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// The in-degree distribution of a ring of `width` cells (1..=`MAX_WIDTH`)
+pub fn in_degrees(rule: u8, width: usize) -> InDegrees {
+    assert!((1..=MAX_WIDTH).contains(&width), "width must be 1..={MAX_WIDTH}");
+    let states = 1usize << width;
+    profile::add_states(states);
+    let mut predecessors = vec![0u32; states];
+    (0..states as u64).for_each(|s| predecessors[successor(rule, width, s) as usize] += 1);
+    let mut counts = vec![0usize; predecessors.iter().copied().max().unwrap_or(0) as usize + 1];
+    predecessors.iter().for_each(|&d| counts[d as usize] += 1);
+    InDegrees { width, counts }
+}
+
+/// A cycle up to time shift and rotation of the ring: of the sequences of
+/// states obtained by rotating every state by the same amount and starting
+/// anywhere on the cycle, the smallest (states compared word by word as
+/// `Automaton::words`)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CycleFingerprint {
+    pub width: usize,
+    /// The canonical sequence of states
+    pub canonical: Vec<Vec<u64>>,
+    /// FNV-1a of the width and canonical states, stable across runs and
+    /// platforms (see `HASH_VERSION`)
+    pub hash: u64,
+}
+
+impl CycleFingerprint {
+    /// Fingerprint the cycle through `states` (in order, from any state) of a
+    /// ring of `width` cells
+    pub fn new(states: &[Vec<u64>], width: usize) -> Self {
+        assert!(!states.is_empty(), "a cycle has at least one state");
+        let mut canonical: Option<Vec<Vec<u64>>> = None;
+        for shift in 0..width {
+            let mut rotated: Vec<Vec<u64>> = states.iter().map(|s| rotate(s, width, shift)).collect();
+            let start = (0..rotated.len()).min_by(|&a, &b| rotated[a].cmp(&rotated[b])).unwrap();
+            if canonical.as_ref().is_some_and(|best| rotated[start] > best[0]) {
+                continue;
+            }
+            rotated.rotate_left(start);
+            if canonical.as_ref().is_none_or(|best| rotated < *best) {
+                canonical = Some(rotated);
+            }
+        }
+        let canonical = canonical.unwrap();
+        let words: Vec<u64> = [width as u64, canonical.len() as u64].into_iter().chain(canonical.iter().flatten().copied()).collect();
+        Self { width, hash: fingerprint(&words), canonical }
+    }
+
+    /// Fingerprint of an exhaustively enumerated cycle
+    pub fn of_attractor(attractor: &Attractor, width: usize) -> Self {
+        Self::new(&attractor.cycle.iter().map(|&s| vec![s as u64]).collect::<Vec<_>>(), width)
+    }
+
+    pub fn period(&self) -> usize {
+        self.canonical.len()
+    }
+
+    /// Stable name: the period and 48 bits of the hash, e.g. `p7-3f09c1d2e4a5`
+    pub fn id(&self) -> String {
+        format!("p{}-{:012x}", self.period(), self.hash >> 16)
+    }
+}
+
+/// `words` (a row of `width` cells) rotated so that cell i is old cell i + `shift`
+fn rotate(words: &[u64], width: usize, shift: usize) -> Vec<u64> {
+    if shift == 0 {
+        return words.to_vec();
+    }
+    if width <= 64 {
+        let w = words[0];
+        return vec![((w >> shift) | (w << (width - shift))) & tail_mask(width)];
+    }
+    let bit = |i: usize| words[i / 64] >> (i % 64) & 1;
+    let mut rotated = vec![0u64; words.len()];
+    for i in 0..width {
+        rotated[i / 64] |= bit((i + shift) % width) << (i % 64);
+    }
+    rotated
+}
+
+/// One attractor (up to rotation) reached from sampled rows
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampledAttractor {
+    pub fingerprint: CycleFingerprint,
+    /// Samples ending on this cycle or a rotation of it, from even- and
+    /// odd-numbered samples: two capture occasions for
+    /// `AttractorSample::chapman`
+    pub hits: [usize; 2],
+    pub max_transient: usize,
+    pub total_transient: usize,
+}
+
+impl SampledAttractor {
+    pub fn period(&self) -> usize {
+        self.fingerprint.period()
+    }
+
+    pub fn samples(&self) -> usize {
+        self.hits[0] + self.hits[1]
+    }
+
+    pub fn mean_transient(&self) -> f64 {
+        self.total_transient as f64 / self.samples() as f64
+    }
+}
+
+/// Attractors hit by random rows of a ring too wide to enumerate
+pub struct AttractorSample {
+    pub rule: u8,
+    pub width: usize,
+    /// Rows run
+    pub samples: usize,
+    /// Rows that hadn't cycled within the step limit
+    pub unresolved: usize,
+    /// Attractors hit, most hits first (ties: smallest canonical states first)
+    pub attractors: Vec<SampledAttractor>,
+}
+
+impl AttractorSample {
+    /// Rows that reached a cycle
+    pub fn resolved(&self) -> usize {
+        self.samples - self.unresolved
+    }
+
+    /// Attractors hit exactly `k` times
+    pub fn hit_exactly(&self, k: usize) -> usize {
+        self.attractors.iter().filter(|a| a.samples() == k).count()
+    }
+
+    /// Share of a resolved row's basin weight on attractors already seen:
+    /// Good-Turing's 1 - f1/n, with f1 the attractors hit once
+    pub fn coverage(&self) -> f64 {
+        match self.resolved() {
+            0 => 0.0,
+            n => 1.0 - self.hit_exactly(1) as f64 / n as f64,
+        }
+    }
+
+    /// Bias-corrected Chao1 estimate of the number of attractors:
+    /// S + f1(f1 - 1) / 2(f2 + 1). A lower bound, tight when most of the
+    /// weight is on attractors that were hit more than once.
+    pub fn chao1(&self) -> f64 {
+        let (f1, f2) = (self.hit_exactly(1) as f64, self.hit_exactly(2) as f64);
+        self.attractors.len() as f64 + f1 * (f1 - 1.0) / (2.0 * (f2 + 1.0))
+    }
+
+    /// Chapman's two-occasion (Lincoln-Petersen) estimate, treating even-
+    /// and odd-numbered samples as separate captures: (n1 + 1)(n2 + 1) /
+    /// (m + 1) - 1 for n1, n2 attractors seen on each and m seen on both.
+    /// Assumes equal catchability, so unequal basins bias it low.
+    pub fn chapman(&self) -> f64 {
+        let seen = |occasion: usize| self.attractors.iter().filter(|a| a.hits[occasion] > 0).count() as f64;
+        let both = self.attractors.iter().filter(|a| a.hits.iter().all(|&h| h > 0)).count() as f64;
+        (seen(0) + 1.0) * (seen(1) + 1.0) / (both + 1.0) - 1.0
+    }
+}
+
+/// Run `samples` random rows (density 1/2, row i seeded with `seed` + i) of
+/// a ring of `width` cells for up to `max_steps` each, and group them by the
+/// fingerprint of the cycle they end on
+pub fn sample_attractors(rule: u8, width: usize, samples: usize, max_steps: usize, seed: u64) -> AttractorSample {
+    sample_rows(rule, width, samples, max_steps, |i| Automaton::random(width, rule, 0.5, seed.wrapping_add(i as u64)))
+}
+
+/// A band of initial densities: rows with `popcounts.0..=popcounts.1` live cells
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stratum {
+    pub popcounts: (usize, usize),
+    /// Rows sampled from it
+    pub samples: usize,
+}
+
+/// `strata` contiguous bands of the popcounts 0..=width, as equal as they
+/// divide, with `samples` shared out evenly (the first bands get any extra)
+pub fn density_strata(width: usize, strata: usize, samples: usize) -> Vec<Stratum> {
+    assert!((1..=width + 1).contains(&strata), "1 to width + 1 strata");
+    let bound = |s: usize| s * (width + 1) / strata;
+    (0..strata)
+        .map(|s| {
+            let share = samples / strata + (s < samples % strata) as usize;
+            Stratum { popcounts: (bound(s), bound(s + 1) - 1), samples: share }
+        })
+        .collect()
+}
+
+/// Attractors sampled within each density stratum, and all of them together
+pub struct StratifiedSample {
+    pub strata: Vec<Stratum>,
+    pub by_stratum: Vec<AttractorSample>,
+    /// Every row, whatever its stratum; its weights average over the strata
+    /// as sampled, not over coin-flip rows
+    pub pooled: AttractorSample,
+}
+
+/// Like `sample_attractors`, but with each stratum's rows spread evenly over
+/// its popcounts and each row's live cells placed at random (row i seeded
+/// with `seed` + i, numbering across strata), instead of coin flips that
+/// put nearly every row near density 1/2
+pub fn sample_attractors_stratified(
+    rule: u8,
+    width: usize,
+    strata: &[Stratum],
+    max_steps: usize,
+    seed: u64,
+) -> StratifiedSample {
+    let mut first = 0;
+    let by_stratum: Vec<AttractorSample> = strata
+        .iter()
+        .map(|stratum| {
+            let ((lo, hi), n, offset) = (stratum.popcounts, stratum.samples, first);
+            first += n;
+            sample_rows(rule, width, n, max_steps, |j| {
+                // Sample j's popcount is the midpoint of the j-th of n equal slices of lo..=hi
+                let popcount = lo + (2 * j + 1) * (hi - lo + 1) / (2 * n);
+                let seed = seed.wrapping_add((offset + j) as u64);
+                Automaton::from_cells(row_with_popcount(width, popcount, seed), rule)
+            })
+        })
+        .collect();
+    pool(rule, width, strata.to_vec(), by_stratum)
+}
+
+/// Every row of a ring of `width` cells (at most 64) with `popcount` live
+/// cells, as packed words in increasing order (Gosper's hack)
+pub fn rows_with_popcount(width: usize, popcount: usize) -> impl Iterator<Item = u64> {
+    assert!(width <= 64 && popcount <= width, "rows of at most 64 cells");
+    let end = 1u128 << width;
+    let first = (1u128 << popcount) - 1;
+    std::iter::successors(Some(first), move |&x| {
+        if x == 0 {
+            return None;
+        }
+        let low = x & x.wrapping_neg();
+        let ripple = x + low;
+        Some((((ripple ^ x) >> 2) / low) | ripple)
+    })
+    .take_while(move |&x| x < end)
+    .map(|x| x as u64)
+}
+
+/// n choose k, saturating at `u64::MAX`
+pub fn binomial(n: usize, k: usize) -> u64 {
+    if k > n {
+        return 0;
+    }
+    (0..k.min(n - k)).fold(1u128, |c, i| (c * (n - i) as u128 / (i + 1) as u128).min(u64::MAX as u128)) as u64
+}
+
+/// Every row with each popcount in `popcounts`, run as `sample_attractors`
+/// runs its rows: exact outcome weights per popcount, one stratum each
+pub fn enumerate_by_popcount(
+    rule: u8,
+    width: usize,
+    popcounts: std::ops::RangeInclusive<usize>,
+    max_steps: usize,
+) -> StratifiedSample {
+    let mut strata = Vec::new();
+    let by_stratum = popcounts
+        .map(|k| {
+            let rows: Vec<u64> = rows_with_popcount(width, k).collect();
+            strata.push(Stratum { popcounts: (k, k), samples: rows.len() });
+            sample_rows(rule, width, rows.len(), max_steps, |i| Automaton::from_words(vec![rows[i]], width, rule))
+        })
+        .collect();
+    pool(rule, width, strata, by_stratum)
+}
+
+/// The strata's samples, and their attractors merged by fingerprint
+fn pool(rule: u8, width: usize, strata: Vec<Stratum>, by_stratum: Vec<AttractorSample>) -> StratifiedSample {
+    let mut index: HashMap<u64, usize> = HashMap::new();
+    let mut attractors: Vec<SampledAttractor> = Vec::new();
+    for sample in &by_stratum {
+        for a in &sample.attractors {
+            let id = *index.entry(a.fingerprint.hash).or_insert_with(|| {
+                attractors.push(SampledAttractor { hits: [0; 2], max_transient: 0, total_transient: 0, ..a.clone() });
+                attractors.len() - 1
+            });
+            let pooled = &mut attractors[id];
+            (0..2).for_each(|o| pooled.hits[o] += a.hits[o]);
+            pooled.max_transient = pooled.max_transient.max(a.max_transient);
+            pooled.total_transient += a.total_transient;
+        }
+    }
+    sort_by_hits(&mut attractors);
+    let pooled = AttractorSample {
+        rule,
+        width,
+        samples: by_stratum.iter().map(|s| s.samples).sum(),
+        unresolved: by_stratum.iter().map(|s| s.unresolved).sum(),
+        attractors,
+    };
+    StratifiedSample { strata, by_stratum, pooled }
+}
+
+/// A row of `width` cells with exactly `popcount` live, in places drawn by
+/// a partial Fisher-Yates shuffle
+pub fn row_with_popcount(width: usize, popcount: usize, seed: u64) -> Vec<bool> {
+    assert!(popcount <= width, "at most width live cells");
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut order: Vec<usize> = (0..width).collect();
+    let mut cells = vec![false; width];
+    for i in 0..popcount {
+        order.swap(i, rng.random_range(i..width));
+        cells[order[i]] = true;
+    }
+    cells
+}
+
+/// Run `samples` rows, row i being `start(i)`, and group them by attractor
+fn sample_rows(
+    rule: u8,
+    width: usize,
+    samples: usize,
+    max_steps: usize,
+    start: impl Fn(usize) -> Automaton + Sync,
+) -> AttractorSample {
+    let runs: Vec<Option<(usize, CycleFingerprint)>> = (0..samples)
+        .into_par_iter()
+        .map(|i| {
+            let (transient, cycle) = find_attractor(start(i), max_steps)?;
+            Some((transient, CycleFingerprint::new(&cycle, width)))
+        })
+        .collect();
+
+    let mut index: HashMap<u64, usize> = HashMap::new();
+    let mut attractors: Vec<SampledAttractor> = Vec::new();
+    let mut unresolved = 0;
+    for (i, run) in runs.into_iter().enumerate() {
+        let Some((transient, fingerprint)) = run else {
+            unresolved += 1;
+            continue;
+        };
+        let id = *index.entry(fingerprint.hash).or_insert_with(|| {
+            attractors.push(SampledAttractor { fingerprint, hits: [0; 2], max_transient: 0, total_transient: 0 });
+            attractors.len() - 1
+        });
+        let a = &mut attractors[id];
+        a.hits[i % 2] += 1;
+        a.max_transient = a.max_transient.max(transient);
+        a.total_transient += transient;
+    }
+    sort_by_hits(&mut attractors);
+
+    AttractorSample { rule, width, samples, unresolved, attractors }
+}
+
+/// Most hits first; ties, smallest canonical states first
+fn sort_by_hits(attractors: &mut [SampledAttractor]) {
+    attractors.sort_by(|a, b| b.samples().cmp(&a.samples()).then_with(|| a.fingerprint.canonical.cmp(&b.fingerprint.canonical)));
+}
+
+/// Steps before `ca` enters its cycle, and the cycle's states from the
+/// first one reached; None if it hasn't within `max_steps`. Under a rule
+/// with memory the cycle is found on the whole state (see
+/// `Automaton::state`), and its rows are returned.
+pub fn find_attractor(mut ca: Automaton, max_steps: usize) -> Option<(usize, Vec<Vec<u64>>)> {
+    let mut seen = History::new(&ca, Recall::for_run(&ca, max_steps));
+    for _ in 0..seen.horizon(max_steps) {
+        ca.step();
+        if let Some((start, period)) = seen.visit(&ca) {
+            if start + period > max_steps {
+                break;
+            }
+            profile::add_states(seen.stored());
+            // The rows, without any the rule remembers
+            let words = ca.words().len();
+            let cycle = seen.cycle(start, period).into_iter().map(|mut state| {
+                state.truncate(words);
+                state
+            });
+            return Some((start, cycle.collect()));
+        }
+    }
+    profile::add_states(seen.stored());
+    let message = format!("attractor search: no cycle within {max_steps} steps at width {}", ca.width());
+    crate::diagnostics::warn(crate::diagnostics::Kind::Unconverged, message);
+    None
+}
+
+/// FNV-1a over the little-endian bytes of a packed state
+pub fn fingerprint(words: &[u64]) -> u64 {
+    crate::fnv1a(words.iter().copied())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_successor_matches_automaton() {
+        for rule in [30, 90, 110, 184] {
+            for state in [0b1, 0b1011, 0b110_0101, 0b1_1111_0000] {
+                let mut ca = Automaton::from_words(vec![state], 9, rule);
+                ca.step();
+                assert_eq!(successor(rule, 9, state), ca.words()[0], "rule {rule}, state {state:b}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_known_state_graphs() {
+        // Rule 0: everything dies in one step
+        let dead = enumerate_basins(0, 6);
+        assert_eq!(dead.attractors.len(), 1);
+        assert_eq!((dead.attractors[0].cycle.as_slice(), dead.attractors[0].basin), ([0].as_slice(), 64));
+        assert_eq!((dead.attractors[0].max_transient, dead.attractors[0].gardens), (1, 63));
+
+        // Identity: every state is its own fixed point; NOT: 2-cycles of complements
+        assert_eq!(enumerate_basins(204, 5).attractors.len(), 32);
+        let not = enumerate_basins(51, 5);
+        assert!(not.attractors.iter().all(|a| a.period() == 2 && a.basin == 2));
+
+        // Shift: one cycle per necklace (6 binary necklaces of length 4)
+        let shift = enumerate_basins(170, 4);
+        assert_eq!(shift.attractors.len(), 6);
+        assert_eq!(shift.attractors.iter().map(Attractor::period).sum::<usize>(), 16);
+
+        // Labels agree with the attractor table
+        let analysis = enumerate_basins(110, 8);
+        for (id, a) in analysis.attractors.iter().enumerate() {
+            assert!(a.cycle.iter().all(|&s| analysis.attractor[s as usize] == id as u32 && analysis.transient[s as usize] == 0));
+        }
+        assert_eq!(analysis.attractors.iter().map(|a| a.basin).sum::<usize>(), 256);
+    }
+
+    #[test]
+    fn test_in_degrees() {
+        // Rule 0 maps all 64 states onto one; the identity and the shift are bijections
+        let dead = in_degrees(0, 6);
+        assert_eq!((dead.gardens(), dead.max(), dead.histogram()), (63, 64, "0:63 64:1".to_string()));
+        assert!(in_degrees(204, 6).bijective() && in_degrees(170, 6).bijective() && !dead.bijective());
+        // The gardens agree with the basin enumeration's
+        for rule in [30, 54, 110] {
+            let gardens: usize = enumerate_basins(rule, 10).attractors.iter().map(|a| a.gardens).sum();
+            let degrees = in_degrees(rule, 10);
+            assert_eq!(degrees.gardens(), gardens);
+            assert_eq!(degrees.counts.iter().enumerate().map(|(d, n)| d * n).sum::<usize>(), 1024);
+        }
+    }
+
+    #[test]
+    fn test_state_graphs_export() {
+        let metadata = Metadata::default().with("command", "automata basins 0 -w 3 --stg stg.dot");
+        // Rule 0 on 3 cells: seven states fall into the dead one, all of them Gardens of Eden
+        let dead = enumerate_basins(0, 3);
+        let dot = dead.to_dot(&metadata);
+        assert!(dot.starts_with("// command: automata basins 0 -w 3 --stg stg.dot\ndigraph \"rule 0 on 3 cells\" {"));
+        assert_eq!(dot.matches(" -> s0;").count(), 8);
+        assert_eq!((dot.matches("style=dashed").count(), dot.matches("style=filled").count()), (7, 1));
+        assert!(dot.contains("label=\"attractor 0: period 1, basin 8\""));
+
+        let graphml = enumerate_basins(51, 2).to_graphml(&metadata);
+        assert!(graphml.contains("<!--\n  command: automata basins 0 -w 3 - -stg stg.dot\n-->"));
+        assert_eq!(graphml.matches("<edge ").count(), 4);
+        assert!(graphml.contains("<node id=\"s1\"><data key=\"cells\">10</data>"));
+        assert!(graphml.contains("<edge source=\"s1\" target=\"s2\"/>"));
+        assert_eq!(graphml.matches("<data key=\"cycle\">true</data>").count(), 4);
+    }
+
+    #[test]
+    fn test_sampling_agrees_with_enumeration() {
+        let exact = enumerate_basins(110, 12);
+        let mut classes: HashMap<u64, usize> = HashMap::new();
+        for a in &exact.attractors {
+            *classes.entry(CycleFingerprint::of_attractor(a, 12).hash).or_default() += a.basin;
+        }
+        let sample = sample_attractors(110, 12, 2000, 1000, 7);
+        assert_eq!((sample.samples, sample.unresolved), (2000, 0));
+        assert!(sample.attractors.iter().all(|a| classes.contains_key(&a.fingerprint.hash)));
+        // The most common attractor is hit about as often as its (and its rotations') share of states
+        let top = &sample.attractors[0];
+        let share = classes[&top.fingerprint.hash] as f64 / exact.states() as f64;
+        assert!((top.samples() as f64 / 2000.0 - share).abs() < 0.05, "{} hits for a {share:.3} share", top.samples());
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_entry_point_and_rotation() {
+        for width in [31, 80] {
+            let start = Automaton::random(width, 110, 0.5, 3);
+            let (transient, cycle) = find_attractor(start.clone(), 10000).unwrap();
+            let fingerprint = CycleFingerprint::new(&cycle, width);
+            assert_eq!(fingerprint.period(), cycle.len());
+
+            // Entering later in the cycle, or on a rotated ring, gives the same fingerprint
+            let mut later = cycle.clone();
+            later.rotate_left(cycle.len() / 2);
+            assert_eq!(CycleFingerprint::new(&later, width), fingerprint);
+            let mut cells = start.cells();
+            cells.rotate_left(7);
+            let (rotated_transient, rotated) = find_attractor(Automaton::from_cells(cells, 110), 10000).unwrap();
+            assert_eq!((rotated_transient, CycleFingerprint::new(&rotated, width).id()), (transient, fingerprint.id()));
+            assert_eq!(rotate(&cycle[0], width, 7), rotated[0]);
+
+            let other = find_attractor(start.with_rule(30), 10).map(|(_, c)| CycleFingerprint::new(&c, width));
+            assert_ne!(other, Some(fingerprint));
+        }
+    }
+
+    #[test]
+    fn test_stratified_sampling() {
+        let strata = density_strata(10, 3, 10);
+        let bands: Vec<_> = strata.iter().map(|s| (s.popcounts, s.samples)).collect();
+        assert_eq!(bands, [((0, 2), 4), ((3, 6), 3), ((7, 10), 3)]);
+        assert_eq!(row_with_popcount(50, 17, 3).iter().filter(|&&c| c).count(), 17);
+
+        // One popcount per stratum: majority (232) keeps the empty and full rows as they are
+        let sample = sample_attractors_stratified(232, 16, &density_strata(16, 17, 34), 100, 5);
+        assert_eq!((sample.pooled.samples, sample.pooled.unresolved), (34, 0));
+        let only = |s: &AttractorSample| match &s.attractors[..] {
+            [a] => Some(a.fingerprint.canonical[0][0]),
+            _ => None,
+        };
+        assert_eq!(only(&sample.by_stratum[0]), Some(0));
+        assert_eq!(only(&sample.by_stratum[16]), Some(0xffff));
+        let pooled_hits: usize = sample.pooled.attractors.iter().map(SampledAttractor::samples).sum();
+        assert_eq!(pooled_hits, 34);
+    }
+
+    #[test]
+    fn test_popcount_enumeration_is_exact() {
+        let rows: Vec<u64> = rows_with_popcount(5, 2).collect();
+        assert_eq!(rows, [0b00011, 0b00101, 0b00110, 0b01001, 0b01010, 0b01100, 0b10001, 0b10010, 0b10100, 0b11000]);
+        assert_eq!(rows_with_popcount(64, 64).collect::<Vec<_>>(), [u64::MAX]);
+        assert_eq!((binomial(12, 4), binomial(4, 5), binomial(200, 100)), (495, 0, u64::MAX));
+
+        // Every popcount of a 12-cell ring: the same basins as enumerating all 4096 states
+        let exact = enumerate_by_popcount(110, 12, 0..=12, 1000);
+        assert_eq!((exact.pooled.samples, exact.pooled.unresolved), (4096, 0));
+        assert!((0..=12).all(|k| exact.by_stratum[k].samples as u64 == binomial(12, k)));
+        let graph = enumerate_basins(110, 12);
+        let mut basins: HashMap<u64, usize> = HashMap::new();
+        for a in &graph.attractors {
+            *basins.entry(CycleFingerprint::of_attractor(a, 12).hash).or_default() += a.basin;
+        }
+        let pooled: HashMap<u64, usize> =
+            exact.pooled.attractors.iter().map(|a| (a.fingerprint.hash, a.samples())).collect();
+        assert_eq!(pooled, basins);
+    }
+
+    #[test]
+    fn test_capture_recapture_extremes() {
+        // One attractor, hit every time: fully covered
+        let dead = sample_attractors(0, 100, 50, 10, 1);
+        assert_eq!(dead.attractors.len(), 1);
+        assert_eq!((dead.coverage(), dead.chao1(), dead.chapman()), (1.0, 1.0, 1.0));
+
+        // Identity at width 100: every row is its own fixed point, never seen twice
+        let identity = sample_attractors(204, 100, 40, 10, 1);
+        assert_eq!((identity.attractors.len(), identity.hit_exactly(1)), (40, 40));
+        assert_eq!(identity.coverage(), 0.0);
+        assert!(identity.chao1() > 40.0 * 39.0 / 2.0 && identity.chapman() > 400.0);
+
+        // Rows that never settle are set aside
+        let unresolved = sample_attractors(30, 64, 4, 20, 1);
+        assert_eq!((unresolved.unresolved, unresolved.coverage()), (4, 0.0));
+    }
+
+    #[test]
+    fn test_fingerprint_is_stable() {
+        assert_eq!(fingerprint(&[]), 0xcbf2_9ce4_8422_2325);
+        assert_ne!(fingerprint(&[1]), fingerprint(&[2]));
+        assert_ne!(fingerprint(&[1, 0]), fingerprint(&[0, 1]));
+    }
+}
@@ -0,0 +1,272 @@
+//! A bestiary: rules drawn at random from a rule space, each run from the
+//! same random row and sorted by what its diagram does, for spaces far too
+//! big to survey where nobody has names for anything yet
+//!
+//! A space is every Wolfram code with k colors and radius r, general,
+//! totalistic or outer-totalistic: k = 2, r = 2 general is the 2^32 binary
+//! radius-2 rules, k = 3, r = 1 totalistic NKS's 2187 three-color codes.
+//! Each specimen gets
+//! one of Wolfram's four classes, judged from its run alone:
+//!
+//! 1. uniform: the last row is a single color
+//! 2. periodic: some row comes back, up to rotation, so shifting patterns
+//!    count along with still and blinking ones
+//! 3. chaotic: the second half of the diagram deflates barely better than
+//!    its own cells shuffled (to 0.85 of their size or more)
+//! 4. complex: neither, with structure that compresses
+//!
+//! These are heuristics on one finite ring and run: a long transient looks
+//! complex, a slow enough cycle is never seen to close, and chaos with
+//! large-scale structure (rule 18's triangles) can pass for complex.
+
+use crate::diagnostics;
+use crate::wolfram::WolframRule;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use std::ops::Range;
+
+/// Compression ratio (see `Specimen::ratio`) from which a diagram counts as
+/// chaotic: ECA classes 3 and 4 fall either side of it from random rows
+pub const CHAOTIC_RATIO: f64 = 0.85;
+
+/// Wolfram's classes, in his order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Class {
+    Uniform,
+    Periodic,
+    Chaotic,
+    Complex,
+}
+
+impl Class {
+    pub const ALL: [Class; 4] = [Class::Uniform, Class::Periodic, Class::Chaotic, Class::Complex];
+
+    /// Wolfram's number for the class, 1-4
+    pub fn number(self) -> usize {
+        self as usize + 1
+    }
+}
+
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Class::Uniform => "uniform",
+            Class::Periodic => "periodic",
+            Class::Chaotic => "chaotic",
+            Class::Complex => "complex",
+        };
+        f.pad(name)
+    }
+}
+
+/// One sampled rule, its run and what was measured of it
+#[derive(Clone, Debug)]
+pub struct Specimen {
+    pub rule: WolframRule,
+    pub class: Class,
+    /// The diagram, initial row first
+    pub rows: Vec<Vec<u8>>,
+    /// Steps before a row first came back (up to rotation) and how many
+    /// steps later it did, if one did
+    pub cycle: Option<(usize, usize)>,
+    /// Fraction of the last row that isn't color 0
+    pub density: f64,
+    /// Shannon entropy of the last row's colors, as a fraction of log2 k
+    pub entropy: f64,
+    /// Deflated size of the diagram's second half over that of its cells
+    /// shuffled: near 1 for noise, whatever the colors and their mix
+    pub ratio: f64,
+}
+
+/// Run `rule` for `generations` steps from `initial` and classify it
+pub fn examine(rule: WolframRule, initial: Vec<u8>, generations: usize) -> Specimen {
+    let mut rows = vec![initial];
+    for _ in 0..generations {
+        rows.push(rule.step(&rows[rows.len() - 1]));
+    }
+    specimen(rule, rows)
+}
+
+/// `examine` for a run already made: `rows`, the initial one first, are
+/// `rule`'s
+pub fn specimen(rule: WolframRule, rows: Vec<Vec<u8>>) -> Specimen {
+    let mut seen = HashMap::new();
+    let cycle = rows.iter().enumerate().find_map(|(t, row)| {
+        let start = *seen.entry(canonical_rotation(row)).or_insert(t);
+        (start < t).then_some((start, t - start))
+    });
+    let last = &rows[rows.len() - 1];
+    let k = rule.colors as usize;
+    let mut counts = vec![0; k];
+    last.iter().for_each(|&c| counts[c as usize] += 1);
+    let density = 1.0 - counts[0] as f64 / last.len() as f64;
+    let entropy = crate::shannon_entropy(&counts) / (k as f64).log2();
+    let ratio = compression_ratio(&rows[rows.len() / 2..]);
+
+    let class = if counts.iter().filter(|&&n| n > 0).count() == 1 {
+        Class::Uniform
+    } else if cycle.is_some() {
+        Class::Periodic
+    } else {
+        diagnostics::check_threshold("bestiary chaotic or complex: compression ratio", ratio, CHAOTIC_RATIO);
+        if ratio >= CHAOTIC_RATIO { Class::Chaotic } else { Class::Complex }
+    };
+    Specimen { rule, class, rows, cycle, density, entropy, ratio }
+}
+
+/// The row's least rotation, so rows equal up to a shift compare equal.
+/// Two candidate starts race along the row, and whichever first reads a
+/// larger cell is pushed past everything it read: linear time, where trying
+/// every rotation is quadratic per row.
+pub(crate) fn canonical_rotation(row: &[u8]) -> Vec<u8> {
+    let n = row.len();
+    let (mut i, mut j, mut k) = (0, 1, 0);
+    while i < n && j < n && k < n {
+        let (a, b) = (row[(i + k) % n], row[(j + k) % n]);
+        if a == b {
+            k += 1;
+            continue;
+        }
+        if a > b { i += k + 1 } else { j += k + 1 }
+        if i == j {
+            j += 1;
+        }
+        k = 0;
+    }
+    let start = i.min(j);
+    row[start..].iter().chain(&row[..start]).copied().collect()
+}
+
+/// Deflated size of `rows` over that of the same cells shuffled, which
+/// keeps their colors' frequencies and loses everything else. Cells are
+/// deflated a byte each, so a pattern matches itself however far it moved.
+fn compression_ratio(rows: &[Vec<u8>]) -> f64 {
+    let deflated = |cells: &[u8]| {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(cells).unwrap();
+        encoder.finish().unwrap().len()
+    };
+    let cells = rows.concat();
+    let mut shuffled = cells.clone();
+    shuffled.shuffle(&mut ChaCha8Rng::seed_from_u64(0));
+    deflated(&cells) as f64 / deflated(&shuffled) as f64
+}
+
+/// A gallery entry: the specimen, where its thumbnail is (relative to the
+/// page), the generations it shows and the command line that draws it again
+pub struct Card<'a> {
+    pub specimen: &'a Specimen,
+    pub image: String,
+    pub shown: Range<usize>,
+    pub command: String,
+}
+
+/// The gallery as one HTML page: a section per class, in class order,
+/// with each card's thumbnail and stats
+pub fn write_html(out: &mut impl Write, title: &str, cards: &[Card]) -> io::Result<()> {
+    let escape = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+    writeln!(out, "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>{}</title>", escape(title))?;
+    writeln!(
+        out,
+        "<style>\nbody {{ font: 14px system-ui, sans-serif; margin: 2em; }}\n\
+         .cards {{ display: flex; flex-wrap: wrap; gap: 1em; }}\n\
+         figure {{ margin: 0; padding: 0.5em; border: 1px solid #ccc; }}\n\
+         img {{ display: block; image-rendering: pixelated; }}\n\
+         figcaption {{ font-size: 12px; }}\n\
+         code {{ font-size: 11px; word-break: break-all; }}\n</style>\n</head>\n<body>"
+    )?;
+    writeln!(out, "<h1>{}</h1>\n<ul>", escape(title))?;
+    let by_class = |class: Class| cards.iter().filter(move |card| card.specimen.class == class);
+    for class in Class::ALL {
+        let n = by_class(class).count();
+        writeln!(out, "<li><a href=\"#{class}\">Class {}: {class}</a> ({n})</li>", class.number())?;
+    }
+    writeln!(out, "</ul>")?;
+
+    for class in Class::ALL {
+        writeln!(out, "<h2 id=\"{class}\">Class {}: {class}</h2>\n<div class=\"cards\">", class.number())?;
+        for card in by_class(class) {
+            let s = card.specimen;
+            let cycle = match s.cycle {
+                Some((transient, period)) => format!("period {period} after {transient}"),
+                None => "no cycle seen".to_string(),
+            };
+            let rule = escape(&s.rule.to_string());
+            writeln!(out, "<figure>\n<img src=\"{}\" alt=\"{rule}\">\n<figcaption>", escape(&card.image))?;
+            writeln!(out, "<b>{rule}</b>")?;
+            if let Some(table) = s.rule.table_rule() {
+                writeln!(out, "<br>{table}")?;
+            }
+            writeln!(out, "<br>generations {}-{}, {cycle}", card.shown.start, card.shown.end - 1)?;
+            writeln!(
+                out,
+                "<br>density {:.3}, entropy {:.3}, compression {:.3}\n<br><code>{}</code>",
+                s.density,
+                s.entropy,
+                s.ratio,
+                escape(&card.command)
+            )?;
+            writeln!(out, "</figcaption>\n</figure>")?;
+        }
+        writeln!(out, "</div>")?;
+    }
+    writeln!(out, "</body>\n</html>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_rules_classify() {
+        let row = crate::wolfram::random_row(2, 120, 3);
+        let class = |rule: &str| examine(rule.parse().unwrap(), row.clone(), 200).class;
+        assert_eq!(class("k=2,r=1,rule=0"), Class::Uniform);
+        assert_eq!(class("k=2,r=1,rule=160"), Class::Uniform);
+        // Rule 170 shifts the row: it recurs up to rotation at once
+        let shift = examine("k=2,r=1,rule=170".parse().unwrap(), row.clone(), 200);
+        assert_eq!((shift.class, shift.cycle), (Class::Periodic, Some((0, 1))));
+        assert_eq!(class("k=2,r=1,rule=108"), Class::Periodic);
+        assert_eq!(class("k=2,r=1,rule=30"), Class::Chaotic);
+        assert_eq!(class("k=2,r=1,rule=110"), Class::Complex);
+
+        // Least rotations, checked against trying every one
+        for row in [vec![2, 0, 1, 0, 0, 1], vec![1, 1, 1], vec![0, 1, 0, 1], crate::wolfram::random_row(3, 50, 2)] {
+            let every = (0..row.len()).map(|s| [&row[s..], &row[..s]].concat()).min().unwrap();
+            assert_eq!(canonical_rotation(&row), every);
+        }
+
+        // Noise is incompressible in any number of colors
+        let noise = [crate::wolfram::random_row(2, 4000, 1), crate::wolfram::random_row(3, 4000, 1)];
+        assert!(noise.iter().all(|row| (compression_ratio(std::slice::from_ref(row)) - 1.0).abs() < 0.05));
+    }
+
+    #[test]
+    fn test_gallery_groups_by_class() {
+        let row = crate::wolfram::random_row(2, 40, 3);
+        let rules = ["k=2,r=1,rule=30", "k=2,r=1,rule=0"];
+        let specimens: Vec<Specimen> = rules.iter().map(|r| examine(r.parse().unwrap(), row.clone(), 40)).collect();
+        let cards: Vec<Card> = specimens
+            .iter()
+            .enumerate()
+            .map(|(i, specimen)| {
+                Card { specimen, image: format!("{i}.png"), shown: 0..41, command: "a < b".to_string() }
+            })
+            .collect();
+        let mut html = Vec::new();
+        write_html(&mut html, "Test", &cards).unwrap();
+        let html = String::from_utf8(html).unwrap();
+        assert!(html.contains("Class 1: uniform</a> (1)") && html.contains("Class 4: complex</a> (0)"));
+        // The uniform section comes first, and text is escaped
+        assert!(html.find("src=\"1.png\"") < html.find("src=\"0.png\""));
+        assert!(html.contains("<code>a &lt; b</code>") && html.contains("table:1e"));
+        assert!(html.contains("generations 0-40, "));
+    }
+}
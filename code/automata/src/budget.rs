@@ -0,0 +1,103 @@
+//! A process-wide memory budget for `--memory-budget`
+//!
+//! Work that would hold memory in proportion to its steps or its diagram
+//! asks `fits` before choosing how to store it: cycle detection keeps every
+//! state, then only their 64-bit keys, then (Brent's method) one state;
+//! PNG pixels are deflated in memory, then streamed to the output in
+//! chunks; animation windows lose rows. Each switch is recorded with
+//! `degrade`, once per kind, for the CLI to report. Without a budget
+//! nothing changes.
+//!
+//! The estimates are of the big allocations only, and rough: the budget
+//! keeps a run from growing with its length, not its peak to the byte.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+static BUDGET: AtomicU64 = AtomicU64::new(0);
+static DEGRADATIONS: Mutex<Vec<(&'static str, String)>> = Mutex::new(Vec::new());
+
+/// Bytes a stored state costs beyond its words: the `Vec` and its
+/// allocation, and its lookup entry
+pub const STATE_OVERHEAD: u64 = 64;
+
+/// Bytes a state's key and step cost in a hash table, growth included
+pub const KEY_BYTES: u64 = 40;
+
+/// Limit the big allocations to about `bytes`; None for no limit
+pub fn set_budget(bytes: Option<u64>) {
+    BUDGET.store(bytes.unwrap_or(0), Ordering::Relaxed);
+}
+
+/// The budget as set
+pub fn budget() -> Option<u64> {
+    Some(BUDGET.load(Ordering::Relaxed)).filter(|&bytes| bytes > 0)
+}
+
+/// The share of the budget one piece of work gets: all of it, or on a
+/// worker of a parallel survey, a thread's share
+pub fn share() -> Option<u64> {
+    let threads = if rayon::current_thread_index().is_some() { rayon::current_num_threads() } else { 1 };
+    budget().map(|bytes| bytes / threads as u64)
+}
+
+/// Whether `bytes` more fit the share
+pub fn fits(bytes: u64) -> bool {
+    share().is_none_or(|share| bytes <= share)
+}
+
+/// Record that `what` (e.g. "cycle detection") ran in a leaner way, `how`;
+/// only the first note for each `what` is kept
+pub fn degrade(what: &'static str, how: String) {
+    let mut notes = DEGRADATIONS.lock().unwrap();
+    if notes.iter().all(|&(kind, _)| kind != what) {
+        notes.push((what, how));
+    }
+}
+
+/// The degradations so far, in the order they were first applied, as
+/// "what: how"
+pub fn degradations() -> Vec<String> {
+    DEGRADATIONS.lock().unwrap().iter().map(|(what, how)| format!("{what}: {how}")).collect()
+}
+
+/// How many of `wanted` items of `each` bytes fit, at least `min`: all of
+/// them without a budget. Records a degradation for `what` if fewer
+pub fn shrink(what: &'static str, wanted: usize, each: u64, min: usize) -> usize {
+    let Some(share) = share() else { return wanted };
+    let fitting = (share / each.max(1)).min(wanted as u64) as usize;
+    if fitting >= wanted {
+        return wanted;
+    }
+    let kept = fitting.max(min);
+    let (needed, allowed) = (bytes((wanted as u64).saturating_mul(each)), bytes(share));
+    degrade(what, format!("{kept} of {wanted} rows kept ({needed} needed, {allowed} allowed)"));
+    kept
+}
+
+/// A byte count for a note, in the largest binary unit under it
+pub fn bytes(n: u64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB"];
+    match (n.max(1).ilog2() / 10).min(units.len() as u32 - 1) {
+        0 => format!("{n} B"),
+        p => format!("{:.1} {}", n as f64 / (1u64 << (10 * p)) as f64, units[p as usize]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notes_kept_once_per_kind() {
+        // Tests share the notes, so this one uses kinds of its own
+        degrade("test kind a", "first".to_string());
+        degrade("test kind a", "second".to_string());
+        degrade("test kind b", "third".to_string());
+        let notes: Vec<String> = degradations().into_iter().filter(|note| note.starts_with("test kind")).collect();
+        assert_eq!(notes, ["test kind a: first", "test kind b: third"]);
+
+        let sizes = [0, 1023, 1536, 3 << 30].map(bytes);
+        assert_eq!(sizes, ["0 B", "1023 B", "1.5 KiB", "3.0 GiB"]);
+    }
+}
@@ -0,0 +1,376 @@
+//! Batches of experiments: a TOML file of `[[experiment]]` tables, each a
+//! command with its flags, where an array value is a grid axis
+//!
+//! ```toml
+//! [defaults]
+//! init = "random"
+//! seed = 7
+//!
+//! [[experiment]]
+//! name = "entropy"
+//! command = "entropy"
+//! rules = [30, 90, 110]
+//! width = [31, 63, 127]
+//! gens = 200
+//!
+//! [[experiment]]
+//! name = "images"
+//! command = "render"
+//! rule = 110
+//! width = [101, 201]
+//! out = "batch/rule{rule}-w{width}.png"
+//! ```
+//!
+//! Keys are the command's long flags, as in `--config` files, plus `rule`
+//! or `rules` for the rule argument and `args` for other positionals; to
+//! pass a list to one flag, write it as a string ("1,2,3"). Every
+//! combination of the axes is one run, last axis fastest, and `{key}` in a
+//! string stands for that run's value (`{name}` and `{run}` too). Defaults
+//! apply to the commands that have the flag. Each run is the program itself
+//! in a child process, its output saved to `<out-dir>/<name>-<run>.txt`;
+//! `summary.csv` there lists every run with its axis values, exit status
+//! and time.
+
+use super::config::{scalar, shell_line};
+use super::log::{self, note, verbose};
+use super::preset;
+use super::sink;
+use automata::toml::{self, Table, Value};
+use clap::Args;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Most runs one batch file may expand to
+pub const MAX_RUNS: usize = 10_000;
+
+#[derive(Args, Clone)]
+pub struct BatchArgs {
+    /// TOML file of [[experiment]] tables (and an optional [defaults] table)
+    pub file: PathBuf,
+    /// Directory for each run's output and summary.csv
+    #[arg(short, long, default_value = "batch")]
+    pub out_dir: PathBuf,
+    /// Print the command lines without running them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// One point of an experiment's grid
+struct Run {
+    experiment: String,
+    number: usize,
+    /// The axis values, in the order the axes were written
+    point: Vec<(String, String)>,
+    argv: Vec<String>,
+}
+
+pub fn batch(args: &BatchArgs, cli: &clap::Command) {
+    let BatchArgs { ref file, ref out_dir, dry_run } = *args;
+    let runs = read_batch(file, cli).unwrap_or_else(|e| {
+        eprintln!("{}: {e}", file.display());
+        std::process::exit(1);
+    });
+    // Check every command line before running any
+    for run in &runs {
+        if let Err(e) = cli.clone().try_get_matches_from(&run.argv) {
+            eprintln!("{}: {} run {}: {}", file.display(), run.experiment, run.number, shell_line(&run.argv));
+            e.exit();
+        }
+    }
+    if dry_run {
+        for run in &runs {
+            println!("{}-{}: {}", run.experiment, run.number, shell_line(&run.argv));
+        }
+        return;
+    }
+
+    if let Err(e) = std::fs::create_dir_all(out_dir) {
+        eprintln!("can't create {}: {e}", out_dir.display());
+        std::process::exit(1);
+    }
+    let exe = std::env::current_exe().unwrap_or_else(|e| {
+        eprintln!("can't find this program to run the experiments: {e}");
+        std::process::exit(1);
+    });
+    note!("Batch {}: {} runs, output in {}", file.display(), runs.len(), out_dir.display());
+
+    let mut results = Vec::new();
+    for (i, run) in runs.iter().enumerate() {
+        let path = out_dir.join(format!("{}-{}.txt", run.experiment, run.number));
+        let start = Instant::now();
+        verbose!("  {}-{}: {}", run.experiment, run.number, shell_line(&run.argv));
+        // Each run reports at this run's verbosity, its notes on this stderr
+        let status = std::fs::File::create(&path).and_then(|out| {
+            let mut child = std::process::Command::new(&exe);
+            child.args(&run.argv[1..]).args(log::flags());
+            // ...and within this run's memory budget, unless it sets its own
+            let own = run.argv.iter().any(|arg| arg.starts_with("--memory-budget"));
+            if let Some(bytes) = automata::budget::budget().filter(|_| !own) {
+                child.arg(format!("--memory-budget={bytes}"));
+            }
+            // ...and preset
+            let own = run.argv.iter().any(|arg| arg.starts_with("--preset"));
+            if let Some(preset) = preset::preset().filter(|_| !own) {
+                child.arg(format!("--preset={}", preset.name()));
+            }
+            // ...and sink
+            let own = run.argv.iter().any(|arg| arg.starts_with("--sink"));
+            if let Some(destination) = sink::destination().filter(|_| !own && sink::redirected()) {
+                child.arg(format!("--sink={destination}"));
+            }
+            child.stdout(out).status()
+        });
+        let seconds = start.elapsed().as_secs_f64();
+        let status = match status {
+            Ok(status) if status.success() => "ok".to_string(),
+            Ok(status) => status.code().map_or("killed".into(), |code| format!("exit {code}")),
+            Err(e) => format!("error: {e}"),
+        };
+        note!("  [{}/{}] {}-{}: {status} ({seconds:.2}s)", i + 1, runs.len(), run.experiment, run.number);
+        results.push((status, seconds, path));
+    }
+
+    // One column per axis of any experiment, blank where an experiment has no such axis
+    let mut axes: Vec<&str> = Vec::new();
+    for (axis, _) in runs.iter().flat_map(|run| &run.point) {
+        if !axes.contains(&axis.as_str()) {
+            axes.push(axis);
+        }
+    }
+    fn value<'a>(run: &'a Run, axis: &str) -> &'a str {
+        run.point.iter().find(|(a, _)| a == axis).map_or("", |(_, v)| v.as_str())
+    }
+    let headers: Vec<&str> = ["experiment", "run"].into_iter().chain(axes.iter().copied()).collect();
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for run in &runs {
+        widths[0] = widths[0].max(run.experiment.len());
+        widths[1] = widths[1].max(run.number.to_string().len());
+        for (w, axis) in widths[2..].iter_mut().zip(&axes) {
+            *w = (*w).max(value(run, axis).len());
+        }
+    }
+
+    println!();
+    let header: Vec<String> = headers.iter().zip(&widths).map(|(h, &w)| format!("{h:<w$}")).collect();
+    println!("{}  {:>8}  status", header.join("  "), "seconds");
+    let mut csv = format!("{},status,seconds,output,command\n", headers.join(","));
+    for (run, (status, seconds, path)) in runs.iter().zip(&results) {
+        let mut cells = vec![run.experiment.clone(), run.number.to_string()];
+        cells.extend(axes.iter().map(|axis| value(run, axis).to_string()));
+        let row: Vec<String> = cells.iter().zip(&widths).map(|(c, &w)| format!("{c:<w$}")).collect();
+        println!("{}  {seconds:>8.2}  {status}", row.join("  "));
+
+        cells.extend([status.clone(), format!("{seconds:.3}"), path.display().to_string(), shell_line(&run.argv)]);
+        let fields: Vec<String> = cells.iter().map(|c| csv_field(c)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    let summary = out_dir.join("summary.csv");
+    if let Err(e) = std::fs::write(&summary, csv) {
+        eprintln!("can't write {}: {e}", summary.display());
+        std::process::exit(1);
+    }
+    let failed = results.iter().filter(|(status, _, _)| status != "ok").count();
+    println!("\n{} runs, {failed} failed; summary in {}", runs.len(), summary.display());
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Every run of every experiment in `file`, in order
+fn read_batch(file: &std::path::Path, cli: &clap::Command) -> Result<Vec<Run>, String> {
+    let text = std::fs::read_to_string(file).map_err(|e| format!("can't read it: {e}"))?;
+    let doc = toml::parse(&text)?;
+    let empty = Table::default();
+    let mut defaults = &empty;
+    let mut experiments = Vec::new();
+    for (key, value) in &doc.0 {
+        match (key.as_str(), value) {
+            ("defaults", Value::Table(table)) => defaults = table,
+            ("experiment", Value::Array(tables)) => {
+                for table in tables {
+                    match table {
+                        Value::Table(table) => experiments.push(table),
+                        other => return Err(format!("[[experiment]] holds tables, not a {}", other.type_name())),
+                    }
+                }
+            }
+            _ => return Err(format!("unknown key {key:?}: expected a [defaults] table and [[experiment]] tables")),
+        }
+    }
+    if experiments.is_empty() {
+        return Err("no [[experiment]] tables: nothing to run".into());
+    }
+
+    let mut runs = Vec::new();
+    let mut names: Vec<String> = Vec::new();
+    for (number, experiment) in experiments.into_iter().enumerate() {
+        let name = match experiment.get("name") {
+            Some(Value::String(name)) => name.clone(),
+            Some(other) => {
+                return Err(format!("experiment {}: name must be a string, not a {}", number + 1, other.type_name()))
+            }
+            None => format!("experiment{}", number + 1),
+        };
+        let context = |e: String| format!("experiment {name}: {e}");
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)) {
+            return Err(context("names are letters, digits, '-', '_' and '.', as they name files".into()));
+        }
+        if names.contains(&name) {
+            return Err(context("the name is used twice".into()));
+        }
+        names.push(name.clone());
+
+        let command = match experiment.get("command") {
+            Some(Value::String(command)) => command,
+            Some(other) => return Err(context(format!("command must be a string, not a {}", other.type_name()))),
+            None => return Err(context("no command".into())),
+        };
+        let sub = cli.find_subcommand(command).ok_or_else(|| context(format!("no such command {command:?}")))?;
+        let has = |long: &str| sub.get_arguments().any(|arg| arg.get_long() == Some(long));
+        let takes_rule = sub.get_arguments().any(|arg| arg.is_positional() && arg.get_id() == "rule");
+
+        // One list of values per setting, the rule first, then the defaults the command has, then the experiment's own
+        let values = |key: &str, value: &Value| -> Result<Vec<Value>, String> {
+            match value {
+                Value::Array(items) if items.is_empty() => Err(format!("{key} is an empty grid axis")),
+                Value::Array(items) => items.iter().map(|v| scalar(key, v).map(|_| v.clone())).collect(),
+                Value::Table(_) => Err(format!("{key} can't be a table")),
+                value => Ok(vec![value.clone()]),
+            }
+        };
+        let mut rules = None;
+        let mut own = Vec::new();
+        let mut positional = Vec::new();
+        for (key, value) in &experiment.0 {
+            match (key.as_str(), value) {
+                ("name" | "command", _) => {}
+                ("args", Value::Array(args)) => {
+                    positional = args.iter().map(|a| scalar("args", a)).collect::<Result<_, _>>().map_err(context)?
+                }
+                ("args", other) => return Err(context(format!("args must be an array, not a {}", other.type_name()))),
+                ("rule" | "rules", _) if !takes_rule => return Err(context(format!("{command} takes no rule"))),
+                ("rule" | "rules", _) if rules.is_some() => return Err(context("give rule or rules, not both".into())),
+                ("rule" | "rules", value) => rules = Some(values("rule", value).map_err(context)?),
+                (key, value) => own.push((key, values(key, value).map_err(context)?)),
+            }
+        }
+        let mut columns: Vec<(&str, Vec<Value>)> = rules.map(|rules| ("rule", rules)).into_iter().collect();
+        for (key, value) in &defaults.0 {
+            if has(&key.replace('_', "-")) && !own.iter().any(|(k, _)| k == key) {
+                columns.push((key, values(key, value).map_err(|e| format!("defaults: {e}"))?));
+            }
+        }
+        columns.extend(own);
+
+        let count = columns.iter().try_fold(1usize, |n, (_, values)| n.checked_mul(values.len()));
+        let count = count.filter(|&count| runs.len() + count <= MAX_RUNS);
+        let count = count.ok_or(format!("more than {MAX_RUNS} runs: make the grids smaller or split the batch"))?;
+
+        let mut index = vec![0; columns.len()];
+        for run in 1..=count {
+            let point: Vec<(&str, &Value)> =
+                columns.iter().zip(&index).map(|((key, values), &i)| (*key, &values[i])).collect();
+            let fill = |s: &str| {
+                let mut s = s.replace("{name}", &name).replace("{run}", &run.to_string());
+                for (key, value) in &point {
+                    s = s.replace(&format!("{{{key}}}"), &value.to_string());
+                }
+                s
+            };
+            let mut argv = vec!["automata".to_string(), command.clone()];
+            argv.extend(point.iter().filter(|(key, _)| *key == "rule").map(|(_, rule)| rule.to_string()));
+            argv.extend(positional.iter().map(|a| fill(a)));
+            for &(key, value) in point.iter().filter(|(key, _)| *key != "rule") {
+                match value {
+                    Value::Boolean(false) => {}
+                    Value::Boolean(true) => argv.push(format!("--{}", key.replace('_', "-"))),
+                    value => argv.extend([format!("--{}", key.replace('_', "-")), fill(&value.to_string())]),
+                }
+            }
+            let axes = point.iter().zip(&columns).filter(|(_, (_, values))| values.len() > 1);
+            let point = axes.map(|((key, value), _)| (key.to_string(), value.to_string())).collect();
+            runs.push(Run { experiment: name.clone(), number: run, point, argv });
+
+            // Next grid point, last axis fastest
+            for (i, (_, values)) in index.iter_mut().zip(&columns).rev() {
+                *i += 1;
+                if *i < values.len() {
+                    break;
+                }
+                *i = 0;
+            }
+        }
+    }
+    Ok(runs)
+}
+
+/// A CSV field, quoted if it has a comma, quote or newline
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) { format!("\"{}\"", s.replace('"', "\"\"")) } else { s.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::TempDir;
+    use super::*;
+    use clap::CommandFactory;
+
+    /// The runs of a batch file holding `text`, or why there are none
+    fn expand(name: &str, text: &str) -> Result<Vec<Run>, String> {
+        let dir = TempDir::new("batch");
+        let path = dir.join(&format!("{name}.toml"));
+        std::fs::write(&path, text).unwrap();
+        read_batch(&path, &crate::Cli::command())
+    }
+
+    #[test]
+    fn test_grid_order() {
+        let text = "[defaults]\nseed = 7\nfont = 3\n\n\
+                    [[experiment]]\ncommand = \"entropy\"\nrules = [30, 110]\nwidth = [31, 63, 127]\nquiet = true\n\n\
+                    [[experiment]]\nname = \"image\"\ncommand = \"render\"\nrule = 90\n\
+                    out = \"{name}-{rule}-{run}.png\"\n";
+        let runs = expand("order", text).unwrap();
+        let points: Vec<Vec<(String, String)>> = runs.iter().map(|run| run.point.clone()).collect();
+        // The product of the axes in the order written, last axis fastest
+        let point = |rule: &str, width: &str| vec![("rule".into(), rule.into()), ("width".into(), width.into())];
+        let grid: Vec<_> = ["30", "110"].iter().flat_map(|r| ["31", "63", "127"].map(|w| point(r, w))).collect();
+        assert_eq!(points[..6], grid);
+        assert_eq!(runs.iter().map(|run| run.number).collect::<Vec<_>>(), [1, 2, 3, 4, 5, 6, 1]);
+        // Defaults only go to commands with the flag, before the experiment's own
+        let words = |argv: &[String]| argv.join(" ");
+        assert_eq!(words(&runs[4].argv), "automata entropy 110 --seed 7 --width 63 --quiet");
+        assert_eq!((runs[6].experiment.as_str(), runs[6].point.len()), ("image", 0));
+        assert_eq!(words(&runs[6].argv), "automata render 90 --seed 7 --out image-90-1.png");
+    }
+
+    #[test]
+    fn test_run_cap() {
+        let axis = format!("[{}]", (0..100).map(|i| i.to_string()).collect::<Vec<_>>().join(","));
+        let grid = |name: &str| {
+            format!("[[experiment]]\nname = \"{name}\"\ncommand = \"run\"\nwidth = {axis}\nseed = {axis}\n")
+        };
+        assert_eq!(expand("cap", &grid("a")).unwrap().len(), MAX_RUNS);
+        // The cap is on the whole batch, not each experiment
+        let error = expand("over", &(grid("a") + &grid("b"))).err().unwrap();
+        assert_eq!(error, format!("more than {MAX_RUNS} runs: make the grids smaller or split the batch"));
+    }
+
+    #[test]
+    fn test_bad_axes() {
+        let experiment = |setting: &str| format!("[[experiment]]\nname = \"e\"\ncommand = \"run\"\n{setting}\n");
+        let errors = [
+            ("width = []", "experiment e: width is an empty grid axis"),
+            ("width = [31, [63, 127]]", "experiment e: width must be a string or number, not a array"),
+            ("width = { from = 31, to = 127 }", "experiment e: width can't be a table"),
+            ("rule = 30\nrules = [90]", "experiment e: give rule or rules, not both"),
+            ("args = 3", "experiment e: args must be an array, not a integer"),
+        ];
+        for (setting, message) in errors {
+            assert_eq!(expand("axes", &experiment(setting)).err().as_deref(), Some(message), "{setting}");
+        }
+        let unknown = expand("unknown", "[[experiment]]\ncommand = \"nonsense\"\n").err().unwrap();
+        assert!(unknown.contains("no such command"));
+        assert!(expand("none", "[defaults]\nseed = 1\n").err().unwrap().contains("nothing to run"));
+    }
+}
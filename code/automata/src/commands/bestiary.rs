@@ -0,0 +1,346 @@
+//! Rule spaces beyond the ECAs, by Wolfram code: `bestiary` samples random
+//! rules from one and lays them out as an HTML gallery grouped by class, and
+//! `wolfram-survey` classifies every code of a family small enough to list
+//!
+//! Every rule runs from the same row, so results compare rules and not
+//! initial conditions; each gallery card carries the `wolfram` command line
+//! that draws its thumbnail again. A thumbnail shorter than the run shows
+//! the window `--pick` chooses, by default around the run's biggest change
+//! or else after its transient, rather than the initial row dissolving.
+
+use super::json::{report, Json};
+use super::log::verbose;
+use super::progress::Progress;
+use super::run::StatesInit;
+use super::{metadata, parse_width, SurveyOutput};
+use automata::bestiary::{examine, write_html, Card, Class, Specimen};
+use automata::metadata::Tagged;
+use automata::parquet::Column;
+use automata::render::{write_png_states, RenderOptions};
+use automata::window::pick_window;
+use automata::wolfram::{random_row, WolframRule};
+use clap::{Args, ValueEnum};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args, Clone)]
+pub struct BestiaryArgs {
+    /// Rules to sample (at most the size of the space)
+    #[arg(short = 'n', long, default_value_t = 48)]
+    pub count: usize,
+    /// Colors k of the rule space
+    #[arg(short = 'k', long, default_value_t = 2)]
+    pub colors: u8,
+    /// Radius r of the rule space
+    #[arg(short, long, default_value_t = 2)]
+    pub radius: usize,
+    /// Which codes: general (any table) or totalistic (the next state depends on the neighborhood's sum)
+    #[arg(long, value_enum, default_value_t = Family::General)]
+    pub family: Family,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 120, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 200)]
+    pub generations: usize,
+    /// RNG seed for the rules and for the random row they all start from
+    #[arg(long, default_value_t = 42)]
+    pub seed: u64,
+    /// Pixels per cell side in the thumbnails
+    #[arg(short, long, default_value_t = 2)]
+    pub cell_size: usize,
+    /// Rows per thumbnail, 0 for the whole run
+    #[arg(long, default_value_t = 100)]
+    pub window: usize,
+    /// Which rows a thumbnail shorter than the run shows
+    #[arg(long, value_enum, default_value_t = Pick::Auto)]
+    pub pick: Pick,
+    /// Directory for index.html and the thumbnails
+    #[arg(short, long, default_value = "bestiary")]
+    pub out: PathBuf,
+}
+
+/// Families of Wolfram codes
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Family {
+    /// Any table over the k^(2r+1) neighborhoods
+    General,
+    /// A table over neighborhood sums: mirror-symmetric, and few enough to survey for small k and r
+    Totalistic,
+    /// A table over the center's color and its neighbors' sum, as Life's births and survivals
+    OuterTotalistic,
+}
+
+impl Family {
+    fn codes(self) -> automata::wolfram::Family {
+        match self {
+            Family::General => automata::wolfram::Family::General,
+            Family::Totalistic => automata::wolfram::Family::Totalistic,
+            Family::OuterTotalistic => automata::wolfram::Family::OuterTotalistic,
+        }
+    }
+}
+
+/// How many codes `family` has for k and r, exiting for a pair it can't have
+fn family_size(family: automata::wolfram::Family, colors: u8, radius: usize) -> Option<u64> {
+    family.size(colors, radius).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    })
+}
+
+/// How `bestiary` picks each thumbnail's rows
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Pick {
+    /// Around the biggest entropy change if there is one, else after the transient
+    Auto,
+    /// The first rows, from the initial one
+    Start,
+    /// From the end of the transient, or the last rows if no cycle was seen
+    Settled,
+    /// The window of median mean entropy: the run's most typical stretch
+    Median,
+    /// Around the biggest entropy change, or the first rows if nothing changes
+    Event,
+}
+
+pub fn bestiary(args: &BestiaryArgs) {
+    let BestiaryArgs { count, colors, radius, family, width, generations, seed, cell_size, window, pick, ref out } =
+        *args;
+    let pick = match pick {
+        Pick::Auto => automata::window::Pick::Auto,
+        Pick::Start => automata::window::Pick::Start,
+        Pick::Settled => automata::window::Pick::Settled,
+        Pick::Median => automata::window::Pick::Median,
+        Pick::Event => automata::window::Pick::Event,
+    };
+    let family = family.codes();
+    let size = family_size(family, colors, radius);
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let first = WolframRule::random(colors, radius, family, &mut rng).unwrap();
+    let entries = first.table.len();
+    if count == 0 || size.is_some_and(|size| count as u64 > size) {
+        eprintln!("--count must be 1 to the {} rules in the space", size.map_or("all".to_string(), |n| n.to_string()));
+        std::process::exit(1);
+    }
+
+    // Distinct rules, so a small space isn't shown the same rule twice
+    let mut seen = HashSet::from([first.table.clone()]);
+    let mut rules = vec![first];
+    while rules.len() < count {
+        let rule = WolframRule::random(colors, radius, family, &mut rng).unwrap();
+        if seen.insert(rule.table.clone()) {
+            rules.push(rule);
+        }
+    }
+
+    let initial = random_row(colors, width, seed);
+    let progress = Progress::rules("Bestiary", count);
+    let specimens: Vec<Specimen> = rules
+        .into_par_iter()
+        .map(|rule| {
+            let specimen = examine(rule, initial.clone(), generations);
+            progress.tick();
+            specimen
+        })
+        .collect();
+    drop(progress);
+
+    if let Err(e) = std::fs::create_dir_all(out) {
+        eprintln!("Can't create {}: {e}", out.display());
+        std::process::exit(1);
+    }
+    let digits = count.to_string().len();
+    let options = RenderOptions { cell_size: cell_size.max(1), ..RenderOptions::default() };
+    let init = format!("random, seed {seed}");
+    let cards: Vec<Card> = specimens
+        .iter()
+        .enumerate()
+        .map(|(i, specimen)| {
+            let image = format!("{:0digits$}.png", i + 1);
+            let path = out.join(&image);
+            let rule = &specimen.rule;
+            let height = if window == 0 { specimen.rows.len() } else { window.min(specimen.rows.len()) };
+            let skip = pick_window(&specimen.rows, colors, specimen.cycle.map(|(t, _)| t), height, pick);
+            let shown = skip..skip + height;
+            let gens = height - 1;
+            // As `wolfram` tags the same image
+            let mut params: Vec<(_, &dyn std::fmt::Display)> =
+                vec![("rule", rule), ("width", &width), ("generations", &gens), ("init", &init)];
+            if skip > 0 {
+                params.push(("skip", &skip));
+            }
+            let metadata = metadata(&params);
+            let written = std::fs::File::create(&path).and_then(|file| {
+                let mut out = Tagged::new(BufWriter::new(file), &metadata);
+                write_png_states(&mut out, &specimen.rows[shown.clone()], colors, &options)?;
+                out.flush()
+            });
+            if let Err(e) = written {
+                eprintln!("Can't write {}: {e}", path.display());
+                std::process::exit(1);
+            }
+            let skipped = if skip > 0 { format!(" --skip {skip}") } else { String::new() };
+            let command = format!(
+                "automata wolfram {rule} -w {width} -g {gens}{skipped} --init random --seed {seed} -c {} -o {image}",
+                options.cell_size
+            );
+            Card { specimen, image, shown, command }
+        })
+        .collect();
+    verbose!("Thumbnails -> {}", out.display());
+
+    let space = format!("k={colors},r={radius} {family}");
+    let title = format!("Bestiary: {count} random {space} rules (of {colors}^{entries})");
+    let page = out.join("index.html");
+    let written = std::fs::File::create(&page).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        write_html(&mut out, &title, &cards)?;
+        out.flush()
+    });
+    if let Err(e) = written {
+        eprintln!("Can't write {}: {e}", page.display());
+        std::process::exit(1);
+    }
+
+    println!("{count} random {space} rules (width={width}, gens={generations}, seed={seed}) -> {}", page.display());
+    for class in Class::ALL {
+        let n = specimens.iter().filter(|s| s.class == class).count();
+        println!("  Class {} ({class}): {n}", class.number());
+    }
+}
+
+/// Most codes `wolfram-survey` runs; bigger families are for `bestiary`
+const MAX_SURVEY: u64 = 1 << 16;
+
+#[derive(Args, Clone)]
+pub struct WolframSurveyArgs {
+    /// Colors k
+    #[arg(short = 'k', long, default_value_t = 3)]
+    pub colors: u8,
+    /// Radius r
+    #[arg(short, long, default_value_t = 1)]
+    pub radius: usize,
+    /// Which codes: totalistic (the next state depends on the neighborhood's sum) or general (any table)
+    #[arg(long, value_enum, default_value_t = Family::Totalistic)]
+    pub family: Family,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 120, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 200)]
+    pub generations: usize,
+    /// Initial row for every code: single, random or digits:0120...
+    #[arg(long, default_value_t = StatesInit::Random)]
+    pub init: StatesInit,
+    /// RNG seed for `--init random`
+    #[arg(long, default_value_t = 42)]
+    pub seed: u64,
+    /// Complex (class 4) codes to list in the table, richest first
+    #[arg(long, default_value_t = 20)]
+    pub top: usize,
+    #[command(flatten)]
+    pub output: SurveyOutput,
+}
+
+pub fn wolfram_survey(args: &WolframSurveyArgs) {
+    let WolframSurveyArgs { colors, radius, family, width, generations, ref init, seed, top, ref output } = *args;
+    let family = family.codes();
+    let size = family_size(family, colors, radius).filter(|&n| n <= MAX_SURVEY).unwrap_or_else(|| {
+        let sample = format!("bestiary -k {colors} -r {radius} --family {family}");
+        eprintln!("k={colors},r={radius} {family} has more than {MAX_SURVEY} codes; `{sample}` samples it");
+        std::process::exit(1);
+    }) as usize;
+    let initial = init.row(colors, width, seed);
+    let progress = Progress::rules(&format!("k={colors},r={radius} {family}"), size);
+    let (specimens, canonical): (Vec<Specimen>, Vec<usize>) = (0..size)
+        .into_par_iter()
+        .map(|code| {
+            let rule = family.rule(colors, radius, &code.to_string()).unwrap();
+            // The smallest code a mirror or complement of it has
+            let canonical: usize = rule.canonical().code().parse().unwrap();
+            let specimen = examine(rule, initial.clone(), generations);
+            progress.tick();
+            (specimen, canonical)
+        })
+        .unzip();
+    drop(progress);
+
+    let csv = || {
+        std::iter::once("code,canonical,class,transient,period,density,entropy,ratio\n".to_string())
+            .chain(specimens.iter().enumerate().map(|(code, s)| {
+                let [transient, period] = [s.cycle.map(|(t, _)| t), s.cycle.map(|(_, p)| p)]
+                    .map(|n| n.map_or(String::new(), |n| n.to_string()));
+                let (class, canonical) = (s.class, canonical[code]);
+                format!("{code},{canonical},{class},{transient},{period},{},{},{}\n", s.density, s.entropy, s.ratio)
+            }))
+            .collect()
+    };
+    let json = || {
+        let rules = specimens.iter().enumerate().map(|(code, s)| {
+            Json::object([
+                ("code", code.into()),
+                ("canonical", canonical[code].into()),
+                ("class", s.class.to_string().into()),
+                ("transient", s.cycle.map(|(t, _)| t).into()),
+                ("period", s.cycle.map(|(_, p)| p).into()),
+                ("density", s.density.into()),
+                ("entropy", s.entropy.into()),
+                ("ratio", s.ratio.into()),
+            ])
+        });
+        let parameters = Json::object([
+            ("colors", colors.into()),
+            ("radius", radius.into()),
+            ("family", family.to_string().into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("init", init.to_string().into()),
+            ("seed", seed.into()),
+        ]);
+        report("wolfram-survey", parameters, Json::object([("rules", Json::array(rules))]))
+    };
+    let columns = || {
+        vec![
+            Column::required("code", (0..size).collect::<Vec<_>>()),
+            Column::required("canonical", canonical.clone()),
+            Column::required("class", specimens.iter().map(|s| s.class.to_string()).collect::<Vec<_>>()),
+            Column::optional("transient", specimens.iter().map(|s| s.cycle.map(|(t, _)| t)).collect::<Vec<_>>()),
+            Column::optional("period", specimens.iter().map(|s| s.cycle.map(|(_, p)| p)).collect::<Vec<_>>()),
+            Column::required("density", specimens.iter().map(|s| s.density).collect::<Vec<_>>()),
+            Column::required("entropy", specimens.iter().map(|s| s.entropy).collect::<Vec<_>>()),
+            Column::required("ratio", specimens.iter().map(|s| s.ratio).collect::<Vec<_>>()),
+        ]
+    };
+    if !output.table() {
+        return output.write(csv, json, columns);
+    }
+
+    let seeded = if matches!(init, StatesInit::Random) { format!(", seed={seed}") } else { String::new() };
+    let run = format!("width={width}, gens={generations}, init={init}{seeded}");
+    println!("Survey of all {size} k={colors},r={radius} {family} codes ({run})");
+    for class in Class::ALL {
+        let n = specimens.iter().filter(|s| s.class == class).count();
+        println!("  Class {} ({class}): {n}", class.number());
+    }
+
+    // Class 4 candidates: the least compressible before chaos first
+    let mut complex: Vec<(usize, &Specimen)> =
+        specimens.iter().enumerate().filter(|(_, s)| s.class == Class::Complex).collect();
+    complex.sort_by(|a, b| b.1.ratio.total_cmp(&a.1.ratio));
+    if !complex.is_empty() {
+        println!("\n{:>6} {:>8} {:>8} {:>8}", "Code", "Ratio", "Density", "Entropy");
+        println!("{}", "-".repeat(33));
+        for (code, s) in complex.iter().take(top) {
+            println!("{code:>6} {:>8.3} {:>8.3} {:>8.3}", s.ratio, s.density, s.entropy);
+        }
+        if complex.len() > top {
+            println!("  ... {} more (--top, or --format csv for every code)", complex.len() - top);
+        }
+    }
+    output.write(csv, json, columns);
+}
@@ -0,0 +1,314 @@
+//! Checkpoints for the 256-rule surveys: `--checkpoint FILE` appends each
+//! rule's result to FILE as soon as it is computed, and `--resume FILE`
+//! reruns the survey computing only the rules FILE lacks
+//!
+//! The file is text: a header line naming the survey and its settings, then
+//! one `rule<TAB>fields` line per finished rule, in the order they finished.
+//! Floats are written in Rust's shortest round-trip form, so a resumed
+//! survey reports exactly what an uninterrupted one would. A line cut short
+//! by a kill (no final newline) is ignored and recomputed.
+//!
+//! `--dedupe` surveys one rule per mirror/complement class instead, the
+//! smallest, since its equivalents behave the same mirrored or inverted.
+
+use super::log::{debug, note, verbose};
+use super::progress::Progress;
+use automata::{symmetry, CycleAnalysis};
+use clap::Args;
+use rayon::prelude::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+const MAGIC: &str = "# automata survey checkpoint:";
+
+#[derive(Args, Clone)]
+pub struct CheckpointArgs {
+    /// Append each rule's result to this file as the survey runs
+    #[arg(long, value_name = "FILE", conflicts_with = "resume")]
+    pub checkpoint: Option<PathBuf>,
+    /// Continue the survey checkpointed in this file, skipping the rules it
+    /// has; the other flags must match the ones it was started with
+    #[arg(long, value_name = "FILE")]
+    pub resume: Option<PathBuf>,
+    /// Survey only the smallest rule of each of the 88 mirror/complement classes
+    #[arg(long)]
+    pub dedupe: bool,
+}
+
+/// A per-rule result as whitespace-separated fields
+pub trait Record: Sized {
+    fn write(&self) -> String;
+    fn read(fields: &[&str]) -> Option<Self>;
+}
+
+impl CheckpointArgs {
+    /// The rules surveyed, in order: all 256, or with `--dedupe` each
+    /// class's representative
+    pub fn rules(&self) -> Vec<u8> {
+        if self.dedupe { symmetry::representatives() } else { (0..=255).collect() }
+    }
+
+    /// The rules surveyed, in words
+    pub fn describe(&self) -> String {
+        if self.dedupe { "the 88 class representatives".to_string() } else { "all 256 rules".to_string() }
+    }
+
+    /// Of a result for each of the 256 rules, those of the rules surveyed
+    pub fn select<T>(&self, results: Vec<T>) -> Vec<T> {
+        let rules = self.rules();
+        results.into_iter().enumerate().filter(|&(rule, _)| rules.contains(&(rule as u8))).map(|(_, r)| r).collect()
+    }
+
+    /// Each surveyed rule's result, in the order of `rules`, computed in
+    /// parallel; `settings` identifies the survey and its parameters in the
+    /// file's header
+    pub fn survey<T: Record + Send>(&self, settings: &str, compute: impl Fn(u8) -> T + Sync) -> Vec<T> {
+        // The settings start with the survey's name
+        let name = settings.split(' ').next().unwrap_or("survey");
+        let rules = self.rules();
+        let settings = &if self.dedupe { format!("{settings} dedupe") } else { settings.to_string() };
+        let start = Instant::now();
+        let timed = |rule: u8| {
+            let start = Instant::now();
+            let result = compute(rule);
+            debug!("{name}: rule {rule} in {:.1} ms", start.elapsed().as_secs_f64() * 1e3);
+            result
+        };
+        let finished = |count: usize| verbose!("{name}: {count} rules in {:.2} s", start.elapsed().as_secs_f64());
+        let threads = match rayon::current_num_threads() {
+            1 => "1 thread".to_string(),
+            n => format!("{n} threads"),
+        };
+        let Some(path) = self.resume.as_ref().or(self.checkpoint.as_ref()) else {
+            verbose!("{settings} on {threads}");
+            let progress = Progress::rules(name, rules.len());
+            let results = rules
+                .par_iter()
+                .map(|&rule| {
+                    let result = timed(rule);
+                    progress.tick();
+                    result
+                })
+                .collect();
+            drop(progress);
+            finished(rules.len());
+            return results;
+        };
+        let fail = |e: String| -> ! {
+            eprintln!("{}: {e}", path.display());
+            std::process::exit(1);
+        };
+
+        let mut done: Vec<Option<T>> = (0..256).map(|_| None).collect();
+        if self.resume.is_some() {
+            load(path, settings, &mut done).unwrap_or_else(|e| fail(e));
+            let count = done.iter().flatten().count();
+            note!("Resuming {}: {count} of {} rules done", path.display(), rules.len());
+        } else if path.exists() {
+            fail("already holds a survey checkpoint; continue it with --resume, or remove it".into());
+        } else {
+            std::fs::write(path, format!("{MAGIC} {settings}\n")).unwrap_or_else(|e| fail(e.to_string()));
+        }
+
+        let file = std::fs::OpenOptions::new().append(true).open(path).unwrap_or_else(|e| fail(e.to_string()));
+        let file = Mutex::new(file);
+        let missing: Vec<u8> = rules.iter().copied().filter(|&rule| done[rule as usize].is_none()).collect();
+        let count = missing.len();
+        verbose!("{settings} on {threads}, appending to {}", path.display());
+        let progress = Progress::rules(name, count);
+        let computed: Vec<(u8, T)> = missing
+            .into_par_iter()
+            .map(|rule| {
+                let result = timed(rule);
+                // One write per line, so a kill loses at most the line being written
+                let line = format!("{rule}\t{}\n", result.write());
+                if let Err(e) = file.lock().unwrap().write_all(line.as_bytes()) {
+                    fail(format!("can't append rule {rule}: {e}"));
+                }
+                progress.tick();
+                (rule, result)
+            })
+            .collect();
+        drop(progress);
+        finished(count);
+        for (rule, result) in computed {
+            done[rule as usize] = Some(result);
+        }
+        rules.iter().map(|&rule| done[rule as usize].take().expect("every rule is loaded or computed")).collect()
+    }
+}
+
+/// Fill `done` from a checkpoint written with the same `settings`
+fn load<T: Record>(path: &Path, settings: &str, done: &mut [Option<T>]) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut lines = text.split_inclusive('\n');
+    let header = lines.next().unwrap_or("").trim_end();
+    let Some(recorded) = header.strip_prefix(MAGIC).map(str::trim) else {
+        return Err("not a survey checkpoint".into());
+    };
+    if recorded != settings {
+        return Err(format!("was started as `{recorded}`, not `{settings}`; rerun with the same settings"));
+    }
+    for (number, line) in lines.enumerate() {
+        let Some(line) = line.strip_suffix('\n') else {
+            break;
+        };
+        let bad = || format!("line {}: bad record {line:?}", number + 2);
+        let (rule, fields) = line.split_once('\t').ok_or_else(bad)?;
+        let rule: u8 = rule.parse().map_err(|_| bad())?;
+        let fields: Vec<&str> = fields.split_whitespace().collect();
+        done[rule as usize] = Some(T::read(&fields).ok_or_else(bad)?);
+    }
+    Ok(())
+}
+
+impl Record for CycleAnalysis {
+    fn write(&self) -> String {
+        format!("{} {} {} {}", self.transient, self.period, self.died, self.final_density)
+    }
+
+    fn read(fields: &[&str]) -> Option<Self> {
+        let [transient, period, died, final_density] = fields else {
+            return None;
+        };
+        Some(CycleAnalysis {
+            transient: transient.parse().ok()?,
+            period: period.parse().ok()?,
+            died: died.parse().ok()?,
+            final_density: final_density.parse().ok()?,
+        })
+    }
+}
+
+impl Record for usize {
+    fn write(&self) -> String {
+        self.to_string()
+    }
+
+    fn read(fields: &[&str]) -> Option<Self> {
+        fields.first().filter(|_| fields.len() == 1)?.parse().ok()
+    }
+}
+
+impl Record for (f64, f64) {
+    fn write(&self) -> String {
+        format!("{} {}", self.0, self.1)
+    }
+
+    fn read(fields: &[&str]) -> Option<Self> {
+        let [a, b] = fields else {
+            return None;
+        };
+        Some((a.parse().ok()?, b.parse().ok()?))
+    }
+}
+
+impl Record for (usize, usize, f64) {
+    fn write(&self) -> String {
+        format!("{} {} {}", self.0, self.1, self.2)
+    }
+
+    fn read(fields: &[&str]) -> Option<Self> {
+        let [a, b, c] = fields else {
+            return None;
+        };
+        Some((a.parse().ok()?, b.parse().ok()?, c.parse().ok()?))
+    }
+}
+
+/// Block counts for k = 1, 2, ... and the sample ceiling they were counted under
+impl Record for (Vec<usize>, usize) {
+    fn write(&self) -> String {
+        let counts: Vec<String> = self.0.iter().map(usize::to_string).collect();
+        format!("{} {}", self.1, counts.join(" "))
+    }
+
+    fn read(fields: &[&str]) -> Option<Self> {
+        let (samples, counts) = fields.split_first()?;
+        Some((counts.iter().map(|c| c.parse().ok()).collect::<Option<_>>()?, samples.parse().ok()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Init, InitArgs, Quiescent, TempDir};
+    use super::*;
+    use automata::find_cycle_from;
+
+    /// `analyze`'s header settings
+    fn settings(width: usize, max_steps: usize, init: &InitArgs) -> String {
+        format!("analyze width={width} max_steps={max_steps}{}", init.describe())
+    }
+
+    fn random(seed: u64) -> InitArgs {
+        InitArgs { init: Init::Random, density: 0.5, seed, init_file: None, quiescent: Quiescent::Dead }
+    }
+
+    fn loaded(path: &Path, settings: &str) -> Result<Vec<Option<String>>, String> {
+        let mut done: Vec<Option<CycleAnalysis>> = (0..256).map(|_| None).collect();
+        load(path, settings, &mut done)?;
+        Ok(done.iter().map(|result| result.as_ref().map(Record::write)).collect())
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = TempDir::new("checkpoint");
+        let path = dir.join("round-trip");
+        let (init, settings) = (random(7), settings(12, 500, &random(7)));
+        let compute = |rule: u8| find_cycle_from(init.automaton(12, rule), 500);
+        let args = CheckpointArgs { checkpoint: Some(path.clone()), resume: None, dedupe: true };
+        let surveyed: Vec<String> = args.survey(&settings, compute).iter().map(Record::write).collect();
+
+        let done = loaded(&path, &format!("{settings} dedupe")).unwrap();
+        assert_eq!(done.iter().flatten().count(), 88);
+        let reloaded: Vec<String> = args.rules().iter().map(|&rule| done[rule as usize].clone().unwrap()).collect();
+        assert_eq!(reloaded, surveyed);
+        // A resumed survey computes nothing and reports the same
+        let resume = CheckpointArgs { checkpoint: None, resume: Some(path.clone()), ..args };
+        let resumed = resume.survey(&settings, |_| -> CycleAnalysis { panic!("every rule is done") });
+        assert_eq!(resumed.iter().map(Record::write).collect::<Vec<_>>(), surveyed);
+    }
+
+    #[test]
+    fn test_settings_must_match() {
+        let dir = TempDir::new("checkpoint");
+        let path = dir.join("settings");
+        let started = settings(12, 500, &random(7));
+        std::fs::write(&path, format!("{MAGIC} {started}\n30\t3 4 false 0.5\n")).unwrap();
+        assert_eq!(loaded(&path, &started).unwrap()[30].as_deref(), Some("3 4 false 0.5"));
+        let live = InitArgs { quiescent: Quiescent::Live, ..random(7) };
+        let single = InitArgs { init: Init::Single, ..random(7) };
+        let others = [
+            settings(13, 500, &random(7)),
+            settings(12, 501, &random(7)),
+            settings(12, 500, &random(8)),
+            settings(12, 500, &single),
+            settings(12, 500, &live),
+            format!("{started} dedupe"),
+        ];
+        for other in others {
+            let error = loaded(&path, &other).unwrap_err();
+            assert!(error.contains(&format!("was started as `{started}`, not `{other}`")), "{error}");
+        }
+        std::fs::write(&path, "rule\tperiod\n").unwrap();
+        assert_eq!(loaded(&path, &started).unwrap_err(), "not a survey checkpoint");
+    }
+
+    #[test]
+    fn test_truncated_records() {
+        let dir = TempDir::new("checkpoint");
+        let path = dir.join("truncated");
+        let started = settings(12, 500, &random(7));
+        // Killed partway through rule 90's line: it is recomputed
+        std::fs::write(&path, format!("{MAGIC} {started}\n30\t3 4 false 0.5\n90\t0 1 tr")).unwrap();
+        let done = loaded(&path, &started).unwrap();
+        assert_eq!((done[30].is_some(), done[90].is_none()), (true, true));
+        // A whole line that doesn't read is an error, not a gap
+        for record in ["90\t0 1 true\n", "90 0 1 true 0\n", "256\t0 1 true 0\n"] {
+            std::fs::write(&path, format!("{MAGIC} {started}\n30\t3 4 false 0.5\n{record}")).unwrap();
+            assert!(loaded(&path, &started).unwrap_err().starts_with("line 3: bad record"), "{record:?}");
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! `classes`: the 256 elementary rules in their 88 mirror/complement
+//! classes, each under its smallest rule
+
+use super::json::{print_report, Json};
+use super::{parse_rule, ReportFormat};
+use automata::symmetry::{self, Class};
+use clap::Args;
+
+#[derive(Args, Clone)]
+pub struct ClassesArgs {
+    /// Only this rule's class (a rule number, or a Wolfram code for one)
+    #[arg(value_parser = parse_rule)]
+    pub rule: Option<u8>,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+pub fn classes(args: &ClassesArgs) {
+    let ClassesArgs { rule, format } = *args;
+    let classes = match rule {
+        Some(rule) => vec![symmetry::class(rule)],
+        None => symmetry::classes(),
+    };
+
+    if format == ReportFormat::Json {
+        let entries = classes.iter().map(|class| {
+            Json::object([
+                ("representative", class.representative.into()),
+                ("mirror", class.mirror.into()),
+                ("complement", class.complement.into()),
+                ("both", class.both.into()),
+                ("members", Json::array(class.members.clone())),
+            ])
+        });
+        let results = Json::object([("classes", Json::array(entries))]);
+        return print_report("classes", Json::object([("rule", rule.into())]), results);
+    }
+
+    match rule {
+        Some(rule) => {
+            let class = &classes[0];
+            println!("Rule {rule} is in the class of rule {}: {}", class.representative, members(class));
+        }
+        None => {
+            let sizes = |n| classes.iter().filter(|c| c.members.len() == n).count();
+            println!(
+                "{} classes of the 256 elementary rules under mirror and complement ({} of 1 rule, {} of 2, {} of 4)",
+                classes.len(),
+                sizes(1),
+                sizes(2),
+                sizes(4)
+            );
+        }
+    }
+    println!("{:>4} {:>7} {:>11} {:>5}  Members", "Rule", "Mirror", "Complement", "Both");
+    println!("{}", "-".repeat(48));
+    for class in &classes {
+        println!(
+            "{:>4} {:>7} {:>11} {:>5}  {}",
+            class.representative,
+            class.mirror,
+            class.complement,
+            class.both,
+            members(class)
+        );
+    }
+}
+
+fn members(class: &Class) -> String {
+    class.members.iter().map(u8::to_string).collect::<Vec<_>>().join(" ")
+}
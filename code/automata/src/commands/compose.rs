@@ -0,0 +1,98 @@
+//! `compose`: the radius-t lookup table of an elementary rule applied t
+//! times, checked against t steps of the rule on random rows
+
+use super::json::{print_report, Json};
+use super::{parse_rule, parse_width, ReportFormat};
+use automata::compose::{Iterated, MAX_TIMES};
+use clap::Args;
+
+/// Most windows listed in the table; wider tables print as hex only
+const MAX_LISTED: usize = 128;
+
+#[derive(Args, Clone)]
+pub struct ComposeArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(value_parser = parse_rule)]
+    pub rule: u8,
+    /// Steps composed, which is the table's radius (1-8)
+    #[arg(short, long, default_value_t = 2)]
+    pub times: usize,
+    /// Number of cells in the rows the table is checked on
+    #[arg(short, long, default_value_t = 101, value_parser = parse_width)]
+    pub width: usize,
+    /// Random rows the table is checked on
+    #[arg(long, default_value_t = 100)]
+    pub samples: usize,
+    /// Seed of the first random row (each next row's is one more)
+    #[arg(long, default_value_t = 42)]
+    pub seed: u64,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+pub fn compose(args: &ComposeArgs) {
+    let ComposeArgs { rule, times, width, samples, seed, format } = *args;
+    if !(1..=MAX_TIMES).contains(&times) {
+        eprintln!("--times must be 1 to {MAX_TIMES}");
+        std::process::exit(1);
+    }
+    let iterated = Iterated::new(rule, times);
+    let verified = iterated.verify(width, samples, seed).unwrap_or_else(|e| {
+        eprintln!("Rule {rule} x {times}: the table disagrees with the rule: {e}");
+        std::process::exit(1);
+    });
+    let entries = iterated.table.len();
+    let lambda = iterated.ones() as f64 / entries as f64;
+    let dependencies = iterated.dependencies();
+    let spec = iterated.table_rule().map(|table| table.to_string());
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("times", times.into()),
+            ("width", width.into()),
+            ("samples", samples.into()),
+            ("seed", seed.into()),
+        ]);
+        let results = Json::object([
+            ("radius", iterated.radius().into()),
+            ("table", iterated.hex().into()),
+            ("spec", spec.into()),
+            ("ones", iterated.ones().into()),
+            ("lambda", lambda.into()),
+            ("depends_on", Json::array(dependencies.iter().map(|&offset| offset as i64))),
+            ("verified_cells", verified.into()),
+        ]);
+        return print_report("compose", parameters, results);
+    }
+
+    let steps = if times == 1 { "once".to_string() } else { format!("{times} times") };
+    println!("Rule {rule} applied {steps}: a radius-{times} table of {entries} windows");
+    match &spec {
+        Some(spec) => println!("  Table: {spec}"),
+        None if entries <= 4 * MAX_LISTED => println!("  Table (hex, highest window first): {}", iterated.hex()),
+        None => println!("  Table: {} hex digits (--format json for them)", entries / 4),
+    }
+    println!("  Ones: {} of {entries} windows (λ = {lambda:.3})", iterated.ones());
+    let offsets: Vec<String> = dependencies.iter().map(|offset| format!("{offset:+}")).collect();
+    let reads = match offsets.len() {
+        0 => "no cell".to_string(),
+        1 => format!("cell {}", offsets[0]),
+        _ => format!("cells {}", offsets.join(", ")),
+    };
+    println!("  Reads: {reads} of -{times}..+{times}");
+    if entries <= MAX_LISTED {
+        // As `run` prints a table rule's
+        println!("  Transition table:");
+        let size = 2 * times + 1;
+        let entries: Vec<String> =
+            (0..entries).rev().map(|w| format!("{w:0size$b} -> {}", iterated.table[w] as u8)).collect();
+        for line in entries.chunks(4) {
+            println!("    {}", line.join("    "));
+        }
+    }
+    println!("  Verified: one step of it is rule {rule} applied {steps}, on {samples} random rows of {width} cells");
+    if let Some(spec) = spec {
+        println!("  Run it: automata run {spec}");
+    }
+}
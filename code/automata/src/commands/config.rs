@@ -0,0 +1,162 @@
+//! Experiment files for `--config`: a TOML description of the rules, ring,
+//! initial condition and analyses of an experiment, expanded into the
+//! command lines that run it
+//!
+//! ```toml
+//! rules = [30, 110, "k=2,r=1,code=10"]
+//! width = 64
+//! generations = 200
+//! boundary = "periodic"
+//!
+//! [init]
+//! kind = "random"
+//! density = 0.3
+//! seed = 7
+//!
+//! [[analysis]]
+//! command = "entropy"
+//! block = 4
+//!
+//! [[analysis]]
+//! command = "render"
+//! out = "rule{rule}.png"
+//! ```
+//!
+//! Each analysis runs once per rule, or once if its command takes no rule
+//! (the 256-rule surveys). The shared settings go to the commands that have
+//! the flag, so one file can mix commands with different options; an
+//! analysis's own keys, which are the command's long flags, come after and
+//! win. `true` passes a bare flag, `false` leaves it out, arrays are
+//! comma-joined, `args` lists positional arguments, and `{rule}` in a
+//! string stands for the run's rule.
+
+use automata::toml::{self, Table, Value};
+use std::path::Path;
+
+/// Settings under `[init]` and the flags they become
+const INIT_KEYS: [(&str, &str); 5] =
+    [("kind", "init"), ("density", "density"), ("seed", "seed"), ("file", "init-file"), ("quiescent", "quiescent")];
+
+/// The command lines, program name first, that run the experiment in `path`
+pub fn expand(path: &Path, cli: &clap::Command) -> Result<Vec<Vec<String>>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("can't read {}: {e}", path.display()))?;
+    let doc = toml::parse(&text)?;
+    for (key, _) in &doc.0 {
+        if !["rule", "rules", "width", "generations", "boundary", "init", "analysis"].contains(&key.as_str()) {
+            let expected = "rule(s), width, generations, boundary, init or analysis";
+            return Err(format!("unknown key {key:?}: expected {expected}"));
+        }
+    }
+
+    let rules: Vec<String> = match (doc.get("rule"), doc.get("rules")) {
+        (Some(_), Some(_)) => return Err("give rule or rules, not both".into()),
+        (Some(rule), None) => vec![scalar("rule", rule)?],
+        (None, Some(Value::Array(rules))) => rules.iter().map(|r| scalar("rules", r)).collect::<Result<_, _>>()?,
+        (None, Some(other)) => return Err(format!("rules must be an array, not a {}", other.type_name())),
+        (None, None) => Vec::new(),
+    };
+    match doc.get("boundary") {
+        None => {}
+        Some(Value::String(b)) if b == "periodic" || b == "ring" => {}
+        Some(other) => return Err(format!("boundary {other}: only periodic (ring) boundaries are simulated")),
+    }
+
+    // Shared flags, as (long name, value)
+    let mut shared = Vec::new();
+    for (key, flag) in [("width", "width"), ("generations", "gens")] {
+        if let Some(value) = doc.get(key) {
+            shared.push((flag.to_string(), Some(scalar(key, value)?)));
+        }
+    }
+    match doc.get("init") {
+        None => {}
+        Some(Value::Table(init)) => {
+            for (key, value) in &init.0 {
+                let (_, flag) = INIT_KEYS
+                    .iter()
+                    .find(|(k, _)| k == key)
+                    .ok_or(format!("unknown init key {key:?}: expected kind, density, seed, file or quiescent"))?;
+                shared.push((flag.to_string(), Some(scalar(key, value)?)));
+            }
+        }
+        Some(other) => return Err(format!("init must be a table, not a {}", other.type_name())),
+    }
+
+    let analyses: Vec<&Table> = match doc.get("analysis") {
+        Some(Value::Array(tables)) => tables
+            .iter()
+            .map(|t| match t {
+                Value::Table(table) => Ok(table),
+                other => Err(format!("analysis must be [[analysis]] tables, not a {}", other.type_name())),
+            })
+            .collect::<Result<_, _>>()?,
+        Some(other) => return Err(format!("analysis must be [[analysis]] tables, not a {}", other.type_name())),
+        None => return Err("no [[analysis]] tables: nothing to run".into()),
+    };
+
+    let mut runs = Vec::new();
+    for (number, analysis) in analyses.iter().enumerate() {
+        let context = |e: String| format!("analysis {}: {e}", number + 1);
+        let command = match analysis.get("command") {
+            Some(Value::String(command)) => command,
+            Some(other) => return Err(context(format!("command must be a string, not a {}", other.type_name()))),
+            None => return Err(context("no command".into())),
+        };
+        let sub = cli.find_subcommand(command).ok_or_else(|| context(format!("no such command {command:?}")))?;
+        let has = |long: &str| sub.get_arguments().any(|arg| arg.get_long() == Some(long));
+        let takes_rule = sub.get_arguments().any(|arg| arg.is_positional() && arg.get_id() == "rule");
+
+        let mut positional = Vec::new();
+        let mut own = Vec::new();
+        for (key, value) in &analysis.0 {
+            match (key.as_str(), value) {
+                ("command", _) => {}
+                ("args", Value::Array(args)) => {
+                    positional = args.iter().map(|a| scalar("args", a)).collect::<Result<_, _>>().map_err(context)?
+                }
+                ("args", other) => return Err(context(format!("args must be an array, not a {}", other.type_name()))),
+                (_, Value::Boolean(false)) => {}
+                (_, Value::Boolean(true)) => own.push((key.replace('_', "-"), None)),
+                (_, Value::Table(_)) => return Err(context(format!("{key} can't be a table"))),
+                (_, value) => own.push((key.replace('_', "-"), Some(value.to_string()))),
+            }
+        }
+        let flags: Vec<&(String, Option<String>)> = shared
+            .iter()
+            .filter(|(long, _)| has(long) && !own.iter().any(|(k, _)| k == long))
+            .chain(&own)
+            .collect();
+
+        let rules: Vec<Option<&String>> =
+            if takes_rule && !rules.is_empty() { rules.iter().map(Some).collect() } else { vec![None] };
+        for rule in rules {
+            let fill = |s: &str| rule.map_or(s.to_string(), |rule| s.replace("{rule}", rule));
+            let mut argv = vec!["automata".to_string(), command.clone()];
+            argv.extend(rule.cloned());
+            argv.extend(positional.iter().map(|a| fill(a)));
+            for (long, value) in &flags {
+                argv.push(format!("--{long}"));
+                argv.extend(value.as_deref().map(fill));
+            }
+            runs.push(argv);
+        }
+    }
+    Ok(runs)
+}
+
+/// A value that fits in one command-line argument
+pub fn scalar(key: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(_) | Value::Integer(_) | Value::Float(_) | Value::Boolean(_) => Ok(value.to_string()),
+        other => Err(format!("{key} must be a string or number, not a {}", other.type_name())),
+    }
+}
+
+/// A command line as it would be typed into a shell
+pub fn shell_line(argv: &[String]) -> String {
+    let quote = |a: &String| {
+        let plain = !a.is_empty() && !a.contains(|c: char| c.is_whitespace() || "'\"$&;|<>(){}*?".contains(c));
+        if plain { a.clone() } else { format!("'{}'", a.replace('\'', r"'\''")) }
+    };
+    argv.iter().map(quote).collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,164 @@
+//! `lyapunov`: damage spreading between two runs a cell apart, averaged over
+//! random rows: the Hamming distance over time, its growth rate and the
+//! Lyapunov exponent, for one rule or every elementary class
+
+use super::json::{print_report, Json};
+use super::progress::Progress;
+use super::{parse_binary_rule, parse_width, BinaryRule, ReportFormat};
+use automata::damage::{damage_spreading, Damage};
+use automata::evolve::TableRule;
+use automata::symmetry;
+use clap::Args;
+
+#[derive(Args, Clone)]
+pub struct LyapunovArgs {
+    /// Rule number (0-255), a Wolfram code, or a radius-2 or 3 table
+    /// (default: every elementary class, by its smallest rule)
+    #[arg(value_parser = parse_binary_rule)]
+    pub rule: Option<BinaryRule>,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 101, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to run each pair of copies
+    #[arg(short = 'g', long = "gens", default_value_t = 200)]
+    pub generations: usize,
+    /// Random rows averaged over
+    #[arg(short = 'n', long, default_value_t = 16)]
+    pub samples: usize,
+    /// Seed of the first row; row i uses seed + i
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+/// Where the damage went, as a classification: healed everywhere, stayed
+/// within a tenth of the ring over the second half of the run, or spread
+/// past it
+fn verdict(damage: &Damage) -> &'static str {
+    match damage.late_fraction() {
+        _ if damage.healed == damage.samples => "heals (class 1)",
+        f if f < 0.1 => "stays local (class 2)",
+        _ => "spreads (class 3 or 4)",
+    }
+}
+
+/// An exponent, with -∞ for defects that all died
+fn exponent(x: Option<f64>) -> String {
+    x.map_or("-inf".to_string(), |x| format!("{x:.3}"))
+}
+
+/// The summary fields of a report
+fn fields(damage: &Damage) -> [(&'static str, Json); 8] {
+    [
+        ("lyapunov", damage.lyapunov.into()),
+        ("extinct", damage.extinct.into()),
+        ("growth", damage.growth.into()),
+        ("final_distance", damage.distance.last().copied().into()),
+        ("final_fraction", damage.final_fraction().into()),
+        ("late_fraction", damage.late_fraction().into()),
+        ("healed", damage.healed.into()),
+        ("verdict", verdict(damage).into()),
+    ]
+}
+
+pub fn lyapunov(args: &LyapunovArgs) {
+    let LyapunovArgs { rule, width, generations, samples, seed, format } = *args;
+    if samples == 0 || generations == 0 {
+        eprintln!("--samples and --gens must be at least 1");
+        std::process::exit(1);
+    }
+    let Some(rule) = rule else {
+        return survey(args);
+    };
+    let table = match rule {
+        BinaryRule::Elementary(r) => TableRule::new(1, r as u128),
+        BinaryRule::Table(t) => t,
+    };
+    let damage = damage_spreading(table, width, generations, samples, seed);
+    let fitted = Damage::unwrapped(table.radius, width, generations);
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("samples", samples.into()),
+            ("seed", seed.into()),
+        ]);
+        let series = [("fitted_generations", fitted.into()), ("distance", damage.distance.clone().into())];
+        let results = Json::object(fields(&damage).into_iter().chain(series));
+        return print_report("lyapunov", parameters, results);
+    }
+
+    println!("Damage spreading: rule {rule} (width={width}, gens={generations}, samples={samples}, seed={seed})");
+    println!("  Lyapunov exponent: {} (defects died out in {} of {samples})", exponent(damage.lyapunov), damage.extinct);
+    match damage.growth {
+        Some(rate) => println!("  Damage growth: {rate:.3} cells per step (generations 0-{fitted})"),
+        None => println!("  Damage growth: not fitted (generations 0-{fitted})"),
+    }
+    println!(
+        "  Final damage: {:.1} cells ({:.1}% of the ring, {:.1}% over the second half); healed in {} of {samples}",
+        damage.distance[generations],
+        100.0 * damage.final_fraction(),
+        100.0 * damage.late_fraction(),
+        damage.healed
+    );
+    println!("  Verdict: {}", verdict(&damage));
+    println!("{:>5} {:>9}", "Gen", "Distance");
+    println!("{}", "-".repeat(15));
+    for (g, distance) in damage.distance.iter().enumerate() {
+        // Every 10th generation, plus the first few and the last
+        if g <= 5 || g % 10 == 0 || g == generations {
+            println!("{g:>5} {distance:>9.2}");
+        }
+    }
+}
+
+/// Every elementary class's smallest rule, most chaotic first
+fn survey(args: &LyapunovArgs) {
+    let LyapunovArgs { width, generations, samples, seed, format, .. } = *args;
+    let rules: Vec<u8> = symmetry::classes().iter().map(|class| class.representative).collect();
+    let progress = Progress::rules("lyapunov", rules.len());
+    let mut damages: Vec<(u8, Damage)> = rules
+        .iter()
+        .map(|&rule| {
+            let damage = damage_spreading(TableRule::new(1, rule as u128), width, generations, samples, seed);
+            progress.tick();
+            (rule, damage)
+        })
+        .collect();
+    drop(progress);
+    let key = |damage: &Damage| damage.lyapunov.unwrap_or(f64::NEG_INFINITY);
+    damages.sort_by(|(_, a), (_, b)| key(b).total_cmp(&key(a)));
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("samples", samples.into()),
+            ("seed", seed.into()),
+        ]);
+        let entry = |(rule, damage): &(u8, Damage)| {
+            Json::object([("rule", (*rule).into())].into_iter().chain(fields(damage)))
+        };
+        let entries = damages.iter().map(entry);
+        let results = Json::object([("rules", Json::array(entries))]);
+        return print_report("lyapunov", parameters, results);
+    }
+
+    let settings = format!("width={width}, gens={generations}, samples={samples}, seed={seed}");
+    println!("Damage spreading in the {} elementary classes ({settings})", rules.len());
+    println!("{:>4} {:>9} {:>8} {:>8} {:>7}  Verdict", "Rule", "Lyapunov", "Growth", "Damage", "Healed");
+    println!("{}", "-".repeat(62));
+    for (rule, damage) in &damages {
+        println!(
+            "{rule:>4} {:>9} {:>8} {:>7.1}% {:>7}  {}",
+            exponent(damage.lyapunov),
+            damage.growth.map_or("-".to_string(), |g| format!("{g:.3}")),
+            100.0 * damage.late_fraction(),
+            format!("{}/{samples}", damage.healed),
+            verdict(damage)
+        );
+    }
+}
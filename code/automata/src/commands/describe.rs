@@ -0,0 +1,111 @@
+//! `describe`: a run in words, a labeled line per aspect, or one paragraph
+//! to paste as an image's alt text
+
+use super::json::{print_report, Json};
+use super::{parse_rule, parse_width, Init, InitArgs, ReportFormat};
+use automata::describe::{describe as measure, Spread};
+use clap::Args;
+
+#[derive(Args, Clone)]
+pub struct DescribeArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 30, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 101, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 100)]
+    pub generations: usize,
+    /// One paragraph (alt text) instead of a line per aspect
+    #[arg(long, conflicts_with = "format")]
+    pub alt: bool,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+pub fn describe(args: &DescribeArgs) {
+    let DescribeArgs { rule, width, generations, alt, format, ref init } = *args;
+    if generations == 0 {
+        eprintln!("--gens must be at least 1");
+        std::process::exit(1);
+    }
+    let description = measure(&init.automaton(width, rule), generations);
+    let subject = format!("Rule {rule} from {}", start(init, rule));
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("init", init.to_json()),
+        ]);
+        let d = &description;
+        let (edges, extent) = match d.spread {
+            Spread::Fills { edges, .. } | Spread::Grows { edges } => (Json::array(edges), Json::Null),
+            Spread::Confined { extent } => (Json::Null, extent.into()),
+            Spread::Vanishes { .. } => (Json::Null, Json::Null),
+        };
+        let at = match d.spread {
+            Spread::Vanishes { at } | Spread::Fills { at, .. } => Some(at),
+            _ => None,
+        };
+        let spread = match d.spread {
+            Spread::Vanishes { .. } => "vanishes",
+            Spread::Fills { .. } => "fills",
+            Spread::Grows { .. } => "grows",
+            Spread::Confined { .. } => "confined",
+        };
+        let results = Json::object([
+            ("class", d.class.to_string().into()),
+            ("wolfram_class", d.class.number().into()),
+            ("transient", d.cycle.map(|(transient, _)| transient).into()),
+            ("period", d.cycle.map(|(_, period)| period).into()),
+            ("compression_ratio", d.ratio.into()),
+            ("mirror_rule", d.mirror_rule.into()),
+            ("mirrored_rows", d.mirrored_rows.into()),
+            ("spread", spread.into()),
+            ("spread_at", at.into()),
+            ("edge_speeds", edges),
+            ("extent", extent),
+            ("ether_spatial_period", d.structures.map(|s| s.ether.0).into()),
+            ("ether_temporal_period", d.structures.map(|s| s.ether.1).into()),
+            ("vacuum", d.structures.and_then(|s| s.vacuum).into()),
+            ("particles_from", d.structures.map(|s| s.from).into()),
+            ("particles_then", d.structures.map(|s| s.particles.0).into()),
+            ("particles_end", d.structures.map(|s| s.particles.1).into()),
+            ("event_at", d.event.map(|e| e.at).into()),
+            ("event_entropy_before", d.event.map(|e| e.before).into()),
+            ("event_entropy_after", d.event.map(|e| e.after).into()),
+            ("density_start", d.density.start.into()),
+            ("density_end", d.density.end.into()),
+            ("density_period", d.density.period.into()),
+            ("lines", Json::Object(d.lines().into_iter().map(|(label, line)| (label, line.into())).collect())),
+            ("alt_text", d.alt_text(&subject).into()),
+        ]);
+        print_report("describe", parameters, results);
+        return;
+    }
+
+    if alt {
+        println!("{}", description.alt_text(&subject));
+        return;
+    }
+    println!("{subject} (width={width}, gens={generations}{})", init.describe());
+    for (label, line) in description.lines() {
+        println!("  {label}: {line}");
+    }
+}
+
+/// The initial row, as a sentence names it
+fn start(init: &InitArgs, rule: u8) -> String {
+    let cell = if init.quiescent.resolve(rule) { "dead" } else { "live" };
+    match (&init.init_file, &init.init) {
+        (Some(path), _) => format!("the last row of {}", path.display()),
+        (None, Init::Single) => format!("a single {cell} cell"),
+        (None, Init::Random) => format!("a random row ({:.0}% live)", 100.0 * init.density),
+        (None, init @ Init::Cells(_)) => format!("the row {init}"),
+    }
+}
@@ -0,0 +1,103 @@
+//! `discover`: rules of a k-color, radius-r space that are number-conserving,
+//! monotone or outer-totalistic, each with certificates checked against its
+//! table
+
+use super::json::{print_report, Json};
+use super::ReportFormat;
+use automata::discover::{search, Certificate, Property};
+use clap::Args;
+
+#[derive(Args, Clone)]
+pub struct DiscoverArgs {
+    /// Colors k of the rule space
+    #[arg(short = 'k', long, default_value_t = 2)]
+    pub colors: u8,
+    /// Radius r of the rule space
+    #[arg(short, long, default_value_t = 2)]
+    pub radius: usize,
+    /// Rules whose every ring keeps its sum of cell values
+    #[arg(long)]
+    pub conserving: bool,
+    /// Rules where raising a cell never lowers the next state
+    #[arg(long)]
+    pub monotone: bool,
+    /// Rules depending only on the center and the sum of the rest
+    #[arg(long)]
+    pub outer_totalistic: bool,
+    /// Most rules to find, 0 for all of them
+    #[arg(short = 'n', long, default_value_t = 20)]
+    pub limit: usize,
+    /// Most search nodes (table entries assigned, or codes tried) before giving up
+    #[arg(long, default_value_t = 1 << 24)]
+    pub budget: u64,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+pub fn discover(args: &DiscoverArgs) {
+    let DiscoverArgs { colors, radius, conserving, monotone, outer_totalistic, limit, budget, format } = *args;
+    let properties: Vec<Property> = [
+        (conserving, Property::Conserving),
+        (monotone, Property::Monotone),
+        (outer_totalistic, Property::OuterTotalistic),
+    ]
+    .into_iter()
+    .filter_map(|(wanted, property)| wanted.then_some(property))
+    .collect();
+    if properties.is_empty() {
+        eprintln!("Give one or more of --conserving, --monotone and --outer-totalistic");
+        std::process::exit(1);
+    }
+    let search = search(colors, radius, &properties, limit, budget).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    let names: Vec<String> = properties.iter().map(|p| p.to_string()).collect();
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("colors", colors.into()),
+            ("radius", radius.into()),
+            ("properties", Json::array(names)),
+            ("limit", limit.into()),
+            ("budget", budget.into()),
+        ]);
+        let rules = search.found.iter().map(|found| {
+            let certificates = found.certificates.iter().map(|(property, certificate)| {
+                let evidence = match certificate {
+                    Certificate::Conserving { flux } => ("flux", Json::array(flux.clone())),
+                    Certificate::Monotone { pairs } => ("pairs", (*pairs).into()),
+                    Certificate::OuterTotalistic { rule } => ("rule", rule.to_string().into()),
+                };
+                Json::object([("property", property.to_string().into()), evidence])
+            });
+            Json::object([("rule", found.rule.to_string().into()), ("certificates", Json::array(certificates))])
+        });
+        let results = Json::object([
+            ("built", search.built.to_string().into()),
+            ("nodes", search.nodes.into()),
+            ("complete", search.complete.into()),
+            ("found", search.found.len().into()),
+            ("rules", Json::array(rules)),
+        ]);
+        return print_report("discover", parameters, results);
+    }
+
+    let how = match (search.complete, limit > 0 && search.found.len() >= limit) {
+        (true, _) => "all there are".to_string(),
+        (false, true) => format!("stopped at --limit {limit}"),
+        (false, false) => format!("the --budget of {budget} ran out, so there may be more"),
+    };
+    println!("{} rules with k={colors}, r={radius}: {} found, {how}", names.join(", "), search.found.len());
+    let checked = if properties.len() > 1 { ", checking the rest on each" } else { "" };
+    println!("  Built {} tables ({} search nodes){checked}", search.built, search.nodes);
+    for found in &search.found {
+        match found.rule.elementary() {
+            Some(rule) => println!("{} (rule {rule})", found.rule),
+            None => println!("{}", found.rule),
+        }
+        for (property, certificate) in &found.certificates {
+            println!("    {property}: {certificate}");
+        }
+    }
+}
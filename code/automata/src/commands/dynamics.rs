@@ -0,0 +1,2223 @@
+//! Long-run dynamics: cycles, entropy, compression and block complexity
+
+use automata::basins::{
+    binomial, density_strata, enumerate_basins, enumerate_by_popcount, find_attractor, in_degrees, sample_attractors,
+    sample_attractors_stratified, CycleFingerprint, InDegrees, SampledAttractor, StratifiedSample, MAX_GRAPH_WIDTH,
+    MAX_WIDTH,
+};
+use automata::diagnostics;
+use automata::ether::{best_ether_candidate, EtherSettings};
+use automata::evolve::TableRule;
+use automata::field::{Field, FieldOptions};
+use automata::gpu::{self, GpuError};
+use automata::inference::mean_std;
+use automata::linear::{Linear, Orbit};
+use automata::metadata::Tagged;
+use automata::parquet::Column;
+use automata::preimage::Surjectivity;
+use automata::profile;
+use automata::render::{write_curves, write_plot, PlotOptions};
+use automata::stochastic::{recurrences, sweep_mixture, MixturePoint, SweepSettings};
+use automata::symmetry::Rule;
+use automata::wolfram::{Family, WolframRule};
+use automata::{
+    block_complexity_from, compression_ratio_from, find_cycle_from, fit_growth, series_period, Automaton, GrowthClass,
+    GrowthFit, HASH_VERSION, INTERESTING_RULES,
+};
+use super::json::{print_report, report, value_name, Json};
+use super::log::{note, verbose};
+use super::checkpoint::CheckpointArgs;
+use super::progress::Progress;
+use super::{
+    metadata, parse_binary_rule, parse_rule, parse_width, require_block, write_csv, BinaryRule, InitArgs, MemoryArgs,
+    NoiseArgs, ReportFormat, ScheduleArgs, SurveyOutput,
+};
+use clap::{Args, ValueEnum};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args, Clone)]
+pub struct AnalyzeArgs {
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 31, value_parser = parse_width)]
+    pub width: usize,
+    /// Give up looking for a cycle after this many steps
+    #[arg(short, long, default_value_t = 1000)]
+    pub max_steps: usize,
+    /// Step all rules together on the GPU (needs the `gpu` feature)
+    #[arg(long, conflicts_with_all = ["checkpoint", "resume"])]
+    pub gpu: bool,
+    /// Ring whose every state is stepped for in-degree statistics and
+    /// Garden-of-Eden counts (0 for none)
+    #[arg(long, default_value_t = 12)]
+    pub in_degree_width: usize,
+    #[command(flatten)]
+    pub init: InitArgs,
+    #[command(flatten)]
+    pub output: SurveyOutput,
+    #[command(flatten)]
+    pub checkpoint: CheckpointArgs,
+}
+
+/// Widest ring `analyze --in-degree-width` steps every state of, per rule
+const MAX_IN_DEGREE_WIDTH: usize = 20;
+
+/// Widest ring `analyze` decides each rule's bijectivity on
+const MAX_DECIDED_RING: usize = 64;
+
+/// A rule's shortest orphan as bits, or nothing for a surjective rule
+fn orphan(decided: &Surjectivity) -> String {
+    decided.orphan.iter().flatten().map(|&c| if c { '1' } else { '0' }).collect()
+}
+
+pub fn analyze(args: &AnalyzeArgs) {
+    let AnalyzeArgs { width, max_steps, gpu, in_degree_width, ref init, ref output, ref checkpoint } = *args;
+    // Analyze all 256 rules for cycle behavior
+    if in_degree_width > MAX_IN_DEGREE_WIDTH {
+        eprintln!("--in-degree-width steps all 2^width states of every rule: up to {MAX_IN_DEGREE_WIDTH}");
+        std::process::exit(1);
+    }
+
+    // Rules are independent: run them in parallel, then report in rule order
+    let initial = survey_rows(init, width);
+    let rules = checkpoint.rules();
+    // Predecessor counts on the small ring, every state of it
+    let degrees: Option<Vec<InDegrees>> =
+        (in_degree_width > 0).then(|| rules.par_iter().map(|&rule| in_degrees(rule, in_degree_width)).collect());
+    // Surjective and injective on the infinite lattice, bijective on which rings
+    let decided: Vec<Surjectivity> =
+        rules.par_iter().map(|&rule| Surjectivity::of(TableRule::new(1, rule as u128), MAX_DECIDED_RING)).collect();
+    let gpu = gpu_survey(gpu, || gpu::find_cycles(&initial, max_steps)).map(|all| checkpoint.select(all));
+    let analyses: Vec<_> = gpu.unwrap_or_else(|| {
+        let settings = format!("analyze width={width} max_steps={max_steps}{}", init.describe());
+        checkpoint.survey(&settings, |rule| find_cycle_from(initial[rule as usize].clone(), max_steps))
+    });
+    const CLASSES: [&str; 4] = ["dies", "short_cycle", "long_cycle", "no_cycle"];
+    let classes: Vec<usize> = analyses
+        .iter()
+        .map(|analysis| {
+            if analysis.died {
+                0
+            } else if analysis.period > 0 && analysis.period <= 10 {
+                1
+            } else if analysis.period > 10 {
+                2
+            } else {
+                3
+            }
+        })
+        .collect();
+    let states = 1usize << in_degree_width;
+    let degree = |i: usize| degrees.as_ref().map(|degrees| &degrees[i]);
+    let csv = || {
+        let extra = if degrees.is_some() { ",gardens,garden_share,max_in_degree,bijective,in_degrees" } else { "" };
+        let lattice = "surjective,injective,orphan,ring_bijective";
+        std::iter::once(format!("rule,canonical,transient,period,died,final_density,class,{lattice}{extra}\n"))
+            .chain(rules.iter().copied().zip(&analyses).zip(&classes).enumerate().map(|(i, ((rule, a), &class))| {
+                let (canonical, class) = (Rule(rule).canonical(), CLASSES[class]);
+                let extra = degree(i).map_or(String::new(), |d| {
+                    let share = d.gardens() as f64 / states as f64;
+                    format!(",{},{share},{},{},{}", d.gardens(), d.max(), d.bijective(), d.histogram())
+                });
+                let (transient, period, died, density) = (a.transient, a.period, a.died, a.final_density);
+                let d = &decided[i];
+                let lattice = format!("{},{},{},{}", d.surjective(), d.injective, orphan(d), d.ring_widths());
+                format!("{rule},{canonical},{transient},{period},{died},{density},{class},{lattice}{extra}\n")
+            }))
+            .collect()
+    };
+    let json = || {
+        let rules = rules.iter().copied().zip(&analyses).zip(&classes).enumerate().map(|(i, ((rule, a), &class))| {
+            let mut fields = vec![
+                ("rule", rule.into()),
+                ("canonical", Rule(rule).canonical().0.into()),
+                ("transient", a.transient.into()),
+                ("period", (a.period > 0).then_some(a.period).into()),
+                ("died", a.died.into()),
+                ("final_density", a.final_density.into()),
+                ("class", CLASSES[class].into()),
+                ("surjective", decided[i].surjective().into()),
+                ("injective", decided[i].injective.into()),
+                ("orphan", decided[i].orphan.as_ref().map(|_| orphan(&decided[i])).into()),
+                ("ring_bijective", Json::array(decided[i].rings.iter().copied())),
+            ];
+            if let Some(d) = degree(i) {
+                fields.extend([
+                    ("gardens", d.gardens().into()),
+                    ("max_in_degree", d.max().into()),
+                    ("bijective", d.bijective().into()),
+                    ("in_degrees", Json::array(d.counts.iter().copied())),
+                ]);
+            }
+            Json::object(fields)
+        });
+        let mut parameters = vec![
+            ("width", width.into()),
+            ("max_steps", max_steps.into()),
+            ("max_decided_ring", MAX_DECIDED_RING.into()),
+        ];
+        if degrees.is_some() {
+            parameters.push(("in_degree_width", in_degree_width.into()));
+        }
+        parameters.push(("initial", init.to_json()));
+        report("analyze", Json::object(parameters), Json::object([("rules", Json::array(rules))]))
+    };
+    let columns = || {
+        let mut columns = vec![
+            Column::required("rule", rules.clone()),
+            Column::required("canonical", canonical(&rules)),
+            Column::required("transient", analyses.iter().map(|a| a.transient).collect::<Vec<_>>()),
+            Column::optional("period", analyses.iter().map(|a| (a.period > 0).then_some(a.period)).collect::<Vec<_>>()),
+            Column::required("died", analyses.iter().map(|a| a.died).collect::<Vec<_>>()),
+            Column::required("final_density", analyses.iter().map(|a| a.final_density).collect::<Vec<_>>()),
+            Column::required("class", classes.iter().map(|&class| CLASSES[class]).collect::<Vec<_>>()),
+            Column::required("surjective", decided.iter().map(Surjectivity::surjective).collect::<Vec<_>>()),
+            Column::required("injective", decided.iter().map(|d| d.injective).collect::<Vec<_>>()),
+            Column::optional(
+                "orphan",
+                decided.iter().map(|d| d.orphan.as_ref().map(|_| orphan(d))).collect::<Vec<_>>(),
+            ),
+            Column::required("ring_bijective", decided.iter().map(Surjectivity::ring_widths).collect::<Vec<_>>()),
+        ];
+        if let Some(degrees) = &degrees {
+            columns.extend([
+                Column::required("gardens", degrees.iter().map(InDegrees::gardens).collect::<Vec<_>>()),
+                Column::required("max_in_degree", degrees.iter().map(InDegrees::max).collect::<Vec<_>>()),
+                Column::required("bijective", degrees.iter().map(InDegrees::bijective).collect::<Vec<_>>()),
+                Column::required("in_degrees", degrees.iter().map(InDegrees::histogram).collect::<Vec<_>>()),
+            ]);
+        }
+        columns
+    };
+    if !output.table() {
+        return output.write(csv, json, columns);
+    }
+
+    println!("Analyzing {} (width={width}, max_steps={max_steps}{})", checkpoint.describe(), init.describe());
+    let (extra, rule_width) = match degrees {
+        Some(_) => (format!(" {:>8} {:>6}", "Gardens", "Max in"), 78),
+        None => (String::new(), 62),
+    };
+    let headings = ["Rule", "Transient", "Period", "Died?", "Density", "Lattice"];
+    let [rule, transient, period, died, density, lattice] = headings;
+    println!("{rule:>4} {transient:>10} {period:>8} {died:>6} {density:>8} {lattice:>11}{extra}");
+    println!("{}", "-".repeat(rule_width));
+
+    let mut class_counts = [0usize; 4]; // die, short cycle, long cycle, no cycle found
+
+    for (i, ((rule, analysis), &class)) in rules.iter().copied().zip(&analyses).zip(&classes).enumerate() {
+        class_counts[class] += 1;
+
+        // Only print interesting rules (not immediately dying, or complex behavior)
+        if !analysis.died || analysis.transient > 1 {
+            let extra = degree(i).map_or(String::new(), |d| format!(" {:>8} {:>6}", d.gardens(), d.max()));
+            let lattice = match &decided[i] {
+                d if d.injective => "injective",
+                d if d.surjective() => "surjective",
+                _ => "-",
+            };
+            println!(
+                "{:>4} {:>10} {:>8} {:>6} {:>8.3} {lattice:>11}{extra}",
+                rule,
+                analysis.transient,
+                if analysis.period > 0 {
+                    analysis.period.to_string()
+                } else {
+                    ">max".to_string()
+                },
+                if analysis.died { "yes" } else { "no" },
+                analysis.final_density
+            );
+        }
+    }
+
+    println!("{}", "-".repeat(rule_width));
+    println!("Summary:");
+    println!("  Dies immediately: {}", class_counts[0]);
+    println!("  Short cycle (<=10): {}", class_counts[1]);
+    println!("  Long cycle (>10): {}", class_counts[2]);
+    println!("  No cycle found: {}", class_counts[3]);
+    let listed = |keep: &dyn Fn(&Surjectivity) -> bool| {
+        rules.iter().zip(&decided).filter(|(_, d)| keep(d)).map(|(rule, _)| rule.to_string()).collect::<Vec<_>>()
+    };
+    let surjective = listed(&|d| d.surjective());
+    println!("  Surjective on the infinite lattice (no orphan): {}: {}", surjective.len(), surjective.join(", "));
+    println!("  Injective (reversible) there: {}", listed(&|d| d.injective).join(", "));
+    let rings = listed(&|d| !d.rings.is_empty() && !d.injective);
+    println!("  Bijective on some rings of 4-{MAX_DECIDED_RING} cells without being injective: {}", rings.join(", "));
+    if let Some(degrees) = &degrees {
+        let bijective: Vec<String> =
+            rules.iter().zip(degrees).filter(|(_, d)| d.bijective()).map(|(rule, _)| rule.to_string()).collect();
+        let none = degrees.iter().filter(|d| d.gardens() == 0).count();
+        println!("  Gardens of Eden on {in_degree_width} cells: none for {none} rules (surjective there)");
+        println!("  Bijective on {in_degree_width} cells (every state one predecessor): {}", bijective.join(", "));
+    }
+    output.write(csv, json, columns);
+}
+
+#[derive(Args, Clone)]
+pub struct CycleArgs {
+    /// Rule number (0-255), a Wolfram code such as k=2,r=2,code=20, or a
+    /// radius-2 or -3 table: table:<hex> or random:<radius>[:<seed>]
+    #[arg(default_value_t = BinaryRule::Elementary(110), value_parser = parse_binary_rule)]
+    pub rule: BinaryRule,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 31, value_parser = parse_width)]
+    pub width: usize,
+    /// Give up looking for a cycle after this many steps (with --noise,
+    /// the steps run for recurrence statistics)
+    #[arg(short, long, default_value_t = 10000)]
+    pub max_steps: usize,
+    /// Print the report as a table or as JSON
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    #[command(flatten)]
+    pub init: InitArgs,
+    #[command(flatten)]
+    pub noise: NoiseArgs,
+    #[command(flatten)]
+    pub memory: MemoryArgs,
+    #[command(flatten)]
+    pub schedule: ScheduleArgs,
+}
+
+pub fn cycle(args: &CycleArgs) {
+    let CycleArgs { rule, width, max_steps, format, ref init, noise, memory, ref schedule } = *args;
+    if noise.is_noisy() {
+        return recurrence(args);
+    }
+    // Analyze single rule for cycle; with memory the state is every row it
+    // reads, and under a schedule the step within it too
+    let start = memory.apply(schedule.apply(init.automaton_for(width, rule)));
+    let analysis = find_cycle_from(start.clone(), max_steps);
+    let attractor = find_attractor(start.clone(), max_steps).map(|(_, states)| CycleFingerprint::new(&states, width));
+    let algebra = predicted(rule, memory.memory == 0 && schedule.schedule().is_none(), &start.cells());
+    // The simulation checked against the algebra, unless it or the
+    // factoring gave up
+    let agrees = algebra.as_ref().and_then(|(_, orbit, _)| {
+        let period = orbit.period.as_ref()?.to_u64();
+        (analysis.period > 0).then(|| orbit.transient == analysis.transient && period == Some(analysis.period as u64))
+    });
+    if format == ReportFormat::Json {
+        let predicted = algebra.as_ref().map(|(linear, orbit, ring)| {
+            let period = |orbit: &Orbit| orbit.period.as_ref().map(|p| Json::Number(p.to_string()));
+            Json::object([
+                ("polynomial", linear.polynomial().into()),
+                ("transient", orbit.transient.into()),
+                ("period", period(orbit).into()),
+                ("period_divides", divisor_of(orbit).into()),
+                ("ring_transient", ring.transient.into()),
+                ("ring_period", period(ring).into()),
+                ("agrees", agrees.into()),
+            ])
+        });
+        let parameters = Json::object([
+            ("rule", schedule.rule_json(rule)),
+            ("width", width.into()),
+            ("max_steps", max_steps.into()),
+            ("initial", init.to_json()),
+            ("memory", memory.to_json()),
+            ("schedule", schedule.to_json()),
+        ]);
+        let results = Json::object([
+            ("transient", analysis.transient.into()),
+            ("period", (analysis.period > 0).then_some(analysis.period).into()),
+            ("died", analysis.died.into()),
+            ("final_density", analysis.final_density.into()),
+            ("attractor", attractor.as_ref().map(|fingerprint| fingerprint.id()).into()),
+            ("attractor_hash", attractor.as_ref().map(|fingerprint| format!("{:016x}", fingerprint.hash)).into()),
+            ("initial_hash", format!("{:016x}", start.state_hash()).into()),
+            ("hash_version", HASH_VERSION.into()),
+            ("predicted", predicted.into()),
+        ]);
+        return print_report("cycle", parameters, results);
+    }
+
+    let settings = format!("{}{}", init.describe(), memory.describe());
+    println!("Analyzing {} (width={width}, max_steps={max_steps}{settings})", schedule.title(rule));
+
+    println!("  Transient length: {}", analysis.transient);
+    if analysis.period > 0 {
+        println!("  Cycle period: {}", analysis.period);
+    } else {
+        println!("  Cycle period: not found within {max_steps} steps");
+    }
+    println!("  Died: {}", if analysis.died { "yes" } else { "no" });
+    println!("  Final density: {:.3}", analysis.final_density);
+    if let Some(fingerprint) = attractor {
+        println!("  Attractor: {} (same for any entry point or rotation)", fingerprint.id());
+    }
+    if let Some((linear, orbit, ring)) = algebra {
+        let period = |orbit: &Orbit| orbit.period.as_ref().map_or_else(|| divisor_of(orbit), ToString::to_string);
+        println!("  Predicted by GF(2) algebra (T = {} mod x^{width} - 1):", linear.polynomial());
+        println!("    Transient length: {}", orbit.transient);
+        match orbit.period {
+            Some(ref period) => println!("    Cycle period: {period}"),
+            None => println!("    Cycle period: a divisor of {} (too big to factor here)", divisor_of(&orbit)),
+        }
+        println!("    Any row on this ring: transient at most {}, period dividing {}", ring.transient, period(&ring));
+        match agrees {
+            Some(true) => println!("    Simulation agrees"),
+            Some(false) => println!("    Simulation DISAGREES"),
+            None if analysis.period == 0 => {
+                println!("    Simulation can't check it: no cycle within {max_steps} steps")
+            }
+            None => println!("    Simulation can't check it without the exact period"),
+        }
+    }
+}
+
+/// A linear rule's cycle from `cells`, and its step's on the whole ring:
+/// None for other rules, affine ones, or runs with memory or a schedule
+fn predicted(rule: BinaryRule, plain: bool, cells: &[bool]) -> Option<(Linear, Orbit, Orbit)> {
+    let table = match rule {
+        BinaryRule::Elementary(r) => TableRule::new(1, r as u128),
+        BinaryRule::Table(t) => t,
+    };
+    let linear = Linear::detect(table).ok().filter(|linear| plain && !linear.affine)?;
+    let orbit = linear.orbit(cells).ok()?;
+    let ring = linear.ring(cells.len()).ok()?;
+    Some((linear, orbit, ring))
+}
+
+/// The multiple of an orbit's period its ring fixes: 2^k (2^s - 1)
+fn divisor_of(orbit: &Orbit) -> String {
+    match orbit.bound {
+        (0, s) => format!("2^{s} - 1"),
+        (k, s) => format!("2^{k} (2^{s} - 1)"),
+    }
+}
+
+/// `cycle` with noise: no cycle to find, so how the run revisits its states
+fn recurrence(args: &CycleArgs) {
+    let CycleArgs { rule, width, max_steps, format, ref init, noise, memory, ref schedule } = *args;
+    let stats = recurrences(noise.apply(memory.apply(schedule.apply(init.automaton_for(width, rule)))), max_steps);
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", schedule.rule_json(rule)),
+            ("width", width.into()),
+            ("max_steps", max_steps.into()),
+            ("initial", init.to_json()),
+            ("noise", noise.to_json()),
+            ("memory", memory.to_json()),
+            ("schedule", schedule.to_json()),
+        ]);
+        let results = Json::object([
+            ("distinct_states", stats.distinct.into()),
+            ("first_return", stats.first.map(|(step, _)| step).into()),
+            ("first_return_gap", stats.first.map(|(_, gap)| gap).into()),
+            ("returns", stats.returns.into()),
+            ("mean_return_gap", stats.mean_return.into()),
+            ("most_visits", stats.most_visits.into()),
+            ("mean_density", stats.mean_density.into()),
+        ]);
+        return print_report("cycle", parameters, results);
+    }
+
+    let settings = format!("{}{}{}", init.describe(), memory.describe(), noise.describe());
+    println!("Recurrence of {} (width={width}, steps={max_steps}{settings})", schedule.title(rule));
+    println!("  Distinct states: {} of {} rows", stats.distinct, max_steps + 1);
+    match stats.first {
+        Some((step, gap)) => println!("  First return: step {step}, to the state of step {}", step - gap),
+        None => println!("  First return: none within {max_steps} steps"),
+    }
+    println!("  Returns: {} ({:.1}% of steps)", stats.returns, 100.0 * stats.returns as f64 / max_steps.max(1) as f64);
+    if let Some(gap) = stats.mean_return {
+        println!("  Mean return gap: {gap:.1} steps");
+    }
+    println!("  Most visits to one state: {}", stats.most_visits);
+    println!("  Mean density: {:.3}", stats.mean_density);
+}
+
+#[derive(Args, Clone)]
+pub struct BasinsArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 110, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Number of cells in the ring (every one of the 2^width states is run)
+    #[arg(short, long, default_value_t = 12, value_parser = parse_width)]
+    pub width: usize,
+    /// Attractors to list, largest basin first
+    #[arg(short, long, default_value_t = 20)]
+    pub top: usize,
+    /// Write one row per attractor to this CSV file
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
+    /// Write one row per state (its attractor and transient) to this CSV file
+    #[arg(long)]
+    pub states_csv: Option<PathBuf>,
+    /// Write the state transition graph (a node per state, an edge per step)
+    /// as Graphviz DOT (.dot, .gv) or GraphML (.graphml); - prints the DOT
+    /// instead of the report. Up to 16 cells
+    #[arg(long, value_name = "FILE")]
+    pub stg: Option<PathBuf>,
+    /// Draw the basin-of-attraction field, DDLab style, as PNG or SVG: each
+    /// cycle with its transient trees fanning out. Up to 16 cells
+    #[arg(long, value_name = "FILE")]
+    pub field: Option<PathBuf>,
+    /// Draw one basin of each set that ring rotations map onto each other
+    #[arg(long, requires = "field")]
+    pub distinct: bool,
+    /// Pixels along the field's longer side
+    #[arg(long, default_value_t = 1200, requires = "field")]
+    pub field_size: usize,
+    /// Print the report as a table or as JSON; the files are written either way
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+pub fn basins(args: &BasinsArgs) {
+    let BasinsArgs { rule, width, top, ref csv, ref states_csv, ref stg, ref field, distinct, field_size, format } =
+        *args;
+    // Every initial state at once: which attractor it reaches, and after how long
+
+    if !(1..=MAX_WIDTH).contains(&width) {
+        eprintln!("Exhaustive enumeration needs a width of 1..={MAX_WIDTH}, not {width}");
+        std::process::exit(1);
+    }
+    for (flag, given) in [("--stg", stg.is_some()), ("--field", field.is_some())] {
+        if given && width > MAX_GRAPH_WIDTH {
+            eprintln!("{flag} draws a node per state: up to {MAX_GRAPH_WIDTH} cells, not {width} (2^{width} states)");
+            std::process::exit(1);
+        }
+    }
+    let svg = match field.as_ref().map(|path| path.extension().and_then(|e| e.to_str())) {
+        None | Some(Some("png")) => false,
+        Some(Some("svg")) => true,
+        Some(_) => {
+            eprintln!("--field draws .png or .svg files");
+            std::process::exit(1);
+        }
+    };
+    if field_size < 16 {
+        eprintln!("--field-size must be at least 16 pixels");
+        std::process::exit(1);
+    }
+    let stdout = stg.as_ref().is_some_and(|path| path.as_os_str() == "-");
+    if stdout && format == ReportFormat::Json {
+        eprintln!("--stg - prints the DOT instead of the report; give --stg a file to have both");
+        std::process::exit(1);
+    }
+    let graphml = match stg.as_ref().map(|path| (path, path.extension().and_then(|e| e.to_str()))) {
+        None => false,
+        Some(_) if stdout => false,
+        Some((_, Some("dot" | "gv"))) => false,
+        Some((_, Some("graphml"))) => true,
+        Some((path, _)) => {
+            eprintln!("--stg writes .dot, .gv or .graphml files, or - for DOT on stdout, not {}", path.display());
+            std::process::exit(1);
+        }
+    };
+    let analysis = enumerate_basins(rule, width);
+    let graph_metadata = || metadata(&[("rule", &rule), ("width", &width)]);
+    if stdout {
+        print!("{}", analysis.to_dot(&graph_metadata()));
+        return;
+    }
+    let states = analysis.states();
+    let bits = |state: u32| -> String { (0..width).map(|i| if state >> i & 1 == 1 { '1' } else { '0' }).collect() };
+
+    let attractors = &analysis.attractors;
+    let mut periods: Vec<usize> = attractors.iter().map(|a| a.period()).collect();
+    periods.sort_unstable();
+    periods.dedup();
+    let gardens: usize = attractors.iter().map(|a| a.gardens).sum();
+
+    // Rho shapes: each state is a tail of length t into a cycle of period p
+    let period_of = |s: usize| attractors[analysis.attractor[s] as usize].period();
+    let max_transient = analysis.transient.iter().copied().max().unwrap_or(0) as usize;
+    let mean_transient = analysis.transient.iter().map(|&t| t as f64).sum::<f64>() / states as f64;
+    let mean_rho = mean_transient + (0..states).map(|s| period_of(s) as f64).sum::<f64>() / states as f64;
+    let max_rho = (0..states).map(|s| analysis.transient[s] as usize + period_of(s)).max().unwrap_or(0);
+    let mut shapes: HashMap<(usize, usize), usize> = HashMap::new();
+    for s in 0..states {
+        *shapes.entry((analysis.transient[s] as usize, period_of(s))).or_default() += 1;
+    }
+    let mut common: Vec<_> = shapes.iter().collect();
+    common.sort_by_key(|&(&shape, &count)| (std::cmp::Reverse(count), shape));
+    let mut histogram = vec![0usize; max_transient + 1];
+    analysis.transient.iter().for_each(|&t| histogram[t as usize] += 1);
+    // Rotations of a cycle are separate attractors here but share an ID
+    let fingerprint = |a| CycleFingerprint::of_attractor(a, width).id();
+
+    let table = format == ReportFormat::Table;
+    if !table {
+        let parameters = Json::object([("rule", rule.into()), ("width", width.into()), ("top", top.into())]);
+        let listed = attractors.iter().enumerate().take(top).map(|(id, a)| {
+            Json::object([
+                ("attractor", id.into()),
+                ("period", a.period().into()),
+                ("basin", a.basin.into()),
+                ("share", (a.basin as f64 / states as f64).into()),
+                ("gardens", a.gardens.into()),
+                ("mean_transient", a.mean_transient().into()),
+                ("max_transient", a.max_transient.into()),
+                ("id", fingerprint(a).into()),
+                ("cycle_state", bits(a.cycle[0]).into()),
+            ])
+        });
+        let shapes = common.iter().take(5).map(|&(&(transient, period), &count)| {
+            Json::object([("transient", transient.into()), ("period", period.into()), ("states", count.into())])
+        });
+        let results = Json::object([
+            ("states", states.into()),
+            ("attractor_count", attractors.len().into()),
+            ("periods", periods.into()),
+            ("gardens", gardens.into()),
+            ("transient", Json::object([("mean", mean_transient.into()), ("max", max_transient.into())])),
+            ("rho", Json::object([("mean", mean_rho.into()), ("max", max_rho.into())])),
+            ("rho_shapes", shapes.len().into()),
+            ("common_rho_shapes", Json::array(shapes)),
+            ("transient_histogram", histogram.clone().into()),
+            ("attractors", Json::array(listed)),
+            ("hash_version", HASH_VERSION.into()),
+        ]);
+        print_report("basins", parameters, results);
+    } else {
+        println!("State graph of rule {rule} on a ring of {width} ({states} states)");
+        println!("  Attractors: {} (periods {periods:?})", attractors.len());
+        println!("  Garden-of-Eden states: {gardens} ({:.1}%)", 100.0 * gardens as f64 / states as f64);
+        println!("  Transient: mean {mean_transient:.2}, max {max_transient}; rho (transient + period): mean {mean_rho:.2}, max {max_rho}");
+        let listed: Vec<String> = common.iter().take(5).map(|((t, p), n)| format!("({t}, {p}) x{n}")).collect();
+        println!("  Rho shapes (transient, period): {} distinct; most common {}", shapes.len(), listed.join(", "));
+        if max_transient < 16 {
+            let listed: Vec<String> = histogram.iter().enumerate().map(|(t, n)| format!("{t}:{n}")).collect();
+            println!("  Transient histogram: {}\n", listed.join(" "));
+        } else {
+            // Quantiles off the cumulative histogram
+            let quantile = |q: f64| {
+                let target = (q * states as f64).ceil() as usize;
+                histogram.iter().scan(0, |seen, n| { *seen += n; Some(*seen) }).position(|seen| seen >= target.max(1)).unwrap()
+            };
+            println!("  Transient quantiles: 25% {}, 50% {}, 90% {}, 99% {}\n", quantile(0.25), quantile(0.5), quantile(0.9), quantile(0.99));
+        }
+
+        println!("{:>4} {:>7} {:>8} {:>7} {:>8} {:>8} {:>7}  {:<20} Cycle state", "#", "Period", "Basin", "Share", "Gardens",
+            "Mean tr", "Max tr", "ID");
+        for (id, a) in attractors.iter().enumerate().take(top) {
+            println!("{id:>4} {:>7} {:>8} {:>6.2}% {:>8} {:>8.2} {:>7}  {:<20} {}", a.period(), a.basin,
+                100.0 * a.basin as f64 / states as f64, a.gardens, a.mean_transient(), a.max_transient, fingerprint(a),
+                bits(a.cycle[0]));
+        }
+        if attractors.len() > top {
+            println!("{:>4} ... {} more", "", attractors.len() - top);
+        }
+    }
+
+    if let Some(path) = csv {
+        let table: String = std::iter::once("attractor,period,basin,share,gardens,mean_transient,max_transient,id,cycle_state\n".to_string())
+            .chain(attractors.iter().enumerate().map(|(id, a)| {
+                format!("{id},{},{},{},{},{},{},{},{}\n", a.period(), a.basin, a.basin as f64 / states as f64, a.gardens,
+                    a.mean_transient(), a.max_transient, fingerprint(a), bits(a.cycle[0]))
+            }))
+            .collect();
+        write_csv(path, &table, &metadata(&[("rule", &rule), ("width", &width), ("hash_version", &HASH_VERSION)]));
+    }
+    if let Some(path) = states_csv {
+        let table: String = std::iter::once("state,attractor,transient,period\n".to_string())
+            .chain((0..states).map(|s| {
+                format!("{},{},{},{}\n", bits(s as u32), analysis.attractor[s], analysis.transient[s], period_of(s))
+            }))
+            .collect();
+        write_csv(path, &table, &metadata(&[("rule", &rule), ("width", &width)]));
+    }
+    if let Some(path) = stg {
+        let graph = if graphml { analysis.to_graphml(&graph_metadata()) } else { analysis.to_dot(&graph_metadata()) };
+        if let Err(e) = std::fs::write(path, graph) {
+            eprintln!("Can't write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+        if table {
+            println!("  -> {}", path.display());
+        }
+    }
+    if let Some(path) = field {
+        let drawn = Field::new(&analysis, distinct);
+        let options = FieldOptions { size: field_size, ..FieldOptions::default() };
+        let metadata = metadata(&[("rule", &rule), ("width", &width), ("distinct", &distinct)]);
+        let written = std::fs::File::create(path).and_then(|file| {
+            let mut out = Tagged::new(BufWriter::new(file), &metadata);
+            if svg { drawn.write_svg(&mut out, &options) } else { drawn.write_png(&mut out, &options) }?;
+            out.flush()
+        });
+        if let Err(e) = written {
+            eprintln!("Can't write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+        if table {
+            println!("  -> {} ({} basins drawn)", path.display(), drawn.basins.len());
+        }
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct SampleBasinsArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 110, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 64, value_parser = parse_width)]
+    pub width: usize,
+    /// Random initial rows to run
+    #[arg(short = 'n', long, default_value_t = 1000)]
+    pub samples: usize,
+    /// Steps per row before giving up on finding its cycle
+    #[arg(short, long, default_value_t = 10000)]
+    pub max_steps: usize,
+    /// Seed of the first row; row i uses seed + i
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+    /// Attractors to list, most hits first
+    #[arg(short, long, default_value_t = 20)]
+    pub top: usize,
+    /// Write one row per attractor hit to this CSV file
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
+    /// Spread the rows evenly over this many bands of density, with exact
+    /// popcounts, instead of coin flips (width + 1 for one per popcount)
+    #[arg(long)]
+    pub strata: Option<usize>,
+    /// Run every row with each popcount in a range (k, lo-hi, or all with
+    /// no value) instead of sampling: exact weights per popcount
+    #[arg(long, num_args = 0..=1, default_missing_value = "all", value_parser = parse_popcounts,
+        conflicts_with = "strata")]
+    pub exact: Option<(usize, usize)>,
+    /// Write one row per stratum (or popcount) and attractor hit in it to this CSV file
+    #[arg(long)]
+    pub strata_csv: Option<PathBuf>,
+    /// Print the report as a table or as JSON; the CSV files are written either way
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+/// Most rows `--exact` runs
+const MAX_EXACT_ROWS: u64 = 1 << 24;
+
+/// `k`, `lo-hi` or `all` live cells
+fn parse_popcounts(s: &str) -> Result<(usize, usize), String> {
+    let bad = || format!("expected a popcount, lo-hi or all, not {s:?}");
+    match s.split_once('-') {
+        _ if s == "all" => Ok((0, usize::MAX)),
+        Some((lo, hi)) => Ok((lo.parse().map_err(|_| bad())?, hi.parse().map_err(|_| bad())?)),
+        None => s.parse().map(|k| (k, k)).map_err(|_| bad()),
+    }
+}
+
+pub fn sample_basins(args: &SampleBasinsArgs) {
+    let SampleBasinsArgs {
+        rule,
+        width,
+        samples,
+        max_steps,
+        seed,
+        top,
+        ref csv,
+        strata,
+        exact,
+        ref strata_csv,
+        format,
+    } = *args;
+    // Random rows instead of every state: which attractors they reach, and how many were missed
+
+    if samples == 0 {
+        eprintln!("--samples must be at least 1");
+        std::process::exit(1);
+    }
+    if strata_csv.is_some() && strata.is_none() && exact.is_none() {
+        eprintln!("--strata-csv needs --strata or --exact");
+        std::process::exit(1);
+    }
+    let exact = exact.map(|(lo, hi)| (lo, hi.min(width)));
+    if let Some((lo, hi)) = exact {
+        let rows = (lo..=hi).fold(0u64, |total, k| total.saturating_add(binomial(width, k)));
+        if width > 64 || lo > hi || rows > MAX_EXACT_ROWS {
+            eprintln!("--exact runs at most {MAX_EXACT_ROWS} rows of at most 64 cells; {lo}-{hi} live of {width} is {}",
+                if lo > hi { "empty".to_string() } else { format!("{rows} rows") });
+            std::process::exit(1);
+        }
+    }
+    if let Some(strata) = strata
+        && !(1..=(width + 1).min(samples)).contains(&strata)
+    {
+        eprintln!("--strata must be 1 to width + 1 ({}) and at most --samples", width + 1);
+        std::process::exit(1);
+    }
+    let stratified = match (strata, exact) {
+        (_, Some((lo, hi))) => Some(enumerate_by_popcount(rule, width, lo..=hi, max_steps)),
+        (Some(n), _) => {
+            Some(sample_attractors_stratified(rule, width, &density_strata(width, n, samples), max_steps, seed))
+        }
+        (None, None) => None,
+    };
+    let unstratified;
+    let sample = match &stratified {
+        Some(stratified) => &stratified.pooled,
+        None => {
+            unstratified = sample_attractors(rule, width, samples, max_steps, seed);
+            &unstratified
+        }
+    };
+    let resolved = sample.resolved();
+    let attractors = &sample.attractors;
+    // Rows wider than 64 cells are shown by their first 64
+    let bits = |canonical: &[u64]| -> String {
+        let shown = width.min(64);
+        let row: String = (0..shown).map(|i| if canonical[0] >> i & 1 == 1 { '1' } else { '0' }).collect();
+        if width > shown { format!("{row}...") } else { row }
+    };
+
+    // Each attractor's share of the resolved rows, and its standard error when sampled
+    let weight = |a: &SampledAttractor| {
+        let weight = a.samples() as f64 / resolved as f64;
+        (weight, if exact.is_some() { 0.0 } else { (weight * (1.0 - weight) / resolved as f64).sqrt() })
+    };
+    let mut periods: Vec<usize> = attractors.iter().map(|a| a.period()).collect();
+    periods.sort_unstable();
+    periods.dedup();
+
+    if format == ReportFormat::Json {
+        let popcounts = |(lo, hi): (usize, usize)| {
+            Json::object([("min_popcount", lo.into()), ("max_popcount", hi.into())])
+        };
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("samples", exact.is_none().then_some(samples).into()),
+            ("max_steps", max_steps.into()),
+            ("seed", seed.into()),
+            ("top", top.into()),
+            ("strata", strata.into()),
+            ("exact", exact.map(popcounts).into()),
+        ]);
+        let listed = attractors.iter().enumerate().take(top).map(|(id, a)| {
+            let (weight, error) = weight(a);
+            Json::object([
+                ("attractor", id.into()),
+                ("id", a.fingerprint.id().into()),
+                ("hash", format!("{:016x}", a.fingerprint.hash).into()),
+                ("period", a.period().into()),
+                ("hits", a.samples().into()),
+                ("weight", weight.into()),
+                ("weight_error", error.into()),
+                ("mean_transient", a.mean_transient().into()),
+                ("max_transient", a.max_transient.into()),
+                ("cycle_state", bits(&a.fingerprint.canonical[0]).into()),
+            ])
+        });
+        let strata = stratified.as_ref().map(|stratified| {
+            Json::array(stratified.strata.iter().zip(&stratified.by_stratum).map(|(stratum, sample)| {
+                let hits = sample.attractors.iter().map(|a| {
+                    Json::object([("id", a.fingerprint.id().into()), ("hits", a.samples().into())])
+                });
+                Json::object([
+                    ("min_popcount", stratum.popcounts.0.into()),
+                    ("max_popcount", stratum.popcounts.1.into()),
+                    ("samples", sample.samples.into()),
+                    ("unresolved", sample.unresolved.into()),
+                    ("attractors", Json::array(hits)),
+                ])
+            }))
+        });
+        // Nothing is missed when every row was run
+        let estimated = |estimate: f64| (exact.is_none() && resolved > 0).then_some(estimate);
+        let results = Json::object([
+            ("samples", sample.samples.into()),
+            ("resolved", resolved.into()),
+            ("unresolved", sample.unresolved.into()),
+            ("attractor_count", attractors.len().into()),
+            ("periods", periods.into()),
+            ("hit_once", sample.hit_exactly(1).into()),
+            ("hit_twice", sample.hit_exactly(2).into()),
+            ("chao1", estimated(sample.chao1()).into()),
+            ("chapman", estimated(sample.chapman()).into()),
+            ("coverage", estimated(sample.coverage()).into()),
+            ("attractors", Json::array(listed)),
+            ("strata", strata.into()),
+            ("hash_version", HASH_VERSION.into()),
+        ]);
+        print_report("sample-basins", parameters, results);
+    } else {
+        let from = match (strata, exact) {
+            (_, Some((lo, hi))) => {
+                let live = if lo == hi { lo.to_string() } else { format!("{lo}-{hi}") };
+                format!("every row with {live} live cells ({} rows, exact)", sample.samples)
+            }
+            (Some(n), _) => format!("{samples} rows in {n} density strata (exact popcounts, seed {seed})"),
+            (None, None) => format!("{samples} random rows (seed {seed})"),
+        };
+        println!("Attractors of rule {rule} on a ring of {width}, from {from}");
+        println!("  Cycled within {max_steps} steps: {resolved} ({} unresolved)", sample.unresolved);
+        if resolved == 0 {
+            println!("  No row settled; try a larger --max-steps or a smaller --width");
+            return;
+        }
+        println!("  Attractors hit: {} (periods {periods:?}); once: {}, twice: {}", attractors.len(), sample.hit_exactly(1),
+            sample.hit_exactly(2));
+        // Nothing is missed when every row was run
+        if exact.is_none() {
+            println!("  Estimated attractors: Chao1 {:.1}, Chapman {:.1} (even vs odd samples)", sample.chao1(),
+                sample.chapman());
+            println!("  Coverage: {:.1}% of basin weight on attractors already hit (Good-Turing)",
+                100.0 * sample.coverage());
+        }
+        if width <= MAX_WIDTH && exact != Some((0, width)) {
+            println!("  (width {width} can be enumerated exactly: automata basins {rule} -w {width})");
+        }
+        println!();
+
+        println!("{:>4} {:<20} {:>7} {:>6} {:>16} {:>8} {:>7}  Cycle state", "#", "ID", "Period", "Hits", "Weight",
+            "Mean tr", "Max tr");
+        for (id, a) in attractors.iter().enumerate().take(top) {
+            let (weight, error) = weight(a);
+            println!("{id:>4} {:<20} {:>7} {:>6} {:>7.2}% ± {:>5.2}% {:>8.2} {:>7}  {}", a.fingerprint.id(), a.period(),
+                a.samples(), 100.0 * weight, 100.0 * error, a.mean_transient(), a.max_transient,
+                bits(&a.fingerprint.canonical[0]));
+        }
+        if attractors.len() > top {
+            println!("{:>4} ... {} more", "", attractors.len() - top);
+        }
+        if let Some(stratified) = &stratified {
+            print_strata(stratified, top.min(4));
+        }
+    }
+
+    let metadata = || {
+        let sampled = if exact.is_some() { "exact".to_string() } else { format!("{samples}") };
+        let params: [(_, &dyn std::fmt::Display); 6] = [
+            ("rule", &rule),
+            ("width", &width),
+            ("samples", &sampled),
+            ("seed", &seed),
+            ("max_steps", &max_steps),
+            ("hash_version", &HASH_VERSION),
+        ];
+        metadata(&params)
+    };
+    if let Some(path) = csv {
+        let table: String = std::iter::once("attractor,id,hash,period,hits,weight,mean_transient,max_transient\n".to_string())
+            .chain(attractors.iter().enumerate().map(|(id, a)| {
+                format!("{id},{},{:016x},{},{},{},{},{}\n", a.fingerprint.id(), a.fingerprint.hash, a.period(), a.samples(),
+                    a.samples() as f64 / resolved as f64, a.mean_transient(), a.max_transient)
+            }))
+            .collect();
+        write_csv(path, &table, &metadata());
+    }
+    if let (Some(path), Some(stratified)) = (strata_csv, &stratified) {
+        let mut table = "stratum,min_popcount,max_popcount,samples,unresolved,id,hash,period,hits,weight\n".to_string();
+        for (s, (stratum, sample)) in stratified.strata.iter().zip(&stratified.by_stratum).enumerate() {
+            let (lo, hi) = stratum.popcounts;
+            for a in &sample.attractors {
+                table += &format!("{s},{lo},{hi},{},{},{},{:016x},{},{},{}\n", sample.samples, sample.unresolved,
+                    a.fingerprint.id(), a.fingerprint.hash, a.period(), a.samples(),
+                    a.samples() as f64 / sample.resolved() as f64);
+            }
+        }
+        write_csv(path, &table, &metadata());
+    }
+}
+
+/// Per-stratum weights of the `columns` most-hit attractors overall: how
+/// the chance of ending on each depends on the starting density
+fn print_strata(stratified: &StratifiedSample, columns: usize) {
+    let width = stratified.pooled.width as f64;
+    let shown = &stratified.pooled.attractors[..columns.min(stratified.pooled.attractors.len())];
+    println!();
+    println!("Weights by initial density (share of each stratum's resolved rows; columns are attractors # above):");
+    print!("{:>9} {:>13} {:>6} {:>6} {:>6}", "Popcount", "Density", "Rows", "Unres", "Hit");
+    (0..shown.len()).for_each(|id| print!(" {:>7}", format!("#{id}")));
+    println!(" {:>7}", "Other");
+    for (stratum, sample) in stratified.strata.iter().zip(&stratified.by_stratum) {
+        let (lo, hi) = stratum.popcounts;
+        let resolved = sample.resolved();
+        let density = format!("{:.3}-{:.3}", lo as f64 / width, hi as f64 / width);
+        print!("{:>9} {density:>13} {:>6} {:>6} {:>6}", format!("{lo}-{hi}"), sample.samples, sample.unresolved,
+            sample.attractors.len());
+        let hits = |hash: u64| sample.attractors.iter().find(|a| a.fingerprint.hash == hash).map_or(0, |a| a.samples());
+        let weight = |hits: usize| match resolved {
+            0 => "-".to_string(),
+            n => format!("{:.1}%", 100.0 * hits as f64 / n as f64),
+        };
+        let mut other = resolved;
+        for a in shown {
+            let h = hits(a.fingerprint.hash);
+            other -= h;
+            print!(" {:>7}", weight(h));
+        }
+        println!(" {:>7}", weight(other));
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct EntropyArgs {
+    /// Rule number (0-255), a Wolfram code such as k=2,r=2,code=20, or a
+    /// radius-2 or -3 table: table:<hex> or random:<radius>[:<seed>]
+    #[arg(default_value_t = BinaryRule::Elementary(110), value_parser = parse_binary_rule)]
+    pub rule: BinaryRule,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 79, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 100)]
+    pub generations: usize,
+    /// Block length k for the k-block entropy
+    #[arg(short, long, default_value_t = 3)]
+    pub block_size: usize,
+    /// Print the report as a table or as JSON (every generation)
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    #[command(flatten)]
+    pub init: InitArgs,
+    #[command(flatten)]
+    pub noise: NoiseArgs,
+    #[command(flatten)]
+    pub schedule: ScheduleArgs,
+}
+
+pub fn entropy(args: &EntropyArgs) {
+    let EntropyArgs { rule, width, generations, block_size, format, ref init, noise, ref schedule } = *args;
+    require_block("--block-size", block_size, width);
+    diagnostics::check_sampling(&format!("{block_size}-block entropy"), width, 2f64.powi(block_size as i32));
+    // Track entropy over time for a rule
+
+    let mut ca = noise.apply(schedule.apply(init.automaton_for(width, rule)));
+    let mut entropies = Vec::with_capacity(generations + 1);
+    let mut densities = Vec::with_capacity(generations + 1);
+    let progress = Progress::generations(&schedule.title(rule), generations);
+    for g in 0..=generations {
+        if g > 0 {
+            ca.step();
+            progress.tick();
+        }
+        entropies.push(ca.block_entropy(block_size));
+        densities.push(ca.density());
+    }
+    drop(progress);
+
+    // Summary statistics
+    let mean: f64 = entropies.iter().sum::<f64>() / entropies.len() as f64;
+    let variance: f64 = entropies.iter().map(|h| (h - mean).powi(2)).sum::<f64>()
+        / entropies.len() as f64;
+    let min = entropies.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = entropies.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", schedule.rule_json(rule)),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("block_size", block_size.into()),
+            ("initial", init.to_json()),
+            ("noise", noise.to_json()),
+            ("schedule", schedule.to_json()),
+        ]);
+        let results = Json::object([
+            ("max_entropy", (block_size as f64).into()),
+            ("entropy", entropies.into()),
+            ("density", densities.into()),
+            ("mean", mean.into()),
+            ("std_dev", variance.sqrt().into()),
+            ("min", min.into()),
+            ("max", max.into()),
+        ]);
+        return print_report("entropy", parameters, results);
+    }
+
+    let settings = format!("{}{}", init.describe(), noise.describe());
+    println!("Entropy analysis: {} (width={width}, blocks={block_size}{settings})", schedule.title(rule));
+    println!("Max possible entropy: {:.3} bits", block_size as f64);
+    println!("{:>5} {:>8} {:>8}", "Gen", "Entropy", "Density");
+    println!("{}", "-".repeat(25));
+    for g in 0..=generations {
+        // Print every 10th generation, plus first few and last
+        if g <= 5 || g % 10 == 0 || g == generations {
+            println!("{:>5} {:>8.4} {:>8.3}", g, entropies[g], densities[g]);
+        }
+    }
+    println!("{}", "-".repeat(25));
+
+    println!("Mean entropy:  {:.4}", mean);
+    println!("Std dev:       {:.4}", variance.sqrt());
+    println!("Range:         [{:.4}, {:.4}]", min, max);
+    println!("Normalized:    {:.1}% of max", 100.0 * mean / block_size as f64);
+}
+
+#[derive(Args, Clone)]
+pub struct ReturnMapArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 110, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 101, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to record, after the skipped ones
+    #[arg(short = 'g', long = "gens", default_value_t = 1000)]
+    pub generations: usize,
+    /// Generations to drop first as transient
+    #[arg(long, default_value_t = 200)]
+    pub skip: usize,
+    /// Scalar recorded each generation
+    #[arg(short, long, value_enum, default_value_t = Observable::Density)]
+    pub observable: Observable,
+    /// Block length k for the k-block entropy
+    #[arg(short, long, default_value_t = 3)]
+    pub block_size: usize,
+    /// Longest period of the observable to look for
+    #[arg(short, long, default_value_t = 100)]
+    pub max_period: usize,
+    /// Values closer than this count as equal
+    #[arg(short, long, default_value_t = 1e-9)]
+    pub tolerance: f64,
+    /// Plot x(t+1) against x(t) to this PNG
+    #[arg(long)]
+    pub plot: Option<PathBuf>,
+    /// Write t,x(t),x(t+1) rows to this CSV file
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
+    /// Print the report as a table or as JSON; the files are written either way
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+/// Per-generation scalars for `return-map`
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Observable {
+    /// Fraction of live cells
+    Density,
+    /// k-block entropy per cell
+    Entropy,
+}
+
+pub fn return_map(args: &ReturnMapArgs) {
+    let ReturnMapArgs {
+        rule,
+        width,
+        generations,
+        skip,
+        observable,
+        block_size,
+        max_period,
+        tolerance,
+        ref plot,
+        ref csv,
+        format,
+        ref init,
+    } = *args;
+    if observable == Observable::Entropy {
+        require_block("--block-size", block_size, width);
+    }
+    // The observable's own dynamics: fixed points and periodic orbits of the
+    // map x(t) -> x(t+1) show up as isolated dots, chaos as a scattered cloud
+
+    let measure = |ca: &Automaton| match observable {
+        Observable::Density => ca.density(),
+        Observable::Entropy => ca.block_entropy(block_size) / block_size as f64,
+    };
+    let mut ca = init.automaton(width, rule);
+    let progress = Progress::generations(&format!("Rule {rule}"), skip + generations);
+    (0..skip).for_each(|_| {
+        ca.step();
+        progress.tick();
+    });
+    let mut series = vec![measure(&ca)];
+    for _ in 0..generations {
+        ca.step();
+        progress.tick();
+        series.push(measure(&ca));
+    }
+    drop(progress);
+    let pairs: Vec<[f64; 2]> = series.windows(2).map(|w| [w[0], w[1]]).collect();
+
+    let name = match observable {
+        Observable::Density => "density".to_string(),
+        Observable::Entropy => format!("{block_size}-block entropy per cell"),
+    };
+    let (mean, std_dev) = mean_std(&series);
+    // Lag-1 autocorrelation: near 1 for slow drift, near 0 for uncorrelated jumps
+    let covariance: f64 = pairs.iter().map(|[x, y]| (x - mean) * (y - mean)).sum::<f64>() / pairs.len().max(1) as f64;
+    let autocorrelation = (std_dev > tolerance).then(|| covariance / (std_dev * std_dev));
+    let mut distinct: Vec<i64> = series.iter().map(|x| (x / tolerance.max(f64::EPSILON)).round() as i64).collect();
+    distinct.sort_unstable();
+    distinct.dedup();
+    let period = series_period(&series, max_period, tolerance);
+
+    let table = format == ReportFormat::Table;
+    if !table {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("skip", skip.into()),
+            ("observable", value_name(observable)),
+            ("block_size", block_size.into()),
+            ("max_period", max_period.into()),
+            ("tolerance", tolerance.into()),
+            ("initial", init.to_json()),
+        ]);
+        let results = Json::object([
+            ("values", series.len().into()),
+            ("mean", mean.into()),
+            ("std", std_dev.into()),
+            ("autocorrelation", autocorrelation.into()),
+            ("distinct_values", distinct.len().into()),
+            ("period", period.into()),
+            ("orbit", period.map(|period| series[..period].to_vec()).into()),
+        ]);
+        print_report("return-map", parameters, results);
+    } else {
+        println!("Return map of {name}: Rule {rule} (width={width}, gens={skip}..{}{})",
+            skip + generations, init.describe());
+        println!("  Mean {mean:.4}, std {std_dev:.4}");
+        if let Some(autocorrelation) = autocorrelation {
+            println!("  Lag-1 autocorrelation: {autocorrelation:.3}");
+        }
+        println!("  Distinct values: {} of {}", distinct.len(), series.len());
+        match period {
+            Some(1) => println!("  Fixed point: x* = {:.4}", series[0]),
+            Some(period) => {
+                let orbit: Vec<String> = series[..period].iter().map(|x| format!("{x:.4}")).collect();
+                println!("  Periodic orbit, period {period}: {}", orbit.join(" -> "));
+            }
+            None => println!("  No periodic orbit up to period {max_period}"),
+        }
+    }
+
+    let metadata = init.tag(metadata(&[
+        ("rule", &rule),
+        ("width", &width),
+        ("skip", &skip),
+        ("generations", &generations),
+        ("observable", &name),
+    ]));
+    if let Some(path) = plot {
+        let options = PlotOptions { path: false, ..PlotOptions::default() };
+        let written = std::fs::File::create(path)
+            .and_then(|file| write_plot(&mut Tagged::new(BufWriter::new(file), &metadata), &pairs, &options));
+        if let Err(e) = written {
+            eprintln!("Can't write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+        if table {
+            println!("  Plot -> {}", path.display());
+        }
+    }
+    if let Some(path) = csv {
+        let rows: String = std::iter::once("t,x,next\n".to_string())
+            .chain(pairs.iter().enumerate().map(|(t, [x, next])| format!("{},{x},{next}\n", skip + t)))
+            .collect();
+        if let Err(e) = std::fs::write(path, metadata.csv_header() + &rows) {
+            eprintln!("Can't write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+        if table {
+            println!("  Pairs -> {}", path.display());
+        }
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct InterpolateArgs {
+    /// Rule followed with probability p
+    #[arg(default_value_t = 204, value_parser = parse_rule)]
+    pub rule_a: u8,
+    /// Rule followed otherwise
+    #[arg(default_value_t = 30, value_parser = parse_rule)]
+    pub rule_b: u8,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 200, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations measured at each p, after the skipped ones
+    #[arg(short = 'g', long = "gens", default_value_t = 200)]
+    pub generations: usize,
+    /// Generations to drop first as transient
+    #[arg(long, default_value_t = 100)]
+    pub skip: usize,
+    /// Values of p, evenly spaced from 0 to 1
+    #[arg(short = 'n', long, default_value_t = 21)]
+    pub points: usize,
+    /// Block length k for the k-block entropy
+    #[arg(short, long, default_value_t = 3)]
+    pub block_size: usize,
+    /// Runs per p, each with its own coin flips
+    #[arg(short, long, default_value_t = 4)]
+    pub replicates: usize,
+    /// Seed for the per-cell coin flips
+    #[arg(long = "mix-seed", default_value_t = 1)]
+    pub mix_seed: u64,
+    /// Plot density, entropy and activity against p to this PNG
+    #[arg(long)]
+    pub plot: Option<PathBuf>,
+    /// Write one row per p to this CSV file
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
+    /// Print the report as a table or as JSON; the files are written either way
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+pub fn interpolate(args: &InterpolateArgs) {
+    let InterpolateArgs {
+        rule_a,
+        rule_b,
+        width,
+        generations,
+        skip,
+        points,
+        block_size,
+        replicates,
+        mix_seed,
+        ref plot,
+        ref csv,
+        format,
+        ref init,
+    } = *args;
+    // Each cell follows rule A with probability p, else rule B: order parameters against p
+
+    if points < 2 || generations == 0 || replicates == 0 {
+        eprintln!("Need --points >= 2 and a nonzero --gens and --replicates");
+        std::process::exit(1);
+    }
+    require_block("--block-size", block_size, width);
+    let ps: Vec<f64> = (0..points).map(|i| i as f64 / (points - 1) as f64).collect();
+    let settings = SweepSettings { skip, generations, block_size, replicates, seed: mix_seed };
+    let sweep = sweep_mixture(&init.automaton(width, rule_a), rule_b, &ps, &settings);
+
+    // Where the behavior changes fastest, as a rough transition point
+    let steepest = sweep.windows(2).max_by(|x, y| {
+        let jump = |w: &[MixturePoint]| (w[1].activity.0 - w[0].activity.0).abs();
+        jump(x).total_cmp(&jump(y))
+    });
+
+    let table = format == ReportFormat::Table;
+    if !table {
+        let parameters = Json::object([
+            ("rule_a", rule_a.into()),
+            ("rule_b", rule_b.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("skip", skip.into()),
+            ("points", points.into()),
+            ("block_size", block_size.into()),
+            ("replicates", replicates.into()),
+            ("mix_seed", mix_seed.into()),
+            ("initial", init.to_json()),
+        ]);
+        let spread = |(mean, std_dev): (f64, f64)| Json::object([("mean", mean.into()), ("std", std_dev.into())]);
+        let sweep_json = sweep.iter().map(|point| {
+            Json::object([
+                ("p", point.p.into()),
+                ("density", spread(point.density)),
+                ("entropy", spread(point.entropy)),
+                ("activity", spread(point.activity)),
+            ])
+        });
+        let steepest = steepest.map(|w| Json::object([("from_p", w[0].p.into()), ("to_p", w[1].p.into())]));
+        let results = Json::object([("sweep", Json::array(sweep_json)), ("steepest_activity_change", steepest.into())]);
+        print_report("interpolate", parameters, results);
+    } else {
+        println!("Rule {rule_a} with probability p, else rule {rule_b} (width={width}, skip={skip}, gens={generations}, \
+            {replicates} runs per p{})", init.describe());
+        println!("{:>5}  {:>15}  {:>15}  {:>15}", "p", "Density", format!("Entropy/{block_size}"), "Activity");
+        for point in &sweep {
+            let show = |(mean, std_dev): (f64, f64)| format!("{mean:.3} ± {std_dev:.3}");
+            println!("{:>5.2}  {:>15}  {:>15}  {:>15}", point.p, show(point.density), show(point.entropy),
+                show(point.activity));
+        }
+        if let Some([a, b]) = steepest {
+            println!("  Steepest change in activity: p {:.2} -> {:.2} ({:.3} -> {:.3})", a.p, b.p, a.activity.0,
+                b.activity.0);
+        }
+    }
+
+    let metadata = init.tag(metadata(&[("rule_a", &rule_a), ("rule_b", &rule_b), ("width", &width), ("skip", &skip),
+        ("generations", &generations), ("replicates", &replicates), ("mix_seed", &mix_seed)]));
+    if let Some(path) = plot {
+        let curve = |value: fn(&MixturePoint) -> f64| -> Vec<[f64; 2]> {
+            sweep.iter().map(|point| [point.p, value(point)]).collect()
+        };
+        let (density, entropy, activity) = (curve(|x| x.density.0), curve(|x| x.entropy.0), curve(|x| x.activity.0));
+        let curves = [(&density[..], [40, 90, 220]), (&entropy[..], [30, 150, 60]), (&activity[..], [200, 0, 0])];
+        let written = std::fs::File::create(path)
+            .and_then(|file| {
+                write_curves(&mut Tagged::new(BufWriter::new(file), &metadata), &curves, &PlotOptions::default())
+            });
+        if let Err(e) = written {
+            eprintln!("Can't write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+        if table {
+            println!("  Plot (p across; density blue, entropy green, activity red) -> {}", path.display());
+        }
+    }
+    if let Some(path) = csv {
+        let table: String = std::iter::once("p,density,density_std,entropy,entropy_std,activity,activity_std\n".to_string())
+            .chain(sweep.iter().map(|x| {
+                format!("{},{},{},{},{},{},{}\n", x.p, x.density.0, x.density.1, x.entropy.0, x.entropy.1, x.activity.0,
+                    x.activity.1)
+            }))
+            .collect();
+        write_csv(path, &table, &metadata);
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct EtherArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 110, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Longest spatial and temporal period tried
+    #[arg(long, default_value_t = 14)]
+    pub max_period: usize,
+    /// Cells per matched window, at least the longest period
+    #[arg(long, default_value_t = 16)]
+    pub window: usize,
+    /// Random rows simulated
+    #[arg(short, long, default_value_t = 4)]
+    pub samples: usize,
+    /// Number of cells in each row
+    #[arg(short, long, default_value_t = 256, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to drop first as transient
+    #[arg(long, default_value_t = 200)]
+    pub skip: usize,
+    /// Generations measured after the transient
+    #[arg(short = 'g', long = "gens", default_value_t = 32)]
+    pub generations: usize,
+    /// Seed of the first random row
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+    /// Least fraction of cells the background must cover
+    #[arg(long, default_value_t = 0.5)]
+    pub min_coverage: f64,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+pub fn ether(args: &EtherArgs) {
+    let EtherArgs { rule, max_period, window, samples, width, skip, generations, seed, min_coverage, format } = *args;
+    // The background random rows settle into, and the particles moving through it
+
+    let periods_fit = (1..=MAX_WIDTH).contains(&max_period) && (max_period..=63).contains(&window);
+    if !periods_fit || width < window || samples == 0 {
+        eprintln!("Need 1 <= --max-period <= {MAX_WIDTH}, --max-period <= --window <= 63, --width >= --window \
+            and --samples >= 1");
+        std::process::exit(1);
+    }
+    let settings = EtherSettings { max_period, window, samples, width, skip, generations, seed, min_coverage };
+    let best = best_ether_candidate(rule, &settings);
+    let found = best.as_ref().filter(|ether| ether.coverage >= min_coverage);
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("max_period", max_period.into()),
+            ("window", window.into()),
+            ("samples", samples.into()),
+            ("width", width.into()),
+            ("skip", skip.into()),
+            ("generations", generations.into()),
+            ("seed", seed.into()),
+            ("min_coverage", min_coverage.into()),
+        ]);
+        let tile = |rows: &[Vec<bool>]| -> Json {
+            Json::array(rows.iter().map(|row| row.iter().map(|&c| if c { '1' } else { '0' }).collect::<String>()))
+        };
+        let results = Json::object([
+            ("found", found.is_some().into()),
+            ("spatial_period", found.map(|e| e.spatial_period()).into()),
+            ("temporal_period", found.map(|e| e.temporal_period()).into()),
+            ("homogeneous", found.and_then(|e| e.homogeneous()).into()),
+            ("coverage", found.map(|e| e.coverage).into()),
+            ("particle_density", found.map(|e| 1.0 - e.coverage).into()),
+            ("tile", found.map_or(Json::Null, |e| tile(&e.rows))),
+            ("best_coverage", best.as_ref().map(|e| e.coverage).into()),
+        ]);
+        print_report("ether", parameters, results);
+        return;
+    }
+
+    println!("Rule {rule}: ether search (periods <= {max_period}, {window}-cell windows, {samples} rows of {width}, \
+        skip={skip}, gens={generations})");
+    let Some(ether) = found else {
+        match best {
+            Some(best) => println!("  No ether: the best background ({} x {}) covers {:.1}% of cells, under {:.1}%",
+                best.spatial_period(), best.temporal_period(), 100.0 * best.coverage, 100.0 * min_coverage),
+            None => println!("  No ether: no cycles within the period bound"),
+        }
+        return;
+    };
+    match ether.homogeneous() {
+        Some(live) => println!("  Vacuum: all {} (quiescent)", if live { "live" } else { "dead" }),
+        None => println!("  Ether: {} cells x {} steps", ether.spatial_period(), ether.temporal_period()),
+    }
+    println!("  Coverage: {:.1}% of cells; particle density {:.4}", 100.0 * ether.coverage, 1.0 - ether.coverage);
+    if ether.homogeneous().is_none() {
+        for row in &ether.rows {
+            println!("    {}", row.iter().map(|&c| if c { '█' } else { '·' }).collect::<String>());
+        }
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct EntropySurveyArgs {
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 79, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 100)]
+    pub generations: usize,
+    /// Block length k for the k-block entropy
+    #[arg(short, long, default_value_t = 3)]
+    pub block_size: usize,
+    /// Step all rules together on the GPU (needs the `gpu` feature)
+    #[arg(long, conflicts_with_all = ["checkpoint", "resume"])]
+    pub gpu: bool,
+    /// Survey the two-color outer-totalistic (Life-like) codes of this radius instead of the 256 ECAs
+    #[arg(long, value_name = "RADIUS", conflicts_with_all = ["gpu", "checkpoint", "resume", "dedupe"])]
+    pub outer: Option<usize>,
+    #[command(flatten)]
+    pub init: InitArgs,
+    #[command(flatten)]
+    pub output: SurveyOutput,
+    #[command(flatten)]
+    pub checkpoint: CheckpointArgs,
+}
+
+pub fn entropy_survey(args: &EntropySurveyArgs) {
+    let EntropySurveyArgs { width, generations, block_size, gpu, outer, ref init, ref output, ref checkpoint } = *args;
+    require_block("--block-size", block_size, width);
+    // Survey all 256 rules by entropy signature
+    let max_entropy = block_size as f64;
+    if let Some(radius) = outer {
+        return outer_entropy_survey(args, radius);
+    }
+
+    let initial = survey_rows(init, width);
+    let rules = checkpoint.rules();
+    let signatures: Vec<_> = gpu_survey(gpu, || {
+        let series = gpu::entropy_series(&initial, generations, block_size, ENTROPY_SKIP)?;
+        Ok(series.iter().map(|entropies| mean_std(entropies)).collect())
+    })
+    .map(|all| checkpoint.select(all))
+    .unwrap_or_else(|| {
+        let settings =
+            format!("entropy-survey width={width} gens={generations} block_size={block_size}{}", init.describe());
+        checkpoint.survey(&settings, |rule| {
+            entropy_signature_from(initial[rule as usize].clone(), generations, block_size)
+        })
+    });
+    let csv = || {
+        std::iter::once("rule,canonical,mean,std_dev,norm_mean,norm_std,class\n".to_string())
+            .chain(rules.iter().copied().zip(&signatures).map(|(rule, &(mean, std_dev))| {
+                let (norm_mean, norm_std) = (mean / max_entropy, std_dev / max_entropy);
+                let (canonical, class) = (Rule(rule).canonical(), entropy_class(norm_mean, norm_std).1);
+                format!("{rule},{canonical},{mean},{std_dev},{norm_mean},{norm_std},{class}\n")
+            }))
+            .collect()
+    };
+    let json = || {
+        let rules = rules.iter().copied().zip(&signatures).map(|(rule, &(mean, std_dev))| {
+            let (norm_mean, norm_std) = (mean / max_entropy, std_dev / max_entropy);
+            Json::object([
+                ("rule", rule.into()),
+                ("canonical", Rule(rule).canonical().0.into()),
+                ("mean", mean.into()),
+                ("std_dev", std_dev.into()),
+                ("norm_mean", norm_mean.into()),
+                ("norm_std", norm_std.into()),
+                ("class", entropy_class(norm_mean, norm_std).1.into()),
+            ])
+        });
+        let parameters = Json::object([
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("block_size", block_size.into()),
+            ("skip", ENTROPY_SKIP.into()),
+            ("initial", init.to_json()),
+        ]);
+        report("entropy-survey", parameters, Json::object([("rules", Json::array(rules))]))
+    };
+    let columns = || {
+        let normalized: Vec<(f64, f64)> = signatures.iter().map(|&(m, s)| (m / max_entropy, s / max_entropy)).collect();
+        vec![
+            Column::required("rule", rules.clone()),
+            Column::required("canonical", canonical(&rules)),
+            Column::required("mean", signatures.iter().map(|s| s.0).collect::<Vec<_>>()),
+            Column::required("std_dev", signatures.iter().map(|s| s.1).collect::<Vec<_>>()),
+            Column::required("norm_mean", normalized.iter().map(|n| n.0).collect::<Vec<_>>()),
+            Column::required("norm_std", normalized.iter().map(|n| n.1).collect::<Vec<_>>()),
+            Column::required("class", normalized.iter().map(|&(m, s)| entropy_class(m, s).1).collect::<Vec<_>>()),
+        ]
+    };
+    if !output.table() {
+        return output.write(csv, json, columns);
+    }
+
+    println!("Entropy survey (width={width}, gens={generations}, blocks={block_size}{})", init.describe());
+    println!("{:>4} {:>7} {:>7} {:>8}", "Rule", "Mean", "StdDev", "Class");
+    println!("{}", "-".repeat(32));
+
+    let mut classes: [Vec<u8>; 5] = Default::default(); // dead, periodic, fractal, complex, chaotic
+
+    for (rule, &(mean, std_dev)) in rules.iter().copied().zip(&signatures) {
+        let norm_mean = mean / max_entropy;
+        let norm_std = std_dev / max_entropy;
+
+        // Classify based on entropy signature
+        let (class_idx, class_name) = entropy_class(norm_mean, norm_std);
+
+        classes[class_idx].push(rule);
+
+        // Only print interesting rules
+        if class_idx >= 2 {
+            println!(
+                "{:>4} {:>7.3} {:>7.3} {:>8}",
+                rule, norm_mean, norm_std, class_name
+            );
+        }
+    }
+
+    println!("{}", "-".repeat(32));
+    println!("Classification:");
+    println!("  Dead:     {} rules", classes[0].len());
+    println!("  Periodic: {} rules", classes[1].len());
+    println!("  Fractal:  {} rules ({:?}...)", classes[2].len(),
+        &classes[2][..classes[2].len().min(5)]);
+    println!("  Complex:  {} rules", classes[3].len());
+    println!("  Chaotic:  {} rules ({:?})", classes[4].len(), classes[4]);
+    output.write(csv, json, columns);
+}
+
+/// `entropy-survey --outer`: every outer-totalistic code's signature, by code
+fn outer_entropy_survey(args: &EntropySurveyArgs, radius: usize) {
+    let EntropySurveyArgs { width, generations, block_size, ref init, ref output, .. } = *args;
+    let max_entropy = block_size as f64;
+    let results = outer_survey(init, width, radius, |ca| {
+        let (mean, std_dev) = entropy_signature_from(ca, generations, block_size);
+        (mean, std_dev, entropy_class(mean / max_entropy, std_dev / max_entropy))
+    });
+    let canonical = canonical_outer(radius, results.len());
+    let csv = || {
+        std::iter::once("code,canonical,rule,mean,std_dev,norm_mean,norm_std,class\n".to_string())
+            .chain(results.iter().enumerate().map(|(code, (rule, (mean, std_dev, class)))| {
+                let (norm_mean, norm_std) = (mean / max_entropy, std_dev / max_entropy);
+                format!("{code},{},{rule},{mean},{std_dev},{norm_mean},{norm_std},{}\n", canonical[code], class.1)
+            }))
+            .collect()
+    };
+    let json = || {
+        let rules = results.iter().enumerate().map(|(code, (rule, signature))| {
+            let (mean, std_dev, class) = *signature;
+            Json::object([
+                ("code", code.into()),
+                ("canonical", canonical[code].into()),
+                ("rule", rule.clone().into()),
+                ("mean", mean.into()),
+                ("std_dev", std_dev.into()),
+                ("norm_mean", (mean / max_entropy).into()),
+                ("norm_std", (std_dev / max_entropy).into()),
+                ("class", class.1.into()),
+            ])
+        });
+        let parameters = Json::object([
+            ("outer_radius", radius.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("block_size", block_size.into()),
+            ("skip", ENTROPY_SKIP.into()),
+            ("initial", init.to_json()),
+        ]);
+        report("entropy-survey", parameters, Json::object([("rules", Json::array(rules))]))
+    };
+    let columns = || {
+        vec![
+            Column::required("code", (0..results.len()).collect::<Vec<_>>()),
+            Column::required("canonical", canonical.clone()),
+            Column::required("rule", results.iter().map(|r| r.0.clone()).collect::<Vec<_>>()),
+            Column::required("mean", results.iter().map(|r| r.1.0).collect::<Vec<_>>()),
+            Column::required("std_dev", results.iter().map(|r| r.1.1).collect::<Vec<_>>()),
+            Column::required("norm_mean", results.iter().map(|r| r.1.0 / max_entropy).collect::<Vec<_>>()),
+            Column::required("norm_std", results.iter().map(|r| r.1.1 / max_entropy).collect::<Vec<_>>()),
+            Column::required("class", results.iter().map(|r| r.1.2.1).collect::<Vec<_>>()),
+        ]
+    };
+    if !output.table() {
+        return output.write(csv, json, columns);
+    }
+
+    println!(
+        "Entropy survey of the {} radius-{radius} outer-totalistic codes (width={width}, gens={generations}, \
+         blocks={block_size}{})",
+        results.len(),
+        init.describe()
+    );
+    for (index, name) in ["dead", "periodic", "fractal", "complex", "chaotic"].into_iter().enumerate() {
+        let rules: Vec<&str> = results.iter().filter(|r| r.1.2.0 == index).map(|r| r.0.as_str()).collect();
+        print_examples(name, &rules);
+    }
+    output.write(csv, json, columns);
+}
+
+/// A class's count of rules and its first few, by births and survivals
+fn print_examples(class: &str, rules: &[&str]) {
+    let examples = match rules.len() {
+        0 => String::new(),
+        1..=5 => format!(" ({})", rules.join(" ")),
+        _ => format!(" ({} ...)", rules[..5].join(" ")),
+    };
+    println!("  {:<11} {:>5} rules{examples}", format!("{class}:"), rules.len());
+}
+
+/// Every two-color outer-totalistic code of `radius`, in code order, with
+/// its births and survivals and what `measure` makes of a run from `init`
+fn outer_survey<T: Send>(
+    init: &InitArgs,
+    width: usize,
+    radius: usize,
+    measure: impl Fn(Automaton) -> T + Sync,
+) -> Vec<(String, T)> {
+    let size = Family::OuterTotalistic.size(2, radius).unwrap_or_else(|e| {
+        eprintln!("--outer: {e}");
+        std::process::exit(1);
+    });
+    let progress = Progress::rules(&format!("Radius-{radius} outer-totalistic"), size.unwrap() as usize);
+    let results = (0..size.unwrap())
+        .into_par_iter()
+        .map(|code| {
+            let rule = WolframRule::outer_totalistic(2, radius, &code.to_string()).unwrap();
+            let ca = init.automaton_for(width, BinaryRule::from(rule.table_rule().unwrap()));
+            let result = (rule.life_notation().unwrap(), measure(ca));
+            progress.tick();
+            result
+        })
+        .collect();
+    drop(progress);
+    results
+}
+
+/// Each rule's class representative, for a survey's `canonical` column
+fn canonical(rules: &[u8]) -> Vec<u8> {
+    rules.iter().map(|&rule| Rule(rule).canonical().0).collect()
+}
+
+/// The smallest outer-totalistic code equivalent to each of codes 0 to
+/// `count` - 1: its complement's, when that's smaller (the codes are all
+/// their own mirrors)
+fn canonical_outer(radius: usize, count: usize) -> Vec<usize> {
+    (0..count)
+        .map(|code| {
+            let rule = WolframRule::outer_totalistic(2, radius, &code.to_string()).unwrap();
+            rule.canonical().code().parse().unwrap()
+        })
+        .collect()
+}
+
+/// Generations skipped before the entropy signature, to avoid transient bias
+pub const ENTROPY_SKIP: usize = 50;
+
+/// Entropy-survey class (index, name) from the signature normalized by the
+/// maximum entropy: dead, periodic, fractal, complex or chaotic
+pub fn entropy_class(norm_mean: f64, norm_std: f64) -> (usize, &'static str) {
+    // The comparisons made, in order, to warn of a class decided by a hair
+    let mut compared = Vec::new();
+    let mut compare = |name: &'static str, value: f64, threshold: f64| {
+        compared.push((name, value, threshold));
+        value
+    };
+    let class = if compare("mean", norm_mean, 0.05) < 0.05 {
+        (0, "dead")
+    } else if compare("std", norm_std, 0.02) < 0.02 && compare("mean", norm_mean, 0.3) < 0.3 {
+        (1, "periodic")
+    } else if compare("std", norm_std, 0.15) > 0.15 {
+        (2, "fractal")
+    } else if compare("mean", norm_mean, 0.75) > 0.75 && compare("std", norm_std, 0.1) < 0.1 {
+        (4, "chaotic")
+    } else {
+        (3, "complex")
+    };
+    for (name, value, threshold) in compared {
+        diagnostics::check_threshold(&format!("entropy class {}: normalized {name}", class.1), value, threshold);
+    }
+    class
+}
+
+
+/// Mean and standard deviation of k-block entropy after the transient
+pub fn entropy_signature(rule: u8, width: usize, generations: usize, block_size: usize) -> (f64, f64) {
+    entropy_signature_from(Automaton::new(width, rule), generations, block_size)
+}
+
+/// `entropy_signature` from an arbitrary initial row
+pub fn entropy_signature_from(mut ca: Automaton, generations: usize, block_size: usize) -> (f64, f64) {
+    diagnostics::check_sampling(&format!("{block_size}-block entropy"), ca.width(), 2f64.powi(block_size as i32));
+    let mut entropies = Vec::with_capacity(generations + 1);
+
+    // Skip transient phase
+    for _ in 0..ENTROPY_SKIP {
+        ca.step();
+    }
+
+    entropies.push(ca.block_entropy(block_size));
+    for _ in 0..generations {
+        ca.step();
+        entropies.push(ca.block_entropy(block_size));
+    }
+
+    mean_std(&entropies)
+}
+
+/// The initial row of every rule for a survey, in rule order, each over
+/// its own background under `--quiescent auto`
+fn survey_rows(init: &InitArgs, width: usize) -> Vec<Automaton> {
+    let initial = init.automaton(width, 0);
+    (0..=255u8).map(|rule| initial.with_rule(rule).on_background(init.quiescent.resolve(rule))).collect()
+}
+
+/// Run a survey on the GPU when asked, or None (with a note on stderr) to
+/// fall back to the CPU
+fn gpu_survey<T>(enabled: bool, survey: impl FnOnce() -> Result<Vec<T>, GpuError>) -> Option<Vec<T>> {
+    if !enabled {
+        return None;
+    }
+    let results = survey().map_err(|e| note!("GPU survey unavailable ({e}); running on the CPU")).ok();
+    if results.is_some() {
+        verbose!("Survey ran on the GPU");
+    }
+    profile::set_backend(if results.is_some() { "gpu" } else { "cpu (GPU unavailable)" });
+    results
+}
+
+#[derive(Args, Clone)]
+pub struct CompressArgs {
+    /// Rule number (0-255), a Wolfram code such as k=2,r=2,code=20, or a
+    /// radius-2 or -3 table: table:<hex> or random:<radius>[:<seed>]
+    #[arg(default_value_t = BinaryRule::Elementary(110), value_parser = parse_binary_rule)]
+    pub rule: BinaryRule,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 79, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 200)]
+    pub generations: usize,
+    /// Print the report as a table or as JSON
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    #[command(flatten)]
+    pub init: InitArgs,
+    #[command(flatten)]
+    pub schedule: ScheduleArgs,
+}
+
+pub fn compress(args: &CompressArgs) {
+    let CompressArgs { rule, width, generations, format, ref init, ref schedule } = *args;
+    // Compression analysis for single rule
+
+    let (raw, compressed, ratio) = compression_ratio_from(schedule.apply(init.automaton_for(width, rule)), generations);
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", schedule.rule_json(rule)),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("initial", init.to_json()),
+            ("schedule", schedule.to_json()),
+        ]);
+        let results =
+            Json::object([("raw_bits", raw.into()), ("compressed_bits", compressed.into()), ("ratio", ratio.into())]);
+        return print_report("compress", parameters, results);
+    }
+
+    println!("Compression analysis: {} (width={width}, gens={generations}{})", schedule.title(rule), init.describe());
+
+    println!("  Raw size:        {} bits", raw);
+    println!("  Compressed:      {} bits", compressed);
+    println!("  Ratio:           {:.3} (lower = more compressible)", ratio);
+    println!("  Incompressible:  {:.1}%", ratio * 100.0);
+}
+
+#[derive(Args, Clone)]
+pub struct CompressSurveyArgs {
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 79, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 200)]
+    pub generations: usize,
+    /// Survey the two-color outer-totalistic (Life-like) codes of this radius instead of the 256 ECAs
+    #[arg(long, value_name = "RADIUS", conflicts_with_all = ["checkpoint", "resume", "dedupe"])]
+    pub outer: Option<usize>,
+    #[command(flatten)]
+    pub init: InitArgs,
+    #[command(flatten)]
+    pub output: SurveyOutput,
+    #[command(flatten)]
+    pub checkpoint: CheckpointArgs,
+}
+
+pub fn compress_survey(args: &CompressSurveyArgs) {
+    let CompressSurveyArgs { width, generations, outer, ref init, ref output, ref checkpoint } = *args;
+    // Survey all 256 rules by compression ratio
+    if let Some(radius) = outer {
+        return outer_compress_survey(args, radius);
+    }
+
+    let initial = survey_rows(init, width);
+    let rules = checkpoint.rules();
+    let settings = format!("compress-survey width={width} gens={generations}{}", init.describe());
+    let sizes: Vec<(usize, usize, f64)> =
+        checkpoint.survey(&settings, |rule| compression_ratio_from(initial[rule as usize].clone(), generations));
+    let class = compression_class;
+    let csv = || {
+        std::iter::once("rule,canonical,raw_bits,compressed_bits,ratio,class\n".to_string())
+            .chain(rules.iter().copied().zip(&sizes).map(|(rule, &(raw, compressed, ratio))| {
+                format!("{rule},{},{raw},{compressed},{ratio},{}\n", Rule(rule).canonical(), class(ratio))
+            }))
+            .collect()
+    };
+    let json = || {
+        let rules = rules.iter().copied().zip(&sizes).map(|(rule, &(raw, compressed, ratio))| {
+            Json::object([
+                ("rule", rule.into()),
+                ("canonical", Rule(rule).canonical().0.into()),
+                ("raw_bits", raw.into()),
+                ("compressed_bits", compressed.into()),
+                ("ratio", ratio.into()),
+                ("class", class(ratio).into()),
+            ])
+        });
+        let parameters =
+            Json::object([("width", width.into()), ("generations", generations.into()), ("initial", init.to_json())]);
+        report("compress-survey", parameters, Json::object([("rules", Json::array(rules))]))
+    };
+    let columns = || {
+        vec![
+            Column::required("rule", rules.clone()),
+            Column::required("canonical", canonical(&rules)),
+            Column::required("raw_bits", sizes.iter().map(|s| s.0).collect::<Vec<_>>()),
+            Column::required("compressed_bits", sizes.iter().map(|s| s.1).collect::<Vec<_>>()),
+            Column::required("ratio", sizes.iter().map(|s| s.2).collect::<Vec<_>>()),
+            Column::required("class", sizes.iter().map(|s| class(s.2)).collect::<Vec<_>>()),
+        ]
+    };
+    if !output.table() {
+        return output.write(csv, json, columns);
+    }
+
+    println!("Compression survey (width={width}, gens={generations}{})", init.describe());
+    println!("{:>4} {:>8} {:>12}", "Rule", "Ratio", "Class");
+    println!("{}", "-".repeat(28));
+
+    let mut results: Vec<(u8, f64)> = rules.iter().copied().zip(&sizes).map(|(rule, size)| (rule, size.2)).collect();
+
+    // Sort by compression ratio
+    results.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+    // Classify and print
+    for (rule, ratio) in &results {
+        // Only print interesting ones (not trivial)
+        if *ratio >= 0.05 {
+            println!("{:>4} {:>8.3} {:>12}", rule, ratio, class(*ratio));
+        }
+    }
+
+    // Summary
+    println!("{}", "-".repeat(28));
+    let trivial = results.iter().filter(|(_, r)| *r < 0.05).count();
+    let periodic = results.iter().filter(|(_, r)| *r >= 0.05 && *r < 0.20).count();
+    let structured = results.iter().filter(|(_, r)| *r >= 0.20 && *r < 0.50).count();
+    let complex = results.iter().filter(|(_, r)| *r >= 0.50 && *r < 0.80).count();
+    let chaotic = results.iter().filter(|(_, r)| *r >= 0.80).count();
+
+    println!("Classification:");
+    println!("  Trivial (<5%):     {}", trivial);
+    println!("  Periodic (5-20%):  {}", periodic);
+    println!("  Structured (20-50%): {}", structured);
+    println!("  Complex (50-80%):  {}", complex);
+    println!("  Chaotic (>80%):    {}", chaotic);
+
+    // Most compressible and least compressible
+    println!("\nMost compressible: Rule {} ({:.1}%)", results[trivial].0, results[trivial].1 * 100.0);
+    println!("Least compressible: Rule {} ({:.1}%)", results.last().unwrap().0, results.last().unwrap().1 * 100.0);
+    output.write(csv, json, columns);
+}
+
+/// Compress-survey class of a compression ratio
+fn compression_class(ratio: f64) -> &'static str {
+    if ratio < 0.05 {
+        "trivial"      // nearly empty or constant
+    } else if ratio < 0.20 {
+        "periodic"     // highly repetitive
+    } else if ratio < 0.50 {
+        "structured"   // has exploitable patterns
+    } else if ratio < 0.80 {
+        "complex"      // some structure
+    } else {
+        "chaotic"      // nearly incompressible
+    }
+}
+
+/// `compress-survey --outer`: every outer-totalistic code's ratio, by code
+fn outer_compress_survey(args: &CompressSurveyArgs, radius: usize) {
+    let CompressSurveyArgs { width, generations, ref init, ref output, .. } = *args;
+    let results = outer_survey(init, width, radius, |ca| compression_ratio_from(ca, generations));
+    let canonical = canonical_outer(radius, results.len());
+    let csv = || {
+        std::iter::once("code,canonical,rule,raw_bits,compressed_bits,ratio,class\n".to_string())
+            .chain(results.iter().enumerate().map(|(code, (rule, (raw, compressed, ratio)))| {
+                let class = compression_class(*ratio);
+                format!("{code},{},{rule},{raw},{compressed},{ratio},{class}\n", canonical[code])
+            }))
+            .collect()
+    };
+    let json = || {
+        let rules = results.iter().enumerate().map(|(code, (rule, sizes))| {
+            let (raw, compressed, ratio) = *sizes;
+            Json::object([
+                ("code", code.into()),
+                ("canonical", canonical[code].into()),
+                ("rule", rule.clone().into()),
+                ("raw_bits", raw.into()),
+                ("compressed_bits", compressed.into()),
+                ("ratio", ratio.into()),
+                ("class", compression_class(ratio).into()),
+            ])
+        });
+        let parameters = Json::object([
+            ("outer_radius", radius.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("initial", init.to_json()),
+        ]);
+        report("compress-survey", parameters, Json::object([("rules", Json::array(rules))]))
+    };
+    let columns = || {
+        vec![
+            Column::required("code", (0..results.len()).collect::<Vec<_>>()),
+            Column::required("canonical", canonical.clone()),
+            Column::required("rule", results.iter().map(|r| r.0.clone()).collect::<Vec<_>>()),
+            Column::required("raw_bits", results.iter().map(|r| r.1.0).collect::<Vec<_>>()),
+            Column::required("compressed_bits", results.iter().map(|r| r.1.1).collect::<Vec<_>>()),
+            Column::required("ratio", results.iter().map(|r| r.1.2).collect::<Vec<_>>()),
+            Column::required("class", results.iter().map(|r| compression_class(r.1.2)).collect::<Vec<_>>()),
+        ]
+    };
+    if !output.table() {
+        return output.write(csv, json, columns);
+    }
+
+    println!(
+        "Compression survey of the {} radius-{radius} outer-totalistic codes (width={width}, gens={generations}{})",
+        results.len(),
+        init.describe()
+    );
+    for name in ["trivial", "periodic", "structured", "complex", "chaotic"] {
+        let rules: Vec<&str> =
+            results.iter().filter(|r| compression_class(r.1.2) == name).map(|r| r.0.as_str()).collect();
+        print_examples(name, &rules);
+    }
+    let (most, least) = (
+        results.iter().min_by(|a, b| a.1.2.total_cmp(&b.1.2)).unwrap(),
+        results.iter().max_by(|a, b| a.1.2.total_cmp(&b.1.2)).unwrap(),
+    );
+    println!("\nMost compressible: {} ({:.1}%)", most.0, most.1.2 * 100.0);
+    println!("Least compressible: {} ({:.1}%)", least.0, least.1.2 * 100.0);
+    output.write(csv, json, columns);
+}
+
+#[derive(Args, Clone)]
+pub struct ComplexityArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 110, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 201, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 200)]
+    pub generations: usize,
+    /// Longest block length to count
+    #[arg(short = 'k', long, default_value_t = 12)]
+    pub max_k: usize,
+    /// Transient generations to discard before counting
+    #[arg(short, long, default_value_t = 0)]
+    pub skip: usize,
+    /// Print the report as a table or as JSON
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+/// A growth fit's fields, for JSON reports
+pub fn fit_json(fit: &GrowthFit) -> [(&'static str, Json); 4] {
+    [
+        ("class", fit.class.to_string().into()),
+        ("exponent", fit.exponent.into()),
+        ("r_squared", fit.r_squared.into()),
+        ("points", fit.points.into()),
+    ]
+}
+
+pub fn complexity(args: &ComplexityArgs) {
+    let ComplexityArgs { rule, width, generations, max_k, skip, format, ref init } = *args;
+    require_block("--max-k", max_k, width);
+    // Language complexity: distinct k-blocks as a function of k and time
+
+    let growth = block_complexity_from(init.automaton(width, rule), generations, max_k, 4, skip);
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("max_k", max_k.into()),
+            ("skip", skip.into()),
+            ("initial", init.to_json()),
+        ]);
+        let snapshots = growth.times.iter().zip(&growth.counts).map(|(&t, counts)| {
+            Json::object([("t", t.into()), ("samples", growth.samples_at(t).into()), ("counts", counts.clone().into())])
+        });
+        let fit = fit_growth(growth.final_counts(), growth.final_samples());
+        let results = Json::object([("snapshots", Json::array(snapshots)), ("fit", Json::object(fit_json(&fit)))]);
+        return print_report("complexity", parameters, results);
+    }
+
+    println!("Block complexity: Rule {rule} (width={width}, gens={generations}, max_k={max_k}, skip={skip}{})", init.describe());
+
+    // Table: one row per k, one column per time snapshot
+    print!("{:>3} {:>8}", "k", "2^k");
+    for t in &growth.times {
+        print!(" {:>8}", format!("t={t}"));
+    }
+    println!("  log2 N(k) at t={}", growth.times.last().unwrap());
+    println!("{}", "-".repeat(13 + 9 * growth.times.len() + 30));
+
+    let final_counts = growth.final_counts();
+    for k in 1..=final_counts.len() {
+        print!("{:>3} {:>8}", k, 1u64 << k);
+        for snapshot in &growth.counts {
+            print!(" {:>8}", snapshot[k - 1]);
+        }
+        let log_n = (final_counts[k - 1] as f64).log2();
+        println!("  {:<20} {:.2}", "#".repeat((log_n * 1.5).round() as usize), log_n);
+    }
+
+    let samples = growth.final_samples();
+    let fit = fit_growth(final_counts, samples);
+    println!("{}", "-".repeat(13 + 9 * growth.times.len() + 30));
+    println!("Sample ceiling: {samples} block occurrences per k");
+    println!("Growth class:   {}", fit.class);
+    match fit.class {
+        GrowthClass::Exponential => println!("Entropy est.:   {:.3} bits/cell (R²={:.3}, {} points)", fit.exponent, fit.r_squared, fit.points),
+        GrowthClass::Polynomial => println!("Degree est.:    {:.2} (R²={:.3}, {} points)", fit.exponent, fit.r_squared, fit.points),
+        GrowthClass::Bounded => println!("Block count saturates: language is eventually periodic"),
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct ComplexitySurveyArgs {
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 201, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 200)]
+    pub generations: usize,
+    /// Longest block length to count
+    #[arg(short = 'k', long, default_value_t = 12)]
+    pub max_k: usize,
+    /// Transient generations to discard before counting
+    #[arg(short, long, default_value_t = 50)]
+    pub skip: usize,
+    #[command(flatten)]
+    pub init: InitArgs,
+    #[command(flatten)]
+    pub output: SurveyOutput,
+    #[command(flatten)]
+    pub checkpoint: CheckpointArgs,
+}
+
+pub fn complexity_survey(args: &ComplexitySurveyArgs) {
+    let ComplexitySurveyArgs { width, generations, max_k, skip, ref init, ref output, ref checkpoint } = *args;
+    require_block("--max-k", max_k, width);
+    // Fit a growth class to the block complexity of every rule
+
+    let initial = survey_rows(init, width);
+    let rules = checkpoint.rules();
+    let settings =
+        format!("complexity-survey width={width} gens={generations} max_k={max_k} skip={skip}{}", init.describe());
+    let growths: Vec<(Vec<usize>, usize)> = checkpoint.survey(&settings, |rule| {
+        let growth = block_complexity_from(initial[rule as usize].clone(), generations, max_k, 1, skip);
+        (growth.final_counts().to_vec(), growth.final_samples())
+    });
+    // Distinct blocks of the longest length, and the fit
+    let fits: Vec<(u8, usize, GrowthFit)> = rules.iter().copied()
+        .zip(&growths)
+        .map(|(rule, (counts, samples))| (rule, counts.last().copied().unwrap_or(0), fit_growth(counts, *samples)))
+        .collect();
+    let csv = || {
+        std::iter::once("rule,canonical,blocks_at_max_k,class,exponent,r_squared,points\n".to_string())
+            .chain(fits.iter().map(|(rule, blocks, fit)| {
+                let canonical = Rule(*rule).canonical();
+                format!("{rule},{canonical},{blocks},{},{},{},{}\n", fit.class, fit.exponent, fit.r_squared, fit.points)
+            }))
+            .collect()
+    };
+    let json = || {
+        let rules = fits.iter().map(|(rule, blocks, fit)| {
+            let fields = [
+                ("rule", (*rule).into()),
+                ("canonical", Rule(*rule).canonical().0.into()),
+                ("blocks_at_max_k", (*blocks).into()),
+            ];
+            Json::object(fields.into_iter().chain(fit_json(fit)))
+        });
+        let parameters = Json::object([
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("max_k", max_k.into()),
+            ("skip", skip.into()),
+            ("initial", init.to_json()),
+        ]);
+        report("complexity-survey", parameters, Json::object([("rules", Json::array(rules))]))
+    };
+    let columns = || {
+        vec![
+            Column::required("rule", fits.iter().map(|f| f.0).collect::<Vec<_>>()),
+            Column::required("canonical", canonical(&rules)),
+            Column::required("blocks_at_max_k", fits.iter().map(|f| f.1).collect::<Vec<_>>()),
+            Column::required("class", fits.iter().map(|f| f.2.class.to_string()).collect::<Vec<_>>()),
+            Column::required("exponent", fits.iter().map(|f| f.2.exponent).collect::<Vec<_>>()),
+            Column::required("r_squared", fits.iter().map(|f| f.2.r_squared).collect::<Vec<_>>()),
+            Column::required("points", fits.iter().map(|f| f.2.points).collect::<Vec<_>>()),
+        ]
+    };
+    if !output.table() {
+        return output.write(csv, json, columns);
+    }
+
+    println!("Complexity survey (width={width}, gens={generations}, max_k={max_k}, skip={skip}{})", init.describe());
+    println!("{:>4} {:>8} {:>12} {:>9} {:>6}", "Rule", "N(kmax)", "Class", "Exponent", "R²");
+    println!("{}", "-".repeat(43));
+    for (rule, blocks, fit) in &fits {
+        // Only print rules whose language keeps growing
+        if fit.class != GrowthClass::Bounded {
+            println!("{:>4} {:>8} {:>12} {:>9.3} {:>6.3}", rule, blocks, fit.class, fit.exponent, fit.r_squared);
+        }
+    }
+
+    println!("{}", "-".repeat(43));
+    println!("Growth classes:");
+    for class in [GrowthClass::Bounded, GrowthClass::Polynomial, GrowthClass::Exponential] {
+        let count = fits.iter().filter(|(_, _, f)| f.class == class).count();
+        println!("  {:<12} {} rules", format!("{class}:"), count);
+    }
+
+    println!("\nWolfram Class 3/4 rules:");
+    for (rule, _, fit) in fits.iter().filter(|f| INTERESTING_RULES.contains(&f.0)) {
+        println!("  Rule {:>3}: {} ({:.3})", rule, fit.class, fit.exponent);
+    }
+    output.write(csv, json, columns);
+}
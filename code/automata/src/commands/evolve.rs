@@ -0,0 +1,377 @@
+//! Density classification: genetic search for rules, and benchmarks that
+//! compare rules on the same initial conditions
+
+use automata::evolve::{binomial_initials, density_initials, Ga, GaConfig, IslandStats, TableRule};
+use automata::inference::{mcnemar_exact, wilson_interval};
+use super::log::note;
+use super::parse_width;
+use clap::{Args, ValueEnum};
+use rayon::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Checkpoint, log, genealogy and best-rule file names inside a run directory
+const CHECKPOINT: &str = "checkpoint.txt";
+const LOG: &str = "fitness.csv";
+const GENEALOGY: &str = "genealogy.dot";
+const BEST: &str = "best.json";
+
+#[derive(Args, Clone)]
+pub struct EvolveArgs {
+    /// Rule radius; the chromosome is a 2^(2r+1)-bit table (1-3)
+    #[arg(short, long, default_value_t = 3)]
+    pub radius: usize,
+    /// Number of cells in the ring (odd, so the majority is defined)
+    #[arg(short, long, default_value_t = 149, value_parser = parse_width)]
+    pub width: usize,
+    /// GA generations (with `--resume`, the total including those already run)
+    #[arg(short = 'g', long = "gens", default_value_t = 50)]
+    pub generations: usize,
+    /// Steps each rule gets to settle (default: 2 x width)
+    #[arg(short, long)]
+    pub max_steps: Option<usize>,
+    /// Fresh random initial conditions per generation
+    #[arg(short, long, default_value_t = 100)]
+    pub initials: usize,
+    /// Rules per island
+    #[arg(short, long, default_value_t = 100)]
+    pub population: usize,
+    /// Rules kept unchanged each generation, per island
+    #[arg(short, long, default_value_t = 20)]
+    pub elite: usize,
+    /// Bit flips per offspring
+    #[arg(long, default_value_t = 2)]
+    pub mutations: usize,
+    /// Independent subpopulations (island model)
+    #[arg(long, default_value_t = 1)]
+    pub islands: usize,
+    /// Generations between migrations
+    #[arg(long, default_value_t = 5)]
+    pub migration_interval: usize,
+    /// Rules each island sends to the next
+    #[arg(long, default_value_t = 2)]
+    pub migrants: usize,
+    /// RNG seed; the same seed gives the same run
+    #[arg(short, long, default_value_t = 42)]
+    pub seed: u64,
+    /// Initial conditions for the final test of the best rule
+    #[arg(long, default_value_t = 1000)]
+    pub test_initials: usize,
+    /// Directory for a checkpoint after every generation and a fitness.csv log
+    #[arg(short, long, conflicts_with = "resume")]
+    pub out: Option<PathBuf>,
+    /// Continue the run checkpointed in this directory; its settings
+    /// override the GA flags, except `--gens` and `--test-initials`
+    #[arg(long)]
+    pub resume: Option<PathBuf>,
+    /// Write the best rule's genealogy as Graphviz DOT (default with a run
+    /// directory: genealogy.dot inside it)
+    #[arg(long)]
+    pub genealogy: Option<PathBuf>,
+}
+
+pub fn evolve(args: &EvolveArgs) {
+    let EvolveArgs {
+        radius,
+        width,
+        generations,
+        max_steps,
+        initials,
+        population,
+        elite,
+        mutations,
+        islands,
+        migration_interval,
+        migrants,
+        seed,
+        test_initials,
+        ref out,
+        ref resume,
+        ref genealogy,
+    } = *args;
+    // Evolve radius-r rules for density classification: settle to all 1s if
+    // most cells start live, all 0s otherwise
+
+    let mut ga = match resume {
+        Some(dir) => match Ga::load(&dir.join(CHECKPOINT)) {
+            Ok(ga) => ga,
+            Err(e) => {
+                eprintln!("Can't resume from {}: {e}", dir.display());
+                std::process::exit(1);
+            }
+        },
+        None => {
+            if !(1..=automata::evolve::MAX_RADIUS).contains(&radius) || elite == 0 || elite > population {
+                eprintln!("Need 1 <= radius <= {} and 1 <= elite <= population", automata::evolve::MAX_RADIUS);
+                std::process::exit(1);
+            }
+            Ga::new(GaConfig {
+                radius,
+                width,
+                max_steps: max_steps.unwrap_or(2 * width),
+                initials,
+                islands,
+                population,
+                elite,
+                mutations,
+                migration_interval,
+                migrants: migrants.min(population - elite),
+                seed,
+            })
+        }
+    };
+    let run_dir = resume.as_ref().or(out.as_ref());
+    if let Some(dir) = run_dir {
+        let prepared = match resume {
+            Some(_) => truncate_log(&dir.join(LOG), ga.generation),
+            None if dir.join(CHECKPOINT).exists() => {
+                eprintln!("{} already holds a run; continue it with --resume", dir.display());
+                std::process::exit(1);
+            }
+            None => std::fs::create_dir_all(dir)
+                .and_then(|_| std::fs::write(dir.join(LOG), "generation,island,best,mean,best_rule\n")),
+        };
+        if let Err(e) = prepared {
+            eprintln!("Can't prepare {}: {e}", dir.display());
+            std::process::exit(1);
+        }
+    }
+
+    let GaConfig { radius, width, max_steps, initials, islands, population, elite, seed, .. } = ga.config;
+    println!("Evolving radius-{radius} rules for density classification ({}-bit chromosomes)", 1 << (2 * radius + 1));
+    println!("(width={width}, max_steps={max_steps}, initials={initials}, islands={islands} x {population}, elite={elite}, seed={seed})");
+    if ga.generation > 0 {
+        note!("Resuming at generation {}", ga.generation + 1);
+    }
+    println!("{:>5} {:>7} {:>7} {:>7}  Best rule (hex)", "Gen", "Best", "Mean", "Cached");
+    println!("{}", "-".repeat(68));
+
+    let mut best = None;
+    while ga.generation < generations {
+        if best.is_some() {
+            ga.advance();
+        }
+        // A resumed checkpoint is already scored; this reuses its fitness cache
+        let (lookups, evaluations) = (ga.lookups, ga.evaluations);
+        let stats = ga.score();
+        // Share of rules equivalent to one already scored this generation
+        let cached = match ga.lookups - lookups {
+            0 => "-".to_string(),
+            n => format!("{:.0}%", 100.0 * (n - (ga.evaluations - evaluations)) as f64 / n as f64),
+        };
+        let top = stats.iter().max_by(|a, b| a.best.total_cmp(&b.best)).unwrap();
+        let mean = stats.iter().map(|s| s.mean).sum::<f64>() / stats.len() as f64;
+        println!("{:>5} {:>7.3} {:>7.3} {:>7}  {:0width$x}", ga.generation + 1, top.best, mean, cached, top.best_rule.table,
+            width = top.best_rule.entries() / 4);
+        best = Some((top.best_rule, top.best_id));
+
+        if let Some(dir) = run_dir {
+            let saved = log_generation(&dir.join(LOG), ga.generation + 1, &stats)
+                .and_then(|_| ga.save(&dir.join(CHECKPOINT)));
+            if let Err(e) = saved {
+                eprintln!("Can't checkpoint to {}: {e}", dir.display());
+                std::process::exit(1);
+            }
+        }
+        if ga.generation + 1 == generations {
+            break;
+        }
+    }
+
+    let Some((best, best_id)) = best else { return };
+
+    // Per-generation fitness is measured on 100 fresh rows; rank on a bigger, independent set
+    println!("{}", "-".repeat(68));
+    if ga.lookups > 0 {
+        println!("Fitness cache: {} of {} lookups hit ({:.1}%)", ga.lookups - ga.evaluations, ga.lookups,
+            100.0 * (ga.lookups - ga.evaluations) as f64 / ga.lookups as f64);
+    }
+    let mut rng = ChaCha8Rng::seed_from_u64(seed ^ 0x7E57);
+    let tests = density_initials(&mut rng, test_initials, width);
+    println!("Best rule of the last generation: {:0width$x}", best.table, width = best.entries() / 4);
+    let test_fitness = best.fitness(&tests, max_steps);
+    println!("  Fitness on {test_initials} fresh initial conditions: {test_fitness:.3}");
+
+    // For `benchmark-task --rules run-dir/best.json`
+    if let Some(dir) = run_dir {
+        let json = format!("{{\"radius\": {}, \"table\": \"{:0w$x}\", \"generation\": {}, \"test_fitness\": {test_fitness}}}\n",
+            best.radius, best.table, ga.generation + 1, w = best.entries() / 4);
+        if let Err(e) = std::fs::write(dir.join(BEST), json) {
+            eprintln!("Can't write {}: {e}", dir.join(BEST).display());
+            std::process::exit(1);
+        }
+    }
+
+    let genealogy = genealogy.clone().or_else(|| run_dir.map(|dir| dir.join(GENEALOGY)));
+    if let Some(path) = genealogy {
+        if let Err(e) = std::fs::write(&path, ga.genealogy_dot(best_id)) {
+            eprintln!("Can't write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+        println!("  Lineage: {} ancestors back to generation 1 -> {}", ga.lineage(best_id).len() - 1, path.display());
+    }
+}
+
+/// Append one row per island to the fitness log
+fn log_generation(path: &Path, generation: usize, stats: &[IslandStats]) -> std::io::Result<()> {
+    let mut log = std::fs::OpenOptions::new().append(true).open(path)?;
+    for (island, s) in stats.iter().enumerate() {
+        writeln!(log, "{generation},{island},{},{},{:x}", s.best, s.mean, s.best_rule.table)?;
+    }
+    Ok(())
+}
+
+/// Drop log rows for generations after the checkpoint's, left by a run that
+/// stopped between logging and saving
+fn truncate_log(path: &Path, bred: usize) -> std::io::Result<()> {
+    let text = std::fs::read_to_string(path)?;
+    let kept: String = text
+        .lines()
+        .enumerate()
+        .filter(|(i, line)| *i == 0 || line.split(',').next().and_then(|g| g.parse::<usize>().ok()) <= Some(bred))
+        .map(|(_, line)| format!("{line}\n"))
+        .collect();
+    std::fs::write(path, kept)
+}
+
+/// Tasks `benchmark-task` can score rules on
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Task {
+    /// Settle to all 1s if most cells start live, all 0s otherwise
+    Density,
+}
+
+#[derive(Args, Clone)]
+pub struct BenchmarkTaskArgs {
+    /// Task to score the rules on
+    #[arg(value_enum)]
+    pub task: Task,
+    /// Comma-separated rules: eca:N, gkl, majority[:r], table:<hex>, a
+    /// two-color Wolfram code (k=2,r=3,code=240), or a best.json written by
+    /// `evolve`
+    #[arg(short, long, value_delimiter = ',', default_value = "eca:232,majority,gkl")]
+    pub rules: Vec<String>,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 149, value_parser = parse_width)]
+    pub width: usize,
+    /// Initial conditions per distribution, shared by every rule
+    #[arg(short, long, default_value_t = 1000)]
+    pub initials: usize,
+    /// Steps each rule gets to settle (default: 2 x width)
+    #[arg(short, long)]
+    pub max_steps: Option<usize>,
+    /// RNG seed for the initial conditions
+    #[arg(short, long, default_value_t = 42)]
+    pub seed: u64,
+}
+
+pub fn benchmark_task(args: &BenchmarkTaskArgs) {
+    let BenchmarkTaskArgs { task, ref rules, width, initials, max_steps, seed } = *args;
+    // Score rules on identical initial conditions, so differences between
+    // them are paired and can be tested directly
+    let Task::Density = task;
+
+    // Wolfram codes have commas of their own, so k=2,r=3,code=240 arrives in pieces
+    let specs = rules.iter().fold(Vec::<String>::new(), |mut specs, piece| {
+        match specs.last_mut() {
+            Some(last) if piece.contains('=') && !piece.starts_with("k=")
+                && (last.contains('=') || last.starts_with("totalistic:") || last.starts_with('B')) =>
+            {
+                last.push(',');
+                last.push_str(piece);
+            }
+            _ => specs.push(piece.clone()),
+        }
+        specs
+    });
+    let parsed: Vec<TableRule> = specs
+        .iter()
+        .map(|spec| {
+            let rule = if spec.ends_with(".json") { read_rule_json(Path::new(spec)) } else { spec.parse() };
+            rule.unwrap_or_else(|e| {
+                eprintln!("Bad rule {spec:?}: {e}");
+                std::process::exit(1);
+            })
+        })
+        .collect();
+    let max_steps = max_steps.unwrap_or(2 * width);
+
+    // Uniform density is the GA's training distribution; coin flips put
+    // almost every row near the hard 1/2 threshold
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let distributions = [
+        ("uniform density", density_initials(&mut rng, initials, width)),
+        ("coin flips", binomial_initials(&mut rng, initials, width)),
+    ];
+
+    println!("Density classification benchmark (width={width}, max_steps={max_steps}, initials={initials} per distribution, seed={seed})");
+    print!("{:<4} {:<28}", "#", "Rule");
+    for (name, _) in &distributions {
+        print!(" {:>24}", name);
+    }
+    println!();
+    println!("{}", "-".repeat(33 + 25 * distributions.len()));
+
+    // outcomes[d][r][i]: rule r classified initial condition i of distribution d
+    let outcomes: Vec<Vec<Vec<bool>>> = distributions
+        .iter()
+        .map(|(_, tests)| {
+            parsed
+                .iter()
+                .map(|rule| tests.par_iter().map(|ic| rule.classifies(ic, max_steps)).collect())
+                .collect()
+        })
+        .collect();
+
+    for (r, spec) in specs.iter().enumerate() {
+        print!("{:<4} {:<28}", format!("[{}]", r + 1), spec);
+        for per_rule in &outcomes {
+            let correct = per_rule[r].iter().filter(|&&c| c).count();
+            let (low, high) = wilson_interval(correct, initials);
+            print!(" {:>24}", format!("{:.3} [{low:.3}, {high:.3}]", correct as f64 / initials as f64));
+        }
+        println!();
+    }
+
+    if parsed.len() < 2 {
+        return;
+    }
+    println!("{}", "-".repeat(33 + 25 * distributions.len()));
+    println!("Pairwise exact McNemar p-values (same initial conditions; < 0.05: the rules differ)");
+    for ((name, _), per_rule) in distributions.iter().zip(&outcomes) {
+        println!("  {name}:");
+        print!("{:>8}", "");
+        for j in 2..=parsed.len() {
+            print!(" {:>9}", format!("[{j}]"));
+        }
+        println!();
+        for i in 0..parsed.len() - 1 {
+            print!("{:>8}", format!("[{}]", i + 1));
+            for j in 1..parsed.len() {
+                if j <= i {
+                    print!(" {:>9}", "");
+                    continue;
+                }
+                // Discordant pairs: only i right, only j right
+                let (b, c) = per_rule[i].iter().zip(&per_rule[j]).fold((0, 0), |(b, c), (&x, &y)| {
+                    (b + (x && !y) as usize, c + (!x && y) as usize)
+                });
+                print!(" {:>9}", format!("{:.2e}", mcnemar_exact(b, c)));
+            }
+            println!();
+        }
+    }
+}
+
+/// The rule in a best.json written by `evolve`
+fn read_rule_json(path: &Path) -> Result<TableRule, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    // The file is flat and machine-written: the table is the string after "table":
+    let table = text
+        .split_once("\"table\"")
+        .and_then(|(_, rest)| rest.split('"').nth(1))
+        .ok_or("no \"table\" field")?;
+    format!("table:{table}").parse()
+}
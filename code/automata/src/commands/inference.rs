@@ -0,0 +1,1429 @@
+//! Causal structure: inferring rules, radii and dependencies from observations
+
+// Neighborhood arithmetic is spelled out bit by bit (`(0 << 2) | (c << 1) | r`)
+#![allow(clippy::identity_op)]
+
+use super::dynamics::{entropy_class, entropy_signature, entropy_signature_from, fit_json};
+use super::json::{print_report, report, Json};
+use super::checkpoint::CheckpointArgs;
+use super::{parse_rule, parse_width, read_spacetime, InitArgs, ReportFormat, SurveyOutput};
+use automata::inference::{
+    binomial_upper_tail, hashed_row, mean_std, rule_dependencies, wilson_interval, DensityBaseline,
+    NeighborhoodCounts, PositionTest, TransitionDataset,
+};
+use automata::evolve::TableRule;
+use automata::parquet::Column;
+use automata::symmetry::{self, Rule};
+use automata::{block_complexity, find_cycle_from, fit_growth, GrowthFit, INTERESTING_RULES};
+use clap::Args;
+use rayon::prelude::*;
+use std::path::PathBuf;
+
+#[derive(Args, Clone, Copy)]
+pub struct InferArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 110, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 50, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 20)]
+    pub generations: usize,
+    /// Probability of flipping each observed outcome
+    #[arg(short, long, default_value_t = 0.0)]
+    pub noise: f64,
+    /// Folds for cross-validation over the training transitions (0 to skip)
+    #[arg(short = 'k', long, default_value_t = 5)]
+    pub folds: usize,
+    /// Print the report as a table or as JSON
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+pub fn infer(args: &InferArgs) {
+    let InferArgs { rule, width, generations, noise, folds, format } = *args;
+    // Infer rule from observations: can we recover the causal mechanism?
+    // This tests whether we can learn the rule vs. just correlations
+
+    // Generate training data from random initial conditions (simulated once,
+    // shared by the local learner and the correlational baseline)
+    let num_trials = 10;
+    let training = TransitionDataset::training(rule, width, generations, num_trials, noise);
+    let counts = training.neighborhood_counts();
+
+    // Infer rule: majority vote for each neighborhood
+    let inferred_rule = counts.infer_rule();
+
+    // Now test generalization: does the inferred rule work on a different distribution?
+    // Sparse (10% density) and dense (90%) test sets, each simulated once
+    let sparse_density = 10;
+    let dense_density = 90;
+    let sparse = TransitionDataset::simulate(
+        rule,
+        generations,
+        (0..5).map(|trial| hashed_row(trial * 99999 + 11111, width, sparse_density)),
+        0.0,
+        0,
+    );
+    let dense = TransitionDataset::simulate(
+        rule,
+        generations,
+        (0..5).map(|trial| hashed_row(trial * 77777 + 33333, width, dense_density)),
+        0.0,
+        0,
+    );
+    let rate = |(errors, total): (usize, usize)| errors as f64 / total as f64;
+    let causal_rate_sparse = rate(sparse.free_run_errors(inferred_rule));
+    let causal_rate_dense = rate(dense.free_run_errors(inferred_rule));
+
+    // Compare with a "correlational" baseline that uses global features
+    let baseline = DensityBaseline::train(&training);
+    let corr_rate_sparse = rate(baseline.errors(&sparse));
+    let corr_rate_dense = rate(baseline.errors(&dense));
+
+    // In-distribution held-out accuracy: a standard, comparable number
+    let cv = (folds > 0).then(|| training.cross_validate(folds));
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("noise", noise.into()),
+            ("folds", folds.into()),
+            ("trials", num_trials.into()),
+        ]);
+        let neighborhoods = (0..8).map(|i| {
+            Json::object([
+                ("neighborhood", format!("{:03b}", i).into()),
+                ("observations", counts.observations[i].into()),
+                ("p_one", counts.p_one(i).into()),
+                ("inferred", ((inferred_rule >> i) & 1).into()),
+                ("true", ((rule >> i) & 1).into()),
+            ])
+        });
+        // Sparse and dense test sets: their densities, and each learner's error rates
+        let error_rates = |sparse: f64, dense: f64| Json::object([("sparse", sparse.into()), ("dense", dense.into())]);
+        let accuracy = |scores: &[f64]| {
+            let (mean, std_dev) = mean_std(scores);
+            Json::object([("mean", mean.into()), ("std_dev", std_dev.into())])
+        };
+        let results = Json::object([
+            ("inferred_rule", inferred_rule.into()),
+            ("exact", (inferred_rule == rule).into()),
+            ("neighborhoods", Json::array(neighborhoods)),
+            ("test_densities", error_rates(sparse_density as f64 / 100.0, dense_density as f64 / 100.0)),
+            ("local_error_rate", error_rates(causal_rate_sparse, causal_rate_dense)),
+            ("baseline_error_rate", error_rates(corr_rate_sparse, corr_rate_dense)),
+            (
+                "cross_validation",
+                cv.as_ref().map_or(Json::Null, |cv| {
+                    let folds = cv.local.len().into();
+                    Json::object([("folds", folds), ("local", accuracy(&cv.local)), ("baseline", accuracy(&cv.baseline))])
+                }),
+            ),
+        ]);
+        return print_report("infer", parameters, results);
+    }
+
+    println!("Rule inference test (true rule={rule}, width={width}, gens={generations}, noise={noise})");
+    println!("\nNeighborhood observations:");
+    println!("  NHD   Count   P(1)   Inferred   True");
+    println!("{}", "-".repeat(45));
+
+    for i in 0..8 {
+        let inferred_bit = (inferred_rule >> i) & 1;
+        let true_bit = (rule >> i) & 1;
+
+        let pattern = format!("{}{}{}", (i >> 2) & 1, (i >> 1) & 1, i & 1);
+        let match_mark = if inferred_bit == true_bit { "✓" } else { "✗" };
+        println!(
+            "  {}   {:>6}   {:.3}      {}          {} {}",
+            pattern, counts.observations[i], counts.p_one(i), inferred_bit, true_bit, match_mark
+        );
+    }
+
+    println!("{}", "-".repeat(45));
+    println!("Inferred rule: {}", inferred_rule);
+    println!("True rule:     {}", rule);
+    println!("Match:         {}", if inferred_rule == rule { "EXACT" } else { "MISMATCH" });
+
+    println!("\nGeneralization test (biased initial conditions):");
+    println!("  Sparse ({}% density): {:.4}% error rate", sparse_density, causal_rate_sparse * 100.0);
+    println!("  Dense ({}% density):  {:.4}% error rate", dense_density, causal_rate_dense * 100.0);
+
+    println!("\nCorrelational baseline (global features only):");
+    println!("  Sparse ({}% density): {:.2}% error rate", sparse_density, corr_rate_sparse * 100.0);
+    println!("  Dense ({}% density):  {:.2}% error rate", dense_density, corr_rate_dense * 100.0);
+
+    println!("\nComparison (OOD generalization):");
+    println!("  {:20} {:>10} {:>10}", "Learner", "Sparse", "Dense");
+    println!("  {:20} {:>9.2}% {:>9.2}%", "Local (causal)", causal_rate_sparse * 100.0, causal_rate_dense * 100.0);
+    println!("  {:20} {:>9.2}% {:>9.2}%", "Global (correlational)", corr_rate_sparse * 100.0, corr_rate_dense * 100.0);
+
+    if let Some(cv) = cv {
+        println!("\nCross-validation ({}-fold, held-out one-step accuracy):", cv.local.len());
+        println!("  {:20} {:>10} {:>10}", "Learner", "Mean", "Std dev");
+        for (name, scores) in [("Local (causal)", &cv.local), ("Global (correlational)", &cv.baseline)] {
+            let (mean, std) = mean_std(scores);
+            println!("  {:20} {:>9.2}% {:>9.2}%", name, mean * 100.0, std * 100.0);
+        }
+        if noise > 0.0 {
+            println!("  (noise ceiling: {:.2}%)", (1.0 - noise) * 100.0);
+        }
+    }
+
+    if inferred_rule == rule {
+        println!("\n→ Rule recovery successful: learned causal mechanism, not just correlations.");
+    } else {
+        println!("\n→ Rule recovery failed: noise or insufficient data prevented causal learning.");
+    }
+}
+
+#[derive(Args, Clone, Copy)]
+pub struct RadiusArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 110, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 50, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 20)]
+    pub generations: usize,
+    /// Largest radius to test
+    #[arg(short = 'r', long, default_value_t = 4)]
+    pub max_radius: usize,
+    /// Probability of flipping each observed outcome
+    #[arg(short, long, default_value_t = 0.0)]
+    pub noise: f64,
+    /// Fraction of observations a radius may get wrong and still count as consistent
+    #[arg(short = 't', long, default_value_t = 0.0)]
+    pub noise_tolerance: f64,
+    /// Print the report as a table or as JSON
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+/// How well one candidate radius explains the observations
+struct RadiusTest {
+    /// Distinct windows seen
+    windows: usize,
+    /// Windows seen with both outcomes: (window, zeros, ones)
+    inconsistent: Vec<(u64, usize, usize)>,
+    errors: usize,
+    total: usize,
+    consistent: bool,
+}
+
+pub fn radius(args: &RadiusArgs) {
+    let RadiusArgs { rule, width, generations, max_radius, noise, noise_tolerance, format } = *args;
+    // Infer the radius (locality) of a rule from observations alone
+    // Key question: can we discover that ECAs use 3-cell neighborhoods?
+
+    // Generate observations from random initial conditions
+    let num_trials = 10;
+    let data = TransitionDataset::training(rule, width, generations, num_trials, noise);
+
+    // For each candidate radius, check if the mapping is consistent: noise puts
+    // a floor under the errors of even the true radius, so allow a fraction of
+    // observations to disagree with their window's majority. Stop at the first
+    // consistent one, the minimal radius
+    let mut tests: Vec<RadiusTest> = Vec::new();
+    for r in 0..=max_radius {
+        let mapping = data.window_counts(r); // window -> (count_0, count_1)
+        let (errors, total) = data.majority_errors(r);
+
+        // Check consistency: each window should map to only one output
+        let inconsistent = mapping
+            .iter()
+            .filter(|(_, (zeros, ones))| *zeros > 0 && *ones > 0)
+            .map(|(&window, &(zeros, ones))| (window, zeros, ones))
+            .collect();
+        let consistent = errors as f64 / total as f64 <= noise_tolerance;
+        tests.push(RadiusTest { windows: mapping.len(), inconsistent, errors, total, consistent });
+        if consistent {
+            break;
+        }
+    }
+    let inferred = tests.last().filter(|test| test.consistent).map(|_| tests.len() - 1);
+    // Confidence that a smaller radius won't do: how unlikely radius r - 1's
+    // errors would be if its true error rate were the tolerance
+    let too_small_p = inferred
+        .filter(|&r| r > 0)
+        .map(|r| binomial_upper_tail(tests[r - 1].errors, tests[r].total, noise_tolerance));
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("max_radius", max_radius.into()),
+            ("noise", noise.into()),
+            ("noise_tolerance", noise_tolerance.into()),
+            ("trials", num_trials.into()),
+        ]);
+        let radii = tests.iter().enumerate().map(|(r, test)| {
+            Json::object([
+                ("radius", r.into()),
+                ("windows_observed", test.windows.into()),
+                ("windows_possible", (1usize << (2 * r + 1)).into()),
+                ("inconsistent_windows", test.inconsistent.len().into()),
+                ("errors", test.errors.into()),
+                ("observations", test.total.into()),
+                ("consistent", test.consistent.into()),
+            ])
+        });
+        let results = Json::object([
+            ("transitions", data.len().into()),
+            ("radii", Json::array(radii)),
+            ("inferred_radius", inferred.into()),
+            ("smaller_radius_p_value", too_small_p.into()),
+        ]);
+        return print_report("radius", parameters, results);
+    }
+
+    println!("Radius inference (true rule={rule}, width={width}, gens={generations}, noise={noise})");
+    println!("Testing radii 0 to {max_radius}, tolerating {:.1}% majority-vote errors...\n", noise_tolerance * 100.0);
+    println!("Collected {} row transitions\n", data.len());
+
+    for (r, test) in tests.iter().enumerate() {
+        let window_size = 2 * r + 1;
+        let RadiusTest { windows: total_windows, ref inconsistent, errors, total, consistent } = *test;
+        let error_rate = errors as f64 / total as f64;
+        let consistency_rate = (total_windows - inconsistent.len()) as f64 / total_windows as f64;
+
+        println!("Radius {r} (window size {window_size}):");
+        println!("  Unique windows observed: {total_windows} / {} possible", 1usize << window_size);
+        println!("  Windows with a single outcome: {:.1}%", consistency_rate * 100.0);
+        println!("  Majority-vote errors: {errors} / {total} ({:.2}%)", error_rate * 100.0);
+        println!("  Consistent: {}", if consistent { "YES" } else { "NO" });
+
+        if !inconsistent.is_empty() && !consistent {
+            println!("  Inconsistent windows: {} (examples below)", inconsistent.len());
+            for (window, zeros, ones) in inconsistent.iter().take(3) {
+                println!("    {:0width$b} -> 0 ({} times), 1 ({} times)", window, zeros, ones, width = window_size);
+            }
+        }
+
+        println!();
+    }
+
+    // If consistent, we found the minimal radius
+    if let Some(r) = inferred {
+        println!("→ Inferred radius: {r}");
+        match too_small_p {
+            Some(p) => println!("  Confidence radius {} is too small: {:.4} (binomial p={p:.2e})", r - 1, 1.0 - p),
+            None => println!("  Confidence: radius 0 is the smallest possible"),
+        }
+        println!("  (True ECA radius is 1)");
+        if r == 1 {
+            println!("  SUCCESS: Correctly identified 3-cell neighborhood");
+        } else if r < 1 {
+            println!("  INTERESTING: Rule has effective radius < 1 (some neighbors don't matter)");
+        } else {
+            println!("  NOTE: Found consistent at r={r}, but r=1 should suffice for ECAs");
+        }
+        return;
+    }
+
+    println!("→ No radius up to {max_radius} is consistent");
+    if noise > noise_tolerance {
+        println!("  Noise exceeds the tolerance; try --noise-tolerance above {noise}");
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct RadiusSurveyArgs {
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 50, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 20)]
+    pub generations: usize,
+    #[command(flatten)]
+    pub output: SurveyOutput,
+    #[command(flatten)]
+    pub checkpoint: CheckpointArgs,
+}
+
+pub fn radius_survey(args: &RadiusSurveyArgs) {
+    let RadiusSurveyArgs { width, generations, ref output, ref checkpoint } = *args;
+    // Survey all 256 rules for their effective radius
+    let max_radius: usize = 2; // ECAs can't have radius > 1, but let's verify
+
+    if output.table() {
+        println!("Radius survey (width={width}, gens={generations})");
+        println!("Finding effective radius for {}...\n", checkpoint.describe());
+    }
+
+    let mut radius_counts = [0usize; 3]; // count rules with effective radius 0, 1, 2+
+    let mut radius_0_rules = Vec::new();
+    let mut radius_gt1_rules = Vec::new();
+
+    let rules = checkpoint.rules();
+    let settings = format!("radius-survey width={width} gens={generations}");
+    let radii: Vec<usize> = checkpoint.survey(&settings, |rule| {
+        // Generate observations
+        let num_trials = 5;
+        let data = TransitionDataset::training(rule, width, generations, num_trials, 0.0);
+
+        // Find minimal consistent radius
+        (0..=max_radius)
+            .find(|&r| data.consistent_at_radius(r))
+            .unwrap_or(max_radius + 1)
+    });
+    // Radius max_radius + 1 means none up to max_radius was consistent
+    let csv = || {
+        std::iter::once("rule,canonical,effective_radius\n".to_string())
+            .chain(rules.iter().copied().zip(&radii).map(|(rule, radius)| {
+                format!("{rule},{},{radius}\n", Rule(rule).canonical())
+            }))
+            .collect()
+    };
+    let json = || {
+        let rules = rules.iter().copied().zip(&radii).map(|(rule, &radius)| {
+            Json::object([
+                ("rule", rule.into()),
+                ("canonical", Rule(rule).canonical().0.into()),
+                ("effective_radius", (radius <= max_radius).then_some(radius).into()),
+            ])
+        });
+        let parameters =
+            Json::object([("width", width.into()), ("generations", generations.into()), ("max_radius", max_radius.into())]);
+        report("radius-survey", parameters, Json::object([("rules", Json::array(rules))]))
+    };
+    let columns = || {
+        let found = radii.iter().map(|&radius| (radius <= max_radius).then_some(radius));
+        vec![
+            Column::required("rule", rules.clone()),
+            Column::required("canonical", rules.iter().map(|&rule| Rule(rule).canonical().0).collect::<Vec<_>>()),
+            Column::optional("effective_radius", found.collect::<Vec<_>>()),
+        ]
+    };
+    if !output.table() {
+        return output.write(csv, json, columns);
+    }
+
+    for (rule, &effective_radius) in rules.iter().copied().zip(&radii) {
+
+        // Categorize
+        if effective_radius == 0 {
+            radius_counts[0] += 1;
+            radius_0_rules.push(rule);
+        } else if effective_radius == 1 {
+            radius_counts[1] += 1;
+        } else {
+            radius_counts[2] += 1;
+            radius_gt1_rules.push(rule);
+        }
+    }
+
+    println!("Results:");
+    println!("  Effective radius 0: {} rules", radius_counts[0]);
+    println!("  Effective radius 1: {} rules", radius_counts[1]);
+    println!("  Effective radius >1: {} rules (unexpected!)", radius_counts[2]);
+
+    println!("\nRules with effective radius 0 (neighbors don't matter):");
+    for chunk in radius_0_rules.chunks(16) {
+        let s: String = chunk.iter().map(|r| format!("{:>4}", r)).collect::<Vec<_>>().join("");
+        println!("  {}", s);
+    }
+
+    if !radius_gt1_rules.is_empty() {
+        println!("\nRules with effective radius >1 (unexpected for ECAs):");
+        for r in &radius_gt1_rules {
+            println!("  Rule {}", r);
+        }
+    }
+
+    // Analyze what makes radius-0 rules special
+    println!("\nAnalysis of radius-0 rules:");
+    println!("These rules have output that depends only on the center cell.");
+    println!("Checking: does output = f(center) for some f?");
+
+    for &rule in &radius_0_rules {
+        // Check what the rule does for center=0 and center=1
+        let mut center_0_outputs = Vec::new();
+        let mut center_1_outputs = Vec::new();
+
+        for neighborhood in 0..8u8 {
+            let center = (neighborhood >> 1) & 1;
+            let output = (rule >> neighborhood) & 1;
+
+            if center == 0 {
+                center_0_outputs.push(output);
+            } else {
+                center_1_outputs.push(output);
+            }
+        }
+
+        let f_of_0 = if center_0_outputs.iter().all(|&x| x == 0) {
+            "0"
+        } else if center_0_outputs.iter().all(|&x| x == 1) {
+            "1"
+        } else {
+            "?"
+        };
+
+        let f_of_1 = if center_1_outputs.iter().all(|&x| x == 0) {
+            "0"
+        } else if center_1_outputs.iter().all(|&x| x == 1) {
+            "1"
+        } else {
+            "?"
+        };
+
+        if f_of_0 != "?" && f_of_1 != "?" {
+            let name = if f_of_0 == "0" && f_of_1 == "0" {
+                "constant 0"
+            } else if f_of_0 == "1" && f_of_1 == "1" {
+                "constant 1"
+            } else if f_of_0 == "0" && f_of_1 == "1" {
+                "identity"
+            } else {
+                "NOT"
+            };
+            println!("  Rule {:>3}: f(0)={}, f(1)={} ({})", rule, f_of_0, f_of_1, name);
+        }
+    }
+    output.write(csv, json, columns);
+}
+
+#[derive(Args, Clone, Copy)]
+pub struct DependencyArgs {
+    /// Print the report as a table or as JSON
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+pub fn dependency(args: &DependencyArgs) {
+    let DependencyArgs { format } = *args;
+    // Analyze which positions in the neighborhood actually matter
+    // For each rule, determine: does output depend on left? center? right?
+
+    let mut dependencies = Vec::with_capacity(256);
+    let mut dep_counts: std::collections::HashMap<(bool, bool, bool), Vec<u8>> =
+        std::collections::HashMap::new();
+
+    for rule in 0..=255u8 {
+        // For each position, check if changing it ever changes the output
+        // while holding other positions fixed
+
+        // Left matters if there exist (c,r) such that rule(0,c,r) ≠ rule(1,c,r)
+        let left_matters = (0..4).any(|cr| {
+            let c = (cr >> 1) & 1;
+            let r = cr & 1;
+            let n0 = (0 << 2) | (c << 1) | r; // left=0
+            let n1 = (1 << 2) | (c << 1) | r; // left=1
+            ((rule >> n0) & 1) != ((rule >> n1) & 1)
+        });
+
+        // Center matters if there exist (l,r) such that rule(l,0,r) ≠ rule(l,1,r)
+        let center_matters = (0..4).any(|lr| {
+            let l = (lr >> 1) & 1;
+            let r = lr & 1;
+            let n0 = (l << 2) | (0 << 1) | r; // center=0
+            let n1 = (l << 2) | (1 << 1) | r; // center=1
+            ((rule >> n0) & 1) != ((rule >> n1) & 1)
+        });
+
+        // Right matters if there exist (l,c) such that rule(l,c,0) ≠ rule(l,c,1)
+        let right_matters = (0..4).any(|lc| {
+            let l = (lc >> 1) & 1;
+            let c = lc & 1;
+            let n0 = (l << 2) | (c << 1) | 0; // right=0
+            let n1 = (l << 2) | (c << 1) | 1; // right=1
+            ((rule >> n0) & 1) != ((rule >> n1) & 1)
+        });
+
+        dep_counts
+            .entry((left_matters, center_matters, right_matters))
+            .or_default()
+            .push(rule);
+        dependencies.push((left_matters, center_matters, right_matters));
+    }
+
+    if format == ReportFormat::Json {
+        let rules = (0..=255u8).zip(&dependencies).map(|(rule, &(left, center, right))| {
+            let function = (left && !center && right).then(|| lr_function(rule).0);
+            Json::object([
+                ("rule", rule.into()),
+                ("left", left.into()),
+                ("center", center.into()),
+                ("right", right.into()),
+                ("function_of_left_right", function.into()),
+            ])
+        });
+        return print_report("dependency", Json::object([]), Json::object([("rules", Json::array(rules))]));
+    }
+
+    println!("Dependency analysis for all 256 rules");
+    println!("Checking which neighborhood positions are necessary...\n");
+
+    // Print results by dependency pattern
+    let patterns = [
+        ((false, false, false), "none (constant)"),
+        ((false, true, false), "center only"),
+        ((true, false, false), "left only"),
+        ((false, false, true), "right only"),
+        ((true, true, false), "left + center"),
+        ((false, true, true), "center + right"),
+        ((true, false, true), "left + right (symmetric)"),
+        ((true, true, true), "all three"),
+    ];
+
+    for ((l, c, r), name) in patterns {
+        if let Some(rules) = dep_counts.get(&(l, c, r)) {
+            println!("{}: {} rules", name, rules.len());
+            if rules.len() <= 16 {
+                for chunk in rules.chunks(8) {
+                    let s: String = chunk.iter().map(|r| format!("{:>4}", r)).collect::<Vec<_>>().join("");
+                    println!("    {}", s);
+                }
+            } else {
+                println!("    (first 8: {:?}...)", &rules[..8]);
+            }
+            println!();
+        }
+    }
+
+    // Interesting follow-up: for "left + right" rules (ignoring center),
+    // what Boolean functions of (left, right) do they implement?
+    println!("Analysis of center-ignoring rules (left + right only):");
+    if let Some(rules) = dep_counts.get(&(true, false, true)) {
+        for &rule in rules {
+            let (f, func_name) = lr_function(rule);
+            println!("  Rule {:>3}: f(l,r) = {} ({})", rule, f, func_name);
+        }
+    }
+}
+
+/// Truth table of a center-ignoring rule as a function of (left, right),
+/// outputs for lr = 00, 01, 10, 11, and its name
+fn lr_function(rule: u8) -> (String, &'static str) {
+    // The rule is a function of (left, right) only
+    // For each (l, r), what's the output?
+    let mut f = String::new();
+    for lr in 0..4 {
+        let l = (lr >> 1) & 1;
+        let r = lr & 1;
+        // Output should be same for both center values
+        let n0 = (l << 2) | (0 << 1) | r;
+        let out = (rule >> n0) & 1;
+        f.push(if out == 1 { '1' } else { '0' });
+    }
+    let func_name = match f.as_str() {
+        "0000" => "FALSE",
+        "1111" => "TRUE",
+        "0001" => "NOR",
+        "0010" => "l AND NOT r",
+        "0011" => "NOT r",
+        "0100" => "NOT l AND r",
+        "0101" => "NOT l",
+        "0110" => "XOR",
+        "0111" => "NAND",
+        "1000" => "AND",
+        "1001" => "XNOR",
+        "1010" => "l",
+        "1011" => "l OR NOT r",
+        "1100" => "r",
+        "1101" => "NOT l OR r",
+        "1110" => "OR",
+        _ => "?",
+    };
+    (f, func_name)
+}
+
+#[derive(Args, Clone, Copy)]
+pub struct DependencyInferArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 90, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 50, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 30)]
+    pub generations: usize,
+    /// Probability of flipping each observed outcome
+    #[arg(short, long, default_value_t = 0.0)]
+    pub noise: f64,
+    /// Significance level for declaring that a position matters
+    #[arg(short, long, default_value_t = 0.01)]
+    pub alpha: f64,
+    /// Print the report as a table or as JSON
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+/// The neighborhood positions dependency-infer tests: title, name, bit in
+/// the neighborhood index, and the two held fixed
+const POSITIONS: [(&str, &str, usize, [&str; 2]); 3] =
+    [("LEFT", "left", 2, ["c", "r"]), ("CENTER", "center", 1, ["l", "r"]), ("RIGHT", "right", 0, ["l", "c"])];
+
+/// Trajectories dependency-infer observes
+const DEPENDENCY_TRIALS: usize = 10;
+
+/// What the observations say about each position, left to right
+struct DependencyInference {
+    transitions: usize,
+    /// Per-context significance level, after the Bonferroni correction
+    threshold: f64,
+    /// The four contexts of the other two positions, for each position
+    tests: [[PositionTest; 4]; 3],
+    matters: [bool; 3],
+    /// Not significant anywhere, but with too few samples to rule out
+    undetermined: [bool; 3],
+}
+
+impl DependencyInference {
+    fn new(args: &DependencyInferArgs) -> Self {
+        let DependencyInferArgs { rule, width, generations, noise, alpha, .. } = *args;
+        let data = TransitionDataset::training(rule, width, generations, DEPENDENCY_TRIALS, noise);
+
+        // For each position (left, center, right), hold the other two fixed and test
+        // whether this position's value changes the distribution of outcomes. A
+        // position matters if any of its four contexts is significant after a
+        // Bonferroni correction; too few samples leave it undetermined. The exact
+        // test stays valid on small samples, so significance counts; only a
+        // failure to reject needs enough data behind it
+        let counts = data.neighborhood_counts();
+        let threshold = alpha / 4.0;
+        let tests = POSITIONS.map(|(_, _, bit, _)| counts.position_tests(bit));
+        let matters = tests.map(|tests| tests.iter().any(|test| test.p_value < threshold));
+        let undetermined = [0, 1, 2].map(|p| !matters[p] && tests[p].iter().any(|test| !test.enough_samples()));
+        DependencyInference { transitions: data.len(), threshold, tests, matters, undetermined }
+    }
+
+    fn names(flags: [bool; 3]) -> Vec<&'static str> {
+        POSITIONS.iter().zip(flags).filter(|&(_, flag)| flag).map(|(position, _)| position.1).collect()
+    }
+
+    fn report(&self, args: &DependencyInferArgs) -> Json {
+        let DependencyInferArgs { rule, width, generations, noise, alpha, .. } = *args;
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("noise", noise.into()),
+            ("alpha", alpha.into()),
+            ("trials", DEPENDENCY_TRIALS.into()),
+        ]);
+        let positions = POSITIONS.iter().enumerate().map(|(p, &(_, name, _, [first, second]))| {
+            let contexts = self.tests[p].iter().enumerate().map(|(context, test)| {
+                let side =
+                    |(n, ones): (usize, usize)| Json::object([("observations", n.into()), ("ones", ones.into())]);
+                Json::object([
+                    ("fixed", Json::object([(first, (context >> 1).into()), (second, (context & 1).into())])),
+                    ("dead", side(test.dead)),
+                    ("live", side(test.live)),
+                    ("p_value", test.p_value.into()),
+                    ("enough_samples", test.enough_samples().into()),
+                ])
+            });
+            Json::object([
+                ("position", name.into()),
+                ("matters", self.matters[p].into()),
+                ("undetermined", self.undetermined[p].into()),
+                ("contexts", Json::array(contexts)),
+            ])
+        });
+        let truth = rule_dependencies(rule);
+        let results = Json::object([
+            ("transitions", self.transitions.into()),
+            ("threshold", self.threshold.into()),
+            ("positions", Json::array(positions)),
+            ("dependencies", Self::names(self.matters).into()),
+            ("undetermined", Self::names(self.undetermined).into()),
+            ("true_dependencies", Self::names(truth).into()),
+            ("match", (self.matters == truth).into()),
+        ]);
+        report("dependency-infer", parameters, results)
+    }
+}
+
+pub fn dependency_infer(args: &DependencyInferArgs) {
+    let DependencyInferArgs { rule, noise, alpha, format, .. } = *args;
+    // Infer which neighborhood positions matter from observations alone
+    // (Statistical inference vs. direct rule analysis)
+    let inference = DependencyInference::new(args);
+    if format == ReportFormat::Json {
+        return super::sink::emit(inference.report(args));
+    }
+
+    println!("Dependency inference from observations (rule={rule}, noise={noise})");
+    println!("(Not examining rule directly—only observing behavior)\n");
+    println!("Collected {} transitions\n", inference.transitions);
+    let threshold = inference.threshold;
+    println!("Fisher exact test per context; significant at p < {threshold} (alpha={alpha}, Bonferroni over 4)\n");
+
+    for (p, &(title, name, _, [first, second])) in POSITIONS.iter().enumerate() {
+        println!("Testing whether {title} matters:");
+        for (context, test) in inference.tests[p].iter().enumerate() {
+            let (dead_n, dead_ones) = test.dead;
+            let (live_n, live_ones) = test.live;
+            let verdict = if test.p_value < threshold {
+                "DIFFERENT".to_string()
+            } else if !test.enough_samples() {
+                format!("too few samples (need {} each side)", PositionTest::MIN_SAMPLES)
+            } else {
+                "no evidence".to_string()
+            };
+            println!("  At ({first}={}, {second}={}): {name}=0 → {dead_ones}/{dead_n} ones, {name}=1 → {live_ones}/{live_n} ones, p={:.2e} — {verdict}",
+                context >> 1, context & 1, test.p_value);
+        }
+        if inference.matters[p] {
+            // Significant somewhere: the position matters whatever the thin contexts say
+        } else if inference.undetermined[p] {
+            println!("  WARNING: not enough data to rule {title} out — UNDETERMINED");
+        } else {
+            println!("  No significant differences — {title} does NOT matter");
+        }
+        println!();
+    }
+
+    // Summary
+    let deps = DependencyInference::names(inference.matters);
+    let unknown = DependencyInference::names(inference.undetermined);
+
+    if deps.is_empty() && unknown.is_empty() {
+        println!("→ Inferred: CONSTANT rule (no dependencies)");
+    } else if deps.is_empty() {
+        println!("→ Inferred dependencies: none significant");
+    } else {
+        println!("→ Inferred dependencies: {}", deps.join(" + "));
+    }
+    if !unknown.is_empty() {
+        println!("→ Undetermined (insufficient data): {}", unknown.join(", "));
+    }
+
+    // Compare with ground truth
+    println!("\nGround truth (from rule {rule} = 0b{:08b}):", rule);
+    let truth = rule_dependencies(rule);
+    let true_deps = DependencyInference::names(truth);
+
+    if true_deps.is_empty() {
+        println!("  True dependencies: CONSTANT");
+    } else {
+        println!("  True dependencies: {}", true_deps.join(" + "));
+    }
+
+    println!("  Match: {}", if inference.matters == truth { "YES" } else { "NO" });
+}
+
+#[derive(Args, Clone, Copy)]
+pub struct BudgetArgs {
+    /// Show one rule's curve instead of the average over all 256
+    #[arg(value_parser = parse_rule)]
+    pub rule: Option<u8>,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 20, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations per trajectory
+    #[arg(short = 'g', long = "gens", default_value_t = 16)]
+    pub generations: usize,
+    /// Trajectories in the full dataset
+    #[arg(short, long, default_value_t = 16)]
+    pub trials: usize,
+    /// Probability of flipping each observed outcome
+    #[arg(short, long, default_value_t = 0.0)]
+    pub noise: f64,
+    /// Majority-vote error fraction tolerated by radius inference
+    #[arg(long, default_value_t = 0.0)]
+    pub noise_tolerance: f64,
+    /// Significance level for dependency inference
+    #[arg(short, long, default_value_t = 0.01)]
+    pub alpha: f64,
+    /// Print the report as a table or as JSON
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+/// How well each inference method did at one observation budget
+#[derive(Clone, Copy, Default)]
+struct BudgetScore {
+    /// Whole rule recovered
+    rule: f64,
+    /// Fraction of the 8 rule-table entries recovered
+    table: f64,
+    /// Minimal consistent radius equals the true effective radius
+    radius: f64,
+    /// Significant positions equal the true dependencies
+    dependencies: f64,
+}
+
+/// Reads one method's score out of a BudgetScore
+type Score = fn(&BudgetScore) -> f64;
+
+pub fn budget(args: &BudgetArgs) {
+    let BudgetArgs { rule, width, generations, trials, noise, noise_tolerance, alpha, format } = *args;
+    // Repeat rule, radius and dependency inference on growing prefixes of one
+    // dataset: how many observations does each method need?
+
+    // Row transitions per budget: doubling, ending at the full dataset
+    let full = trials * generations;
+    let mut budgets: Vec<usize> = std::iter::successors(Some(1), |&b| Some(b * 2)).take_while(|&b| b < full).collect();
+    budgets.push(full);
+
+    let rules: Vec<u8> = match rule {
+        Some(rule) => vec![rule],
+        None => (0..=255).collect(),
+    };
+
+    let curves: Vec<Vec<BudgetScore>> = rules
+        .par_iter()
+        .map(|&rule| {
+            let data = TransitionDataset::training(rule, width, generations, trials, noise);
+            let [left, _, right] = rule_dependencies(rule);
+            let true_radius = if left || right { 1 } else { 0 };
+
+            budgets
+                .iter()
+                .map(|&b| {
+                    let prefix = data.truncated(b);
+                    let counts = prefix.neighborhood_counts();
+                    let inferred = counts.infer_rule();
+                    BudgetScore {
+                        rule: (inferred == rule) as u8 as f64,
+                        table: (8 - (inferred ^ rule).count_ones()) as f64 / 8.0,
+                        radius: (prefix.min_radius(2, noise_tolerance) == Some(true_radius)) as u8 as f64,
+                        dependencies: (counts.dependencies(alpha) == rule_dependencies(rule)) as u8 as f64,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    let mean = |i: usize, score: Score| {
+        curves.iter().map(|c| score(&c[i])).sum::<f64>() / curves.len() as f64
+    };
+    // Smallest budget from which each method stays perfect, or how many
+    // rules it never settles for
+    let settled = |score: Score| -> Result<usize, usize> {
+        // Per rule: index of the first budget after its last miss
+        let first: Vec<usize> = curves
+            .iter()
+            .map(|curve| curve.iter().rposition(|s| score(s) < 1.0).map_or(0, |i| i + 1))
+            .collect();
+        match first.iter().filter(|&&i| i == budgets.len()).count() {
+            0 => Ok(budgets[*first.iter().max().unwrap()]),
+            unsettled => Err(unsettled),
+        }
+    };
+    let methods: [(&str, Score); 3] =
+        [("rule", |s| s.rule), ("radius", |s| s.radius), ("dependencies", |s| s.dependencies)];
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("trials", trials.into()),
+            ("noise", noise.into()),
+            ("noise_tolerance", noise_tolerance.into()),
+            ("alpha", alpha.into()),
+        ]);
+        let points = budgets.iter().enumerate().map(|(i, &b)| {
+            Json::object([
+                ("rows", b.into()),
+                ("cells", (b * width).into()),
+                ("rule", mean(i, |s| s.rule).into()),
+                ("table", mean(i, |s| s.table).into()),
+                ("radius", mean(i, |s| s.radius).into()),
+                ("dependencies", mean(i, |s| s.dependencies).into()),
+            ])
+        });
+        // Rows needed to stay correct, or null with the rules that never do
+        let needed = methods.map(|(name, score)| {
+            let settled = settled(score);
+            let unsettled = settled.err().unwrap_or(0);
+            (name, Json::object([("rows", settled.ok().into()), ("unsettled_rules", unsettled.into())]))
+        });
+        let results = Json::object([("budgets", Json::array(points)), ("rows_needed", Json::object(needed))]);
+        return print_report("budget", parameters, results);
+    }
+
+    match rule {
+        Some(rule) => println!("Observation budget for rule {rule}"),
+        None => println!("Observation budget, averaged over all 256 rules"),
+    }
+    println!("(width={width}, gens={generations}, trials={trials}, noise={noise}, tolerance={noise_tolerance}, alpha={alpha})\n");
+    println!("{:>6} {:>8} {:>7} {:>7} {:>7} {:>7}", "Rows", "Cells", "Rule", "Table", "Radius", "Deps");
+    println!("{}", "-".repeat(48));
+    for (i, &b) in budgets.iter().enumerate() {
+        println!(
+            "{:>6} {:>8} {:>7.3} {:>7.3} {:>7.3} {:>7.3}",
+            b,
+            b * width,
+            mean(i, |s| s.rule),
+            mean(i, |s| s.table),
+            mean(i, |s| s.radius),
+            mean(i, |s| s.dependencies)
+        );
+    }
+
+    println!("{}", "-".repeat(48));
+    if rules.len() == 1 {
+        println!("Rows needed to stay correct:");
+    } else {
+        println!("Rows needed for every rule to stay correct:");
+    }
+    for ((_, score), label) in methods.into_iter().zip(["Rule table:  ", "Radius:      ", "Dependencies:"]) {
+        match settled(score) {
+            Ok(rows) => println!("  {label} {rows} rows"),
+            Err(unsettled) => println!("  {label} not reached for {unsettled} rule(s)"),
+        }
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct IdentifyArgs {
+    /// Rule to simulate when no file is given (0-255)
+    #[arg(default_value_t = 110, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Read observed spacetime diagrams instead: one row per line, `#`/`1`
+    /// live and ` `/`.`/`0` dead, blank lines between trajectories
+    #[arg(short, long)]
+    pub file: Option<PathBuf>,
+    /// Number of cells in the ring (simulation)
+    #[arg(short, long, default_value_t = 50, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations per trajectory (simulation)
+    #[arg(short = 'g', long = "gens", default_value_t = 20)]
+    pub generations: usize,
+    /// Trajectories to simulate
+    #[arg(short, long, default_value_t = 10)]
+    pub trials: usize,
+    /// Probability of flipping each observed outcome (simulation)
+    #[arg(short, long, default_value_t = 0.0)]
+    pub noise: f64,
+    /// Significance level for the radius and dependency tests
+    #[arg(short, long, default_value_t = 0.01)]
+    pub alpha: f64,
+    /// Print the report as a table or as JSON
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+/// Everything identify works out from a nonempty set of transitions
+struct Identification {
+    /// Disagreements with the radius-1 majority, of all cell updates
+    floor_errors: usize,
+    total: usize,
+    /// Majority-vote errors at radius 0, 1 and 2
+    radius_errors: [(usize, usize); 3],
+    /// Binomial p-value of radius 0's errors against the noise floor
+    p_zero: f64,
+    radius: usize,
+    /// Per position: smallest p-value, whether it matters, and whether too
+    /// few samples leave it open
+    dependencies: [(f64, bool, bool); 3],
+    counts: NeighborhoodCounts,
+    inferred: u8,
+    /// Rule-table entries less than 95% confident
+    uncertain: Vec<usize>,
+    /// Normalized entropy mean and std, and their class
+    entropy: (f64, f64, &'static str),
+    growth: GrowthFit,
+}
+
+impl Identification {
+    fn new(data: &TransitionDataset, alpha: f64) -> Self {
+        // 1. Noise: an ECA is a radius-1 function, so disagreements with the
+        // radius-1 majority are noise (or under-sampling)
+        let (floor_errors, total) = data.majority_errors(1);
+        let noise_rate = floor_errors as f64 / total as f64;
+
+        // 2. Radius: the smallest radius whose errors don't significantly exceed the noise floor
+        let radius_errors = [0, 1, 2].map(|r| data.majority_errors(r));
+        let p_zero = binomial_upper_tail(radius_errors[0].0, total, noise_rate);
+        let radius = if p_zero < alpha { 1 } else { 0 };
+
+        // 3. Dependencies
+        let counts = data.neighborhood_counts();
+        let matters = counts.dependencies(alpha);
+        let dependencies = [0, 1, 2].map(|p| {
+            let tests = counts.position_tests(2 - p);
+            let p_min = tests.iter().map(|t| t.p_value).fold(1.0, f64::min);
+            (p_min, matters[p], !matters[p] && tests.iter().any(|t| !t.enough_samples()))
+        });
+
+        // 4. Rule table, with the posterior confidence in each entry
+        let inferred = counts.infer_rule();
+        let uncertain = (0..8).rev().filter(|&index| counts.majority_confidence(index) < 0.95).collect();
+
+        // 5. What kind of rule is it?
+        let (mean, std_dev) = entropy_signature(inferred, 79, 100, 3);
+        let (_, class) = entropy_class(mean / 3.0, std_dev / 3.0);
+        let growth = block_complexity(inferred, 201, 200, 12, 1, 50);
+        let growth = fit_growth(growth.final_counts(), growth.final_samples());
+        Identification {
+            floor_errors,
+            total,
+            radius_errors,
+            p_zero,
+            radius,
+            dependencies,
+            counts,
+            inferred,
+            uncertain,
+            entropy: (mean / 3.0, std_dev / 3.0, class),
+            growth,
+        }
+    }
+
+    fn results(&self) -> [(&'static str, Json); 6] {
+        let (low, high) = wilson_interval(self.floor_errors, self.total);
+        let noise = Json::object([
+            ("errors", self.floor_errors.into()),
+            ("observations", self.total.into()),
+            ("rate", (self.floor_errors as f64 / self.total as f64).into()),
+            ("ci_low", low.into()),
+            ("ci_high", high.into()),
+        ]);
+        let error_rate = |&(errors, n): &(usize, usize)| errors as f64 / n as f64;
+        let radius = Json::object([
+            ("error_rates", Json::array(self.radius_errors.iter().map(error_rate))),
+            ("radius_zero_p_value", self.p_zero.into()),
+            ("radius", self.radius.into()),
+        ]);
+        let dependencies = ["left", "center", "right"].into_iter().zip(self.dependencies).map(
+            |(name, (p_min, matters, undetermined))| {
+                Json::object([
+                    ("position", name.into()),
+                    ("min_p_value", p_min.into()),
+                    ("matters", matters.into()),
+                    ("undetermined", undetermined.into()),
+                ])
+            },
+        );
+        let counts = &self.counts;
+        let table = (0..8).rev().map(|index| {
+            let seen = counts.observations[index];
+            Json::object([
+                ("neighborhood", format!("{index:03b}").into()),
+                ("observations", seen.into()),
+                ("output", (seen > 0).then_some((self.inferred >> index) & 1).into()),
+                ("p_one", (seen > 0).then(|| counts.p_one(index)).into()),
+                ("confidence", counts.majority_confidence(index).into()),
+            ])
+        });
+        let table = Json::object([
+            ("entries", Json::array(table)),
+            ("uncertain", Json::array(self.uncertain.iter().map(|index| format!("{index:03b}")))),
+            ("candidates", (1usize << self.uncertain.len()).into()),
+        ]);
+        let (mean, std_dev, class) = self.entropy;
+        let classification = Json::object([
+            ("equivalents", wolfram_equivalents(self.inferred).into()),
+            ("entropy", Json::object([("class", class.into()), ("mean", mean.into()), ("std", std_dev.into())])),
+            ("block_complexity", Json::object(fit_json(&self.growth))),
+            ("interesting", INTERESTING_RULES.contains(&self.inferred).into()),
+        ]);
+        [
+            ("noise", noise),
+            ("radius", radius),
+            ("dependencies", Json::array(dependencies)),
+            ("rule_table", table),
+            ("inferred_rule", self.inferred.into()),
+            ("classification", classification),
+        ]
+    }
+}
+
+/// The identify report: the observations, what was found in them (if there
+/// were any) and, for a simulation, the truth
+fn identify_report(args: &IdentifyArgs, data: &TransitionDataset, truth: Option<u8>) -> Json {
+    let IdentifyArgs { rule, ref file, width, generations, trials, noise, alpha, .. } = *args;
+    let parameters = match file {
+        Some(path) => Json::object([("file", path.display().to_string().into()), ("alpha", alpha.into())]),
+        None => Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("trials", trials.into()),
+            ("noise", noise.into()),
+            ("alpha", alpha.into()),
+        ]),
+    };
+    let identification = (!data.is_empty()).then(|| Identification::new(data, alpha));
+    let inferred = identification.as_ref().map(|found| found.inferred);
+    let observed = [
+        ("transitions", data.len().into()),
+        ("width", data.width().into()),
+        ("cell_observations", (data.len() * data.width()).into()),
+    ];
+    let found = identification.iter().flat_map(Identification::results);
+    let truth = [
+        ("true_rule", truth.into()),
+        ("match", truth.zip(inferred).map(|(truth, inferred)| truth == inferred).into()),
+    ];
+    report("identify", parameters, Json::object(observed.into_iter().chain(found).chain(truth)))
+}
+
+pub fn identify(args: &IdentifyArgs) {
+    let IdentifyArgs { rule, ref file, width, generations, trials, noise, alpha, format } = *args;
+    // The full pipeline on observations alone: noise, radius, dependencies,
+    // rule table, then what kind of rule it is
+
+    let (data, truth) = match file {
+        Some(path) => match read_spacetime(path) {
+            Ok(trajectories) => (TransitionDataset::from_trajectories(trajectories), None),
+            Err(e) => {
+                eprintln!("Can't read {}: {e}", path.display());
+                std::process::exit(1);
+            }
+        },
+        None => (TransitionDataset::training(rule, width, generations, trials, noise), Some(rule)),
+    };
+    if format == ReportFormat::Json {
+        return super::sink::emit(identify_report(args, &data, truth));
+    }
+
+    match file {
+        Some(path) => println!("Identifying automaton from {}", path.display()),
+        None => println!("Identifying automaton from simulation (hidden rule, noise={noise})"),
+    }
+    let total_cells = data.len() * data.width();
+    println!("  {} row transitions of width {} ({total_cells} cell observations)\n", data.len(), data.width());
+    if data.is_empty() {
+        println!("No transitions to learn from");
+        return;
+    }
+    let found = Identification::new(&data, alpha);
+
+    println!("1. Noise estimate");
+    let (floor_errors, total) = (found.floor_errors, found.total);
+    let (low, high) = wilson_interval(floor_errors, total);
+    println!("  Radius-1 majority disagreements: {floor_errors} / {total} = {:.2}% (95% CI {:.2}%-{:.2}%)\n",
+        100.0 * floor_errors as f64 / total as f64, low * 100.0, high * 100.0);
+
+    println!("2. Radius");
+    for (r, (errors, n)) in found.radius_errors.into_iter().enumerate() {
+        println!("  r={r}: {:.2}% majority-vote errors", 100.0 * errors as f64 / n as f64);
+    }
+    println!("  → Radius {} (radius 0 vs. noise floor: binomial p={:.2e})\n", found.radius, found.p_zero);
+
+    println!("3. Dependencies (Fisher exact, alpha={alpha})");
+    for (name, (p_min, matters, undetermined)) in ["left", "center", "right"].into_iter().zip(found.dependencies) {
+        let verdict = if matters {
+            "MATTERS"
+        } else if undetermined {
+            "undetermined (too few samples)"
+        } else {
+            "does not matter"
+        };
+        println!("  {name:<6}: min p={p_min:.2e} — {verdict}");
+    }
+    println!();
+
+    println!("4. Rule table");
+    let (counts, inferred) = (&found.counts, found.inferred);
+    for index in (0..8).rev() {
+        let seen = counts.observations[index];
+        let confidence = counts.majority_confidence(index);
+        if seen == 0 {
+            println!("  {index:03b} → ?  (never observed)");
+        } else {
+            println!("  {index:03b} → {}  ({seen} obs, P(1)={:.3}, confidence {confidence:.4})",
+                (inferred >> index) & 1, counts.p_one(index));
+        }
+    }
+    match found.uncertain.len() {
+        0 => println!("  → Rule {inferred} (every entry at least 95% confident)\n"),
+        n => println!("  → Rule {inferred}, but {n} uncertain entries: {} candidate rules\n", 1 << n),
+    }
+
+    println!("5. Classification of rule {inferred}");
+    let equivalents = wolfram_equivalents(inferred);
+    println!("  Equivalent rules (mirror/complement): {equivalents:?}");
+    let (mean, std_dev, class) = found.entropy;
+    println!("  Entropy class: {class} (mean {mean:.3}, std {std_dev:.3} of max)");
+    let fit = &found.growth;
+    println!("  Block complexity: {} (exponent {:.3}, R² {:.3})", fit.class, fit.exponent, fit.r_squared);
+    if INTERESTING_RULES.contains(&inferred) {
+        println!("  One of the Wolfram Class 3/4 rules");
+    }
+
+    if let Some(rule) = truth {
+        println!("\nGround truth: rule {rule} — Match: {}", if inferred == rule { "YES" } else { "NO" });
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct DiffRuleArgs {
+    /// First rule (0-255)
+    pub a: u8,
+    /// Second rule (0-255)
+    pub b: u8,
+    /// Number of cells in the ring for the entropy comparison
+    #[arg(short, long, default_value_t = 79, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations for the entropy comparison
+    #[arg(short = 'g', long = "gens", default_value_t = 100)]
+    pub generations: usize,
+    /// Number of cells in the ring for the cycle comparison
+    #[arg(long, default_value_t = 31, value_parser = parse_width)]
+    pub cycle_width: usize,
+    /// Give up looking for a cycle after this many steps
+    #[arg(short, long, default_value_t = 10000)]
+    pub max_steps: usize,
+    /// Print the report as a table or as JSON
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+pub fn diff_rule(args: &DiffRuleArgs) {
+    let DiffRuleArgs { a, b, width, generations, cycle_width, max_steps, format, ref init } = *args;
+    if width < 3 {
+        eprintln!("--width must be at least 3: the entropy class reads 3-cell blocks");
+        std::process::exit(1);
+    }
+    // Side by side: table entries, symmetry, dependencies, then behavior
+
+    let differing: Vec<usize> = (0..8).rev().filter(|&index| (a ^ b) >> index & 1 == 1).collect();
+    let names: Vec<String> = differing.iter().map(|index| format!("{index:03b}")).collect();
+
+    // Symmetry: the same rule up to mirroring and/or swapping live and dead
+    let table = TableRule::new(1, a as u128);
+    let relation = [
+        (table, "identical", "identical"),
+        (table.mirror(), "mirror", "mirror images (left and right swapped)"),
+        (table.complement(), "complement", "complements (live and dead swapped)"),
+        (table.mirror().complement(), "mirror-complement", "mirror-complements (both swapped)"),
+    ]
+    .into_iter()
+    .find(|(rule, _, _)| rule.table == b as u128);
+
+    let dependencies = |rule: u8| -> Vec<&str> {
+        let names = ["left", "center", "right"];
+        (0..3).filter(|&i| rule_dependencies(rule)[i]).map(|i| names[i]).collect()
+    };
+
+    // Behavior from the same initial row: entropy class as in entropy-survey, cycles as in cycle
+    let classes = [a, b].map(|rule| {
+        let (mean, std_dev) = entropy_signature_from(init.automaton(width, rule), generations, 3);
+        (mean / 3.0, std_dev / 3.0)
+    });
+    let class = classes.map(|(mean, std_dev)| entropy_class(mean, std_dev).1);
+    let cycles = [a, b].map(|rule| find_cycle_from(init.automaton(cycle_width, rule), max_steps));
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("a", a.into()),
+            ("b", b.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("cycle_width", cycle_width.into()),
+            ("max_steps", max_steps.into()),
+            ("initial", init.to_json()),
+        ]);
+        let rules = [a, b].into_iter().enumerate().map(|(i, rule)| {
+            let (mean, std_dev) = classes[i];
+            Json::object([
+                ("rule", rule.into()),
+                ("equivalents", wolfram_equivalents(rule).into()),
+                ("dependencies", dependencies(rule).into()),
+                ("entropy", Json::object([("class", class[i].into()), ("mean", mean.into()), ("std", std_dev.into())])),
+                ("transient", cycles[i].transient.into()),
+                ("period", (cycles[i].period > 0).then_some(cycles[i].period).into()),
+                ("final_density", cycles[i].final_density.into()),
+            ])
+        });
+        let results = Json::object([
+            ("differing", names.into()),
+            ("symmetry", relation.map(|(_, name, _)| name).into()),
+            ("rules", Json::array(rules)),
+        ]);
+        return print_report("diff-rule", parameters, results);
+    }
+
+    println!("Rule {a} vs rule {b}\n");
+    println!("  {:<6} {:>4} {:>4}", "lcr", a, b);
+    for index in (0..8).rev() {
+        let marker = if differing.contains(&index) { "  <-" } else { "" };
+        println!("  {index:03b}    {:>4} {:>4}{marker}", a >> index & 1, b >> index & 1);
+    }
+    match differing.len() {
+        0 => println!("  Identical tables"),
+        n => println!("  Differ on {n} of 8 neighborhoods: {}", names.join(", ")),
+    }
+    match relation {
+        Some((_, _, name)) => println!("  Symmetry: {name}"),
+        None => println!("  Symmetry: unrelated ({a} ~ {:?}, {b} ~ {:?})", wolfram_equivalents(a), wolfram_equivalents(b)),
+    }
+    let positions = |rule: u8| {
+        let used = dependencies(rule);
+        if used.is_empty() { "none".to_string() } else { used.join(" + ") }
+    };
+    println!("  Depends on: {} vs {}\n", positions(a), positions(b));
+
+    println!("Behavior (entropy: width={width}, gens={generations}; cycles: width={cycle_width}{})", init.describe());
+    println!("  {:<28} {:>12} {:>12}", "", a, b);
+    println!("  {:<28} {:>12} {:>12}", "Entropy class", class[0], class[1]);
+    println!("  {:<28} {:>12.3} {:>12.3}", "Entropy mean (of max)", classes[0].0, classes[1].0);
+    println!("  {:<28} {:>12.3} {:>12.3}", "Entropy std", classes[0].1, classes[1].1);
+    let period = |rule: usize| match cycles[rule].period {
+        0 => ">max".to_string(),
+        period => period.to_string(),
+    };
+    println!("  {:<28} {:>12} {:>12}", "Transient", cycles[0].transient, cycles[1].transient);
+    println!("  {:<28} {:>12} {:>12}", "Period", period(0), period(1));
+    println!("  {:<28} {:>12.3} {:>12.3}", "Final density", cycles[0].final_density, cycles[1].final_density);
+}
+
+/// The rule with left and right swapped, its 0/1 complement, and both:
+/// the four rules that behave identically up to symmetry (sorted, deduplicated)
+fn wolfram_equivalents(rule: u8) -> Vec<u8> {
+    symmetry::class(rule).members
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dependency_infer_report() {
+        let format = ReportFormat::Json;
+        let args = DependencyInferArgs { rule: 90, width: 50, generations: 30, noise: 0.0, alpha: 0.01, format };
+        let json = DependencyInference::new(&args).report(&args).to_string();
+        assert!(json.starts_with(r#"{"schema":"automata-report","version":1,"command":"dependency-infer","#));
+        assert!(json.contains(r#""parameters":{"rule":90,"width":50,"#));
+        assert!(json.contains(r#"{"position":"center","matters":false,"undetermined":false,"contexts":[{"#));
+        assert!(json.contains(r#""dependencies":["left","right"],"undetermined":[],"#), "{json}");
+        assert!(json.ends_with(r#""true_dependencies":["left","right"],"match":true}}"#));
+    }
+
+    #[test]
+    fn test_identify_report() {
+        let args = IdentifyArgs {
+            rule: 30,
+            file: None,
+            width: 50,
+            generations: 20,
+            trials: 10,
+            noise: 0.0,
+            alpha: 0.01,
+            format: ReportFormat::Json,
+        };
+        let data = TransitionDataset::training(30, 50, 20, 10, 0.0);
+        let json = identify_report(&args, &data, Some(30)).to_string();
+        assert!(json.starts_with(r#"{"schema":"automata-report","version":1,"command":"identify","#));
+        assert!(json.contains(r#""parameters":{"rule":30,"width":50,"#));
+        assert!(json.contains(r#""results":{"transitions":200,"width":50,"cell_observations":10000,"#));
+        assert!(json.contains(r#""radius":1},"dependencies":[{"position":"left","#), "{json}");
+        assert!(json.contains(r#""uncertain":[],"candidates":1},"inferred_rule":30,"#));
+        assert!(json.contains(r#""classification":{"equivalents":[30,86,135,149],"#));
+        assert!(json.ends_with(r#""true_rule":30,"match":true}}"#));
+
+        // A single row has no transitions: nothing is inferred, and there's no truth to match
+        let data = TransitionDataset::from_trajectories(vec![vec![vec![true, false, false]]]);
+        let json = identify_report(&IdentifyArgs { file: Some("one.txt".into()), ..args }, &data, None).to_string();
+        assert!(json.contains(r#""parameters":{"file":"one.txt","alpha":0.01},"#), "{json}");
+        assert!(json.ends_with(r#"{"transitions":0,"width":3,"cell_observations":0,"true_rule":null,"match":null}}"#));
+    }
+}
@@ -0,0 +1,142 @@
+//! Minimal JSON for `--format json`: values are built in key order and
+//! printed compactly, with no parser and no extra dependencies
+//!
+//! Every report has the same envelope, so scripts can check what they got:
+//! `{"schema": "automata-report", "version": 1, "command": ..., "parameters": {...}, "results": ...}`,
+//! with `"preset": ...` after the command when `--preset` is given.
+//! The version goes up when a field is renamed or removed; new fields may
+//! appear without one.
+
+use clap::ValueEnum;
+use std::fmt;
+
+/// Bumped whenever an existing field changes meaning, name or type
+pub const VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// Already formatted; `f64`s that aren't finite become `null` instead
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    pub fn object(fields: impl IntoIterator<Item = (&'static str, Json)>) -> Self {
+        Json::Object(fields.into_iter().collect())
+    }
+
+    pub fn array<T: Into<Json>>(items: impl IntoIterator<Item = T>) -> Self {
+        Json::Array(items.into_iter().map(Into::into).collect())
+    }
+}
+
+/// The report envelope around one command's parameters and results, with
+/// the `--preset` that chose them, if any
+pub fn report(command: &str, parameters: Json, results: Json) -> Json {
+    let preset = super::preset::preset().map(|preset| ("preset", preset.name().into()));
+    let head = [("schema", "automata-report".into()), ("version", VERSION.into()), ("command", command.into())];
+    Json::object(head.into_iter().chain(preset).chain([("parameters", parameters), ("results", results)]))
+}
+
+/// Send a report to the `--sink`: by default, one line of stdout
+pub fn print_report(command: &str, parameters: Json, results: Json) {
+    super::sink::emit(report(command, parameters, results));
+}
+
+/// The name clap shows for a value, as used on the command line
+pub fn value_name(value: impl ValueEnum) -> Json {
+    value.to_possible_value().expect("no skipped values").get_name().into()
+}
+
+macro_rules! integers {
+    ($($t:ty),*) => {
+        $(impl From<$t> for Json {
+            fn from(n: $t) -> Self {
+                Json::Number(n.to_string())
+            }
+        })*
+    };
+}
+integers!(u8, u16, u32, u64, usize, i32, i64);
+
+impl From<f64> for Json {
+    fn from(x: f64) -> Self {
+        if x.is_finite() { Json::Number(x.to_string()) } else { Json::Null }
+    }
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::String(s.to_string())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::String(s)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+impl<T: Into<Json>> From<Vec<T>> for Json {
+    fn from(items: Vec<T>) -> Self {
+        Json::array(items)
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{b}"),
+            Json::Number(n) => write!(f, "{n}"),
+            Json::String(s) => write_string(f, s),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    write!(f, "{}{item}", if i > 0 { "," } else { "" })?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    write!(f, "{}", if i > 0 { "," } else { "" })?;
+                    write_string(f, key)?;
+                    write!(f, ":{value}")?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\t' => write!(f, "\\t")?,
+            c if c < ' ' => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    write!(f, "\"")
+}
@@ -0,0 +1,410 @@
+//! Job queue for `serve`: scripts submit batches of endpoint requests, a pool
+//! of workers answers them in the resident process, and the answers are kept
+//! (in memory, or in a `--store` directory) for status and result queries
+//!
+//! A job is any `/rule/...` request target, as it would be fetched with GET.
+//! The store is an append-only `jobs.tsv` index plus one file per result;
+//! finished jobs in it are reloaded on restart, queued ones are not. Each
+//! line records the hash version its answer's state and attractor hashes
+//! were made with, and jobs from another version are left out on reload.
+
+use super::json::Json;
+use super::log::note;
+use super::serve::{respond, Response};
+use automata::HASH_VERSION;
+use std::collections::{BTreeMap, VecDeque};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Instant;
+
+/// Most jobs accepted in one submission
+pub const MAX_BATCH: usize = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Status {
+    Queued,
+    Running,
+    Done,
+    /// The endpoint answered with an error
+    Failed,
+}
+
+impl Status {
+    fn name(self) -> &'static str {
+        match self {
+            Status::Queued => "queued",
+            Status::Running => "running",
+            Status::Done => "done",
+            Status::Failed => "failed",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        [Status::Queued, Status::Running, Status::Done, Status::Failed].into_iter().find(|s| s.name() == name)
+    }
+}
+
+struct Job {
+    target: String,
+    status: Status,
+    submitted: Instant,
+    /// Seconds waiting in the queue, then running
+    waited: f64,
+    ran: f64,
+    /// HTTP status and content type of the answer, once there is one
+    answer: Option<(u16, &'static str)>,
+    /// The answer's body; with a store, it is read back from `file` instead
+    body: Option<Vec<u8>>,
+    file: Option<PathBuf>,
+}
+
+impl Job {
+    fn to_json(&self, id: u64) -> Json {
+        let finished = matches!(self.status, Status::Done | Status::Failed);
+        Json::object([
+            ("id", id.into()),
+            ("target", self.target.as_str().into()),
+            ("status", self.status.name().into()),
+            ("http_status", self.answer.map(|(code, _)| code).into()),
+            ("content_type", self.answer.map(|(_, content_type)| content_type).into()),
+            ("queued_seconds", (self.status != Status::Queued).then_some(self.waited).into()),
+            ("run_seconds", finished.then_some(self.ran).into()),
+            ("result", finished.then(|| format!("/jobs/{id}/result")).into()),
+        ])
+    }
+}
+
+#[derive(Default)]
+struct Queue {
+    jobs: BTreeMap<u64, Job>,
+    waiting: VecDeque<u64>,
+    next_id: u64,
+}
+
+/// The queue and its workers, shared by every connection
+pub struct Lab {
+    queue: Mutex<Queue>,
+    /// Signalled when a job is queued
+    ready: Condvar,
+    /// Signalled when a job finishes
+    finished: Condvar,
+    store: Option<PathBuf>,
+    /// Held while a line is appended to the store's index, so the
+    /// workers' lines don't interleave
+    index: Mutex<()>,
+}
+
+impl Lab {
+    /// Start `workers` threads on a queue, reloading the finished jobs in `store`
+    pub fn start(workers: usize, store: Option<PathBuf>) -> io::Result<Arc<Lab>> {
+        let mut queue = Queue::default();
+        if let Some(dir) = &store {
+            std::fs::create_dir_all(dir)?;
+            (queue.jobs, queue.next_id) = read_index(dir)?;
+            create_index(dir)?;
+        }
+        let lab = Arc::new(Lab::new(queue, store));
+        for _ in 0..workers.max(1) {
+            let lab = Arc::clone(&lab);
+            std::thread::spawn(move || lab.work());
+        }
+        Ok(lab)
+    }
+
+    fn new(queue: Queue, store: Option<PathBuf>) -> Self {
+        Lab {
+            queue: Mutex::new(queue),
+            ready: Condvar::new(),
+            finished: Condvar::new(),
+            store,
+            index: Mutex::new(()),
+        }
+    }
+
+    /// Jobs reloaded from the store
+    pub fn reloaded(&self) -> usize {
+        self.queue.lock().unwrap().jobs.len()
+    }
+
+    fn work(&self) {
+        loop {
+            let (id, target) = {
+                let mut queue = self.queue.lock().unwrap();
+                let id = loop {
+                    match queue.waiting.pop_front() {
+                        Some(id) => break id,
+                        None => queue = self.ready.wait(queue).unwrap(),
+                    }
+                };
+                let job = queue.jobs.get_mut(&id).expect("queued jobs exist");
+                job.status = Status::Running;
+                job.waited = job.submitted.elapsed().as_secs_f64();
+                (id, job.target.clone())
+            };
+
+            let start = Instant::now();
+            let Response(code, content_type, body) = respond(&target);
+            let ran = start.elapsed().as_secs_f64();
+            let status = if code == 200 { Status::Done } else { Status::Failed };
+            // Stored results are written before the job reads as finished
+            let file = self.store.as_ref().map(|dir| (dir, format!("{id}.{}", extension(content_type))));
+            let stored = file.as_ref().map(|(dir, name)| {
+                std::fs::write(dir.join(name), &body)?;
+                let line = format!("{id}\t{target}\t{}\t{code}\t{content_type}\t{name}\t{ran}\t{HASH_VERSION}", status.name());
+                let _index = self.index.lock().unwrap();
+                append_index(dir, &line)
+            });
+            if let Some(Err(e)) = &stored {
+                eprintln!("  job {id}: can't store the result: {e}");
+            }
+
+            let mut queue = self.queue.lock().unwrap();
+            let job = queue.jobs.get_mut(&id).expect("running jobs exist");
+            job.status = status;
+            job.ran = ran;
+            job.answer = Some((code, content_type));
+            match (file, stored) {
+                (Some((dir, name)), Some(Ok(()))) => job.file = Some(dir.join(name)),
+                _ => job.body = Some(body),
+            }
+            self.finished.notify_all();
+            note!("  job {id} {target} -> {code} ({ran:.3}s)");
+        }
+    }
+
+    /// Route a request under /jobs
+    pub fn respond(&self, method: &str, path: &str, query: &str, body: &[u8]) -> Response {
+        let json = |status: u16, value: Json| Response(status, "application/json", format!("{value}\n").into_bytes());
+        let rest = path.strip_prefix("/jobs").unwrap_or(path).trim_start_matches('/');
+        let mut parts = rest.splitn(2, '/');
+        let (id, sub) = (parts.next().unwrap_or(""), parts.next());
+
+        match (method, id, sub) {
+            ("POST", "", None) => match self.submit(body) {
+                Ok(ids) => json(202, Json::object([("jobs", Json::array(ids))])),
+                Err(message) => Response::error(400, message),
+            },
+            ("GET" | "HEAD", "", None) => {
+                let wanted = query.strip_prefix("status=").map(Status::parse);
+                if wanted == Some(None) {
+                    return Response::error(400, "status must be queued, running, done or failed");
+                }
+                let queue = self.queue.lock().unwrap();
+                let count = |status: Status| queue.jobs.values().filter(|job| job.status == status).count();
+                let listed = queue.jobs.iter().filter(|(_, job)| wanted.flatten().is_none_or(|s| job.status == s));
+                json(200, Json::object([
+                    ("queued", count(Status::Queued).into()),
+                    ("running", count(Status::Running).into()),
+                    ("done", count(Status::Done).into()),
+                    ("failed", count(Status::Failed).into()),
+                    ("jobs", Json::array(listed.map(|(&id, job)| job.to_json(id)))),
+                ]))
+            }
+            ("POST", _, _) => Response::error(405, "POST goes to /jobs"),
+            ("GET" | "HEAD", id, sub) => {
+                let Ok(id) = id.parse::<u64>() else {
+                    return Response::error(404, format!("no such job: {id}"));
+                };
+                let queue = self.queue.lock().unwrap();
+                let Some(job) = queue.jobs.get(&id) else {
+                    return Response::error(404, format!("no such job: {id}"));
+                };
+                match (sub, job.answer) {
+                    (None, _) => json(200, job.to_json(id)),
+                    (Some("result"), None) => Response::error(409, format!("job {id} is {}", job.status.name())),
+                    (Some("result"), Some((code, content_type))) => {
+                        let body = match (&job.body, &job.file) {
+                            (Some(body), _) => Ok(body.clone()),
+                            (None, Some(file)) => std::fs::read(file),
+                            (None, None) => unreachable!("finished jobs keep their body or its file"),
+                        };
+                        match body {
+                            Ok(body) => Response(code, content_type, body),
+                            Err(e) => Response::error(500, format!("can't read the stored result of job {id}: {e}")),
+                        }
+                    }
+                    (Some(other), _) => Response::error(404, format!("no such endpoint: /jobs/{id}/{other}")),
+                }
+            }
+            _ => Response::error(405, "jobs take GET, HEAD and POST"),
+        }
+    }
+
+    /// Queue one job per non-empty line of `body`, each a /rule/ target
+    fn submit(&self, body: &[u8]) -> Result<Vec<u64>, String> {
+        let text = std::str::from_utf8(body).map_err(|_| "the body must be UTF-8, one request target per line")?;
+        let targets: Vec<&str> = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+        if targets.is_empty() || targets.len() > MAX_BATCH {
+            return Err(format!("submit 1 to {MAX_BATCH} targets, one per line, e.g. /rule/110/analysis.json?width=31"));
+        }
+        if let Some(bad) = targets.iter().find(|t| !t.starts_with("/rule/") || t.contains(char::is_whitespace)) {
+            return Err(format!("jobs are /rule/... targets without spaces, not '{bad}'"));
+        }
+
+        let mut queue = self.queue.lock().unwrap();
+        let first = queue.next_id;
+        for (id, target) in (first..).zip(&targets) {
+            let job = Job {
+                target: target.to_string(),
+                status: Status::Queued,
+                submitted: Instant::now(),
+                waited: 0.0,
+                ran: 0.0,
+                answer: None,
+                body: None,
+                file: None,
+            };
+            queue.jobs.insert(id, job);
+            queue.waiting.push_back(id);
+        }
+        queue.next_id = first + targets.len() as u64;
+        self.ready.notify_all();
+        Ok((first..queue.next_id).collect())
+    }
+}
+
+const INDEX_HEADER: &str = "id\ttarget\tstatus\thttp_status\tcontent_type\tfile\trun_seconds\thash_version\n";
+
+fn extension(content_type: &str) -> &'static str {
+    match content_type {
+        "image/png" => "png",
+        "image/svg+xml" => "svg",
+        "application/json" => "json",
+        _ => "txt",
+    }
+}
+
+/// Start a store's index with its header, unless it has one
+fn create_index(dir: &Path) -> io::Result<()> {
+    match std::fs::OpenOptions::new().write(true).create_new(true).open(dir.join("jobs.tsv")) {
+        Ok(mut index) => index.write_all(INDEX_HEADER.as_bytes()),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Add one finished job's (tab-separated) line to a store's index, in a
+/// single write
+fn append_index(dir: &Path, line: &str) -> io::Result<()> {
+    let mut index = std::fs::OpenOptions::new().append(true).open(dir.join("jobs.tsv"))?;
+    index.write_all(format!("{line}\n").as_bytes())
+}
+
+/// The finished jobs recorded in a store's index under the current hash
+/// version, and the next free job id
+fn read_index(dir: &Path) -> io::Result<(BTreeMap<u64, Job>, u64)> {
+    let text = match std::fs::read_to_string(dir.join("jobs.tsv")) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((BTreeMap::new(), 0)),
+        Err(e) => return Err(e),
+    };
+    let (mut jobs, mut next_id, mut stale) = (BTreeMap::new(), 0, 0);
+    for (number, line) in text.lines().enumerate().skip(1) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let bad = || io::Error::new(io::ErrorKind::InvalidData, format!("jobs.tsv line {}: '{line}'", number + 1));
+        // Indexes from before the column hashed as version 1 does
+        let (job, version) = match fields[..] {
+            [ref job @ .., version] if job.len() == 7 => (job, version),
+            ref job => (job, "1"),
+        };
+        let [id, target, status, code, content_type, file, ran] = *job else {
+            return Err(bad());
+        };
+        let version: u32 = version.parse().map_err(|_| bad())?;
+        let id: u64 = id.parse().map_err(|_| bad())?;
+        next_id = next_id.max(id + 1);
+        if version != HASH_VERSION {
+            stale += 1;
+            continue;
+        }
+        let content_type = ["image/png", "image/svg+xml", "application/json", "text/plain; charset=utf-8"]
+            .into_iter()
+            .find(|&known| known == content_type)
+            .ok_or_else(bad)?;
+        let job = Job {
+            target: target.to_string(),
+            status: Status::parse(status).ok_or_else(bad)?,
+            submitted: Instant::now(),
+            waited: 0.0,
+            ran: ran.parse().map_err(|_| bad())?,
+            answer: Some((code.parse().map_err(|_| bad())?, content_type)),
+            body: None,
+            file: Some(dir.join(file)),
+        };
+        jobs.insert(id, job);
+    }
+    if stale > 0 {
+        note!("  {stale} stored jobs hashed with another version left out");
+    }
+    Ok((jobs, next_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::TempDir;
+    use super::*;
+
+    #[test]
+    fn test_store_round_trip() {
+        let store = TempDir::new("lab-round-trip");
+        let dir = store.path().to_path_buf();
+        let lab = Lab::start(2, Some(dir.clone())).unwrap();
+        let body = b"/rule/90/analysis.json?width=8\n\n/rule/300.png\n";
+        assert_eq!(lab.respond("POST", "/jobs", "", body).0, 202);
+        let running = |queue: &mut Queue| queue.jobs.values().any(|job| job.answer.is_none());
+        let wait = std::time::Duration::from_secs(30);
+        let (queue, timeout) = lab.finished.wait_timeout_while(lab.queue.lock().unwrap(), wait, running).unwrap();
+        assert!(!timeout.timed_out(), "jobs never finished");
+        drop(queue);
+        let answer = lab.respond("GET", "/jobs/0/result", "", b"");
+
+        let (jobs, next_id) = read_index(&dir).unwrap();
+        assert_eq!(next_id, 2);
+        let (analysis, png) = (&jobs[&0], &jobs[&1]);
+        assert_eq!((analysis.target.as_str(), analysis.status), ("/rule/90/analysis.json?width=8", Status::Done));
+        assert_eq!(analysis.answer, Some((200, "application/json")));
+        assert_eq!(std::fs::read(analysis.file.as_ref().unwrap()).unwrap(), answer.2);
+        // A rule out of range is an answered job that failed
+        assert_eq!((png.status, png.answer.map(|(code, _)| code)), (Status::Failed, Some(400)));
+        // A restarted lab serves the same result from the store
+        let restarted = Lab::start(1, Some(dir.clone())).unwrap();
+        assert_eq!(restarted.reloaded(), 2);
+        assert_eq!(restarted.respond("GET", "/jobs/0/result", "", b"").2, answer.2);
+    }
+
+    #[test]
+    fn test_read_index() {
+        let store = TempDir::new("lab-index");
+        let dir = store.path().to_path_buf();
+        create_index(&dir).unwrap();
+        let line = |id: u64, version: &str| {
+            format!("{id}\t/rule/30.svg\tdone\t200\timage/svg+xml\t{id}.svg\t0.5{version}")
+        };
+        append_index(&dir, &line(0, &format!("\t{HASH_VERSION}"))).unwrap();
+        // Another hash version's answer is left out, but its id stays taken
+        append_index(&dir, &line(4, &format!("\t{}", HASH_VERSION + 1))).unwrap();
+        // A line from before the hash_version column hashed as version 1
+        append_index(&dir, &line(2, "")).unwrap();
+        let (jobs, next_id) = read_index(&dir).unwrap();
+        assert_eq!(next_id, 5);
+        assert_eq!(jobs.keys().copied().collect::<Vec<_>>(), [0, 2]);
+        assert_eq!((jobs[&2].ran, jobs[&2].file.clone()), (0.5, Some(dir.join("2.svg"))));
+
+        append_index(&dir, "7\t/rule/30.svg\tdone").unwrap();
+        assert!(read_index(&dir).err().unwrap().to_string().contains("jobs.tsv line 5"));
+    }
+
+    #[test]
+    fn test_submit() {
+        let lab = Lab::new(Queue::default(), None);
+        let too_many = "/rule/30.png\n".repeat(MAX_BATCH + 1);
+        for body in ["", "\n \n", "/rule/30.png\n/jobs/3", "/rule/30.png?width=8 extra", "rule/30.png", &too_many] {
+            assert!(lab.submit(body.as_bytes()).is_err(), "{body:?}");
+        }
+        assert!(lab.queue.lock().unwrap().jobs.is_empty());
+        assert_eq!(lab.submit(b" /rule/30.png \n/rule/90.svg").unwrap(), [0, 1]);
+        assert_eq!(lab.submit(b"/rule/110.png").unwrap(), [2]);
+        assert_eq!(lab.queue.lock().unwrap().jobs[&0].target, "/rule/30.png");
+    }
+}
@@ -0,0 +1,169 @@
+//! `lattice`: a coupled map lattice, its binned block entropy measured the
+//! way `entropy` measures an automaton's, and optionally an ECA run from
+//! the same row thresholded at 1/2 to set the two kinds of chaos side by side
+
+use super::dynamics::{entropy_class, entropy_signature_from, ENTROPY_SKIP};
+use super::json::{print_report, Json};
+use super::run::parse_color;
+use super::{metadata, parse_probability, parse_rule, parse_width, ReportFormat};
+use automata::diagnostics;
+use automata::inference::mean_std;
+use automata::lattice::{Lattice, LocalMap};
+use automata::metadata::Tagged;
+use automata::render::{write_png_gray, RenderOptions};
+use clap::Args;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args, Clone)]
+pub struct LatticeArgs {
+    /// Local map: logistic:<r> (r up to 4) or tent:<mu> (mu up to 2)
+    #[arg(long, default_value = "logistic:4")]
+    pub map: LocalMap,
+    /// Coupling ε: the share of each cell's next value taken from its two neighbors
+    #[arg(short = 'e', long, default_value_t = 0.3, value_parser = parse_probability)]
+    pub coupling: f64,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 128, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations measured, after the first 50 (the diagram shows them all)
+    #[arg(short = 'g', long = "gens", default_value_t = 200)]
+    pub generations: usize,
+    /// Equal-width bins over [0, 1] each cell is read as before measuring (2: live above 1/2)
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u16).range(2..=256))]
+    pub bins: u16,
+    /// Block size for the entropy, in cells
+    #[arg(short, long, default_value_t = 3)]
+    pub block_size: usize,
+    /// Seed of the random initial row
+    #[arg(long, default_value_t = 42)]
+    pub seed: u64,
+    /// Also measure this elementary rule, from the initial row thresholded at 1/2
+    #[arg(long, value_parser = parse_rule)]
+    pub compare: Option<u8>,
+    /// Draw the diagram to this PNG, shaded from the dead color (0) to the live one (1)
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+    /// Pixels per cell side
+    #[arg(short, long, default_value_t = 2)]
+    pub cell_size: usize,
+    /// Color for 1, as RRGGBB hex
+    #[arg(long, default_value = "000000", value_parser = parse_color)]
+    pub live: [u8; 3],
+    /// Color for 0, as RRGGBB hex
+    #[arg(long, default_value = "ffffff", value_parser = parse_color)]
+    pub dead: [u8; 3],
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+pub fn lattice(args: &LatticeArgs) {
+    let LatticeArgs {
+        map,
+        coupling,
+        width,
+        generations,
+        bins,
+        block_size,
+        seed,
+        compare,
+        ref out,
+        cell_size,
+        live,
+        dead,
+        format,
+    } = *args;
+    let bins = bins as usize;
+    let max_entropy = block_size as f64 * (bins as f64).log2();
+    if block_size == 0 || block_size > width || max_entropy > 64.0 {
+        eprintln!("--block-size must be 1 to {width}, with {bins}^block-size at most 2^64");
+        std::process::exit(1);
+    }
+    diagnostics::check_sampling(&format!("{block_size}-block entropy in {bins} bins"), width, max_entropy.exp2());
+
+    let mut lattice = Lattice::random(width, map, coupling, seed);
+    let start = lattice.clone();
+    let mut rows = vec![lattice.cells().to_vec()];
+    let mut entropies = Vec::with_capacity(generations + 1);
+    for t in 1..=ENTROPY_SKIP + generations {
+        lattice.step();
+        if out.is_some() {
+            rows.push(lattice.cells().to_vec());
+        }
+        if t >= ENTROPY_SKIP {
+            entropies.push(lattice.block_entropy(bins, block_size));
+        }
+    }
+    let (mean, std_dev) = mean_std(&entropies);
+    let (_, class) = entropy_class(mean / max_entropy, std_dev / max_entropy);
+    let discrete = compare.map(|rule| {
+        let (mean, std_dev) = entropy_signature_from(start.binarized(rule), generations, block_size);
+        let max = block_size as f64;
+        (rule, mean, std_dev, entropy_class(mean / max, std_dev / max).1)
+    });
+
+    if let Some(path) = out {
+        let options = RenderOptions { cell_size: cell_size.max(1), live, dead, ..RenderOptions::default() };
+        let params: [(_, &dyn std::fmt::Display); 5] =
+            [("map", &map), ("coupling", &coupling), ("width", &width), ("generations", &rows.len()), ("seed", &seed)];
+        let metadata = metadata(&params);
+        let written = std::fs::File::create(path).and_then(|file| {
+            let mut out = Tagged::new(BufWriter::new(file), &metadata);
+            write_png_gray(&mut out, &rows, &options)?;
+            out.flush()
+        });
+        if let Err(e) = written {
+            eprintln!("Can't write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+    }
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("map", map.to_string().into()),
+            ("coupling", coupling.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("bins", bins.into()),
+            ("block_size", block_size.into()),
+            ("seed", seed.into()),
+            ("compare", compare.into()),
+        ]);
+        let results = Json::object([
+            ("mean_entropy", mean.into()),
+            ("std_entropy", std_dev.into()),
+            ("max_entropy", max_entropy.into()),
+            ("class", class.into()),
+            ("mean_cell", lattice.mean().into()),
+            (
+                "compare",
+                discrete.map_or(Json::Null, |(rule, mean, std_dev, class)| {
+                    Json::object([
+                        ("rule", rule.into()),
+                        ("mean_entropy", mean.into()),
+                        ("std_entropy", std_dev.into()),
+                        ("max_entropy", block_size.into()),
+                        ("class", class.into()),
+                    ])
+                }),
+            ),
+        ]);
+        return print_report("lattice", parameters, results);
+    }
+
+    println!("Coupled {map} maps (width={width}, coupling={coupling}, gens={generations}, seed={seed})");
+    println!("  Entropy of {block_size}-blocks in {bins} bins: {}", signature(mean, std_dev, max_entropy, class));
+    println!("  Mean cell at the end: {:.3}", lattice.mean());
+    if let Some((rule, mean, std_dev, class)) = discrete {
+        let signature = signature(mean, std_dev, block_size as f64, class);
+        println!("  Rule {rule} from the row thresholded at 1/2: {signature}");
+    }
+    if let Some(path) = out {
+        println!("  -> {}", path.display());
+    }
+}
+
+/// An entropy signature as the report prints it, with its normalized mean
+fn signature(mean: f64, std_dev: f64, max: f64, class: &str) -> String {
+    format!("{mean:.3} ± {std_dev:.3} bits of {max:.3} ({:.3}, {class})", mean / max)
+}
@@ -0,0 +1,186 @@
+//! `life`: a Life-like rule on a torus, from a random grid or a plaintext
+//! pattern, with its population and block entropy over the run and the
+//! grid printed or drawn
+
+use super::json::{print_report, Json};
+use super::run::parse_color;
+use super::{metadata, parse_probability, parse_width, ReportFormat};
+use automata::diagnostics;
+use automata::inference::mean_std;
+use automata::life::{Grid, LifeRule, MAX_BLOCK_SIDE};
+use automata::metadata::Tagged;
+use automata::render::{write_png_rows, RenderOptions};
+use clap::Args;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+/// Most cells a grid may have
+const MAX_CELLS: usize = 1 << 24;
+
+#[derive(Args, Clone)]
+pub struct LifeArgs {
+    /// Births and survivals, e.g. B3/S23 (Life), B36/S23 (HighLife), B2/S (Seeds)
+    #[arg(default_value = "B3/S23")]
+    pub rule: LifeRule,
+    /// Cells across
+    #[arg(short, long, default_value_t = 64, value_parser = parse_width)]
+    pub width: usize,
+    /// Cells down
+    #[arg(long, default_value_t = 48, value_parser = parse_width)]
+    pub height: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 100)]
+    pub generations: usize,
+    /// Fraction of cells live in the random start
+    #[arg(short, long, default_value_t = 0.35, value_parser = parse_probability)]
+    pub density: f64,
+    /// Seed of the random start
+    #[arg(long, default_value_t = 42)]
+    pub seed: u64,
+    /// Start from this plaintext (.cells) pattern, centered, instead of a random grid
+    #[arg(long, value_name = "FILE")]
+    pub pattern: Option<PathBuf>,
+    /// Side of the square blocks the entropy reads, in cells
+    #[arg(short, long, default_value_t = 2)]
+    pub block_size: usize,
+    /// Print the grid every this many generations as well as the last (0: only the last)
+    #[arg(long, default_value_t = 0)]
+    pub every: usize,
+    /// Draw the last grid to this PNG instead of printing it
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+    /// Pixels per cell side
+    #[arg(short, long, default_value_t = 4)]
+    pub cell_size: usize,
+    /// Color for live cells, as RRGGBB hex
+    #[arg(long, default_value = "000000", value_parser = parse_color)]
+    pub live: [u8; 3],
+    /// Color for dead cells, as RRGGBB hex
+    #[arg(long, default_value = "ffffff", value_parser = parse_color)]
+    pub dead: [u8; 3],
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+pub fn life(args: &LifeArgs) {
+    let LifeArgs {
+        rule,
+        width,
+        height,
+        generations,
+        density,
+        seed,
+        ref pattern,
+        block_size,
+        every,
+        ref out,
+        cell_size,
+        live,
+        dead,
+        format,
+    } = *args;
+    if width.saturating_mul(height) > MAX_CELLS {
+        eprintln!("--width x --height must be at most {MAX_CELLS} cells");
+        std::process::exit(1);
+    }
+    if !(1..=MAX_BLOCK_SIDE.min(width).min(height)).contains(&block_size) {
+        eprintln!("--block-size must be 1 to {}", MAX_BLOCK_SIDE.min(width).min(height));
+        std::process::exit(1);
+    }
+    let blocks = format!("{block_size}x{block_size}-block entropy");
+    diagnostics::check_sampling(&blocks, width * height, ((block_size * block_size) as f64).exp2());
+    let mut grid = match pattern {
+        None => Grid::random(width, height, density, seed, rule),
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Grid::from_plaintext(&text, width, height, rule))
+            .unwrap_or_else(|e| {
+                eprintln!("{}: {e}", path.display());
+                std::process::exit(1);
+            }),
+    };
+    let start = match pattern {
+        Some(path) => format!("pattern={}", path.display()),
+        None => format!("density={density}, seed={seed}"),
+    };
+    let printing = format == ReportFormat::Table && out.is_none();
+
+    let mut populations = vec![grid.population()];
+    let mut entropies = vec![grid.block_entropy(block_size)];
+    for t in 1..=generations {
+        if printing && every > 0 && (t - 1).is_multiple_of(every) {
+            print_grid(&grid, t - 1);
+        }
+        grid.step();
+        populations.push(grid.population());
+        entropies.push(grid.block_entropy(block_size));
+    }
+    let max_entropy = (block_size * block_size) as f64;
+    let (mean_entropy, std_entropy) = mean_std(&entropies);
+    let mean_density = populations.iter().sum::<usize>() as f64 / (populations.len() * width * height) as f64;
+    let (min, max) = (*populations.iter().min().unwrap(), *populations.iter().max().unwrap());
+
+    if let Some(path) = out {
+        let options = RenderOptions { cell_size: cell_size.max(1), live, dead, ..RenderOptions::default() };
+        let params: [(_, &dyn std::fmt::Display); 5] =
+            [("rule", &rule), ("width", &width), ("height", &height), ("generations", &generations), ("start", &start)];
+        let metadata = metadata(&params);
+        let written = std::fs::File::create(path).and_then(|file| {
+            let mut out = Tagged::new(BufWriter::new(file), &metadata);
+            write_png_rows(&mut out, &grid.rows(), &options)?;
+            out.flush()
+        });
+        if let Err(e) = written {
+            eprintln!("Can't write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+    }
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.to_string().into()),
+            ("width", width.into()),
+            ("height", height.into()),
+            ("generations", generations.into()),
+            ("density", pattern.is_none().then_some(density).into()),
+            ("seed", pattern.is_none().then_some(seed).into()),
+            ("pattern", pattern.as_ref().map(|path| path.display().to_string()).into()),
+            ("block_size", block_size.into()),
+        ]);
+        let results = Json::object([
+            ("population_start", populations[0].into()),
+            ("population_end", grid.population().into()),
+            ("population_min", min.into()),
+            ("population_max", max.into()),
+            ("mean_density", mean_density.into()),
+            ("entropy_end", entropies[generations].into()),
+            ("mean_entropy", mean_entropy.into()),
+            ("std_entropy", std_entropy.into()),
+            ("max_entropy", max_entropy.into()),
+            ("populations", Json::array(populations)),
+        ]);
+        return print_report("life", parameters, results);
+    }
+
+    if printing {
+        print_grid(&grid, generations);
+    }
+    println!("Life-like rule {rule} ({width} x {height} torus, gens={generations}, {start})");
+    let (first, last) = (populations[0], grid.population());
+    println!("  Population: {first} -> {last} (min {min}, max {max}), mean density {mean_density:.3}");
+    println!(
+        "  Entropy of {block_size}x{block_size} blocks: {:.3} at the end, \
+         {mean_entropy:.3} ± {std_entropy:.3} bits of {max_entropy} over the run ({:.3})",
+        entropies[generations],
+        mean_entropy / max_entropy
+    );
+    if let Some(path) = out {
+        println!("  -> {}", path.display());
+    }
+}
+
+/// The grid in a frame the width of its rows, under its generation
+fn print_grid(grid: &Grid, generation: usize) {
+    let line = "-".repeat(grid.width());
+    println!("Generation {generation}\n{line}\n{grid}\n{line}");
+}
@@ -0,0 +1,111 @@
+//! `linear`: whether a rule is linear or affine over GF(2), its XOR formula
+//! and polynomial, superposition checked by simulation, and any generation
+//! computed exactly without the ones before
+
+use super::json::{print_report, Json};
+use super::{parse_binary_rule, parse_width, BinaryRule, InitArgs, ReportFormat};
+use automata::evolve::TableRule;
+use automata::linear::{superposes, Circulant, Linear};
+use clap::Args;
+
+/// Most steps of the exact generation checked against stepping
+const MAX_CHECKED: u64 = 1000;
+
+/// Widest row printed
+const MAX_SHOWN: usize = 200;
+
+#[derive(Args, Clone)]
+pub struct LinearArgs {
+    /// Rule number (0-255), a Wolfram code, or a radius-2 or 3 table (r=2:hex)
+    #[arg(default_value = "90", value_parser = parse_binary_rule)]
+    pub rule: BinaryRule,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 101, value_parser = parse_width)]
+    pub width: usize,
+    /// Generation to compute
+    #[arg(short = 'g', long = "gens", default_value_t = 1_000_000_000_000)]
+    pub generations: u64,
+    /// Random pairs of rows superposition is checked on, seeded by --seed
+    #[arg(long, default_value_t = 20)]
+    pub samples: usize,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+pub fn linear(args: &LinearArgs) {
+    let LinearArgs { rule, width, generations, samples, format, ref init } = *args;
+    let table = match rule {
+        BinaryRule::Elementary(rule) => TableRule::new(1, rule as u128),
+        BinaryRule::Table(table) => table,
+    };
+    let mut ca = init.automaton_for(width, rule);
+    let start = ca.cells();
+    // The rule with its superposition check, the generation checked
+    // against stepping and whether it agreed, generation T and how many
+    // cells of the start each of its cells depends on
+    let exact = Linear::detect(table).map(|linear| {
+        let superposed = superposes(table, width, 2 * width, samples, init.seed);
+        let checked = generations.min(MAX_CHECKED);
+        (0..checked).for_each(|_| ca.step());
+        let matches = linear.advance(&start, checked) == ca.cells();
+        let row = linear.advance(&start, generations);
+        // T^t as a matrix: the cells of the start that generation t reads
+        let power = Circulant::step(&linear, width).pow(generations);
+        let reads = (power.offsets().len(), power.constant);
+        (linear, superposed, checked, matches, row, reads)
+    });
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("samples", samples.into()),
+            ("init", init.to_json()),
+        ]);
+        let results = match &exact {
+            Ok((linear, superposed, checked, matches, row, reads)) => Json::object([
+                ("linear", true.into()),
+                ("affine", linear.affine.into()),
+                ("formula", linear.to_string().into()),
+                ("taps", Json::array(linear.taps.iter().map(|&o| o as i64))),
+                ("polynomial", linear.polynomial().into()),
+                ("superposed_cells", superposed.as_ref().ok().copied().into()),
+                ("checked_generations", (*checked).into()),
+                ("checked", (*matches).into()),
+                ("live", row.iter().filter(|&&c| c).count().into()),
+                ("reads", reads.0.into()),
+                ("complemented", reads.1.into()),
+            ]),
+            Err(witness) => Json::object([("linear", false.into()), ("witness", witness.clone().into())]),
+        };
+        return print_report("linear", parameters, results);
+    }
+
+    let (linear, superposed, checked, matches, row, reads) = match exact {
+        Ok(exact) => exact,
+        Err(witness) => {
+            println!("Rule {rule} is neither linear nor affine over GF(2): {witness}");
+            println!("  Runs don't superpose, so generation {generations} can't be computed without stepping to it");
+            return;
+        }
+    };
+    let kind = if linear.affine { "affine" } else { "linear" };
+    println!("Rule {rule} is {kind} over GF(2): x'[i] = {linear}");
+    println!("  A step multiplies the row by T(x) = {} mod x^{width} - 1", linear.polynomial());
+    match superposed {
+        Ok(cells) => println!("  Superposition holds on {samples} random pairs: {cells} cells compared"),
+        Err(e) => println!("  Superposition FAILS: {e}"),
+    }
+    let agrees = if matches { "agrees" } else { "DISAGREES" };
+    println!("  Generation {checked} computed directly {agrees} with {checked} steps of the rule");
+    let live = row.iter().filter(|&&c| c).count();
+    println!("Generation {generations}: {live} live of {width} ({:.3})", live as f64 / width as f64);
+    let flipped = if reads.1 { ", complemented" } else { "" };
+    println!("  Each of its cells is the XOR of {} cells of the first row{flipped}", reads.0);
+    if width <= MAX_SHOWN {
+        println!("  {}", row.iter().map(|&c| if c { '#' } else { ' ' }).collect::<String>());
+    }
+}
@@ -0,0 +1,126 @@
+//! Diagnostics on stderr, at the verbosity `--quiet`, `-v` and `-vv` choose
+//!
+//! Results (tables, CSV and JSON reports, images written to `-o -`) go to
+//! stdout and nothing else does, so they can be piped and parsed; notes
+//! about the run go through these macros to stderr instead. `note!` is on
+//! by default and off with `--quiet`, `verbose!` needs `-v` and `debug!`
+//! `-vv`. Errors that end the run are printed regardless, with `eprintln!`.
+//!
+//! Warnings the analyses raised about their own numbers (see
+//! `automata::diagnostics`) are printed once the command is done: as notes,
+//! or with `--warnings json` as one JSON line each, printed even with
+//! `--quiet` since a pipeline asked for them.
+
+use super::json::{Json, VERSION};
+use super::ReportFormat;
+use automata::diagnostics;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// How much goes to stderr, least first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    /// Errors only: no notes and no progress bars
+    Quiet,
+    /// Notes, warnings and progress bars
+    Normal,
+    /// Also settings, timings and files written
+    Verbose,
+    /// Also per-rule and per-request detail
+    Debug,
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Normal as u8);
+static JSON_WARNINGS: AtomicBool = AtomicBool::new(false);
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// The level for `--quiet` and a count of `-v` flags
+pub fn level(quiet: bool, verbose: u8) -> Level {
+    match (quiet, verbose) {
+        (true, _) => Level::Quiet,
+        (false, 0) => Level::Normal,
+        (false, 1) => Level::Verbose,
+        (false, _) => Level::Debug,
+    }
+}
+
+/// Print warnings as JSON lines rather than notes
+pub fn set_warnings(format: ReportFormat) {
+    JSON_WARNINGS.store(format == ReportFormat::Json, Ordering::Relaxed);
+}
+
+/// The flags that give a child process this process's level and warnings
+pub fn flags() -> Vec<&'static str> {
+    let mut flags = match LEVEL.load(Ordering::Relaxed) {
+        0 => vec!["--quiet"],
+        1 => vec![],
+        2 => vec!["-v"],
+        _ => vec!["-vv"],
+    };
+    if JSON_WARNINGS.load(Ordering::Relaxed) {
+        flags.push("--warnings=json");
+    }
+    flags
+}
+
+/// Print the warnings `command` raised on stderr, in the `--warnings` format:
+/// `{"schema": "automata-warning", "version": 1, "command": ..., "kind": ..., "message": ..., "count": ...}`
+pub fn report_warnings(command: &str) {
+    let (warnings, dropped) = diagnostics::warnings();
+    let json = JSON_WARNINGS.load(Ordering::Relaxed);
+    for warning in &warnings {
+        if json {
+            let line = Json::object([
+                ("schema", "automata-warning".into()),
+                ("version", VERSION.into()),
+                ("command", command.into()),
+                ("kind", warning.kind.code().into()),
+                ("message", warning.message.as_str().into()),
+                ("count", warning.count.into()),
+            ]);
+            eprintln!("{line}");
+        } else {
+            let times = if warning.count > 1 { format!(" (x{})", warning.count) } else { String::new() };
+            note!("Warning [{}]: {}{times}", warning.kind, warning.message);
+        }
+    }
+    if dropped > 0 && !json {
+        note!("Warning: {dropped} more not shown");
+    }
+}
+
+/// Whether messages at `level` are shown
+pub fn enabled(level: Level) -> bool {
+    LEVEL.load(Ordering::Relaxed) >= level as u8
+}
+
+/// A note or warning on stderr, unless `--quiet`
+macro_rules! note {
+    ($($arg:tt)*) => {
+        if $crate::commands::log::enabled($crate::commands::log::Level::Normal) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// Detail on stderr with `-v`
+macro_rules! verbose {
+    ($($arg:tt)*) => {
+        if $crate::commands::log::enabled($crate::commands::log::Level::Verbose) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+/// More detail on stderr with `-vv`
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::commands::log::enabled($crate::commands::log::Level::Debug) {
+            eprintln!($($arg)*);
+        }
+    };
+}
+
+pub(crate) use {debug, note, verbose};
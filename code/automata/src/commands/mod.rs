@@ -0,0 +1,749 @@
+//! One module per family of subcommands: argument structs plus the code
+//! that runs the analysis and prints its report
+
+pub mod batch;
+pub mod bestiary;
+pub mod checkpoint;
+pub mod classes;
+pub mod compose;
+pub mod config;
+pub mod damage;
+pub mod describe;
+pub mod discover;
+pub mod dynamics;
+pub mod evolve;
+pub mod inference;
+pub mod json;
+pub mod lab;
+pub mod lattice;
+pub mod life;
+pub mod linear;
+pub mod log;
+pub mod morph;
+pub mod notebook;
+pub mod perform;
+pub mod preimages;
+pub mod preset;
+pub mod profile;
+pub mod progress;
+pub mod run;
+pub mod selftest;
+pub mod serve;
+pub mod sink;
+pub mod stream;
+
+use automata::ether::{detect_ether, EtherSettings};
+use automata::evolve::TableRule;
+use automata::parquet::{write_parquet, Column};
+use automata::spacetime::{is_npy, read_npy, read_pbm};
+use automata::wolfram::WolframRule;
+use automata::metadata::Metadata;
+use automata::{check_block, check_width, quiescent_state, Automaton, MemoryRule, Schedule, MAX_MEMORY_DEPTH};
+use clap::{Args, ValueEnum};
+use json::{value_name, Json};
+use log::{note, verbose};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Off with `--no-metadata`
+static METADATA: AtomicBool = AtomicBool::new(true);
+/// The command line being run, if not the process's own (`--config` runs)
+static COMMAND_LINE: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn set_metadata(enabled: bool) {
+    METADATA.store(enabled, Ordering::Relaxed);
+}
+
+pub fn set_command_line(argv: &[String]) {
+    *COMMAND_LINE.lock().unwrap() = Some(config::shell_line(argv));
+}
+
+/// Provenance for the files a command writes: the version and command line,
+/// the `--preset` if any, then `params`; empty with `--no-metadata`
+pub fn metadata(params: &[(&'static str, &dyn fmt::Display)]) -> Metadata {
+    if !METADATA.load(Ordering::Relaxed) {
+        return Metadata::default();
+    }
+    let line = COMMAND_LINE.lock().unwrap().clone();
+    let line = line.unwrap_or_else(|| {
+        // The program by name, not by the path it was run from
+        let mut args: Vec<String> = std::env::args().collect();
+        if let Some(name) = Path::new(&args[0]).file_stem() {
+            args[0] = name.to_string_lossy().into_owned();
+        }
+        config::shell_line(&args)
+    });
+    let base = match preset::preset() {
+        Some(preset) => Metadata::new(&line).with("preset", preset.name()),
+        None => Metadata::new(&line),
+    };
+    params.iter().fold(base, |m, &(key, value)| m.with(key, value))
+}
+
+/// Write `metadata` as JSON next to `path` (`x.npy` gets `x.npy.json`), for
+/// formats with no room for it
+pub fn write_sidecar(path: &Path, metadata: &Metadata) -> std::io::Result<()> {
+    if metadata.is_empty() {
+        return Ok(());
+    }
+    let fields = metadata.0.iter().map(|(key, value)| (*key, Json::from(value.as_str())));
+    let head = [("schema", "automata-metadata".into()), ("version", json::VERSION.into())];
+    let json = Json::object(head.into_iter().chain(fields));
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".json");
+    verbose!("Metadata -> {}", Path::new(&sidecar).display());
+    std::fs::write(sidecar, format!("{json}\n"))
+}
+
+/// A ring width: at least one cell
+pub fn parse_width(s: &str) -> Result<usize, String> {
+    let width = s.parse().map_err(|e| format!("{e}"))?;
+    check_width(width).map_err(|e| e.to_string())?;
+    Ok(width)
+}
+
+/// A probability, 0 to 1
+pub fn parse_probability(s: &str) -> Result<f64, String> {
+    let p: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if !(0.0..=1.0).contains(&p) {
+        return Err(format!("{p} is not a probability (0 to 1)"));
+    }
+    Ok(p)
+}
+
+/// A byte count, with an optional binary suffix: 4096, 64K, 512M, 1.5G
+pub fn parse_bytes(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let scale: u64 = match unit.to_ascii_lowercase().trim_end_matches("ib").trim_end_matches('b') {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        "t" => 1 << 40,
+        _ => return Err(format!("unknown unit '{unit}': expected K, M, G or T")),
+    };
+    let number: f64 = number.trim().parse().map_err(|_| format!("'{s}' is not a size, e.g. 512M"))?;
+    let bytes = number * scale as f64;
+    if !(1.0..u64::MAX as f64).contains(&bytes) {
+        return Err(format!("'{s}' is not a size of at least a byte"));
+    }
+    Ok(bytes as u64)
+}
+
+fn parse_memory_depth(s: &str) -> Result<usize, String> {
+    let depth: usize = s.parse().map_err(|e| format!("{e}"))?;
+    if depth > MAX_MEMORY_DEPTH {
+        return Err(format!("memory is at most {MAX_MEMORY_DEPTH} rows"));
+    }
+    Ok(depth)
+}
+
+/// Exit with the reason unless blocks of `block` cells can be read off a
+/// ring of `width` (`flag` names the option that set it)
+pub fn require_block(flag: &str, block: usize, width: usize) {
+    if let Err(e) = check_block(block, width) {
+        eprintln!("{flag}: {e}");
+        std::process::exit(1);
+    }
+}
+
+/// An elementary rule, as its number (0-255) or any Wolfram code for one
+/// (k=2,r=1,code=10 is rule 150)
+pub fn parse_rule(s: &str) -> Result<u8, String> {
+    if s.bytes().all(|b| b.is_ascii_digit()) {
+        return s.parse().map_err(|_| format!("rule numbers are 0-255, not {s}"));
+    }
+    let rule: WolframRule = s.parse()?;
+    rule.elementary().ok_or_else(|| format!("{rule} is not an elementary rule (k=2, r=1); `wolfram` runs any code"))
+}
+
+/// A two-state rule for the commands that run lookup tables as well as
+/// elementary rules
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BinaryRule {
+    Elementary(u8),
+    /// Radius 2 or 3 (radius-1 tables are elementary rules)
+    Table(TableRule),
+}
+
+impl BinaryRule {
+    /// The rule in a file name: `110`, or `table-0096e81f`
+    pub fn slug(self) -> String {
+        self.to_string().replace(':', "-")
+    }
+}
+
+impl From<TableRule> for BinaryRule {
+    fn from(table: TableRule) -> Self {
+        match table.radius {
+            1 => BinaryRule::Elementary(table.table as u8),
+            _ => BinaryRule::Table(table),
+        }
+    }
+}
+
+impl fmt::Display for BinaryRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BinaryRule::Elementary(rule) => write!(f, "{rule}"),
+            BinaryRule::Table(table) => write!(f, "{table}"),
+        }
+    }
+}
+
+/// A rule number, or a table's spec as a string
+impl From<BinaryRule> for Json {
+    fn from(rule: BinaryRule) -> Self {
+        match rule {
+            BinaryRule::Elementary(rule) => rule.into(),
+            BinaryRule::Table(table) => table.to_string().into(),
+        }
+    }
+}
+
+/// An elementary rule as `parse_rule` reads it, or a lookup table of radius
+/// 2 or 3: table:<8 or 32 hex digits>, random:<radius>[:<seed>] (one of the
+/// 2^32 radius-2 tables, say) or a Wolfram code such as k=2,r=2,code=20
+pub fn parse_binary_rule(s: &str) -> Result<BinaryRule, String> {
+    match parse_rule(s) {
+        Ok(rule) => Ok(BinaryRule::Elementary(rule)),
+        Err(e) if s.bytes().all(|b| b.is_ascii_digit()) => Err(e),
+        Err(_) => s.parse::<TableRule>().map(BinaryRule::from),
+    }
+}
+
+/// How the simulation and analysis commands seed their first row
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Init {
+    /// One live cell in the center
+    Single,
+    /// Each cell live with probability `--density`, from a `--seed`ed RNG
+    Random,
+    /// A given row, leftmost cell first, padded with dead cells or
+    /// truncated to the width
+    Cells(Vec<bool>),
+}
+
+impl FromStr for Init {
+    type Err = String;
+
+    /// `single`, `random`, `bits:0110...` or `hex:deadbeef` (each hex digit
+    /// is four cells, most significant bit first)
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.split_once(':') {
+            None if s == "single" => Ok(Init::Single),
+            None if s == "random" => Ok(Init::Random),
+            Some((kind @ ("bits" | "hex"), "")) => Err(format!("{kind}: names no cells; give at least one digit")),
+            Some(("bits", bits)) => bits
+                .chars()
+                .map(|c| match c {
+                    '0' => Ok(false),
+                    '1' => Ok(true),
+                    _ => Err(format!("{c:?} is not a bit")),
+                })
+                .collect::<Result<_, _>>()
+                .map(Init::Cells),
+            Some(("hex", hex)) => hex
+                .chars()
+                .map(|c| c.to_digit(16).ok_or_else(|| format!("{c:?} is not a hex digit")))
+                .collect::<Result<Vec<_>, _>>()
+                .map(|digits| Init::Cells(digits.iter().flat_map(|d| (0..4).rev().map(move |b| (d >> b) & 1 == 1)).collect())),
+            _ => Err("expected single, random, bits:<0s and 1s> or hex:<digits>".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Init {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Init::Single => write!(f, "single"),
+            Init::Random => write!(f, "random"),
+            Init::Cells(cells) => write!(f, "bits:{}", cells.iter().map(|&c| if c { '1' } else { '0' }).collect::<String>()),
+        }
+    }
+}
+
+/// The quiescent state that initial rows, population, density and images
+/// are taken relative to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Quiescent {
+    /// All dead: cells are what they are
+    #[value(alias = "0")]
+    Dead,
+    /// All live: the row and its measures count dead cells, as for rule 255
+    #[value(alias = "1")]
+    Live,
+    /// The rule's own quiescent state: live when 000 -> 1 and 111 -> 1,
+    /// otherwise dead
+    Auto,
+    /// The homogeneous background random rows settle into, found by
+    /// simulation; rules whose ether is periodic (or absent) count as dead
+    Ether,
+}
+
+impl Quiescent {
+    /// Whether `rule` is measured against a live background
+    pub fn resolve(self, rule: u8) -> bool {
+        match self {
+            Quiescent::Dead => false,
+            Quiescent::Live => true,
+            Quiescent::Auto => quiescent_state(rule).unwrap_or(false),
+            Quiescent::Ether => detect_ether(rule, &EtherSettings { max_period: 1, ..EtherSettings::default() })
+                .and_then(|ether| ether.homogeneous())
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Initial-condition flags shared by the simulation and analysis commands
+/// (surveys run every rule from the same row)
+#[derive(Args, Clone)]
+pub struct InitArgs {
+    /// Initial row: single, random, bits:0110... or hex:deadbeef
+    #[arg(long, default_value_t = Init::Single)]
+    pub init: Init,
+    /// Fraction of live cells with `--init random`
+    #[arg(long, default_value_t = 0.5)]
+    pub density: f64,
+    /// RNG seed for `--init random`; the same seed gives the same row
+    #[arg(long, default_value_t = 42)]
+    pub seed: u64,
+    /// Start from the last row of a spacetime diagram file (as printed by
+    /// `run`), or from a file holding a bits:/hex: row
+    #[arg(long, conflicts_with = "init")]
+    pub init_file: Option<PathBuf>,
+    /// Quiescent background the row is laid over, drawn against and
+    /// measured from: dead (0), live (1), auto (the rule's own) or ether
+    /// (the one random rows settle into)
+    #[arg(long, value_enum, default_value_t = Quiescent::Dead)]
+    pub quiescent: Quiescent,
+}
+
+impl InitArgs {
+    /// The first row for `rule` on a ring of `width` cells, laid over its
+    /// background (so `single` is one defect in a live vacuum)
+    pub fn automaton(&self, width: usize, rule: u8) -> Automaton {
+        if let Some(path) = &self.init_file {
+            let cells = read_init_file(path).unwrap_or_else(|e| {
+                eprintln!("Can't read {}: {e}", path.display());
+                std::process::exit(1);
+            });
+            if cells.len() != width {
+                note!("Note: the {}-cell row in {} is {} to {width}", cells.len(), path.display(),
+                    if cells.len() < width { "padded" } else { "truncated" });
+            }
+            return InitArgs { init: Init::Cells(cells), init_file: None, ..*self }.automaton(width, rule);
+        }
+        let ca = match &self.init {
+            Init::Single => Automaton::new(width, rule),
+            Init::Random => {
+                if !(0.0..=1.0).contains(&self.density) {
+                    eprintln!("Density must be between 0 and 1, got {}", self.density);
+                    std::process::exit(1);
+                }
+                Automaton::random(width, rule, self.density, self.seed)
+            }
+            Init::Cells(cells) => {
+                let mut row = cells.clone();
+                row.resize(width, false);
+                Automaton::from_cells(row, rule)
+            }
+        };
+        ca.on_background(self.quiescent.resolve(rule))
+    }
+
+    /// `automaton` for a rule that may be a lookup table, whose own
+    /// quiescent state `--quiescent auto` finds the same way
+    pub fn automaton_for(&self, width: usize, rule: BinaryRule) -> Automaton {
+        let table = match rule {
+            BinaryRule::Elementary(rule) => return self.automaton(width, rule),
+            BinaryRule::Table(table) => table,
+        };
+        let background = match self.quiescent {
+            Quiescent::Dead => false,
+            Quiescent::Live => true,
+            Quiescent::Auto => table.quiescent().unwrap_or(false),
+            Quiescent::Ether => {
+                eprintln!("--quiescent ether is found for elementary rules; use auto, dead or live with {table}");
+                std::process::exit(1);
+            }
+        };
+        let dead = InitArgs { quiescent: Quiescent::Dead, ..self.clone() };
+        dead.automaton(width, 0).with_table(table).on_background(background)
+    }
+
+
+    /// Report suffix such as ", init=random, density=0.3, seed=42"; empty
+    /// for the default single cell, so existing reports are unchanged
+    pub fn describe(&self) -> String {
+        let quiescent = match self.quiescent {
+            Quiescent::Dead => String::new(),
+            quiescent => format!(", quiescent={}", quiescent.to_possible_value().unwrap().get_name()),
+        };
+        let init = match (&self.init_file, &self.init) {
+            (Some(path), _) => format!(", init-file={}", path.display()),
+            (None, Init::Single) => String::new(),
+            (None, Init::Random) => format!(", init=random, density={}, seed={}", self.density, self.seed),
+            (None, init @ Init::Cells(_)) => format!(", init={init}"),
+        };
+        init + &quiescent
+    }
+
+    /// `metadata` with the initial-condition parameters that matter added
+    pub fn tag(&self, metadata: Metadata) -> Metadata {
+        if metadata.is_empty() {
+            return metadata;
+        }
+        let metadata = match (&self.init_file, &self.init) {
+            (Some(path), _) => metadata.with("init_file", path.display()),
+            (None, Init::Random) => {
+                metadata.with("init", "random").with("density", self.density).with("seed", self.seed)
+            }
+            (None, init) => metadata.with("init", init),
+        };
+        match self.quiescent {
+            Quiescent::Dead => metadata,
+            quiescent => metadata.with("quiescent", quiescent.to_possible_value().unwrap().get_name()),
+        }
+    }
+
+    /// The initial-condition parameters, for a JSON report
+    pub fn to_json(&self) -> Json {
+        Json::object([
+            ("init", self.init.to_string().into()),
+            ("density", self.density.into()),
+            ("seed", self.seed.into()),
+            ("init_file", self.init_file.as_ref().map(|path| path.display().to_string()).into()),
+            ("quiescent", value_name(self.quiescent)),
+        ])
+    }
+}
+
+/// Noise flags for the commands that step one run
+#[derive(Args, Clone, Copy)]
+pub struct NoiseArgs {
+    /// Probability of flipping each cell after every step, 0 for none
+    #[arg(long, default_value_t = 0.0, value_parser = parse_probability)]
+    pub noise: f64,
+    /// RNG seed for the flips; the same seed flips the same cells
+    #[arg(long, default_value_t = 1)]
+    pub noise_seed: u64,
+}
+
+impl NoiseArgs {
+    /// `ca`, stepped with these flips
+    pub fn apply(self, ca: Automaton) -> Automaton {
+        ca.with_noise(self.noise, self.noise_seed)
+    }
+
+    pub fn is_noisy(self) -> bool {
+        self.noise > 0.0
+    }
+
+    /// Report suffix such as ", noise=0.01, noise-seed=1"; empty without
+    /// noise, like `InitArgs::describe`
+    pub fn describe(self) -> String {
+        if self.is_noisy() { format!(", noise={}, noise-seed={}", self.noise, self.noise_seed) } else { String::new() }
+    }
+
+    pub fn to_json(self) -> Json {
+        Json::object([("p", self.noise.into()), ("seed", self.noise_seed.into())])
+    }
+}
+
+/// Memory flags for the commands that step one run
+#[derive(Args, Clone, Copy)]
+pub struct MemoryArgs {
+    /// Rows before the current one the rule also reads, 0 for none (up to 32)
+    #[arg(long, default_value_t = 0, value_parser = parse_memory_depth)]
+    pub memory: usize,
+    /// How --memory combines a cell's past states with its present
+    #[arg(long, value_enum, default_value_t = Memory::Majority)]
+    pub memory_rule: Memory,
+}
+
+/// Memory rules (see `automata::MemoryRule`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Memory {
+    /// The rule reads each cell's majority state over the current row and the remembered ones
+    Majority,
+    /// The rule's output is XORed with the cell --memory rows back (second-order, reversible)
+    Xor,
+}
+
+impl MemoryArgs {
+    /// `ca`, stepped by its rule with this memory
+    pub fn apply(self, ca: Automaton) -> Automaton {
+        let rule = match self.memory_rule {
+            Memory::Majority => MemoryRule::Majority,
+            Memory::Xor => MemoryRule::Xor,
+        };
+        ca.with_memory(rule, self.memory)
+    }
+
+    /// Report suffix such as ", memory=2 (majority)"; empty without memory
+    pub fn describe(self) -> String {
+        if self.memory == 0 {
+            return String::new();
+        }
+        let rule = self.memory_rule.to_possible_value().expect("no skipped values");
+        format!(", memory={} ({})", self.memory, rule.get_name())
+    }
+
+    pub fn to_json(self) -> Json {
+        let rule = if self.memory > 0 { value_name(self.memory_rule) } else { Json::Null };
+        Json::object([("depth", self.memory.into()), ("rule", rule)])
+    }
+}
+
+/// Schedule flags for the commands that step one run
+#[derive(Args, Clone)]
+pub struct ScheduleArgs {
+    /// Elementary rules that take turns stepping the row, in place of RULE, e.g. 90,110
+    #[arg(long, value_delimiter = ',', value_parser = parse_rule, conflicts_with = "rule")]
+    pub rules: Vec<u8>,
+    /// How --rules take turns: alternate (a step each, the default) or blocks:<T> (T steps each)
+    #[arg(long, value_parser = parse_schedule, requires = "rules")]
+    pub schedule: Option<usize>,
+}
+
+/// Steps each rule of a schedule runs: `alternate` is 1, `blocks:<T>` T
+fn parse_schedule(s: &str) -> Result<usize, String> {
+    match s.strip_prefix("blocks:") {
+        None if s == "alternate" => Ok(1),
+        None => Err(format!("expected alternate or blocks:<steps>, not '{s}'")),
+        Some(steps) => match steps.parse() {
+            Ok(0) | Err(_) => Err(format!("'{steps}' is not a number of steps (at least 1)")),
+            Ok(steps) => Ok(steps),
+        },
+    }
+}
+
+impl ScheduleArgs {
+    /// The schedule, if --rules gave one
+    pub fn schedule(&self) -> Option<Schedule> {
+        (!self.rules.is_empty()).then(|| Schedule::new(self.rules.clone(), self.schedule.unwrap_or(1)))
+    }
+
+    /// `ca`, stepped by the schedule's rules in turn (unchanged without one)
+    pub fn apply(&self, ca: Automaton) -> Automaton {
+        match self.schedule() {
+            Some(schedule) => ca.with_schedule(schedule),
+            None => ca,
+        }
+    }
+
+    /// What a report header calls the rule: "Rule 110", or "Rules 90,110
+    /// alternating" for a schedule in its place
+    pub fn title(&self, rule: impl fmt::Display) -> String {
+        match self.schedule() {
+            Some(schedule) => format!("Rules {schedule}"),
+            None => format!("Rule {rule}"),
+        }
+    }
+
+    /// The rule for a JSON report: null when a schedule replaces it
+    pub fn rule_json(&self, rule: impl Into<Json>) -> Json {
+        if self.rules.is_empty() { rule.into() } else { Json::Null }
+    }
+
+    pub fn to_json(&self) -> Json {
+        let rules = Json::array(self.rules.clone());
+        self.schedule().map_or(Json::Null, |schedule| Json::object([("rules", rules), ("block", schedule.block().into())]))
+    }
+}
+
+/// How a survey reports: its usual table, one CSV row per rule, or a JSON
+/// report with one object per rule
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Table,
+    Csv,
+    Json,
+    Parquet,
+}
+
+/// How a single analysis reports: its usual table or a JSON report
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Table,
+    Json,
+}
+
+/// Output flags shared by the 256-rule surveys
+#[derive(Args, Clone)]
+pub struct SurveyOutput {
+    /// Print the table, one CSV row per rule with every computed column, the
+    /// same columns as JSON, or write them typed as Parquet (to --output;
+    /// needs the `parquet` feature)
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    pub format: Format,
+    /// Write the CSV (or the JSON or Parquet) to this file instead; the
+    /// table still goes to stdout
+    #[arg(short, long, required_if_eq("format", "parquet"))]
+    pub output: Option<PathBuf>,
+}
+
+impl SurveyOutput {
+    /// Whether the table is printed: always, unless the CSV or JSON goes to stdout
+    pub fn table(&self) -> bool {
+        self.format == Format::Table || self.output.is_some() || sink::redirected()
+    }
+
+    /// Emit the CSV (header and rows), the JSON report or the Parquet columns
+    /// where asked, and the report to a `--sink`; each is built only if
+    /// needed. Parquet files keep the report's envelope, without the
+    /// results, as `automata.report` metadata
+    pub fn write(
+        &self,
+        table: impl FnOnce() -> String,
+        json: impl Fn() -> Json,
+        columns: impl FnOnce() -> Vec<Column>,
+    ) {
+        if sink::redirected() {
+            sink::emit(json());
+        }
+        match (&self.output, self.format) {
+            (Some(path), Format::Parquet) => {
+                let Json::Object(mut envelope) = json() else { unreachable!("reports are objects") };
+                envelope.retain(|(key, _)| *key != "results");
+                let command = metadata(&[]).0.into_iter().find(|(key, _)| *key == "command");
+                let report = ("automata.report", Json::Object(envelope).to_string());
+                let metadata: Vec<(&str, String)> = std::iter::once(report)
+                    .chain(command.map(|(_, line)| ("automata.command", line)))
+                    .collect();
+                // Encoded in memory first, so a failed write leaves no empty file
+                let mut bytes = Vec::new();
+                let written =
+                    write_parquet(&mut bytes, &columns(), &metadata).and_then(|()| std::fs::write(path, bytes));
+                if let Err(e) = written {
+                    eprintln!("Can't write {}: {e}", path.display());
+                    std::process::exit(1);
+                }
+                println!("  -> {}", path.display());
+            }
+            (Some(path), Format::Json) => write_csv(path, &format!("{}\n", json()), &Metadata::default()),
+            (Some(path), _) => {
+                let Json::Object(envelope) = json() else { unreachable!("reports are objects") };
+                let parameters = envelope.into_iter().find(|(key, _)| *key == "parameters").map(|(_, p)| p.to_string());
+                let metadata = match parameters {
+                    Some(parameters) => metadata(&[("parameters", &parameters)]),
+                    None => metadata(&[]),
+                };
+                write_csv(path, &table(), &metadata)
+            }
+            (None, _) if sink::redirected() => {}
+            (None, Format::Csv) => print!("{}", table()),
+            (None, Format::Json) => sink::emit(json()),
+            (None, Format::Table | Format::Parquet) => {}
+        }
+    }
+}
+
+/// Write a CSV (or JSON) report, or exit with the error
+pub fn write_csv(path: &Path, table: &str, metadata: &Metadata) {
+    if let Err(e) = std::fs::write(path, metadata.csv_header() + table) {
+        eprintln!("Can't write {}: {e}", path.display());
+        std::process::exit(1);
+    }
+    println!("  -> {}", path.display());
+}
+
+/// The starting row stored in an `--init-file`: a bits:/hex: spec, or the
+/// last row of a spacetime diagram, so a saved run picks up where it ended
+fn read_init_file(path: &Path) -> Result<Vec<bool>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let spec = text.trim();
+    if spec.starts_with("bits:") || spec.starts_with("hex:") {
+        return match spec.parse()? {
+            Init::Cells(cells) => Ok(cells),
+            _ => unreachable!("bits: and hex: parse to cells"),
+        };
+    }
+    let trajectories = read_spacetime(path)?;
+    Ok(trajectories.into_iter().flatten().next_back().expect("read_spacetime rejects empty diagrams"))
+}
+
+/// The rows of a saved diagram: an NPY array, a PBM bitmap (one pixel per
+/// cell), or a text diagram, whose trajectories are taken one after another
+pub fn read_diagram(path: &Path) -> Result<Vec<Vec<bool>>, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    if is_npy(&bytes) {
+        read_npy(&bytes)
+    } else if bytes.starts_with(b"P4") || bytes.starts_with(b"P1") {
+        read_pbm(&bytes)
+    } else {
+        Ok(read_spacetime(path)?.into_iter().flatten().collect())
+    }
+}
+
+/// Trajectories from a text spacetime diagram
+pub fn read_spacetime(path: &Path) -> Result<Vec<Vec<Vec<bool>>>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut trajectories = vec![Vec::new()];
+    for (number, line) in text.lines().enumerate() {
+        if line.is_empty() {
+            if !trajectories.last().unwrap().is_empty() {
+                trajectories.push(Vec::new());
+            }
+            continue;
+        }
+        let row = line
+            .chars()
+            .map(|c| match c {
+                '#' | '1' => Ok(true),
+                ' ' | '.' | '0' => Ok(false),
+                _ => Err(format!("line {}: unexpected {c:?}", number + 1)),
+            })
+            .collect::<Result<Vec<bool>, _>>()?;
+        trajectories.last_mut().unwrap().push(row);
+    }
+
+    let width = trajectories[0].first().map_or(0, Vec::len);
+    if width == 0 {
+        return Err("no rows".to_string());
+    }
+    if let Some(bad) = trajectories.iter().flatten().find(|row| row.len() != width) {
+        return Err(format!("rows must all have width {width}, found one of width {}", bad.len()));
+    }
+    Ok(trajectories)
+}
+
+/// A test's own empty directory, removed with everything in it when dropped
+#[cfg(test)]
+pub struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    /// Named for the process, `name` and a counter, so no two tests (or
+    /// runs) share one
+    pub fn new(name: &str) -> Self {
+        static NEXT: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("automata-{}-{name}-{n}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// The path of `file` in the directory
+    pub fn join(&self, file: &str) -> PathBuf {
+        self.0.join(file)
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
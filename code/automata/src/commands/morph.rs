@@ -0,0 +1,319 @@
+//! `morph`: an animation whose frames are whole spacetime diagrams, one per
+//! rule along a path through rule space, each labeled
+//!
+//! Gray-code order walks all 256 ECAs changing one table bit per frame, so
+//! the diagram morphs rather than jumps; an `evolve` run's fitness log gives
+//! its best rule generation by generation. Every frame runs from the same
+//! initial row, each distinct frame is rendered once (in parallel), and a
+//! frame that repeats the last is folded into it as a longer delay.
+
+use super::progress::Progress;
+use super::run::parse_color;
+use super::{metadata, parse_binary_rule, parse_width, BinaryRule, InitArgs};
+use automata::evolve::TableRule;
+use automata::metadata::Tagged;
+use automata::render::{sheet_frame, sheet_size, write_apng_frames, write_canvas, RenderOptions, SheetOptions};
+use clap::Args;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Args, Clone)]
+pub struct MorphArgs {
+    /// Rules to visit: gray (all 256 ECAs, one table bit apart), sequential (0-255),
+    /// rules:30,90,110 or lineage:<fitness.csv> (an evolve run's best rule each generation)
+    #[arg(default_value = "gray")]
+    pub path: RulePath,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 101, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations per diagram
+    #[arg(short = 'g', long = "gens", default_value_t = 100)]
+    pub generations: usize,
+    /// Milliseconds per frame
+    #[arg(long, default_value_t = 100)]
+    pub delay: u16,
+    /// Pixels per cell side
+    #[arg(short, long, default_value_t = 2)]
+    pub cell_size: usize,
+    /// Pixels per dot of the labels (rule number, or generation for a lineage), 0 for none
+    #[arg(long, default_value_t = 2)]
+    pub label_scale: usize,
+    /// Output file
+    #[arg(short, long, default_value = "morph.png")]
+    pub out: PathBuf,
+    /// Also write every frame to this directory as frame-0001.png, ..., for a video encoder
+    #[arg(long)]
+    pub frames: Option<PathBuf>,
+    /// Live cell (and label) color, as RRGGBB hex
+    #[arg(long, default_value = "000000", value_parser = parse_color)]
+    pub live: [u8; 3],
+    /// Dead cell color, as RRGGBB hex
+    #[arg(long, default_value = "ffffff", value_parser = parse_color)]
+    pub dead: [u8; 3],
+    /// Border and label background color, as RRGGBB hex
+    #[arg(long, default_value = "e0e0e0", value_parser = parse_color)]
+    pub background: [u8; 3],
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+/// A path through rule space
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RulePath {
+    /// Every ECA in reflected Gray-code order: rule i ^ (i >> 1)
+    Gray,
+    /// Every ECA, 0 to 255
+    Sequential,
+    /// The given rules, in order
+    Rules(Vec<BinaryRule>),
+    /// The best rule of each generation in an `evolve` fitness log
+    Lineage(PathBuf),
+}
+
+impl std::str::FromStr for RulePath {
+    type Err = String;
+
+    /// `gray`, `sequential`, `rules:<rule>,<rule>,...` or `lineage:<file>`
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.split_once(':') {
+            None if s == "gray" => Ok(RulePath::Gray),
+            None if s == "sequential" => Ok(RulePath::Sequential),
+            Some(("rules", rules)) => {
+                rules.split(',').map(parse_binary_rule).collect::<Result<_, _>>().map(RulePath::Rules)
+            }
+            Some(("lineage", path)) if !path.is_empty() => Ok(RulePath::Lineage(PathBuf::from(path))),
+            _ => Err("expected gray, sequential, rules:<rule>,... or lineage:<fitness.csv>".to_string()),
+        }
+    }
+}
+
+impl RulePath {
+    /// Each stop's rule and label: the rule number for an ECA, the
+    /// generation in a lineage, else the stop's position from 1
+    fn stops(&self) -> Result<Vec<(BinaryRule, String)>, String> {
+        let label = |(i, rule): (usize, BinaryRule)| match rule {
+            BinaryRule::Elementary(n) => (rule, n.to_string()),
+            BinaryRule::Table(_) => (rule, (i + 1).to_string()),
+        };
+        match self {
+            RulePath::Gray => {
+                Ok((0..=255u8).map(|i| BinaryRule::Elementary(i ^ (i >> 1))).enumerate().map(label).collect())
+            }
+            RulePath::Sequential => Ok((0..=255u8).map(BinaryRule::Elementary).enumerate().map(label).collect()),
+            RulePath::Rules(rules) => Ok(rules.iter().copied().enumerate().map(label).collect()),
+            RulePath::Lineage(path) => {
+                let log = std::fs::read_to_string(path).map_err(|e| format!("Can't read {}: {e}", path.display()))?;
+                lineage(&log).map_err(|e| format!("{}: {e}", path.display()))
+            }
+        }
+    }
+}
+
+/// The best rule of each generation of a fitness log (columns generation,
+/// island, best, mean, best_rule), labeled with the generation it first led
+/// in, so a rule that stays best makes one frame
+fn lineage(log: &str) -> Result<Vec<(BinaryRule, String)>, String> {
+    let mut best: Vec<(usize, f64, TableRule)> = Vec::new();
+    for (n, line) in log.lines().enumerate().skip(1).filter(|(_, line)| !line.is_empty()) {
+        let fields: Vec<&str> = line.split(',').collect();
+        let bad = || format!("line {}: expected generation,island,best,mean,best_rule", n + 1);
+        let [generation, _, fitness, _, table] = fields[..] else { return Err(bad()) };
+        let generation: usize = generation.parse().map_err(|_| bad())?;
+        let fitness: f64 = fitness.parse().map_err(|_| bad())?;
+        let rule: TableRule = format!("table:{table}").parse().map_err(|e| format!("line {}: {e}", n + 1))?;
+        match best.last_mut() {
+            Some(last) if last.0 == generation => {
+                if fitness > last.1 {
+                    *last = (generation, fitness, rule);
+                }
+            }
+            _ => best.push((generation, fitness, rule)),
+        }
+    }
+    if best.is_empty() {
+        return Err("no generations logged".to_string());
+    }
+    let mut since = best[0].0;
+    Ok(best
+        .iter()
+        .enumerate()
+        .map(|(i, &(generation, _, rule))| {
+            if i > 0 && best[i - 1].2 != rule {
+                since = generation;
+            }
+            (BinaryRule::from(rule), since.to_string())
+        })
+        .collect())
+}
+
+pub fn morph(args: &MorphArgs) {
+    let MorphArgs {
+        ref path,
+        width,
+        generations,
+        delay,
+        cell_size,
+        label_scale,
+        ref out,
+        ref frames,
+        live,
+        dead,
+        background,
+        ref init,
+    } = *args;
+    let stops = path.stops().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    if stops.is_empty() {
+        eprintln!("The path has no rules");
+        std::process::exit(1);
+    }
+    let options = RenderOptions { cell_size: cell_size.max(1), live, dead, ..RenderOptions::default() };
+    let sheet = SheetOptions { columns: 1, gap: 4 * label_scale.max(1), label_scale, background };
+    let (image_width, image_height) = sheet_size(1, width, generations + 1, &sheet, &options);
+
+    let (distinct, order) = distinct(&stops);
+    let progress = Progress::rules("Frames", distinct.len());
+    let canvases: Vec<Vec<u8>> = distinct
+        .par_iter()
+        .map(|(rule, label)| {
+            let canvas = sheet_frame(&init.automaton_for(width, *rule), label, generations, &sheet, &options);
+            progress.tick();
+            canvas.unwrap_or_else(|e| {
+                eprintln!("Can't draw {rule}: {e}");
+                std::process::exit(1);
+            })
+        })
+        .collect();
+    drop(progress);
+
+    let shown = fold_repeats(&order, delay);
+    let sequence: Vec<(&[u8], u16)> = shown.iter().map(|&(frame, ms)| (canvases[frame].as_slice(), ms)).collect();
+
+    let params: [(_, &dyn std::fmt::Display); 3] =
+        [("path", &describe(path)), ("width", &width), ("generations", &generations)];
+    let metadata = init.tag(metadata(&params));
+    let written = std::fs::File::create(out).and_then(|file| {
+        let mut out = Tagged::new(BufWriter::new(file), &metadata);
+        write_apng_frames(&mut out, image_width, image_height, &sequence)?;
+        out.flush()
+    });
+    if let Err(e) = written {
+        eprintln!("Can't write {}: {e}", out.display());
+        std::process::exit(1);
+    }
+    println!(
+        "{} rules along {} (width={width}, gens={generations}{}): {} frames of {image_width} x {image_height} -> {}",
+        stops.len(),
+        describe(path),
+        init.describe(),
+        sequence.len(),
+        out.display()
+    );
+
+    if let Some(dir) = frames {
+        let written = std::fs::create_dir_all(dir).and_then(|_| {
+            order.iter().enumerate().try_for_each(|(i, &frame)| {
+                let file = std::fs::File::create(dir.join(format!("frame-{:04}.png", i + 1)))?;
+                let mut out = BufWriter::new(file);
+                write_canvas(&mut out, image_width, image_height, &canvases[frame])?;
+                out.flush()
+            })
+        });
+        if let Err(e) = written {
+            eprintln!("Can't write frames to {}: {e}", dir.display());
+            std::process::exit(1);
+        }
+        println!("{} frames -> {}", stops.len(), dir.display());
+        // yuv420p needs even dimensions
+        let even = "scale=trunc(iw/2)*2:trunc(ih/2)*2";
+        println!(
+            "  ffmpeg -framerate {} -i {}/frame-%04d.png -pix_fmt yuv420p -vf '{even}' morph.mp4",
+            1000.0 / delay.max(1) as f64,
+            dir.display()
+        );
+    }
+}
+
+/// Each distinct stop once, in path order, and every stop's index among them
+fn distinct(stops: &[(BinaryRule, String)]) -> (Vec<&(BinaryRule, String)>, Vec<usize>) {
+    let mut index = HashMap::new();
+    let mut distinct = Vec::new();
+    let order = stops
+        .iter()
+        .map(|stop| {
+            *index.entry(stop).or_insert_with(|| {
+                distinct.push(stop);
+                distinct.len() - 1
+            })
+        })
+        .collect();
+    (distinct, order)
+}
+
+/// The frames shown and for how many milliseconds: a repeat of the last
+/// frame lengthens it instead, up to the longest delay APNG can hold
+fn fold_repeats(order: &[usize], delay: u16) -> Vec<(usize, u16)> {
+    let mut shown: Vec<(usize, u16)> = Vec::new();
+    for &frame in order {
+        match shown.last_mut() {
+            Some((last, ms)) if *last == frame && *ms <= u16::MAX - delay => *ms += delay,
+            _ => shown.push((frame, delay)),
+        }
+    }
+    shown
+}
+
+/// The path as a report names it
+fn describe(path: &RulePath) -> String {
+    match path {
+        RulePath::Gray => "Gray-code order".to_string(),
+        RulePath::Sequential => "rule order".to_string(),
+        RulePath::Rules(rules) => {
+            format!("rules:{}", rules.iter().map(|rule| rule.to_string()).collect::<Vec<_>>().join(","))
+        }
+        RulePath::Lineage(path) => format!("the lineage in {}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_morph_path() {
+        let stops = |path: &str| path.parse::<RulePath>().unwrap().stops().unwrap();
+        // Morphing a rule into itself is the identity: one frame, held for the whole path
+        let same = stops("rules:30,30,30");
+        let (frames, order) = distinct(&same);
+        assert_eq!((frames.len(), fold_repeats(&order, 100)), (1, vec![(0, 300)]));
+        assert_eq!(frames, [&(BinaryRule::Elementary(30), "30".to_string())]);
+        // A rule that comes back is drawn once but shown again
+        let back = stops("rules:30,90,30");
+        let (frames, order) = distinct(&back);
+        assert_eq!((frames.len(), fold_repeats(&order, 100)), (2, vec![(0, 100), (1, 100), (0, 100)]));
+        // Held frames split before their delay overflows
+        assert_eq!(fold_repeats(&[0; 700], 100), [(0, 65500), (0, 4500)]);
+
+        // Gray-code order visits every ECA, one table bit apart
+        let gray: Vec<u8> = stops("gray")
+            .into_iter()
+            .map(|(rule, label)| match rule {
+                BinaryRule::Elementary(n) if label == n.to_string() => n,
+                _ => panic!("{rule} labeled {label}"),
+            })
+            .collect();
+        assert_eq!(distinct(&stops("gray")).0.len(), 256);
+        assert!(gray.windows(2).all(|w| (w[0] ^ w[1]).count_ones() == 1));
+
+        // A lineage shows each leader from the generation it took over
+        let log = "generation,island,best,mean,best_rule\n0,0,0.5,0.1,1e\n0,1,0.7,0.2,6e\n1,0,0.7,0.3,6e\n\
+            2,0,0.9,0.4,5a\n";
+        let labels: Vec<String> = lineage(log).unwrap().into_iter().map(|(_, label)| label).collect();
+        assert_eq!(labels, ["0", "0", "2"]);
+        assert!(lineage("generation,island,best,mean,best_rule\n").is_err());
+    }
+}
@@ -0,0 +1,438 @@
+//! `notebook`: a TOML script of steps run in order in one process, sharing
+//! the simulations they name, so a rule is run once and then measured,
+//! drawn and reported on without running it again
+//!
+//! ```toml
+//! [[step]]
+//! do = "run"
+//! name = "r110"
+//! rule = 110
+//! width = 201
+//! generations = 400
+//! init = "random"
+//! seed = 7
+//!
+//! [[step]]
+//! do = "run"
+//! name = "then30"
+//! from = "r110"
+//! rule = 30
+//! generations = 100
+//!
+//! [[step]]
+//! do = "metrics"
+//! metrics = ["density", "entropy:4", "compression", "cycle", "class"]
+//!
+//! [[step]]
+//! do = "render"
+//! runs = ["r110"]
+//! out = "figures/{run}.png"
+//! cell_size = 2
+//!
+//! [[step]]
+//! do = "report"
+//! out = "report.md"
+//! title = "Rule 110, then 30"
+//! ```
+//!
+//! A run takes `rule`, `width` and `generations` and the `init`,
+//! `density`, `seed` and `quiescent` of the simulation commands; with
+//! `from` it starts from another run's last row, on its background, with
+//! its rule and width unless they are given. `metrics` and `render` read
+//! the runs in `runs`, every one so far if it's left out, and `{run}` in a
+//! figure's path is the run's name. A report is Markdown: the title, then
+//! what every step before it did, in order, with figures linked relative
+//! to the report. The whole script is checked before any step runs.
+
+use super::{metadata, parse_binary_rule, BinaryRule, Init, InitArgs, Quiescent};
+use automata::metadata::Tagged;
+use automata::notebook::{Metric, Simulation};
+use automata::render::{write_png_rows, RenderOptions};
+use automata::toml::{self, Table, Value};
+use automata::wolfram::WolframRule;
+use clap::{Args, ValueEnum};
+use std::collections::HashMap;
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Args, Clone)]
+pub struct NotebookArgs {
+    /// TOML script of [[step]] tables, each with do = "run", "metrics", "render" or "report"
+    pub file: PathBuf,
+    /// Check the script and list its steps without running them
+    #[arg(long)]
+    pub check: bool,
+}
+
+/// A simulation to make: its name, where it starts and what runs it
+struct RunStep {
+    name: String,
+    from: Option<String>,
+    rule: Option<BinaryRule>,
+    width: Option<usize>,
+    generations: usize,
+    init: InitArgs,
+}
+
+enum Step {
+    Run(RunStep),
+    Metrics { runs: Option<Vec<String>>, metrics: Vec<Metric> },
+    Render { runs: Option<Vec<String>>, out: String, cell_size: usize },
+    Report { out: PathBuf, title: String },
+}
+
+/// A run made, with how it was made, for tables and the report
+struct Made {
+    rule: BinaryRule,
+    simulation: Simulation,
+    settings: String,
+}
+
+pub fn notebook(args: &NotebookArgs) {
+    let NotebookArgs { ref file, check } = *args;
+    let steps = read_script(file).unwrap_or_else(|e| {
+        eprintln!("{}: {e}", file.display());
+        std::process::exit(1);
+    });
+    if check {
+        for (i, step) in steps.iter().enumerate() {
+            println!("{}: {}", i + 1, summary(step));
+        }
+        return;
+    }
+
+    let mut made: Vec<(String, Made)> = Vec::new();
+    // What each step did, as Markdown for the reports after it
+    let mut sections: Vec<String> = Vec::new();
+    for (i, step) in steps.iter().enumerate() {
+        println!("[{}/{}] {}", i + 1, steps.len(), summary(step));
+        match step {
+            Step::Run(run) => {
+                let done = simulate(run, &made);
+                println!("  {}", done.settings);
+                sections.push(format!("- **{}**: {}\n", run.name, done.settings));
+                made.push((run.name.clone(), done));
+            }
+            Step::Metrics { runs, metrics } => {
+                let table = metrics_table(&pick(&made, runs), metrics);
+                print!("{}", table.text);
+                sections.push(table.markdown);
+            }
+            Step::Render { runs, out, cell_size } => {
+                for (name, done) in pick(&made, runs) {
+                    let path = PathBuf::from(out.replace("{run}", name));
+                    if let Err(e) = render(&path, done, *cell_size) {
+                        eprintln!("Can't write {}: {e}", path.display());
+                        std::process::exit(1);
+                    }
+                    println!("  {name} -> {}", path.display());
+                    sections.push(format!("![{name}]({})\n", path.display()));
+                }
+            }
+            Step::Report { out, title } => {
+                let report = report(out, title, &sections);
+                if let Err(e) = create_parent(out).and_then(|_| std::fs::write(out, report)) {
+                    eprintln!("Can't write {}: {e}", out.display());
+                    std::process::exit(1);
+                }
+                println!("  -> {}", out.display());
+            }
+        }
+    }
+}
+
+/// The runs a step reads, by name and in script order
+fn pick<'a>(made: &'a [(String, Made)], runs: &Option<Vec<String>>) -> Vec<(&'a str, &'a Made)> {
+    match runs {
+        None => made.iter().map(|(name, done)| (name.as_str(), done)).collect(),
+        Some(names) => names
+            .iter()
+            .map(|name| {
+                let (name, done) = made.iter().find(|(made, _)| made == name).expect("checked when read");
+                (name.as_str(), done)
+            })
+            .collect(),
+    }
+}
+
+fn simulate(run: &RunStep, made: &[(String, Made)]) -> Made {
+    let base = run.from.as_ref().map(|from| &made.iter().find(|(name, _)| name == from).expect("checked").1);
+    let rule = run.rule.or(base.map(|done| done.rule)).expect("checked: a run has a rule or a from");
+    let (init, label) = match base {
+        Some(done) => {
+            let last = done.simulation.deviations().pop().expect("a run has a first row");
+            let quiescent = if done.simulation.background { Quiescent::Live } else { Quiescent::Dead };
+            let from = format!(", from {}'s last row", run.from.as_deref().unwrap_or_default());
+            (InitArgs { init: Init::Cells(last), init_file: None, quiescent, ..run.init.clone() }, from)
+        }
+        None => (run.init.clone(), run.init.describe()),
+    };
+    let width = run.width.or(base.map(|done| done.simulation.width())).unwrap_or(101);
+    let ca = init.automaton_for(width, rule);
+    let simulation = Simulation::run(wolfram_rule(rule), ca, run.generations);
+    let settings = format!("rule {rule}, {width} cells, {} generations{label}", run.generations);
+    Made { rule, simulation, settings }
+}
+
+/// A binary rule as the bestiary examines it
+fn wolfram_rule(rule: BinaryRule) -> WolframRule {
+    let (radius, table) = match rule {
+        BinaryRule::Elementary(rule) => (1, rule as u128),
+        BinaryRule::Table(table) => (table.radius, table.table),
+    };
+    WolframRule::general(2, radius, &table.to_string()).expect("binary tables to radius 3 are general rules")
+}
+
+/// A metrics table, one row per run, aligned for the terminal and in Markdown
+struct MetricsTable {
+    text: String,
+    markdown: String,
+}
+
+fn metrics_table(runs: &[(&str, &Made)], metrics: &[Metric]) -> MetricsTable {
+    let mut headers = vec!["run".to_string()];
+    headers.extend(metrics.iter().map(Metric::to_string));
+    let rows: Vec<Vec<String>> = runs
+        .iter()
+        .map(|(name, done)| {
+            let readings = metrics.iter().map(|&metric| match done.simulation.measure(metric) {
+                Ok(reading) => reading.to_string(),
+                Err(e) => format!("({e})"),
+            });
+            std::iter::once(name.to_string()).chain(readings).collect()
+        })
+        .collect();
+    let widths: Vec<usize> = (0..headers.len())
+        .map(|c| rows.iter().map(|row| row[c].len()).chain([headers[c].len()]).max().unwrap())
+        .collect();
+    let line = |cells: &[String]| {
+        let cells: Vec<String> = cells.iter().zip(&widths).map(|(cell, &w)| format!("{cell:<w$}")).collect();
+        format!("  {}\n", cells.join("  ").trim_end())
+    };
+    let text = std::iter::once(line(&headers)).chain(rows.iter().map(|row| line(row))).collect();
+    let markdown_line = |cells: &[String]| format!("| {} |\n", cells.join(" | "));
+    let rule = vec!["---".to_string(); headers.len()];
+    let markdown = [markdown_line(&headers), markdown_line(&rule)]
+        .into_iter()
+        .chain(rows.iter().map(|row| markdown_line(row)))
+        .collect();
+    MetricsTable { text, markdown }
+}
+
+fn render(path: &Path, done: &Made, cell_size: usize) -> std::io::Result<()> {
+    create_parent(path)?;
+    let simulation = &done.simulation;
+    let metadata = metadata(&[("rule", &done.rule), ("settings", &done.settings)]);
+    let file = std::fs::File::create(path)?;
+    let mut out = Tagged::new(BufWriter::new(file), &metadata);
+    let options = RenderOptions { cell_size: cell_size.max(1), ..RenderOptions::default() };
+    write_png_rows(&mut out, &simulation.deviations(), &options)?;
+    out.flush()
+}
+
+fn create_parent(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => std::fs::create_dir_all(parent),
+        _ => Ok(()),
+    }
+}
+
+/// The Markdown report at `out`: runs listed, then tables and figures as
+/// they came, with figure paths made relative to the report's directory
+fn report(out: &Path, title: &str, sections: &[String]) -> String {
+    let dir = out.parent().unwrap_or(Path::new(""));
+    let mut report = format!("# {title}\n");
+    let mut last_kind = "";
+    for section in sections {
+        let kind = match section.chars().next() {
+            Some('-') => "list",
+            Some('|') => "table",
+            _ => "figure",
+        };
+        // A blank line between blocks of different kinds, and around every table
+        if kind != last_kind || kind == "table" {
+            report.push('\n');
+        }
+        match section.strip_prefix("![").and_then(|rest| rest.split_once("](")) {
+            Some((name, path)) => {
+                let path = relative(Path::new(path.trim_end().trim_end_matches(')')), dir);
+                report += &format!("![{name}]({})\n", path.display());
+            }
+            None => report.push_str(section),
+        }
+        last_kind = kind;
+    }
+    report
+}
+
+/// `path` as seen from `dir`, when both are relative to the same place
+fn relative(path: &Path, dir: &Path) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix(dir) {
+        return rest.to_path_buf();
+    }
+    let plain = |p: &Path| p.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    if path.is_absolute() || !plain(dir) || !plain(path) {
+        return path.to_path_buf();
+    }
+    let up = dir.components().filter(|c| matches!(c, Component::Normal(_))).count();
+    std::iter::repeat_n(Path::new(".."), up).collect::<PathBuf>().join(path)
+}
+
+fn summary(step: &Step) -> String {
+    let which = |runs: &Option<Vec<String>>| runs.as_ref().map_or("every run".to_string(), |names| names.join(", "));
+    match step {
+        Step::Run(run) => match &run.from {
+            Some(from) => format!("run {} from {from}", run.name),
+            None => format!("run {}", run.name),
+        },
+        Step::Metrics { runs, metrics } => {
+            let metrics: Vec<String> = metrics.iter().map(Metric::to_string).collect();
+            format!("metrics of {}: {}", which(runs), metrics.join(", "))
+        }
+        Step::Render { runs, out, .. } => format!("render {} to {out}", which(runs)),
+        Step::Report { out, .. } => format!("report to {}", out.display()),
+    }
+}
+
+/// Every step of the script at `path`, checked: keys, values and the runs
+/// each step names
+fn read_script(path: &Path) -> Result<Vec<Step>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("can't read it: {e}"))?;
+    let doc = toml::parse(&text)?;
+    let tables: Vec<&Table> = match (doc.0.as_slice(), doc.get("step")) {
+        ([(key, _)], Some(Value::Array(items))) if key == "step" => items
+            .iter()
+            .map(|item| match item {
+                Value::Table(table) => Ok(table),
+                other => Err(format!("step must be [[step]] tables, not a {}", other.type_name())),
+            })
+            .collect::<Result<_, _>>()?,
+        _ => return Err("a notebook is [[step]] tables and nothing else".to_string()),
+    };
+    let mut names: HashMap<String, usize> = HashMap::new();
+    let mut steps = Vec::new();
+    for (i, table) in tables.into_iter().enumerate() {
+        let step = read_step(table, &names, i + 1).map_err(|e| format!("step {}: {e}", i + 1))?;
+        if let Step::Run(run) = &step
+            && let Some(first) = names.insert(run.name.clone(), i + 1)
+        {
+            return Err(format!("step {}: step {first} already made a run called {:?}", i + 1, run.name));
+        }
+        steps.push(step);
+    }
+    Ok(steps)
+}
+
+fn read_step(table: &Table, names: &HashMap<String, usize>, number: usize) -> Result<Step, String> {
+    let kind = match table.get("do") {
+        Some(Value::String(kind)) => kind.as_str(),
+        _ => return Err("needs do = \"run\", \"metrics\", \"render\" or \"report\"".to_string()),
+    };
+    let keys: &[&str] = match kind {
+        "run" => &["name", "from", "rule", "width", "generations", "init", "density", "seed", "quiescent"],
+        "metrics" => &["runs", "metrics"],
+        "render" => &["runs", "out", "cell_size"],
+        "report" => &["out", "title"],
+        _ => return Err(format!("unknown step {kind:?}: expected run, metrics, render or report")),
+    };
+    if let Some((key, _)) = table.0.iter().find(|(key, _)| key != "do" && !keys.contains(&key.as_str())) {
+        return Err(format!("unknown key {key:?} for a {kind} step: expected {}", keys.join(", ")));
+    }
+    let string = |key: &str| match table.get(key) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s.clone())),
+        Some(other) => Err(format!("{key} must be a string, not a {}", other.type_name())),
+    };
+    let count = |key: &str| match table.get(key) {
+        None => Ok(None),
+        Some(&Value::Integer(n)) if n >= 0 => Ok(Some(n as usize)),
+        Some(other) => Err(format!("{key} must be a whole number, not {other}")),
+    };
+    let known = |name: &String| match names.contains_key(name) {
+        true => Ok(name.clone()),
+        false => Err(format!("no run called {name:?} before it")),
+    };
+    let runs = match table.get("runs") {
+        None => None,
+        Some(Value::Array(items)) => Some(
+            items
+                .iter()
+                .map(|item| match item {
+                    Value::String(name) => known(name),
+                    other => Err(format!("runs must be names, not a {}", other.type_name())),
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        Some(other) => Err(format!("runs must be an array of names, not a {}", other.type_name()))?,
+    };
+    if kind != "run" && kind != "report" && names.is_empty() {
+        return Err(format!("a {kind} step needs a run before it"));
+    }
+
+    Ok(match kind {
+        "run" => {
+            let from = string("from")?.map(|name| known(&name)).transpose()?;
+            let rule = match table.get("rule") {
+                None if from.is_none() => return Err("a run needs a rule, or a from to take one".to_string()),
+                None => None,
+                Some(rule) => Some(parse_binary_rule(&super::config::scalar("rule", rule)?)?),
+            };
+            let defaults = InitArgs {
+                init: Init::Single,
+                density: 0.5,
+                seed: 42,
+                init_file: None,
+                quiescent: Quiescent::Dead,
+            };
+            let density = match table.get("density") {
+                None => defaults.density,
+                Some(&Value::Float(x)) if (0.0..=1.0).contains(&x) => x,
+                Some(&Value::Integer(n @ (0 | 1))) => n as f64,
+                Some(other) => return Err(format!("density must be between 0 and 1, not {other}")),
+            };
+            let init = InitArgs {
+                init: string("init")?.map(|init| init.parse()).transpose()?.unwrap_or(defaults.init),
+                density,
+                seed: count("seed")?.map_or(defaults.seed, |seed| seed as u64),
+                quiescent: match string("quiescent")? {
+                    None => defaults.quiescent,
+                    Some(name) => Quiescent::from_str(&name, false).map_err(|_| {
+                        format!("quiescent must be dead, live, auto or ether, not {name:?}")
+                    })?,
+                },
+                ..defaults
+            };
+            let width = count("width")?;
+            if width == Some(0) {
+                return Err("width must be at least 1".to_string());
+            }
+            let name = string("name")?.unwrap_or_else(|| format!("run{number}"));
+            Step::Run(RunStep { name, from, rule, width, generations: count("generations")?.unwrap_or(100), init })
+        }
+        "metrics" => {
+            let metrics = match table.get("metrics") {
+                Some(Value::Array(items)) if !items.is_empty() => items
+                    .iter()
+                    .map(|item| match item {
+                        Value::String(metric) => metric.parse(),
+                        other => Err(format!("metrics must be names, not a {}", other.type_name())),
+                    })
+                    .collect::<Result<_, _>>()?,
+                _ => return Err("needs metrics = [...], e.g. [\"density\", \"entropy:4\", \"class\"]".to_string()),
+            };
+            Step::Metrics { runs, metrics }
+        }
+        "render" => {
+            let out = string("out")?.ok_or("needs out, the figure's path, e.g. \"figures/{run}.png\"")?;
+            let picked = runs.as_ref().map_or(names.len(), Vec::len);
+            if picked > 1 && !out.contains("{run}") {
+                return Err(format!("{out:?} would be written once per run; put {{run}} in it"));
+            }
+            Step::Render { runs, out, cell_size: count("cell_size")?.unwrap_or(1) }
+        }
+        _ => {
+            let out = PathBuf::from(string("out")?.ok_or("needs out, the report's path, e.g. \"report.md\"")?);
+            Step::Report { out, title: string("title")?.unwrap_or_else(|| "Notebook".to_string()) }
+        }
+    })
+}
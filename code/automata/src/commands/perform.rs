@@ -0,0 +1,325 @@
+//! `perform`: a run played live, one generation per clock tick, as OSC
+//! messages or MIDI notes for a synthesizer to sound
+//!
+//! The clock keeps to the tempo by scheduling each tick from the last
+//! deadline rather than from when the previous one finished. Between ticks
+//! the loop waits on a channel fed by a stdin reader and, with `--listen`,
+//! a UDP thread decoding OSC, so the rule, seed and tempo change on the
+//! next tick without stopping the music.
+
+use super::log::note;
+use super::{parse_rule, parse_width, Init, InitArgs};
+use automata::perform::{midi_note, osc_message, parse_osc_message, toggles, NoteMap, OscArg};
+use automata::Automaton;
+use clap::{Args, ValueEnum};
+use std::io::{BufRead, Write};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::path::PathBuf;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+
+#[derive(Args, Clone)]
+pub struct PerformArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 90, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Number of cells in the ring, one pitch each
+    #[arg(short, long, default_value_t = 24, value_parser = parse_width)]
+    pub width: usize,
+    /// Tempo in beats per minute
+    #[arg(long, default_value_t = 120.0)]
+    pub bpm: f64,
+    /// Generations per beat: 4 plays sixteenth notes
+    #[arg(long, default_value_t = 4)]
+    pub steps_per_beat: u32,
+    /// Stop after this many generations (default: until quit)
+    #[arg(short = 'g', long = "gens")]
+    pub generations: Option<usize>,
+    /// Send OSC messages to this address, e.g. SuperCollider's 127.0.0.1:57120
+    #[arg(long, value_name = "HOST:PORT")]
+    pub osc: Option<String>,
+    /// Write MIDI to this raw MIDI device, e.g. /dev/snd/midiC1D0, or file
+    #[arg(long, value_name = "PATH")]
+    pub midi: Option<PathBuf>,
+    /// MIDI channel
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=16))]
+    pub channel: u8,
+    /// Velocity of each note on
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u8).range(1..=127))]
+    pub velocity: u8,
+    /// MIDI note of cell 0 (48 is the C below middle C)
+    #[arg(long, default_value_t = 48, value_parser = clap::value_parser!(u8).range(0..=127))]
+    pub lowest: u8,
+    /// Scale the cells climb, left to right
+    #[arg(long, value_enum, default_value_t = Scale::Pentatonic)]
+    pub scale: Scale,
+    /// Also take control messages as OSC on this UDP port: /rule N, /seed N,
+    /// /bpm X, /reset and /quit (with or without an /automata prefix)
+    #[arg(long, value_name = "PORT")]
+    pub listen: Option<u16>,
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+/// Scales for `perform`
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Scale {
+    Chromatic,
+    Major,
+    Minor,
+    Pentatonic,
+}
+
+/// A change asked for mid-performance
+enum Control {
+    Rule(u8),
+    /// A fresh random row from this seed
+    Seed(u64),
+    Bpm(f64),
+    /// Back to the initial row
+    Reset,
+    Quit,
+}
+
+impl Control {
+    /// `rule 30`, `seed 7`, `bpm 140`, `reset` or `quit`, from stdin
+    fn parse(line: &str) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let (command, value) = (words.next().unwrap_or(""), words.next());
+        let value = || value.ok_or_else(|| format!("{command} needs a value"));
+        match command {
+            "rule" => Ok(Control::Rule(parse_rule(value()?)?)),
+            "seed" => value()?.parse().map(Control::Seed).map_err(|e| format!("seed: {e}")),
+            "bpm" => value()?.parse().map(Control::Bpm).map_err(|e| format!("bpm: {e}")),
+            "reset" => Ok(Control::Reset),
+            "quit" | "q" => Ok(Control::Quit),
+            _ => Err(format!("unknown command {line:?}; try rule N, seed N, bpm X, reset or quit")),
+        }
+    }
+
+    /// The same controls as OSC messages
+    fn from_osc(packet: &[u8]) -> Result<Self, String> {
+        let (address, args) = parse_osc_message(packet).ok_or("not an OSC message")?;
+        let number = args.first().and_then(OscArg::as_f64);
+        let value = || number.ok_or_else(|| format!("{address} needs a number"));
+        let whole = |x: f64| (x >= 0.0 && x.fract() == 0.0).then_some(x).ok_or(format!("{address}: {x} isn't whole"));
+        match address.strip_prefix("/automata").unwrap_or(&address) {
+            "/rule" => u8::try_from(whole(value()?)? as u64).map(Control::Rule).map_err(|e| format!("/rule: {e}")),
+            "/seed" => Ok(Control::Seed(whole(value()?)? as u64)),
+            "/bpm" => Ok(Control::Bpm(value()?)),
+            "/reset" => Ok(Control::Reset),
+            "/quit" => Ok(Control::Quit),
+            _ => Err(format!("unknown address {address}")),
+        }
+    }
+}
+
+/// Where the notes go
+struct Outputs {
+    osc: Option<(UdpSocket, SocketAddr)>,
+    midi: Option<std::fs::File>,
+    channel: u8,
+}
+
+impl Outputs {
+    fn row(&mut self, generation: usize, ca: &Automaton) {
+        let cells: String = ca.deviations().map(|live| if live { '1' } else { '0' }).collect();
+        self.send("/automata/row", &[OscArg::Int(generation as i32), OscArg::Str(cells)]);
+    }
+
+    fn note(&mut self, note: u8, velocity: u8) {
+        self.send("/automata/note", &[OscArg::Int(note as i32), OscArg::Int(velocity as i32)]);
+        if let Some(midi) = &mut self.midi {
+            // A device that has gone away ends the performance
+            if let Err(e) = midi.write_all(&midi_note(self.channel, note, velocity)) {
+                eprintln!("Can't write MIDI: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    fn send(&mut self, address: &str, args: &[OscArg]) {
+        if let Some((socket, target)) = &self.osc {
+            // UDP: a receiver that isn't listening yet just misses the message
+            let _ = socket.send_to(&osc_message(address, args), target);
+        }
+    }
+}
+
+pub fn perform(args: &PerformArgs) {
+    let PerformArgs {
+        rule,
+        width,
+        bpm,
+        steps_per_beat,
+        generations,
+        ref osc,
+        ref midi,
+        channel,
+        velocity,
+        lowest,
+        scale,
+        listen,
+        ref init,
+    } = *args;
+    let interval = |bpm: f64| Duration::from_secs_f64(60.0 / (bpm * steps_per_beat as f64));
+    if !(bpm.is_finite() && bpm > 0.0) || steps_per_beat == 0 {
+        eprintln!("--bpm and --steps-per-beat must be positive");
+        std::process::exit(1);
+    }
+
+    let osc = osc.as_ref().map(|target| {
+        let address = target.to_socket_addrs().ok().and_then(|mut addresses| addresses.next()).unwrap_or_else(|| {
+            eprintln!("Can't resolve --osc {target}; expected HOST:PORT, e.g. 127.0.0.1:57120");
+            std::process::exit(1);
+        });
+        let local = if address.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+        let socket = UdpSocket::bind(local).unwrap_or_else(|e| {
+            eprintln!("Can't open a UDP socket: {e}");
+            std::process::exit(1);
+        });
+        (socket, address)
+    });
+    let midi = midi.as_ref().map(|path| {
+        std::fs::OpenOptions::new().append(true).create(true).open(path).unwrap_or_else(|e| {
+            eprintln!("Can't open {}: {e}", path.display());
+            std::process::exit(1);
+        })
+    });
+    if osc.is_none() && midi.is_none() {
+        note!("No --osc or --midi: showing the rows only");
+    }
+    let mut outputs = Outputs { osc, midi, channel: channel - 1 };
+    let scale = match scale {
+        Scale::Chromatic => automata::perform::Scale::Chromatic,
+        Scale::Major => automata::perform::Scale::Major,
+        Scale::Minor => automata::perform::Scale::Minor,
+        Scale::Pentatonic => automata::perform::Scale::Pentatonic,
+    };
+    let notes = NoteMap { lowest, scale };
+
+    let (controls, received) = mpsc::channel();
+    read_stdin(controls.clone());
+    if let Some(port) = listen {
+        listen_osc(port, controls);
+    }
+
+    println!("Rule {rule} (width={width}{}) at {bpm} bpm, {steps_per_beat} steps per beat", init.describe());
+    note!("Type rule N, seed N, bpm X, reset or quit and Enter to change it as it plays");
+    let (mut ca, mut rule, mut tick) = (init.automaton(width, rule), rule, interval(bpm));
+    let mut previous: Option<Automaton> = None;
+    let mut next_tick = Instant::now();
+    let mut generation = 0;
+    loop {
+        outputs.row(generation, &ca);
+        for (i, live) in toggles(previous.as_ref(), &ca) {
+            if let Some(note) = notes.note(i) {
+                outputs.note(note, if live { velocity } else { 0 });
+            }
+        }
+        println!("{ca}");
+        previous = Some(ca.clone());
+        if generations.is_some_and(|limit| generation >= limit) {
+            break;
+        }
+
+        // Until the next tick, apply controls as they arrive; a tick lost to
+        // a stall is skipped, not rushed through
+        next_tick = (next_tick + tick).max(Instant::now());
+        let (mut replaced, mut quit) = (false, false);
+        loop {
+            let control = match received.recv_timeout(next_tick.saturating_duration_since(Instant::now())) {
+                Ok(control) => control,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => {
+                    std::thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+                    break;
+                }
+            };
+            match control {
+                Control::Rule(new) => {
+                    rule = new;
+                    ca = ca.with_rule(rule);
+                    note!("Rule {rule} from generation {}", generation + 1);
+                }
+                Control::Seed(seed) => {
+                    let random = InitArgs { init: Init::Random, seed, init_file: None, ..init.clone() };
+                    (ca, replaced) = (random.automaton(width, rule), true);
+                    note!("Random row from seed {seed} at generation {}", generation + 1);
+                }
+                Control::Bpm(new) if new.is_finite() && new > 0.0 => {
+                    tick = interval(new);
+                    note!("{new} bpm");
+                }
+                Control::Bpm(new) => note!("bpm must be positive, not {new}"),
+                Control::Reset => {
+                    (ca, replaced) = (init.automaton(width, rule), true);
+                    note!("Initial row at generation {}", generation + 1);
+                }
+                Control::Quit => {
+                    quit = true;
+                    break;
+                }
+            }
+        }
+        if quit {
+            break;
+        }
+        if !replaced {
+            ca.step();
+        }
+        generation += 1;
+    }
+
+    // Let go of every note still sounding
+    if let Some(last) = &previous {
+        for i in (0..last.width()).filter(|&i| last.deviates(i)) {
+            if let Some(note) = notes.note(i) {
+                outputs.note(note, 0);
+            }
+        }
+    }
+    println!("Rule {rule} stopped at generation {generation}");
+}
+
+/// Send each stdin line as a control; a bad line gets a note, and the end
+/// of input just leaves the performance running
+fn read_stdin(controls: Sender<Control>) {
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match Control::parse(&line) {
+                Ok(control) => {
+                    if controls.send(control).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => note!("{e}"),
+            }
+        }
+    });
+}
+
+/// Send each OSC message arriving on `port` as a control
+fn listen_osc(port: u16, controls: Sender<Control>) {
+    let socket = UdpSocket::bind(("0.0.0.0", port)).unwrap_or_else(|e| {
+        eprintln!("Can't listen on UDP port {port}: {e}");
+        std::process::exit(1);
+    });
+    std::thread::spawn(move || {
+        let mut packet = [0u8; 1536];
+        while let Ok((len, from)) = socket.recv_from(&mut packet) {
+            match Control::from_osc(&packet[..len]) {
+                Ok(control) => {
+                    if controls.send(control).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => note!("OSC from {from}: {e}"),
+            }
+        }
+    });
+}
@@ -0,0 +1,261 @@
+//! `preimages`: the rows a rule maps onto a target row, counted and listed
+//! by walks on its de Bruijn diagram, at widths brute force can't reach.
+//! `goe-search`: whether a row has a predecessor, and the shortest orphan,
+//! by SAT or by the diagram
+
+use super::json::{print_report, Json};
+use super::log::verbose;
+use super::{parse_binary_rule, parse_width, BinaryRule, Init, InitArgs, ReportFormat};
+use automata::evolve::TableRule;
+use automata::preimage::{orphan, sat_orphan, sat_preimage, Boundary, Preimages, SatSearch};
+use automata::sat::Outcome;
+use clap::{Args, ValueEnum};
+
+/// Width of the target row without `-w` or a `bits:`/`hex:` row
+const DEFAULT_WIDTH: usize = 101;
+
+#[derive(Args, Clone)]
+pub struct PreimagesArgs {
+    /// Rule number (0-255), a Wolfram code, or a radius-2 or 3 table (r=2:hex)
+    #[arg(default_value = "30", value_parser = parse_binary_rule)]
+    pub rule: BinaryRule,
+    /// Number of cells in the target row (default: a given row's own, else 101)
+    #[arg(short, long, value_parser = parse_width)]
+    pub width: Option<usize>,
+    /// Treat the target as an open row, whose preimages are 2r cells wider,
+    /// instead of a ring
+    #[arg(long)]
+    pub open: bool,
+    /// Only count the preimages
+    #[arg(long)]
+    pub count: bool,
+    /// Most preimages listed
+    #[arg(long, default_value_t = 20)]
+    pub limit: usize,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    /// The target row (the usual first-row flags)
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+/// The rule as a table, and the target row its first-row flags give
+fn target(rule: BinaryRule, width: Option<usize>, init: &InitArgs) -> (TableRule, Vec<bool>) {
+    let width = width.unwrap_or(match &init.init {
+        Init::Cells(cells) if init.init_file.is_none() => cells.len(),
+        _ => DEFAULT_WIDTH,
+    });
+    let table = match rule {
+        BinaryRule::Elementary(rule) => TableRule::new(1, rule as u128),
+        BinaryRule::Table(table) => table,
+    };
+    (table, init.automaton_for(width, rule).cells())
+}
+
+fn bits(cells: &[bool]) -> String {
+    cells.iter().map(|&c| if c { '1' } else { '0' }).collect()
+}
+
+pub fn preimages(args: &PreimagesArgs) {
+    let PreimagesArgs { rule, width, open, count, limit, format, ref init } = *args;
+    let (table, target) = target(rule, width, init);
+    let width = target.len();
+    let boundary = if open { Boundary::Open } else { Boundary::Ring };
+    let diagram = Preimages::new(table, &target, boundary).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    let total = diagram.count();
+    let found = if count { Vec::new() } else { diagram.enumerate(limit) };
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("boundary", if open { "open" } else { "ring" }.into()),
+            ("target", bits(&target).into()),
+            ("limit", (!count).then_some(limit).into()),
+        ]);
+        let results = Json::object([
+            ("count", Json::Number(total.to_string())),
+            ("garden_of_eden", total.is_zero().into()),
+            ("preimage_width", diagram.width().into()),
+            ("preimages", Json::array(found.iter().map(|x| bits(x)))),
+        ]);
+        return print_report("preimages", parameters, results);
+    }
+
+    let shape = if open { "open row" } else { "ring" };
+    println!("Rule {rule}, {width}-cell {shape}: {} de Bruijn nodes, {} edges", diagram.nodes(), 2 * diagram.nodes());
+    if width <= 200 {
+        println!("  Target    {}", target.iter().map(|&c| if c { '#' } else { '.' }).collect::<String>());
+    }
+    if total.is_zero() {
+        println!("No preimages: the target is a Garden of Eden");
+        return;
+    }
+    let size = match total.to_u64() {
+        Some(1) => " preimage".to_string(),
+        Some(_) => " preimages".to_string(),
+        None => format!(" preimages (about 2^{})", total.bits() - 1),
+    };
+    println!("{total}{size}, {} cells wide", diagram.width());
+    for x in &found {
+        println!("  {}", x.iter().map(|&c| if c { '#' } else { '.' }).collect::<String>());
+    }
+    if !count && total.to_u64().is_none_or(|n| n > found.len() as u64) {
+        println!("  ... the first {} (--limit)", found.len());
+    }
+}
+
+/// How `goe-search` answers
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    /// The built-in CDCL solver
+    Sat,
+    /// Walks on the de Bruijn diagram, and its node sets breadth-first
+    DeBruijn,
+}
+
+#[derive(Args, Clone)]
+pub struct GoeSearchArgs {
+    /// Rule number (0-255), a Wolfram code, or a radius-2 or 3 table (r=2:hex)
+    #[arg(default_value = "110", value_parser = parse_binary_rule)]
+    pub rule: BinaryRule,
+    /// Number of cells in the target row (default: a given row's own, else 101)
+    #[arg(short, long, value_parser = parse_width)]
+    pub width: Option<usize>,
+    /// Treat the target as an open row, whose predecessors are 2r cells
+    /// wider, instead of a ring
+    #[arg(long)]
+    pub open: bool,
+    /// Look for the shortest orphan (a row with no predecessor anywhere) up
+    /// to this many cells, instead of a target's predecessor
+    #[arg(long, value_name = "MAX")]
+    pub orphans: Option<usize>,
+    #[arg(long, value_enum, default_value_t = Backend::Sat)]
+    pub backend: Backend,
+    /// Conflicts each SAT search may take before it gives up
+    #[arg(long, default_value_t = 1_000_000)]
+    pub conflicts: u64,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    /// The target row (the usual first-row flags)
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+/// A SAT search's formula, for the report
+fn formula(search: &SatSearch) -> String {
+    format!("{} variables, {} clauses", search.vars, search.clauses)
+}
+
+pub fn goe_search(args: &GoeSearchArgs) {
+    let GoeSearchArgs { rule, width, open, orphans, backend, conflicts, format, ref init } = *args;
+    if let Some(max) = orphans {
+        return orphan_search(rule, max, backend, conflicts, format);
+    }
+    let (table, target) = target(rule, width, init);
+    let width = target.len();
+    let boundary = if open { Boundary::Open } else { Boundary::Ring };
+    let preimages = Preimages::new(table, &target, boundary).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+    // The predecessor, or None when there's none and Err when undecided
+    let (found, how) = match backend {
+        Backend::Sat => {
+            let search = sat_preimage(&preimages, Some(conflicts));
+            let how = format!("by SAT ({})", formula(&search));
+            match search.outcome {
+                Outcome::Sat(row) => (Ok(Some(row)), how),
+                Outcome::Unsat => (Ok(None), how),
+                Outcome::Unknown => (Err(conflicts), how),
+            }
+        }
+        Backend::DeBruijn => (Ok(preimages.enumerate(1).pop()), "by de Bruijn walks".to_string()),
+    };
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("boundary", if open { "open" } else { "ring" }.into()),
+            ("target", bits(&target).into()),
+            ("backend", if backend == Backend::Sat { "sat" } else { "de-bruijn" }.into()),
+            ("conflicts", conflicts.into()),
+        ]);
+        let results = Json::object([
+            ("decided", found.is_ok().into()),
+            ("garden_of_eden", found.as_ref().ok().map(Option::is_none).into()),
+            ("predecessor", found.as_ref().ok().and_then(|row| row.as_deref().map(bits)).into()),
+        ]);
+        return print_report("goe-search", parameters, results);
+    }
+
+    let shape = if open { "open row" } else { "ring" };
+    println!("Rule {rule}, {width}-cell {shape}, {how}");
+    match found {
+        Ok(Some(row)) if row.len() <= 200 => {
+            println!("A predecessor, {} cells wide:", row.len());
+            println!("  {}", row.iter().map(|&c| if c { '#' } else { '.' }).collect::<String>());
+        }
+        Ok(Some(row)) => println!("A predecessor, {} cells wide (--format json has it)", row.len()),
+        Ok(None) => println!("No predecessor: the target is a Garden of Eden"),
+        Err(conflicts) => println!("Undecided after {conflicts} conflicts (--conflicts)"),
+    }
+}
+
+/// The shortest orphan up to `max` cells: SAT length by length, or the
+/// diagram's breadth-first search over node sets
+fn orphan_search(rule: BinaryRule, max: usize, backend: Backend, conflicts: u64, format: ReportFormat) {
+    let table = match rule {
+        BinaryRule::Elementary(rule) => TableRule::new(1, rule as u128),
+        BinaryRule::Table(table) => table,
+    };
+    // The orphan, or None for none up to max; Err is the length left undecided
+    let found: Result<Option<Vec<bool>>, usize> = match backend {
+        Backend::Sat => (1..=max)
+            .find_map(|length| {
+                let search = sat_orphan(table, length, Some(conflicts));
+                let said = match search.outcome {
+                    Outcome::Sat(_) => "an orphan",
+                    Outcome::Unsat => "none",
+                    Outcome::Unknown => "undecided",
+                };
+                verbose!("Length {length}: {said} ({})", formula(&search));
+                match search.outcome {
+                    Outcome::Sat(row) => Some(Ok(Some(row))),
+                    Outcome::Unsat => None,
+                    Outcome::Unknown => Some(Err(length)),
+                }
+            })
+            .unwrap_or(Ok(None)),
+        Backend::DeBruijn => Ok(orphan(table).filter(|row| row.len() <= max)),
+    };
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("max_length", max.into()),
+            ("backend", if backend == Backend::Sat { "sat" } else { "de-bruijn" }.into()),
+            ("conflicts", conflicts.into()),
+        ]);
+        let results = Json::object([
+            ("orphan", found.as_ref().ok().and_then(|row| row.as_deref().map(bits)).into()),
+            ("undecided_length", found.as_ref().err().copied().into()),
+        ]);
+        return print_report("goe-search", parameters, results);
+    }
+
+    match found {
+        Ok(Some(row)) => {
+            println!("Rule {rule}: shortest orphan, {} cells: {}", row.len(), bits(&row));
+            println!("  No row has it anywhere in its image, so every row containing it is a Garden of Eden");
+        }
+        Ok(None) => println!("Rule {rule}: no orphan of up to {max} cells (a surjective rule has none at any length)"),
+        Err(length) => {
+            println!("Rule {rule}: no orphan shorter than {length}; length {length} undecided (--conflicts)")
+        }
+    }
+}
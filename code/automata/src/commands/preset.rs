@@ -0,0 +1,202 @@
+//! `--preset quick|standard|thorough`: one set of sizes for the cycle
+//! searches and the long-run analyses
+//!
+//! Each command otherwise has defaults of its own (31 cells for `analyze`,
+//! 79 for `entropy`, 201 for `complexity`), so their numbers don't compare.
+//! A preset fills in, unless the command line gives them, by adding the
+//! flags to the command line before it is parsed again:
+//!
+//! - for the cycle searches `analyze`, `cycle` and `sample-basins`: the
+//!   ring width and step limit, and `sample-basins`'s row count;
+//! - for the long-run analyses `trajectory`, `entropy`, `return-map`,
+//!   `interpolate`, `describe`, `lattice`, `compress` and `complexity`,
+//!   and the surveys `bestiary`, `wolfram-survey`, `entropy-survey`,
+//!   `compress-survey` and `complexity-survey`: the ring width,
+//!   generations, transient and block size of those that take them, and
+//!   `interpolate`'s replicates.
+//!
+//! Cycle searches get a narrower ring than the rest, since a period can
+//! grow as 2^width; within each group the sizes are the same. Widths are
+//! odd and blocks get at least four samples each, so a preset raises no
+//! sampling or width warnings of its own. The preset is recorded in JSON
+//! reports and file metadata, and reaches `--config` runs and batch
+//! children too.
+//!
+//! Every other command keeps its own defaults, whatever the preset: among
+//! them `basins` (every state of its ring), `budget`, `identify` and the
+//! other inference commands, `lyapunov`, drawing, playback and evolution.
+//! Their sizes are part of what they show or can afford.
+
+use super::log::verbose;
+use clap::ValueEnum;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// The preset chosen, as its index + 1; 0 for none
+static PRESET: AtomicU8 = AtomicU8::new(0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// Small rings and short runs, for a first look: cycle searches on 23
+    /// cells for 1000 steps, runs of 100 generations on 41
+    Quick,
+    /// Sizes that settle most measurements: cycle searches on 31 cells for
+    /// 10,000 steps, runs of 200 generations on 79
+    Standard,
+    /// Large rings, long runs and big ensembles: cycle searches on 41 cells
+    /// for 100,000 steps, runs of 1000 generations on 199
+    Thorough,
+}
+
+/// Cycle searches, whose periods can grow as 2^width
+const CYCLE_COMMANDS: &[&str] = &["analyze", "cycle", "sample-basins"];
+
+/// Analyses of a long run on a wide ring
+const RUN_COMMANDS: &[&str] = &[
+    "bestiary",
+    "wolfram-survey",
+    "trajectory",
+    "entropy",
+    "entropy-survey",
+    "return-map",
+    "interpolate",
+    "describe",
+    "lattice",
+    "compress",
+    "compress-survey",
+    "complexity",
+    "complexity-survey",
+];
+
+/// A flag a preset sets for some commands, and its value under each preset
+struct Setting {
+    commands: &'static [&'static str],
+    flag: &'static str,
+    values: [&'static str; 3],
+}
+
+/// The wide rings are odd and at least four samples for each possible
+/// block, so no preset warns of an undersampled entropy or a 2^n artifact
+const SETTINGS: [Setting; 8] = [
+    Setting { commands: CYCLE_COMMANDS, flag: "width", values: ["23", "31", "41"] },
+    Setting { commands: CYCLE_COMMANDS, flag: "max-steps", values: ["1000", "10000", "100000"] },
+    Setting { commands: CYCLE_COMMANDS, flag: "samples", values: ["100", "1000", "10000"] },
+    Setting { commands: RUN_COMMANDS, flag: "width", values: ["41", "79", "199"] },
+    Setting { commands: RUN_COMMANDS, flag: "gens", values: ["100", "200", "1000"] },
+    Setting { commands: RUN_COMMANDS, flag: "skip", values: ["50", "100", "500"] },
+    Setting { commands: RUN_COMMANDS, flag: "block-size", values: ["3", "4", "5"] },
+    Setting { commands: RUN_COMMANDS, flag: "replicates", values: ["2", "4", "16"] },
+];
+
+impl Preset {
+    /// The preset's name, as given to `--preset`
+    pub fn name(self) -> &'static str {
+        ["quick", "standard", "thorough"][self as usize]
+    }
+}
+
+pub fn set_preset(preset: Option<Preset>) {
+    PRESET.store(preset.map_or(0, |p| p as u8 + 1), Ordering::Relaxed);
+}
+
+/// The preset chosen, if any
+pub fn preset() -> Option<Preset> {
+    match PRESET.load(Ordering::Relaxed) {
+        0 => None,
+        n => Some(Preset::value_variants()[n as usize - 1]),
+    }
+}
+
+/// `argv` (program name first) with the chosen preset's flags added for
+/// those its subcommand takes and doesn't set. Unchanged without a preset,
+/// or if it doesn't parse, to fail later with the usual message
+pub fn expand(cli: &clap::Command, argv: &[String]) -> Vec<String> {
+    match preset() {
+        Some(preset) => apply(preset, cli, argv),
+        None => argv.to_vec(),
+    }
+}
+
+fn apply(preset: Preset, cli: &clap::Command, argv: &[String]) -> Vec<String> {
+    let mut expanded = argv.to_vec();
+    let Ok(matches) = cli.clone().try_get_matches_from(argv) else { return expanded };
+    let Some((name, sub_matches)) = matches.subcommand() else { return expanded };
+    let sub = cli.find_subcommand(name).expect("a parsed subcommand exists");
+    let mut added = Vec::new();
+    for setting in SETTINGS.iter().filter(|s| s.commands.contains(&name)) {
+        let Some(arg) = sub.get_arguments().find(|arg| arg.get_long() == Some(setting.flag)) else { continue };
+        if sub_matches.value_source(arg.get_id().as_str()) != Some(clap::parser::ValueSource::CommandLine) {
+            added.push(format!("--{}={}", setting.flag, setting.values[preset as usize]));
+        }
+    }
+    if !added.is_empty() {
+        verbose!("Preset {}: {}", preset.name(), added.join(" "));
+    }
+    // Before any `--`, after which they would be positional
+    let end = expanded.iter().position(|arg| arg == "--").unwrap_or(expanded.len());
+    expanded.splice(end..end, added);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn applied(preset: Preset, line: &str) -> String {
+        let argv: Vec<String> = line.split(' ').map(String::from).collect();
+        apply(preset, &crate::Cli::command(), &argv).join(" ")
+    }
+
+    #[test]
+    fn test_sizes() {
+        // What each preset's help says it sets: cycle width and steps, then
+        // run generations and width
+        let sizes = [
+            (Preset::Quick, "23", "1000", "100", "41"),
+            (Preset::Standard, "31", "10000", "200", "79"),
+            (Preset::Thorough, "41", "100000", "1000", "199"),
+        ];
+        for (preset, width, steps, gens, run_width) in sizes {
+            let help = preset.to_possible_value().unwrap().get_help().unwrap().to_string().replace(",000", "000");
+            let sizes = format!("{width} cells for {steps} steps, runs of {gens} generations on {run_width}");
+            assert!(help.contains(&sizes), "{help}");
+            assert!(applied(preset, "automata cycle 30").ends_with(&format!("--width={width} --max-steps={steps}")));
+            let complexity = applied(preset, "automata complexity 30");
+            assert!(complexity.starts_with(&format!("automata complexity 30 --width={run_width} --gens={gens} ")));
+        }
+        assert_eq!(
+            applied(Preset::Standard, "automata entropy-survey"),
+            "automata entropy-survey --width=79 --gens=200 --block-size=4"
+        );
+        assert_eq!(applied(Preset::Quick, "automata sample-basins 110").matches("--").count(), 3);
+        // Drawing, the exhaustive and the inference commands keep their own sizes
+        for line in ["automata render 110 -w 300", "automata basins 30", "automata budget 30", "automata identify"] {
+            assert_eq!(applied(Preset::Thorough, line), line);
+        }
+    }
+
+    #[test]
+    fn test_help_names_commands() {
+        // --preset's help lists the commands it reaches, and no others
+        let cli = crate::Cli::command();
+        let preset = cli.get_arguments().find(|arg| arg.get_long() == Some("preset")).unwrap();
+        let help = preset.get_help().unwrap().to_string();
+        let named: Vec<&str> = help.split(|c: char| !c.is_ascii_lowercase() && c != '-').collect();
+        for command in CYCLE_COMMANDS.iter().chain(RUN_COMMANDS) {
+            assert!(named.contains(command), "{command} missing from: {help}");
+        }
+        for command in ["basins", "budget", "identify", "lyapunov", "render"] {
+            assert!(!named.contains(&command), "{command} in: {help}");
+        }
+    }
+
+    #[test]
+    fn test_flags_win() {
+        let line = "automata trajectory 30 -w 101 --gens=50 --block-size 6";
+        assert_eq!(applied(Preset::Thorough, line), format!("{line} --skip=500"));
+        // Preset flags go before a `--`, and a line that doesn't parse is left alone
+        let analyze = applied(Preset::Quick, "automata analyze --max-steps 5 --");
+        assert_eq!(analyze, "automata analyze --max-steps 5 --width=23 --");
+        assert_eq!(applied(Preset::Quick, "automata cycle --nonsense"), "automata cycle --nonsense");
+    }
+}
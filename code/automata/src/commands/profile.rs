@@ -0,0 +1,84 @@
+//! The `--profile` footer: wall time, peak memory, the memory budget and
+//! the library's counters over one command, printed to stderr so that stdout (tables,
+//! CSV, JSON reports) is the same with and without it
+
+use super::json::{Json, VERSION};
+use super::ReportFormat;
+use automata::budget;
+use automata::profile::{self, Counters};
+use std::time::Instant;
+
+pub struct Profiler {
+    start: Instant,
+    counters: Counters,
+}
+
+impl Profiler {
+    pub fn start() -> Self {
+        Self { start: Instant::now(), counters: profile::snapshot() }
+    }
+
+    pub fn report(&self, command: &str, format: ReportFormat) {
+        let wall = self.start.elapsed().as_secs_f64();
+        let now = profile::snapshot();
+        let states = now.states - self.counters.states;
+        let lookups = now.cache_lookups - self.counters.cache_lookups;
+        let hits = now.cache_hits - self.counters.cache_hits;
+        let backend = now.backend.unwrap_or("cpu");
+        let threads = rayon::current_num_threads();
+        let peak = peak_memory();
+        let degradations = budget::degradations();
+
+        match format {
+            ReportFormat::Json => eprintln!(
+                "{}",
+                Json::object([
+                    ("schema", "automata-profile".into()),
+                    ("version", VERSION.into()),
+                    ("command", command.into()),
+                    ("wall_seconds", wall.into()),
+                    ("peak_memory_bytes", peak.into()),
+                    ("memory_budget_bytes", budget::budget().into()),
+                    ("degradations", Json::array(degradations)),
+                    ("states_stored", states.into()),
+                    ("cache_lookups", lookups.into()),
+                    ("cache_hits", hits.into()),
+                    ("backend", backend.into()),
+                    ("threads", threads.into()),
+                ])
+            ),
+            ReportFormat::Table => {
+                eprintln!("\nProfile ({command}):");
+                eprintln!("  wall time      {wall:.3} s");
+                match peak {
+                    Some(bytes) => eprintln!("  peak memory    {:.1} MiB", bytes as f64 / (1 << 20) as f64),
+                    None => eprintln!("  peak memory    unknown"),
+                }
+                if let Some(bytes) = budget::budget() {
+                    let (n, plural) = (degradations.len(), if degradations.len() == 1 { "" } else { "s" });
+                    eprintln!("  memory budget  {}, {n} degradation{plural}", budget::bytes(bytes));
+                }
+                eprintln!("  states stored  {states}");
+                match lookups {
+                    0 => eprintln!("  cache hits     none (no cached lookups)"),
+                    _ => {
+                        let rate = 100.0 * hits as f64 / lookups as f64;
+                        eprintln!("  cache hits     {hits} of {lookups} ({rate:.1}%)")
+                    }
+                }
+                eprintln!("  backend        {backend}, {threads} thread{}", if threads == 1 { "" } else { "s" });
+            }
+        }
+    }
+}
+
+/// The process's peak resident set size, in bytes
+fn peak_memory() -> Option<u64> {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return None;
+    }
+    // Linux counts kilobytes, macOS bytes
+    let scale = if cfg!(target_os = "macos") { 1 } else { 1024 };
+    Some(usage.ru_maxrss as u64 * scale)
+}
@@ -0,0 +1,114 @@
+//! Progress on stderr for the slow commands: rules done and an ETA for the
+//! 256-rule surveys, a generation counter for long single-rule runs
+//!
+//! The line is redrawn in place at most ten times a second and erased when
+//! the work is done, so it never mixes into a report. It only appears when
+//! both stdout and stderr are terminals: piped or redirected output, and
+//! `--quiet`, get none.
+
+use super::log::{self, Level};
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const REDRAW: Duration = Duration::from_millis(100);
+const BAR: usize = 30;
+
+/// Counts `total` units of work, from any thread
+pub struct Progress {
+    label: String,
+    kind: Kind,
+    total: u64,
+    done: AtomicU64,
+    start: Instant,
+    /// When the line was last drawn; None if progress is off
+    drawn: Option<Mutex<Instant>>,
+}
+
+impl Progress {
+    /// A bar over `total` rules
+    pub fn rules(label: &str, total: usize) -> Self {
+        Self::new(label, Kind::Rules, total)
+    }
+
+    /// A counter over `total` generations
+    pub fn generations(label: &str, total: usize) -> Self {
+        Self::new(label, Kind::Generations, total)
+    }
+
+    fn new(label: &str, kind: Kind, total: usize) -> Self {
+        let terminals = std::io::stdout().is_terminal() && std::io::stderr().is_terminal();
+        let shown = terminals && log::enabled(Level::Normal);
+        let start = Instant::now();
+        Self {
+            label: label.to_string(),
+            kind,
+            total: total as u64,
+            done: AtomicU64::new(0),
+            start,
+            // Nothing for the first redraw interval: quick runs never show a line
+            drawn: shown.then(|| Mutex::new(start)),
+        }
+    }
+
+    /// Count one more unit done
+    pub fn tick(&self) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        let Some(drawn) = &self.drawn else {
+            return;
+        };
+        // Another thread drawing now means this tick can go unshown
+        let Ok(mut drawn) = drawn.try_lock() else {
+            return;
+        };
+        if drawn.elapsed() >= REDRAW {
+            *drawn = Instant::now();
+            eprint!("\r{}\x1b[K", self.line(done));
+            let _ = std::io::stderr().flush();
+        }
+    }
+
+    fn line(&self, done: u64) -> String {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let eta = match done {
+            0 => String::new(),
+            _ => format!(", ETA {}", clock(elapsed / done as f64 * self.total.saturating_sub(done) as f64)),
+        };
+        let fraction = done as f64 / self.total.max(1) as f64;
+        match self.kind {
+            Kind::Rules => {
+                let filled = ((fraction * BAR as f64) as usize).min(BAR);
+                let bar = format!("[{}{}]", "#".repeat(filled), "-".repeat(BAR - filled));
+                format!("{} {bar} {done}/{} rules{eta}", self.label, self.total)
+            }
+            Kind::Generations => {
+                format!("{}: generation {done}/{} ({:.0}%){eta}", self.label, self.total, 100.0 * fraction)
+            }
+        }
+    }
+}
+
+enum Kind {
+    Rules,
+    Generations,
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        if self.drawn.is_some() {
+            eprint!("\r\x1b[K");
+            let _ = std::io::stderr().flush();
+        }
+    }
+}
+
+/// Seconds as `1h02m`, `3m07s` or `12s`
+fn clock(seconds: f64) -> String {
+    let s = seconds.round() as u64;
+    match s {
+        0..60 => format!("{s}s"),
+        60..3600 => format!("{}m{:02}s", s / 60, s % 60),
+        _ => format!("{}h{:02}m", s / 3600, s / 60 % 60),
+    }
+}
@@ -0,0 +1,2038 @@
+//! Visualization: spacetime diagrams in the terminal or as PNG/SVG images
+
+use super::json::{print_report, report, value_name, Json};
+use super::log::{note, verbose};
+use super::progress::Progress;
+use super::{
+    metadata, parse_binary_rule, parse_rule, parse_width, read_diagram, require_block, write_csv, write_sidecar,
+    BinaryRule, Init, InitArgs, MemoryArgs, NoiseArgs, Quiescent, ReportFormat, ScheduleArgs,
+};
+use automata::basins::find_attractor;
+use automata::damage::difference_pattern;
+use automata::ether::{detect_ether, EtherSettings};
+use automata::evolve::{TableRule, MAX_RADIUS};
+use automata::hashlife::HashLife;
+use automata::inference::TransitionDataset;
+use automata::linear::{Circulant, Linear};
+use automata::metadata::{Metadata, Tagged};
+use automata::preimage::Inverse;
+use automata::render::{
+    apng_window, image_size, sheet_size, write_apng, write_pbm, write_plot, write_png, write_png_diff, write_png_ether,
+    write_png_rgb, write_png_rows, write_png_states, write_png_tile, write_ppm, write_sheet, write_svg, PlotOptions,
+    RenderOptions, SheetOptions, SvgOptions,
+};
+use automata::session::{Bookmark, Note, Session};
+use automata::spacetime::{diff_spacetimes, write_npy};
+use automata::symmetry;
+use automata::wolfram::{random_row, Family, WolframRule};
+use automata::{principal_plane, Automaton};
+use clap::{Args, ValueEnum};
+use std::collections::VecDeque;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[derive(Args, Clone)]
+pub struct RunArgs {
+    /// Rule number (0-255), a Wolfram code such as k=2,r=2,code=20, or a
+    /// radius-2 or -3 table: table:<hex> or random:<radius>[:<seed>]
+    #[arg(default_value_t = BinaryRule::Elementary(110), value_parser = parse_binary_rule)]
+    pub rule: BinaryRule,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 79, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate (default: 40, or until quit with --live)
+    #[arg(short = 'g', long = "gens")]
+    pub generations: Option<usize>,
+    /// Animate in place instead of printing every row: space pauses, n
+    /// steps while paused, +/- change speed, h/l pan, b bookmarks, 1-9
+    /// jump to a bookmark, a adds a note, s saves, e exports, q quits
+    #[arg(long)]
+    pub live: bool,
+    /// Frames per second for --live
+    #[arg(long, default_value_t = 10.0)]
+    pub fps: f64,
+    /// Session file for --live, restored from if it exists (its rule, row
+    /// and view replacing the command line's); s writes it, or
+    /// automata-session.toml without one, and e exports the screen beside
+    /// it as PNG and CSV
+    #[arg(long, requires = "live")]
+    pub session: Option<PathBuf>,
+    /// Start at generation T of a linear rule, reached by powers of its
+    /// step as a circulant matrix over GF(2) instead of T steps (rings of a
+    /// few thousand cells; no noise, memory or schedule)
+    #[arg(long, value_name = "T")]
+    pub fast_forward: Option<u64>,
+    /// Reach --fast-forward T for any rule by memoized block evolution
+    /// (HashLife), stepping the rest plainly if its tables outgrow the
+    /// memory budget
+    #[arg(long, requires = "fast_forward")]
+    pub turbo: bool,
+    /// Run backwards, each row the one preimage of the row above: for rules
+    /// that permute the ring's rows (reversible ones, or ones such as 150 on
+    /// the widths they permute)
+    #[arg(long, conflicts_with_all = ["live", "fast_forward"])]
+    pub reverse: bool,
+    /// Show the damage instead: the XOR of the run and a copy with this
+    /// cell flipped (default: the middle one), the perturbation's light cone
+    #[arg(long, value_name = "CELL", num_args = 0..=1, conflicts_with_all = ["live", "fast_forward", "reverse"])]
+    pub damage: Option<Option<usize>>,
+    #[command(flatten)]
+    pub init: InitArgs,
+    #[command(flatten)]
+    pub noise: NoiseArgs,
+    #[command(flatten)]
+    pub memory: MemoryArgs,
+    #[command(flatten)]
+    pub schedule: ScheduleArgs,
+}
+
+pub fn run(args: &RunArgs) {
+    let RunArgs {
+        rule, width, generations, live, ref session, fast_forward, ref init, noise, memory, ref schedule, ..
+    } = *args;
+    if live {
+        let restored = session.as_deref().filter(|path| path.exists()).map(read_session);
+        return run_live(args, restored);
+    }
+
+    let mut ca = noise.apply(memory.apply(schedule.apply(init.automaton_for(width, rule))));
+    if let Some(t) = fast_forward {
+        ca = jump(args, &ca, t);
+    }
+    let name = schedule.title(rule);
+    let jumped = fast_forward.map(|t| format!(", from generation {t}")).unwrap_or_default();
+    let label = format!("{}{}{}{jumped}", init.describe(), memory.describe(), noise.describe());
+    if args.reverse {
+        run_backwards(args, &ca, &name, &label);
+    } else if let Some(cell) = args.damage {
+        let cell = damaged_cell(cell, width);
+        show_damage(&ca, cell, &name, generations.unwrap_or(40), &label);
+    } else {
+        run_and_display(ca, &name, generations.unwrap_or(40), &label);
+    }
+
+    // Show what makes each rule tick
+    let rules = match rule {
+        _ if !schedule.rules.is_empty() => schedule.rules.clone(),
+        BinaryRule::Elementary(rule) => vec![rule],
+        BinaryRule::Table(table) => {
+            let size = 2 * table.radius + 1;
+            println!("\nRule {rule} transition table (radius {}):", table.radius);
+            let entries: Vec<String> =
+                (0..table.entries()).rev().map(|w| format!("{w:0size$b} -> {}", (table.table >> w) & 1)).collect();
+            for line in entries.chunks(4) {
+                println!("  {}", line.join("    "));
+            }
+            return;
+        }
+    };
+    for rule in rules {
+        println!("\nRule {rule} transition table:");
+        println!("  neighborhood -> next");
+        for i in (0..8).rev() {
+            let pattern = format!(
+                "{}{}{}",
+                (i >> 2) & 1,
+                (i >> 1) & 1,
+                i & 1
+            );
+            let result = (rule >> i) & 1;
+            println!("      {pattern}      ->  {result}");
+        }
+    }
+}
+
+/// Run a CA for n generations and print each row, under a header of the
+/// rule's `name` ("Rule 30") and `label`'s settings
+fn run_and_display(mut ca: Automaton, name: &str, generations: usize, label: &str) {
+    println!("{name}{label}");
+    println!("{}", "-".repeat(ca.width()));
+    println!("{ca}");
+
+    for _ in 0..generations {
+        ca.step();
+        println!("{ca}");
+    }
+
+    println!("{}", "-".repeat(ca.width()));
+}
+
+/// `--damage`'s cell on a ring of `width`, by default the middle one; exits
+/// if it's off the ring
+fn damaged_cell(cell: Option<usize>, width: usize) -> usize {
+    let cell = cell.unwrap_or(width / 2);
+    if cell >= width {
+        eprintln!("--damage {cell} is off a {width}-cell ring (cells 0 to {})", width - 1);
+        std::process::exit(1);
+    }
+    cell
+}
+
+/// `run_and_display` for the damage: each row the cells where the run and
+/// its copy with `cell` flipped differ
+fn show_damage(ca: &Automaton, cell: usize, name: &str, generations: usize, label: &str) {
+    let pattern = difference_pattern(ca, cell, generations);
+    println!("{name}{label}, damage from flipping cell {cell}");
+    println!("{}", "-".repeat(ca.width()));
+    for row in &pattern {
+        println!("{}", row.iter().map(|&x| if x { '#' } else { ' ' }).collect::<String>());
+    }
+    println!("{}", "-".repeat(ca.width()));
+    let last = pattern.last().map_or(0, |row| row.iter().filter(|&&x| x).count());
+    println!("{last} cells damaged after {generations} generations");
+}
+
+/// `run_and_display` in reverse: each row the preimage of the one above,
+/// through the rule's inverse on the ring. Exits unless the run is plain
+/// and the rule permutes the ring's rows
+fn run_backwards(args: &RunArgs, ca: &Automaton, name: &str, label: &str) {
+    let RunArgs { rule, width, generations, noise, memory, ref schedule, .. } = *args;
+    if noise.is_noisy() || memory.memory > 0 || schedule.schedule().is_some() {
+        eprintln!("--reverse undoes one rule: drop --noise, --memory and --rules");
+        std::process::exit(1);
+    }
+    let table = match rule {
+        BinaryRule::Elementary(rule) => TableRule::new(1, rule as u128),
+        BinaryRule::Table(table) => table,
+    };
+    let inverse = Inverse::new(table, width).unwrap_or_else(|e| {
+        eprintln!("Can't run rule {rule} backwards: {e}");
+        std::process::exit(1);
+    });
+    let background = ca.background();
+    let show =
+        |cells: &[bool]| cells.iter().map(|&cell| if cell != background { '#' } else { ' ' }).collect::<String>();
+    println!("{name}{label}, backwards");
+    println!("{}", "-".repeat(width));
+    let mut cells = ca.cells();
+    println!("{}", show(&cells));
+    for _ in 0..generations.unwrap_or(40) {
+        cells = inverse.step(&cells);
+        println!("{}", show(&cells));
+    }
+    println!("{}", "-".repeat(width));
+}
+
+/// `ca`, built from `args`, `t` generations on: with `--turbo` by memoized
+/// block evolution, else the circulant power of its linear rule's step
+/// applied to the row, rebuilt over the same background. Exits unless the
+/// run is plain and, without `--turbo`, the rule linear
+fn jump(args: &RunArgs, ca: &Automaton, t: u64) -> Automaton {
+    let RunArgs { rule, width, ref init, noise, memory, ref schedule, turbo, .. } = *args;
+    if noise.is_noisy() || memory.memory > 0 || schedule.schedule().is_some() {
+        eprintln!("--fast-forward steps one rule: drop --noise, --memory and --rules");
+        std::process::exit(1);
+    }
+    let table = match rule {
+        BinaryRule::Elementary(rule) => TableRule::new(1, rule as u128),
+        BinaryRule::Table(table) => table,
+    };
+    let cells = if turbo {
+        turbo_jump(table, ca, t)
+    } else {
+        let linear = Linear::detect(table).unwrap_or_else(|e| {
+            eprintln!("--fast-forward needs a rule linear over GF(2), or --turbo, and {rule} isn't linear: {e}");
+            std::process::exit(1);
+        });
+        Circulant::step(&linear, width).pow(t).apply(&ca.cells())
+    };
+    let background = ca.background();
+    let deviations = cells.iter().map(|&cell| cell != background).collect();
+    let quiescent = if background { Quiescent::Live } else { Quiescent::Dead };
+    InitArgs { init: Init::Cells(deviations), init_file: None, quiescent, ..init.clone() }.automaton_for(width, rule)
+}
+
+/// The cells of `ca`, whose rule is `table`, `t` generations on by
+/// HashLife, then stepping whatever its tables couldn't hold
+fn turbo_jump(table: TableRule, ca: &Automaton, t: u64) -> Vec<bool> {
+    let mut hashlife = HashLife::new(table);
+    let (cells, done) = hashlife.advance(&ca.cells(), t);
+    let (nodes, results) = hashlife.size();
+    verbose!("Turbo: generation {done} of {t} from {nodes} blocks and {results} memoized results");
+    if done == t {
+        return cells;
+    }
+    let cap = hashlife.cap();
+    note!("Turbo: {cap} blocks filled by generation {done}; stepping the other {}", t - done);
+    let mut ca = Automaton::from_cells(cells, 0).with_table(table);
+    let progress = Progress::generations(&format!("Rule {table}"), (t - done) as usize);
+    for _ in done..t {
+        ca.step();
+        progress.tick();
+    }
+    ca.cells()
+}
+
+/// The --live keys, under the status line when there's nothing else to say
+const KEYS: &str = "space pause, n step, +/- speed, h/l pan, b mark, 1-9 jump, a note, s save, e export, q quit";
+
+/// A saved session, or exit saying why it can't be read
+fn read_session(path: &Path) -> Session {
+    let session = std::fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|text| Session::parse(&text));
+    session.unwrap_or_else(|e| {
+        eprintln!("Can't restore the session in {}: {e}", path.display());
+        std::process::exit(1);
+    })
+}
+
+/// Animate the run in the alternate screen, newest row at the bottom, until
+/// the user quits (at `generations`, if given, it holds the last frame);
+/// from `restored`'s rule, row and view if there is one
+fn run_live(args: &RunArgs, restored: Option<Session>) {
+    let RunArgs { rule, width, generations, fps, ref session, ref init, noise, memory, ref schedule, .. } = *args;
+    let session = session.as_deref().unwrap_or(Path::new("automata-session.toml"));
+    let bad_rule = |e: String| {
+        eprintln!("Can't restore the session in {}: {e}", session.display());
+        std::process::exit(1);
+    };
+    let rule = restored.as_ref().map_or(rule, |saved| parse_binary_rule(&saved.rule).unwrap_or_else(bad_rule));
+    let width = restored.as_ref().map_or(width, |saved| saved.row.len());
+    // Bookmarks and restores rebuild the run from a row of deviations, on
+    // the background the first row was laid over
+    let fresh = || noise.apply(memory.apply(schedule.apply(init.automaton_for(width, rule))));
+    let background = restored.as_ref().map_or_else(|| fresh().background(), |saved| saved.background);
+    let quiescent = if background { Quiescent::Live } else { Quiescent::Dead };
+    let from_row = |row: &[bool]| {
+        let init = InitArgs { init: Init::Cells(row.to_vec()), init_file: None, quiescent, ..init.clone() };
+        noise.apply(memory.apply(schedule.apply(init.automaton_for(width, rule))))
+    };
+    let mut ca = match (&restored, args.fast_forward) {
+        (Some(saved), _) => from_row(&saved.row),
+        (None, Some(t)) => jump(args, &fresh(), t),
+        (None, None) => fresh(),
+    };
+    let mut state = restored.unwrap_or_else(|| Session {
+        rule: rule.to_string(),
+        start: init.describe(),
+        generation: args.fast_forward.unwrap_or(0) as usize,
+        row: ca.deviations().collect(),
+        background,
+        offset: 0,
+        fps,
+        bookmarks: Vec::new(),
+        notes: Vec::new(),
+    });
+    let name = schedule.title(rule);
+    let label = format!("{}{}{}", state.start, memory.describe(), noise.describe());
+
+    let terminal = RawTerminal::enable();
+    let (rows, columns) = terminal_size();
+    let rows = rows.saturating_sub(3).max(1);
+    let mut history = VecDeque::from([(state.generation, ca.deviations().collect::<Vec<bool>>())]);
+    let (mut paused, mut fps) = (false, state.fps.clamp(0.5, 120.0));
+    // A note being typed, and the last key's outcome
+    let (mut typing, mut message): (Option<Vec<u8>>, Option<String>) = (None, None);
+    let mut next_step = Instant::now();
+    let start = state.generation;
+
+    let mut out = io::stdout().lock();
+    let _ = write!(out, "\x1b[?1049h\x1b[?25l"); // alternate screen, hidden cursor
+    loop {
+        state.offset = state.offset.min(width.saturating_sub(columns));
+        let generation = state.generation;
+        let fits = |line: String| line.chars().take(columns).collect::<String>();
+        let run = if paused { "paused" } else { "running" };
+        let last = (state.offset + columns).min(width) - 1;
+        let pan = if width > columns { format!(" | cells {}-{last}", state.offset) } else { String::new() };
+        let status = format!("{name}{label} | gen {generation} | {fps:.1} fps | {run}{pan}");
+        let mut frame = format!("\x1b[H{}\x1b[K\n", fits(status));
+        let on_screen: Vec<String> = state
+            .notes
+            .iter()
+            .filter(|note| history.iter().any(|(g, _)| *g == note.generation))
+            .map(|note| format!("{}: {}", note.generation, note.text))
+            .collect();
+        let second = match (&typing, &message) {
+            (Some(text), _) => {
+                let typed = String::from_utf8_lossy(text);
+                format!("Note for generation {generation}: {typed}_ (Enter adds it, Esc drops it)")
+            }
+            (None, Some(message)) => message.clone(),
+            (None, None) if !on_screen.is_empty() => format!("Notes: {}", on_screen.join("; ")),
+            (None, None) => KEYS.to_string(),
+        };
+        frame.push_str(&fits(second));
+        frame.push_str("\x1b[K\n");
+        for (_, row) in &history {
+            frame.extend(row[state.offset..].iter().take(columns).map(|&cell| if cell { '#' } else { ' ' }));
+            frame.push_str("\x1b[K\n");
+        }
+        frame.push_str("\x1b[J");
+        if out.write_all(frame.as_bytes()).and_then(|_| out.flush()).is_err() {
+            break;
+        }
+
+        // Wait for a key or the next step; without a terminal there are no keys
+        let done = generations.is_some_and(|limit| generation >= start + limit);
+        let key = match &terminal {
+            Some(terminal) => terminal.key((!paused && !done).then(|| next_step.saturating_duration_since(Instant::now()))),
+            None if done => break,
+            None => {
+                std::thread::sleep(next_step.saturating_duration_since(Instant::now()));
+                None
+            }
+        };
+        if let (Some(text), Some(key)) = (&mut typing, key) {
+            match key {
+                b'\r' | b'\n' => {
+                    let text = String::from_utf8_lossy(text).trim().to_string();
+                    if !text.is_empty() {
+                        message = Some(format!("Noted generation {generation}"));
+                        state.notes.push(Note { generation, text });
+                    }
+                    typing = None;
+                }
+                0x1b | 0x03 => typing = None,
+                0x7f | 0x08 => {
+                    // Drop the last character, all its UTF-8 bytes
+                    while text.pop().is_some_and(|byte| byte & 0xc0 == 0x80) {}
+                }
+                byte if byte >= b' ' => text.push(byte),
+                _ => {}
+            }
+            continue;
+        }
+        if key.is_some() {
+            message = None;
+        }
+        let interval = Duration::from_secs_f64(1.0 / fps);
+        let step = match key {
+            Some(b'q' | b'Q' | 0x03) => break,
+            Some(b' ') => {
+                paused = !paused;
+                next_step = Instant::now() + interval;
+                false
+            }
+            Some(b'n' | b'.') => paused && !done,
+            Some(b'+' | b'=') => {
+                fps = (fps * 1.5).min(120.0);
+                false
+            }
+            Some(b'-') => {
+                fps = (fps / 1.5).max(0.5);
+                false
+            }
+            Some(b'h') => {
+                state.offset = state.offset.saturating_sub((columns / 4).max(1));
+                false
+            }
+            Some(b'l') => {
+                state.offset += (columns / 4).max(1);
+                false
+            }
+            Some(b'b') if state.bookmarks.len() < 9 => {
+                state.bookmarks.push(Bookmark { generation, row: ca.deviations().collect() });
+                message = Some(format!("Bookmark {}: generation {generation}", state.bookmarks.len()));
+                false
+            }
+            Some(b'b') => {
+                message = Some("All 9 bookmarks are taken".to_string());
+                false
+            }
+            Some(digit @ b'1'..=b'9') => {
+                match state.bookmarks.get((digit - b'1') as usize) {
+                    Some(bookmark) => {
+                        ca = from_row(&bookmark.row);
+                        state.generation = bookmark.generation;
+                        history = VecDeque::from([(bookmark.generation, bookmark.row.clone())]);
+                        let number = digit - b'0';
+                        message = Some(format!("Back at bookmark {number}, generation {}", bookmark.generation));
+                    }
+                    None => message = Some(format!("No bookmark {}", digit - b'0')),
+                }
+                false
+            }
+            Some(b'a') => {
+                typing = Some(Vec::new());
+                paused = true;
+                false
+            }
+            Some(b's') => {
+                state.row = ca.deviations().collect();
+                state.fps = fps;
+                message = Some(match std::fs::write(session, state.to_toml()) {
+                    Ok(()) => format!("Saved to {}", session.display()),
+                    Err(e) => format!("Can't write {}: {e}", session.display()),
+                });
+                false
+            }
+            Some(b'e') => {
+                let shown: Vec<(usize, Vec<bool>)> = history
+                    .iter()
+                    .map(|(g, row)| (*g, row[state.offset..].iter().take(columns).copied().collect()))
+                    .collect();
+                message = Some(match export_screen(session, &shown, rule, state.offset) {
+                    Ok((png, csv)) => format!("Exported the screen to {} and {}", png.display(), csv.display()),
+                    Err(e) => format!("Can't export the screen: {e}"),
+                });
+                false
+            }
+            Some(_) => false,
+            None => !paused && !done,
+        };
+        if step {
+            ca.step();
+            state.generation += 1;
+            history.push_back((state.generation, ca.deviations().collect()));
+            if history.len() > rows {
+                history.pop_front();
+            }
+            next_step = (next_step + interval).max(Instant::now());
+        }
+    }
+
+    drop(terminal);
+    let _ = write!(out, "\x1b[?25h\x1b[?1049l");
+    let _ = out.flush();
+    println!("{name} stopped at generation {}", state.generation);
+}
+
+/// The rows on screen, each its generation and the cells in view from
+/// column `offset`, as a PNG and a CSV named after the session file:
+/// `automata-session-120.png` for generation 120 at the bottom
+fn export_screen(
+    session: &Path,
+    shown: &[(usize, Vec<bool>)],
+    rule: BinaryRule,
+    offset: usize,
+) -> io::Result<(PathBuf, PathBuf)> {
+    let last = shown.last().map_or(0, |(generation, _)| *generation);
+    let stem = session.file_stem().unwrap_or_default().to_string_lossy();
+    let named = |extension: &str| session.with_file_name(format!("{stem}-{last}.{extension}"));
+    let (png, csv) = (named("png"), named("csv"));
+    let first = shown.first().map_or(0, |(generation, _)| *generation);
+    let metadata = metadata(&[("rule", &rule), ("generations", &format!("{first}-{last}")), ("offset", &offset)]);
+    let rows: Vec<Vec<bool>> = shown.iter().map(|(_, row)| row.clone()).collect();
+    let file = std::fs::File::create(&png)?;
+    let mut out = Tagged::new(BufWriter::new(file), &metadata);
+    write_png_rows(&mut out, &rows, &RenderOptions::default())?;
+    out.flush()?;
+    let table: String = std::iter::once("generation,cells\n".to_string())
+        .chain(shown.iter().map(|(generation, row)| {
+            format!("{generation},{}\n", row.iter().map(|&cell| if cell { '1' } else { '0' }).collect::<String>())
+        }))
+        .collect();
+    std::fs::write(&csv, metadata.csv_header() + &table)?;
+    Ok((png, csv))
+}
+
+/// Terminal height in rows and width in columns, or 24 x 80 when stdout
+/// isn't a terminal
+fn terminal_size() -> (usize, usize) {
+    #[cfg(unix)]
+    {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        // SAFETY: TIOCGWINSZ writes a winsize into the pointer it's given
+        if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0 && size.ws_row > 0 {
+            return (size.ws_row as usize, (size.ws_col as usize).max(1));
+        }
+    }
+    (24, 80)
+}
+
+/// Stdin in raw mode, for single-key controls without Enter; the saved
+/// settings are restored on drop. None when stdin isn't a terminal.
+#[cfg(unix)]
+struct RawTerminal {
+    saved: libc::termios,
+}
+
+#[cfg(unix)]
+impl RawTerminal {
+    fn enable() -> Option<Self> {
+        // SAFETY: termios is plain data, filled in by tcgetattr before use
+        unsafe {
+            let mut saved: libc::termios = std::mem::zeroed();
+            if libc::isatty(libc::STDIN_FILENO) == 0 || libc::tcgetattr(libc::STDIN_FILENO, &mut saved) != 0 {
+                return None;
+            }
+            // No line buffering, echo or signals: Ctrl-C arrives as a key, so
+            // the terminal is always restored
+            let mut raw = saved;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            (libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) == 0).then_some(Self { saved })
+        }
+    }
+
+    /// The next key pressed within `timeout` (or ever, for None)
+    fn key(&self, timeout: Option<Duration>) -> Option<u8> {
+        let mut stdin = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+        let millis = timeout.map_or(-1, |t| t.as_millis().min(i32::MAX as u128) as i32);
+        let mut key = 0u8;
+        // SAFETY: one pollfd, and a one-byte read into a local
+        unsafe {
+            if libc::poll(&mut stdin, 1, millis) <= 0 {
+                return None;
+            }
+            (libc::read(libc::STDIN_FILENO, (&mut key as *mut u8).cast(), 1) == 1).then_some(key)
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        // SAFETY: restores the settings read in `enable`
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved) };
+    }
+}
+
+/// Elsewhere there is no raw mode: `--live` just animates
+#[cfg(not(unix))]
+struct RawTerminal;
+
+#[cfg(not(unix))]
+impl RawTerminal {
+    fn enable() -> Option<Self> {
+        None
+    }
+
+    fn key(&self, _timeout: Option<Duration>) -> Option<u8> {
+        None
+    }
+}
+
+/// How `wolfram` seeds its first row
+#[derive(Clone, Debug)]
+pub enum StatesInit {
+    /// One cell of color 1 in the center
+    Single,
+    /// Each cell any color with equal probability, from a `--seed`ed RNG
+    Random,
+    /// A given row of color digits, padded with 0s or truncated to the width
+    Digits(Vec<u8>),
+}
+
+impl std::str::FromStr for StatesInit {
+    type Err = String;
+
+    /// `single`, `random` or `digits:0120...`
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.split_once(':') {
+            None if s == "single" => Ok(StatesInit::Single),
+            None if s == "random" => Ok(StatesInit::Random),
+            Some(("digits", digits)) if !digits.is_empty() => digits
+                .chars()
+                .map(|c| c.to_digit(10).map(|d| d as u8).ok_or_else(|| format!("{c:?} is not a digit")))
+                .collect::<Result<_, _>>()
+                .map(StatesInit::Digits),
+            _ => Err("expected single, random or digits:<colors>".to_string()),
+        }
+    }
+}
+
+impl StatesInit {
+    /// The first row of `width` cells for a `colors`-color rule; exits if
+    /// the digits name a color it doesn't have
+    pub fn row(&self, colors: u8, width: usize, seed: u64) -> Vec<u8> {
+        match self {
+            StatesInit::Single => {
+                let mut row = vec![0; width];
+                row[width / 2] = 1;
+                row
+            }
+            StatesInit::Random => random_row(colors, width, seed),
+            StatesInit::Digits(digits) => {
+                if let Some(&bad) = digits.iter().find(|&&d| d >= colors) {
+                    eprintln!("{bad} is not a color of a {colors}-color rule (0 to {})", colors - 1);
+                    std::process::exit(1);
+                }
+                let mut row = digits.clone();
+                row.resize(width, 0);
+                row
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for StatesInit {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            StatesInit::Single => write!(f, "single"),
+            StatesInit::Random => write!(f, "random"),
+            StatesInit::Digits(digits) => {
+                write!(f, "digits:{}", digits.iter().map(|d| d.to_string()).collect::<String>())
+            }
+        }
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct WolframArgs {
+    /// Wolfram code: k=3,r=1,code=1635 (totalistic), k=2,r=2,rule=N (general), k=2,r=1,outer=N or B1/S12
+    /// (outer-totalistic), totalistic:777 (k=3, r=1) or 0-255
+    #[arg(default_value = "totalistic:1635")]
+    pub rule: WolframRule,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 79, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 40)]
+    pub generations: usize,
+    /// Generations to run before the first row shown
+    #[arg(long, default_value_t = 0)]
+    pub skip: usize,
+    /// Initial row: single, random or digits:0120...
+    #[arg(long, default_value = "single")]
+    pub init: StatesInit,
+    /// RNG seed for `--init random`
+    #[arg(long, default_value_t = 42)]
+    pub seed: u64,
+    /// Write a PNG instead of printing the rows, shaded from white (color 0) to black
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+    /// Pixels per cell side for --out
+    #[arg(short, long, default_value_t = 1)]
+    pub cell_size: usize,
+}
+
+/// Characters for the colors of a printed multi-color row, lightest first
+const SHADES: &[u8] = b" .:-=+*%@#";
+
+pub fn wolfram(args: &WolframArgs) {
+    let WolframArgs { ref rule, width, generations, skip, ref init, seed, ref out, cell_size } = *args;
+    let k = rule.colors;
+
+    let mut row = init.row(k, width, seed);
+    let progress = Progress::generations(&format!("Rule {rule}"), skip + generations);
+    for _ in 0..skip {
+        row = rule.step(&row);
+        progress.tick();
+    }
+    let mut rows = vec![row.clone()];
+    for _ in 0..generations {
+        row = rule.step(&row);
+        rows.push(row.clone());
+        progress.tick();
+    }
+    drop(progress);
+    let kind = rule.family;
+    let elementary = rule.elementary().map(|n| format!(", elementary rule {n}")).unwrap_or_default();
+    let elementary = rule.life_notation().map(|life| format!(", {life}{elementary}")).unwrap_or(elementary);
+    let seeded = if matches!(init, StatesInit::Random) { format!(", init=random, seed={seed}") } else { String::new() };
+    let gens = if skip > 0 { format!("{skip}..{}", skip + generations) } else { generations.to_string() };
+    let title = format!("Rule {rule} ({kind}, {k} colors{elementary}; width={width}, gens={gens}{seeded})");
+
+    if let Some(path) = out {
+        let options = RenderOptions { cell_size: cell_size.max(1), ..RenderOptions::default() };
+        let init = match init {
+            StatesInit::Random => format!("random, seed {seed}"),
+            StatesInit::Single => "single".to_string(),
+            StatesInit::Digits(digits) => digits.iter().map(|d| d.to_string()).collect(),
+        };
+        let mut params: Vec<(_, &dyn std::fmt::Display)> =
+            vec![("rule", rule), ("width", &width), ("generations", &generations), ("init", &init)];
+        if skip > 0 {
+            params.push(("skip", &skip));
+        }
+        let metadata = metadata(&params);
+        let written = std::fs::File::create(path).and_then(|file| {
+            let mut out = Tagged::new(BufWriter::new(file), &metadata);
+            write_png_states(&mut out, &rows, k, &options)?;
+            out.flush()
+        });
+        if let Err(e) = written {
+            eprintln!("Can't write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+        println!("{title} -> {}", path.display());
+        return;
+    }
+
+    println!("{title}");
+    println!("{}", "-".repeat(width));
+    let shade = |c: u8| SHADES[c as usize * (SHADES.len() - 1) / (k as usize - 1)] as char;
+    for row in &rows {
+        println!("{}", row.iter().map(|&c| shade(c)).collect::<String>());
+    }
+    println!("{}", "-".repeat(width));
+
+    // Totalistic tables are short; general ones are listed when they fit on a screen
+    let digits = |v: usize, n: usize| -> String {
+        (0..n).rev().map(|i| char::from(b'0' + (v / (k as usize).pow(i as u32) % k as usize) as u8)).collect()
+    };
+    if rule.family == Family::OuterTotalistic {
+        println!("\nNext color by neighbor sum and center color:");
+        println!("  sum {}", (0..k).map(|c| format!("  c={c}")).collect::<String>());
+        for (sum, next) in rule.table.chunks(k as usize).enumerate().rev() {
+            println!("  {sum:>3} {}", next.iter().map(|n| format!("{n:>5}")).collect::<String>());
+        }
+    } else if rule.family == Family::Totalistic {
+        println!("\nNext color by neighborhood sum:");
+        println!("  sum -> next");
+        for (sum, next) in rule.table.iter().enumerate().rev() {
+            println!("  {sum:>3} ->  {next} '{}'", shade(*next));
+        }
+    } else if rule.table.len() <= 32 {
+        println!("\nTransition table:");
+        println!("  neighborhood -> next");
+        for (v, next) in rule.table.iter().enumerate().rev() {
+            println!("  {:>12} ->  {next}", digits(v, 2 * rule.radius + 1));
+        }
+    } else {
+        println!("\n{} neighborhoods; color shades: {}", rule.table.len(),
+            (0..k).map(|c| format!("{c} '{}'", shade(c))).collect::<Vec<_>>().join(", "));
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct ViewArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 110, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Number of cells in the ring, one pixel column each
+    #[arg(short, long, default_value_t = 512, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations on screen, one pixel row each; older rows scroll off the top
+    #[arg(long, default_value_t = 384)]
+    pub rows: usize,
+    /// Screen pixels per cell side: 1, 2, 4 or 8
+    #[arg(short, long, default_value_t = 2)]
+    pub cell_size: usize,
+    /// Generations per second
+    #[arg(long, default_value_t = 60.0)]
+    pub fps: f64,
+    /// Live cell color, as RRGGBB hex
+    #[arg(long, default_value = "000000", value_parser = parse_color)]
+    pub live: [u8; 3],
+    /// Dead cell color, as RRGGBB hex
+    #[arg(long, default_value = "ffffff", value_parser = parse_color)]
+    pub dead: [u8; 3],
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+#[cfg(not(feature = "gui"))]
+pub fn view(_args: &ViewArgs) {
+    eprintln!("This build has no window support; rebuild with `--features gui`");
+    std::process::exit(1);
+}
+
+/// Run the rule in a window, initial row at the top until the run scrolls.
+/// Clicking (or dragging) toggles cells of the initial row and restarts.
+#[cfg(feature = "gui")]
+pub fn view(args: &ViewArgs) {
+    use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Scale, Window, WindowOptions};
+
+    let ViewArgs { rule, width, rows, cell_size, fps, live, dead, ref init } = *args;
+    let scale = match cell_size {
+        1 => Scale::X1,
+        2 => Scale::X2,
+        4 => Scale::X4,
+        8 => Scale::X8,
+        _ => {
+            eprintln!("--cell-size must be 1, 2, 4 or 8");
+            std::process::exit(1);
+        }
+    };
+    if rows == 0 {
+        eprintln!("--rows must be at least 1");
+        std::process::exit(1);
+    }
+    let mut window = Window::new("automata", width, rows, WindowOptions { scale, ..WindowOptions::default() })
+        .unwrap_or_else(|e| {
+            eprintln!("Can't open a window: {e}");
+            std::process::exit(1);
+        });
+    window.set_target_fps(60);
+
+    let pixel = |[r, g, b]: [u8; 3]| u32::from_be_bytes([0, r, g, b]);
+    let (live, dead) = (pixel(live), pixel(dead));
+    let draw = |buffer: &mut [u32], row: usize, ca: &Automaton| {
+        for (p, cell) in buffer[row * width..(row + 1) * width].iter_mut().zip(ca.deviations()) {
+            *p = if cell { live } else { dead };
+        }
+    };
+
+    // The first row is kept as deviations from the background, so it carries over rule changes
+    let original: Vec<bool> = init.automaton(width, rule).deviations().collect();
+    let mut first = original.clone();
+    let mut rule = rule;
+    let start = |first: &[bool], rule: u8| {
+        Automaton::from_cells(first.to_vec(), rule).on_background(init.quiescent.resolve(rule))
+    };
+    let mut ca = start(&first, rule);
+    let mut buffer = vec![dead; width * rows];
+    let (mut filled, mut generation, mut restart) = (0, 0, true);
+    let (mut paused, mut fps, mut typed) = (false, fps.clamp(0.5, 1000.0), String::new());
+    let (mut painting, mut step_once, mut next_step) = (None, false, Instant::now());
+    let mut title = String::new();
+
+    while window.is_open() {
+        for key in window.get_keys_pressed(KeyRepeat::Yes) {
+            let digit = [Key::Key0, Key::NumPad0].iter().find_map(|&zero| (key as usize).checked_sub(zero as usize).filter(|&d| d < 10));
+            if let Some(d) = digit {
+                typed.push(char::from(b'0' + d as u8));
+                continue;
+            }
+            match key {
+                Key::Escape | Key::Q => return println!("Rule {rule} stopped at generation {generation}"),
+                Key::Backspace => {
+                    typed.pop();
+                }
+                Key::Enter | Key::NumPadEnter => {
+                    if let Ok(typed_rule) = typed.parse() {
+                        (rule, restart) = (typed_rule, true);
+                    }
+                    typed.clear();
+                }
+                Key::Up | Key::Right => (rule, restart) = (rule.wrapping_add(1), true),
+                Key::Down | Key::Left => (rule, restart) = (rule.wrapping_sub(1), true),
+                Key::Space => {
+                    paused = !paused;
+                    next_step = Instant::now();
+                }
+                Key::N | Key::Period => step_once = paused,
+                Key::Equal | Key::NumPadPlus => fps = (fps * 1.5).min(1000.0),
+                Key::Minus | Key::NumPadMinus => fps = (fps / 1.5).max(0.5),
+                Key::R => (first, restart) = (original.clone(), true),
+                Key::C => (first, restart) = (vec![false; width], true),
+                _ => {}
+            }
+        }
+
+        // Paint the column under the mouse with the opposite of the cell first pressed
+        match window.get_mouse_pos(MouseMode::Discard).filter(|_| window.get_mouse_down(MouseButton::Left)) {
+            Some((x, _)) => {
+                let column = (x as usize).min(width - 1);
+                let paint = *painting.get_or_insert(!first[column]);
+                if first[column] != paint {
+                    first[column] = paint;
+                    restart = true;
+                }
+            }
+            None => painting = None,
+        }
+
+        if restart {
+            ca = start(&first, rule);
+            buffer.fill(dead);
+            draw(&mut buffer, 0, &ca);
+            (filled, generation, restart) = (1, 0, false);
+            next_step = Instant::now() + Duration::from_secs_f64(1.0 / fps);
+        }
+
+        // Catch up on the generations due since the last frame, at most a screenful
+        let now = Instant::now();
+        let mut steps = 0;
+        if paused {
+            steps = std::mem::take(&mut step_once) as usize;
+        } else {
+            let interval = Duration::from_secs_f64(1.0 / fps);
+            while next_step <= now && steps < rows {
+                steps += 1;
+                next_step += interval;
+            }
+            next_step = next_step.max(now);
+        }
+        for _ in 0..steps {
+            ca.step();
+            generation += 1;
+            if filled < rows {
+                filled += 1;
+            } else {
+                buffer.copy_within(width.., 0);
+            }
+            draw(&mut buffer, filled - 1, &ca);
+        }
+
+        let state = if paused { "paused" } else { "running" };
+        let entry = if typed.is_empty() { String::new() } else { format!(" | rule {typed}_") };
+        let caption = format!(
+            "Rule {rule} | gen {generation} | {fps:.0} gen/s | {state}{entry} | click edits row 0, arrows or digits+Enter change rule, space pause, n step, +/- speed, r reset, c clear, q quit"
+        );
+        if caption != title {
+            window.set_title(&caption);
+            title = caption;
+        }
+        if let Err(e) = window.update_with_buffer(&buffer, width, rows) {
+            eprintln!("Can't draw the window: {e}");
+            std::process::exit(1);
+        }
+    }
+    println!("Rule {rule} stopped at generation {generation}");
+}
+
+#[derive(Args, Clone)]
+pub struct RenderArgs {
+    /// Rule number (0-255), a Wolfram code such as k=2,r=2,code=20, or a
+    /// radius-2 or -3 table: table:<hex> or random:<radius>[:<seed>]
+    #[arg(default_value_t = BinaryRule::Elementary(110), value_parser = parse_binary_rule)]
+    pub rule: BinaryRule,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 1001, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 500)]
+    pub generations: usize,
+    /// Output file, or - for stdout (default: rule<N>.png, .svg, ...)
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+    /// Image format (default: from the output file's extension, else png)
+    #[arg(short, long, value_enum)]
+    pub format: Option<Format>,
+    /// Pixels (SVG: user units) per cell side
+    #[arg(short, long, default_value_t = 1)]
+    pub cell_size: usize,
+    /// Cells per pixel side, blending colors by live fraction (PNG/PPM only; overrides --cell-size)
+    #[arg(short, long, default_value_t = 1)]
+    pub downscale: usize,
+    /// Blank border around the diagram (SVG only)
+    #[arg(long, default_value_t = 0.0)]
+    pub margin: f64,
+    /// Outline width for live cells, 0 for none (SVG only)
+    #[arg(long, default_value_t = 0.0)]
+    pub stroke: f64,
+    /// Outline color, as RRGGBB hex
+    #[arg(long, default_value = "808080", value_parser = parse_color)]
+    pub stroke_color: [u8; 3],
+    /// Width of grid lines between cells, 0 for none (SVG only)
+    #[arg(long, default_value_t = 0.0)]
+    pub grid: f64,
+    /// Grid line color, as RRGGBB hex
+    #[arg(long, default_value = "c8c8c8", value_parser = parse_color)]
+    pub grid_color: [u8; 3],
+    /// Live cell color, as RRGGBB hex
+    #[arg(long, default_value = "000000", value_parser = parse_color)]
+    pub live: [u8; 3],
+    /// Dead cell color, as RRGGBB hex
+    #[arg(long, default_value = "ffffff", value_parser = parse_color)]
+    pub dead: [u8; 3],
+    /// Find the rule's ether and draw it faintly, so particles stand out (PNG only)
+    #[arg(long)]
+    pub ether: bool,
+    /// Two or three comma-separated rule numbers run from the same seed, as
+    /// the red, green and blue channels of one image (PNG only)
+    #[arg(long, value_delimiter = ',', value_parser = parse_rule, conflicts_with = "rule", value_name = "RULES")]
+    pub rgb: Option<Vec<u8>>,
+    /// How --rgb combines its channels
+    #[arg(long, value_enum, default_value_t = Blend::Add, requires = "rgb")]
+    pub blend: Blend,
+    /// Draw one period of the cycle the run falls into instead: a tile that
+    /// repeats seamlessly across and down, e.g. as a wallpaper (PNG only)
+    #[arg(long, conflicts_with_all = ["generations", "ether", "rgb", "downscale"])]
+    pub tile: bool,
+    /// Scale the tile to this many pixels, blending cells by coverage
+    #[arg(long, value_name = "WxH", value_parser = parse_size, requires = "tile")]
+    pub size: Option<(usize, usize)>,
+    /// Give up looking for the tile's cycle after this many steps
+    #[arg(long, default_value_t = 100_000, requires = "tile")]
+    pub max_steps: usize,
+    /// Draw the damage instead: the XOR of the run and a copy with this
+    /// cell flipped (default: the middle one), live where they differ (PNG only)
+    #[arg(long, value_name = "CELL", num_args = 0..=1, conflicts_with_all = ["ether", "rgb", "tile"])]
+    pub damage: Option<Option<usize>>,
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+/// Channel blends for `render --rgb`
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Blend {
+    /// Light on black: overlapping live cells mix toward white
+    Add,
+    /// Ink on white: overlapping live cells mix toward black
+    Subtract,
+}
+
+/// Image formats for `render`
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    Png,
+    Svg,
+    /// netpbm color (P6)
+    Ppm,
+    /// netpbm 1-bit (P4): live black, dead white
+    Pbm,
+    /// NumPy array of 0/1 bytes, one row per generation, for other tools and engines
+    Npy,
+}
+
+pub fn render(args: &RenderArgs) {
+    let RenderArgs {
+        rule,
+        width,
+        generations,
+        ref out,
+        format,
+        cell_size,
+        downscale,
+        margin,
+        stroke,
+        stroke_color,
+        grid,
+        grid_color,
+        live,
+        dead,
+        ether,
+        ref rgb,
+        blend,
+        tile,
+        ref init,
+        ..
+    } = *args;
+    // Write the spacetime diagram as an image, for sizes the terminal can't show
+    if let Some(rules) = rgb {
+        return render_rgb(args, rules, blend);
+    }
+    if tile {
+        return render_tile(args);
+    }
+    if let Some(cell) = args.damage {
+        return render_damage(args, damaged_cell(cell, width));
+    }
+
+    let from_extension = out.as_ref().and_then(|p| p.extension()).and_then(|e| Format::from_str(&e.to_string_lossy(), true).ok());
+    let format = format.or(from_extension).unwrap_or(Format::Png);
+    let extension = format.to_possible_value().unwrap().get_name().to_string();
+    let path = out.clone().unwrap_or_else(|| PathBuf::from(format!("rule{}.{extension}", rule.slug())));
+    if format == Format::Svg && downscale > 1 {
+        eprintln!("--downscale is for PNG output; SVG diagrams scale losslessly");
+        std::process::exit(1);
+    }
+    if format == Format::Pbm && downscale > 1 {
+        eprintln!("--downscale blends colors, which a 1-bit PBM can't show; use -f ppm");
+        std::process::exit(1);
+    }
+    if format == Format::Npy && (downscale > 1 || cell_size > 1) {
+        eprintln!("NPY arrays hold one value per cell; --cell-size and --downscale are for images");
+        std::process::exit(1);
+    }
+    if ether && format != Format::Png {
+        eprintln!("--ether is for PNG output");
+        std::process::exit(1);
+    }
+    let background = match rule {
+        BinaryRule::Elementary(rule) => ether.then(|| detect_ether(rule, &EtherSettings::default())).flatten(),
+        BinaryRule::Table(table) if ether => {
+            eprintln!("--ether is found for elementary rules, not {table}");
+            std::process::exit(1);
+        }
+        BinaryRule::Table(_) => None,
+    };
+    if ether && background.is_none() {
+        note!("No ether found for rule {rule}; drawing every cell");
+    }
+
+    let options = RenderOptions { cell_size: cell_size.max(1), downscale: downscale.max(1), live, dead };
+    let (image_width, image_height) = image_size(width, generations + 1, &options);
+    let svg = SvgOptions { cell_size: cell_size.max(1) as f64, margin, stroke, stroke_color, grid, grid_color, live, dead };
+
+    // With `-o -` the image goes to stdout, for pipes, and the report to stderr
+    let ca = init.automaton_for(width, rule);
+    let to_stdout = path.as_os_str() == "-";
+    let metadata = match to_stdout {
+        true => Metadata::default(),
+        false => init.tag(metadata(&[("rule", &rule), ("width", &width), ("generations", &generations)])),
+    };
+    let file: io::Result<Box<dyn Write>> =
+        if to_stdout { Ok(Box::new(io::stdout().lock())) } else { std::fs::File::create(&path).map(|f| Box::new(f) as _) };
+    let written = file.and_then(|file| {
+        let mut out = Tagged::new(BufWriter::new(file), &metadata);
+        match format {
+            Format::Png => match &background {
+                Some(background) => write_png_ether(&mut out, ca, generations, background, &options),
+                None => write_png(&mut out, ca, generations, &options),
+            },
+            Format::Svg => write_svg(&mut out, ca, generations, &svg),
+            Format::Ppm => write_ppm(&mut out, ca, generations, &options),
+            Format::Pbm => write_pbm(&mut out, ca, generations, cell_size),
+            Format::Npy => write_npy(&mut out, ca, generations).and_then(|()| write_sidecar(&path, &metadata)),
+        }?;
+        out.flush()
+    });
+    if let Err(e) = written {
+        eprintln!("Can't write {}: {e}", path.display());
+        std::process::exit(1);
+    }
+    let size = match format {
+        Format::Png | Format::Ppm | Format::Pbm => format!("{image_width} x {image_height} pixels"),
+        Format::Svg => format!("{} x {} units", svg.cell_size * width as f64 + 2.0 * margin,
+            svg.cell_size * (generations + 1) as f64 + 2.0 * margin),
+        Format::Npy => format!("{} x {width} array", generations + 1),
+    };
+    let destination = if to_stdout { "stdout".to_string() } else { path.display().to_string() };
+    let faint = match &background {
+        Some(ether) => format!(", ether {} x {} faint", ether.spatial_period(), ether.temporal_period()),
+        None => String::new(),
+    };
+    let report =
+        format!("Rule {rule} (width={width}, gens={generations}{}{faint}): {size} -> {destination}", init.describe());
+    if to_stdout {
+        eprintln!("{report}");
+    } else {
+        println!("{report}");
+    }
+}
+
+/// `render --rgb`: one diagram per rule from the same seed, composited
+fn render_rgb(args: &RenderArgs, rules: &[u8], blend: Blend) {
+    let RenderArgs { width, generations, ref out, cell_size, downscale, ether, ref init, .. } = *args;
+    if !(2..=3).contains(&rules.len()) {
+        eprintln!("--rgb takes two or three rules, one per color channel, e.g. --rgb 30,90,110");
+        std::process::exit(1);
+    }
+    png_only(args, "--rgb");
+    if ether {
+        eprintln!("--ether draws one rule's background; --rgb has several");
+        std::process::exit(1);
+    }
+    let names: Vec<String> = rules.iter().map(u8::to_string).collect();
+    let path = out.clone().unwrap_or_else(|| PathBuf::from(format!("rgb{}.png", names.join("-"))));
+
+    // Each rule gets its own quiescent background from the one seed
+    let initial: Vec<Automaton> = rules.iter().map(|&rule| init.automaton(width, rule)).collect();
+    let options =
+        RenderOptions { cell_size: cell_size.max(1), downscale: downscale.max(1), ..RenderOptions::default() };
+    let (image_width, image_height) = image_size(width, generations + 1, &options);
+    let to_stdout = path.as_os_str() == "-";
+    let metadata = match to_stdout {
+        true => Metadata::default(),
+        false => {
+            let rules = names.join(",");
+            init.tag(metadata(&[("rules", &rules), ("width", &width), ("generations", &generations)]))
+        }
+    };
+    let blend = match blend {
+        Blend::Add => automata::render::Blend::Add,
+        Blend::Subtract => automata::render::Blend::Subtract,
+    };
+    let file: io::Result<Box<dyn Write>> = match to_stdout {
+        true => Ok(Box::new(io::stdout().lock())),
+        false => std::fs::File::create(&path).map(|f| Box::new(f) as _),
+    };
+    let written = file.and_then(|file| {
+        let mut out = Tagged::new(BufWriter::new(file), &metadata);
+        write_png_rgb(&mut out, &initial, generations, blend, &options)?;
+        out.flush()
+    });
+    if let Err(e) = written {
+        eprintln!("Can't write {}: {e}", path.display());
+        std::process::exit(1);
+    }
+    let channels = ["R", "G", "B"][..rules.len()].join(", ");
+    let destination = if to_stdout { "stdout".to_string() } else { path.display().to_string() };
+    let size = format!("{image_width} x {image_height} pixels");
+    let report = format!(
+        "Rules {} as {channels} (width={width}, gens={generations}{}): {size} -> {destination}",
+        names.join(", "),
+        init.describe()
+    );
+    if to_stdout {
+        eprintln!("{report}");
+    } else {
+        println!("{report}");
+    }
+}
+
+/// `render --tile`: one period of the run's cycle, resampled to `--size`
+fn render_tile(args: &RenderArgs) {
+    let RenderArgs { rule, width, ref out, cell_size, live, dead, size, max_steps, ref init, .. } = *args;
+    png_only(args, "--tile");
+    let path = out.clone().unwrap_or_else(|| PathBuf::from(format!("rule{}-tile.png", rule.slug())));
+
+    // The cycle's states are packed rows; running again from the start to
+    // where it begins keeps the drawing relative to the run's background
+    let Some((transient, states)) = find_attractor(init.automaton_for(width, rule), max_steps) else {
+        eprintln!("Rule {rule} found no cycle within {max_steps} steps; try a larger --max-steps or a smaller --width");
+        std::process::exit(1);
+    };
+    let period = states.len();
+    let mut start = init.automaton_for(width, rule);
+    (0..transient).for_each(|_| start.step());
+    let blank = if start.population() == 0 { " (the run died: the tile is blank)" } else { "" };
+
+    let options = RenderOptions { cell_size: cell_size.max(1), downscale: 1, live, dead };
+    let (image_width, image_height) = size.unwrap_or_else(|| image_size(width, period, &options));
+    let to_stdout = path.as_os_str() == "-";
+    let metadata = match to_stdout {
+        true => Metadata::default(),
+        false => {
+            let params: [(_, &dyn std::fmt::Display); 4] =
+                [("rule", &rule), ("width", &width), ("transient", &transient), ("period", &period)];
+            init.tag(metadata(&params))
+        }
+    };
+    let file: io::Result<Box<dyn Write>> = match to_stdout {
+        true => Ok(Box::new(io::stdout().lock())),
+        false => std::fs::File::create(&path).map(|f| Box::new(f) as _),
+    };
+    let written = file.and_then(|file| {
+        let mut out = Tagged::new(BufWriter::new(file), &metadata);
+        write_png_tile(&mut out, start, period, size, &options)?;
+        out.flush()
+    });
+    if let Err(e) = written {
+        eprintln!("Can't write {}: {e}", path.display());
+        std::process::exit(1);
+    }
+    let destination = if to_stdout { "stdout".to_string() } else { path.display().to_string() };
+    let report = format!(
+        "Rule {rule} (width={width}{}): period {period} after {transient} steps, {width} x {period} cells tiled as \
+         {image_width} x {image_height} pixels{blank} -> {destination}",
+        init.describe()
+    );
+    if to_stdout {
+        eprintln!("{report}");
+    } else {
+        println!("{report}");
+    }
+}
+
+/// `render --damage`: the difference pattern of a one-cell perturbation
+fn render_damage(args: &RenderArgs, cell: usize) {
+    let RenderArgs { rule, width, generations, ref out, cell_size, downscale, live, dead, ref init, .. } = *args;
+    png_only(args, "--damage");
+    let path = out.clone().unwrap_or_else(|| PathBuf::from(format!("rule{}-damage.png", rule.slug())));
+
+    let pattern = difference_pattern(&init.automaton_for(width, rule), cell, generations);
+    let options = RenderOptions { cell_size: cell_size.max(1), downscale: downscale.max(1), live, dead };
+    let (image_width, image_height) = image_size(width, generations + 1, &options);
+    let to_stdout = path.as_os_str() == "-";
+    let metadata = match to_stdout {
+        true => Metadata::default(),
+        false => {
+            let params: [(_, &dyn std::fmt::Display); 4] =
+                [("rule", &rule), ("width", &width), ("generations", &generations), ("damage", &cell)];
+            init.tag(metadata(&params))
+        }
+    };
+    let file: io::Result<Box<dyn Write>> = match to_stdout {
+        true => Ok(Box::new(io::stdout().lock())),
+        false => std::fs::File::create(&path).map(|f| Box::new(f) as _),
+    };
+    let written = file.and_then(|file| {
+        let mut out = Tagged::new(BufWriter::new(file), &metadata);
+        write_png_rows(&mut out, &pattern, &options)?;
+        out.flush()
+    });
+    if let Err(e) = written {
+        eprintln!("Can't write {}: {e}", path.display());
+        std::process::exit(1);
+    }
+    let destination = if to_stdout { "stdout".to_string() } else { path.display().to_string() };
+    let last = pattern.last().map_or(0, |row| row.iter().filter(|&&x| x).count());
+    let report = format!(
+        "Rule {rule} (width={width}, gens={generations}{}): damage from flipping cell {cell}, {last} cells at the end, \
+         {image_width} x {image_height} pixels -> {destination}",
+        init.describe()
+    );
+    if to_stdout {
+        eprintln!("{report}");
+    } else {
+        println!("{report}");
+    }
+}
+
+/// Exit unless `render` is writing a PNG, for the modes that only draw one
+fn png_only(args: &RenderArgs, flag: &str) {
+    let other_extension = |e: &std::ffi::OsStr| !e.eq_ignore_ascii_case("png");
+    let from_extension = args.out.as_ref().and_then(|p| p.extension()).is_some_and(other_extension);
+    if args.format.is_some_and(|f| f != Format::Png) || from_extension {
+        eprintln!("{flag} is for PNG output");
+        std::process::exit(1);
+    }
+}
+
+/// `WIDTHxHEIGHT` in pixels, e.g. 1920x1080
+fn parse_size(s: &str) -> Result<(usize, usize), String> {
+    let bad = || format!("expected WIDTHxHEIGHT, e.g. 1920x1080, not {s:?}");
+    let (w, h) = s.split_once(['x', 'X']).ok_or_else(bad)?;
+    match (w.parse(), h.parse()) {
+        (Ok(w), Ok(h)) if w > 0 && h > 0 => Ok((w, h)),
+        _ => Err(bad()),
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct AnimateArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 110, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 201, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate (one frame each)
+    #[arg(short = 'g', long = "gens", default_value_t = 200)]
+    pub generations: usize,
+    /// Rows of spacetime per frame; 1 shows just the current generation
+    #[arg(long, default_value_t = 100)]
+    pub window: usize,
+    /// Milliseconds per frame
+    #[arg(long, default_value_t = 50)]
+    pub delay: u16,
+    /// Pixels per cell side
+    #[arg(short, long, default_value_t = 2)]
+    pub cell_size: usize,
+    /// Output file (default: rule<N>.png)
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+    /// Live cell color, as RRGGBB hex
+    #[arg(long, default_value = "000000", value_parser = parse_color)]
+    pub live: [u8; 3],
+    /// Dead cell color, as RRGGBB hex
+    #[arg(long, default_value = "ffffff", value_parser = parse_color)]
+    pub dead: [u8; 3],
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+pub fn animate(args: &AnimateArgs) {
+    let AnimateArgs { rule, width, generations, window, delay, cell_size, ref out, live, dead, ref init } = *args;
+    // Write the evolution as an animated PNG, scrolling a window down the spacetime
+
+    if window == 0 {
+        eprintln!("--window must be at least 1");
+        std::process::exit(1);
+    }
+    let path = out.clone().unwrap_or_else(|| PathBuf::from(format!("rule{rule}.png")));
+    let options = RenderOptions { cell_size: cell_size.max(1), live, dead, ..RenderOptions::default() };
+
+    let ca = init.automaton(width, rule);
+    let window = apng_window(width, window, &options);
+    let metadata = init.tag(metadata(&[("rule", &rule), ("width", &width), ("generations", &generations)]));
+    let written = std::fs::File::create(&path).and_then(|file| {
+        write_apng(&mut Tagged::new(BufWriter::new(file), &metadata), ca, generations, window, delay, &options)
+    });
+    if let Err(e) = written {
+        eprintln!("Can't write {}: {e}", path.display());
+        std::process::exit(1);
+    }
+    let (image_width, image_height) = image_size(width, window, &options);
+    println!(
+        "Rule {rule} (width={width}, gens={generations}{}): {} frames of {image_width} x {image_height} pixels, {delay} ms each -> {}",
+        init.describe(),
+        generations + 1,
+        path.display()
+    );
+}
+
+#[derive(Args, Clone)]
+pub struct ClassSheetArgs {
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 63, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 47)]
+    pub generations: usize,
+    /// Tiles per row
+    #[arg(long, default_value_t = 11)]
+    pub columns: usize,
+    /// Pixels per cell side
+    #[arg(short, long, default_value_t = 2)]
+    pub cell_size: usize,
+    /// Cells per pixel side, blending colors by live fraction (overrides --cell-size)
+    #[arg(short, long, default_value_t = 1)]
+    pub downscale: usize,
+    /// Pixels between tiles
+    #[arg(long, default_value_t = 8)]
+    pub gap: usize,
+    /// Pixels per dot of the rule-number labels, 0 for none
+    #[arg(long, default_value_t = 2)]
+    pub label_scale: usize,
+    /// Output file
+    #[arg(short, long, default_value = "classes.png")]
+    pub out: PathBuf,
+    /// Live cell (and label) color, as RRGGBB hex
+    #[arg(long, default_value = "000000", value_parser = parse_color)]
+    pub live: [u8; 3],
+    /// Dead cell color, as RRGGBB hex
+    #[arg(long, default_value = "ffffff", value_parser = parse_color)]
+    pub dead: [u8; 3],
+    /// Gap and label background color, as RRGGBB hex
+    #[arg(long, default_value = "e0e0e0", value_parser = parse_color)]
+    pub background: [u8; 3],
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+pub fn class_sheet(args: &ClassSheetArgs) {
+    let ClassSheetArgs {
+        width,
+        generations,
+        columns,
+        cell_size,
+        downscale,
+        gap,
+        label_scale,
+        ref out,
+        live,
+        dead,
+        background,
+        ref init,
+    } = *args;
+    // One thumbnail per mirror/complement class: every distinct ECA behavior on one sheet
+
+    // Each class is represented by its smallest rule number
+    let representatives = symmetry::representatives();
+    let initial = init.automaton(width, 0);
+    let tiles: Vec<_> = representatives
+        .iter()
+        .map(|&rule| initial.with_rule(rule).on_background(init.quiescent.resolve(rule)))
+        .collect();
+
+    let options = RenderOptions { cell_size: cell_size.max(1), downscale: downscale.max(1), live, dead };
+    let sheet = SheetOptions { columns: columns.max(1), gap, label_scale, background };
+    let metadata = init.tag(metadata(&[("width", &width), ("generations", &generations)]));
+    let written = std::fs::File::create(out)
+        .and_then(|file| {
+            write_sheet(&mut Tagged::new(BufWriter::new(file), &metadata), &tiles, generations, &sheet, &options)
+        });
+    if let Err(e) = written {
+        eprintln!("Can't write {}: {e}", out.display());
+        std::process::exit(1);
+    }
+    let (image_width, image_height) = sheet_size(tiles.len(), width, generations + 1, &sheet, &options);
+    println!(
+        "{} equivalence classes (width={width}, gens={generations}{}): {image_width} x {image_height} pixels -> {}",
+        tiles.len(),
+        init.describe(),
+        out.display()
+    );
+}
+
+#[derive(Args, Clone)]
+pub struct TrajectoryArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 110, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 101, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 500)]
+    pub generations: usize,
+    /// Generations to drop first, to plot only the attractor
+    #[arg(long, default_value_t = 0)]
+    pub skip: usize,
+    /// Coordinates for each generation
+    #[arg(short, long, value_enum, default_value_t = Projection::DensityEntropy)]
+    pub projection: Projection,
+    /// Block length k for the k-block entropy
+    #[arg(short, long, default_value_t = 3)]
+    pub block_size: usize,
+    /// Side of the plot, in pixels
+    #[arg(short, long, default_value_t = 512)]
+    pub size: usize,
+    /// Output image (default: rule<N>-trajectory.png)
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+    /// Also write generation,x,y rows to this CSV file
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
+    /// Print the report as a table or as JSON; the files are written either way
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+/// Two-dimensional views of a row for `trajectory`
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Projection {
+    /// Density (x) against k-block entropy per cell (y)
+    DensityEntropy,
+    /// The first two principal components of the rows as 0/1 vectors
+    Pca,
+}
+
+pub fn trajectory(args: &TrajectoryArgs) {
+    let TrajectoryArgs {
+        rule,
+        width,
+        generations,
+        skip,
+        projection,
+        block_size,
+        size,
+        ref out,
+        ref csv,
+        format,
+        ref init,
+    } = *args;
+    if projection == Projection::DensityEntropy {
+        require_block("--block-size", block_size, width);
+    }
+    // Each generation as a point in the plane, joined in time order: cycles
+    // close into loops, transients lead into them, chaos fills a cloud
+
+    let mut ca = init.automaton(width, rule);
+    let progress = Progress::generations(&format!("Rule {rule}"), skip + generations);
+    (0..skip).for_each(|_| {
+        ca.step();
+        progress.tick();
+    });
+    let mut rows = Vec::with_capacity(generations + 1);
+    rows.push(ca.clone());
+    for _ in 0..generations {
+        ca.step();
+        progress.tick();
+        rows.push(ca.clone());
+    }
+    drop(progress);
+
+    let (points, axes) = match projection {
+        Projection::DensityEntropy => {
+            let points = rows.iter().map(|row| [row.density(), row.block_entropy(block_size) / block_size as f64]).collect();
+            (points, ["density".to_string(), format!("{block_size}-block entropy per cell")])
+        }
+        Projection::Pca => {
+            let (points, explained) = principal_plane(&rows.iter().map(Automaton::cells).collect::<Vec<_>>());
+            let axis = |c: usize| format!("PC{} ({:.1}% of variance)", c + 1, 100.0 * explained[c]);
+            (points, [axis(0), axis(1)])
+        }
+    };
+
+    let path = out.clone().unwrap_or_else(|| PathBuf::from(format!("rule{rule}-trajectory.png")));
+    let options = PlotOptions { size, ..PlotOptions::default() };
+    let projection_name = projection.to_possible_value().unwrap().get_name().to_string();
+    let metadata = init.tag(metadata(&[
+        ("rule", &rule),
+        ("width", &width),
+        ("skip", &skip),
+        ("generations", &generations),
+        ("projection", &projection_name),
+    ]));
+    let written = std::fs::File::create(&path)
+        .and_then(|file| write_plot(&mut Tagged::new(BufWriter::new(file), &metadata), &points, &options));
+    if let Err(e) = written {
+        eprintln!("Can't write {}: {e}", path.display());
+        std::process::exit(1);
+    }
+    if let Some(csv) = csv {
+        let table: String = std::iter::once("generation,x,y\n".to_string())
+            .chain(points.iter().enumerate().map(|(t, [x, y])| format!("{},{x},{y}\n", skip + t)))
+            .collect();
+        if let Err(e) = std::fs::write(csv, metadata.csv_header() + &table) {
+            eprintln!("Can't write {}: {e}", csv.display());
+            std::process::exit(1);
+        }
+    }
+
+    let ranges = [0, 1].map(|axis| {
+        let values = points.iter().map(|p| p[axis]);
+        values.fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), v| (a.min(v), b.max(v)))
+    });
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("skip", skip.into()),
+            ("projection", value_name(projection)),
+            ("block_size", block_size.into()),
+            ("initial", init.to_json()),
+        ]);
+        let axes = axes.iter().zip(ranges).map(|(name, (min, max))| {
+            Json::object([("name", name.as_str().into()), ("min", min.into()), ("max", max.into())])
+        });
+        let results = Json::object([
+            ("points", points.len().into()),
+            ("axes", Json::array(axes)),
+            ("out", path.display().to_string().into()),
+            ("csv", csv.as_ref().map(|csv| csv.display().to_string()).into()),
+        ]);
+        return print_report("trajectory", parameters, results);
+    }
+
+    println!("Rule {rule} (width={width}, gens={skip}..{}{}): {} points -> {}",
+        skip + generations, init.describe(), points.len(), path.display());
+    for (axis, name) in ["x", "y"].into_iter().enumerate() {
+        let (min, max) = ranges[axis];
+        println!("  {name}: {} from {min:.3} to {max:.3}", axes[axis]);
+    }
+    if let Some(csv) = csv {
+        println!("  Points -> {}", csv.display());
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct FactorArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 110, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Sliding-block code applied to every row: eca:N, majority[:r],
+    /// table:<hex>, a two-color Wolfram code, or block:<pattern>[,<pattern>...]
+    /// marking the cells at the center of any of the (odd-length) patterns
+    #[arg(short, long, value_parser = parse_block_map)]
+    pub map: TableRule,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 79, value_parser = parse_width)]
+    pub width: usize,
+    /// Generations to simulate
+    #[arg(short = 'g', long = "gens", default_value_t = 40)]
+    pub generations: usize,
+    /// Block length k for the k-block entropy
+    #[arg(short, long, default_value_t = 3)]
+    pub block_size: usize,
+    /// Write the filtered diagram to this PNG instead of printing it
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+    /// Pixels per cell side for --out
+    #[arg(short, long, default_value_t = 1)]
+    pub cell_size: usize,
+    /// Print the report as a table or as JSON, with the filtered rows
+    /// unless they go to --out
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+/// A run, its image under the block map, and what the image's transitions
+/// say about whether it is itself a cellular automaton
+struct Factored {
+    original: Vec<Vec<bool>>,
+    filtered: Vec<Vec<bool>>,
+    data: TransitionDataset,
+    /// The least radius of a rule the filtered rows follow exactly
+    radius: Option<usize>,
+}
+
+impl Factored {
+    fn new(args: &FactorArgs) -> Self {
+        let FactorArgs { rule, map, width, generations, ref init, .. } = *args;
+        let mut ca = init.automaton(width, rule);
+        let mut original = vec![ca.cells()];
+        let progress = Progress::generations(&format!("Rule {rule}"), generations);
+        for _ in 0..generations {
+            ca.step();
+            progress.tick();
+            original.push(ca.cells());
+        }
+        drop(progress);
+        let filtered: Vec<Vec<bool>> = original.iter().map(|row| map.step(row)).collect();
+        // A factor map onto another CA makes the filtered rows deterministic
+        let data = TransitionDataset::from_trajectories(vec![filtered.clone()]);
+        let radius = if data.is_empty() { None } else { data.min_radius(MAX_RADIUS, 0.0) };
+        Factored { original, filtered, data, radius }
+    }
+
+    /// Mean density and k-block entropy per cell, of the original rows and the filtered
+    fn measures(&self, rule: u8, block_size: usize) -> [(&'static str, f64, f64); 2] {
+        let mean = |rows: &[Vec<bool>], measure: &dyn Fn(&Automaton) -> f64| {
+            rows.iter().map(|row| measure(&Automaton::from_cells(row.clone(), rule))).sum::<f64>() / rows.len() as f64
+        };
+        let entropy = |ca: &Automaton| ca.block_entropy(block_size) / block_size as f64;
+        let (original, filtered) = (&self.original, &self.filtered);
+        [
+            ("density", mean(original, &Automaton::density), mean(filtered, &Automaton::density)),
+            ("entropy", mean(original, &entropy), mean(filtered, &entropy)),
+        ]
+    }
+
+    /// Radius-1 neighborhoods the filtered rows never show
+    fn unseen(&self) -> usize {
+        self.data.neighborhood_counts().observations.iter().filter(|&&n| n == 0).count()
+    }
+
+    fn report(&self, args: &FactorArgs) -> Json {
+        let FactorArgs { rule, map, width, generations, block_size, ref out, ref init, .. } = *args;
+        let Factored { ref filtered, ref data, radius, .. } = *self;
+        let digits = map.entries() / 4;
+        let map = Json::object([("radius", map.radius.into()), ("table", format!("{:0digits$x}", map.table).into())]);
+        let parameters = Json::object([
+            ("rule", rule.into()),
+            ("map", map),
+            ("width", width.into()),
+            ("generations", generations.into()),
+            ("block_size", block_size.into()),
+            ("initial", init.to_json()),
+        ]);
+        let measures = self.measures(rule, block_size).map(|(name, original, filtered)| {
+            (name, Json::object([("original", original.into()), ("filtered", filtered.into())]))
+        });
+        // The rule the filtered rows follow, or how far they are from any radius-MAX_RADIUS rule
+        let disagreement = (radius.is_none() && !data.is_empty()).then(|| {
+            let (errors, total) = data.majority_errors(MAX_RADIUS);
+            errors as f64 / total as f64
+        });
+        let dynamics = Json::object([
+            ("transitions", data.len().into()),
+            ("radius", radius.into()),
+            ("eca", (radius == Some(1)).then(|| data.neighborhood_counts().infer_rule()).into()),
+            ("unseen_neighborhoods", (radius == Some(1)).then(|| self.unseen()).into()),
+            ("disagreement", disagreement.into()),
+        ]);
+        let rows = out.is_none().then(|| {
+            Json::array(filtered.iter().map(|row| row.iter().map(|&c| if c { '1' } else { '0' }).collect::<String>()))
+        });
+        let results =
+            Json::object(measures.into_iter().chain([("filtered_dynamics", dynamics), ("filtered", rows.into())]));
+        report("factor", parameters, results)
+    }
+}
+
+pub fn factor(args: &FactorArgs) {
+    let FactorArgs { rule, map, width, generations, block_size, ref out, cell_size, format, ref init } = *args;
+    require_block("--block-size", block_size, width);
+    // Post-process a run with a local map, then compare the filtered diagram
+    // with the original and ask whether it is itself a cellular automaton
+    let factored = Factored::new(args);
+    let filtered = &factored.filtered;
+
+    let digits = map.entries() / 4;
+    let table = format == ReportFormat::Table;
+    if table {
+        println!("Rule {rule} through the radius-{} block map table:{:0digits$x}{}", map.radius, map.table,
+            init.describe());
+    }
+    match out {
+        Some(path) => {
+            let options = RenderOptions { cell_size: cell_size.max(1), ..RenderOptions::default() };
+            let map = format!("radius={},table={:x}", map.radius, map.table);
+            let params: [(_, &dyn std::fmt::Display); 4] =
+                [("rule", &rule), ("map", &map), ("width", &width), ("generations", &generations)];
+            let metadata = init.tag(metadata(&params));
+            let written = std::fs::File::create(path).and_then(|file| {
+                write_png_rows(&mut Tagged::new(BufWriter::new(file), &metadata), filtered, &options)
+            });
+            if let Err(e) = written {
+                eprintln!("Can't write {}: {e}", path.display());
+                std::process::exit(1);
+            }
+            if table {
+                println!("  Filtered diagram -> {}", path.display());
+            }
+        }
+        None if table => {
+            println!("{}", "-".repeat(width));
+            for row in filtered {
+                println!("{}", Automaton::from_cells(row.clone(), rule));
+            }
+            println!("{}", "-".repeat(width));
+        }
+        None => {}
+    }
+    if !table {
+        return super::sink::emit(factored.report(args));
+    }
+
+    println!("  {:<28} {:>10} {:>10}", "", "original", "filtered");
+    let names = ["Mean density".to_string(), format!("Mean {block_size}-block entropy/cell")];
+    for (name, (_, original, filtered)) in names.iter().zip(factored.measures(rule, block_size)) {
+        println!("  {name:<28} {original:>10.4} {filtered:>10.4}");
+    }
+    let data = &factored.data;
+    match factored.radius {
+        _ if data.is_empty() => println!("  No transitions to test the filtered dynamics on"),
+        Some(1) => {
+            let (eca, unseen) = (data.neighborhood_counts().infer_rule(), factored.unseen());
+            let caveat = if unseen > 0 { format!(" ({unseen} of 8 neighborhoods never seen, taken as 0)") } else { String::new() };
+            println!("  Filtered rows follow ECA {eca} on all {} transitions{caveat}", data.len());
+        }
+        Some(0) => println!("  Filtered rows follow a radius-0 rule on all {} transitions", data.len()),
+        Some(r) => println!("  Filtered rows follow a radius-{r} rule on all {} transitions", data.len()),
+        None => {
+            let (errors, total) = data.majority_errors(MAX_RADIUS);
+            println!("  Filtered rows are not a radius-{MAX_RADIUS} CA: {:.2}% of cell updates disagree with their window's majority",
+                100.0 * errors as f64 / total as f64);
+        }
+    }
+}
+
+#[derive(Args, Clone)]
+pub struct DiffSpacetimeArgs {
+    /// First diagram: an NPY array, a PBM bitmap or a text diagram
+    pub a: PathBuf,
+    /// Second diagram, of the same width
+    pub b: PathBuf,
+    /// Write the XOR image to this PNG: cells live only in A red, only in B blue, in both faint
+    #[arg(long)]
+    pub xor: Option<PathBuf>,
+    /// Pixels per cell side for --xor
+    #[arg(short, long, default_value_t = 1)]
+    pub cell_size: usize,
+    /// Differing rows listed with their Hamming distance
+    #[arg(long, default_value_t = 10)]
+    pub show: usize,
+    /// Write the Hamming distance of every shared row to this CSV file
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+pub fn diff_spacetime(args: &DiffSpacetimeArgs) {
+    let DiffSpacetimeArgs { ref a, ref b, ref xor, cell_size, show, ref csv, format } = *args;
+    // Where two saved runs part, e.g. the same rule from two engines or versions
+
+    let load = |path: &PathBuf| {
+        read_diagram(path).unwrap_or_else(|e| {
+            eprintln!("Can't read {}: {e}", path.display());
+            std::process::exit(1);
+        })
+    };
+    let (rows_a, rows_b) = (load(a), load(b));
+    let diff = diff_spacetimes(&rows_a, &rows_b).unwrap_or_else(|e| {
+        eprintln!("Can't compare {} ({} wide) with {} ({} wide): {e}", a.display(), rows_a[0].len(), b.display(),
+            rows_b.first().map_or(0, Vec::len));
+        std::process::exit(1);
+    });
+    let compared = diff.hamming.len();
+
+    let metadata = metadata(&[("a", &a.display()), ("b", &b.display())]);
+    if let Some(path) = xor {
+        let options = RenderOptions { cell_size: cell_size.max(1), ..RenderOptions::default() };
+        let written = std::fs::File::create(path).and_then(|file| {
+            let mut out = Tagged::new(BufWriter::new(file), &metadata);
+            write_png_diff(&mut out, &rows_a, &rows_b, &options)?;
+            out.flush()
+        });
+        if let Err(e) = written {
+            eprintln!("Can't write {}: {e}", path.display());
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = csv {
+        let table: String = std::iter::once("row,hamming\n".to_string())
+            .chain(diff.hamming.iter().enumerate().map(|(t, h)| format!("{t},{h}\n")))
+            .collect();
+        write_csv(path, &table, &metadata);
+    }
+
+    if format == ReportFormat::Json {
+        let parameters = Json::object([
+            ("a", a.display().to_string().into()),
+            ("b", b.display().to_string().into()),
+        ]);
+        let results = Json::object([
+            ("width", diff.width.into()),
+            ("rows_a", diff.rows.0.into()),
+            ("rows_b", diff.rows.1.into()),
+            ("identical", diff.identical().into()),
+            ("first_divergence", diff.first_divergence().into()),
+            ("differing_cells", diff.total().into()),
+            ("hamming", diff.hamming.clone().into()),
+        ]);
+        print_report("diff-spacetime", parameters, results);
+        return;
+    }
+
+    println!("A: {} ({} rows x {} cells)", a.display(), diff.rows.0, diff.width);
+    println!("B: {} ({} rows x {} cells)", b.display(), diff.rows.1, diff.width);
+    if diff.rows.0 != diff.rows.1 {
+        println!("  Lengths differ: comparing the first {compared} rows");
+    }
+    let Some(first) = diff.first_divergence() else {
+        println!("  {} over all {compared} shared rows", if diff.identical() { "Identical" } else { "No differences" });
+        return;
+    };
+    let differing = diff.hamming.iter().filter(|&&h| h > 0).count();
+    let (worst, max) = diff.hamming.iter().enumerate().max_by_key(|&(_, &h)| h).map(|(t, &h)| (t, h)).unwrap();
+    println!("  First divergence: row {first} ({} cells differ)", diff.hamming[first]);
+    let cells = compared * diff.width;
+    let percent = 100.0 * diff.total() as f64 / cells as f64;
+    println!("  Differing cells: {} of {cells} ({percent:.3}%), in {differing} of {compared} rows", diff.total());
+    println!("  Largest Hamming distance: {max} at row {worst}");
+    if show > 0 {
+        println!("  {:>6} {:>8}", "Row", "Hamming");
+        for (t, h) in diff.hamming.iter().enumerate().filter(|&(_, &h)| h > 0).take(show) {
+            println!("  {t:>6} {h:>8}");
+        }
+        if differing > show {
+            println!("  ... {} more differing rows", differing - show);
+        }
+    }
+    if let Some(path) = xor {
+        println!("  XOR image (A only red, B only blue) -> {}", path.display());
+    }
+}
+
+/// A `TableRule` spec, or block:<pattern>[,<pattern>...] for the map that
+/// marks cells centered on any of the patterns
+fn parse_block_map(s: &str) -> Result<TableRule, String> {
+    let Some(patterns) = s.strip_prefix("block:") else {
+        return s.parse();
+    };
+    let mut radius = None;
+    let mut table = 0u128;
+    for pattern in patterns.split(',') {
+        let size = pattern.len();
+        if size % 2 == 0 || !(3..=2 * MAX_RADIUS + 1).contains(&size) || !pattern.chars().all(|c| c == '0' || c == '1') {
+            return Err(format!("a block is 3, 5 or 7 binary digits, not {pattern:?}"));
+        }
+        if radius.is_some_and(|r| r != size / 2) {
+            return Err("all blocks must have the same length".to_string());
+        }
+        radius = Some(size / 2);
+        table |= 1 << usize::from_str_radix(pattern, 2).unwrap();
+    }
+    Ok(TableRule::new(radius.unwrap_or(1), table))
+}
+
+/// An RRGGBB color, with or without a leading '#'
+pub(super) fn parse_color(s: &str) -> Result<[u8; 3], String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("expected RRGGBB hex, got {s:?}"));
+    }
+    Ok(std::array::from_fn(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn test_damage() {
+        // What run --damage draws: the pattern from its starting row, by default from the middle cell
+        let damage = |argv: &[&str]| {
+            let Some(crate::Command::Run(args)) = crate::Cli::try_parse_from(argv).unwrap().command else {
+                unreachable!()
+            };
+            let RunArgs { rule, width, generations, ref init, noise, memory, ref schedule, damage, .. } = args;
+            let ca = noise.apply(memory.apply(schedule.apply(init.automaton_for(width, rule))));
+            difference_pattern(&ca, damaged_cell(damage.unwrap(), width), generations.unwrap_or(40))
+        };
+        // The identity doesn't spread it: one cell, the flipped one, every generation
+        let pattern = damage(&["automata", "run", "204", "--damage", "--init", "random"]);
+        assert_eq!(pattern.len(), 41);
+        assert!(pattern.iter().all(|row| row.iter().enumerate().all(|(i, &x)| x == (i == 39))));
+        let pattern = damage(&["automata", "run", "204", "-w", "20", "-g", "5", "--damage", "3"]);
+        assert!(pattern.iter().all(|row| row.iter().enumerate().all(|(i, &x)| x == (i == 3))));
+        // Both copies take the same flips, so a linear rule's damage is the same with noise as without
+        let clean = damage(&["automata", "run", "90", "--damage", "--init", "random"]);
+        assert_eq!(damage(&["automata", "run", "90", "--damage", "--init", "random", "--noise", "0.05"]), clean);
+        assert_eq!(clean[4].iter().filter(|&&x| x).count(), 2);
+    }
+
+    fn factor_args(argv: &[&str]) -> FactorArgs {
+        match crate::Cli::try_parse_from(["automata", "factor"].iter().chain(argv)).unwrap().command {
+            Some(crate::Command::Factor(args)) => args,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_factor() {
+        // x[i] ⊕ x[i+1] commutes with rule 90 (both are linear and
+        // shift-invariant), so it maps every run onto another run of rule 90
+        let args = factor_args(&["90", "-m", "eca:102", "-w", "40", "-g", "30", "--init", "random"]);
+        let factored = Factored::new(&args);
+        let rule90 = TableRule::new(1, 90);
+        assert!(factored.original.windows(2).all(|w| rule90.step(&w[0]) == w[1]));
+        assert!(factored.filtered.windows(2).all(|w| rule90.step(&w[0]) == w[1]));
+        assert_eq!(factored.radius, Some(1));
+        let json = factored.report(&args).to_string();
+        assert!(json.starts_with(r#"{"schema":"automata-report","version":1,"command":"factor","#));
+        assert!(json.contains(r#""map":{"radius":1,"table":"66"},"width":40,"#), "{json}");
+        assert!(json.contains(r#""filtered_dynamics":{"transitions":30,"radius":1,"eca":90,"#));
+        assert!(json.contains(r#""unseen_neighborhoods":0,"disagreement":null},"filtered":["#));
+
+        // The identity map leaves the run as it was
+        let factored = Factored::new(&factor_args(&["110", "-m", "eca:204", "--init", "random"]));
+        assert_eq!((&factored.filtered, factored.radius), (&factored.original, Some(1)));
+        // Majority voting over rule 30 isn't deterministic at any radius, and with --out the rows stay out
+        let args = factor_args(&["30", "-m", "majority", "--init", "random", "--out", "filtered.png"]);
+        let factored = Factored::new(&args);
+        assert_eq!(factored.radius, None);
+        let json = factored.report(&args).to_string();
+        assert!(json.contains(r#""radius":null,"eca":null,"unseen_neighborhoods":null,"disagreement":0."#), "{json}");
+        assert!(json.ends_with(r#""filtered":null}}"#));
+    }
+}
@@ -0,0 +1,45 @@
+//! `selftest`: the engines checked against facts known in closed form, a
+//! line per check, exiting with status 1 if any fails
+
+use super::json::{print_report, Json};
+use super::ReportFormat;
+use automata::selftest::run_checks;
+use clap::Args;
+
+#[derive(Args, Clone)]
+pub struct SelftestArgs {
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    pub format: ReportFormat,
+}
+
+pub fn selftest(args: &SelftestArgs) {
+    let checks = run_checks();
+    let passed = checks.iter().filter(|check| check.passed).count();
+
+    if args.format == ReportFormat::Json {
+        let results = checks.iter().map(|check| {
+            Json::object([
+                ("name", check.name.into()),
+                ("claim", check.claim.into()),
+                ("passed", check.passed.into()),
+                ("detail", check.detail.as_str().into()),
+            ])
+        });
+        let failed = checks.len() - passed;
+        let results =
+            Json::object([("passed", passed.into()), ("failed", failed.into()), ("checks", Json::array(results))]);
+        print_report("selftest", Json::object([]), results);
+    } else {
+        println!("Self-test: {} checks against results known in closed form", checks.len());
+        let width = checks.iter().map(|check| check.name.len()).max().unwrap_or(0);
+        for check in &checks {
+            let status = if check.passed { "ok" } else { "FAIL" };
+            println!("  {status:<4}  {:<width$}  {}", check.name, check.claim);
+            println!("        {:<width$}  {}", "", check.detail);
+        }
+        println!("{passed} of {} passed", checks.len());
+    }
+    if passed < checks.len() {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,343 @@
+//! HTTP server: rendered diagrams and analyses by URL, for dashboards and
+//! notebooks that would otherwise shell out to the CLI, and a job queue
+//! (`lab`) for scripts that submit thousands of them
+//!
+//! It is plain HTTP/1.1 over the standard library: a thread per connection,
+//! `Connection: close`, and images encoded into memory before they are
+//! sent, so a request is capped at `MAX_CELLS`. Query parameters are the
+//! CLI's flags, run through the same render and analysis functions.
+
+use super::dynamics::{entropy_class, entropy_signature_from};
+use super::json::{report, Json};
+use super::lab::{Lab, MAX_BATCH};
+use super::log::note;
+use super::{Init, InitArgs, Quiescent};
+use automata::basins::{find_attractor, CycleFingerprint};
+use automata::render::{write_png, write_svg, RenderOptions, SvgOptions};
+use automata::{check_block, compression_ratio_from, find_cycle_from, Automaton, HASH_VERSION};
+use clap::{Args, ValueEnum};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Args, Clone)]
+pub struct ServeArgs {
+    /// Port to listen on
+    #[arg(short, long, default_value_t = 8000)]
+    pub port: u16,
+    /// Address to bind; 0.0.0.0 serves other machines too
+    #[arg(long, default_value = "127.0.0.1")]
+    pub bind: String,
+    /// Threads answering queued jobs (default: one per core)
+    #[arg(long)]
+    pub workers: Option<usize>,
+    /// Keep job results in this directory (an index, jobs.tsv, and a file
+    /// per result), reloading finished jobs on restart; default: in memory
+    #[arg(long)]
+    pub store: Option<PathBuf>,
+}
+
+/// Largest diagram served, in cells, so one request can't exhaust memory
+const MAX_CELLS: usize = 1 << 24;
+
+/// Largest request body: a full batch of job targets
+const MAX_BODY: usize = MAX_BATCH * 256;
+
+const INDEX: &str = "\
+automata server
+
+GET /rule/<n>.png            spacetime diagram: width, gens, cell, downscale, live, dead (RRGGBB)
+GET /rule/<n>.svg            vector diagram: width, gens, cell
+GET /rule/<n>/analysis.json  cycle, attractor, density, entropy and compression: width, gens, max_steps, block
+
+Every endpoint takes init (single, random, bits:..., hex:...), density, seed and quiescent (dead, live, auto),
+as on the command line.
+Example: /rule/110.png?width=400&gens=400&init=random
+
+POST /jobs                   queue the /rule/... targets in the body, one per line; answers {\"jobs\": [ids]}
+GET /jobs                    every job's status, with counts; status=queued|running|done|failed filters
+GET /jobs/<id>               one job's status and timings
+GET /jobs/<id>/result        the finished job's answer, as its endpoint gave it
+";
+
+pub fn serve(args: &ServeArgs) {
+    let ServeArgs { port, ref bind, workers, ref store } = *args;
+    let listener = TcpListener::bind((bind.as_str(), port)).unwrap_or_else(|e| {
+        eprintln!("Can't listen on {bind}:{port}: {e}");
+        std::process::exit(1);
+    });
+    let workers = workers.unwrap_or_else(rayon::current_num_threads);
+    let lab = Lab::start(workers, store.clone()).unwrap_or_else(|e| {
+        eprintln!("Can't open the job store {}: {e}", store.as_ref().unwrap().display());
+        std::process::exit(1);
+    });
+    note!("Serving on http://{bind}:{port}/ with {workers} job workers (Ctrl-C stops)");
+    if let Some(dir) = store {
+        note!("  Job results -> {} ({} reloaded)", dir.display(), lab.reloaded());
+    }
+
+    for stream in listener.incoming() {
+        match stream {
+            // Requests are independent and short; one thread each, and jobs go to the workers
+            Ok(stream) => {
+                let lab = Arc::clone(&lab);
+                std::thread::spawn(move || {
+                    if let Err(e) = handle(stream, &lab) {
+                        note!("  connection error: {e}");
+                    }
+                });
+            }
+            Err(e) => note!("  accept failed: {e}"),
+        }
+    }
+}
+
+/// A response: status, content type and body
+pub struct Response(pub u16, pub &'static str, pub Vec<u8>);
+
+impl Response {
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        let mut body = message.into().into_bytes();
+        body.push(b'\n');
+        Response(status, "text/plain; charset=utf-8", body)
+    }
+}
+
+/// Read one request, answer it and close the connection
+fn handle(stream: TcpStream, lab: &Lab) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Headers are read and ignored, but for the body's length
+    let (mut header, mut length) = (String::new(), 0);
+    while reader.read_line(&mut header)? > 2 {
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = value.trim().parse().unwrap_or(usize::MAX);
+        }
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let Response(status, content_type, body) = if length > MAX_BODY {
+        Response::error(413, format!("request bodies are at most {MAX_BODY} bytes"))
+    } else if path == "/jobs" || path.starts_with("/jobs/") {
+        let mut request = vec![0; length];
+        reader.read_exact(&mut request)?;
+        lab.respond(method, path, query, &request)
+    } else {
+        match method {
+            "GET" | "HEAD" => respond(target),
+            _ => Response::error(405, "only GET and HEAD are supported"),
+        }
+    };
+    note!("{method} {target} -> {status} ({} bytes)", body.len());
+
+    let reason = match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Content Too Large",
+        _ => "Internal Server Error",
+    };
+    let mut out = io::BufWriter::new(stream);
+    write!(
+        out,
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    if method != "HEAD" {
+        out.write_all(&body)?;
+    }
+    out.flush()
+}
+
+/// Route a GET request target (path and query) to its endpoint
+pub fn respond(target: &str) -> Response {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query: HashMap<String, String> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+
+    let Some(resource) = path.strip_prefix("/rule/") else {
+        return match path {
+            "/" => Response(200, "text/plain; charset=utf-8", INDEX.as_bytes().to_vec()),
+            _ => Response::error(404, format!("no such endpoint: {path} (see /)")),
+        };
+    };
+    let (rule, endpoint) = match resource.split_once(['.', '/']) {
+        Some((rule, _)) => (rule, &resource[rule.len()..]),
+        None => (resource, ""),
+    };
+    let Ok(rule) = rule.parse::<u8>() else {
+        return Response::error(400, format!("rule must be 0-255, not '{rule}'"));
+    };
+    let result = match endpoint {
+        ".png" => png(rule, &query),
+        ".svg" => svg(rule, &query),
+        "/analysis.json" => analysis(rule, &query),
+        _ => return Response::error(404, format!("no such endpoint: {path} (see /)")),
+    };
+    result.unwrap_or_else(|message| Response::error(400, message))
+}
+
+fn png(rule: u8, query: &HashMap<String, String>) -> Result<Response, String> {
+    let (width, generations) = (param(query, "width", 400)?, param(query, "gens", 200)?);
+    let options = RenderOptions {
+        cell_size: param(query, "cell", 1)?,
+        downscale: param(query, "downscale", 1)?,
+        live: color(query, "live", [0, 0, 0])?,
+        dead: color(query, "dead", [255, 255, 255])?,
+    };
+    if !(1..=16).contains(&options.cell_size) || options.downscale == 0 {
+        return Err("cell must be 1-16 and downscale at least 1".into());
+    }
+    let ca = initial(rule, width, generations, query)?;
+    let mut body = Vec::new();
+    write_png(&mut body, ca, generations, &options).map_err(|e| e.to_string())?;
+    Ok(Response(200, "image/png", body))
+}
+
+fn svg(rule: u8, query: &HashMap<String, String>) -> Result<Response, String> {
+    let (width, generations) = (param(query, "width", 100)?, param(query, "gens", 100)?);
+    let options = SvgOptions { cell_size: param(query, "cell", 4.0)?, ..SvgOptions::default() };
+    let ca = initial(rule, width, generations, query)?;
+    let mut body = Vec::new();
+    write_svg(&mut body, ca, generations, &options).map_err(|e| e.to_string())?;
+    Ok(Response(200, "image/svg+xml", body))
+}
+
+fn analysis(rule: u8, query: &HashMap<String, String>) -> Result<Response, String> {
+    let (width, generations) = (param(query, "width", 79)?, param(query, "gens", 100)?);
+    let (max_steps, block_size): (usize, usize) = (param(query, "max_steps", 10000)?, param(query, "block", 3)?);
+    if !(1..=16).contains(&block_size) || max_steps.saturating_mul(width) > MAX_CELLS {
+        return Err(format!("block must be 1-16 and width x max_steps at most {MAX_CELLS}"));
+    }
+    let ca = initial(rule, width, generations, query)?;
+    check_block(block_size, width).map_err(|e| e.to_string())?;
+
+    let cycle = find_cycle_from(ca.clone(), max_steps);
+    let attractor = find_attractor(ca.clone(), max_steps).map(|(_, states)| CycleFingerprint::new(&states, width));
+    let (mean, std_dev) = entropy_signature_from(ca.clone(), generations, block_size);
+    let max_entropy = block_size as f64;
+    let (_, class) = entropy_class(mean / max_entropy, std_dev / max_entropy);
+    let (_, _, ratio) = compression_ratio_from(ca.clone(), generations);
+    let mut last = ca.clone();
+    (0..generations).for_each(|_| last.step());
+
+    let parameters = Json::object([
+        ("rule", rule.into()),
+        ("width", width.into()),
+        ("generations", generations.into()),
+        ("max_steps", max_steps.into()),
+        ("block_size", block_size.into()),
+        ("initial", init_args(query)?.to_json()),
+    ]);
+    let results = Json::object([
+        ("hash_version", HASH_VERSION.into()),
+        ("initial_hash", format!("{:016x}", ca.state_hash()).into()),
+        (
+            "cycle",
+            Json::object([
+                ("transient", cycle.transient.into()),
+                ("period", cycle.period.into()),
+                ("died", cycle.died.into()),
+                ("attractor", attractor.as_ref().map(|a| a.id().to_string()).into()),
+                ("attractor_hash", attractor.as_ref().map(|a| format!("{:016x}", a.hash)).into()),
+            ]),
+        ),
+        ("density", Json::object([("initial", ca.density().into()), ("final", last.density().into())])),
+        (
+            "entropy",
+            Json::object([("mean", mean.into()), ("std", std_dev.into()), ("class", class.into())]),
+        ),
+        ("compression_ratio", ratio.into()),
+    ]);
+    let json = report("analysis", parameters, results).to_string() + "\n";
+    Ok(Response(200, "application/json", json.into_bytes()))
+}
+
+/// The first row, as the CLI's --init/--density/--seed would give it
+fn initial(rule: u8, width: usize, generations: usize, query: &HashMap<String, String>) -> Result<Automaton, String> {
+    if width == 0 || width.saturating_mul(generations + 1) > MAX_CELLS {
+        return Err(format!("width must be at least 1 and width x (gens + 1) at most {MAX_CELLS}"));
+    }
+    Ok(init_args(query)?.automaton(width, rule))
+}
+
+fn init_args(query: &HashMap<String, String>) -> Result<InitArgs, String> {
+    let density = param(query, "density", 0.5)?;
+    if !(0.0..=1.0).contains(&density) {
+        return Err("density must be in [0, 1]".into());
+    }
+    let quiescent = match query.get("quiescent") {
+        None => Quiescent::Dead,
+        Some(value) => Quiescent::from_str(value, true).map_err(|_| format!("bad value for quiescent: '{value}'"))?,
+    };
+    let init = param(query, "init", Init::Single)?;
+    Ok(InitArgs { init, density, seed: param(query, "seed", 42)?, init_file: None, quiescent })
+}
+
+/// Query parameter `key` parsed as a T, or `default` when absent
+fn param<T: std::str::FromStr>(query: &HashMap<String, String>, key: &str, default: T) -> Result<T, String> {
+    match query.get(key) {
+        None => Ok(default),
+        Some(value) => value.parse().map_err(|_| format!("bad value for {key}: '{value}'")),
+    }
+}
+
+fn color(query: &HashMap<String, String>, key: &str, default: [u8; 3]) -> Result<[u8; 3], String> {
+    query.get(key).map_or(Ok(default), |value| super::run::parse_color(value).map_err(|e| format!("{key}: {e}")))
+}
+
+/// Decode `+` and `%XX` escapes in a query component
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(std::str::from_utf8(h).ok()?, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+            (b'+', _) => decoded.push(b' '),
+            (byte, _) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analysis_report() {
+        let Response(status, kind, body) = respond("/rule/110/analysis.json?width=31&init=hex:%460&gens=20");
+        let body = String::from_utf8(body).unwrap();
+        assert_eq!((status, kind), (200, "application/json"));
+        assert!(body.starts_with(r#"{"schema":"automata-report","version":1,"command":"analysis","parameters":{"#));
+        assert!(body.contains(r#""initial":{"init":"bits:11110000","#), "{body}");
+        assert!(body.contains(r#""cycle":{"transient":"#) && body.ends_with("}}\n"));
+        // A row that isn't one is refused, not written into the report
+        assert_eq!(respond("/rule/110/analysis.json?init=bits:1%22").0, 400);
+    }
+}
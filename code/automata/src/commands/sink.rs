@@ -0,0 +1,488 @@
+//! `--sink`: where the commands' JSON reports go
+//!
+//! Every report, a single analysis's or a survey's, is handed to
+//! `emit`, which passes it to the `OutputSink` chosen for the process:
+//! stdout by default, or appended to a JSON-lines, CSV or SQL file, or
+//! POSTed to an HTTP endpoint. The CSV and SQL sinks flatten a report into
+//! rows, one per rule of a survey and one for anything else: the command,
+//! then the parameters as `parameters.<key>` (nested objects dotted), then
+//! the results' numbers, strings and flags, with arrays and objects kept as
+//! JSON text. A CSV file takes its header from the first report and
+//! refuses reports with other columns. A new format is one more sink; the
+//! commands don't change.
+//!
+//! Like `--preset`, the sink works on the command line: a subcommand with a
+//! `--format table|json` not given gets `--format=json`, so single analyses
+//! build their report, and batch children inherit it. A subcommand with no
+//! JSON report to send, such as `run` or `render`, is refused rather than
+//! run with the sink left empty.
+//!
+//! There is no SQLite library here, so the SQL sink writes a script of
+//! `CREATE TABLE IF NOT EXISTS` and `INSERT` statements, a table per
+//! command, for `sqlite3 results.db < results.sql`; nor TLS, so endpoints
+//! are plain `http://`. The terminal tables stay each command's own.
+
+use super::json::Json;
+use super::log::verbose;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The `--sink` given, if any
+static DESTINATION: Mutex<Option<Destination>> = Mutex::new(None);
+
+/// Where reports go, one whole report per call
+pub trait OutputSink {
+    fn write(&mut self, report: &Json) -> io::Result<()>;
+}
+
+/// A `--sink` value
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Destination {
+    Stdout,
+    /// A report per line, appended
+    JsonLines(PathBuf),
+    /// Rows appended under the file's header, which a new file gets from
+    /// the first report
+    Csv(PathBuf),
+    /// `sqlite3` statements appended, a transaction per report
+    Sql(PathBuf),
+    /// POSTed as `application/json`, one request per report
+    Http { host: String, port: u16, path: String },
+}
+
+impl std::str::FromStr for Destination {
+    type Err = String;
+
+    /// `-`, `http://HOST[:PORT][/PATH]`, or a file named for its format:
+    /// `.jsonl` or `.json`, `.csv`, `.sql`
+    fn from_str(s: &str) -> Result<Self, String> {
+        if s == "-" {
+            return Ok(Destination::Stdout);
+        }
+        if let Some(rest) = s.strip_prefix("http://") {
+            let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+            let (host, port) = match authority.rsplit_once(':') {
+                Some((host, port)) => (host, port.parse().map_err(|_| format!("{port:?} is not a port"))?),
+                None => (authority, 80),
+            };
+            if host.is_empty() {
+                return Err(format!("no host in {s:?}"));
+            }
+            let path = if path.is_empty() { "/" } else { path };
+            return Ok(Destination::Http { host: host.to_string(), port, path: path.to_string() });
+        }
+        if s.starts_with("https://") {
+            return Err("there is no TLS client in this build; give an http:// endpoint".to_string());
+        }
+        let path = PathBuf::from(s);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("jsonl" | "json") => Ok(Destination::JsonLines(path)),
+            Some("csv") => Ok(Destination::Csv(path)),
+            Some("sql") => Ok(Destination::Sql(path)),
+            Some("db" | "sqlite" | "sqlite3") => Err(format!(
+                "SQLite databases aren't written directly; give a .sql file and load it with sqlite3 {s} < FILE.sql"
+            )),
+            _ => Err(format!("expected -, http://..., or a .jsonl, .csv or .sql file, not {s:?}")),
+        }
+    }
+}
+
+impl fmt::Display for Destination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Destination::Stdout => write!(f, "stdout"),
+            Destination::JsonLines(path) | Destination::Csv(path) | Destination::Sql(path) => {
+                write!(f, "{}", path.display())
+            }
+            Destination::Http { host, port, path } => write!(f, "http://{host}:{port}{path}"),
+        }
+    }
+}
+
+impl Destination {
+    pub fn open(&self) -> Box<dyn OutputSink> {
+        match self {
+            Destination::Stdout => Box::new(Stdout),
+            Destination::JsonLines(path) => Box::new(JsonLines(path.clone())),
+            Destination::Csv(path) => Box::new(Csv(path.clone())),
+            Destination::Sql(path) => Box::new(Sql(path.clone())),
+            Destination::Http { host, port, path } => {
+                Box::new(Http { host: host.clone(), port: *port, path: path.clone() })
+            }
+        }
+    }
+}
+
+pub fn set_destination(destination: Option<Destination>) {
+    *DESTINATION.lock().unwrap() = destination;
+}
+
+/// The `--sink` given, if any
+pub fn destination() -> Option<Destination> {
+    DESTINATION.lock().unwrap().clone()
+}
+
+/// Whether reports go somewhere other than stdout, so a command's table
+/// can still be printed there
+pub fn redirected() -> bool {
+    destination().is_some_and(|d| d != Destination::Stdout)
+}
+
+/// Hand a report to the chosen sink, or exit with the error
+pub fn emit(report: Json) {
+    let destination = destination().unwrap_or(Destination::Stdout);
+    if let Err(e) = destination.open().write(&report) {
+        eprintln!("Can't send the report to {destination}: {e}");
+        std::process::exit(1);
+    }
+    if destination != Destination::Stdout {
+        verbose!("Report -> {destination}");
+    }
+}
+
+/// `argv` with `--format=json` added if its subcommand takes `--format
+/// table|json` and it isn't given, so single analyses build the report a
+/// sink takes. Unchanged without a sink, or if it doesn't parse
+pub fn expand(cli: &clap::Command, argv: &[String]) -> Vec<String> {
+    let mut expanded = argv.to_vec();
+    if !redirected() {
+        return expanded;
+    }
+    let Ok(matches) = cli.clone().try_get_matches_from(argv) else { return expanded };
+    let Some((name, sub_matches)) = matches.subcommand() else { return expanded };
+    let sub = cli.find_subcommand(name).expect("a parsed subcommand exists");
+    let Some(arg) = sub.get_arguments().find(|arg| arg.get_long() == Some("format")) else { return expanded };
+    let values: Vec<String> = arg.get_possible_values().iter().map(|v| v.get_name().to_string()).collect();
+    if values == ["table", "json"] && sub_matches.value_source("format") != Some(clap::parser::ValueSource::CommandLine)
+    {
+        let end = expanded.iter().position(|arg| arg == "--").unwrap_or(expanded.len());
+        expanded.insert(end, "--format=json".to_string());
+    }
+    expanded
+}
+
+/// Subcommands without a report of their own that still honour a sink
+const FORWARDS_SINK: [&str; 1] = ["batch"];
+
+/// An error naming `argv`'s subcommand if a redirected sink would get
+/// nothing from it: it needs a `--format` with `json`, or to hand the sink
+/// on to its runs. Experiment files are checked a line at a time
+pub fn check(cli: &clap::Command, argv: &[String]) -> Result<(), String> {
+    if redirected() { check_subcommand(cli, argv) } else { Ok(()) }
+}
+
+fn check_subcommand(cli: &clap::Command, argv: &[String]) -> Result<(), String> {
+    let Ok(matches) = cli.clone().try_get_matches_from(argv) else { return Ok(()) };
+    let name = match matches.subcommand_name() {
+        Some(name) => name,
+        None if matches.get_raw("config").is_some() => return Ok(()),
+        // With no subcommand, main runs `run`
+        None => "run",
+    };
+    let sub = cli.find_subcommand(name).expect("a parsed subcommand exists");
+    let reports = sub
+        .get_arguments()
+        .filter(|arg| arg.get_long() == Some("format"))
+        .any(|arg| arg.get_possible_values().iter().any(|value| value.get_name() == "json"));
+    if reports || FORWARDS_SINK.contains(&name) {
+        Ok(())
+    } else {
+        Err(format!("--sink takes JSON reports, and {name} doesn't make one"))
+    }
+}
+
+struct Stdout;
+
+impl OutputSink for Stdout {
+    fn write(&mut self, report: &Json) -> io::Result<()> {
+        writeln!(io::stdout(), "{report}")
+    }
+}
+
+struct JsonLines(PathBuf);
+
+impl OutputSink for JsonLines {
+    fn write(&mut self, report: &Json) -> io::Result<()> {
+        writeln!(append(&self.0)?, "{report}")
+    }
+}
+
+struct Csv(PathBuf);
+
+impl OutputSink for Csv {
+    fn write(&mut self, report: &Json) -> io::Result<()> {
+        let rows = rows(report);
+        let Some(first) = rows.first() else { return Ok(()) };
+        let columns: Vec<&str> = first.iter().map(|(key, _)| key.as_str()).collect();
+        let header = columns.iter().map(|c| csv_cell(&Json::String(c.to_string()))).collect::<Vec<_>>().join(",");
+        let existing = match std::fs::File::open(&self.0) {
+            Ok(file) => BufReader::new(file).lines().next().transpose()?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+        let mut text = String::new();
+        match existing {
+            Some(line) if line != header => {
+                return Err(io::Error::other("it has other columns; give each command a file of its own"));
+            }
+            Some(_) => {}
+            None => text = header + "\n",
+        }
+        for row in &rows {
+            text += &row.iter().map(|(_, value)| csv_cell(value)).collect::<Vec<_>>().join(",");
+            text += "\n";
+        }
+        append(&self.0)?.write_all(text.as_bytes())
+    }
+}
+
+struct Sql(PathBuf);
+
+impl OutputSink for Sql {
+    fn write(&mut self, report: &Json) -> io::Result<()> {
+        let rows = rows(report);
+        let Some(first) = rows.first() else { return Ok(()) };
+        let table = sql_name(&command(report));
+        let columns = first.iter().map(|(key, _)| sql_name(key)).collect::<Vec<_>>().join(", ");
+        let mut text = format!("BEGIN;\nCREATE TABLE IF NOT EXISTS {table} ({columns});\n");
+        for row in &rows {
+            let values = row.iter().map(|(_, value)| sql_value(value)).collect::<Vec<_>>().join(", ");
+            text += &format!("INSERT INTO {table} ({columns}) VALUES ({values});\n");
+        }
+        text += "COMMIT;\n";
+        append(&self.0)?.write_all(text.as_bytes())
+    }
+}
+
+struct Http {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl OutputSink for Http {
+    fn write(&mut self, report: &Json) -> io::Result<()> {
+        let body = report.to_string();
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port))?;
+        stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}:{}\r\nUser-Agent: automata/{}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            self.path,
+            self.host,
+            self.port,
+            env!("CARGO_PKG_VERSION"),
+            body.len()
+        )?;
+        let mut status = String::new();
+        BufReader::new(stream).read_line(&mut status)?;
+        match status.split_whitespace().nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            _ => Err(io::Error::other(format!("the server answered {:?}", status.trim_end()))),
+        }
+    }
+}
+
+fn append(path: &Path) -> io::Result<std::fs::File> {
+    std::fs::OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// A report's `"command"`
+fn command(report: &Json) -> String {
+    match field(report, "command") {
+        Some(Json::String(command)) => command.clone(),
+        _ => "report".to_string(),
+    }
+}
+
+fn field<'a>(json: &'a Json, key: &str) -> Option<&'a Json> {
+    match json {
+        Json::Object(fields) => fields.iter().find(|(k, _)| *k == key).map(|(_, value)| value),
+        _ => None,
+    }
+}
+
+/// A flattened row: column names and values, none of them objects
+type Row = Vec<(String, Json)>;
+
+/// A report as rows: one per object of a survey's `"rules"`, else one for
+/// its results, each after the command and the parameters
+fn rows(report: &Json) -> Vec<Row> {
+    let mut head = vec![("command".to_string(), Json::String(command(report)))];
+    if let Some(parameters) = field(report, "parameters") {
+        flatten(parameters, "parameters.", &mut head);
+    }
+    let results = field(report, "results").unwrap_or(&Json::Null);
+    let each: Vec<&Json> = match field(results, "rules") {
+        Some(Json::Array(rules)) if rules.iter().all(|rule| matches!(rule, Json::Object(_))) => rules.iter().collect(),
+        _ => vec![results],
+    };
+    each.into_iter()
+        .map(|results| {
+            let mut row = head.clone();
+            match results {
+                Json::Object(_) => flatten(results, "", &mut row),
+                Json::Null => {}
+                _ => row.push(("results".to_string(), results.clone())),
+            }
+            row
+        })
+        .collect()
+}
+
+/// An object's fields under `prefix`, nested objects' with their keys
+/// dotted on, and arrays as JSON text
+fn flatten(json: &Json, prefix: &str, row: &mut Row) {
+    let Json::Object(fields) = json else { return };
+    for (key, value) in fields {
+        match value {
+            Json::Object(_) => flatten(value, &format!("{prefix}{key}."), row),
+            Json::Array(_) => row.push((format!("{prefix}{key}"), Json::String(value.to_string()))),
+            _ => row.push((format!("{prefix}{key}"), value.clone())),
+        }
+    }
+}
+
+/// Quoted only if it would otherwise split a row
+fn csv_cell(value: &Json) -> String {
+    match value {
+        Json::Null => String::new(),
+        Json::String(s) if s.contains([',', '"', '\n', '\r']) => format!("\"{}\"", s.replace('"', "\"\"")),
+        Json::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn sql_name(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Numbers bare, flags as 1 and 0 (SQLite has no booleans), text quoted
+fn sql_value(value: &Json) -> String {
+    match value {
+        Json::Null => "NULL".to_string(),
+        Json::Bool(b) => (*b as u8).to_string(),
+        Json::Number(n) => n.clone(),
+        Json::String(s) => format!("'{}'", s.replace('\'', "''")),
+        other => format!("'{}'", other.to_string().replace('\'', "''")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::TempDir;
+    use super::*;
+
+    /// A survey's report: two rules, one with a quote and a newline in its
+    /// verdict
+    fn survey() -> Json {
+        let parameters = Json::object([("width", 31.into()), ("initial", Json::object([("init", "random".into())]))]);
+        let rule = |rule: u8, verdict: &str| {
+            Json::object([
+                ("rule", rule.into()),
+                ("cycle", Json::object([("period", Json::Null), ("died", false.into())])),
+                ("verdict", verdict.into()),
+                ("densities", vec![0.5, 0.25].into()),
+            ])
+        };
+        let results = Json::object([("rules", Json::array([rule(30, "chaotic"), rule(110, "\"complex\",\nlocal")]))]);
+        Json::object([("command", "entropy-survey".into()), ("parameters", parameters), ("results", results)])
+    }
+
+    #[test]
+    fn test_rows() {
+        let rows = rows(&survey());
+        let columns: Vec<&str> = rows[0].iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(
+            columns,
+            [
+                "command",
+                "parameters.width",
+                "parameters.initial.init",
+                "rule",
+                "cycle.period",
+                "cycle.died",
+                "verdict",
+                "densities"
+            ]
+        );
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1][3].1, 110.into());
+        assert_eq!(rows[1][7].1, "[0.5,0.25]".into());
+        // Anything but a survey is one row of its results
+        let single = Json::object([("command", "cycle".into()), ("results", Json::object([("period", 7.into())]))]);
+        assert_eq!(super::rows(&single), [vec![("command".into(), "cycle".into()), ("period".into(), 7.into())]]);
+    }
+
+    #[test]
+    fn test_csv() {
+        let dir = TempDir::new("sink");
+        let path = dir.join("report.csv");
+        let mut csv = Destination::Csv(path.clone()).open();
+        csv.write(&survey()).unwrap();
+        // A second report with the same columns goes under the one header
+        csv.write(&survey()).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let header = "command,parameters.width,parameters.initial.init,rule,cycle.period,cycle.died,verdict,densities";
+        let rule30 = "entropy-survey,31,random,30,,false,chaotic,\"[0.5,0.25]\"";
+        let rule110 = "entropy-survey,31,random,110,,false,\"\"\"complex\"\",\nlocal\",\"[0.5,0.25]\"";
+        assert_eq!(text, format!("{header}\n{rule30}\n{rule110}\n{rule30}\n{rule110}\n"));
+        // One with other columns is refused, and the file left as it was
+        let other = Json::object([("command", "cycle".into()), ("results", Json::object([("period", 7.into())]))]);
+        assert!(csv.write(&other).unwrap_err().to_string().contains("other columns"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+    }
+
+    #[test]
+    fn test_sql() {
+        let dir = TempDir::new("sink");
+        let path = dir.join("report.sql");
+        let report = Json::object([
+            ("command", "cycle".into()),
+            ("parameters", Json::object([("init", "bits:1'0".into())])),
+            ("results", Json::object([("died", true.into()), ("note", "it's \"done\"\n".into())])),
+        ]);
+        Destination::Sql(path.clone()).open().write(&report).unwrap();
+        let columns = r#""command", "parameters.init", "died", "note""#;
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            format!(
+                "BEGIN;\nCREATE TABLE IF NOT EXISTS \"cycle\" ({columns});\n\
+                 INSERT INTO \"cycle\" ({columns}) VALUES ('cycle', 'bits:1''0', 1, 'it''s \"done\"\n');\nCOMMIT;\n"
+            )
+        );
+        assert_eq!(sql_name(r#"a"b"#), r#""a""b""#);
+    }
+
+    #[test]
+    fn test_check() {
+        use clap::CommandFactory;
+        let cli = crate::Cli::command();
+        let check = |line: &str| check_subcommand(&cli, &line.split(' ').map(String::from).collect::<Vec<_>>());
+        assert_eq!(check("automata basins 30 -w 8"), Ok(()));
+        assert_eq!(check("automata analyze -w 12"), Ok(()));
+        assert_eq!(check("automata batch runs.toml"), Ok(()));
+        assert_eq!(check("automata render 30").unwrap_err(), "--sink takes JSON reports, and render doesn't make one");
+        assert_eq!(check("automata --quiet").unwrap_err(), "--sink takes JSON reports, and run doesn't make one");
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let dir = TempDir::new("sink");
+        let path = dir.join("report.jsonl");
+        let mut sink = Destination::JsonLines(path.clone()).open();
+        sink.write(&survey()).unwrap();
+        let other = Json::object([("command", "cycle".into()), ("results", Json::object([("period", 7.into())]))]);
+        sink.write(&other).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        // Each report whole on its line, the newline in a value escaped
+        assert_eq!(lines, [survey().to_string(), other.to_string()]);
+        assert!(lines[0].contains(r#""verdict":"\"complex\",\nlocal""#));
+    }
+}
@@ -0,0 +1,205 @@
+//! `stream`: each generation sent as a frame of bytes at a fixed rate, to a
+//! serial port driving an LED strip or matrix, or to any file or pipe
+//!
+//! A serial device is put in raw mode at the requested baud rate first, so
+//! no byte of a frame is taken for a control character; anything that isn't
+//! a terminal device is written as it is.
+
+use super::progress::Progress;
+use super::run::parse_color;
+use super::{parse_rule, parse_width, InitArgs};
+use automata::led::{Display, FrameSink, Framing, PixelFormat, Scroller};
+use clap::{Args, ValueEnum};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+#[derive(Args, Clone)]
+pub struct StreamArgs {
+    /// Rule number (0-255) or a Wolfram code for one, e.g. k=2,r=1,code=10
+    #[arg(default_value_t = 30, value_parser = parse_rule)]
+    pub rule: u8,
+    /// Number of cells in the ring
+    #[arg(short, long, default_value_t = 16, value_parser = parse_width)]
+    pub width: usize,
+    /// Serial port (e.g. /dev/ttyUSB0), file or FIFO to stream to; - for stdout
+    #[arg(short, long)]
+    pub out: PathBuf,
+    /// Baud rate, for a serial port
+    #[arg(long, default_value_t = 115200)]
+    pub baud: u32,
+    /// Pixels across the display (default: the width)
+    #[arg(long)]
+    pub columns: Option<usize>,
+    /// Pixel rows: the last this many generations, newest at the bottom; 1 for a strip
+    #[arg(long, default_value_t = 16)]
+    pub rows: usize,
+    /// Frames (generations) per second
+    #[arg(long, default_value_t = 10.0)]
+    pub fps: f64,
+    /// Stop after this many generations (default: until killed)
+    #[arg(short = 'g', long = "gens")]
+    pub generations: Option<usize>,
+    /// Bytes per pixel: packed bits, one 0/1 byte, or RGB
+    #[arg(short, long, value_enum, default_value_t = Pixels::Rgb)]
+    pub format: Pixels,
+    /// Live pixel color with -f rgb, as RRGGBB hex
+    #[arg(long, default_value = "ffffff", value_parser = parse_color)]
+    pub live: [u8; 3],
+    /// Dead pixel color with -f rgb, as RRGGBB hex
+    #[arg(long, default_value = "000000", value_parser = parse_color)]
+    pub dead: [u8; 3],
+    /// Every other row runs right to left, as on most wired LED panels
+    #[arg(long)]
+    pub serpentine: bool,
+    /// Start each frame with the Adalight header that Arduino LED-strip sketches sync on (-f rgb only)
+    #[arg(long)]
+    pub adalight: bool,
+    #[command(flatten)]
+    pub init: InitArgs,
+}
+
+/// Pixel encodings for `stream`
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Pixels {
+    /// One bit per pixel, each row padded to a byte, most significant bit first
+    Bits,
+    /// One byte per pixel, 1 live and 0 dead
+    Bytes,
+    /// Three bytes per pixel, in the --live and --dead colors
+    Rgb,
+}
+
+/// Serial rates every platform's termios has
+const BAUD_RATES: [u32; 6] = [9600, 19200, 38400, 57600, 115200, 230400];
+
+pub fn stream(args: &StreamArgs) {
+    let StreamArgs {
+        rule,
+        width,
+        ref out,
+        baud,
+        columns,
+        rows,
+        fps,
+        generations,
+        format,
+        live,
+        dead,
+        serpentine,
+        adalight,
+        ref init,
+    } = *args;
+    let columns = columns.unwrap_or(width);
+    if columns == 0 || rows == 0 || !(fps.is_finite() && fps > 0.0) {
+        eprintln!("--columns, --rows and --fps must be positive");
+        std::process::exit(1);
+    }
+    if adalight && (format != Pixels::Rgb || columns * rows > 1 << 16) {
+        eprintln!("--adalight frames up to 65536 RGB pixels; use -f rgb and a smaller display");
+        std::process::exit(1);
+    }
+    if !BAUD_RATES.contains(&baud) {
+        eprintln!("--baud must be one of {}", BAUD_RATES.map(|rate| rate.to_string()).join(", "));
+        std::process::exit(1);
+    }
+    let format = match format {
+        Pixels::Bits => PixelFormat::Bits,
+        Pixels::Bytes => PixelFormat::Bytes,
+        Pixels::Rgb => PixelFormat::Rgb { live, dead },
+    };
+    let framing = if adalight { Framing::Adalight } else { Framing::Raw };
+    let display = Display { columns, rows, serpentine, format, framing };
+
+    let to_stdout = out.as_os_str() == "-";
+    let mut sink = open(out, baud).unwrap_or_else(|e| {
+        eprintln!("Can't open {}: {e}", out.display());
+        std::process::exit(1);
+    });
+    let destination = if to_stdout { "stdout".to_string() } else { out.display().to_string() };
+    let report = format!(
+        "Rule {rule} (width={width}{}) -> {destination}: {columns} x {rows} frames of {} bytes at {fps} fps",
+        init.describe(),
+        display.frame_len()
+    );
+    if to_stdout {
+        eprintln!("{report}");
+    } else {
+        println!("{report}");
+    }
+
+    let mut ca = init.automaton(width, rule);
+    let mut scroller = Scroller::new(display);
+    let interval = Duration::from_secs_f64(1.0 / fps);
+    let progress = generations.map(|limit| Progress::generations(&format!("Rule {rule}"), limit));
+    let mut next_frame = Instant::now();
+    let mut generation = 0;
+    loop {
+        if let Err(e) = sink.send(&scroller.push(&ca)) {
+            eprintln!("Can't write to {destination}: {e}");
+            std::process::exit(1);
+        }
+        if generations.is_some_and(|limit| generation >= limit) {
+            break;
+        }
+        // A frame late from a slow link is followed at once, not by a burst
+        next_frame = (next_frame + interval).max(Instant::now());
+        std::thread::sleep(next_frame.saturating_duration_since(Instant::now()));
+        ca.step();
+        generation += 1;
+        if let Some(progress) = &progress {
+            progress.tick();
+        }
+    }
+}
+
+/// Stdout, or the file at `path`, set up as a raw serial line if it is one
+fn open(path: &Path, baud: u32) -> io::Result<Box<dyn Write>> {
+    if path.as_os_str() == "-" {
+        return Ok(Box::new(io::stdout().lock()));
+    }
+    let file = std::fs::OpenOptions::new().write(true).create(true).truncate(true).open(path)?;
+    configure_serial(&file, baud)?;
+    Ok(Box::new(file))
+}
+
+/// Raw mode at `baud`: no echo, line editing, newline translation or flow
+/// control, 8 data bits; files that aren't terminals are left alone
+#[cfg(unix)]
+fn configure_serial(file: &std::fs::File, baud: u32) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+    let speed = match baud {
+        9600 => libc::B9600,
+        19200 => libc::B19200,
+        38400 => libc::B38400,
+        57600 => libc::B57600,
+        115200 => libc::B115200,
+        _ => libc::B230400,
+    };
+    let fd = file.as_raw_fd();
+    // SAFETY: termios is plain data, filled in by tcgetattr before use
+    unsafe {
+        let mut tty: libc::termios = std::mem::zeroed();
+        if libc::isatty(fd) == 0 {
+            return Ok(());
+        }
+        if libc::tcgetattr(fd, &mut tty) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        libc::cfmakeraw(&mut tty);
+        tty.c_cflag |= libc::CLOCAL | libc::CREAD;
+        if libc::cfsetispeed(&mut tty, speed) != 0
+            || libc::cfsetospeed(&mut tty, speed) != 0
+            || libc::tcsetattr(fd, libc::TCSANOW, &tty) != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Elsewhere a serial port is written with whatever settings it has
+#[cfg(not(unix))]
+fn configure_serial(_file: &std::fs::File, _baud: u32) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! An elementary rule iterated: t steps of it as one step of a radius-t rule
+//!
+//! After t steps a cell depends on the 2t + 1 cells around it and nothing
+//! else, so t steps of an ECA are one lookup table of 2^(2t+1) entries,
+//! windows read leftmost cell first as `TableRule`'s are (t = 1 gives back
+//! the rule number). Each entry is its window run t times, the ends falling
+//! off a cell a step until one is left. The table is an object of its own:
+//! how many windows it maps to 1 (Langton's λ), and which of the 2t + 1
+//! cells it actually reads, which for an additive rule is a thin set (rule
+//! 90 twice reads only the cells two away). Radius 3 and below is a
+//! `TableRule` the packed stepper runs; wider tables step a row by lookup.
+
+use crate::evolve::{TableRule, MAX_RADIUS};
+use crate::Automaton;
+
+/// Most steps composed: a radius-8 table has 2^17 entries
+pub const MAX_TIMES: usize = 8;
+
+/// `rule` applied `times` times, as a table over (2 * times + 1)-cell windows
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Iterated {
+    pub rule: u8,
+    pub times: usize,
+    /// Entry w is the next state of a cell whose window, read leftmost
+    /// first, is w
+    pub table: Vec<bool>,
+}
+
+impl Iterated {
+    /// Panics unless `times` is 1 to `MAX_TIMES`
+    pub fn new(rule: u8, times: usize) -> Self {
+        assert!((1..=MAX_TIMES).contains(&times), "a rule can be composed 1 to {MAX_TIMES} times");
+        let size = 2 * times + 1;
+        let table = (0..1usize << size)
+            .map(|window| {
+                let mut cells: Vec<u8> = (0..size).map(|j| (window >> (size - 1 - j)) as u8 & 1).collect();
+                while cells.len() > 1 {
+                    cells = cells.windows(3).map(|c| rule >> (c[0] << 2 | c[1] << 1 | c[2]) & 1).collect();
+                }
+                cells[0] == 1
+            })
+            .collect();
+        Self { rule, times, table }
+    }
+
+    pub fn radius(&self) -> usize {
+        self.times
+    }
+
+    /// Windows mapped to a live cell
+    pub fn ones(&self) -> usize {
+        self.table.iter().filter(|&&live| live).count()
+    }
+
+    /// The table as a `TableRule`, if its radius is one
+    pub fn table_rule(&self) -> Option<TableRule> {
+        let table = self.table.iter().rev().fold(0u128, |t, &live| t << 1 | live as u128);
+        (self.radius() <= MAX_RADIUS).then(|| TableRule::new(self.radius(), table))
+    }
+
+    /// The table in hex, highest window first, as in a `table:` spec
+    pub fn hex(&self) -> String {
+        let digit = |nibble: &[bool]| nibble.iter().rev().fold(0, |d, &live| d << 1 | live as u32);
+        let digits = self.table.chunks(4).rev().map(|nibble| char::from_digit(digit(nibble), 16).unwrap());
+        digits.collect()
+    }
+
+    /// The offsets from the cell (-t..=t) whose state changes the table's
+    /// answer for some window
+    pub fn dependencies(&self) -> Vec<isize> {
+        let size = 2 * self.times;
+        (0..=size)
+            .filter(|&j| {
+                let bit = 1 << (size - j);
+                (0..self.table.len()).any(|w| self.table[w] != self.table[w ^ bit])
+            })
+            .map(|j| j as isize - self.times as isize)
+            .collect()
+    }
+
+    /// One step of a ring by table lookup
+    pub fn step(&self, cells: &[bool]) -> Vec<bool> {
+        let (n, t) = (cells.len(), self.times);
+        (0..n)
+            .map(|i| {
+                let window = (0..=2 * t).fold(0, |w, j| w << 1 | cells[(i + n * t + j - t) % n] as usize);
+                self.table[window]
+            })
+            .collect()
+    }
+
+    /// One step of the table (by lookup, and by the packed stepper if it's a
+    /// `TableRule`) against `times` steps of the rule, on `rows` random
+    /// rows of `width` cells: the cells compared, or the first that differs
+    pub fn verify(&self, width: usize, rows: usize, seed: u64) -> Result<usize, String> {
+        for row in 0..rows {
+            let seed = seed.wrapping_add(row as u64);
+            let mut ca = Automaton::random(width, self.rule, 0.5, seed);
+            let start = ca.cells();
+            (0..self.times).for_each(|_| ca.step());
+            let stepped = ca.cells();
+            let mut results = vec![("lookup", self.step(&start))];
+            if let Some(table) = self.table_rule() {
+                let mut packed = Automaton::from_cells(start.clone(), 0).with_table(table);
+                packed.step();
+                results.push(("packed", packed.cells()));
+            }
+            for (how, result) in results {
+                if let Some(i) = (0..width).find(|&i| result[i] != stepped[i]) {
+                    return Err(format!("row {row} (seed {seed}): cell {i} differs stepped by {how}"));
+                }
+            }
+        }
+        Ok(width * rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_iterated_tables() {
+        // Once is the rule itself
+        for rule in [30, 90, 110] {
+            let once = Iterated::new(rule, 1);
+            assert_eq!(once.table_rule(), Some(TableRule::new(1, rule as u128)));
+            assert_eq!(once.hex(), format!("{rule:02x}"));
+        }
+        // Rule 90 twice is the XOR of the cells two away; the identity reads
+        // only the cell, and t shifts read the cell t to the right
+        assert_eq!(Iterated::new(90, 2).dependencies(), [-2, 2]);
+        assert_eq!(Iterated::new(204, 3).dependencies(), [0]);
+        assert_eq!(Iterated::new(170, 4).dependencies(), [4]);
+        assert_eq!(Iterated::new(30, 2).dependencies(), [-2, -1, 0, 1, 2]);
+
+        for (rule, times) in [(30, 2), (110, 3), (54, 5), (184, 8)] {
+            let iterated = Iterated::new(rule, times);
+            assert_eq!(iterated.table.len(), 1 << (2 * times + 1));
+            assert_eq!(iterated.hex().len(), iterated.table.len().div_ceil(4));
+            assert_eq!(iterated.verify(37, 4, 1), Ok(37 * 4), "rule {rule} x {times}");
+        }
+        // The table is a TableRule to radius 3, with the same spec
+        let table = Iterated::new(110, 3).table_rule().unwrap();
+        assert_eq!(table.to_string(), format!("table:{}", Iterated::new(110, 3).hex()));
+        assert_eq!(table.to_string().parse::<TableRule>(), Ok(table));
+        assert!(Iterated::new(110, 4).table_rule().is_none());
+    }
+}
@@ -0,0 +1,225 @@
+//! Damage spreading: two copies of a run a single flipped cell apart
+//!
+//! The Hamming distance between the copies is the damage. In an ordered
+//! rule (Wolfram's classes 1 and 2) it heals or stays put; in a chaotic one
+//! (class 3) it spreads at up to r cells a side per step until it covers the
+//! ring, where about half the cells differ. Its growth in cells per step,
+//! before the damage can meet itself around the ring, is about the speed
+//! of its two fronts times the fraction of cells between them that differ.
+//!
+//! The standard exponent is Bagnoli, Rechtman and Ruffo's maximum Lyapunov
+//! exponent: the damage as a count of defects per cell, carried along the
+//! reference run by the rule's Boolean derivatives. A defect at cell i + j
+//! sends one to cell i whenever flipping that neighbor changes cell i's
+//! next state, so defects add where the Hamming distance would cancel:
+//! rule 90 doubles them every step (λ = ln 2), rule 150 triples them
+//! (ln 3), and a shift keeps exactly one (0). λ is the growth rate of their
+//! total, renormalized each step so it never overflows, and -∞ for a run
+//! whose defects all die.
+
+use crate::evolve::TableRule;
+use crate::Automaton;
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+/// Damage spreading averaged over random starting rows
+#[derive(Clone, Debug, PartialEq)]
+pub struct Damage {
+    pub width: usize,
+    pub samples: usize,
+    /// Mean Hamming distance between the copies at each generation, from 1
+    /// at generation 0
+    pub distance: Vec<f64>,
+    /// Samples whose copies had come back together by the last generation
+    pub healed: usize,
+    /// Least-squares slope of the mean distance, in cells per step, while
+    /// the damage can't yet have wrapped around the ring; None with fewer
+    /// than two such generations
+    pub growth: Option<f64>,
+    /// The mean Lyapunov exponent of the samples whose defects survived
+    pub lyapunov: Option<f64>,
+    /// Samples whose defects all died, each with exponent -∞
+    pub extinct: usize,
+}
+
+impl Damage {
+    /// Mean fraction of the ring damaged at the end
+    pub fn final_fraction(&self) -> f64 {
+        self.distance.last().map_or(0.0, |d| d / self.width as f64)
+    }
+
+    /// Mean fraction of the ring damaged over the second half of the run,
+    /// steadier than the last generation's (rule 90's comes and goes with
+    /// the bits of t)
+    pub fn late_fraction(&self) -> f64 {
+        let late = &self.distance[self.distance.len() / 2..];
+        late.iter().sum::<f64>() / (late.len() * self.width) as f64
+    }
+
+    /// Last generation the fit of `growth` uses: a single cell's damage
+    /// reaches r cells further each way per step, so it can close around
+    /// the ring only after (width - 1) / 2r of them
+    pub fn unwrapped(radius: usize, width: usize, generations: usize) -> usize {
+        ((width - 1) / (2 * radius)).min(generations)
+    }
+}
+
+/// One sample: its distance series and ln of its final defect count
+struct Run {
+    distance: Vec<usize>,
+    log_defects: Option<f64>,
+}
+
+/// `samples` random rows of `width` cells (density 1/2, row i seeded with
+/// `seed` + i) run for `generations` steps beside copies with the middle
+/// cell flipped
+pub fn damage_spreading(rule: TableRule, width: usize, generations: usize, samples: usize, seed: u64) -> Damage {
+    assert!(width > 0 && samples > 0, "damage needs a ring and a sample");
+    let runs: Vec<Run> = (0..samples)
+        .into_par_iter()
+        .map(|i| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed.wrapping_add(i as u64));
+            run(rule, (0..width).map(|_| rng.random_bool(0.5)).collect(), generations)
+        })
+        .collect();
+    let distance: Vec<f64> = (0..=generations)
+        .map(|t| runs.iter().map(|run| run.distance[t]).sum::<usize>() as f64 / samples as f64)
+        .collect();
+    let healed = runs.iter().filter(|run| run.distance[generations] == 0).count();
+    let surviving: Vec<f64> = runs.iter().filter_map(|run| run.log_defects).collect();
+    let lyapunov = (!surviving.is_empty() && generations > 0)
+        .then(|| surviving.iter().sum::<f64>() / (surviving.len() * generations) as f64);
+    let fitted: Vec<(f64, f64)> =
+        (0..=Damage::unwrapped(rule.radius, width, generations)).map(|t| (t as f64, distance[t])).collect();
+    Damage {
+        width,
+        samples,
+        growth: slope(&fitted),
+        distance,
+        healed,
+        lyapunov,
+        extinct: samples - surviving.len(),
+    }
+}
+
+/// The damage as a spacetime diagram: the XOR of the runs from `ca` and
+/// from it with `cell` flipped, for the initial row and `generations` after.
+/// Its outline is the perturbation's light cone, at most r cells wider a
+/// side per step. Both copies carry `ca`'s noise stream, so they flip the
+/// same cells and only the perturbation tells them apart
+pub fn difference_pattern(ca: &Automaton, cell: usize, generations: usize) -> Vec<Vec<bool>> {
+    assert!(cell < ca.width(), "cell {cell} is off the ring");
+    let (mut a, mut b) = (ca.clone(), ca.with_flipped(cell));
+    let mut rows = Vec::with_capacity(generations + 1);
+    for t in 0..=generations {
+        if t > 0 {
+            a.step();
+            b.step();
+        }
+        rows.push(a.iter().zip(b.iter()).map(|(x, y)| x != y).collect());
+    }
+    rows
+}
+
+/// The copies from `cells` and from it with the middle cell flipped, and
+/// the defects carried along the first
+fn run(rule: TableRule, cells: Vec<bool>, generations: usize) -> Run {
+    let width = cells.len();
+    let (mut reference, mut damaged) = (cells.clone(), cells);
+    damaged[width / 2] ^= true;
+    let mut defects = vec![0.0; width];
+    defects[width / 2] = 1.0;
+    let (mut distance, mut log_defects) = (vec![1], Some(0.0));
+    for _ in 0..generations {
+        if let Some(log) = log_defects.as_mut() {
+            defects = carry(rule, &reference, &defects);
+            let total: f64 = defects.iter().sum();
+            match total > 0.0 {
+                true => {
+                    defects.iter_mut().for_each(|d| *d /= total);
+                    *log += total.ln();
+                }
+                false => log_defects = None,
+            }
+        }
+        (reference, damaged) = (rule.step(&reference), rule.step(&damaged));
+        distance.push(reference.iter().zip(&damaged).filter(|(a, b)| a != b).count());
+    }
+    Run { distance, log_defects }
+}
+
+/// One step of the defects on the run at `cells`: each cell collects those
+/// of the neighbors its next state depends on there
+fn carry(rule: TableRule, cells: &[bool], defects: &[f64]) -> Vec<f64> {
+    let (n, r) = (cells.len(), rule.radius);
+    let size = 2 * r + 1;
+    let next = |w: usize| rule.table >> w & 1;
+    (0..n)
+        .map(|i| {
+            // Leftmost cell, i - r, the most significant bit
+            let w = (0..size).fold(0, |w, j| w << 1 | cells[(i + n * size + j - r) % n] as usize);
+            (0..size)
+                .filter(|&j| next(w) != next(w ^ 1 << (size - 1 - j)))
+                .map(|j| defects[(i + n * size + j - r) % n])
+                .sum()
+        })
+        .collect()
+}
+
+/// Least-squares slope of y on x
+fn slope(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let (mx, my) = (points.iter().map(|p| p.0).sum::<f64>() / n, points.iter().map(|p| p.1).sum::<f64>() / n);
+    let sxx: f64 = points.iter().map(|p| (p.0 - mx).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|p| (p.0 - mx) * (p.1 - my)).sum();
+    Some(sxy / sxx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_damage_spreading() {
+        let elementary = |rule: u128| TableRule::new(1, rule);
+        // Linear rules' defects multiply by their number of taps, from any row
+        let rule90 = damage_spreading(elementary(90), 101, 60, 4, 0);
+        assert!((rule90.lyapunov.unwrap() - 2f64.ln()).abs() < 1e-9);
+        assert!((damage_spreading(elementary(150), 101, 60, 4, 0).lyapunov.unwrap() - 3f64.ln()).abs() < 1e-9);
+        // Rule 90's damage is the Sierpinski triangle: 2^(ones in t) cells
+        // until it wraps (t < 50)
+        assert_eq!(rule90.distance[..8], [1.0, 2.0, 2.0, 4.0, 2.0, 4.0, 4.0, 8.0]);
+        assert!(rule90.growth.unwrap() > 0.0);
+        // A shift carries the one defect along; the identity keeps it
+        for rule in [170, 204] {
+            let shift = damage_spreading(elementary(rule), 50, 40, 3, 1);
+            assert_eq!((shift.lyapunov, shift.distance[40], shift.growth), (Some(0.0), 1.0, Some(0.0)));
+        }
+        // Rule 0 forgets the flip at once, and every defect with it
+        let zero = damage_spreading(elementary(0), 50, 10, 3, 1);
+        assert_eq!((zero.healed, zero.extinct, zero.lyapunov), (3, 3, None));
+        assert_eq!(zero.distance[1..], [0.0; 10]);
+        // Rule 30 spreads over about half the ring; rule 4's damage stays put
+        let rule30 = damage_spreading(elementary(30), 101, 300, 8, 2);
+        assert!(rule30.lyapunov.unwrap() > 0.3 && (rule30.late_fraction() - 0.5).abs() < 0.1);
+        assert!(damage_spreading(elementary(4), 101, 300, 8, 2).late_fraction() < 0.02);
+
+        // The pattern starts at the flipped cell and stays in its light
+        // cone, whose right edge rule 30 damages every step (its new cell is
+        // the left neighbor XORed with the rest)
+        let pattern = difference_pattern(&Automaton::random(101, 30, 0.5, 5), 50, 40);
+        assert_eq!(pattern[0], (0..101).map(|i| i == 50).collect::<Vec<_>>());
+        assert!(pattern.iter().enumerate().all(|(t, row)| (0..101).all(|i| !row[i] || i.abs_diff(50) <= t)));
+        assert!(pattern.iter().enumerate().all(|(t, row)| row[50 + t]));
+        // Rule 90's is Pascal's triangle mod 2 whatever the row
+        let pattern = difference_pattern(&Automaton::random(101, 90, 0.5, 5), 50, 40);
+        assert_eq!(pattern[3].iter().filter(|&&x| x).count(), 4);
+        // The identity never spreads it: one damaged cell, where it was flipped
+        let pattern = difference_pattern(&Automaton::random(101, 204, 0.5, 5), 17, 40);
+        assert!(pattern.iter().all(|row| row.iter().enumerate().all(|(i, &x)| x == (i == 17))));
+    }
+}
@@ -0,0 +1,393 @@
+//! A run put into words, for screen readers and for alt text in reports
+//!
+//! Nothing here is canned per rule: each line reports a measurement the rest
+//! of the crate already makes. The pattern is the bestiary's class, from the
+//! run's own recurrence and compressibility; particles are the patches an
+//! `ether` leaves uncovered; an event is where `window` finds the rows'
+//! texture shifting most. Symmetry, spread and density come straight from
+//! the rows, measured (as images and densities are) against the background.
+
+use crate::bestiary::{canonical_rotation, examine, Class};
+use crate::ether::{detect_ether, EtherSettings};
+use crate::evolve::TableRule;
+use crate::linear::Linear;
+use crate::window::{find_event, pair_entropy};
+use crate::wolfram::WolframRule;
+use crate::{series_period, Automaton};
+
+/// Change in mean density, start to end, below which it counts as steady
+pub const STEADY_DENSITY: f64 = 0.05;
+
+/// Everything a description says about one run
+#[derive(Clone, Debug)]
+pub struct Description {
+    pub rule: u8,
+    pub width: usize,
+    pub generations: usize,
+    pub class: Class,
+    /// Steps before a row came back (up to rotation) and its period
+    pub cycle: Option<(usize, usize)>,
+    /// The bestiary's compression ratio of the run's second half
+    pub ratio: f64,
+    /// The rule's left-right reflection: itself when it treats both sides alike
+    pub mirror_rule: u8,
+    /// Fraction of the rows that aren't one color which read the same
+    /// reversed, about some axis; None when every row is one color
+    pub mirrored_rows: Option<f64>,
+    pub spread: Spread,
+    pub density: Trend,
+    pub structures: Option<Structures>,
+    pub event: Option<Event>,
+}
+
+/// How the region that differs from the background changes in extent: the
+/// shortest arc of the ring holding every such cell
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Spread {
+    /// Nothing differs from the background from generation `at` on
+    Vanishes { at: usize },
+    /// The region first spans the ring at generation `at`, its left and
+    /// right edges having moved out `edges` cells per step
+    Fills { at: usize, edges: [f64; 2] },
+    /// Still widening at the end, its edges moving out `edges` cells per step
+    Grows { edges: [f64; 2] },
+    /// Never wider than `extent` cells
+    Confined { extent: usize },
+}
+
+/// Mean density over the first and last tenth of the run, and its period
+/// over the second half if it oscillates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Trend {
+    pub start: f64,
+    pub end: f64,
+    pub period: Option<usize>,
+}
+
+/// The background the rule's random rows settle into, and the particles
+/// (runs of cells it doesn't cover) in this run's second half
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Structures {
+    /// The ether's spatial and temporal period
+    pub ether: (usize, usize),
+    /// Its one state, when it is a still homogeneous vacuum
+    pub vacuum: Option<bool>,
+    /// Particles at generation `from` and in the last row
+    pub particles: (usize, usize),
+    pub from: usize,
+}
+
+/// The generation where the texture (adjacent-pair entropy) shifts most,
+/// and its mean either side
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Event {
+    pub at: usize,
+    pub before: f64,
+    pub after: f64,
+}
+
+/// Run the elementary rule of `ca` for `generations` steps and measure what
+/// a description says
+pub fn describe(ca: &Automaton, generations: usize) -> Description {
+    assert!(ca.table().is_none(), "descriptions are of elementary rules");
+    let (rule, width, background) = (ca.rule(), ca.width(), ca.background());
+    let initial = ca.cells().iter().map(|&c| c as u8).collect();
+    let specimen = examine(WolframRule::general(2, 1, &rule.to_string()).unwrap(), initial, generations);
+    let rows = &specimen.rows;
+    let deviations: Vec<Vec<bool>> =
+        rows.iter().map(|row| row.iter().map(|&c| (c == 1) != background).collect()).collect();
+
+    let varied: Vec<&Vec<u8>> = rows.iter().filter(|row| row.iter().any(|&c| c != row[0])).collect();
+    let mirrored = varied.iter().filter(|row| {
+        let reversed: Vec<u8> = row.iter().rev().copied().collect();
+        canonical_rotation(row) == canonical_rotation(&reversed)
+    });
+    let mirrored_rows = (!varied.is_empty()).then(|| mirrored.count() as f64 / varied.len() as f64);
+
+    let densities: Vec<f64> =
+        deviations.iter().map(|row| row.iter().filter(|&&c| c).count() as f64 / width as f64).collect();
+    let tenth = densities.len().div_ceil(10);
+    let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len() as f64;
+    let mut period = series_period(&densities[densities.len() / 2..], 16, 1e-9);
+    if period == Some(1) {
+        period = None;
+    }
+    let density = Trend { start: mean(&densities[..tenth]), end: mean(&densities[densities.len() - tenth..]), period };
+
+    let entropies: Vec<f64> = rows.iter().map(|row| pair_entropy(row, 2)).collect();
+    let span = (rows.len() / 8).max(2);
+    let event = find_event(&entropies, span).map(|at| Event {
+        at,
+        before: mean(&entropies[at - span..at]),
+        after: mean(&entropies[at..at + span]),
+    });
+
+    Description {
+        rule,
+        width,
+        generations,
+        class: specimen.class,
+        cycle: specimen.cycle,
+        ratio: specimen.ratio,
+        mirror_rule: TableRule::new(1, rule as u128).mirror().table as u8,
+        mirrored_rows,
+        spread: spread(&deviations),
+        density,
+        structures: structures(rule, rows),
+        event,
+    }
+}
+
+/// The shortest arc of the ring holding every true cell, as its first cell
+/// and length; None when no cell is
+fn arc(row: &[bool]) -> Option<(usize, usize)> {
+    if !row.contains(&true) {
+        return None;
+    }
+    // The longest run of false cells, going round the ring, ends where the arc starts
+    let (mut longest, mut run, mut start) = (0, 0, 0);
+    for (i, &cell) in row.iter().chain(row).enumerate() {
+        run = if cell { 0 } else { run + 1 };
+        if run > longest {
+            (longest, start) = (run, (i + 1) % row.len());
+        }
+    }
+    Some((start, row.len() - longest.min(row.len())))
+}
+
+/// The region is followed from its first arc: a radius-1 rule moves its
+/// edges a cell a step at most, so each row's region runs from its first to
+/// its last deviating cell within a cell of the last one's edges. However
+/// sparse the row (rule 90's triangles), its extent is that of the light
+/// cone, and it fills the ring when the edges meet round the back, or when
+/// cells deviate beyond them (a background the rule doesn't keep).
+fn spread(deviations: &[Vec<bool>]) -> Spread {
+    let width = deviations[0].len();
+    let last = deviations.len() - 1;
+    if let Some(t) = deviations.iter().rposition(|row| row.contains(&true)).filter(|&t| t < last) {
+        return Spread::Vanishes { at: t + 1 };
+    }
+    let Some((start, first)) = arc(&deviations[0]) else { return Spread::Vanishes { at: 0 } };
+    // A random row has gaps, but no edges to speak of
+    if 2 * first >= width {
+        return Spread::Fills { at: 0, edges: [0.0; 2] };
+    }
+    let mut edges = vec![(start as isize, (start + first) as isize - 1)];
+    let moved = |from: usize, to: usize, edges: &[(isize, isize)]| {
+        let steps = (to - from).max(1) as f64;
+        [(edges[from].0 - edges[to].0) as f64 / steps, (edges[to].1 - edges[from].1) as f64 / steps]
+    };
+    for (t, row) in deviations.iter().enumerate().skip(1) {
+        let (lo, hi) = edges[t - 1];
+        let deviates = |x: &isize| row[x.rem_euclid(width as isize) as usize];
+        let reach = lo - 1..=hi + 1;
+        let inside = reach.clone().filter(deviates).count();
+        if hi - lo + 3 >= width as isize || inside < row.iter().filter(|&&c| c).count() {
+            return Spread::Fills { at: t, edges: moved(0, t - 1, &edges) };
+        }
+        match (reach.clone().find(deviates), reach.rev().find(deviates)) {
+            (Some(a), Some(b)) => edges.push((a, b)),
+            _ => edges.push((lo, hi)),
+        }
+    }
+    // Widening by at least a cell every ten steps, and at the end too
+    let [left, right] = moved(0, last, &edges);
+    let [late_left, late_right] = moved(last / 2, last, &edges);
+    if left + right >= 0.1 && late_left + late_right >= 0.1 {
+        Spread::Grows { edges: [left, right] }
+    } else {
+        Spread::Confined { extent: edges.iter().map(|(lo, hi)| (hi - lo + 1) as usize).max().unwrap() }
+    }
+}
+
+/// The rule's ether, if random rows settle into one and the ring is wide
+/// enough to match it, and the particles counted against it
+fn structures(rule: u8, rows: &[Vec<u8>]) -> Option<Structures> {
+    let settings = EtherSettings::default();
+    if rows[0].len() < settings.window {
+        return None;
+    }
+    let ether = detect_ether(rule, &settings)?;
+    let particles = |row: &Vec<u8>| {
+        let cells: Vec<bool> = row.iter().map(|&c| c == 1).collect();
+        let covered = ether.mask(&cells);
+        // Runs of uncovered cells: each starts after a covered one, or is
+        // the whole ring
+        let starts = (0..covered.len()).filter(|&i| !covered[i] && covered[(i + covered.len() - 1) % covered.len()]);
+        match starts.count() {
+            0 if !covered.contains(&true) => 1,
+            n => n,
+        }
+    };
+    let from = rows.len() / 2;
+    Some(Structures {
+        ether: (ether.spatial_period(), ether.temporal_period()),
+        vacuum: ether.homogeneous(),
+        particles: (particles(&rows[from]), particles(&rows[rows.len() - 1])),
+        from,
+    })
+}
+
+impl Description {
+    /// One labeled sentence per aspect, in reading order
+    pub fn lines(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Pattern", self.pattern()),
+            ("Symmetry", self.symmetry()),
+            ("Spread", self.spreading()),
+            ("Structures", self.structure()),
+            ("Events", self.events()),
+            ("Density", self.trend()),
+        ]
+    }
+
+    /// The lines as one paragraph, after `subject` (say, "Rule 30 from one
+    /// live cell"): alt text for an image of the run
+    pub fn alt_text(&self, subject: &str) -> String {
+        let mut text = format!("{subject}, {} cells wide over {} generations.", self.width, self.generations);
+        for (_, line) in self.lines() {
+            let mut chars = line.chars();
+            let first = chars.next().map(|c| c.to_uppercase().collect::<String>()).unwrap_or_default();
+            text += &format!(" {first}{}.", chars.as_str());
+        }
+        text
+    }
+
+    fn pattern(&self) -> String {
+        let detail = match (self.class, self.cycle) {
+            (Class::Uniform, _) => "every cell ends the same".to_string(),
+            (Class::Periodic, Some((0, 1))) => "every row repeats the first, allowing for shifts".to_string(),
+            (Class::Periodic, Some((0, period))) => {
+                format!("the rows repeat every {period} generations, allowing for shifts")
+            }
+            (Class::Periodic, Some((transient, period))) => {
+                format!("after {} the rows repeat every {period}, allowing for shifts", plural(transient, "generation"))
+            }
+            (Class::Chaotic, _) => {
+                format!("no row recurs, and the second half compresses barely better than noise ({:.2})", self.ratio)
+            }
+            _ => format!("no row recurs, yet the diagram is structured (compression {:.2})", self.ratio),
+        };
+        format!("{} (Wolfram class {}): {detail}", self.class, self.class.number())
+    }
+
+    fn symmetry(&self) -> String {
+        let rows = match self.mirrored_rows {
+            None => "no row has a pattern to reflect".to_string(),
+            Some(1.0) => "every row is mirror-symmetric".to_string(),
+            Some(0.0) => "no row is mirror-symmetric".to_string(),
+            Some(f) => format!("{:.0}% of rows are mirror-symmetric", 100.0 * f),
+        };
+        let sides = if self.mirror_rule == self.rule {
+            format!("{rows}; the rule treats left and right alike")
+        } else {
+            format!("{rows}; the rule's mirror image is rule {}", self.mirror_rule)
+        };
+        match Linear::elementary(self.rule) {
+            Ok(linear) if !linear.taps.is_empty() => format!("{sides}; it is {linear} over GF(2), so runs superpose"),
+            _ => sides,
+        }
+    }
+
+    fn spreading(&self) -> String {
+        match self.spread {
+            Spread::Vanishes { at: 0 } => "nothing differs from the background".to_string(),
+            Spread::Vanishes { at } => format!("the pattern dies out at generation {at}, leaving only background"),
+            Spread::Fills { at: 0, .. } => "the pattern is spread around the ring from the start".to_string(),
+            Spread::Fills { at, edges } => {
+                format!("the pattern widens {}, spanning the ring by generation {at}", widening(edges))
+            }
+            Spread::Grows { edges } => format!("the pattern is still widening at the end, {}", widening(edges)),
+            Spread::Confined { extent } => format!("the pattern stays within {}", plural(extent, "cell")),
+        }
+    }
+
+    fn structure(&self) -> String {
+        let Some(s) = self.structures else {
+            return "no periodic background (ether) found, so no particles stand out".to_string();
+        };
+        let background = match s.vacuum {
+            Some(live) => format!("an all-{} background", if live { "live" } else { "dead" }),
+            None => {
+                format!("a background repeating every {} and {}", plural(s.ether.0, "cell"), plural(s.ether.1, "step"))
+            }
+        };
+        let count = |n: usize| plural(n, "particle");
+        match s.particles {
+            (0, 0) => format!("{background} covers the second half"),
+            (a, b) => format!("{background}, with {} at generation {} and {} at the end", count(a), s.from, count(b)),
+        }
+    }
+
+    fn events(&self) -> String {
+        match self.event {
+            None => "no abrupt change in texture".to_string(),
+            Some(Event { at, before, after }) => format!(
+                "the texture changes abruptly at generation {at}, pair entropy {} from {before:.2} to {after:.2}",
+                if after > before { "rising" } else { "falling" }
+            ),
+        }
+    }
+
+    fn trend(&self) -> String {
+        let Trend { start, end, period } = self.density;
+        let trend = if (end - start).abs() < STEADY_DENSITY {
+            format!("the density holds near {end:.2}")
+        } else {
+            format!("the density {} from {start:.2} to {end:.2}", if end > start { "rises" } else { "falls" })
+        };
+        match period {
+            Some(p) => format!("{trend}, oscillating with period {p}"),
+            None => trend,
+        }
+    }
+}
+
+/// "1 cell", "2 cells"
+fn plural(n: usize, noun: &str) -> String {
+    if n == 1 { format!("1 {noun}") } else { format!("{n} {noun}s") }
+}
+
+/// How fast the edges of the pattern move out, both at once if they match
+fn widening([left, right]: [f64; 2]) -> String {
+    if (left - right).abs() < 0.005 {
+        format!("{left:.2} cells per generation on each side")
+    } else {
+        format!("{left:.2} cells per generation to the left and {right:.2} to the right")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_descriptions_measure_the_run() {
+        let rule30 = describe(&Automaton::new(101, 30), 100);
+        assert_eq!((rule30.class, rule30.mirror_rule), (Class::Chaotic, 86));
+        assert_eq!(rule30.spread, Spread::Fills { at: 50, edges: [1.0, 1.0] });
+        assert!(rule30.density.end > 0.4);
+
+        // A single cell under rule 90 is Pascal's triangle mod 2: always symmetric
+        let rule90 = describe(&Automaton::new(101, 90), 40);
+        assert_eq!((rule90.mirrored_rows, rule90.mirror_rule), (Some(1.0), 90));
+        assert_eq!(rule90.spread, Spread::Grows { edges: [1.0, 1.0] });
+        // Rule 110 grows to the left only
+        let rule110 = describe(&Automaton::new(101, 110), 60);
+        assert!(matches!(rule110.spread, Spread::Grows { edges: [l, r] } if l > 0.9 && r == 0.0));
+
+        assert_eq!(describe(&Automaton::new(64, 0), 20).spread, Spread::Vanishes { at: 1 });
+        let still = describe(&Automaton::new(64, 4), 20);
+        assert_eq!((still.class, still.spread), (Class::Periodic, Spread::Confined { extent: 1 }));
+        assert!(still.alt_text("Rule 4").starts_with("Rule 4, 64 cells wide over 20 generations. Periodic"));
+
+        // Rule 110's ether, with particles left in it
+        let rule110 = describe(&Automaton::random(256, 110, 0.5, 1), 400);
+        let structures = rule110.structures.unwrap();
+        assert_eq!((structures.ether, structures.vacuum), ((14, 7), None));
+        assert!(structures.particles.1 > 0);
+
+        assert_eq!(arc(&[false, true, false, false, true]), Some((4, 3)));
+        assert_eq!(arc(&[false, false, true, true, false]), Some((2, 2)));
+    }
+}
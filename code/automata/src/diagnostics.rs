@@ -0,0 +1,127 @@
+//! Warnings about the numbers an analysis produced, kept apart from them
+//!
+//! An analysis that has reason to doubt a result says so with `warn`
+//! rather than printing: an entropy read off too few blocks, a run that
+//! never settled, a class decided by a hair, a period the ring's width set.
+//! Warnings with the same message are counted rather than repeated, so a
+//! survey over 256 rules reports "no cycle within 1000 steps" once, with
+//! how many runs it held for. The CLI prints them on stderr when the
+//! command finishes, as text or JSON lines, leaving stdout to the results.
+
+use std::fmt;
+use std::sync::Mutex;
+
+/// The warnings, and how many new ones came once there were `MAX_WARNINGS`
+static WARNINGS: Mutex<(Vec<Warning>, usize)> = Mutex::new((Vec::new(), 0));
+
+/// Distinct warnings kept
+const MAX_WARNINGS: usize = 256;
+
+/// How near a threshold, as a fraction of it, a value makes a close call
+pub const NEAR_THRESHOLD: f64 = 0.1;
+
+/// Fewest samples per possible block for an entropy estimate to be trusted
+pub const SAMPLES_PER_BLOCK: f64 = 4.0;
+
+/// What kind of doubt a warning raises
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// An entropy from fewer samples than it has possible blocks to fill:
+    /// biased low, and capped at log2 of the samples
+    Undersampled,
+    /// A run that hadn't reached its cycle or died when it was stopped
+    Unconverged,
+    /// A classification from a value close to the threshold it was
+    /// compared with
+    NearThreshold,
+    /// A result the ring's width decides, which another width would change
+    WidthArtifact,
+}
+
+impl Kind {
+    /// The code warnings of this kind are reported under
+    pub fn code(self) -> &'static str {
+        match self {
+            Kind::Undersampled => "undersampled",
+            Kind::Unconverged => "unconverged",
+            Kind::NearThreshold => "near-threshold",
+            Kind::WidthArtifact => "width-artifact",
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.code())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    pub kind: Kind,
+    pub message: String,
+    /// Times it was raised
+    pub count: usize,
+}
+
+/// Raise a warning, or count it again if one with this message was
+pub fn warn(kind: Kind, message: String) {
+    let (warnings, dropped) = &mut *WARNINGS.lock().unwrap();
+    if let Some(warning) = warnings.iter_mut().find(|w| w.kind == kind && w.message == message) {
+        warning.count += 1;
+    } else if warnings.len() < MAX_WARNINGS {
+        warnings.push(Warning { kind, message, count: 1 });
+    } else {
+        *dropped += 1;
+    }
+}
+
+/// The warnings so far, in the order first raised, and how many more were
+/// raised once the list was full
+pub fn warnings() -> (Vec<Warning>, usize) {
+    WARNINGS.lock().unwrap().clone()
+}
+
+/// Warn if `value` is within `NEAR_THRESHOLD` of `threshold`, which
+/// decided `what`; whether it was
+pub fn check_threshold(what: &str, value: f64, threshold: f64) -> bool {
+    let near = (value - threshold).abs() <= NEAR_THRESHOLD * threshold.abs();
+    if near {
+        let percent = 100.0 * NEAR_THRESHOLD;
+        warn(Kind::NearThreshold, format!("{what}: within {percent}% of the {threshold} threshold it was decided by"));
+    }
+    near
+}
+
+/// Warn if an entropy over `patterns` possible blocks was estimated from
+/// only `samples` of them (fewer than `SAMPLES_PER_BLOCK` each)
+pub fn check_sampling(what: &str, samples: usize, patterns: f64) {
+    if (samples as f64) < SAMPLES_PER_BLOCK * patterns {
+        let cap = (samples as f64).log2();
+        warn(
+            Kind::Undersampled,
+            format!("{what}: {samples} blocks sampled of {patterns} possible, so biased low and at most {cap:.2} bits"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warnings_are_counted() {
+        // Tests share the list, so this one looks only at its own
+        let ours = || warnings().0.into_iter().filter(|w| w.message.starts_with("test:")).collect::<Vec<_>>();
+        warn(Kind::Unconverged, "test: one".to_string());
+        warn(Kind::Unconverged, "test: one".to_string());
+        warn(Kind::WidthArtifact, "test: two".to_string());
+        let counts: Vec<(Kind, usize)> = ours().iter().map(|w| (w.kind, w.count)).collect();
+        assert_eq!(counts, [(Kind::Unconverged, 2), (Kind::WidthArtifact, 1)]);
+
+        assert!(check_threshold("test: class", 0.29, 0.3) && !check_threshold("test: class", 0.2, 0.3));
+        check_sampling("test: entropy", 100, 256.0);
+        check_sampling("test: entropy", 100, 8.0);
+        assert_eq!(ours().iter().filter(|w| w.kind == Kind::Undersampled).count(), 1);
+    }
+}
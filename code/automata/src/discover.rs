@@ -0,0 +1,380 @@
+//! Rules with a structural property, searched for in spaces too big to list
+//!
+//! A rule is number-conserving when every ring's sum of cell values is the
+//! same after a step as before (rule 184's traffic), monotone when raising
+//! any cell of a neighborhood never lowers the next state, and
+//! outer-totalistic when the next state depends only on the center and the
+//! sum of the rest. Radius-2 binary rules alone number 2^32, so the search
+//! doesn't test every table: it builds only tables with one property (the
+//! first of conserving, outer-totalistic and monotone asked for, which is
+//! roughly fewest first) and checks the others on each.
+//!
+//! Number conservation has an exact local form (Hattori and Takesue): f is
+//! conserving exactly when there is a flux J over 2r-cell blocks with
+//! f(x_1..x_n) = x_c + J(x_1..x_n-1) - J(x_2..x_n) for every window, so a
+//! ring's sum telescopes. Fixing J(0..0) = 0, the windows starting with 0
+//! determine J, and J determines every other window, so the search assigns
+//! those in order and drops a branch as soon as a forced entry falls outside
+//! 0..k. Monotone tables are built entry by entry, each at least every entry
+//! one cell lower; outer-totalistic ones are the family's codes in turn.
+//!
+//! Each rule found comes with a certificate per property, checked against
+//! its table on every window: the flux, the number of one-cell raises
+//! compared, or the outer-totalistic code that lists the same table.
+
+use crate::wolfram::WolframRule;
+use std::fmt;
+use std::ops::ControlFlow;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Property {
+    Conserving,
+    Monotone,
+    OuterTotalistic,
+}
+
+impl fmt::Display for Property {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Property::Conserving => "number-conserving",
+            Property::Monotone => "monotone",
+            Property::OuterTotalistic => "outer-totalistic",
+        })
+    }
+}
+
+/// Why a rule has a property, in a form its table can be checked against
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Certificate {
+    /// J over the k^(2r) blocks of 2r cells, indexed as windows are
+    Conserving { flux: Vec<i64> },
+    /// Neighborhood pairs one cell apart, all in order
+    Monotone { pairs: usize },
+    /// The outer-totalistic rule with the same table
+    OuterTotalistic { rule: WolframRule },
+}
+
+impl Certificate {
+    /// The certificate for `property`, or why the rule hasn't it; `rule`
+    /// must be general
+    pub fn derive(rule: &WolframRule, property: Property) -> Result<Certificate, String> {
+        let windows = Windows::new(rule);
+        let certificate = match property {
+            Property::Conserving => {
+                if rule.table[0] != 0 {
+                    return Err("the all-0 neighborhood doesn't map to 0".to_string());
+                }
+                let mut flux = vec![0i64; windows.blocks];
+                for y in 1..windows.blocks {
+                    flux[y] = flux[y / windows.k] + windows.center(y) as i64 - rule.table[y] as i64;
+                }
+                Certificate::Conserving { flux }
+            }
+            Property::Monotone => Certificate::Monotone { pairs: windows.raises().count() },
+            Property::OuterTotalistic => {
+                let mut code = WolframRule::outer_totalistic(rule.colors, rule.radius, "0")?;
+                let mut set = vec![false; code.table.len()];
+                for w in 0..rule.table.len() {
+                    let index = windows.outer(w);
+                    if set[index] && code.table[index] != rule.table[w] {
+                        return Err(format!("{} and another with its center and sum differ", windows.show(w)));
+                    }
+                    (code.table[index], set[index]) = (rule.table[w], true);
+                }
+                Certificate::OuterTotalistic { rule: code }
+            }
+        };
+        certificate.check(rule).map(|()| certificate)
+    }
+
+    /// Checks the certificate against every window of `rule`'s table
+    pub fn check(&self, rule: &WolframRule) -> Result<(), String> {
+        let windows = Windows::new(rule);
+        let table = &rule.table;
+        match self {
+            Certificate::Conserving { flux } => {
+                let m = windows.blocks;
+                let broken = (0..table.len())
+                    .find(|&w| table[w] as i64 != windows.center(w) as i64 + flux[w / windows.k] - flux[w % m]);
+                match broken {
+                    Some(w) => Err(format!("no flux: {} doesn't balance", windows.show(w))),
+                    None => Ok(()),
+                }
+            }
+            Certificate::Monotone { pairs } => {
+                if let Some((low, high)) = windows.raises().find(|&(low, high)| table[low] > table[high]) {
+                    return Err(format!("{} maps above {}", windows.show(low), windows.show(high)));
+                }
+                match windows.raises().count() == *pairs {
+                    true => Ok(()),
+                    false => Err(format!("{pairs} pairs, not {}", windows.raises().count())),
+                }
+            }
+            Certificate::OuterTotalistic { rule: code } => match code.to_general() {
+                Some(general) if general.table == *table => Ok(()),
+                _ => Err(format!("{code} is another table")),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Certificate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Certificate::Conserving { flux } => {
+                let flux: Vec<String> = flux.iter().map(|j| j.to_string()).collect();
+                write!(f, "flux {}", flux.join(","))
+            }
+            Certificate::Monotone { pairs } => write!(f, "{pairs} one-cell raises in order"),
+            Certificate::OuterTotalistic { rule } => match rule.life_notation() {
+                Some(notation) => write!(f, "{rule} ({notation})"),
+                None => write!(f, "{rule}"),
+            },
+        }
+    }
+}
+
+/// A rule found, with a certificate for each property asked for
+#[derive(Clone, Debug)]
+pub struct Found {
+    pub rule: WolframRule,
+    pub certificates: Vec<(Property, Certificate)>,
+}
+
+/// What a search found and how far it got
+#[derive(Clone, Debug)]
+pub struct Search {
+    pub found: Vec<Found>,
+    /// The property whose tables were built; the rest were checked
+    pub built: Property,
+    /// Search nodes: table entries assigned, or outer-totalistic codes tried
+    pub nodes: u64,
+    /// Every table with the property was built: `found` is all of them
+    pub complete: bool,
+}
+
+/// Up to `limit` general rules with k colors and radius r having every one
+/// of `properties` (0 for no limit), building at most `budget` nodes
+pub fn search(colors: u8, radius: usize, properties: &[Property], limit: usize, budget: u64) -> Result<Search, String> {
+    let zero = WolframRule::general(colors, radius, "0")?;
+    let built = [Property::Conserving, Property::OuterTotalistic, Property::Monotone]
+        .into_iter()
+        .find(|p| properties.contains(p))
+        .ok_or("no property to search for")?;
+    let mut search = Search { found: Vec::new(), built, nodes: 0, complete: false };
+    let mut visit = |table: &[u8]| {
+        let rule = WolframRule { table: table.to_vec(), ..zero.clone() };
+        let certificates: Result<Vec<_>, _> =
+            properties.iter().map(|&p| Certificate::derive(&rule, p).map(|c| (p, c))).collect();
+        if let Ok(certificates) = certificates {
+            search.found.push(Found { rule, certificates });
+        }
+        match limit > 0 && search.found.len() >= limit {
+            true => ControlFlow::Break(()),
+            false => ControlFlow::Continue(()),
+        }
+    };
+    let mut builder = Builder { windows: Windows::new(&zero), table: zero.table.clone(), nodes: 0, budget };
+    let flow = match built {
+        Property::Conserving => builder.conserving(&mut visit),
+        Property::Monotone => builder.monotone(0, &mut visit),
+        Property::OuterTotalistic => builder.outer_totalistic(&mut visit),
+    };
+    search.nodes = builder.nodes;
+    search.complete = flow.is_continue();
+    Ok(search)
+}
+
+/// The windows of a general table: n cells, k colors, leftmost most
+/// significant
+struct Windows {
+    k: usize,
+    n: usize,
+    radius: usize,
+    /// Blocks of n - 1 cells: k^(n-1)
+    blocks: usize,
+}
+
+impl Windows {
+    fn new(rule: &WolframRule) -> Self {
+        let (k, n) = (rule.colors as usize, 2 * rule.radius + 1);
+        Self { k, n, radius: rule.radius, blocks: k.pow(n as u32 - 1) }
+    }
+
+    fn cell(&self, w: usize, j: usize) -> usize {
+        w / self.k.pow((self.n - 1 - j) as u32) % self.k
+    }
+
+    fn center(&self, w: usize) -> usize {
+        self.cell(w, self.radius)
+    }
+
+    /// The outer-totalistic index, k * (sum of the others) + center
+    fn outer(&self, w: usize) -> usize {
+        let sum: usize = (0..self.n).map(|j| self.cell(w, j)).sum();
+        self.k * (sum - self.center(w)) + self.center(w)
+    }
+
+    /// Every window with one cell raised by one: (lower, higher)
+    fn raises(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.k * self.blocks).flat_map(move |w| {
+            let raise = move |j: usize| (w, w + self.k.pow((self.n - 1 - j) as u32));
+            (0..self.n).filter(move |&j| self.cell(w, j) + 1 < self.k).map(raise)
+        })
+    }
+
+    fn show(&self, w: usize) -> String {
+        (0..self.n).map(|j| char::from_digit(self.cell(w, j) as u32, 10).unwrap()).collect()
+    }
+}
+
+/// Builds tables with one property, calling `visit` with each; a break
+/// from it, or the budget running out, stops the build
+struct Builder {
+    windows: Windows,
+    table: Vec<u8>,
+    nodes: u64,
+    budget: u64,
+}
+
+type Visit<'a> = dyn FnMut(&[u8]) -> ControlFlow<()> + 'a;
+
+impl Builder {
+    fn node(&mut self) -> ControlFlow<()> {
+        self.nodes += 1;
+        match self.nodes > self.budget {
+            true => ControlFlow::Break(()),
+            false => ControlFlow::Continue(()),
+        }
+    }
+
+    fn conserving(&mut self, visit: &mut Visit) -> ControlFlow<()> {
+        let (k, m) = (self.windows.k, self.windows.blocks);
+        // A window starting with a nonzero cell is forced once both its
+        // blocks' fluxes are known
+        let mut ready = vec![Vec::new(); m];
+        for w in m..k * m {
+            ready[(w / k).max(w % m)].push(w);
+        }
+        let mut flux = vec![0i64; m];
+        self.assign_flux(0, &ready, &mut flux, visit)
+    }
+
+    fn assign_flux(&mut self, y: usize, ready: &[Vec<usize>], flux: &mut [i64], visit: &mut Visit) -> ControlFlow<()> {
+        let (k, m) = (self.windows.k, self.windows.blocks);
+        if y == m {
+            return visit(&self.table);
+        }
+        let values = if y == 0 { 0..1 } else { 0..k };
+        'value: for v in values {
+            self.node()?;
+            self.table[y] = v as u8;
+            flux[y] = if y == 0 { 0 } else { flux[y / k] + self.windows.center(y) as i64 - v as i64 };
+            for &w in &ready[y] {
+                let forced = self.windows.center(w) as i64 + flux[w / k] - flux[w % m];
+                if !(0..k as i64).contains(&forced) {
+                    continue 'value;
+                }
+                self.table[w] = forced as u8;
+            }
+            self.assign_flux(y + 1, ready, flux, visit)?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn monotone(&mut self, w: usize, visit: &mut Visit) -> ControlFlow<()> {
+        let (k, n) = (self.windows.k, self.windows.n);
+        if w == self.table.len() {
+            return visit(&self.table);
+        }
+        // Every window one cell lower comes earlier
+        let lowest = (0..n)
+            .filter(|&j| self.windows.cell(w, n - 1 - j) > 0)
+            .map(|j| self.table[w - k.pow(j as u32)])
+            .max()
+            .unwrap_or(0);
+        for v in lowest..k as u8 {
+            self.node()?;
+            self.table[w] = v;
+            self.monotone(w + 1, visit)?;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn outer_totalistic(&mut self, visit: &mut Visit) -> ControlFlow<()> {
+        let colors = self.windows.k as u8;
+        let mut code = WolframRule::outer_totalistic(colors, self.windows.radius, "0").expect("the space is checked");
+        loop {
+            self.node()?;
+            self.table = code.to_general().expect("a general table fits").table;
+            visit(&self.table)?;
+            // The next code: add one, least significant digit first
+            let Some(digit) = code.table.iter().position(|&d| d + 1 < colors) else { break };
+            code.table[..digit].fill(0);
+            code.table[digit] += 1;
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Automaton;
+
+    fn elementary(search: &Search) -> Vec<u8> {
+        let mut rules: Vec<u8> = search.found.iter().map(|found| found.rule.elementary().unwrap()).collect();
+        rules.sort_unstable();
+        rules
+    }
+
+    #[test]
+    fn test_discover() {
+        // The five conserving ECAs: identity, shifts and the traffic rules,
+        // which are exactly the ones conserving on every small ring
+        let conserving = search(2, 1, &[Property::Conserving], 0, 1 << 20).unwrap();
+        assert!(conserving.complete);
+        assert_eq!(elementary(&conserving), [170, 184, 204, 226, 240]);
+        let simulated: Vec<u8> = (0..=255u8)
+            .filter(|&rule| {
+                (1..=8).all(|width| {
+                    (0..1u64 << width).all(|row| {
+                        let mut ca = Automaton::from_cells((0..width).map(|i| row >> i & 1 == 1).collect(), rule);
+                        let before = ca.population();
+                        ca.step();
+                        ca.population() == before
+                    })
+                })
+            })
+            .collect();
+        assert_eq!(elementary(&conserving), simulated);
+
+        // Dedekind's 20 monotone functions of three variables
+        assert_eq!(search(2, 1, &[Property::Monotone], 0, 1 << 20).unwrap().found.len(), 20);
+        // Traffic isn't: a car at 100 moves on, but not with one ahead at 110
+        let both = search(2, 1, &[Property::Monotone, Property::Conserving], 0, 1 << 20).unwrap();
+        assert_eq!(elementary(&both), [170, 204, 240]);
+
+        // Outer-totalistic rules come back with their code
+        let found = search(2, 1, &[Property::OuterTotalistic, Property::Conserving], 0, 1 << 20).unwrap();
+        assert_eq!(elementary(&found), [204]);
+        let Certificate::OuterTotalistic { rule } = &found.found[0].certificates[0].1 else { panic!() };
+        assert_eq!(rule.life_notation().as_deref(), Some("B/S012"));
+
+        // A bigger space, and a certificate checked against another table
+        let radius_2 = search(2, 2, &[Property::Conserving], 0, 1 << 24).unwrap();
+        assert!(radius_2.complete);
+        assert_eq!(radius_2.found.len(), 428);
+        let three = search(3, 1, &[Property::Conserving], 0, 1 << 24).unwrap();
+        assert_eq!(three.found.len(), 144);
+        for found in three.found.iter().step_by(9) {
+            let row = crate::wolfram::random_row(3, 40, 7);
+            let sum = |row: &[u8]| row.iter().map(|&c| c as usize).sum::<usize>();
+            assert_eq!(sum(&found.rule.step(&row)), sum(&row));
+        }
+        let flux = &radius_2.found[1].certificates[0].1;
+        assert!(flux.check(&radius_2.found[2].rule).is_err());
+        let budget = search(2, 2, &[Property::Monotone], 0, 100).unwrap();
+        assert!(!budget.complete && budget.nodes == 101);
+    }
+}
@@ -0,0 +1,227 @@
+//! Backgrounds found by simulation: the vacuum or periodic ether that random
+//! rows settle into
+//!
+//! Many rules relax to a background that is periodic in space and time, with
+//! particles (gliders, defects) moving through it: all dead for most rules,
+//! all live for 255-like ones, and a tile 14 cells wide and 7 steps tall for
+//! rule 110.
+//!
+//! A background that repeats every p cells is a cycle of the p-cell ring, so
+//! the candidates are the short cycles of rings up to `max_period` cells,
+//! found exhaustively and told apart by `CycleFingerprint`. Each is scored
+//! by how much of a set of random runs (after a transient) it covers: a cell
+//! is covered when some `window`-cell stretch through it reads like a stretch
+//! of the candidate, in any phase. The best candidate is the ether if it
+//! covers at least `min_coverage` of the cells; the uncovered cells are the
+//! particles.
+
+use crate::basins::{enumerate_basins, CycleFingerprint, MAX_WIDTH};
+use crate::Automaton;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+/// A background periodic in space and time
+#[derive(Clone, Debug)]
+pub struct Ether {
+    /// One spatial period at each step of the cycle, in time order
+    pub rows: Vec<Vec<bool>>,
+    /// Cells per window matched against it
+    pub window: usize,
+    /// Fraction of cells it covered in the detection runs
+    pub coverage: f64,
+    /// Every `window`-cell stretch of the background, packed as in `Automaton::words`
+    windows: HashSet<u64>,
+}
+
+impl Ether {
+    /// The background repeating `rows` (all the same length) in time
+    pub fn new(rows: Vec<Vec<bool>>, window: usize) -> Self {
+        assert!((1..64).contains(&window), "windows are 1 to 63 cells");
+        assert!(!rows.is_empty() && rows.iter().all(|row| !row.is_empty() && row.len() == rows[0].len()));
+        let windows = rows
+            .iter()
+            .flat_map(|row| (0..row.len()).map(move |start| pack((0..window).map(|j| row[(start + j) % row.len()]))))
+            .collect();
+        Self { rows, window, coverage: 0.0, windows }
+    }
+
+    pub fn spatial_period(&self) -> usize {
+        self.rows[0].len()
+    }
+
+    pub fn temporal_period(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// The single cell state of a homogeneous, still background (a quiescent
+    /// vacuum), if that is what this is
+    pub fn homogeneous(&self) -> Option<bool> {
+        (self.rows.len() == 1 && self.rows[0].len() == 1).then_some(self.rows[0][0])
+    }
+
+    /// Whether each cell of the row lies in a stretch of ether; the rest are
+    /// particles. Rings narrower than the window have no stretches to match
+    pub fn mask(&self, cells: &[bool]) -> Vec<bool> {
+        let (n, w) = (cells.len(), self.window);
+        if n < w {
+            return vec![false; n];
+        }
+        // Windows starting at each cell, then each cell covered by one of the w through it
+        let mut window = pack((0..w).map(|j| cells[j]));
+        let mut starts = Vec::with_capacity(n);
+        for i in 0..n {
+            starts.push(self.windows.contains(&window));
+            window = (window >> 1) | (cells[(i + w) % n] as u64) << (w - 1);
+        }
+        let mut covering = (n - w + 1..n).filter(|&i| starts[i]).count();
+        (0..n)
+            .map(|x| {
+                covering += starts[x] as usize;
+                let covered = covering > 0;
+                covering -= starts[(x + n + 1 - w) % n] as usize;
+                covered
+            })
+            .collect()
+    }
+
+    /// Fraction of the row's cells outside the ether: the ether-relative
+    /// density of particles
+    pub fn defect_density(&self, cells: &[bool]) -> f64 {
+        self.mask(cells).iter().filter(|&&covered| !covered).count() as f64 / cells.len() as f64
+    }
+}
+
+/// How `detect_ether` searches
+#[derive(Clone, Copy, Debug)]
+pub struct EtherSettings {
+    /// Longest spatial and temporal period considered
+    pub max_period: usize,
+    /// Cells per window; longer windows make chance matches rarer
+    pub window: usize,
+    /// Random rows simulated, and their width
+    pub samples: usize,
+    pub width: usize,
+    /// Transient steps run before the covered cells are counted
+    pub skip: usize,
+    /// Generations counted after the transient
+    pub generations: usize,
+    pub seed: u64,
+    /// Least coverage for the best candidate to count as the ether
+    pub min_coverage: f64,
+}
+
+impl Default for EtherSettings {
+    fn default() -> Self {
+        Self { max_period: 14, window: 16, samples: 4, width: 256, skip: 200, generations: 32, seed: 0, min_coverage: 0.5 }
+    }
+}
+
+/// Every background candidate: the cycles of rings up to `max_period` cells
+/// with at most that period, each with its own smallest spatial period and
+/// listed once however rotated or entered, the simplest first
+pub fn ether_candidates(rule: u8, max_period: usize) -> Vec<Vec<Vec<bool>>> {
+    assert!((1..=MAX_WIDTH).contains(&max_period), "periods are 1 to {MAX_WIDTH}");
+    let mut candidates = Vec::new();
+    for width in 1..=max_period {
+        let mut seen = HashSet::new();
+        let rotate = |s: u32, d: usize| ((s >> d) | (s << (width - d))) & ((1u64 << width) - 1) as u32;
+        for attractor in enumerate_basins(rule, width).attractors {
+            let first = attractor.cycle[0];
+            let repeats = (1..width).any(|d| width % d == 0 && rotate(first, d) == first);
+            if attractor.period() > max_period || repeats || !seen.insert(CycleFingerprint::of_attractor(&attractor, width).hash) {
+                continue;
+            }
+            candidates.push(attractor.cycle.iter().map(|&s| (0..width).map(|i| s >> i & 1 == 1).collect()).collect());
+        }
+    }
+    candidates.sort_by_key(|rows: &Vec<Vec<bool>>| rows.len() * rows[0].len());
+    candidates
+}
+
+/// The background random rows of `rule` settle into, if one covers enough of them
+pub fn detect_ether(rule: u8, settings: &EtherSettings) -> Option<Ether> {
+    best_ether_candidate(rule, settings).filter(|ether| ether.coverage >= settings.min_coverage)
+}
+
+/// The candidate covering most of the random runs, however little that is
+pub fn best_ether_candidate(rule: u8, settings: &EtherSettings) -> Option<Ether> {
+    let EtherSettings { max_period, window, samples, width, skip, generations, seed, .. } = *settings;
+    assert!(window >= max_period && width >= window, "need max_period <= window <= width");
+    let candidates: Vec<Ether> =
+        ether_candidates(rule, max_period).into_iter().map(|rows| Ether::new(rows, window)).collect();
+
+    let runs: Vec<Vec<Vec<bool>>> = (0..samples as u64)
+        .into_par_iter()
+        .map(|i| {
+            let mut ca = Automaton::random(width, rule, 0.5, seed + i);
+            (0..skip).for_each(|_| ca.step());
+            (0..generations)
+                .map(|_| {
+                    ca.step();
+                    ca.cells()
+                })
+                .collect()
+        })
+        .collect();
+
+    // Rank by matching window starts (one lookup per window), then measure the winner's coverage
+    let mut owners: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, candidate) in candidates.iter().enumerate() {
+        candidate.windows.iter().for_each(|&w| owners.entry(w).or_default().push(i));
+    }
+    let mut hits = vec![0usize; candidates.len()];
+    for cells in runs.iter().flatten() {
+        let n = cells.len();
+        let mut w = pack((0..window).map(|j| cells[j]));
+        for i in 0..n {
+            owners.get(&w).into_iter().flatten().for_each(|&c| hits[c] += 1);
+            w = (w >> 1) | (cells[(i + window) % n] as u64) << (window - 1);
+        }
+    }
+    // Ties go to the simplest candidate, which comes first
+    let best = (0..candidates.len()).rev().max_by_key(|&i| hits[i])?;
+    let mut ether = candidates.into_iter().nth(best).unwrap();
+    let rows = runs.iter().flatten();
+    ether.coverage = rows.clone().map(|cells| 1.0 - ether.defect_density(cells)).sum::<f64>() / rows.count().max(1) as f64;
+    Some(ether)
+}
+
+/// Cells packed into an integer, the first in bit 0
+fn pack(cells: impl Iterator<Item = bool>) -> u64 {
+    cells.enumerate().fold(0, |word, (i, c)| word | (c as u64) << i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_marks_particles() {
+        let vacuum = Ether::new(vec![vec![false]], 4);
+        assert_eq!(vacuum.homogeneous(), Some(false));
+        let row: Vec<bool> = "..#.......##.#...".chars().map(|c| c == '#').collect();
+        let particles: Vec<bool> = vacuum.mask(&row).iter().map(|&covered| !covered).collect();
+        // Gaps narrower than the window are particles too
+        assert_eq!(particles, "..#.......####...".chars().map(|c| c == '#').collect::<Vec<_>>());
+        assert!((vacuum.defect_density(&row) - 5.0 / 17.0).abs() < 1e-12);
+        assert_eq!(vacuum.mask(&row[..3]), vec![false; 3]);
+    }
+
+    #[test]
+    fn test_detects_vacuum_and_ether() {
+        let settings = EtherSettings { samples: 2, ..EtherSettings::default() };
+        for (rule, live) in [(0, false), (255, true), (128, false)] {
+            let ether = detect_ether(rule, &settings).unwrap();
+            assert_eq!(ether.homogeneous(), Some(live), "rule {rule}");
+            assert!(ether.coverage > 0.99, "rule {rule}: {}", ether.coverage);
+        }
+
+        // Rule 110's ether: 14 cells by 7 steps
+        let ether = detect_ether(110, &settings).unwrap();
+        assert_eq!((ether.spatial_period(), ether.temporal_period()), (14, 7));
+
+        // Chaos and frozen noise have none
+        assert!(detect_ether(30, &settings).is_none());
+        assert!(detect_ether(204, &settings).is_none());
+    }
+}
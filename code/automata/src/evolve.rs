@@ -0,0 +1,877 @@
+//! Evolving larger-radius rules with a genetic algorithm
+//!
+//! A radius-r rule maps each (2r + 1)-cell window to the next state, so its
+//! chromosome is a 2^(2r+1)-bit lookup table: 8 bits for an ECA, 128 for the
+//! radius-3 rules of the density classification task (Mitchell, Crutchfield
+//! and Das). Window bits are read leftmost cell first, so a radius-1 table
+//! is exactly the ECA rule number.
+//!
+//! Fitness is the fraction of random initial conditions a rule classifies:
+//! it should settle to all 1s when most cells start live and all 0s
+//! otherwise. Candidates are evaluated in parallel, and the population can be
+//! split into islands that evolve separately and exchange their best rules.
+//!
+//! Mirroring a rule (reading windows right to left) or conjugating it
+//! (swapping 0s and 1s on input and output) gives an equivalent classifier,
+//! so each generation scores one representative per equivalence class on a
+//! test set closed under the same symmetries, and the rest are cache hits.
+//!
+//! The GA records each rule's parents, so the ancestry of any rule in the
+//! population can be traced back to the random first generation and drawn
+//! as a Graphviz genealogy with the table bits each step changed.
+//!
+//! A `Ga` can be saved after each generation is scored and loaded again,
+//! RNG state included, so an interrupted run continues exactly as if it had
+//! never stopped.
+
+use crate::profile;
+use crate::wolfram::WolframRule;
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+/// Largest radius whose lookup table fits in a `u128`
+pub const MAX_RADIUS: usize = 3;
+
+/// A lookup-table rule of radius 1..=3: bit w of `table` is the next state
+/// of a cell whose window, read leftmost first, is w
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TableRule {
+    pub radius: usize,
+    pub table: u128,
+}
+
+impl TableRule {
+    pub fn new(radius: usize, table: u128) -> Self {
+        assert!((1..=MAX_RADIUS).contains(&radius), "radius must be 1..={MAX_RADIUS}");
+        Self { radius, table: table & Self::table_mask(radius) }
+    }
+
+    /// A table drawn uniformly from all 2^(2^(2r+1)) of radius r (2^32 at
+    /// radius 2), reproducibly from a ChaCha8 stream seeded with `seed`
+    pub fn random(radius: usize, seed: u64) -> Self {
+        Self::new(radius, ChaCha8Rng::seed_from_u64(seed).random())
+    }
+
+    /// The homogeneous row the rule leaves unchanged, as `quiescent_state`
+    /// finds for ECAs: dead when the all-dead window maps to 0, else live
+    /// when the all-live one maps to 1
+    pub fn quiescent(&self) -> Option<bool> {
+        match (self.table & 1, (self.table >> (self.entries() - 1)) & 1) {
+            (0, _) => Some(false),
+            (_, 1) => Some(true),
+            _ => None,
+        }
+    }
+
+    /// Number of table entries (chromosome length in bits)
+    pub fn entries(&self) -> usize {
+        1 << (2 * self.radius + 1)
+    }
+
+    fn table_mask(radius: usize) -> u128 {
+        match 1u32 << (2 * radius + 1) {
+            128 => !0,
+            bits => (1u128 << bits) - 1,
+        }
+    }
+
+    /// Bit of a window for the cell at `offset` (-radius..=radius) from the center
+    fn window_bit(&self, window: usize, offset: isize) -> bool {
+        (window >> (self.radius as isize - offset)) & 1 == 1
+    }
+
+    /// Gacs-Kurdyumov-Levin: a dead cell takes the majority of itself and
+    /// the cells 1 and 3 to its left, a live one of itself and the cells 1
+    /// and 3 to its right. The classic hand-designed density classifier.
+    pub fn gkl() -> Self {
+        let rule = Self { radius: 3, table: 0 };
+        let table = (0..rule.entries()).fold(0u128, |t, w| {
+            let side = if rule.window_bit(w, 0) { 1 } else { -1 };
+            let votes = [0, side, 3 * side].iter().filter(|&&o| rule.window_bit(w, o)).count();
+            t | ((votes >= 2) as u128) << w
+        });
+        Self { table, ..rule }
+    }
+
+    /// Live when most of the 2r + 1 window is live
+    pub fn local_majority(radius: usize) -> Self {
+        let rule = Self::new(radius, 0);
+        let table = (0..rule.entries()).fold(0u128, |t, w| t | ((w.count_ones() as usize > radius) as u128) << w);
+        Self { table, ..rule }
+    }
+
+    /// The left-right reflection: window w maps where its reverse did
+    pub fn mirror(&self) -> Self {
+        let size = 2 * self.radius + 1;
+        let table = (0..self.entries()).fold(0u128, |t, w| {
+            let reversed = (w as u32).reverse_bits() >> (32 - size);
+            t | ((self.table >> reversed) & 1) << w
+        });
+        Self { table, ..*self }
+    }
+
+    /// The 0/1 conjugate: window w maps to the opposite of window !w
+    pub fn complement(&self) -> Self {
+        let last = self.entries() - 1;
+        let table = (0..self.entries()).fold(0u128, |t, w| t | (!(self.table >> (last - w)) & 1) << w);
+        Self { table, ..*self }
+    }
+
+    /// The rule's symmetry class, sorted and deduplicated: its mirror image
+    /// and, with `complement`, its conjugates too
+    pub fn equivalents(&self, complement: bool) -> Vec<Self> {
+        let mut rules = vec![*self, self.mirror()];
+        if complement {
+            rules.extend([self.complement(), self.mirror().complement()]);
+        }
+        rules.sort_unstable_by_key(|r| r.table);
+        rules.dedup();
+        rules
+    }
+
+    /// Smallest table in the symmetry class, the key for fitness caching
+    pub fn canonical(&self, complement: bool) -> Self {
+        self.equivalents(complement)[0]
+    }
+
+    /// One synchronous update of a ring
+    pub fn step(&self, cells: &[bool]) -> Vec<bool> {
+        let mut next = vec![false; cells.len()];
+        self.step_into(cells, &mut next);
+        next
+    }
+
+    /// `step` writing into an existing row, for the inner loop of fitness
+    /// evaluation
+    fn step_into(&self, cells: &[bool], next: &mut [bool]) {
+        let n = cells.len();
+        let size = 2 * self.radius + 1;
+        let mask = (1usize << size) - 1;
+        let wrap = |i: usize| if i < n { i } else { i % n };
+
+        // Window of cell 0: cells -r..=r
+        let mut window = (0..size).fold(0, |w, j| (w << 1) | cells[wrap(j + n * size - self.radius)] as usize);
+        for i in 0..n {
+            next[i] = (self.table >> window) & 1 == 1;
+            // Slide right: cell i + r + 1 enters
+            window = ((window << 1) | cells[wrap(i + self.radius + 1)] as usize) & mask;
+        }
+    }
+
+    /// Run from `initial` for up to `max_steps` steps (stopping early at a
+    /// fixed point) and report whether it settled to the majority verdict
+    pub fn classifies(&self, initial: &[bool], max_steps: usize) -> bool {
+        let majority = 2 * initial.iter().filter(|&&c| c).count() > initial.len();
+        let mut cells = initial.to_vec();
+        let mut next = vec![false; cells.len()];
+        for _ in 0..max_steps {
+            self.step_into(&cells, &mut next);
+            if next == cells {
+                break;
+            }
+            std::mem::swap(&mut cells, &mut next);
+        }
+        cells.iter().all(|&c| c == majority)
+    }
+
+    /// Fraction of `initials` classified correctly
+    pub fn fitness(&self, initials: &[Vec<bool>], max_steps: usize) -> f64 {
+        let correct = initials.iter().filter(|ic| self.classifies(ic, max_steps)).count();
+        correct as f64 / initials.len() as f64
+    }
+}
+
+/// Random rows whose densities are uniform on [0, 1], the usual unbiased
+/// test set for density classification
+pub fn density_initials(rng: &mut ChaCha8Rng, count: usize, width: usize) -> Vec<Vec<bool>> {
+    (0..count)
+        .map(|_| {
+            let density: f64 = rng.random();
+            (0..width).map(|_| rng.random_bool(density)).collect()
+        })
+        .collect()
+}
+
+/// Rows of independent fair coin flips: densities cluster near 1/2, where
+/// classification is hardest
+pub fn binomial_initials(rng: &mut ChaCha8Rng, count: usize, width: usize) -> Vec<Vec<bool>> {
+    (0..count).map(|_| (0..width).map(|_| rng.random_bool(0.5)).collect()).collect()
+}
+
+/// `density_initials` closed under the symmetries of `TableRule::equivalents`:
+/// each random row comes with its reflection and, with `complement`, the
+/// complements of both. `count` is rounded up to a whole number of orbits.
+/// On such a set, equivalent rules have exactly the same fitness.
+pub fn symmetric_initials(rng: &mut ChaCha8Rng, count: usize, width: usize, complement: bool) -> Vec<Vec<bool>> {
+    let orbit = if complement { 4 } else { 2 };
+    let mut rows = Vec::with_capacity(count.next_multiple_of(orbit));
+    for row in density_initials(rng, count.div_ceil(orbit), width) {
+        let mirrored: Vec<bool> = row.iter().rev().copied().collect();
+        if complement {
+            rows.push(row.iter().map(|&c| !c).collect());
+            rows.push(mirrored.iter().map(|&c| !c).collect());
+        }
+        rows.push(row);
+        rows.push(mirrored);
+    }
+    rows
+}
+
+impl std::str::FromStr for TableRule {
+    type Err = String;
+
+    /// `eca:<0-255>`, `gkl`, `majority[:<radius>]`, `table:<hex>`, whose
+    /// length (2, 8 or 32 digits) sets the radius, `random:<radius>[:<seed>]`
+    /// (seed 0 by default), or a two-color Wolfram code such as
+    /// `k=2,r=2,code=20`, `B2/S24,r=2` or `wolfram:<code>`
+    fn from_str(s: &str) -> Result<Self, String> {
+        let wolfram = ["B", "totalistic:", "wolfram:"].iter().any(|prefix| s.starts_with(prefix));
+        if wolfram || s.contains('=') {
+            let rule: WolframRule = s.parse()?;
+            let colors = rule.colors;
+            return rule.table_rule().ok_or(format!("{rule} has {colors} colors, not 2; `wolfram` runs any code"));
+        }
+        let (kind, value) = s.split_once(':').unwrap_or((s, ""));
+        match (kind, value) {
+            ("eca", n) => n.parse::<u8>().map(|n| Self::new(1, n as u128)).map_err(|_| format!("bad ECA number {n:?}")),
+            ("gkl", "") => Ok(Self::gkl()),
+            ("majority", "") => Ok(Self::local_majority(MAX_RADIUS)),
+            ("majority", r) => match r.parse() {
+                Ok(r) if (1..=MAX_RADIUS).contains(&r) => Ok(Self::local_majority(r)),
+                _ => Err(format!("majority radius must be 1..={MAX_RADIUS}")),
+            },
+            ("table", hex) => {
+                let radius = match hex.len() {
+                    2 => 1,
+                    8 => 2,
+                    32 => 3,
+                    n => return Err(format!("a table has 2, 8 or 32 hex digits, not {n}")),
+                };
+                u128::from_str_radix(hex, 16).map(|t| Self::new(radius, t)).map_err(|_| format!("bad hex table {hex:?}"))
+            }
+            ("random", spec) => {
+                let (radius, seed) = spec.split_once(':').unwrap_or((spec, "0"));
+                let seed = seed.parse().map_err(|_| format!("bad seed {seed:?}"))?;
+                match radius.parse() {
+                    Ok(r) if (1..=MAX_RADIUS).contains(&r) => Ok(Self::random(r, seed)),
+                    _ => Err(format!("random rules need a radius of 1..={MAX_RADIUS}, as in random:2")),
+                }
+            }
+            _ => Err(format!(
+                "unknown rule {s:?}: expected eca:N, gkl, majority[:r], table:<hex>, random:<r>[:<seed>] or k=2,r=..,code=.."
+            )),
+        }
+    }
+}
+
+/// The spec `table:<hex>` that parses back to the rule
+impl std::fmt::Display for TableRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "table:{:0digits$x}", self.table, digits = self.entries() / 4)
+    }
+}
+
+/// GA settings
+#[derive(Clone, Copy, Debug)]
+pub struct GaConfig {
+    pub radius: usize,
+    pub width: usize,
+    /// Steps allowed to reach a verdict
+    pub max_steps: usize,
+    /// Initial conditions per generation (fresh each generation, rounded up
+    /// to whole symmetry orbits)
+    pub initials: usize,
+    /// Islands, each with its own population
+    pub islands: usize,
+    /// Rules per island
+    pub population: usize,
+    /// Best rules per island copied unchanged to the next generation
+    pub elite: usize,
+    /// Bits flipped in each offspring
+    pub mutations: usize,
+    /// Generations between migrations (0 for none)
+    pub migration_interval: usize,
+    /// Best rules each island sends to its neighbor on the ring (at most
+    /// `population - elite`)
+    pub migrants: usize,
+    pub seed: u64,
+}
+
+/// Best and mean fitness of one island in one generation
+#[derive(Clone, Copy, Debug)]
+pub struct IslandStats {
+    pub best: f64,
+    pub mean: f64,
+    pub best_rule: TableRule,
+    /// Lineage id of the best rule (see `Ga::genealogy_dot`)
+    pub best_id: usize,
+}
+
+/// One individual in the GA's lineage records
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ancestor {
+    pub rule: TableRule,
+    /// Generation (1-based) the rule was born into
+    pub born: usize,
+    /// Fitness in the first generation it was scored
+    pub fitness: Option<f64>,
+    /// The crossover parent closer to the rule in Hamming distance, and the
+    /// other one; None for the random first generation
+    pub parent: Option<usize>,
+    pub partner: Option<usize>,
+}
+
+/// An island-model GA over lookup-table rules
+pub struct Ga {
+    pub config: GaConfig,
+    /// islands[i] is island i's population, best first once scored
+    pub islands: Vec<Vec<TableRule>>,
+    /// Generations bred so far; the population is generation `generation + 1`
+    pub generation: usize,
+    /// Fitness cache: fitness[i][j] is the score of islands[i][j] once the
+    /// current generation is scored, None until then
+    pub fitness: Option<Vec<Vec<f64>>>,
+    /// Fitness lookups this session, and how many of them had to simulate
+    /// (the rest were equivalent to a rule already scored that generation)
+    pub lookups: usize,
+    pub evaluations: usize,
+    /// ids[i][j] is the lineage id of islands[i][j]; elites and migrants
+    /// keep theirs
+    pub ids: Vec<Vec<usize>>,
+    /// Lineage records of every living rule's ancestors (and their crossover
+    /// partners), pruned each generation
+    pub ancestors: HashMap<usize, Ancestor>,
+    next_id: usize,
+    rng: ChaCha8Rng,
+}
+
+impl Ga {
+    /// Random initial populations
+    pub fn new(config: GaConfig) -> Self {
+        assert!(config.elite >= 1 && config.elite <= config.population, "elite must be 1..=population");
+        let mut rng = ChaCha8Rng::seed_from_u64(config.seed);
+        let islands: Vec<Vec<TableRule>> = (0..config.islands)
+            .map(|_| (0..config.population).map(|_| TableRule::new(config.radius, rng.random())).collect())
+            .collect();
+        let ids: Vec<Vec<usize>> =
+            (0..config.islands).map(|i| (i * config.population..(i + 1) * config.population).collect()).collect();
+        let ancestors = islands
+            .iter()
+            .flatten()
+            .zip(ids.iter().flatten())
+            .map(|(&rule, &id)| (id, Ancestor { rule, born: 1, fitness: None, parent: None, partner: None }))
+            .collect();
+        let next_id = config.islands * config.population;
+        Self { config, islands, generation: 0, fitness: None, lookups: 0, evaluations: 0, ids, ancestors, next_id, rng }
+    }
+
+    /// Score the current generation on a fresh set of initial conditions and
+    /// sort each island best first, or return the cached scores if it has
+    /// been scored already
+    pub fn score(&mut self) -> Vec<IslandStats> {
+        let fitness = match self.fitness.take() {
+            Some(fitness) => fitness,
+            None => self.evaluate(),
+        };
+        let stats = self
+            .islands
+            .iter()
+            .zip(&self.ids)
+            .zip(&fitness)
+            .map(|((island, ids), scores)| IslandStats {
+                best: scores[0],
+                mean: scores.iter().sum::<f64>() / scores.len() as f64,
+                best_rule: island[0],
+                best_id: ids[0],
+            })
+            .collect();
+        self.fitness = Some(fitness);
+        stats
+    }
+
+    fn evaluate(&mut self) -> Vec<Vec<f64>> {
+        let GaConfig { width, max_steps, initials, .. } = self.config;
+        // A tie has no majority, so conjugation is a symmetry only on odd rings
+        let complement = width % 2 == 1;
+        let tests = symmetric_initials(&mut self.rng, initials, width, complement);
+
+        // Score each symmetry class once; fitness evaluation dominates and
+        // every class is independent
+        let mut classes: Vec<TableRule> =
+            self.islands.iter().flatten().map(|rule| rule.canonical(complement)).collect();
+        let lookups = classes.len();
+        self.lookups += lookups;
+        classes.sort_unstable_by_key(|r| r.table);
+        classes.dedup();
+        self.evaluations += classes.len();
+        profile::add_cache(lookups, lookups - classes.len());
+        let cache: HashMap<TableRule, f64> =
+            classes.into_par_iter().map(|rule| (rule, rule.fitness(&tests, max_steps))).collect();
+
+        let scored: Vec<Vec<(TableRule, usize, f64)>> = self
+            .islands
+            .iter()
+            .zip(&self.ids)
+            .map(|(island, ids)| {
+                island.iter().zip(ids).map(|(rule, &id)| (*rule, id, cache[&rule.canonical(complement)])).collect()
+            })
+            .collect();
+
+        let mut fitness = Vec::with_capacity(scored.len());
+        for ((island, ids), mut scores) in self.islands.iter_mut().zip(&mut self.ids).zip(scored) {
+            // Stable sort: ties keep population order, so runs are reproducible
+            scores.sort_by(|a, b| b.2.total_cmp(&a.2));
+            *island = scores.iter().map(|&(rule, _, _)| rule).collect();
+            *ids = scores.iter().map(|&(_, id, _)| id).collect();
+            for &(_, id, f) in &scores {
+                let record = self.ancestors.get_mut(&id).expect("living rules have lineage records");
+                record.fitness.get_or_insert(f);
+            }
+            fitness.push(scores.into_iter().map(|(_, _, f)| f).collect());
+        }
+        fitness
+    }
+
+    /// Breed the next generation from the scored one, and migrate if due
+    pub fn advance(&mut self) {
+        if self.fitness.is_none() {
+            self.score();
+        }
+        let GaConfig { elite, migration_interval, migrants, .. } = self.config;
+
+        // Breed: keep the elite, fill the rest with crossover + mutation of elite parents
+        for (island, ids) in self.islands.iter_mut().zip(&mut self.ids) {
+            let mut next: Vec<TableRule> = island[..elite].to_vec();
+            let mut next_ids: Vec<usize> = ids[..elite].to_vec();
+            while next.len() < island.len() {
+                let (a, b) = (self.rng.random_range(0..elite), self.rng.random_range(0..elite));
+                let child = breed(&mut self.rng, island[a], island[b], self.config.mutations);
+                let distance = |parent: TableRule| (parent.table ^ child.table).count_ones();
+                let (parent, partner) =
+                    if distance(island[a]) <= distance(island[b]) { (ids[a], ids[b]) } else { (ids[b], ids[a]) };
+                self.ancestors.insert(self.next_id, Ancestor {
+                    rule: child,
+                    born: self.generation + 2,
+                    fitness: None,
+                    parent: Some(parent),
+                    partner: Some(partner),
+                });
+                next.push(child);
+                next_ids.push(self.next_id);
+                self.next_id += 1;
+            }
+            *island = next;
+            *ids = next_ids;
+        }
+
+        self.fitness = None;
+        self.generation += 1;
+        if migration_interval > 0 && self.islands.len() > 1 && self.generation.is_multiple_of(migration_interval) {
+            self.migrate(migrants);
+        }
+        self.prune_ancestors();
+    }
+
+    /// Drop lineage records no living rule descends from, keeping the
+    /// crossover partners of every kept ancestor for the genealogy
+    fn prune_ancestors(&mut self) {
+        let mut lineage = HashSet::new();
+        let mut keep = HashSet::new();
+        for &id in self.ids.iter().flatten() {
+            let mut next = Some(id);
+            while let Some(id) = next.filter(|id| lineage.insert(*id)) {
+                keep.insert(id);
+                let record = &self.ancestors[&id];
+                keep.extend(record.partner);
+                next = record.parent;
+            }
+        }
+        self.ancestors.retain(|id, _| keep.contains(id));
+    }
+
+    /// Ids from `id` back through closer parents to the first generation
+    pub fn lineage(&self, id: usize) -> Vec<usize> {
+        std::iter::successors(Some(id), |id| self.ancestors.get(id).and_then(|a| a.parent)).collect()
+    }
+
+    /// Graphviz genealogy of `id`: its line of closer parents back to the
+    /// first generation, each with its fitness, each edge labelled with the
+    /// windows whose output changed, and crossover partners as dashed edges
+    pub fn genealogy_dot(&self, id: usize) -> String {
+        let size = 2 * self.config.radius + 1;
+        let lineage = self.lineage(id);
+        let mut out = String::from("digraph genealogy {\n  rankdir=TB;\n  node [shape=box, fontname=monospace];\n");
+        let mut drawn = HashSet::new();
+        let mut node = |out: &mut String, id: usize, style: &str| {
+            if !drawn.insert(id) {
+                return;
+            }
+            let a = &self.ancestors[&id];
+            let fitness = a.fitness.map_or("unscored".to_string(), |f| format!("fitness {f:.3}"));
+            let _ = writeln!(out, "  n{id} [label=\"gen {}\\n{fitness}\\n{:0w$x}\"{style}];", a.born, a.rule.table,
+                w = a.rule.entries() / 4);
+        };
+
+        node(&mut out, id, ", penwidth=2");
+        for &ancestor in &lineage[1..] {
+            node(&mut out, ancestor, "");
+        }
+        for pair in lineage.windows(2).rev() {
+            let (child, parent) = (&self.ancestors[&pair[0]], &self.ancestors[&pair[1]]);
+
+            // Windows whose output bit changed, as neighborhoods read left to right
+            let changed = parent.rule.table ^ child.rule.table;
+            let mut flips: Vec<String> = (0..child.rule.entries())
+                .filter(|w| (changed >> w) & 1 == 1)
+                .map(|w| format!("{w:0size$b}: {}>{}", (parent.rule.table >> w) & 1, (child.rule.table >> w) & 1))
+                .collect();
+            if flips.len() > 8 {
+                let more = flips.len() - 8;
+                flips.truncate(8);
+                flips.push(format!("+{more} more"));
+            }
+            let label = if flips.is_empty() { "unchanged".to_string() } else { flips.join("\\n") };
+            let _ = writeln!(out, "  n{} -> n{} [label=\"{label}\"];", pair[1], pair[0]);
+
+            if let Some(partner) = child.partner.filter(|p| self.ancestors.contains_key(p)) {
+                node(&mut out, partner, ", style=dashed");
+                let _ = writeln!(out, "  n{partner} -> n{} [style=dashed];", pair[0]);
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Score the current generation, then breed the next. Returns each
+    /// island's statistics for the generation just scored.
+    pub fn step(&mut self) -> Vec<IslandStats> {
+        let stats = self.score();
+        self.advance();
+        stats
+    }
+
+    /// Write the whole state (settings, populations, fitness cache and RNG
+    /// position) to `path` as text, via a temporary file so an interrupted
+    /// save leaves the previous checkpoint intact
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let c = &self.config;
+        let mut out = String::from("# automata evolve checkpoint\n");
+        let seed: String = self.rng.get_seed().iter().map(|b| format!("{b:02x}")).collect();
+        let _ = writeln!(out, "config {} {} {} {} {} {} {} {} {} {} {}", c.radius, c.width, c.max_steps,
+            c.initials, c.islands, c.population, c.elite, c.mutations, c.migration_interval, c.migrants, c.seed);
+        let _ = writeln!(out, "generation {}", self.generation);
+        let _ = writeln!(out, "rng {seed} {} {}", self.rng.get_stream(), self.rng.get_word_pos());
+        for (i, island) in self.islands.iter().enumerate() {
+            let _ = writeln!(out, "island {i}");
+            for (j, rule) in island.iter().enumerate() {
+                match &self.fitness {
+                    Some(fitness) => writeln!(out, "{:x} {} {}", rule.table, self.ids[i][j], fitness[i][j]),
+                    None => writeln!(out, "{:x} {}", rule.table, self.ids[i][j]),
+                }
+                .expect("writing to a String cannot fail");
+            }
+        }
+        let _ = writeln!(out, "lineage {} {}", self.next_id, self.ancestors.len());
+        let mut ids: Vec<&usize> = self.ancestors.keys().collect();
+        ids.sort_unstable();
+        let field = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        for id in ids {
+            let a = &self.ancestors[id];
+            let _ = writeln!(out, "{id} {} {} {} {} {:x}", a.born, field(a.fitness.map(|f| f.to_string())),
+                field(a.parent.map(|p| p.to_string())), field(a.partner.map(|p| p.to_string())), a.rule.table);
+        }
+
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, out)?;
+        std::fs::rename(tmp, path)
+    }
+
+    /// Read a checkpoint written by `save`
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        parse_checkpoint(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Each island's elite sends its best rules to the next island on a
+    /// ring, replacing offspring (the elite itself is never displaced)
+    fn migrate(&mut self, migrants: usize) {
+        let outgoing: Vec<(Vec<TableRule>, Vec<usize>)> = self
+            .islands
+            .iter()
+            .zip(&self.ids)
+            .map(|(island, ids)| {
+                let n = migrants.min(island.len());
+                (island[..n].to_vec(), ids[..n].to_vec())
+            })
+            .collect();
+        let count = self.islands.len();
+        for (from, (emigrants, emigrant_ids)) in outgoing.into_iter().enumerate() {
+            let to = (from + 1) % count;
+            let start = self.islands[to].len() - emigrants.len();
+            self.islands[to][start..].copy_from_slice(&emigrants);
+            self.ids[to][start..].copy_from_slice(&emigrant_ids);
+        }
+    }
+}
+
+fn parse_checkpoint(text: &str) -> Result<Ga, String> {
+    fn number<T: std::str::FromStr>(field: Option<&str>, what: &str) -> Result<T, String> {
+        field.and_then(|f| f.parse().ok()).ok_or_else(|| format!("bad or missing {what}"))
+    }
+
+    // `-` for None
+    fn optional<T: std::str::FromStr>(field: Option<&str>, what: &str) -> Result<Option<T>, String> {
+        match field {
+            Some("-") => Ok(None),
+            field => number(field, what).map(Some),
+        }
+    }
+
+    // Fields after `key` on the next line, which must start with `key`
+    fn header<'a>(lines: &mut impl Iterator<Item = &'a str>, key: &str) -> Result<Vec<&'a str>, String> {
+        let line = lines.next().ok_or_else(|| format!("missing {key} line"))?;
+        match line.split_whitespace().collect::<Vec<_>>().split_first() {
+            Some((&k, rest)) if k == key => Ok(rest.to_vec()),
+            _ => Err(format!("expected {key} line, found {line:?}")),
+        }
+    }
+
+    let mut lines = text.lines().filter(|line| !line.starts_with('#'));
+
+    let fields = header(&mut lines, "config")?;
+    let [radius, width, max_steps, initials, islands, population, elite, mutations, migration_interval, migrants, seed] =
+        fields[..]
+    else {
+        return Err("config line needs 11 fields".to_string());
+    };
+    let config = GaConfig {
+        radius: number(Some(radius), "radius")?,
+        width: number(Some(width), "width")?,
+        max_steps: number(Some(max_steps), "max_steps")?,
+        initials: number(Some(initials), "initials")?,
+        islands: number(Some(islands), "islands")?,
+        population: number(Some(population), "population")?,
+        elite: number(Some(elite), "elite")?,
+        mutations: number(Some(mutations), "mutations")?,
+        migration_interval: number(Some(migration_interval), "migration_interval")?,
+        migrants: number(Some(migrants), "migrants")?,
+        seed: number(Some(seed), "seed")?,
+    };
+    if !(1..=MAX_RADIUS).contains(&config.radius) || config.elite == 0 || config.elite > config.population {
+        return Err("config is out of range".to_string());
+    }
+    let generation = number(header(&mut lines, "generation")?.first().copied(), "generation")?;
+
+    let rng_fields = header(&mut lines, "rng")?;
+    let seed_hex = rng_fields.first().filter(|h| h.len() == 64).ok_or("bad or missing RNG seed")?;
+    let mut key = [0u8; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&seed_hex[2 * i..2 * i + 2], 16).map_err(|_| "bad RNG seed")?;
+    }
+    let mut rng = ChaCha8Rng::from_seed(key);
+    rng.set_stream(number(rng_fields.get(1).copied(), "RNG stream")?);
+    rng.set_word_pos(number(rng_fields.get(2).copied(), "RNG position")?);
+
+    let table = |field: Option<&str>| -> Result<TableRule, String> {
+        let table = field.and_then(|t| u128::from_str_radix(t, 16).ok()).ok_or("bad or missing rule table")?;
+        Ok(TableRule::new(config.radius, table))
+    };
+
+    let mut islands = Vec::with_capacity(config.islands);
+    let mut ids = Vec::with_capacity(config.islands);
+    let mut fitness = Vec::with_capacity(config.islands);
+    for i in 0..config.islands {
+        header(&mut lines, "island")?;
+        let (mut rules, mut island_ids, mut scores) = (Vec::new(), Vec::new(), Vec::new());
+        for _ in 0..config.population {
+            let line = lines.next().ok_or_else(|| format!("island {i} is short of rules"))?;
+            let mut parts = line.split_whitespace();
+            rules.push(table(parts.next())?);
+            island_ids.push(number(parts.next(), "lineage id")?);
+            if let Some(score) = parts.next() {
+                scores.push(number(Some(score), "fitness")?);
+            }
+        }
+        islands.push(rules);
+        ids.push(island_ids);
+        fitness.push(scores);
+    }
+
+    let lineage = header(&mut lines, "lineage")?;
+    let next_id = number(lineage.first().copied(), "next lineage id")?;
+    let mut ancestors = HashMap::new();
+    for _ in 0..number::<usize>(lineage.get(1).copied(), "lineage size")? {
+        let line = lines.next().ok_or("lineage is short of records")?;
+        let mut parts = line.split_whitespace();
+        let id = number(parts.next(), "lineage id")?;
+        let born = number(parts.next(), "birth generation")?;
+        let fitness = optional(parts.next(), "ancestor fitness")?;
+        let parent = optional(parts.next(), "parent id")?;
+        let partner = optional(parts.next(), "partner id")?;
+        ancestors.insert(id, Ancestor { rule: table(parts.next())?, born, fitness, parent, partner });
+    }
+    if let Some(missing) = ids.iter().flatten().find(|&id| !ancestors.contains_key(id)) {
+        return Err(format!("no lineage record for rule {missing}"));
+    }
+
+    // The cache is all or nothing: scored checkpoints carry every fitness
+    let fitness = match fitness.iter().map(Vec::len).sum::<usize>() {
+        0 => None,
+        n if n == config.islands * config.population => Some(fitness),
+        _ => return Err("fitness is missing for some rules".to_string()),
+    };
+    Ok(Ga { config, islands, generation, fitness, lookups: 0, evaluations: 0, ids, ancestors, next_id, rng })
+}
+
+/// Single-point crossover of two tables, then `mutations` random bit flips
+fn breed(rng: &mut ChaCha8Rng, a: TableRule, b: TableRule, mutations: usize) -> TableRule {
+    let entries = a.entries();
+    let point = rng.random_range(1..entries);
+    let low = (1u128 << point) - 1;
+    let mut table = (a.table & low) | (b.table & !low);
+    for _ in 0..mutations {
+        table ^= 1 << rng.random_range(0..entries);
+    }
+    TableRule::new(a.radius, table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Automaton;
+
+    #[test]
+    fn test_radius_one_table_is_eca() {
+        let cells: Vec<bool> = (0..37).map(|i| (i * 5 + 1) % 7 < 3).collect();
+        for rule in [30u8, 90, 110, 184] {
+            let mut ca = Automaton::from_cells(cells.clone(), rule);
+            ca.step();
+            assert_eq!(TableRule::new(1, rule as u128).step(&cells), ca.cells());
+        }
+
+        // Specs print at the radius's own width and parse back; random ones are seeded
+        let rule = TableRule::new(2, 0x0096_e81f);
+        assert_eq!(rule.to_string(), "table:0096e81f");
+        assert_eq!(rule.to_string().parse(), Ok(rule));
+        assert_eq!("random:2:7".parse(), Ok(TableRule::random(2, 7)));
+        assert_eq!("random:2".parse(), Ok(TableRule::random(2, 0)));
+        assert_ne!(TableRule::random(2, 7), TableRule::random(2, 8));
+        assert!("random:4".parse::<TableRule>().is_err());
+        let quiescent = [0xffff_ffff, 0x8000_0000, 1].map(|t| TableRule::new(2, t).quiescent());
+        assert_eq!(quiescent, [Some(true), Some(false), None]);
+    }
+
+    #[test]
+    fn test_majority_rule_fitness() {
+        // Radius-3 local majority: right on lopsided rows, not a perfect classifier
+        let table = (0..128u32).filter(|w| w.count_ones() >= 4).fold(0u128, |t, w| t | 1 << w);
+        let majority = TableRule::new(3, table);
+        assert_eq!("majority".parse(), Ok(majority));
+        assert_eq!("eca:232".parse(), Ok(TableRule::local_majority(1)));
+        // Live when 4 or more of the 7 are: totalistic code 0b11110000
+        assert_eq!("k=2,r=3,code=240".parse(), Ok(majority));
+
+        // GKL is a far better classifier, and its own conjugate-mirror
+        let gkl = TableRule::gkl();
+        assert_eq!(gkl.mirror().complement(), gkl);
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        assert!(gkl.fitness(&density_initials(&mut rng, 100, 149), 298) > 0.75);
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let fitness = majority.fitness(&density_initials(&mut rng, 100, 59), 120);
+        assert!(fitness > 0.2 && fitness < 1.0, "fitness {fitness}");
+
+        let all_lopsided = [vec![true; 59], vec![false; 59]];
+        assert_eq!(majority.fitness(&all_lopsided, 120), 1.0);
+    }
+
+    #[test]
+    fn test_equivalent_rules_share_fitness() {
+        // Rule 110's class: mirror 124, conjugate 137, both 193
+        let eca = TableRule::new(1, 110);
+        let tables: Vec<u128> = eca.equivalents(true).iter().map(|r| r.table).collect();
+        assert_eq!(tables, [110, 124, 137, 193]);
+        assert_eq!(eca.canonical(false).table, 110);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let tests = symmetric_initials(&mut rng, 30, 41, true);
+        assert_eq!(tests.len(), 32);
+        for _ in 0..5 {
+            let rule = TableRule::new(3, rng.random());
+            assert_eq!(rule.mirror().mirror(), rule);
+            assert_eq!(rule.complement().complement(), rule);
+            let fitness = rule.fitness(&tests, 80);
+            for equivalent in rule.equivalents(true) {
+                assert_eq!(equivalent.fitness(&tests, 80), fitness);
+            }
+        }
+    }
+
+    #[test]
+    fn test_resumed_run_matches_uninterrupted() {
+        let config = GaConfig {
+            radius: 2,
+            width: 31,
+            max_steps: 40,
+            initials: 20,
+            islands: 2,
+            population: 12,
+            elite: 4,
+            mutations: 2,
+            migration_interval: 2,
+            migrants: 1,
+            seed: 9,
+        };
+        let mut straight = Ga::new(config);
+        let expected: Vec<_> = (0..5).map(|_| straight.step()[0].best_rule).collect();
+
+        let path = std::env::temp_dir().join(format!("automata-checkpoint-{}.txt", std::process::id()));
+        let mut ga = Ga::new(config);
+        let mut seen = Vec::new();
+        for _ in 0..5 {
+            seen.push(ga.score()[0].best_rule);
+            ga.save(&path).unwrap();
+            ga = Ga::load(&path).unwrap();
+            ga.advance();
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(seen, expected);
+        assert_eq!(ga.islands, straight.islands);
+        assert_eq!(ga.ids, straight.ids);
+        assert_eq!(ga.ancestors, straight.ancestors);
+    }
+
+    #[test]
+    fn test_lineage_reaches_first_generation() {
+        let config = GaConfig {
+            radius: 1,
+            width: 21,
+            max_steps: 30,
+            initials: 8,
+            islands: 1,
+            population: 10,
+            elite: 3,
+            mutations: 1,
+            migration_interval: 0,
+            migrants: 0,
+            seed: 5,
+        };
+        let mut ga = Ga::new(config);
+        for _ in 0..6 {
+            ga.step();
+        }
+        for &id in ga.ids.iter().flatten() {
+            let lineage = ga.lineage(id);
+            let founder = &ga.ancestors[lineage.last().unwrap()];
+            assert_eq!((founder.born, founder.parent), (1, None));
+            // Each step back is to an earlier generation
+            assert!(lineage.windows(2).all(|pair| ga.ancestors[&pair[0]].born > ga.ancestors[&pair[1]].born));
+        }
+        let dot = ga.genealogy_dot(ga.ids[0][0]);
+        assert!(dot.starts_with("digraph genealogy {") && dot.contains("penwidth=2"));
+    }
+}
@@ -0,0 +1,348 @@
+//! Basin-of-attraction fields drawn the way Wuensche's DDLab draws them
+//!
+//! Each basin is its attractor cycle with the transient trees hanging off
+//! it: the cycle states on a ring, and every other state one level further
+//! out than its successor, so a state's distance from the centre is its
+//! transient and Gardens of Eden are the tips of the branches. A node's
+//! predecessors share its angular sector in proportion to the tips above
+//! each, and the cycle states share the full turn the same way, so bushy
+//! trees get room and a fixed point's tree fills the circle around it.
+//!
+//! The field is every basin of the ring, largest first, packed in rows at
+//! one scale, so their sizes compare. Rotating a ring's states maps basins
+//! onto basins, and with `distinct` only one of each such set is drawn, as
+//! DDLab's compressed fields do, with the number it stands for.
+
+use crate::basins::{BasinAnalysis, CycleFingerprint, MAX_GRAPH_WIDTH};
+use crate::render::write_canvas;
+use std::collections::HashMap;
+use std::f64::consts::TAU;
+use std::io::{self, Write};
+
+/// Space around each basin, in tree levels
+const GAP: f64 = 1.0;
+
+/// One basin laid out about its centre, in units of one tree level
+#[derive(Clone, Debug)]
+pub struct Basin {
+    /// Index of its attractor in the analysis
+    pub attractor: usize,
+    /// Basins it stands for: 1, or with `distinct` its rotations too
+    pub copies: usize,
+    pub period: usize,
+    pub states: Vec<u32>,
+    /// Each state's place, relative to `centre`
+    pub points: Vec<[f64; 2]>,
+    /// Index in `states` of each state's successor
+    pub next: Vec<usize>,
+    /// Whether each state is on the cycle
+    pub cycle: Vec<bool>,
+    pub radius: f64,
+    pub centre: [f64; 2],
+}
+
+/// Every basin of a ring, placed
+#[derive(Clone, Debug)]
+pub struct Field {
+    pub rule: u8,
+    pub width: usize,
+    pub basins: Vec<Basin>,
+    /// Extent of the whole field, in tree levels
+    pub size: [f64; 2],
+}
+
+/// Size and colors for `Field::write_png` and `write_svg`
+#[derive(Clone, Copy, Debug)]
+pub struct FieldOptions {
+    /// Pixels along the field's longer side
+    pub size: usize,
+    pub background: [u8; 3],
+    /// Transient states and the edges to their successors
+    pub tree: [u8; 3],
+    /// Cycle states and the edges between them
+    pub cycle: [u8; 3],
+}
+
+impl Default for FieldOptions {
+    fn default() -> Self {
+        Self { size: 1200, background: [255, 255, 255], tree: [90, 90, 90], cycle: [200, 0, 0] }
+    }
+}
+
+impl Field {
+    /// The field of `analysis`'s ring (up to `MAX_GRAPH_WIDTH` cells); one
+    /// basin per set of rotations with `distinct`
+    pub fn new(analysis: &BasinAnalysis, distinct: bool) -> Field {
+        assert!(analysis.width <= MAX_GRAPH_WIDTH, "fields are drawn up to {MAX_GRAPH_WIDTH} cells");
+        let (next, _) = analysis.edges();
+        // Predecessors of every state, as offsets into one array
+        let mut starts = vec![0usize; analysis.states() + 1];
+        next.iter().for_each(|&t| starts[t as usize + 1] += 1);
+        (0..analysis.states()).for_each(|s| starts[s + 1] += starts[s]);
+        let mut filled = starts.clone();
+        let mut predecessors = vec![0u32; analysis.states()];
+        for (s, &t) in next.iter().enumerate() {
+            predecessors[filled[t as usize]] = s as u32;
+            filled[t as usize] += 1;
+        }
+        let tree = |s: u32| {
+            let s = s as usize;
+            predecessors[starts[s]..starts[s + 1]].iter().copied().filter(|&p| analysis.transient[p as usize] > 0)
+        };
+
+        // The first attractor of each rotation class stands for the rest
+        let mut copies: Vec<(usize, usize)> = Vec::new();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for (id, a) in analysis.attractors.iter().enumerate() {
+            let class = CycleFingerprint::of_attractor(a, analysis.width).id();
+            match seen.get(&class) {
+                Some(&i) if distinct => copies[i].1 += 1,
+                _ => {
+                    seen.insert(class, copies.len());
+                    copies.push((id, 1));
+                }
+            }
+        }
+
+        let mut members: Vec<Vec<u32>> = vec![Vec::new(); analysis.attractors.len()];
+        for s in 0..analysis.states() {
+            members[analysis.attractor[s] as usize].push(s as u32);
+        }
+        let basins =
+            copies.into_iter().map(|(id, copies)| lay_out(analysis, id, copies, &members[id], &next, &tree)).collect();
+        let mut field = Field { rule: analysis.rule, width: analysis.width, basins, size: [0.0; 2] };
+        field.pack();
+        field
+    }
+
+    /// Place the basins in rows, in order, centred in their row
+    fn pack(&mut self) {
+        let side = |b: &Basin| 2.0 * b.radius + GAP;
+        let area: f64 = self.basins.iter().map(|b| side(b) * side(b)).sum();
+        let widest = self.basins.iter().map(side).fold(0.0, f64::max);
+        let row_width = area.sqrt().max(widest);
+        let mut rows: Vec<Vec<usize>> = vec![Vec::new()];
+        let mut x = 0.0;
+        for (i, basin) in self.basins.iter().enumerate() {
+            if x > 0.0 && x + side(basin) > row_width {
+                rows.push(Vec::new());
+                x = 0.0;
+            }
+            rows.last_mut().unwrap().push(i);
+            x += side(basin);
+        }
+        let mut y = 0.0;
+        for row in rows {
+            let height = row.iter().map(|&i| side(&self.basins[i])).fold(0.0, f64::max);
+            let mut x = 0.0;
+            for i in row {
+                let basin = &mut self.basins[i];
+                basin.centre = [x + side(basin) / 2.0, y + height / 2.0];
+                x += side(basin);
+            }
+            self.size[0] = self.size[0].max(x);
+            y += height;
+        }
+        self.size[1] = y;
+    }
+
+    /// Pixels per tree level, and the image's size, for `options.size`
+    fn scale(&self, options: &FieldOptions) -> (f64, usize, usize) {
+        let scale = options.size as f64 / self.size[0].max(self.size[1]);
+        let pixels = |extent: f64| ((extent * scale).ceil() as usize).max(1);
+        (scale, pixels(self.size[0]), pixels(self.size[1]))
+    }
+
+    /// Each edge, from a state to its successor, in field coordinates, and
+    /// whether it joins two cycle states
+    fn segments(&self) -> impl Iterator<Item = ([f64; 2], [f64; 2], bool)> + '_ {
+        self.basins.iter().flat_map(|b| {
+            let at = |i: usize| [b.centre[0] + b.points[i][0], b.centre[1] + b.points[i][1]];
+            (0..b.states.len()).map(move |i| (at(i), at(b.next[i]), b.cycle[i]))
+        })
+    }
+
+    /// The field as a PNG: lines for the edges, dots for the states, the
+    /// cycles in their own color on top
+    pub fn write_png(&self, out: &mut impl Write, options: &FieldOptions) -> io::Result<()> {
+        let (scale, width, height) = self.scale(options);
+        let mut canvas = options.background.repeat(width * height);
+        let mut put = |x: i64, y: i64, rgb: [u8; 3]| {
+            if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                canvas[3 * (y as usize * width + x as usize)..][..3].copy_from_slice(&rgb);
+            }
+        };
+        let pixel = |p: [f64; 2]| [(p[0] * scale).round() as i64, (p[1] * scale).round() as i64];
+        for on_cycle in [false, true] {
+            let color = if on_cycle { options.cycle } else { options.tree };
+            for (from, to, _) in self.segments().filter(|&(_, _, cycle)| cycle == on_cycle) {
+                let ([mut x, mut y], [x1, y1]) = (pixel(from), pixel(to));
+                let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+                let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+                let mut error = dx + dy;
+                loop {
+                    put(x, y, color);
+                    if (x, y) == (x1, y1) {
+                        break;
+                    }
+                    let doubled = 2 * error;
+                    if doubled >= dy {
+                        error += dy;
+                        x += sx;
+                    }
+                    if doubled <= dx {
+                        error += dx;
+                        y += sy;
+                    }
+                }
+            }
+            let dot: i64 = if on_cycle { 2 } else { 1 };
+            for (from, _, _) in self.segments().filter(|&(_, _, cycle)| cycle == on_cycle) {
+                let [x, y] = pixel(from);
+                for (dx, dy) in (-dot / 2..=dot / 2).flat_map(|dx| (-dot / 2..=dot / 2).map(move |dy| (dx, dy))) {
+                    put(x + dx, y + dy, color);
+                }
+            }
+        }
+        write_canvas(out, width, height, &canvas)
+    }
+
+    /// The field as an SVG: each state a circle titled with its cells, each
+    /// basin labelled with its period and, for a `distinct` field, how many
+    /// basins it stands for
+    pub fn write_svg(&self, out: &mut impl Write, options: &FieldOptions) -> io::Result<()> {
+        let (scale, width, height) = self.scale(options);
+        let hex = |[r, g, b]: [u8; 3]| format!("#{r:02x}{g:02x}{b:02x}");
+        let xy = |p: [f64; 2]| format!("{:.1} {:.1}", p[0] * scale, p[1] * scale);
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        )?;
+        writeln!(out, r#"<rect width="{width}" height="{height}" fill="{}"/>"#, hex(options.background))?;
+        for on_cycle in [false, true] {
+            let color = hex(if on_cycle { options.cycle } else { options.tree });
+            let path: String = self
+                .segments()
+                .filter(|&(from, to, cycle)| cycle == on_cycle && from != to)
+                .map(|(from, to, _)| format!("M{}L{}", xy(from), xy(to)))
+                .collect();
+            if !path.is_empty() {
+                writeln!(out, r#"<path d="{path}" stroke="{color}" stroke-width="0.5" fill="none"/>"#)?;
+            }
+        }
+        let cells = |s: u32| (0..self.width).map(|i| if s >> i & 1 == 1 { '1' } else { '0' }).collect::<String>();
+        for basin in &self.basins {
+            let [x, y] = [(basin.centre[0] - basin.radius) * scale, (basin.centre[1] - basin.radius) * scale];
+            let copies = if basin.copies > 1 { format!(" x{}", basin.copies) } else { String::new() };
+            writeln!(out, r#"<text x="{x:.1}" y="{y:.1}" font-family="monospace" font-size="9">p{}{copies}</text>"#,
+                basin.period)?;
+            writeln!(out, "<g>")?;
+            for (i, &s) in basin.states.iter().enumerate() {
+                let [x, y] = [basin.centre[0] + basin.points[i][0], basin.centre[1] + basin.points[i][1]];
+                let (r, color) = if basin.cycle[i] { (1.5, options.cycle) } else { (0.8, options.tree) };
+                writeln!(out, r#"<circle cx="{:.1}" cy="{:.1}" r="{r}" fill="{}"><title>{}</title></circle>"#, x * scale,
+                    y * scale, hex(color), cells(s))?;
+            }
+            writeln!(out, "</g>")?;
+        }
+        writeln!(out, "</svg>")
+    }
+}
+
+/// Basin `id`, its cycle on a ring of about a level per state and its
+/// trees fanning out from it, each node on the middle of its sector
+fn lay_out<I: Iterator<Item = u32>>(
+    analysis: &BasinAnalysis,
+    id: usize,
+    copies: usize,
+    states: &[u32],
+    next: &[u32],
+    tree: &impl Fn(u32) -> I,
+) -> Basin {
+    let attractor = &analysis.attractors[id];
+    let transient = |s: u32| analysis.transient[s as usize] as usize;
+    let index: HashMap<u32, usize> = states.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+
+    // Tips of the tree above each state, deepest states first
+    let mut tips = vec![0usize; states.len()];
+    let mut order: Vec<usize> = (0..states.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(transient(states[i])));
+    for &i in &order {
+        tips[i] = tips[i].max(1);
+        if transient(states[i]) > 0 {
+            tips[index[&next[states[i] as usize]]] += tips[i];
+        }
+    }
+
+    let period = attractor.period();
+    let ring = if period == 1 { 0.0 } else { (period as f64 / TAU).max(0.5) };
+    let mut points = vec![[0.0; 2]; states.len()];
+    let total: usize = attractor.cycle.iter().map(|&c| tips[index[&c]]).sum();
+    let mut sectors: Vec<(u32, f64, f64)> = Vec::new();
+    let mut angle = 0.0;
+    for &c in &attractor.cycle {
+        let share = TAU * tips[index[&c]] as f64 / total as f64;
+        sectors.push((c, angle, angle + share));
+        angle += share;
+    }
+    // Out from the cycle, a level at a time
+    while let Some((s, low, high)) = sectors.pop() {
+        let middle = (low + high) / 2.0;
+        let r = ring + transient(s) as f64;
+        points[index[&s]] = [r * middle.cos(), r * middle.sin()];
+        let above: usize = tree(s).map(|p| tips[index[&p]]).sum();
+        let mut from = low;
+        for p in tree(s) {
+            let share = (high - low) * tips[index[&p]] as f64 / above as f64;
+            sectors.push((p, from, from + share));
+            from += share;
+        }
+    }
+
+    Basin {
+        attractor: id,
+        copies,
+        period,
+        states: states.to_vec(),
+        next: states.iter().map(|&s| index[&next[s as usize]]).collect(),
+        cycle: states.iter().map(|&s| transient(s) == 0).collect(),
+        radius: (ring + attractor.max_transient as f64).max(0.5),
+        centre: [0.0; 2],
+        points,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basins::enumerate_basins;
+
+    #[test]
+    fn test_fields_lay_out_trees_and_cycles() {
+        // Rule 0: one fixed point, every other state a tip one level out
+        let dead = Field::new(&enumerate_basins(0, 4), false);
+        let basin = &dead.basins[0];
+        assert_eq!((dead.basins.len(), basin.states.len(), basin.radius), (1, 16, 1.0));
+        let length = |p: [f64; 2]| (p[0] * p[0] + p[1] * p[1]).sqrt();
+        for (i, &p) in basin.points.iter().enumerate() {
+            assert!((length(p) - if basin.cycle[i] { 0.0 } else { 1.0 }).abs() < 1e-9);
+        }
+
+        // Identity: sixteen fixed points, six up to rotation (the necklaces of 4 bits)
+        let identity = enumerate_basins(204, 4);
+        assert_eq!(Field::new(&identity, false).basins.len(), 16);
+        let distinct = Field::new(&identity, true);
+        assert_eq!(distinct.basins.len(), 6);
+        assert_eq!(distinct.basins.iter().map(|b| b.copies).sum::<usize>(), 16);
+
+        // Rule 110's field: every state drawn once, each beside its successor's sector
+        let field = Field::new(&enumerate_basins(110, 10), false);
+        assert_eq!(field.basins.iter().map(|b| b.states.len()).sum::<usize>(), 1024);
+        assert!(field.basins.iter().all(|b| b.points.iter().all(|&p| length(p) <= b.radius + 1e-9)));
+        let (mut png, mut svg) = (Vec::new(), Vec::new());
+        field.write_png(&mut png, &FieldOptions { size: 300, ..FieldOptions::default() }).unwrap();
+        field.write_svg(&mut svg, &FieldOptions::default()).unwrap();
+        assert!(png.starts_with(crate::render::PNG_SIGNATURE));
+        assert_eq!(String::from_utf8(svg).unwrap().matches("<circle").count(), 1024);
+    }
+}
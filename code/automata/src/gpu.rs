@@ -0,0 +1,124 @@
+//! Compute-shader backend for the 256-rule surveys (feature `gpu`)
+//!
+//! Without the feature the surveys here return `GpuError::Disabled`, so
+//! callers can offer `--gpu` unconditionally and fall back to the CPU.
+//!
+//! A `GpuBatch` holds one row per automaton on the device and advances them
+//! all in lockstep: `step` is one dispatch per generation over every word of
+//! every row, and block statistics are an atomic histogram per row. Only the
+//! statistics come back to the host, except for cycle detection, which needs
+//! each generation's state.
+
+#[cfg(feature = "gpu")]
+mod batch;
+
+#[cfg(feature = "gpu")]
+pub use batch::GpuBatch;
+
+use crate::{Automaton, CycleAnalysis};
+#[cfg(feature = "gpu")]
+use crate::{shannon_entropy, CycleTracker};
+use std::fmt;
+
+/// Longest block the histogram kernel supports (2^k bins per row)
+pub const MAX_BLOCK: usize = 12;
+
+/// Why the GPU backend couldn't run
+#[derive(Debug)]
+pub enum GpuError {
+    /// Built without the `gpu` feature
+    Disabled,
+    /// No adapter or device could be created
+    Unavailable(String),
+    /// Block length above `MAX_BLOCK`
+    BlockTooLong(usize),
+    /// Reading results back from the device failed
+    Readback(String),
+}
+
+impl fmt::Display for GpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GpuError::Disabled => write!(f, "built without the `gpu` feature; rebuild with `--features gpu`"),
+            GpuError::Unavailable(reason) => write!(f, "no usable GPU: {reason}"),
+            GpuError::BlockTooLong(k) => write!(f, "block size {k} exceeds the GPU limit of {MAX_BLOCK}"),
+            GpuError::Readback(reason) => write!(f, "GPU readback failed: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for GpuError {}
+
+/// `find_cycle_from` for a batch of automata at once (one per rule in the
+/// surveys): they all step together on the device, and each generation comes
+/// back for cycle detection on the host
+#[cfg(feature = "gpu")]
+pub fn find_cycles(initial: &[Automaton], max_steps: usize) -> Result<Vec<CycleAnalysis>, GpuError> {
+    let mut trackers: Vec<CycleTracker> = initial.iter().map(|ca| CycleTracker::new(ca, max_steps)).collect();
+    let mut results: Vec<Option<CycleAnalysis>> = initial.iter().map(|_| None).collect();
+    let mut batch = GpuBatch::new(initial)?;
+
+    for step in 0..trackers.iter().map(CycleTracker::steps).max().unwrap_or(0) {
+        batch.step(1);
+        let rows = batch.rows()?;
+        for ((tracker, result), ca) in trackers.iter_mut().zip(&mut results).zip(&rows) {
+            if result.is_none() {
+                *result = tracker.observe(step, ca);
+            }
+        }
+        if results.iter().all(Option::is_some) {
+            break;
+        }
+    }
+
+    let last = batch.rows()?;
+    Ok(results
+        .into_iter()
+        .zip(trackers.iter().zip(&last))
+        .map(|(result, (tracker, ca))| result.unwrap_or_else(|| tracker.give_up(ca)))
+        .collect())
+}
+
+/// k-block entropy of each automaton's row at generations
+/// skip..=skip+generations: entropies[i][t] for initial[i]
+#[cfg(feature = "gpu")]
+pub fn entropy_series(
+    initial: &[Automaton],
+    generations: usize,
+    block_size: usize,
+    skip: usize,
+) -> Result<Vec<Vec<f64>>, GpuError> {
+    // As in `Automaton::block_entropy`
+    if let Err(e) = crate::check_block(block_size, initial[0].width()) {
+        panic!("{e}");
+    }
+    let mut batch = GpuBatch::new(initial)?;
+    let mut series = vec![Vec::with_capacity(generations + 1); initial.len()];
+
+    batch.step(skip);
+    for g in 0..=generations {
+        if g > 0 {
+            batch.step(1);
+        }
+        for (s, counts) in series.iter_mut().zip(batch.block_counts(block_size)?) {
+            s.push(shannon_entropy(&counts));
+        }
+    }
+
+    Ok(series)
+}
+
+#[cfg(not(feature = "gpu"))]
+pub fn find_cycles(_initial: &[Automaton], _max_steps: usize) -> Result<Vec<CycleAnalysis>, GpuError> {
+    Err(GpuError::Disabled)
+}
+
+#[cfg(not(feature = "gpu"))]
+pub fn entropy_series(
+    _initial: &[Automaton],
+    _generations: usize,
+    _block_size: usize,
+    _skip: usize,
+) -> Result<Vec<Vec<f64>>, GpuError> {
+    Err(GpuError::Disabled)
+}
@@ -0,0 +1,294 @@
+//! Device buffers and pipelines for stepping a batch of automata
+
+use super::{GpuError, MAX_BLOCK};
+use crate::Automaton;
+use std::sync::mpsc;
+use wgpu::util::DeviceExt;
+
+const WORKGROUP: u32 = 64;
+
+/// Automata of equal width stepped together on the device
+pub struct GpuBatch {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    step_pipeline: wgpu::ComputePipeline,
+    blocks_pipeline: wgpu::ComputePipeline,
+    /// bind_groups[i] reads states[i] and writes states[1 - i]
+    bind_groups: [wgpu::BindGroup; 2],
+    states: [wgpu::Buffer; 2],
+    params: wgpu::Buffer,
+    counts: wgpu::Buffer,
+    readback: wgpu::Buffer,
+    rules: Vec<u8>,
+    width: usize,
+    /// u32 words per row
+    words: usize,
+    /// Index of the buffer holding the current generation
+    current: usize,
+}
+
+impl GpuBatch {
+    /// Upload `rows` (all the same width) to the first available adapter
+    pub fn new(rows: &[Automaton]) -> Result<Self, GpuError> {
+        let width = rows[0].width();
+        assert!(rows.iter().all(|ca| ca.width() == width), "batched automata must share a width");
+        let words = width.div_ceil(32);
+
+        let instance = wgpu::Instance::default();
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .map_err(|e| GpuError::Unavailable(e.to_string()))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor::default()))
+            .map_err(|e| GpuError::Unavailable(e.to_string()))?;
+
+        // Host rows are u64 words; the shader works in u32 halves, low half first
+        let packed: Vec<u32> = rows
+            .iter()
+            .flat_map(|ca| {
+                let halves: Vec<u32> = ca.words().iter().flat_map(|&w| [w as u32, (w >> 32) as u32]).collect();
+                halves.into_iter().take(words)
+            })
+            .collect();
+        let rule_words: Vec<u32> = rows.iter().map(|ca| ca.rule() as u32).collect();
+
+        let state_usage = wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC;
+        let states = [
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("state a"),
+                contents: bytemuck::cast_slice(&packed),
+                usage: state_usage,
+            }),
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("state b"),
+                size: (packed.len() * 4) as u64,
+                usage: state_usage,
+                mapped_at_creation: false,
+            }),
+        ];
+        let rules_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("rules"),
+            contents: bytemuck::cast_slice(&rule_words),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("params"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let counts_size = ((rows.len() << MAX_BLOCK) * 4) as u64;
+        let counts = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("block counts"),
+            size: counts_size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let readback = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size: counts_size.max((packed.len() * 4) as u64),
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let storage = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("survey"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage(1, true),
+                storage(2, true),
+                storage(3, false),
+                storage(4, false),
+            ],
+        });
+        let bind_group = |src: &wgpu::Buffer, dst: &wgpu::Buffer| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &layout,
+                entries: &[
+                    wgpu::BindGroupEntry { binding: 0, resource: params.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 1, resource: rules_buffer.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 2, resource: src.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 3, resource: dst.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 4, resource: counts.as_entire_binding() },
+                ],
+            })
+        };
+        let bind_groups = [bind_group(&states[0], &states[1]), bind_group(&states[1], &states[0])];
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("survey"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("survey.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("survey"),
+            bind_group_layouts: &[Some(&layout)],
+            immediate_size: 0,
+        });
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &module,
+                entry_point: Some(entry_point),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        };
+        let step_pipeline = pipeline("step");
+        let blocks_pipeline = pipeline("blocks");
+
+        let batch = Self {
+            device,
+            queue,
+            step_pipeline,
+            blocks_pipeline,
+            bind_groups,
+            states,
+            params,
+            counts,
+            readback,
+            rules: rows.iter().map(Automaton::rule).collect(),
+            width,
+            words,
+            current: 0,
+        };
+        batch.write_params(0);
+        Ok(batch)
+    }
+
+    fn write_params(&self, block: usize) {
+        let params = [self.width as u32, self.words as u32, self.rules.len() as u32, block as u32];
+        self.queue.write_buffer(&self.params, 0, bytemuck::cast_slice(&params));
+    }
+
+    /// Advance every row `generations` steps in one submission
+    pub fn step(&mut self, generations: usize) {
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        for _ in 0..generations {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&self.step_pipeline);
+            pass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+            pass.dispatch_workgroups((self.words as u32).div_ceil(WORKGROUP), self.rules.len() as u32, 1);
+            drop(pass);
+            self.current = 1 - self.current;
+        }
+        self.queue.submit(Some(encoder.finish()));
+    }
+
+    /// Histogram of the k-blocks of each row's current generation
+    pub fn block_counts(&mut self, k: usize) -> Result<Vec<Vec<usize>>, GpuError> {
+        if k > MAX_BLOCK {
+            return Err(GpuError::BlockTooLong(k));
+        }
+        self.write_params(k);
+
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        encoder.clear_buffer(&self.counts, 0, None);
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&self.blocks_pipeline);
+            pass.set_bind_group(0, &self.bind_groups[self.current], &[]);
+            pass.dispatch_workgroups((self.width as u32).div_ceil(WORKGROUP), self.rules.len() as u32, 1);
+        }
+        self.queue.submit(Some(encoder.finish()));
+
+        let bins = 1 << k;
+        let counts = self.read(&self.counts, ((self.rules.len() << k) * 4) as u64)?;
+        Ok(counts.chunks(bins).map(|row| row.iter().map(|&c| c as usize).collect()).collect())
+    }
+
+    /// Copy the current generation back to the host
+    pub fn rows(&self) -> Result<Vec<Automaton>, GpuError> {
+        let packed = self.read(&self.states[self.current], (self.rules.len() * self.words * 4) as u64)?;
+        Ok(packed
+            .chunks(self.words)
+            .zip(&self.rules)
+            .map(|(halves, &rule)| {
+                let words = halves
+                    .chunks(2)
+                    .map(|pair| pair[0] as u64 | pair.get(1).map_or(0, |&hi| (hi as u64) << 32))
+                    .collect();
+                Automaton::from_words(words, self.width, rule)
+            })
+            .collect())
+    }
+
+    fn read(&self, source: &wgpu::Buffer, size: u64) -> Result<Vec<u32>, GpuError> {
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        encoder.copy_buffer_to_buffer(source, 0, &self.readback, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = self.readback.slice(..size);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|e| GpuError::Readback(e.to_string()))?;
+        receiver
+            .recv()
+            .map_err(|e| GpuError::Readback(e.to_string()))?
+            .map_err(|e| GpuError::Readback(e.to_string()))?;
+
+        let data = {
+            let view = slice.get_mapped_range().map_err(|e| GpuError::Readback(e.to_string()))?;
+            bytemuck::cast_slice(&view).to_vec()
+        };
+        self.readback.unmap();
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shannon_entropy;
+
+    /// None (with a note) on machines without a usable adapter
+    fn batch(rows: &[Automaton]) -> Option<GpuBatch> {
+        GpuBatch::new(rows).map_err(|e| eprintln!("skipping GPU test: {e}")).ok()
+    }
+
+    #[test]
+    fn test_gpu_matches_cpu() {
+        // Widths straddling u32 and u64 word boundaries
+        for width in [5, 32, 33, 64, 65, 130] {
+            let mut cpu: Vec<Automaton> = (0..=255u8).map(|rule| Automaton::new(width, rule)).collect();
+            let Some(mut gpu) = batch(&cpu) else { return };
+
+            for _ in 0..10 {
+                cpu.iter_mut().for_each(Automaton::step);
+                gpu.step(1);
+            }
+            let rows = gpu.rows().unwrap();
+            assert!(rows == cpu, "width {width}");
+
+            let counts = gpu.block_counts(3).unwrap();
+            for (ca, counts) in cpu.iter().zip(counts) {
+                assert_eq!(shannon_entropy(&counts), ca.block_entropy(3));
+            }
+        }
+    }
+}
@@ -0,0 +1,87 @@
+// One row per automaton, packed 32 cells to a word: cell i of a row is bit
+// i % 32 of word i / 32, and bits past the last cell are zero. Invocations
+// are laid out x = word (or cell), y = row.
+
+struct Params {
+    width: u32,
+    words: u32,
+    rows: u32,
+    block: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> rules: array<u32>;
+@group(0) @binding(2) var<storage, read> src: array<u32>;
+@group(0) @binding(3) var<storage, read_write> dst: array<u32>;
+@group(0) @binding(4) var<storage, read_write> counts: array<atomic<u32>>;
+
+fn cell(base: u32, i: u32) -> u32 {
+    return (src[base + i / 32u] >> (i % 32u)) & 1u;
+}
+
+fn pick(word: u32, bit: u32) -> u32 {
+    return select(~word, word, bit == 1u);
+}
+
+// Next state of 32 cells: the union of the neighborhoods the rule maps to 1
+@compute @workgroup_size(64)
+fn step(@builtin(global_invocation_id) id: vec3<u32>) {
+    let w = id.x;
+    let row = id.y;
+    if (w >= params.words || row >= params.rows) {
+        return;
+    }
+
+    let n = params.width;
+    let last = params.words - 1u;
+    let base = row * params.words;
+    let center = src[base + w];
+
+    // left[i] = cells[i - 1] and right[i] = cells[i + 1], wrapping at the edges
+    var left_carry = 0u;
+    if (w == 0u) {
+        left_carry = cell(base, n - 1u);
+    } else {
+        left_carry = src[base + w - 1u] >> 31u;
+    }
+    var right_carry = 0u;
+    if (w == last) {
+        right_carry = cell(base, 0u) << ((n - 1u) % 32u);
+    } else {
+        right_carry = src[base + w + 1u] << 31u;
+    }
+    let left = (center << 1u) | left_carry;
+    let right = (center >> 1u) | right_carry;
+
+    let rule = rules[row];
+    var next = 0u;
+    for (var index = 0u; index < 8u; index++) {
+        if (((rule >> index) & 1u) == 1u) {
+            next |= pick(left, (index >> 2u) & 1u) & pick(center, (index >> 1u) & 1u) & pick(right, index & 1u);
+        }
+    }
+
+    // Rules mapping 000 to 1 would otherwise set the bits past the last cell
+    if (w == last && n % 32u != 0u) {
+        next &= (1u << (n % 32u)) - 1u;
+    }
+    dst[base + w] = next;
+}
+
+// Histogram of the k-blocks (k = params.block, wrapping) starting at each cell,
+// leftmost cell as the most significant bit
+@compute @workgroup_size(64)
+fn blocks(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    let row = id.y;
+    if (i >= params.width || row >= params.rows) {
+        return;
+    }
+
+    let base = row * params.words;
+    var pattern = 0u;
+    for (var j = 0u; j < params.block; j++) {
+        pattern = (pattern << 1u) | cell(base, (i + j) % params.width);
+    }
+    atomicAdd(&counts[(row << params.block) + pattern], 1u);
+}
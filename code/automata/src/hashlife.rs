@@ -0,0 +1,360 @@
+//! Memoized block evolution (HashLife in one dimension) for huge time
+//! horizons
+//!
+//! A row is a binary tree of blocks: a leaf holds 16 cells and a node of
+//! level k two nodes of level k - 1, 16·2^k cells in all. Nodes are
+//! hash-consed, so equal blocks anywhere in the row, or at any time, are one
+//! node. The cells of a node determine its centre half a few steps on (the
+//! light cone eats r cells a side per step), and that result is memoized:
+//! from its two halves and the node straddling them, three results of
+//! level k - 1 half as far ahead, then two more from those, give the centre
+//! of level k twice as far on. A level-k result reaches 2^(b + k - 1)
+//! steps, where 2^b ≤ 8/r is what a leaf pair can take by plain stepping,
+//! and a smaller power of two is reached with centres in place of the
+//! first results.
+//!
+//! A ring of n cells is the periodic row it unrolls into, and `advance`
+//! takes a root big enough that its centre covers the ring and its result
+//! reaches the next power of two of t. The unrolled row has at most n
+//! distinct nodes a level, one per alignment, so it costs O(n log t) nodes
+//! to build, and a rule whose runs repeat in space and time — the regular
+//! ones, classes 1 and 2 — reaches generation 10^12 from results it has
+//! already met. Chaotic rules meet few blocks twice and fill the tables
+//! instead; past its limit of nodes (from the memory budget, if there is
+//! one), or a node for every leaf's width of cells plain stepping would
+//! update, `advance` stops at the last power of two it finished, for the
+//! caller to step the rest.
+
+use crate::evolve::TableRule;
+use crate::{budget, mix, profile, KeyHasher};
+use std::collections::HashMap;
+use std::hash::BuildHasherDefault;
+
+/// Node and result tables keyed by two numbers packed and mixed
+type Table = HashMap<u64, u32, BuildHasherDefault<KeyHasher>>;
+
+/// Cells in a leaf
+const LEAF: usize = 16;
+
+/// Bytes a node and its memoized result cost, with their table entries
+pub const NODE_BYTES: u64 = 64;
+
+/// Nodes and results kept without a memory budget (about 128 MB)
+pub const MAX_NODES: usize = 1 << 21;
+
+#[derive(Clone, Copy, Debug)]
+enum Node {
+    /// Sixteen cells, the leftmost in bit 0
+    Leaf(u16),
+    Pair { level: u8, left: u32, right: u32 },
+}
+
+/// The node limit was reached
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Exhausted;
+
+/// A table rule's block results, kept across runs and generations
+pub struct HashLife {
+    rule: TableRule,
+    /// log2 of the steps a level-1 result takes
+    base: u32,
+    nodes: Vec<Node>,
+    /// The leaf of each 16 cells, u32::MAX until made
+    leaves: Vec<u32>,
+    /// (left, right) -> their pair
+    pairs: Table,
+    /// (node, j) -> its centre half 2^j steps on
+    results: Table,
+    limit: usize,
+    /// The limit for this `advance`, no more than stepping would cost
+    cap: usize,
+    /// Result lookups during this `advance`, and how many were memoized
+    lookups: usize,
+    hits: usize,
+}
+
+impl HashLife {
+    /// Results of `rule`, up to what the memory budget holds
+    pub fn new(rule: TableRule) -> Self {
+        let limit = budget::share().map_or(MAX_NODES, |bytes| (bytes / NODE_BYTES) as usize);
+        Self::with_limit(rule, limit)
+    }
+
+    /// Results of `rule`, up to `limit` nodes and results together
+    pub fn with_limit(rule: TableRule, limit: usize) -> Self {
+        HashLife {
+            rule,
+            base: (LEAF / 2 / rule.radius).ilog2(),
+            nodes: Vec::new(),
+            leaves: vec![u32::MAX; 1 << LEAF],
+            pairs: Table::default(),
+            results: Table::default(),
+            limit,
+            cap: limit,
+            lookups: 0,
+            hits: 0,
+        }
+    }
+
+    /// Nodes and memoized results held
+    pub fn size(&self) -> (usize, usize) {
+        (self.nodes.len(), self.results.len())
+    }
+
+    /// The limit on nodes and results the last `advance` had
+    pub fn cap(&self) -> usize {
+        self.cap
+    }
+
+    /// The ring `cells` `t` generations on, and `t`; or, if the node limit
+    /// is reached first, the last generation it finished (the low bits of
+    /// `t` taken so far) and its row. The tables are emptied then, and kept
+    /// otherwise, for the next call. Its result lookups and memo hits go to
+    /// the `--profile` cache counters
+    pub fn advance(&mut self, cells: &[bool], t: u64) -> (Vec<bool>, u64) {
+        let mut row = cells.to_vec();
+        let mut done = 0;
+        if row.is_empty() {
+            return (row, t);
+        }
+        let stepping = (row.len() as u128 * t as u128 / LEAF as u128).max(1 << 16);
+        self.cap = self.limit.min(stepping.try_into().unwrap_or(usize::MAX));
+        (self.lookups, self.hits) = (0, 0);
+        for j in (0..64).filter(|&j| (t >> j) & 1 == 1) {
+            match self.jump(&row, j) {
+                Ok(next) => {
+                    row = next;
+                    done += 1 << j;
+                }
+                Err(Exhausted) => {
+                    let (cap, lookups, hits) = (self.cap, self.lookups, self.hits);
+                    *self = HashLife { cap, lookups, hits, ..Self::with_limit(self.rule, self.limit) };
+                    break;
+                }
+            }
+        }
+        profile::add_cache(self.lookups, self.hits);
+        (row, done)
+    }
+
+    /// The ring 2^j generations on
+    fn jump(&mut self, cells: &[bool], j: u32) -> Result<Vec<bool>, Exhausted> {
+        let n = cells.len();
+        // The root's centre, LEAF·2^(level-1) cells, covers the ring, and
+        // its result reaches 2^j steps
+        let mut level = 1;
+        while ((LEAF as u128) << (level - 1)) < n as u128 || self.reach(level) < j {
+            level += 1;
+        }
+        // Line cell p of the unrolled ring is ring cell (p + start) mod n,
+        // so the centre, from cell LEAF·2^level / 4, starts at cell 0
+        let quarter = (((LEAF as u128) << level) / 4 % n as u128) as usize;
+        let start = (n - quarter) % n;
+        let mut built = HashMap::new();
+        let root = self.build(cells, level, start, &mut built)?;
+        let centre = self.result(root, j)?;
+        let mut row = Vec::with_capacity(n);
+        self.read(centre, &mut row, n);
+        Ok(row)
+    }
+
+    /// log2 of the most steps a result of `level` takes
+    fn reach(&self, level: u8) -> u32 {
+        self.base + level as u32 - 1
+    }
+
+    fn level(&self, id: u32) -> u8 {
+        match self.nodes[id as usize] {
+            Node::Leaf(_) => 0,
+            Node::Pair { level, .. } => level,
+        }
+    }
+
+    fn children(&self, id: u32) -> (u32, u32) {
+        match self.nodes[id as usize] {
+            Node::Pair { left, right, .. } => (left, right),
+            Node::Leaf(_) => unreachable!("leaves have no children"),
+        }
+    }
+
+    fn bits(&self, id: u32) -> u16 {
+        match self.nodes[id as usize] {
+            Node::Leaf(bits) => bits,
+            Node::Pair { .. } => unreachable!("only leaves have bits"),
+        }
+    }
+
+    fn grow(&mut self, node: Node) -> Result<u32, Exhausted> {
+        if self.nodes.len() + self.results.len() >= self.cap {
+            return Err(Exhausted);
+        }
+        self.nodes.push(node);
+        Ok(self.nodes.len() as u32 - 1)
+    }
+
+    fn leaf(&mut self, bits: u16) -> Result<u32, Exhausted> {
+        if self.leaves[bits as usize] != u32::MAX {
+            return Ok(self.leaves[bits as usize]);
+        }
+        let id = self.grow(Node::Leaf(bits))?;
+        self.leaves[bits as usize] = id;
+        Ok(id)
+    }
+
+    fn pair(&mut self, left: u32, right: u32) -> Result<u32, Exhausted> {
+        let key = mix((left as u64) << 32 | right as u64);
+        if let Some(&id) = self.pairs.get(&key) {
+            return Ok(id);
+        }
+        let id = self.grow(Node::Pair { level: self.level(left) + 1, left, right })?;
+        self.pairs.insert(key, id);
+        Ok(id)
+    }
+
+    /// The node of `level` over the ring's cells from `start` on, wrapping
+    fn build(
+        &mut self,
+        cells: &[bool],
+        level: u8,
+        start: usize,
+        built: &mut HashMap<(u8, usize), u32>,
+    ) -> Result<u32, Exhausted> {
+        if let Some(&id) = built.get(&(level, start)) {
+            return Ok(id);
+        }
+        let n = cells.len();
+        let id = if level == 0 {
+            let bits = (0..LEAF).fold(0, |bits, i| bits | (cells[(start + i) % n] as u16) << i);
+            self.leaf(bits)?
+        } else {
+            let half = (((LEAF as u128) << (level - 1)) % n as u128) as usize;
+            let left = self.build(cells, level - 1, start, built)?;
+            let right = self.build(cells, level - 1, (start + half) % n, built)?;
+            self.pair(left, right)?
+        };
+        built.insert((level, start), id);
+        Ok(id)
+    }
+
+    /// The first `n` cells of `id` onto `row`
+    fn read(&self, id: u32, row: &mut Vec<bool>, n: usize) {
+        match self.nodes[id as usize] {
+            Node::Leaf(bits) => row.extend((0..LEAF).map(|i| (bits >> i) & 1 == 1).take(n - row.len())),
+            Node::Pair { left, right, .. } => {
+                self.read(left, row, n);
+                if row.len() < n {
+                    self.read(right, row, n);
+                }
+            }
+        }
+    }
+
+    /// The centre half of `id`, level 1 or more, where it is
+    fn centre(&mut self, id: u32) -> Result<u32, Exhausted> {
+        let (left, right) = self.children(id);
+        if self.level(id) == 1 {
+            let bits = (self.bits(left) as u32 | (self.bits(right) as u32) << LEAF) >> (LEAF / 2);
+            return self.leaf(bits as u16);
+        }
+        self.pair(self.children(left).1, self.children(right).0)
+    }
+
+    /// The centre half of `id`, level 1 or more, 2^j steps on, for j up to
+    /// `reach` of its level
+    fn result(&mut self, id: u32, j: u32) -> Result<u32, Exhausted> {
+        // Mixing is a bijection, so keys don't collide
+        let key = mix((id as u64) << 8 | j as u64);
+        self.lookups += 1;
+        if let Some(&result) = self.results.get(&key) {
+            self.hits += 1;
+            return Ok(result);
+        }
+        let level = self.level(id);
+        let result = if level == 1 {
+            self.step_leaves(id, 1 << j)?
+        } else {
+            let (left, right) = self.children(id);
+            let middle = self.pair(self.children(left).1, self.children(right).0)?;
+            // The three overlapping halves' centres, 2^(j-1) steps on for a
+            // full result, or as they are for a shorter one
+            let full = j == self.reach(level);
+            let mut stage = |node| if full { self.result(node, j - 1) } else { self.centre(node) };
+            let (a, b, c) = (stage(left)?, stage(middle)?, stage(right)?);
+            let (ab, bc) = (self.pair(a, b)?, self.pair(b, c)?);
+            let rest = if full { j - 1 } else { j };
+            let (left, right) = (self.result(ab, rest)?, self.result(bc, rest)?);
+            self.pair(left, right)?
+        };
+        if self.nodes.len() + self.results.len() >= self.cap {
+            return Err(Exhausted);
+        }
+        self.results.insert(key, result);
+        Ok(result)
+    }
+
+    /// The centre leaf of a pair of leaves `steps` on, by stepping a row that
+    /// shrinks by the radius each side
+    fn step_leaves(&mut self, id: u32, steps: usize) -> Result<u32, Exhausted> {
+        let (left, right) = self.children(id);
+        let bits = self.bits(left) as u32 | (self.bits(right) as u32) << LEAF;
+        let mut row: Vec<bool> = (0..2 * LEAF).map(|i| (bits >> i) & 1 == 1).collect();
+        let size = 2 * self.rule.radius + 1;
+        for _ in 0..steps {
+            row = row
+                .windows(size)
+                .map(|window| {
+                    let w = window.iter().fold(0, |w, &cell| (w << 1) | cell as usize);
+                    (self.rule.table >> w) & 1 == 1
+                })
+                .collect();
+        }
+        let from = LEAF / 2 - self.rule.radius * steps;
+        let bits = row[from..from + LEAF].iter().rev().fold(0, |bits, &cell| (bits << 1) | cell as u16);
+        self.leaf(bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stepped(rule: TableRule, cells: &[bool], t: u64) -> Vec<bool> {
+        (0..t).fold(cells.to_vec(), |row, _| rule.step(&row))
+    }
+
+    #[test]
+    fn test_advance_matches_stepping() {
+        for (i, &radius) in [1, 1, 2, 2, 3, 3].iter().enumerate() {
+            let rule = TableRule::random(radius, i as u64);
+            let mut hashlife = HashLife::new(rule);
+            for width in [1, 2, 5, 16, 37, 100] {
+                let cells: Vec<bool> = (0..width).map(|c| (c * 7 + i) % 5 < 2).collect();
+                for t in [0, 1, 6, 64, 301] {
+                    let (row, done) = hashlife.advance(&cells, t);
+                    assert_eq!(done, t);
+                    assert_eq!(row, stepped(rule, &cells, t), "{rule} on {width} cells, t={t}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_regular_rules_reach_huge_generations() {
+        // Rule 170 shifts the ring a cell left a step, so its period is the width
+        let shift = TableRule::new(1, 170);
+        let cells: Vec<bool> = (0..37).map(|c| c % 3 == 0 || c == 10).collect();
+        let t = 1_000_000_000_000;
+        let mut hashlife = HashLife::new(shift);
+        let (row, done) = hashlife.advance(&cells, t);
+        assert_eq!(done, t);
+        assert_eq!(row, (0..37).map(|c| cells[(c + (t % 37) as usize) % 37]).collect::<Vec<_>>());
+        assert!(hashlife.size().0 < 10_000, "{:?}", hashlife.size());
+
+        // Without room for the nodes it stops at a generation it finished
+        let chaotic = TableRule::new(1, 30);
+        let cells: Vec<bool> = (0..200).map(|c| c == 100).collect();
+        let (row, done) = HashLife::with_limit(chaotic, 2000).advance(&cells, 1 << 20 | 3);
+        assert!(done < 1 << 20);
+        assert_eq!(row, stepped(chaotic, &cells, done));
+    }
+}
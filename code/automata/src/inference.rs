@@ -0,0 +1,714 @@
+//! Learning rules from observed behavior
+//!
+//! A `TransitionDataset` simulates a set of trajectories once and stores
+//! every row, so the inference commands (rule, radius, dependency) and their
+//! generalization tests all work from the same observations instead of
+//! re-simulating them.
+//!
+//! Neighborhood counting is word-parallel: each row is packed into u64 words
+//! together with its left- and right-shifted copies, and the count for each
+//! of the 8 neighborhoods is a popcount over `L & C & R` masks.
+
+use crate::{apply_rule, ring_neighbors, tail_mask, Automaton};
+use std::collections::HashMap;
+
+/// The hand-rolled initial condition used throughout the inference experiments:
+/// cell i is live when `seed * (i + 1) mod 100 < density_percent`
+pub fn hashed_row(seed: usize, width: usize, density_percent: usize) -> Vec<bool> {
+    (0..width)
+        .map(|i| (seed.wrapping_mul(i + 1) % 100) < density_percent)
+        .collect()
+}
+
+/// SplitMix64: cheap, well-mixed bits for observation noise
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Pack cells into u64 words, cell i at bit i % 64 of word i / 64
+fn pack(cells: &[bool]) -> Vec<u64> {
+    cells
+        .chunks(64)
+        .map(|chunk| chunk.iter().rev().fold(0u64, |word, &c| (word << 1) | c as u64))
+        .collect()
+}
+
+/// A row prepared for word-parallel neighborhood counting
+#[derive(Clone)]
+struct PackedRow {
+    left: Vec<u64>,
+    center: Vec<u64>,
+    right: Vec<u64>,
+}
+
+impl PackedRow {
+    fn new(cells: &[bool]) -> Self {
+        let center = pack(cells);
+        let (left, right) = ring_neighbors(&center, cells.len());
+        Self { left, center, right }
+    }
+
+    /// Mask of cells whose neighborhood index (l c r as bits) is `index`
+    fn select(&self, w: usize, index: usize) -> u64 {
+        let pick = |words: &[u64], bit: usize| if bit == 1 { words[w] } else { !words[w] };
+        pick(&self.left, (index >> 2) & 1)
+            & pick(&self.center, (index >> 1) & 1)
+            & pick(&self.right, index & 1)
+    }
+}
+
+/// One simulated run: the true rows and what the observer saw of each step
+struct Trajectory {
+    /// rows[0] is the initial condition; rows[t + 1] = step(rows[t])
+    rows: Vec<Vec<bool>>,
+    /// observed[t] is rows[t + 1] with observation noise applied
+    /// (None when noise-free: the observer sees the true rows)
+    observed: Option<Vec<Vec<bool>>>,
+}
+
+impl Trajectory {
+    fn observed(&self) -> &[Vec<bool>] {
+        self.observed.as_deref().unwrap_or(&self.rows[1..])
+    }
+}
+
+/// Per-neighborhood outcome tallies: for each of the 8 neighborhoods, how
+/// often it was seen and how often the next state was 1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NeighborhoodCounts {
+    pub observations: [usize; 8],
+    pub ones: [usize; 8],
+}
+
+impl NeighborhoodCounts {
+    /// Fraction of 1 outcomes, or 0.5 when the neighborhood was never seen
+    pub fn p_one(&self, index: usize) -> f64 {
+        if self.observations[index] > 0 {
+            self.ones[index] as f64 / self.observations[index] as f64
+        } else {
+            0.5
+        }
+    }
+
+    /// Majority outcome, or None when the neighborhood was never seen
+    pub fn majority(&self, index: usize) -> Option<bool> {
+        let count = self.observations[index];
+        (count > 0).then(|| self.ones[index] > count - self.ones[index])
+    }
+
+    /// Posterior probability (uniform prior on P(1)) that the majority
+    /// outcome is the neighborhood's true output; 0.5 when never seen
+    pub fn majority_confidence(&self, index: usize) -> f64 {
+        let (n, ones) = (self.observations[index], self.ones[index]);
+        // P(p > 1/2) under Beta(ones + 1, zeros + 1) is a binomial tail
+        let p_one = binomial_upper_tail(ones + 1, n + 1, 0.5);
+        p_one.max(1.0 - p_one)
+    }
+
+    /// Majority vote for each neighborhood
+    pub fn infer_rule(&self) -> u8 {
+        (0..8).filter(|&i| self.p_one(i) > 0.5).fold(0u8, |rule, i| rule | (1 << i))
+    }
+
+    /// Which of (left, center, right) significantly change the outcome in
+    /// some context: Fisher p below `alpha`, Bonferroni-corrected over the
+    /// four contexts of each position
+    pub fn dependencies(&self, alpha: f64) -> [bool; 3] {
+        [2, 1, 0].map(|bit| self.position_tests(bit).iter().any(|test| test.p_value < alpha / 4.0))
+    }
+
+    /// Test whether the cell at `bit` of the neighborhood index (2 = left,
+    /// 1 = center, 0 = right) changes the outcome, once for each of the four
+    /// settings of the other two cells (in order 00, 01, 10, 11)
+    pub fn position_tests(&self, bit: usize) -> [PositionTest; 4] {
+        std::array::from_fn(|context| {
+            // Spread the two context bits around the tested bit
+            let low = context & ((1 << bit) - 1);
+            let high = (context >> bit) << (bit + 1);
+            let dead = high | low;
+            let live = dead | (1 << bit);
+            PositionTest::new(
+                (self.observations[dead], self.ones[dead]),
+                (self.observations[live], self.ones[live]),
+            )
+        })
+    }
+}
+
+/// Which of (left, center, right) can change a rule's output, read off its
+/// table: the ground truth for dependency inference
+pub fn rule_dependencies(rule: u8) -> [bool; 3] {
+    [2, 1, 0].map(|bit| {
+        (0..8u8)
+            .filter(|index| index & (1 << bit) == 0)
+            .any(|dead| (rule >> dead) & 1 != (rule >> (dead | 1 << bit)) & 1)
+    })
+}
+
+/// Whether one neighborhood cell changes the outcome, with the other two
+/// cells held fixed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionTest {
+    /// (observations, ones) with the tested cell dead
+    pub dead: (usize, usize),
+    /// (observations, ones) with the tested cell live
+    pub live: (usize, usize),
+    /// Two-sided Fisher exact p-value for "the cell makes no difference"
+    pub p_value: f64,
+}
+
+impl PositionTest {
+    /// Observations needed on each side before a test is worth reading: with
+    /// fewer, even a perfect split can't reach small p-values
+    pub const MIN_SAMPLES: usize = 10;
+
+    fn new(dead: (usize, usize), live: (usize, usize)) -> Self {
+        let p_value = fisher_exact([
+            [dead.1, dead.0 - dead.1],
+            [live.1, live.0 - live.1],
+        ]);
+        Self { dead, live, p_value }
+    }
+
+    /// Both sides have at least `MIN_SAMPLES` observations
+    pub fn enough_samples(&self) -> bool {
+        self.dead.0.min(self.live.0) >= Self::MIN_SAMPLES
+    }
+}
+
+/// Two-sided Fisher exact test on a 2x2 contingency table: the probability,
+/// with margins fixed, of a table no more likely than the one observed
+pub fn fisher_exact(table: [[usize; 2]; 2]) -> f64 {
+    let [[a, b], [c, d]] = table;
+    let (row, col, n) = (a + b, a + c, a + b + c + d);
+    let ln_choose = ln_choose_table(n);
+
+    // Hypergeometric probability of x in the top-left cell
+    let probability =
+        |x: usize| (ln_choose(row, x) + ln_choose(n - row, col - x) - ln_choose(n, col)).exp();
+
+    let observed = probability(a);
+    let p: f64 = (col.saturating_sub(n - row)..=row.min(col))
+        .map(probability)
+        .filter(|&p| p <= observed * (1.0 + 1e-7))
+        .sum();
+    p.min(1.0)
+}
+
+/// One-sided binomial test: P(X >= k) for X ~ Binomial(n, p)
+pub fn binomial_upper_tail(k: usize, n: usize, p: f64) -> f64 {
+    if k == 0 {
+        return 1.0;
+    }
+    if p <= 0.0 {
+        return 0.0;
+    }
+    if p >= 1.0 {
+        return 1.0;
+    }
+    let ln_choose = ln_choose_table(n);
+    let tail: f64 = (k..=n)
+        .map(|x| (ln_choose(n, x) + x as f64 * p.ln() + (n - x) as f64 * (1.0 - p).ln()).exp())
+        .sum();
+    tail.min(1.0)
+}
+
+/// 95% Wilson score interval for a binomial proportion
+pub fn wilson_interval(successes: usize, n: usize) -> (f64, f64) {
+    let z = 1.96;
+    let (k, n) = (successes as f64, n as f64);
+    let p = k / n;
+    let center = (p + z * z / (2.0 * n)) / (1.0 + z * z / n);
+    let half = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt() / (1.0 + z * z / n);
+    ((center - half).max(0.0), (center + half).min(1.0))
+}
+
+/// Exact two-sided McNemar test for paired outcomes: the probability of a
+/// split of the `b + c` discordant pairs at least as lopsided as (b, c)
+/// if either side were equally likely to win each one
+pub fn mcnemar_exact(b: usize, c: usize) -> f64 {
+    if b == c {
+        return 1.0;
+    }
+    (2.0 * binomial_upper_tail(b.max(c), b + c, 0.5)).min(1.0)
+}
+
+/// ln C(n, k) for all n up to `max`, from a table of log factorials
+fn ln_choose_table(max: usize) -> impl Fn(usize, usize) -> f64 {
+    let ln_factorial: Vec<f64> = (0..=max)
+        .scan(0.0, |acc, i| {
+            if i > 0 {
+                *acc += (i as f64).ln();
+            }
+            Some(*acc)
+        })
+        .collect();
+    move |n, k| ln_factorial[n] - ln_factorial[k] - ln_factorial[n - k]
+}
+
+/// Row transitions collected from simulations of a rule
+pub struct TransitionDataset {
+    width: usize,
+    trajectories: Vec<Trajectory>,
+}
+
+impl TransitionDataset {
+    /// Simulate `generations` steps of `rule` from each initial condition,
+    /// flipping each observed outcome with probability `noise`
+    pub fn simulate(
+        rule: u8,
+        generations: usize,
+        initial_rows: impl IntoIterator<Item = Vec<bool>>,
+        noise: f64,
+        noise_seed: u64,
+    ) -> Self {
+        let mut width = 0;
+        let mut trajectories = Vec::new();
+
+        for (trial, cells) in initial_rows.into_iter().enumerate() {
+            width = cells.len();
+            let mut ca = Automaton::from_cells(cells, rule);
+            let mut rows = Vec::with_capacity(generations + 1);
+            rows.push(ca.cells());
+            for _ in 0..generations {
+                ca.step();
+                rows.push(ca.cells());
+            }
+
+            let observed = (noise > 0.0).then(|| {
+                rows[1..]
+                    .iter()
+                    .enumerate()
+                    .map(|(t, row)| {
+                        row.iter()
+                            .enumerate()
+                            .map(|(i, &cell)| {
+                                let key = noise_seed ^ ((trial as u64) << 48) ^ ((t as u64) << 24) ^ i as u64;
+                                let u = (splitmix64(key) >> 11) as f64 / (1u64 << 53) as f64;
+                                cell ^ (u < noise)
+                            })
+                            .collect()
+                    })
+                    .collect()
+            });
+
+            trajectories.push(Trajectory { rows, observed });
+        }
+
+        Self { width, trajectories }
+    }
+
+    /// Observed spacetime diagrams (one row per generation, all the same
+    /// width) as a dataset; any noise is already in the rows
+    pub fn from_trajectories(trajectories: Vec<Vec<Vec<bool>>>) -> Self {
+        let width = trajectories.first().and_then(|rows| rows.first()).map_or(0, Vec::len);
+        assert!(
+            trajectories.iter().flatten().all(|row| row.len() == width),
+            "all rows must have the same width"
+        );
+        let trajectories = trajectories
+            .into_iter()
+            .filter(|rows| !rows.is_empty())
+            .map(|rows| Trajectory { rows, observed: None })
+            .collect();
+        Self { width, trajectories }
+    }
+
+    /// The standard training set: `trials` hashed rows at 50% density
+    pub fn training(rule: u8, width: usize, generations: usize, trials: usize, noise: f64) -> Self {
+        let rows = (0..trials).map(|trial| hashed_row(trial * 12345 + 67890, width, 50));
+        Self::simulate(rule, generations, rows, noise, 0x5EED)
+    }
+
+    /// The first `transitions` row transitions, in trajectory order, so a
+    /// growing budget sees a growing prefix of the same observations
+    pub fn truncated(&self, transitions: usize) -> Self {
+        let mut remaining = transitions;
+        let mut trajectories = Vec::new();
+        for trajectory in &self.trajectories {
+            if remaining == 0 {
+                break;
+            }
+            let steps = (trajectory.rows.len() - 1).min(remaining);
+            remaining -= steps;
+            trajectories.push(Trajectory {
+                rows: trajectory.rows[..=steps].to_vec(),
+                observed: trajectory.observed.as_ref().map(|observed| observed[..steps].to_vec()),
+            });
+        }
+        Self { width: self.width, trajectories }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of row transitions
+    pub fn len(&self) -> usize {
+        self.trajectories.iter().map(|t| t.rows.len() - 1).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// (before, observed after) pairs
+    pub fn transitions(&self) -> impl Iterator<Item = (&[bool], &[bool])> {
+        self.trajectories.iter().flat_map(|t| {
+            t.rows.iter().zip(t.observed()).map(|(b, a)| (b.as_slice(), a.as_slice()))
+        })
+    }
+
+    /// Tally outcomes per radius-1 neighborhood, 64 cells at a time
+    pub fn neighborhood_counts(&self) -> NeighborhoodCounts {
+        count_neighborhoods(self.transitions())
+    }
+
+    /// k-fold cross-validation of the local learner and the density baseline:
+    /// transitions are split into `folds` contiguous blocks, each learner is
+    /// trained on all but one block and scored on one-step prediction of the
+    /// held-out block's observed outcomes
+    pub fn cross_validate(&self, folds: usize) -> CrossValidation {
+        let transitions: Vec<(&[bool], &[bool])> = self.transitions().collect();
+        let n = transitions.len();
+        let folds = folds.clamp(2, n.max(2));
+        let fold_of = |i: usize| i * folds / n;
+
+        let mut result = CrossValidation { local: Vec::new(), baseline: Vec::new() };
+        for fold in 0..folds {
+            let train = || transitions.iter().enumerate().filter(move |(i, _)| fold_of(*i) != fold).map(|(_, &t)| t);
+            let test = || transitions.iter().enumerate().filter(move |(i, _)| fold_of(*i) == fold).map(|(_, &t)| t);
+
+            let rule = count_neighborhoods(train()).infer_rule();
+            let (errors, total) = one_step_errors(rule, test());
+            if total == 0 {
+                continue;
+            }
+            result.local.push(1.0 - errors as f64 / total as f64);
+
+            let (errors, total) = DensityBaseline::fit(train()).one_step_errors(test());
+            result.baseline.push(1.0 - errors as f64 / total as f64);
+        }
+
+        result
+    }
+
+    /// Tally outcomes per window of radius r: pattern bits are the window's
+    /// cells, leftmost first -> (count of 0 outcomes, count of 1 outcomes)
+    pub fn window_counts(&self, r: usize) -> HashMap<u64, (usize, usize)> {
+        let window_size = 2 * r + 1;
+        let mut mapping: HashMap<u64, (usize, usize)> = HashMap::new();
+
+        for (before, after) in self.transitions() {
+            let n = before.len();
+            // Slide the window: shift in the new right cell, mask off the old left
+            let mask = if window_size >= 64 { !0 } else { (1u64 << window_size) - 1 };
+            let mut pattern = 0u64;
+            for j in 0..window_size {
+                pattern = (pattern << 1) | before[(n - r % n + j) % n] as u64;
+            }
+            for i in 0..n {
+                let entry = mapping.entry(pattern & mask).or_insert((0, 0));
+                if after[i] {
+                    entry.1 += 1;
+                } else {
+                    entry.0 += 1;
+                }
+                pattern = (pattern << 1) | before[(i + r + 1) % n] as u64;
+            }
+        }
+
+        mapping
+    }
+
+    /// Whether every window of radius r maps to a single outcome
+    pub fn consistent_at_radius(&self, r: usize) -> bool {
+        self.window_counts(r).values().all(|&(zeros, ones)| zeros == 0 || ones == 0)
+    }
+
+    /// Observations a majority vote per radius-r window gets wrong:
+    /// (minority outcomes, total observations). Zero exactly when consistent.
+    pub fn majority_errors(&self, r: usize) -> (usize, usize) {
+        self.window_counts(r)
+            .values()
+            .fold((0, 0), |(errors, total), &(zeros, ones)| (errors + zeros.min(ones), total + zeros + ones))
+    }
+
+    /// Smallest radius up to `max_radius` whose majority-vote error fraction
+    /// is at most `tolerance` (0 for exact consistency)
+    pub fn min_radius(&self, max_radius: usize, tolerance: f64) -> Option<usize> {
+        (0..=max_radius).find(|&r| {
+            let (errors, total) = self.majority_errors(r);
+            errors as f64 <= tolerance * total as f64
+        })
+    }
+
+    /// Run `rule` freely from each trajectory's initial row and count the
+    /// cells where it diverges from the true trajectory: (errors, total)
+    pub fn free_run_errors(&self, rule: u8) -> (usize, usize) {
+        let mut errors = 0;
+        let mut total = 0;
+        for trajectory in &self.trajectories {
+            let mut ca = Automaton::from_cells(trajectory.rows[0].clone(), rule);
+            for truth in &trajectory.rows[1..] {
+                ca.step();
+                total += truth.len();
+                errors += ca.iter().zip(truth).filter(|&(a, &b)| a != b).count();
+            }
+        }
+        (errors, total)
+    }
+}
+
+/// Tally outcomes per radius-1 neighborhood over (before, after) row pairs
+fn count_neighborhoods<'a>(
+    transitions: impl Iterator<Item = (&'a [bool], &'a [bool])>,
+) -> NeighborhoodCounts {
+    let mut counts = NeighborhoodCounts { observations: [0; 8], ones: [0; 8] };
+
+    for (before, after) in transitions {
+        let row = PackedRow::new(before);
+        let out = pack(after);
+        let last = out.len() - 1;
+        let tail = tail_mask(before.len());
+
+        for w in 0..out.len() {
+            let valid = if w == last { tail } else { !0 };
+            for index in 0..8 {
+                let mask = row.select(w, index) & valid;
+                counts.observations[index] += mask.count_ones() as usize;
+                counts.ones[index] += (mask & out[w]).count_ones() as usize;
+            }
+        }
+    }
+
+    counts
+}
+
+/// Cells where one application of `rule` disagrees with the observed next
+/// row: (errors, total)
+fn one_step_errors<'a>(
+    rule: u8,
+    transitions: impl Iterator<Item = (&'a [bool], &'a [bool])>,
+) -> (usize, usize) {
+    let mut errors = 0;
+    let mut total = 0;
+
+    for (before, after) in transitions {
+        let row = PackedRow::new(before);
+        let out = pack(after);
+        let last = out.len() - 1;
+        let tail = tail_mask(before.len());
+
+        for w in 0..out.len() {
+            let predicted = apply_rule(rule, row.left[w], row.center[w], row.right[w]);
+            let valid = if w == last { tail } else { !0 };
+            errors += ((predicted ^ out[w]) & valid).count_ones() as usize;
+        }
+        total += before.len();
+    }
+
+    (errors, total)
+}
+
+/// Held-out one-step accuracy per fold for each learner
+#[derive(Debug)]
+pub struct CrossValidation {
+    pub local: Vec<f64>,
+    pub baseline: Vec<f64>,
+}
+
+/// (mean, standard deviation) of per-fold scores
+pub fn mean_std(scores: &[f64]) -> (f64, f64) {
+    let n = scores.len() as f64;
+    let mean = scores.iter().sum::<f64>() / n;
+    let variance = scores.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+    (mean, variance.sqrt())
+}
+
+/// A correlational learner that sees only global features: P(next = 1 |
+/// current cell, row density decile). It captures statistics of the training
+/// distribution but not the local mechanism.
+pub struct DensityBaseline {
+    counts: [[usize; 2]; 10],
+    ones: [[usize; 2]; 10],
+}
+
+impl DensityBaseline {
+    fn bucket(row: &[bool]) -> usize {
+        let density = row.iter().filter(|&&c| c).count() as f64 / row.len() as f64;
+        ((density * 10.0) as usize).min(9)
+    }
+
+    /// Fit on the observed transitions (the same data the local learner sees)
+    pub fn train(data: &TransitionDataset) -> Self {
+        Self::fit(data.transitions())
+    }
+
+    fn fit<'a>(transitions: impl Iterator<Item = (&'a [bool], &'a [bool])>) -> Self {
+        let mut counts = [[0; 2]; 10];
+        let mut ones = [[0; 2]; 10];
+        for (before, after) in transitions {
+            let bucket = Self::bucket(before);
+            for (&curr, &next) in before.iter().zip(after) {
+                counts[bucket][curr as usize] += 1;
+                if next {
+                    ones[bucket][curr as usize] += 1;
+                }
+            }
+        }
+        Self { counts, ones }
+    }
+
+    fn predict(&self, bucket: usize, curr: bool) -> bool {
+        let count = self.counts[bucket][curr as usize];
+        let ones = self.ones[bucket][curr as usize];
+        count > 0 && ones > count / 2
+    }
+
+    /// One-step prediction errors against observed rows: (errors, total)
+    fn one_step_errors<'a>(
+        &self,
+        transitions: impl Iterator<Item = (&'a [bool], &'a [bool])>,
+    ) -> (usize, usize) {
+        let mut errors = 0;
+        let mut total = 0;
+        for (before, after) in transitions {
+            let bucket = Self::bucket(before);
+            for (&curr, &next) in before.iter().zip(after) {
+                total += 1;
+                if self.predict(bucket, curr) != next {
+                    errors += 1;
+                }
+            }
+        }
+        (errors, total)
+    }
+
+    /// One-step prediction errors against the true next rows: (errors, total)
+    pub fn errors(&self, data: &TransitionDataset) -> (usize, usize) {
+        let mut errors = 0;
+        let mut total = 0;
+        for trajectory in &data.trajectories {
+            for pair in trajectory.rows.windows(2) {
+                let bucket = Self::bucket(&pair[0]);
+                for (&curr, &next) in pair[0].iter().zip(&pair[1]) {
+                    total += 1;
+                    if self.predict(bucket, curr) != next {
+                        errors += 1;
+                    }
+                }
+            }
+        }
+        (errors, total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packed_counts_match_naive() {
+        // Widths straddling word boundaries
+        for width in [5, 63, 64, 65, 130] {
+            let data = TransitionDataset::training(110, width, 7, 3, 0.0);
+            let counts = data.neighborhood_counts();
+
+            let mut naive = NeighborhoodCounts { observations: [0; 8], ones: [0; 8] };
+            for (before, after) in data.transitions() {
+                let n = before.len();
+                for i in 0..n {
+                    let index = (before[(i + n - 1) % n] as usize) << 2
+                        | (before[i] as usize) << 1
+                        | before[(i + 1) % n] as usize;
+                    naive.observations[index] += 1;
+                    naive.ones[index] += after[i] as usize;
+                }
+            }
+            assert_eq!(counts, naive, "width {width}");
+        }
+    }
+
+    #[test]
+    fn test_cross_validation_scores() {
+        // Noise-free: the local learner predicts held-out steps perfectly
+        let data = TransitionDataset::training(110, 50, 20, 10, 0.0);
+        let cv = data.cross_validate(5);
+        assert_eq!(cv.local.len(), 5);
+        assert!(cv.local.iter().all(|&acc| acc == 1.0));
+        assert!(mean_std(&cv.baseline).0 < 1.0);
+
+        // With noise, held-out accuracy is capped near 1 - noise
+        let noisy = TransitionDataset::training(110, 50, 20, 10, 0.1);
+        let (mean, _) = mean_std(&noisy.cross_validate(5).local);
+        assert!((mean - 0.9).abs() < 0.02, "mean {mean}");
+    }
+
+    #[test]
+    fn test_noise_free_inference_recovers_rule() {
+        for rule in [30, 90, 110, 184] {
+            let data = TransitionDataset::training(rule, 50, 20, 10, 0.0);
+            assert_eq!(data.neighborhood_counts().infer_rule(), rule);
+            assert!(data.consistent_at_radius(1));
+            assert_eq!(data.free_run_errors(rule).0, 0);
+        }
+    }
+
+    #[test]
+    fn test_position_tests() {
+        // Textbook table: two-sided p = 0.002759
+        assert!((fisher_exact([[1, 9], [11, 3]]) - 0.002759).abs() < 1e-6);
+        assert_eq!(fisher_exact([[0, 0], [0, 0]]), 1.0);
+        // Paired version: all 6 discordant pairs one way, 2 * 0.5^6
+        assert!((mcnemar_exact(6, 0) - 0.03125).abs() < 1e-12);
+        assert_eq!(mcnemar_exact(3, 3), 1.0);
+
+        // Rule 90 (left XOR right): the center never matters, the others always do
+        let counts = TransitionDataset::training(90, 50, 30, 10, 0.0).neighborhood_counts();
+        assert!(counts.position_tests(1).iter().all(|t| t.p_value > 0.5));
+        for bit in [0, 2] {
+            assert!(counts.position_tests(bit).iter().all(|t| t.enough_samples() && t.p_value < 1e-6));
+        }
+    }
+
+    #[test]
+    fn test_noise_tolerant_radius() {
+        // Binomial(2, 0.5): P(X >= 1) = 0.75
+        assert!((binomial_upper_tail(1, 2, 0.5) - 0.75).abs() < 1e-12);
+
+        // 5% noise makes every radius inconsistent; a tolerance above the
+        // noise rate recovers radius 1 (radius 0 still errs ~50% for rule 110)
+        let noisy = TransitionDataset::training(110, 50, 20, 10, 0.05);
+        assert_eq!(noisy.min_radius(3, 0.0), None);
+        assert_eq!(noisy.min_radius(3, 0.08), Some(1));
+
+        let clean = TransitionDataset::training(110, 50, 20, 10, 0.0);
+        assert_eq!(clean.min_radius(3, 0.0), Some(1));
+    }
+
+    #[test]
+    fn test_truncated_prefix_and_dependencies() {
+        let data = TransitionDataset::training(90, 30, 10, 4, 0.1);
+        assert_eq!(data.truncated(0).len(), 0);
+        assert_eq!(data.truncated(15).len(), 15);
+        assert_eq!(data.truncated(1000).len(), data.len());
+        let prefix: Vec<_> = data.truncated(15).transitions().map(|(b, a)| (b.to_vec(), a.to_vec())).collect();
+        let full: Vec<_> = data.transitions().take(15).map(|(b, a)| (b.to_vec(), a.to_vec())).collect();
+        assert_eq!(prefix, full);
+
+        // The same rows read back as observations give the same counts
+        let clean = TransitionDataset::training(90, 30, 10, 4, 0.0);
+        let rows = clean.trajectories.iter().map(|t| t.rows.clone()).collect();
+        assert_eq!(TransitionDataset::from_trajectories(rows).neighborhood_counts(), clean.neighborhood_counts());
+
+        assert_eq!(rule_dependencies(90), [true, false, true]);
+        assert_eq!(rule_dependencies(204), [false, true, false]);
+        assert_eq!(rule_dependencies(0), [false; 3]);
+        let larger = TransitionDataset::training(90, 50, 30, 10, 0.0);
+        assert_eq!(larger.neighborhood_counts().dependencies(0.01), rule_dependencies(90));
+    }
+}
@@ -0,0 +1,188 @@
+//! Coupled map lattices: a ring of real-valued cells, each pushed through a
+//! local map and diffusively coupled to its neighbors (Kaneko's CML):
+//!
+//!   x'_i = (1 - ε) f(x_i) + ε/2 (f(x_{i-1}) + f(x_{i+1}))
+//!
+//! The maps here take [0, 1] to itself, and so does the coupling, so a row
+//! bins into `bins` equal-width symbols. With two bins, x >= 1/2 counts as
+//! live, and a lattice's block entropy lands on the same scale as an
+//! automaton's: continuous spatiotemporal chaos measured like the discrete
+//! kind.
+
+use crate::{shannon_entropy, Automaton};
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::fmt;
+
+/// The map each cell goes through before coupling
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LocalMap {
+    /// x -> r x (1 - x), r in [0, 4]: chaotic for most r above 3.57
+    Logistic(f64),
+    /// x -> mu min(x, 1 - x), mu in [0, 2]: chaotic for mu above 1. At
+    /// mu = 2 each step shifts a bit out of the float, so an uncoupled cell
+    /// reaches 0 within about 55 steps; coupling keeps refilling them
+    Tent(f64),
+}
+
+impl LocalMap {
+    pub fn apply(self, x: f64) -> f64 {
+        match self {
+            LocalMap::Logistic(r) => r * x * (1.0 - x),
+            LocalMap::Tent(mu) => mu * x.min(1.0 - x),
+        }
+    }
+}
+
+impl std::str::FromStr for LocalMap {
+    type Err = String;
+
+    /// `logistic:<r>` or `tent:<mu>`; on its own, the fully chaotic one
+    /// (r = 4, mu = 2)
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (name, parameter) = s.split_once(':').map_or((s, None), |(name, p)| (name, Some(p)));
+        let parameter = |default: f64, max: f64| -> Result<f64, String> {
+            let value = match parameter {
+                None => default,
+                Some(p) => p.parse().map_err(|_| format!("'{p}' is not a number"))?,
+            };
+            if !(0.0..=max).contains(&value) {
+                return Err(format!("{name}'s parameter must be 0 to {max} to keep cells in [0, 1]"));
+            }
+            Ok(value)
+        };
+        match name {
+            "logistic" => Ok(LocalMap::Logistic(parameter(4.0, 4.0)?)),
+            "tent" => Ok(LocalMap::Tent(parameter(2.0, 2.0)?)),
+            _ => Err(format!("unknown map '{name}': expected logistic:<r> or tent:<mu>")),
+        }
+    }
+}
+
+impl fmt::Display for LocalMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocalMap::Logistic(r) => write!(f, "logistic:{r}"),
+            LocalMap::Tent(mu) => write!(f, "tent:{mu}"),
+        }
+    }
+}
+
+/// A ring of cells in [0, 1] under a local map and nearest-neighbor coupling
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lattice {
+    cells: Vec<f64>,
+    map: LocalMap,
+    /// ε: how much of each cell's next value comes from its neighbors
+    coupling: f64,
+}
+
+impl Lattice {
+    /// Start from `cells`; panics on an empty ring, a cell outside [0, 1] or
+    /// a coupling outside it
+    pub fn new(cells: Vec<f64>, map: LocalMap, coupling: f64) -> Self {
+        assert!(!cells.is_empty(), "a lattice needs a cell");
+        assert!(cells.iter().all(|x| (0.0..=1.0).contains(x)), "cells must be in [0, 1]");
+        assert!((0.0..=1.0).contains(&coupling), "coupling must be in [0, 1]");
+        Self { cells, map, coupling }
+    }
+
+    /// `width` cells drawn uniformly from [0, 1) by a ChaCha8 stream seeded
+    /// with `seed`
+    pub fn random(width: usize, map: LocalMap, coupling: f64, seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        Self::new((0..width).map(|_| rng.random()).collect(), map, coupling)
+    }
+
+    pub fn cells(&self) -> &[f64] {
+        &self.cells
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn map(&self) -> LocalMap {
+        self.map
+    }
+
+    pub fn coupling(&self) -> f64 {
+        self.coupling
+    }
+
+    pub fn step(&mut self) {
+        let n = self.cells.len();
+        let mapped: Vec<f64> = self.cells.iter().map(|&x| self.map.apply(x)).collect();
+        let e = self.coupling;
+        for i in 0..n {
+            let neighbors = mapped[(i + n - 1) % n] + mapped[(i + 1) % n];
+            // Rounding can step a hair outside [0, 1]
+            self.cells[i] = ((1.0 - e) * mapped[i] + e / 2.0 * neighbors).clamp(0.0, 1.0);
+        }
+    }
+
+    /// Each cell's bin, 0 to `bins` - 1, of `bins` equal widths over [0, 1]
+    pub fn symbols(&self, bins: usize) -> Vec<u8> {
+        assert!((2..=256).contains(&bins), "bins must be 2 to 256");
+        self.cells.iter().map(|&x| ((x * bins as f64) as usize).min(bins - 1) as u8).collect()
+    }
+
+    /// Cells over 1/2 live, as an automaton under `rule`, to run the
+    /// discrete counterpart from the same row
+    pub fn binarized(&self, rule: u8) -> Automaton {
+        Automaton::from_cells(self.symbols(2).iter().map(|&s| s == 1).collect(), rule)
+    }
+
+    /// Shannon entropy in bits of the row's blocks of `k` symbols (with
+    /// wraparound) after binning into `bins`; at most k log2(bins), and with
+    /// two bins the `Automaton::block_entropy` of `binarized`
+    pub fn block_entropy(&self, bins: usize, k: usize) -> f64 {
+        let n = self.width();
+        assert!(k >= 1 && k <= n, "blocks must be 1 to {n} cells");
+        assert!((k as f64) * (bins as f64).log2() <= 64.0, "{k}-blocks of {bins} symbols don't fit a u64");
+        let symbols = self.symbols(bins);
+        let mut patterns: Vec<u64> = (0..n)
+            .map(|i| (0..k).fold(0u64, |pattern, j| pattern * bins as u64 + symbols[(i + j) % n] as u64))
+            .collect();
+        patterns.sort_unstable();
+        shannon_entropy(&patterns.chunk_by(|a, b| a == b).map(<[u64]>::len).collect::<Vec<_>>())
+    }
+
+    /// Mean of the cells
+    pub fn mean(&self) -> f64 {
+        self.cells.iter().sum::<f64>() / self.width() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coupled_logistic_maps() {
+        // Uncoupled, each cell follows its own map
+        let mut lattice = Lattice::new(vec![0.1, 0.5, 0.9], LocalMap::Logistic(4.0), 0.0);
+        lattice.step();
+        let expected = [0.36, 1.0, 0.36];
+        assert!(lattice.cells().iter().zip(expected).all(|(x, e)| (x - e).abs() < 1e-12), "{:?}", lattice.cells());
+        // Fully coupled, each cell is the mean of its neighbors' images
+        let mut lattice = Lattice::new(vec![0.1, 0.5, 0.9], LocalMap::Tent(2.0), 1.0);
+        lattice.step();
+        assert!((lattice.cells()[1] - 0.2).abs() < 1e-12);
+
+        // Two bins measure what the thresholded automaton does
+        let lattice = Lattice::random(200, "logistic:3.9".parse().unwrap(), 0.3, 1);
+        assert_eq!(lattice.block_entropy(2, 5), lattice.binarized(30).block_entropy(5));
+        let entropy = lattice.block_entropy(4, 3);
+        assert!(entropy > 0.0 && entropy <= 6.0, "{entropy}");
+
+        // A fixed point of the map, everywhere, is a fixed point of the lattice
+        let mut still = Lattice::new(vec![0.75; 8], LocalMap::Logistic(4.0), 0.4);
+        (0..10).for_each(|_| still.step());
+        assert!(still.cells().iter().all(|&x| (x - 0.75).abs() < 1e-9));
+        assert_eq!(still.block_entropy(8, 2), 0.0);
+
+        assert!("tent:2.5".parse::<LocalMap>().is_err() && "sine".parse::<LocalMap>().is_err());
+        assert_eq!("tent".parse::<LocalMap>(), Ok(LocalMap::Tent(2.0)));
+    }
+}
@@ -0,0 +1,167 @@
+//! Generations as frames for physical displays: LED strips and matrices
+//! driven over a serial line, or anything else that takes bytes
+//!
+//! A `Scroller` keeps the last `rows` generations as a `columns` x `rows`
+//! grid, newest at the bottom, so a matrix shows the diagram scrolling up
+//! and a one-row strip shows the current generation. Rows wider than the
+//! display are cut to its first `columns` cells, narrower ones padded dead.
+//! Matrices wired back and forth (serpentine, as most WS2812 panels are)
+//! get every other row reversed.
+//!
+//! Each frame is the grid in wiring order as packed bits (each row padded
+//! to a whole byte, most significant bit first), one 0/1 byte per pixel,
+//! or three RGB bytes per pixel; with Adalight framing it is preceded by
+//! the `Ada` header that the common Arduino LED-strip sketches sync on.
+//! A `FrameSink` takes the finished frames: any `Write` is one, and other
+//! transports implement it directly.
+
+use crate::Automaton;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+/// Where frames go, one whole frame per call
+pub trait FrameSink {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()>;
+}
+
+/// A serial port, file or pipe: each frame written and flushed
+impl<W: Write> FrameSink for W {
+    fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        self.write_all(frame)?;
+        self.flush()
+    }
+}
+
+/// How each pixel is encoded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// One bit per pixel, live 1, each row padded to a whole byte
+    Bits,
+    /// One byte per pixel: 1 live, 0 dead
+    Bytes,
+    /// Three bytes per pixel, in these colors
+    Rgb { live: [u8; 3], dead: [u8; 3] },
+}
+
+/// What comes before each frame's pixels
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// Nothing: the receiver counts bytes
+    #[default]
+    Raw,
+    /// `A`, `d`, `a`, the pixel count less one (big-endian), and that
+    /// count's two bytes XORed with 0x55 as a checksum
+    Adalight,
+}
+
+/// A display's geometry and wire format
+#[derive(Clone, Copy, Debug)]
+pub struct Display {
+    pub columns: usize,
+    pub rows: usize,
+    /// Whether odd rows (counting from 0 at the top) run right to left
+    pub serpentine: bool,
+    pub format: PixelFormat,
+    pub framing: Framing,
+}
+
+impl Display {
+    /// Bytes in each frame, header included
+    pub fn frame_len(&self) -> usize {
+        let pixels = match self.format {
+            PixelFormat::Bits => self.columns.div_ceil(8) * self.rows,
+            PixelFormat::Bytes => self.columns * self.rows,
+            PixelFormat::Rgb { .. } => 3 * self.columns * self.rows,
+        };
+        pixels + if self.framing == Framing::Adalight { 6 } else { 0 }
+    }
+}
+
+/// The last `rows` generations, framed for `display`
+pub struct Scroller {
+    display: Display,
+    history: VecDeque<Vec<bool>>,
+}
+
+impl Scroller {
+    pub fn new(display: Display) -> Self {
+        assert!(display.columns > 0 && display.rows > 0, "a display needs at least one pixel");
+        let blank = vec![false; display.columns];
+        Self { display, history: std::iter::repeat_n(blank, display.rows).collect() }
+    }
+
+    /// Scroll `ca`'s row in at the bottom and return the frame to send
+    pub fn push(&mut self, ca: &Automaton) -> Vec<u8> {
+        let columns = self.display.columns;
+        let row = (0..columns).map(|i| i < ca.width() && ca.deviates(i)).collect();
+        self.history.pop_front();
+        self.history.push_back(row);
+        self.frame()
+    }
+
+    /// The grid as it stands, in wiring order
+    pub fn frame(&self) -> Vec<u8> {
+        let Display { columns, rows, serpentine, format, framing } = self.display;
+        let mut frame = Vec::with_capacity(self.display.frame_len());
+        if framing == Framing::Adalight {
+            let [hi, lo] = ((columns * rows).saturating_sub(1).min(u16::MAX as usize) as u16).to_be_bytes();
+            frame.extend([b'A', b'd', b'a', hi, lo, hi ^ lo ^ 0x55]);
+        }
+        for (y, row) in self.history.iter().enumerate() {
+            let reversed = serpentine && y % 2 == 1;
+            let pixels = (0..columns).map(|x| row[if reversed { columns - 1 - x } else { x }]);
+            match format {
+                PixelFormat::Bits => {
+                    let start = frame.len();
+                    frame.resize(start + columns.div_ceil(8), 0);
+                    for (x, _) in pixels.enumerate().filter(|&(_, live)| live) {
+                        frame[start + x / 8] |= 0x80 >> (x % 8);
+                    }
+                }
+                PixelFormat::Bytes => frame.extend(pixels.map(u8::from)),
+                PixelFormat::Rgb { live, dead } => frame.extend(pixels.flat_map(|on| if on { live } else { dead })),
+            }
+        }
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(format: PixelFormat) -> Display {
+        Display { columns: 3, rows: 2, serpentine: false, format, framing: Framing::Raw }
+    }
+
+    #[test]
+    fn test_rows_scroll_up() {
+        let mut scroller = Scroller::new(display(PixelFormat::Bytes));
+        // Rule 170 shifts left; the first frame has a blank row above
+        let mut ca = Automaton::from_cells(vec![true, false, false], 170);
+        assert_eq!(scroller.push(&ca), [0, 0, 0, 1, 0, 0]);
+        ca.step();
+        assert_eq!(scroller.push(&ca), [1, 0, 0, 0, 0, 1]);
+
+        // Wider rings are cut, and serpentine wiring reverses the second row
+        let mut scroller = Scroller::new(Display { serpentine: true, ..display(PixelFormat::Bits) });
+        let wide = Automaton::from_cells(vec![true, true, false, true, true], 204);
+        scroller.push(&wide);
+        assert_eq!(scroller.push(&wide), [0b1100_0000, 0b0110_0000]);
+    }
+
+    #[test]
+    fn test_adalight_rgb_frames() {
+        let rgb = PixelFormat::Rgb { live: [255, 0, 0], dead: [0, 0, 8] };
+        let display = Display { rows: 1, framing: Framing::Adalight, ..display(rgb) };
+        let mut scroller = Scroller::new(display);
+        let frame = scroller.push(&Automaton::from_cells(vec![false, true, false], 204));
+        // Three LEDs: the count less one is 2, and 0 ^ 2 ^ 0x55 = 0x57
+        assert_eq!(frame, [b'A', b'd', b'a', 0, 2, 0x57, 0, 0, 8, 255, 0, 0, 0, 0, 8]);
+        assert_eq!(frame.len(), display.frame_len());
+
+        let mut sent = Vec::new();
+        sent.send(&frame).unwrap();
+        assert_eq!(sent, frame);
+    }
+}
@@ -0,0 +1,1832 @@
+//! Elementary Cellular Automata Explorer
+//!
+//! An elementary CA has:
+//! - A 1D row of cells, each 0 or 1
+//! - A rule that maps each 3-cell neighborhood to the next state of the center cell
+//! - 2^3 = 8 possible neighborhoods, so 2^8 = 256 possible rules
+//!
+//! The rule number encodes the output for each neighborhood:
+//!   neighborhood:  111 110 101 100 011 010 001 000
+//!   bit position:   7   6   5   4   3   2   1   0
+//!
+//! Example: Rule 110
+//!   110 = 0b01101110
+//!   111->0, 110->1, 101->1, 100->0, 011->1, 010->1, 001->1, 000->0
+//!
+//! ```
+//! use automata::Automaton;
+//!
+//! let mut ca = Automaton::new(7, 90);
+//! ca.step();
+//! assert_eq!(ca.to_string(), "  # #  ");
+//! ```
+
+// Cells are addressed by index to mirror the wraparound math.
+#![allow(clippy::needless_range_loop)]
+
+pub mod basins;
+pub mod bestiary;
+pub mod budget;
+pub mod compose;
+pub mod damage;
+pub mod describe;
+pub mod diagnostics;
+pub mod discover;
+pub mod ether;
+pub mod evolve;
+pub mod field;
+pub mod gpu;
+pub mod hashlife;
+pub mod inference;
+pub mod lattice;
+pub mod led;
+pub mod life;
+pub mod linear;
+pub mod metadata;
+pub mod notebook;
+pub mod parquet;
+pub mod perform;
+pub mod preimage;
+pub mod profile;
+pub mod render;
+pub mod sat;
+pub mod selftest;
+pub mod session;
+pub mod spacetime;
+pub mod stochastic;
+pub mod symmetry;
+pub mod toml;
+pub mod window;
+pub mod wolfram;
+
+use evolve::TableRule;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{BuildHasherDefault, Hash, Hasher};
+use std::io::Write;
+
+/// Version of the hashing behind `Automaton::state_hash` and attractor
+/// fingerprints (`basins::CycleFingerprint`), recorded next to stored hashes.
+/// Version 1 is 64-bit FNV-1a over little-endian `u64` words, the width
+/// first, so a hash is the same on every run, build and platform; it goes up
+/// whenever a hash of the same state would change.
+pub const HASH_VERSION: u32 = 1;
+
+/// 64-bit FNV-1a over the little-endian bytes of `words` (hash version 1)
+pub fn fnv1a(words: impl IntoIterator<Item = u64>) -> u64 {
+    words.into_iter().flat_map(u64::to_le_bytes).fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Longest block `block_entropy` reads: blocks are packed into a `u64`
+pub const MAX_BLOCK_SIZE: usize = 64;
+
+/// Blocks up to this long are counted in a table of all 2^k patterns; longer
+/// ones by sorting the width patterns that occur
+const DENSE_BLOCK_SIZE: usize = 16;
+
+/// Parameters no run or measurement can be made from, rejected up front
+/// rather than yielding a silent zero deep inside an analysis
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParamError {
+    /// A ring of no cells
+    EmptyRing,
+    /// A block of no cells, longer than the ring, or past `MAX_BLOCK_SIZE`
+    BlockSize { block: usize, width: usize },
+}
+
+impl fmt::Display for ParamError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParamError::EmptyRing => write!(f, "width must be at least 1: a ring needs a cell"),
+            ParamError::BlockSize { block: 0, .. } => write!(f, "block size must be at least 1"),
+            ParamError::BlockSize { block, width } if block > width => write!(
+                f,
+                "block size {block} is longer than the {width}-cell ring, so blocks would count cells twice; \
+                 use at most {width}"
+            ),
+            ParamError::BlockSize { block, .. } => {
+                write!(f, "block size {block} is past the limit of {MAX_BLOCK_SIZE}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParamError {}
+
+/// Rings need a cell; one or two are fine (the neighborhood wraps onto
+/// itself: on one cell, left, center and right are all that cell)
+pub fn check_width(width: usize) -> Result<(), ParamError> {
+    if width == 0 { Err(ParamError::EmptyRing) } else { Ok(()) }
+}
+
+/// Blocks of `block` cells can be read off a ring of `width` when
+/// 1 <= block <= width, up to `MAX_BLOCK_SIZE`
+pub fn check_block(block: usize, width: usize) -> Result<(), ParamError> {
+    if block == 0 || block > width || block > MAX_BLOCK_SIZE {
+        Err(ParamError::BlockSize { block, width })
+    } else {
+        Ok(())
+    }
+}
+
+/// A row of cells on a ring (toroidal boundaries) together with the rule
+/// that updates it
+///
+/// Cells are bit-packed, 64 to a word: cell i is bit i % 64 of word i / 64.
+/// Bits past the last cell are always zero, so words compare and hash as rows.
+/// Rows have at least one cell (see `check_width`); the constructors panic on
+/// an empty one.
+///
+/// The rule is an elementary one unless `with_table` swaps in a lookup table
+/// of radius 2 or 3, which steps the row the same way, 64 cells at a time, so
+/// every measurement made of a row works under either.
+///
+/// Population, density and rendering count cells that differ from the
+/// background (see `on_background`), which is all dead unless declared
+/// otherwise; stepping, hashing and the packed words see the raw cells.
+///
+/// With `with_noise`, each step flips every cell of the new row with some
+/// probability after the rule has run, so the rule is no longer the whole
+/// story: a state that comes back may not come back again.
+///
+/// With `with_memory`, the rule also reads the cell's last few states, so
+/// the state that determines the future is the row together with the rows
+/// remembered; cycle detection compares the lot (see `state`).
+///
+/// With `with_schedule`, elementary rules take turns stepping the row, and
+/// where the run is in the schedule is part of its state too: a cycle is a
+/// row coming back at the same point of the schedule.
+#[derive(Clone, Eq, PartialEq)]
+pub struct Automaton {
+    words: Vec<u64>,
+    width: usize,
+    rule: u8,
+    /// Steps the row in place of `rule` when set
+    table: Option<TableRule>,
+    background: bool,
+    noise: Option<Noise>,
+    memory: Option<Memory>,
+    /// The schedule and the step within one pass of it, 0 to its period - 1
+    schedule: Option<(Schedule, usize)>,
+    /// `row_key` of the words, kept up to date as they change
+    key: u64,
+}
+
+/// Cells flipped after each step with probability `p`, by coin flips from
+/// a stream of their own
+#[derive(Clone)]
+struct Noise {
+    p: f64,
+    rng: ChaCha8Rng,
+}
+
+/// Rows compare the same however far their coin flips have got
+impl PartialEq for Noise {
+    fn eq(&self, other: &Self) -> bool {
+        self.p.to_bits() == other.p.to_bits()
+    }
+}
+
+impl Eq for Noise {}
+
+/// Longest memory `with_memory` keeps, in rows before the current one
+pub const MAX_MEMORY_DEPTH: usize = 32;
+
+/// How a rule with memory combines a cell's past states with its present
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MemoryRule {
+    /// Alonso-Sanz's elementary CA with memory: the rule reads each cell's
+    /// majority state over the current row and the `depth` before it (a tie
+    /// keeps the current state), in place of the current state
+    Majority,
+    /// Fredkin's second-order construction: the rule's output is XORed with
+    /// the cell `depth` rows back, which makes every rule reversible
+    Xor,
+}
+
+/// Elementary rules that take turns stepping a row, each for `block` steps
+/// before the next: 90,110 with a block of 1 steps by 90 on even steps and
+/// 110 on odd ones
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Schedule {
+    rules: Vec<u8>,
+    block: usize,
+}
+
+impl Schedule {
+    /// Panics without a rule or with a block of 0 steps
+    pub fn new(rules: Vec<u8>, block: usize) -> Self {
+        assert!(!rules.is_empty(), "a schedule needs a rule");
+        assert!(block > 0, "each rule needs a step");
+        Self { rules, block }
+    }
+
+    pub fn rules(&self) -> &[u8] {
+        &self.rules
+    }
+
+    /// Steps each rule runs before the next
+    pub fn block(&self) -> usize {
+        self.block
+    }
+
+    /// Steps in one pass through every rule
+    pub fn period(&self) -> usize {
+        self.rules.len() * self.block
+    }
+
+    /// The rule stepping the row at `step` (any step, not only one pass)
+    pub fn rule_at(&self, step: usize) -> u8 {
+        self.rules[step % self.period() / self.block]
+    }
+}
+
+/// `90,110 alternating` or `90,110 in blocks of 8`
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rules: Vec<String> = self.rules.iter().map(u8::to_string).collect();
+        match self.block {
+            1 => write!(f, "{} alternating", rules.join(",")),
+            block => write!(f, "{} in blocks of {block}", rules.join(",")),
+        }
+    }
+}
+
+/// The rows before the current one a rule with memory reads, most recent
+/// first
+#[derive(Clone, PartialEq, Eq)]
+struct Memory {
+    rule: MemoryRule,
+    past: VecDeque<Vec<u64>>,
+    /// Each remembered row's `row_key`, in the same order
+    keys: VecDeque<u64>,
+}
+
+impl Memory {
+    /// Each cell's majority state over `current` and the remembered rows,
+    /// counted a word at a time in bit planes (plane b holds bit b of every
+    /// cell's count of live states)
+    fn majority(&self, current: &[u64]) -> Vec<u64> {
+        let n = self.past.len() + 1;
+        let bits = usize::BITS - n.leading_zeros();
+        let half = n / 2;
+        (0..current.len())
+            .map(|w| {
+                let mut planes = vec![0u64; bits as usize];
+                for row in std::iter::once(current).chain(self.past.iter().map(Vec::as_slice)) {
+                    let mut carry = row[w];
+                    for plane in planes.iter_mut() {
+                        let next = *plane & carry;
+                        *plane ^= carry;
+                        carry = next;
+                    }
+                }
+                // Counts above half, and counts equal to it, from the top plane down
+                let (mut above, mut equal) = (0u64, !0u64);
+                for (b, &plane) in planes.iter().enumerate().rev() {
+                    if (half >> b) & 1 == 1 {
+                        equal &= plane;
+                    } else {
+                        above |= equal & plane;
+                        equal &= !plane;
+                    }
+                }
+                if n.is_multiple_of(2) { above | (equal & current[w]) } else { above }
+            })
+            .collect()
+    }
+}
+
+impl Noise {
+    /// Flip each of the first `width` cells with probability p. The gaps
+    /// between flips are geometric, so a step costs a draw per flip rather
+    /// than one per cell
+    fn flip(&mut self, words: &mut [u64], width: usize) {
+        let log_q = (1.0 - self.p).ln();
+        let mut i = 0;
+        loop {
+            let u: f64 = self.rng.random();
+            let gap = ((1.0 - u).ln() / log_q).floor();
+            if gap >= (width - i) as f64 {
+                return;
+            }
+            i += gap as usize;
+            words[i / 64] ^= 1 << (i % 64);
+            i += 1;
+        }
+    }
+}
+
+impl Automaton {
+    /// A row of `width` dead cells with a single live cell in the center
+    pub fn new(width: usize, rule: u8) -> Self {
+        assert_width(width);
+        let mut words = vec![0u64; width.div_ceil(64)];
+        // Start with single cell in center
+        let center = width / 2;
+        words[center / 64] |= 1 << (center % 64);
+        Self::packed(words, width, rule)
+    }
+
+    /// A row of `width` cells, each live with probability `density`, drawn
+    /// from a ChaCha8 stream seeded with `seed` so the row is reproducible
+    pub fn random(width: usize, rule: u8, density: f64, seed: u64) -> Self {
+        assert!((0.0..=1.0).contains(&density), "density must be in [0, 1]");
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        Self::from_cells((0..width).map(|_| rng.random_bool(density)).collect(), rule)
+    }
+
+    /// Start from an arbitrary row
+    pub fn from_cells(cells: Vec<bool>, rule: u8) -> Self {
+        assert_width(cells.len());
+        let words = cells
+            .chunks(64)
+            .map(|chunk| chunk.iter().rev().fold(0u64, |word, &c| (word << 1) | c as u64))
+            .collect();
+        Self::packed(words, cells.len(), rule)
+    }
+
+    /// The same row under a different rule, for surveys that run every rule
+    /// from one initial condition
+    pub fn with_rule(&self, rule: u8) -> Self {
+        Self { rule, table: None, schedule: None, ..self.clone() }
+    }
+
+    /// The same row under a lookup-table rule; a radius-1 table is the
+    /// elementary rule it numbers
+    pub fn with_table(&self, table: TableRule) -> Self {
+        match table.radius {
+            1 => self.with_rule(table.table as u8),
+            _ => Self { table: Some(table), schedule: None, ..self.clone() },
+        }
+    }
+
+    /// The same pattern laid over a background of `background` cells: with
+    /// a new background every cell is flipped, so the cells that differed
+    /// from the old one differ from the new one, and analyses from then on
+    /// measure deviations from it (for rule 255, whose vacuum is all live)
+    pub fn on_background(&self, background: bool) -> Self {
+        if background == self.background {
+            return self.clone();
+        }
+        let flip = |row: &Vec<u64>| {
+            let mut words: Vec<u64> = row.iter().map(|w| !w).collect();
+            if let Some(last) = words.last_mut() {
+                *last &= tail_mask(self.width);
+            }
+            words
+        };
+        let memory = self.memory.as_ref().map(|m| {
+            let past: VecDeque<Vec<u64>> = m.past.iter().map(flip).collect();
+            Memory { rule: m.rule, keys: past.iter().map(|row| row_key(row)).collect(), past }
+        });
+        let words = flip(&self.words);
+        Self { key: row_key(&words), words, background, memory, ..self.clone() }
+    }
+
+    /// The same row with cell `i` flipped: a one-cell perturbation, whose
+    /// damage `damage::difference_pattern` follows
+    pub fn with_flipped(&self, i: usize) -> Self {
+        let mut words = self.words.clone();
+        words[i / 64] ^= 1 << (i % 64);
+        Self { key: row_key(&words), words, ..self.clone() }
+    }
+
+    /// State of the quiescent background that deviations are measured from
+    pub fn background(&self) -> bool {
+        self.background
+    }
+
+    /// The same row, stepped with each new cell flipped with probability
+    /// `p`, from a ChaCha8 stream seeded with `seed`; 0 is no noise
+    pub fn with_noise(&self, p: f64, seed: u64) -> Self {
+        assert!((0.0..=1.0).contains(&p), "noise must be in [0, 1]");
+        let noise = (p > 0.0).then(|| Noise { p, rng: ChaCha8Rng::seed_from_u64(seed) });
+        Self { noise, ..self.clone() }
+    }
+
+    /// Probability that a step flips each cell
+    pub fn noise(&self) -> f64 {
+        self.noise.as_ref().map_or(0.0, |noise| noise.p)
+    }
+
+    /// The same row under a rule that also reads each cell's last `depth`
+    /// states (up to `MAX_MEMORY_DEPTH`), combined by `rule`; the row is
+    /// taken to have been the same that long. A depth of 0 is no memory.
+    pub fn with_memory(&self, rule: MemoryRule, depth: usize) -> Self {
+        assert!(depth <= MAX_MEMORY_DEPTH, "memory depth must be at most {MAX_MEMORY_DEPTH}");
+        let memory = (depth > 0).then(|| Memory {
+            rule,
+            past: vec![self.words.clone(); depth].into(),
+            keys: vec![self.key; depth].into(),
+        });
+        Self { memory, ..self.clone() }
+    }
+
+    /// The same row stepped by `schedule`'s rules in turn, from the start
+    /// of it; one rule on its own is just that rule
+    pub fn with_schedule(&self, schedule: Schedule) -> Self {
+        if schedule.rules.len() == 1 {
+            return self.with_rule(schedule.rules[0]);
+        }
+        Self { rule: schedule.rule_at(0), table: None, schedule: Some((schedule, 0)), ..self.clone() }
+    }
+
+    /// The schedule of rules stepping the row, if any, and the step within
+    /// one pass of it; `rule` is the rule that steps it next
+    pub fn schedule(&self) -> Option<(&Schedule, usize)> {
+        self.schedule.as_ref().map(|(schedule, phase)| (schedule, *phase))
+    }
+
+    /// The memory rule and how many rows back it reads, if any
+    pub fn memory(&self) -> Option<(MemoryRule, usize)> {
+        self.memory.as_ref().map(|m| (m.rule, m.past.len()))
+    }
+
+    /// Everything the next rows depend on, packed: the row's words, then
+    /// those of each remembered row, most recent first, then under a
+    /// schedule the step within it. Without either it is the row itself.
+    pub fn state(&self) -> Vec<u64> {
+        let past = self.memory.iter().flat_map(|m| m.past.iter().flatten());
+        let phase = self.schedule.iter().map(|&(_, phase)| phase as u64);
+        self.words.iter().chain(past).copied().chain(phase).collect()
+    }
+
+    /// A quick hash of `state`, from the keys kept as rows were stepped
+    /// rather than rehashing the words; for lookups within a run, where
+    /// `state_hash` is the one to store
+    pub(crate) fn state_key(&self) -> u64 {
+        let key = match &self.memory {
+            None => self.key,
+            Some(m) => m.keys.iter().fold(self.key, |key, &past| mix(key.rotate_left(5) ^ past)),
+        };
+        match self.schedule {
+            None => key,
+            Some((_, phase)) => mix(key.rotate_left(5) ^ phase as u64),
+        }
+    }
+
+    /// Whether `state` (as `state` packs it) is this automaton's, without
+    /// packing its own
+    pub(crate) fn has_state(&self, state: &[u64]) -> bool {
+        let (row, past) = state.split_at(self.words.len().min(state.len()));
+        let remembered = self.memory.iter().flat_map(|m| m.past.iter().flatten());
+        let phase = self.schedule.iter().map(|&(_, phase)| phase as u64);
+        row == self.words && past.iter().copied().eq(remembered.copied().chain(phase))
+    }
+
+    /// Whether the row and every remembered row are all background, so
+    /// nothing can ever come back
+    fn is_dead(&self) -> bool {
+        self.population() == 0 && self.memory.as_ref().is_none_or(|m| m.past.iter().all(|row| *row == self.words))
+    }
+
+    /// Start from a packed row (see `words`)
+    pub fn from_words(mut words: Vec<u64>, width: usize, rule: u8) -> Self {
+        assert_width(width);
+        assert_eq!(words.len(), width.div_ceil(64), "{width} cells need {} words", width.div_ceil(64));
+        if let Some(last) = words.last_mut() {
+            *last &= tail_mask(width);
+        }
+        Self::packed(words, width, rule)
+    }
+
+    fn packed(words: Vec<u64>, width: usize, rule: u8) -> Self {
+        let key = row_key(&words);
+        Self { words, width, rule, table: None, background: false, noise: None, memory: None, schedule: None, key }
+    }
+
+    /// State of cell i
+    pub fn get(&self, i: usize) -> bool {
+        (self.words[i / 64] >> (i % 64)) & 1 == 1
+    }
+
+    /// Whether cell i differs from the background (is live, on the usual
+    /// dead one)
+    pub fn deviates(&self, i: usize) -> bool {
+        self.get(i) != self.background
+    }
+
+    /// Cells as deviations from the background, leftmost first
+    pub fn deviations(&self) -> impl Iterator<Item = bool> + '_ {
+        self.iter().map(|cell| cell != self.background)
+    }
+
+    /// Cells, leftmost first
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.width).map(|i| self.get(i))
+    }
+
+    /// Current row unpacked, leftmost cell first
+    pub fn cells(&self) -> Vec<bool> {
+        self.iter().collect()
+    }
+
+    /// Packed row: cell i is bit i % 64 of word i / 64
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Wolfram rule number (not the rule that steps the row under a table;
+    /// under a schedule, the one that steps it next)
+    pub fn rule(&self) -> u8 {
+        self.rule
+    }
+
+    /// The lookup table of radius 2 or 3 stepping the row, if `with_table`
+    /// gave it one
+    pub fn table(&self) -> Option<TableRule> {
+        self.table
+    }
+
+    /// Apply rule to get next generation, 64 cells at a time
+    pub fn step(&mut self) {
+        // Under a majority memory the rule reads each cell's majority state
+        let majority = match &self.memory {
+            Some(memory) if memory.rule == MemoryRule::Majority => Some(memory.majority(&self.words)),
+            _ => None,
+        };
+        let featured = majority.as_deref().unwrap_or(&self.words);
+        let mut next: Vec<u64> = match self.table {
+            Some(table) => {
+                let windows = ring_windows(featured, self.width, table.radius);
+                (0..featured.len()).map(|w| apply_table(table, &windows, w)).collect()
+            }
+            None => {
+                let (left, right) = ring_neighbors(featured, self.width);
+                (0..featured.len()).map(|w| apply_rule(self.rule, left[w], featured[w], right[w])).collect()
+            }
+        };
+
+        if let Some(memory) = self.memory.as_ref().filter(|memory| memory.rule == MemoryRule::Xor) {
+            let back = memory.past.back().expect("memory has a row");
+            next.iter_mut().zip(back).for_each(|(word, old)| *word ^= old);
+        }
+        if let Some(noise) = &mut self.noise {
+            noise.flip(&mut next, self.width);
+        }
+        // Rules mapping 000 to 1 would otherwise set the bits past the last cell
+        if let Some(last) = next.last_mut() {
+            *last &= tail_mask(self.width);
+        }
+        let key = row_key(&next);
+        let last = std::mem::replace(&mut self.words, next);
+        let last_key = std::mem::replace(&mut self.key, key);
+        if let Some(memory) = &mut self.memory {
+            memory.past.pop_back();
+            memory.past.push_front(last);
+            memory.keys.pop_back();
+            memory.keys.push_front(last_key);
+        }
+        if let Some((schedule, phase)) = &mut self.schedule {
+            *phase = (*phase + 1) % schedule.period();
+            self.rule = schedule.rule_at(*phase);
+        }
+    }
+
+    /// Number of cells in the row
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Count cells that differ from the background: live cells, normally
+    pub fn population(&self) -> usize {
+        let live: usize = self.words.iter().map(|w| w.count_ones() as usize).sum();
+        if self.background { self.width - live } else { live }
+    }
+
+    /// Population as a fraction of the width
+    pub fn density(&self) -> f64 {
+        self.population() as f64 / self.width() as f64
+    }
+
+    /// Spatial entropy based on k-block frequencies
+    /// Measures how "random" the spatial pattern is
+    /// Returns bits per block; max is k for uniform distribution
+    ///
+    /// Panics unless `check_block(k, width)` passes: a block longer than the
+    /// ring would wrap onto itself and count cells twice.
+    pub fn block_entropy(&self, k: usize) -> f64 {
+        if let Err(e) = check_block(k, self.width) {
+            panic!("{e}");
+        }
+
+        // The k-bit pattern starting at each cell (with wraparound)
+        let n = self.width();
+        let patterns = (0..n).map(|i| (0..k).fold(0u64, |pattern, j| (pattern << 1) | self.get((i + j) % n) as u64));
+        if k <= DENSE_BLOCK_SIZE {
+            let mut counts = vec![0usize; 1 << k];
+            patterns.for_each(|pattern| counts[pattern as usize] += 1);
+            shannon_entropy(&counts)
+        } else {
+            // Counts of the patterns that occur, in the same order
+            let mut sorted: Vec<u64> = patterns.collect();
+            sorted.sort_unstable();
+            shannon_entropy(&sorted.chunk_by(|a, b| a == b).map(<[u64]>::len).collect::<Vec<_>>())
+        }
+    }
+
+    /// The row's cells as a compact hash, FNV-1a of the width and packed
+    /// words: the same in every process and on every platform (see
+    /// `HASH_VERSION`), so it can be stored and compared across runs
+    pub fn state_hash(&self) -> u64 {
+        fnv1a(std::iter::once(self.width as u64).chain(self.words.iter().copied()))
+    }
+}
+
+fn assert_width(width: usize) {
+    if let Err(e) = check_width(width) {
+        panic!("{e}");
+    }
+}
+
+impl Hash for Automaton {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.width.hash(state);
+        self.words.hash(state);
+    }
+}
+
+/// Shannon entropy in bits of a histogram: H = -Σ p_i log2(p_i)
+pub fn shannon_entropy(counts: &[usize]) -> f64 {
+    let total = counts.iter().sum::<usize>() as f64;
+    let mut entropy = 0.0;
+    for &count in counts {
+        if count > 0 {
+            let p = count as f64 / total;
+            entropy -= p * p.log2();
+        }
+    }
+    entropy
+}
+
+/// Left and right neighbors of every cell of a packed ring of `n` cells, as
+/// packed rows: bit i of `left` is cell i - 1, bit i of `right` is cell i + 1
+pub(crate) fn ring_neighbors(center: &[u64], n: usize) -> (Vec<u64>, Vec<u64>) {
+    let last = center.len() - 1;
+    let bit = |i: usize| (center[i / 64] >> (i % 64)) & 1;
+
+    // left[i] = cells[i - 1]: shift toward higher indices, cell n-1 wraps to 0
+    let left = (0..center.len())
+        .map(|w| {
+            let carry = if w == 0 { bit(n - 1) } else { center[w - 1] >> 63 };
+            (center[w] << 1) | carry
+        })
+        .collect();
+
+    // right[i] = cells[i + 1]: shift toward lower indices, cell 0 wraps to n-1
+    let right = (0..center.len())
+        .map(|w| {
+            let carry = if w == last { bit(0) << ((n - 1) % 64) } else { center[w + 1] << 63 };
+            (center[w] >> 1) | carry
+        })
+        .collect();
+
+    (left, right)
+}
+
+/// Next state of 64 cells at once: the union of the neighborhoods (l c r as
+/// bits of the index) that the rule maps to 1
+pub(crate) fn apply_rule(rule: u8, left: u64, center: u64, right: u64) -> u64 {
+    let pick = |word: u64, bit: u8| if bit == 1 { word } else { !word };
+    (0..8u8)
+        .filter(|&index| (rule >> index) & 1 == 1)
+        .fold(0, |acc, index| {
+            acc | (pick(left, (index >> 2) & 1) & pick(center, (index >> 1) & 1) & pick(right, index & 1))
+        })
+}
+
+/// The 2r + 1 cells around every cell of a packed ring of `n` cells, as
+/// packed rows: bit i of row j is cell i - r + j, so row 0 is the leftmost
+fn ring_windows(center: &[u64], n: usize, radius: usize) -> Vec<Vec<u64>> {
+    let mut rows = std::collections::VecDeque::from([center.to_vec()]);
+    for _ in 0..radius {
+        let left = ring_neighbors(&rows[0], n).0;
+        let right = ring_neighbors(&rows[rows.len() - 1], n).1;
+        rows.push_front(left);
+        rows.push_back(right);
+    }
+    rows.into()
+}
+
+/// `apply_rule` for word `w` of a table rule's `ring_windows`: the union of
+/// the windows (leftmost cell the high bit of the index) it maps to 1
+fn apply_table(table: TableRule, windows: &[Vec<u64>], w: usize) -> u64 {
+    let last = windows.len() - 1;
+    (0..table.entries())
+        .filter(|&index| (table.table >> index) & 1 == 1)
+        .fold(0, |acc, index| {
+            acc | windows.iter().enumerate().fold(!0, |cells, (j, row)| {
+                cells & if (index >> (last - j)) & 1 == 1 { row[w] } else { !row[w] }
+            })
+        })
+}
+
+/// Valid-bit mask for the last word of a packed row of `width` cells
+pub(crate) fn tail_mask(width: usize) -> u64 {
+    match width % 64 {
+        0 => !0,
+        bits => (1u64 << bits) - 1,
+    }
+}
+
+/// A quick hash of a packed row for lookups within a run: each word, tagged
+/// with its position, is mixed on its own and the results summed, so the
+/// multiplies don't wait on one another the way FNV's do
+pub(crate) fn row_key(words: &[u64]) -> u64 {
+    let sum = words.iter().enumerate().fold(0u64, |sum, (i, &w)| {
+        sum.wrapping_add(mix(w ^ (i as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15)))
+    });
+    mix(sum)
+}
+
+/// SplitMix64's finalizer: every output bit depends on every input bit
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Hashes a `state_key` for a `HashMap` by passing it through: it is mixed
+/// already
+#[derive(Default)]
+pub(crate) struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|&b| self.0 = mix(self.0 ^ b as u64));
+    }
+
+    fn write_u64(&mut self, key: u64) {
+        self.0 = key;
+    }
+}
+
+/// The states of a run so far, each with the step it was first seen at,
+/// looked up by `Automaton::state_key` so a step costs one copy of the
+/// state and no rehashing. A state whose key another already holds is
+/// settled by comparing the states, and kept in a map of its own if new.
+#[derive(Default)]
+pub(crate) struct StateIndex {
+    first: HashMap<u64, usize, BuildHasherDefault<KeyHasher>>,
+    collided: HashMap<Vec<u64>, usize>,
+    /// Every state seen, in order, packed as `Automaton::state` packs them
+    states: Vec<Vec<u64>>,
+}
+
+impl StateIndex {
+    /// The step at which `ca`'s state was first seen; None the first time,
+    /// when it is recorded as the next step
+    pub(crate) fn visit(&mut self, ca: &Automaton) -> Option<usize> {
+        let step = self.states.len();
+        match self.first.entry(ca.state_key()) {
+            Entry::Vacant(entry) => {
+                entry.insert(step);
+            }
+            Entry::Occupied(entry) if ca.has_state(&self.states[*entry.get()]) => return Some(*entry.get()),
+            Entry::Occupied(_) => match self.collided.entry(ca.state()) {
+                Entry::Occupied(entry) => return Some(*entry.get()),
+                Entry::Vacant(entry) => {
+                    entry.insert(step);
+                }
+            },
+        }
+        self.states.push(ca.state());
+        None
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// The states from step `start` on
+    pub(crate) fn split_off(mut self, start: usize) -> Vec<Vec<u64>> {
+        self.states.split_off(start)
+    }
+}
+
+/// How a run remembers the states it has passed through, from most memory
+/// to least
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recall {
+    /// Every state, so a repeat is found the step it happens
+    States,
+    /// Each state's 64-bit key and step; a repeated key is confirmed by
+    /// stepping a copy of the start to the earlier state. As prompt as
+    /// `States`, for one more run up to the repeat
+    Keys,
+    /// Brent's method: one state, saved at each power of two steps, so
+    /// constant memory. A repeat is noticed late, by up to twice the
+    /// transient and period, so runs go on to three times their limit (see
+    /// `History::horizon`); the transient is then found by stepping two
+    /// copies of the start
+    Brent,
+}
+
+impl Recall {
+    /// The most thorough recall whose storage for `steps` states like
+    /// `initial` fits the memory budget, recording a degradation if
+    /// that isn't `States`
+    pub fn for_run(initial: &Automaton, steps: usize) -> Self {
+        let steps = steps as u64 + 1;
+        let words = (initial.words.len() * (1 + initial.memory.as_ref().map_or(0, |m| m.past.len()))) as u64;
+        let states = steps.saturating_mul(8 * words + budget::STATE_OVERHEAD);
+        let keys = steps.saturating_mul(budget::KEY_BYTES);
+        let (recall, how) = match (budget::fits(states), budget::fits(keys)) {
+            (true, _) => return Recall::States,
+            (false, true) => (Recall::Keys, "64-bit keys only, confirmed by replaying"),
+            (false, false) => (Recall::Brent, "Brent's method, one state"),
+        };
+        budget::degrade("cycle detection", format!("{how} (every state would need {})", budget::bytes(states)));
+        recall
+    }
+}
+
+/// The states of a run so far, as `Recall` keeps them, fed one step at a
+/// time
+pub(crate) enum History {
+    States(StateIndex),
+    Keys {
+        start: Box<Automaton>,
+        steps: usize,
+        first: HashMap<u64, usize, BuildHasherDefault<KeyHasher>>,
+        /// States whose key an earlier, different state holds
+        collided: HashMap<Vec<u64>, usize>,
+    },
+    Brent {
+        start: Box<Automaton>,
+        saved: Box<Automaton>,
+        /// Steps from `saved` to the state last visited, up to `power`
+        since: usize,
+        power: usize,
+    },
+}
+
+impl History {
+    pub(crate) fn new(initial: &Automaton, recall: Recall) -> Self {
+        let mut history = match recall {
+            Recall::States => History::States(StateIndex::default()),
+            Recall::Keys => History::Keys {
+                start: Box::new(initial.clone()),
+                steps: 0,
+                first: HashMap::default(),
+                collided: HashMap::new(),
+            },
+            Recall::Brent => {
+                let (start, saved) = (Box::new(initial.clone()), Box::new(initial.clone()));
+                return History::Brent { start, saved, since: 0, power: 1 };
+            }
+        };
+        history.visit(initial);
+        history
+    }
+
+    /// The state after the next step; once a state comes back, the step it
+    /// first appeared at and the period
+    pub(crate) fn visit(&mut self, ca: &Automaton) -> Option<(usize, usize)> {
+        match self {
+            History::States(seen) => {
+                let step = seen.len();
+                seen.visit(ca).map(|start| (start, step - start))
+            }
+            History::Keys { start, steps, first, collided } => {
+                let step = *steps;
+                *steps += 1;
+                let earlier = match first.entry(ca.state_key()) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(step);
+                        return None;
+                    }
+                    Entry::Occupied(entry) => *entry.get(),
+                };
+                let mut replay = start.clone();
+                (0..earlier).for_each(|_| replay.step());
+                if ca.has_state(&replay.state()) {
+                    return Some((earlier, step - earlier));
+                }
+                match collided.entry(ca.state()) {
+                    Entry::Occupied(entry) => Some((*entry.get(), step - *entry.get())),
+                    Entry::Vacant(entry) => {
+                        entry.insert(step);
+                        None
+                    }
+                }
+            }
+            History::Brent { start, saved, since, power } => {
+                *since += 1;
+                if ca.state_key() == saved.state_key() && ca.has_state(&saved.state()) {
+                    let (transient, repeated) = transient(start, *since);
+                    **saved = repeated;
+                    return Some((transient, *since));
+                }
+                if since == power {
+                    (**saved, *since, *power) = (ca.clone(), 0, *power * 2);
+                }
+                None
+            }
+        }
+    }
+
+    /// Steps to run to find every cycle that closes within `max_steps`
+    pub(crate) fn horizon(&self, max_steps: usize) -> usize {
+        match self {
+            History::Brent { .. } => max_steps.saturating_mul(3),
+            _ => max_steps,
+        }
+    }
+
+    /// Once `visit` has found a repeat, the state that came back, as it
+    /// was at the repeat's second visit: `ca` unless Brent's method
+    /// noticed it late
+    pub(crate) fn repeated<'a>(&'a self, ca: &'a Automaton) -> &'a Automaton {
+        match self {
+            History::Brent { saved, .. } => saved,
+            _ => ca,
+        }
+    }
+
+    /// States held, for the profile
+    pub(crate) fn stored(&self) -> usize {
+        match self {
+            History::States(seen) => seen.len(),
+            History::Keys { first, collided, .. } => first.len() + collided.len(),
+            History::Brent { .. } => 1,
+        }
+    }
+
+    /// The `period` states from step `start` on: taken from the history
+    /// if it has them, or stepped again from the initial state
+    pub(crate) fn cycle(self, start: usize, period: usize) -> Vec<Vec<u64>> {
+        let mut ca = match self {
+            History::States(seen) => return seen.split_off(start).into_iter().take(period).collect(),
+            History::Keys { start, .. } | History::Brent { start, .. } => *start,
+        };
+        (0..start).for_each(|_| ca.step());
+        (0..period)
+            .map(|_| {
+                let state = ca.state();
+                ca.step();
+                state
+            })
+            .collect()
+    }
+}
+
+/// Steps from `start` into a cycle of `period`, the first step whose state
+/// comes back `period` steps later, and that state
+fn transient(start: &Automaton, period: usize) -> (usize, Automaton) {
+    let (mut a, mut b) = (start.clone(), start.clone());
+    (0..period).for_each(|_| b.step());
+    let mut steps = 0;
+    while !(a.state_key() == b.state_key() && a.has_state(&b.state())) {
+        a.step();
+        b.step();
+        steps += 1;
+    }
+    (steps, a)
+}
+
+/// The homogeneous row a rule leaves unchanged, preferring all dead when
+/// both are (000 -> 0 makes dead quiescent, 111 -> 1 live). Rules mapping
+/// 000 -> 1 and 111 -> 0 have neither: their vacuum flips every step
+pub fn quiescent_state(rule: u8) -> Option<bool> {
+    match (rule & 1, rule >> 7) {
+        (0, _) => Some(false),
+        (_, 1) => Some(true),
+        _ => None,
+    }
+}
+
+/// Result of running a CA until it cycles or reaches max steps
+#[derive(Debug)]
+pub struct CycleAnalysis {
+    /// Steps before entering cycle (transient length)
+    pub transient: usize,
+    /// Length of the cycle (0 if didn't find one)
+    pub period: usize,
+    /// Whether the CA died (every cell back at the background)
+    pub died: bool,
+    /// Final density
+    pub final_density: f64,
+}
+
+/// Run CA from a single centered cell until it enters a cycle or hits
+/// max_steps; `find_cycle_from` starts anywhere
+pub fn find_cycle(rule: u8, width: usize, max_steps: usize) -> CycleAnalysis {
+    find_cycle_from(Automaton::new(width, rule), max_steps)
+}
+
+/// `find_cycle` from an arbitrary initial row: `cycle` and `analyze` pass
+/// whatever `--init` (random with `--seed`, `bits:`, `hex:`) or
+/// `--init-file` gives, and `sample-basins` many random rows
+pub fn find_cycle_from(mut ca: Automaton, max_steps: usize) -> CycleAnalysis {
+    let mut tracker = CycleTracker::new(&ca, max_steps);
+
+    for step in 0..tracker.steps() {
+        ca.step();
+        if let Some(analysis) = tracker.observe(step, &ca) {
+            return analysis;
+        }
+    }
+
+    // Didn't find cycle within max_steps
+    tracker.give_up(&ca)
+}
+
+/// Smallest period p <= `max_period` with every value within `tolerance` of
+/// the one p steps earlier, if any: the cycle of an observable such as the
+/// density, which can be shorter than the state's (a glider moving around
+/// the ring keeps its density) but never longer
+pub fn series_period(series: &[f64], max_period: usize, tolerance: f64) -> Option<usize> {
+    (1..=max_period.min(series.len().saturating_sub(1)))
+        .find(|&p| series.windows(p + 1).all(|w| (w[p] - w[0]).abs() <= tolerance))
+}
+
+/// State history for cycle detection, fed one generation at a time so that
+/// backends which step many automata in lockstep can share it
+pub(crate) struct CycleTracker {
+    seen: History,
+    /// Whether the row started all background (an empty initial condition)
+    dead_start: bool,
+    max_steps: usize,
+    /// The density after `max_steps`, if the run went past them
+    at_limit: Option<f64>,
+}
+
+impl CycleTracker {
+    /// Tracking a run of up to `max_steps`, in as much memory as the
+    /// budget allows
+    pub(crate) fn new(initial: &Automaton, max_steps: usize) -> Self {
+        let seen = History::new(initial, Recall::for_run(initial, max_steps));
+        Self { seen, dead_start: initial.is_dead(), max_steps, at_limit: None }
+    }
+
+    /// Steps to observe before giving up: more than `max_steps` if the
+    /// history needs them to see every cycle closing within those
+    pub(crate) fn steps(&self) -> usize {
+        self.seen.horizon(self.max_steps)
+    }
+
+    /// Record the state after step `step` (0-based); Some once the run dies
+    /// or revisits an earlier state
+    pub(crate) fn observe(&mut self, step: usize, ca: &Automaton) -> Option<CycleAnalysis> {
+        if step + 1 == self.max_steps {
+            self.at_limit = Some(ca.density());
+        }
+        // Check if died; past the limit, it would have given up first
+        if ca.is_dead() && step >= self.max_steps {
+            return Some(self.give_up(ca));
+        }
+        if ca.is_dead() {
+            // Linear rules of two taps (60, 90 and 102) add each cell to one
+            // neighbor mod 2; on a ring of 2^n cells, n doublings of that sum
+            // cancel every row
+            let plain = ca.table.is_none() && ca.memory.is_none() && ca.schedule.is_none();
+            let additive = plain && linear::Linear::elementary(ca.rule).is_ok_and(|l| l.taps.len() == 2 && !l.affine);
+            if additive && ca.width.is_power_of_two() {
+                let (rule, width) = (ca.rule, ca.width);
+                let message = format!("rule {rule} dies from every start on a ring of 2^n cells, as at width {width}");
+                diagnostics::warn(diagnostics::Kind::WidthArtifact, message);
+            }
+            // A row dead from the start and after one step was never alive
+            return Some(CycleAnalysis {
+                transient: if step == 0 && self.dead_start { 0 } else { step + 1 },
+                period: 1, // stays dead
+                died: true,
+                final_density: 0.0,
+            });
+        }
+
+        // Check if we've seen this state before, and where it first appeared
+        let (cycle_start, period) = self.seen.visit(ca)?;
+        if cycle_start + period > self.max_steps {
+            return Some(self.give_up(ca));
+        }
+        if ca.width > 1 && period.is_multiple_of(ca.width) {
+            let width = ca.width;
+            let message = format!("period {period} at width {width}: a pattern circling the ring, set by the width");
+            diagnostics::warn(diagnostics::Kind::WidthArtifact, message);
+        }
+        Some(CycleAnalysis {
+            transient: cycle_start,
+            period,
+            died: false,
+            final_density: self.seen.repeated(ca).density(),
+        })
+    }
+
+    /// The analysis of a run that didn't close in time, `ca` its last state
+    pub(crate) fn give_up(&self, ca: &Automaton) -> CycleAnalysis {
+        let (max_steps, width) = (self.max_steps, ca.width);
+        let message = format!("cycle detection: no cycle or death within {max_steps} steps at width {width}");
+        diagnostics::warn(diagnostics::Kind::Unconverged, message);
+        CycleAnalysis {
+            transient: self.max_steps,
+            period: 0,
+            died: false,
+            final_density: self.at_limit.unwrap_or_else(|| ca.density()),
+        }
+    }
+}
+
+impl Drop for CycleTracker {
+    fn drop(&mut self) {
+        profile::add_states(self.seen.stored());
+    }
+}
+
+/// Compression analysis: how well does the spacetime diagram compress?
+/// Returns (raw_bits, compressed_bits, ratio)
+pub fn compression_ratio(rule: u8, width: usize, generations: usize) -> (usize, usize, f64) {
+    compression_ratio_from(Automaton::new(width, rule), generations)
+}
+
+/// `compression_ratio` from an arbitrary initial row
+pub fn compression_ratio_from(mut ca: Automaton, generations: usize) -> (usize, usize, f64) {
+    let width = ca.width();
+
+    // Pack spacetime into bytes (8 cells per byte)
+    let total_cells = width * (generations + 1);
+    let mut raw_bytes = Vec::with_capacity(total_cells.div_ceil(8));
+
+    let mut current_byte = 0u8;
+    let mut bit_pos = 0;
+
+    // Helper to flush bits to bytes
+    let flush_cell = |cell: bool, byte: &mut u8, pos: &mut usize, bytes: &mut Vec<u8>| {
+        if cell {
+            *byte |= 1 << (7 - *pos);
+        }
+        *pos += 1;
+        if *pos == 8 {
+            bytes.push(*byte);
+            *byte = 0;
+            *pos = 0;
+        }
+    };
+
+    // First generation; cells are packed as deviations from the background
+    for cell in ca.deviations() {
+        flush_cell(cell, &mut current_byte, &mut bit_pos, &mut raw_bytes);
+    }
+
+    // Subsequent generations
+    for _ in 0..generations {
+        ca.step();
+        for cell in ca.deviations() {
+            flush_cell(cell, &mut current_byte, &mut bit_pos, &mut raw_bytes);
+        }
+    }
+
+    // Flush remaining bits
+    if bit_pos > 0 {
+        raw_bytes.push(current_byte);
+    }
+
+    // Compress with deflate
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(&raw_bytes).unwrap();
+    let compressed = encoder.finish().unwrap();
+
+    let raw_bits = total_cells;
+    let compressed_bits = compressed.len() * 8;
+    let ratio = compressed_bits as f64 / raw_bits as f64;
+
+    (raw_bits, compressed_bits, ratio)
+}
+
+/// Language complexity: how many distinct k-blocks appear in the spacetime diagram?
+///
+/// The words that occur in a rule's rows form its factor language. Periodic and
+/// fractal rules see the number of distinct k-blocks grow polynomially in k;
+/// chaotic rules see it grow exponentially (until the sample size runs out).
+#[derive(Debug)]
+pub struct ComplexityGrowth {
+    /// Generations at which a snapshot of the counts was taken
+    pub times: Vec<usize>,
+    /// counts[s][k - 1] = distinct k-blocks seen in rows skip..=times[s]
+    pub counts: Vec<Vec<usize>>,
+    /// Row width the counts were taken at
+    pub width: usize,
+    /// Transient generations discarded before counting
+    pub skip: usize,
+}
+
+impl ComplexityGrowth {
+    /// Block occurrences available by generation t (one per cell per row);
+    /// no count can exceed this, so counts near it are undersampled
+    pub fn samples_at(&self, t: usize) -> usize {
+        self.width * (t + 1 - self.skip)
+    }
+
+    /// Counts at the final snapshot
+    pub fn final_counts(&self) -> &[usize] {
+        self.counts.last().map(|c| c.as_slice()).unwrap_or(&[])
+    }
+
+    /// Sample ceiling at the final snapshot
+    pub fn final_samples(&self) -> usize {
+        self.samples_at(*self.times.last().unwrap_or(&0))
+    }
+}
+
+/// Count distinct k-blocks (k = 1..=max_k, with wraparound) in rows
+/// skip..=t, snapshotting at `snapshots` evenly spaced times
+pub fn block_complexity(
+    rule: u8,
+    width: usize,
+    generations: usize,
+    max_k: usize,
+    snapshots: usize,
+    skip: usize,
+) -> ComplexityGrowth {
+    block_complexity_from(Automaton::new(width, rule), generations, max_k, snapshots, skip)
+}
+
+/// `block_complexity` from an arbitrary initial row
+pub fn block_complexity_from(
+    mut ca: Automaton,
+    generations: usize,
+    max_k: usize,
+    snapshots: usize,
+    skip: usize,
+) -> ComplexityGrowth {
+    let width = ca.width();
+    let max_k = max_k.min(width).min(64);
+    let snapshots = snapshots.max(1);
+    let mut times: Vec<usize> =
+        (1..=snapshots).map(|s| skip + s * generations / snapshots).collect();
+    times.dedup();
+
+    for _ in 0..skip {
+        ca.step();
+    }
+    let mut seen: Vec<HashSet<u64>> = vec![HashSet::new(); max_k];
+    let mut counts = Vec::with_capacity(times.len());
+    let mut next_snapshot = 0;
+
+    for t in skip..=skip + generations {
+        if t > skip {
+            ca.step();
+        }
+
+        let n = ca.width();
+        for i in 0..n {
+            // Grow the block one cell at a time so every k shares the same scan
+            let mut pattern = 0u64;
+            for k in 1..=max_k {
+                pattern = (pattern << 1) | ca.get((i + k - 1) % n) as u64;
+                seen[k - 1].insert(pattern);
+            }
+        }
+
+        if next_snapshot < times.len() && t == times[next_snapshot] {
+            counts.push(seen.iter().map(|s| s.len()).collect());
+            next_snapshot += 1;
+        }
+    }
+
+    ComplexityGrowth { times, counts, width, skip }
+}
+
+/// How the number of distinct k-blocks grows with k
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GrowthClass {
+    /// Block count stops growing: the language is eventually periodic
+    Bounded,
+    /// N(k) ~ k^d
+    Polynomial,
+    /// N(k) ~ 2^(h k)
+    Exponential,
+}
+
+impl fmt::Display for GrowthClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            GrowthClass::Bounded => "bounded",
+            GrowthClass::Polynomial => "polynomial",
+            GrowthClass::Exponential => "exponential",
+        };
+        f.pad(name)
+    }
+}
+
+/// Fitted growth law for a block-count curve N(k)
+#[derive(Debug)]
+pub struct GrowthFit {
+    /// Best-fitting growth law
+    pub class: GrowthClass,
+    /// Degree d for polynomial growth, entropy h (bits/cell) for exponential
+    pub exponent: f64,
+    /// Goodness of fit of the chosen model
+    pub r_squared: f64,
+    /// Number of block lengths used in the fit
+    pub points: usize,
+}
+
+/// Least-squares line through (x, y); returns (slope, R²)
+fn linear_fit(xs: &[f64], ys: &[f64]) -> (f64, f64) {
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let sxx: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let sxy: f64 = xs.iter().zip(ys).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let syy: f64 = ys.iter().map(|y| (y - mean_y).powi(2)).sum();
+
+    if sxx == 0.0 || syy == 0.0 {
+        return (0.0, 1.0);
+    }
+    let slope = sxy / sxx;
+    (slope, (sxy * sxy) / (sxx * syy))
+}
+
+/// Classify N(k) as bounded, polynomial (log N linear in log k) or
+/// exponential (log N linear in k). Block lengths whose count reaches half
+/// the available samples are dropped: there the curve measures the sample
+/// size, not the rule.
+pub fn fit_growth(counts: &[usize], samples: usize) -> GrowthFit {
+    let mut points: Vec<(usize, usize)> = counts
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| (i + 1, c))
+        .filter(|&(_, c)| c > 0 && 2 * c < samples)
+        .collect();
+    if points.len() < 3 {
+        points = counts.iter().enumerate().map(|(i, &c)| (i + 1, c.max(1))).collect();
+    }
+
+    // Bounded: no growth over the second half of the curve
+    let mid = points.len() / 2;
+    if points.len() < 2 || points[mid].1 == points[points.len() - 1].1 {
+        return GrowthFit {
+            class: GrowthClass::Bounded,
+            exponent: 0.0,
+            r_squared: 1.0,
+            points: points.len(),
+        };
+    }
+
+    let ks: Vec<f64> = points.iter().map(|&(k, _)| k as f64).collect();
+    let log_ks: Vec<f64> = ks.iter().map(|k| k.ln()).collect();
+    let log_ns: Vec<f64> = points.iter().map(|&(_, c)| (c as f64).ln()).collect();
+
+    let (exp_slope, exp_r2) = linear_fit(&ks, &log_ns);
+    let (poly_slope, poly_r2) = linear_fit(&log_ks, &log_ns);
+
+    if exp_r2 > poly_r2 {
+        GrowthFit {
+            class: GrowthClass::Exponential,
+            exponent: exp_slope / std::f64::consts::LN_2,
+            r_squared: exp_r2,
+            points: points.len(),
+        }
+    } else {
+        GrowthFit {
+            class: GrowthClass::Polynomial,
+            exponent: poly_slope,
+            r_squared: poly_r2,
+            points: points.len(),
+        }
+    }
+}
+
+/// Each row's coordinates on the first two principal components of the
+/// rows (as 0/1 vectors), and the fraction of the total variance each
+/// explains. Components come from power iteration on the covariance matrix,
+/// signed so that their largest entry is positive.
+pub fn principal_plane(rows: &[Vec<bool>]) -> (Vec<[f64; 2]>, [f64; 2]) {
+    let (n, width) = (rows.len(), rows.first().map_or(0, Vec::len));
+    let mean: Vec<f64> = (0..width).map(|j| rows.iter().filter(|row| row[j]).count() as f64 / n as f64).collect();
+    let centered: Vec<Vec<f64>> =
+        rows.iter().map(|row| row.iter().zip(&mean).map(|(&x, m)| x as u8 as f64 - m).collect()).collect();
+    let mut covariance = vec![vec![0.0; width]; width];
+    for x in &centered {
+        for j in 0..width {
+            for k in 0..width {
+                covariance[j][k] += x[j] * x[k] / n as f64;
+            }
+        }
+    }
+    let total: f64 = (0..width).map(|j| covariance[j][j]).sum();
+
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f64>();
+    let mut components: Vec<Vec<f64>> = Vec::new();
+    let mut explained = [0.0; 2];
+    for c in 0..2 {
+        // Power iteration, keeping orthogonal to the components found so far
+        let orthonormalize = |v: &mut Vec<f64>| {
+            for u in &components {
+                let overlap = dot(v, u);
+                v.iter_mut().zip(u).for_each(|(x, y)| *x -= overlap * y);
+            }
+            let norm = dot(v, v).sqrt();
+            v.iter_mut().for_each(|x| *x = if norm < 1e-12 { 0.0 } else { *x / norm });
+        };
+        let mut v: Vec<f64> = (0..width).map(|j| 1.0 + j as f64 / width as f64).collect();
+        let mut eigenvalue = 0.0;
+        for _ in 0..1000 {
+            orthonormalize(&mut v);
+            let next: Vec<f64> = covariance.iter().map(|row| dot(row, &v)).collect();
+            let previous = std::mem::replace(&mut eigenvalue, dot(&next, &v));
+            if (eigenvalue - previous).abs() <= 1e-12 * eigenvalue.abs() {
+                break;
+            }
+            v = next;
+        }
+        orthonormalize(&mut v);
+        let largest = v.iter().copied().fold(0.0, |m: f64, x| if x.abs() > m.abs() { x } else { m });
+        if largest < 0.0 {
+            v.iter_mut().for_each(|x| *x = -*x);
+        }
+        explained[c] = if total > 0.0 { eigenvalue.max(0.0) / total } else { 0.0 };
+        components.push(v);
+    }
+
+    let points = centered.iter().map(|x| [dot(x, &components[0]), dot(x, &components[1])]).collect();
+    (points, explained)
+}
+
+impl fmt::Display for Automaton {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for cell in self.deviations() {
+            write!(f, "{}", if cell { '#' } else { ' ' })?;
+        }
+        Ok(())
+    }
+}
+
+/// The "interesting" rules - Wolfram's Class 3 and 4
+pub const INTERESTING_RULES: [u8; 12] = [
+    30,  // Class 3: chaotic
+    45,  // Class 3: chaotic
+    60,  // Class 3: chaotic (XOR)
+    73,  // Class 4: complex
+    89,  // Class 4: complex
+    90,  // Class 3: Sierpinski triangle
+    105, // Class 3: chaotic
+    106, // Class 4: complex
+    110, // Class 4: Turing complete!
+    124, // Class 4: complex
+    137, // Class 4: complex
+    150, // Class 3: chaotic
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_110_known_sequence() {
+        // Rule 110 from single cell should produce known pattern
+        let mut ca = Automaton::new(7, 110);
+        // Initial: ...#...
+        assert_eq!(format!("{ca}"), "   #   ");
+
+        ca.step();
+        // After 1 step: ..##...
+        assert_eq!(format!("{ca}"), "  ##   ");
+
+        ca.step();
+        // After 2 steps: .###...
+        assert_eq!(format!("{ca}"), " ###   ");
+
+        ca.step();
+        // After 3 steps: ##.#...
+        assert_eq!(format!("{ca}"), "## #   ");
+    }
+
+    #[test]
+    fn test_rule_90_sierpinski() {
+        // Rule 90 produces XOR / Sierpinski pattern
+        let mut ca = Automaton::new(7, 90);
+        ca.step();
+        // Should have two cells on either side of center
+        assert_eq!(format!("{ca}"), "  # #  ");
+    }
+
+    #[test]
+    fn test_wrap_around() {
+        // Test that edges wrap
+        let ca = Automaton::from_cells(vec![true, false, false, false, false], 110);
+        // Cell at index 0: neighborhood is (cell[4], cell[0], cell[1]) = (0, 1, 0)
+        // index = 0*4 + 1*2 + 0*1 = 2
+        // Rule 110 = 0b01101110, bit 2 = 1
+        // So cell 0 should become 1
+        let mut ca = ca;
+        ca.step();
+        assert!(ca.get(0));
+    }
+
+    #[test]
+    fn test_block_complexity_growth_classes() {
+        // Rule 0 dies: only the all-zero block survives the transient
+        let dead = block_complexity(0, 101, 100, 10, 1, 50);
+        assert!(dead.final_counts().iter().all(|&c| c == 1));
+        assert_eq!(fit_growth(dead.final_counts(), dead.final_samples()).class, GrowthClass::Bounded);
+
+        // Rule 30's language is full: every k-block appears
+        let chaotic = block_complexity(30, 201, 200, 10, 1, 50);
+        assert_eq!(chaotic.final_counts()[7], 256);
+        let fit = fit_growth(chaotic.final_counts(), chaotic.final_samples());
+        assert_eq!(fit.class, GrowthClass::Exponential);
+        assert!((fit.exponent - 1.0).abs() < 0.05);
+
+        // Rule 90 (Sierpinski) grows polynomially
+        let fractal = block_complexity(90, 201, 200, 12, 1, 50);
+        assert_eq!(fit_growth(fractal.final_counts(), fractal.final_samples()).class, GrowthClass::Polynomial);
+    }
+
+    #[test]
+    fn test_series_period() {
+        let cycle: Vec<f64> = [0.1, 0.5, 0.3].repeat(5);
+        assert_eq!(series_period(&cycle, 10, 1e-9), Some(3));
+        assert_eq!(series_period(&cycle, 2, 1e-9), None);
+        assert_eq!(series_period(&[0.4; 6], 10, 1e-9), Some(1));
+        assert_eq!(series_period(&[0.1, 0.2, 0.4, 0.8], 10, 1e-9), None);
+    }
+
+    #[test]
+    fn test_principal_plane_of_two_states() {
+        // A period-2 orbit varies along one direction only
+        let (a, b) = (vec![true, false, false, true], vec![false, true, false, true]);
+        let (points, explained) = principal_plane(&[a.clone(), b.clone(), a, b]);
+        assert!((explained[0] - 1.0).abs() < 1e-9 && explained[1].abs() < 1e-9);
+        assert!((points[0][0] - points[2][0]).abs() < 1e-9);
+        assert!((points[0][0] + points[1][0]).abs() < 1e-9);
+        assert!((points[0][0].abs() - 0.5f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_word_parallel_step_matches_per_cell() {
+        // Widths straddling word boundaries, including rings of one and two
+        // cells, whose neighborhoods wrap onto themselves
+        for width in [1, 2, 5, 63, 64, 65, 130] {
+            for rule in 0..=255u8 {
+                let mut ca = Automaton::from_cells((0..width).map(|i| (i * 7 + 3) % 5 < 2).collect(), rule);
+                for _ in 0..3 {
+                    let cells = ca.cells();
+                    let expected: Vec<bool> = (0..width)
+                        .map(|i| {
+                            let index = (cells[(i + width - 1) % width] as u8) << 2
+                                | (cells[i] as u8) << 1
+                                | cells[(i + 1) % width] as u8;
+                            (rule >> index) & 1 == 1
+                        })
+                        .collect();
+                    ca.step();
+                    assert_eq!(ca.cells(), expected, "rule {rule}, width {width}");
+                    assert!(ca == Automaton::from_cells(expected, rule));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_table_rules_step_packed_rows() {
+        // Radius 2 and 3 against the per-cell step, on rings down to fewer
+        // cells than a window, which then wraps onto itself
+        for width in [1, 3, 5, 64, 70, 130] {
+            for (radius, seed) in [(2, 1), (2, 2), (3, 3)] {
+                let table = TableRule::random(radius, seed);
+                let mut cells: Vec<bool> = (0..width).map(|i| (i * 7 + 3) % 5 < 2).collect();
+                let mut ca = Automaton::from_cells(cells.clone(), 0).with_table(table);
+                for _ in 0..4 {
+                    cells = table.step(&cells);
+                    ca.step();
+                    assert_eq!(ca.cells(), cells, "{table}, width {width}");
+                }
+            }
+        }
+
+        // A radius-1 table is the ECA it numbers, and another rule drops the table
+        let eca = Automaton::new(9, 0).with_table(TableRule::new(1, 90));
+        assert_eq!((eca.rule(), eca.table()), (90, None));
+        let table = TableRule::random(2, 4);
+        assert_eq!(Automaton::new(9, 0).with_table(table).with_rule(30).table(), None);
+
+        // Cycles, entropy and compression see the table's diagram: a shift by two
+        let shift = TableRule::new(2, (0..32).filter(|w| w & 1 == 1).fold(0, |t, w| t | 1 << w));
+        let ca = Automaton::random(40, 0, 0.5, 9).with_table(shift);
+        let cycle = find_cycle_from(ca.clone(), 100);
+        assert_eq!((cycle.transient, cycle.period), (0, 20));
+        let mut stepped = ca.clone();
+        stepped.step();
+        assert_eq!(stepped.block_entropy(4), ca.block_entropy(4));
+        assert!(compression_ratio_from(ca, 100).2 < 0.5);
+    }
+
+    #[test]
+    fn test_degenerate_parameters() {
+        assert_eq!(check_width(0), Err(ParamError::EmptyRing));
+        assert_eq!(check_width(1), Ok(()));
+        for (block, width) in [(0, 5), (6, 5), (65, 100)] {
+            assert_eq!(check_block(block, width), Err(ParamError::BlockSize { block, width }));
+        }
+
+        // A block as long as the ring: one pattern per rotation, all distinct for an aperiodic row
+        let row = Automaton::random(40, 30, 0.5, 7);
+        assert!((row.block_entropy(40) - 40f64.log2()).abs() < 1e-12);
+        assert_eq!(Automaton::new(5, 30).block_entropy(5), 5f64.log2());
+
+        // An empty initial condition is dead from the start: no transient
+        let dead = find_cycle_from(Automaton::from_cells(vec![false; 8], 90), 100);
+        assert_eq!((dead.transient, dead.period, dead.died), (0, 1, true));
+
+        // Zero generations: the initial row alone
+        let none = find_cycle(30, 8, 0);
+        assert_eq!((none.transient, none.period), (0, 0));
+        assert_eq!(compression_ratio(30, 16, 0).0, 16);
+        assert_eq!(block_complexity(30, 16, 0, 4, 1, 0).final_counts(), [2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_all_rules_deterministic() {
+        // Every rule should be deterministic
+        for rule in 0..=255u8 {
+            let mut ca1 = Automaton::new(20, rule);
+            let mut ca2 = Automaton::new(20, rule);
+            for _ in 0..10 {
+                ca1.step();
+                ca2.step();
+            }
+            assert_eq!(ca1.cells(), ca2.cells());
+        }
+    }
+
+    #[test]
+    fn test_random_row_is_seeded() {
+        let a = Automaton::random(1000, 30, 0.3, 42);
+        assert!(a == Automaton::random(1000, 30, 0.3, 42));
+        assert!(a != Automaton::random(1000, 30, 0.3, 43));
+        assert!((a.density() - 0.3).abs() < 0.05, "density {}", a.density());
+        assert_eq!(Automaton::random(70, 30, 1.0, 0).population(), 70);
+    }
+
+    #[test]
+    fn test_state_hash_is_pinned() {
+        // Stored hashes depend on these: a change here is a new HASH_VERSION
+        assert_eq!(fnv1a([]), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(Automaton::new(7, 30).state_hash(), 0x92fc_16a9_7f3a_6caa);
+        assert_eq!(Automaton::random(200, 30, 0.5, 1).state_hash(), 0xf32e_0e48_9bf4_5422);
+        // The width counts: the same words on a wider ring are another state
+        let one = Automaton::from_words(vec![1], 64, 30);
+        assert_ne!(one.state_hash(), Automaton::from_words(vec![1], 63, 30).state_hash());
+    }
+
+    #[test]
+    fn test_state_index_keys() {
+        // Keys follow the words through every way of changing them
+        let mut ca = Automaton::random(130, 110, 0.5, 3).with_memory(MemoryRule::Xor, 2).with_noise(0.1, 1);
+        for _ in 0..5 {
+            ca.step();
+            assert_eq!(ca.key, row_key(&ca.words));
+        }
+        let flipped = ca.on_background(true);
+        assert_eq!(flipped.key, row_key(&flipped.words));
+        let mut mixed = Automaton::random(130, 110, 0.5, 3);
+        stochastic::step_mixed(&mut mixed, 30, 0.5, &mut ChaCha8Rng::seed_from_u64(0));
+        assert_eq!(mixed.key, row_key(&mixed.words));
+
+        // Two states under one key are told apart by comparing them
+        let (a, b) = (Automaton::new(9, 30), Automaton::random(9, 30, 0.5, 1));
+        let mut index = StateIndex::default();
+        assert_eq!(index.visit(&a), None);
+        index.first.insert(b.state_key(), 0);
+        assert_eq!((index.visit(&b), index.visit(&b), index.visit(&a)), (None, Some(1), Some(0)));
+    }
+
+    #[test]
+    fn test_recalls_find_the_same_cycles() {
+        // Each recall, run step by step, against every state kept
+        let run = |mut ca: Automaton, recall: Recall| {
+            let mut history = History::new(&ca, recall);
+            for _ in 0..2000 {
+                ca.step();
+                if let Some((start, period)) = history.visit(&ca) {
+                    return Some((start, period, history.cycle(start, period)));
+                }
+            }
+            None
+        };
+        let starts = [
+            Automaton::new(17, 110),
+            Automaton::random(12, 30, 0.5, 4),
+            Automaton::random(40, 184, 0.5, 2),
+            Automaton::random(10, 90, 0.5, 7).with_memory(MemoryRule::Majority, 2),
+        ];
+        for start in starts {
+            let states = run(start.clone(), Recall::States);
+            assert!(states.is_some());
+            assert_eq!(run(start.clone(), Recall::Keys), states);
+            assert_eq!(run(start, Recall::Brent), states);
+        }
+
+        // A key held by an earlier, different state doesn't fool keys alone
+        let (a, b) = (Automaton::new(9, 204), Automaton::random(9, 204, 0.5, 1));
+        let mut history = History::new(&a, Recall::Keys);
+        if let History::Keys { first, .. } = &mut history {
+            first.insert(b.state_key(), 0);
+        }
+        assert_eq!((history.visit(&b), history.visit(&b)), (None, Some((1, 1))));
+    }
+
+    #[test]
+    fn test_memory_rules() {
+        // Majority memory against each cell's states counted one by one, at
+        // depths with and without ties, across a word boundary
+        for depth in 1..=6 {
+            let start = Automaton::random(70, 110, 0.5, depth as u64);
+            let mut ca = start.with_memory(MemoryRule::Majority, depth);
+            let mut rows = vec![start.cells(); depth + 1];
+            for _ in 0..8 {
+                let featured: Vec<bool> = (0..70)
+                    .map(|i| {
+                        let live = rows.iter().filter(|row| row[i]).count();
+                        if 2 * live == rows.len() { rows[0][i] } else { 2 * live > rows.len() }
+                    })
+                    .collect();
+                let mut expected = Automaton::from_cells(featured, 110);
+                expected.step();
+                ca.step();
+                assert_eq!(ca.cells(), expected.cells(), "depth {depth}");
+                rows.insert(0, ca.cells());
+                rows.pop();
+            }
+            assert_eq!(ca.state().len(), 2 * (depth + 1));
+        }
+
+        // XOR memory makes any rule reversible: no transient, even for rule 0
+        for rule in [0, 30, 110] {
+            let ca = Automaton::random(12, rule, 0.5, 1).with_memory(MemoryRule::Xor, 1);
+            let cycle = find_cycle_from(ca, 1 << 16);
+            assert!(cycle.transient == 0 && cycle.period > 0 && !cycle.died, "rule {rule}: {cycle:?}");
+        }
+        // Rule 204 copies the row, so rows go x, 0, x, x, 0, ...: the empty
+        // row isn't death, and x coming back isn't the state coming back
+        let mut blink = Automaton::new(5, 204).with_memory(MemoryRule::Xor, 1);
+        blink.step();
+        assert_eq!(blink.population(), 0);
+        assert_eq!(find_cycle_from(blink, 10).period, 3);
+    }
+
+    #[test]
+    fn test_rule_schedules() {
+        // Alternating 90 and 110 is stepping by each in turn
+        let start = Automaton::random(70, 30, 0.5, 2);
+        let mut scheduled = start.with_schedule(Schedule::new(vec![90, 110], 1));
+        let mut by_hand = start.clone();
+        for t in 0..6 {
+            assert_eq!(scheduled.rule(), [90, 110][t % 2]);
+            scheduled.step();
+            by_hand = by_hand.with_rule([90, 110][t % 2]);
+            by_hand.step();
+            assert_eq!(scheduled.words(), by_hand.words());
+        }
+        let blocks = Schedule::new(vec![30, 90, 150], 2);
+        assert_eq!((0..7).map(|t| blocks.rule_at(t)).collect::<Vec<_>>(), [30, 30, 90, 90, 150, 150, 30]);
+        assert_eq!(blocks.to_string(), "30,90,150 in blocks of 2");
+
+        // Complementing then copying brings the row back after two steps,
+        // but the schedule only after four: the composite's period is 4
+        let ca = Automaton::random(9, 0, 0.5, 1).with_schedule(Schedule::new(vec![51, 204], 1));
+        let cycle = find_cycle_from(ca.clone(), 100);
+        assert_eq!((cycle.transient, cycle.period), (0, 4));
+        assert_eq!(find_cycle_from(ca.with_rule(51), 100).period, 2);
+        assert_eq!(ca.state().len(), 2);
+    }
+
+    #[test]
+    fn test_background_counts_deviations() {
+        assert_eq!([0, 255, 1, 232].map(quiescent_state), [Some(false), Some(true), None, Some(false)]);
+
+        // One dead cell in rule 255's live vacuum is one deviation, and it dies out
+        let ca = Automaton::new(9, 255).on_background(true);
+        assert_eq!((ca.population(), ca.to_string(), ca.background()), (1, "    #    ".to_string(), true));
+        assert_eq!(ca.cells().iter().filter(|&&c| !c).count(), 1);
+        let cycle = find_cycle_from(ca.clone(), 10);
+        assert!(cycle.died && cycle.transient == 1 && cycle.final_density == 0.0, "{cycle:?}");
+        assert_eq!(compression_ratio_from(ca, 20), compression_ratio(0, 9, 20));
+
+        // Deviations from a live background follow the 0/1 conjugate rule (110 -> 137)
+        let mut on_ones = Automaton::random(70, 110, 0.2, 5).on_background(true);
+        let mut conjugate = Automaton::random(70, 137, 0.2, 5);
+        for _ in 0..30 {
+            assert_eq!(on_ones.to_string(), conjugate.to_string());
+            assert_eq!(on_ones.population(), conjugate.population());
+            on_ones.step();
+            conjugate.step();
+        }
+        assert_eq!(on_ones.on_background(false).words(), conjugate.words());
+    }
+}
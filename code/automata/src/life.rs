@@ -0,0 +1,243 @@
+//! Life-like cellular automata: two-state cells on a torus, each updated
+//! from how many of its eight neighbors (the Moore neighborhood) are live
+//!
+//! A rule is its births and survivals, B3/S23 for Conway's Life: a dead
+//! cell with 3 live neighbors is born, a live one with 2 or 3 survives, and
+//! every other cell is dead next step. The other rules of the family are
+//! other digit sets (B36/S23 HighLife, B2/S Seeds, B3678/S34678 Day &
+//! Night), and `WolframRule`'s outer-totalistic codes are the same idea on
+//! a ring.
+//!
+//! A step counts neighbors with sums of three along each row, then adds
+//! each row's to the rows above and below. Block entropy reads the grid's
+//! k x k squares (with wraparound), so a grid scores on the same scale as a
+//! ring's k-blocks: bits, at most k² of them.
+
+use crate::shannon_entropy;
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::fmt;
+
+/// Largest block side `Grid::block_entropy` reads: 8 x 8 cells fill a u64
+pub const MAX_BLOCK_SIDE: usize = 8;
+
+/// Births and survivals: bit n set when a cell with n live neighbors is
+/// born (if dead) or survives (if live)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct LifeRule {
+    pub births: u16,
+    pub survivals: u16,
+}
+
+impl LifeRule {
+    /// Conway's Game of Life
+    pub const CONWAY: LifeRule = LifeRule { births: 1 << 3, survivals: 1 << 2 | 1 << 3 };
+
+    /// A cell's next state, from its own and its live neighbors' count
+    pub fn next(self, live: bool, neighbors: usize) -> bool {
+        let sums = if live { self.survivals } else { self.births };
+        (sums >> neighbors) & 1 == 1
+    }
+}
+
+impl std::str::FromStr for LifeRule {
+    type Err = String;
+
+    /// `B<digits>/S<digits>`, digits 0-8 in any order, either letter's
+    /// case, with or without the slash: B3/S23, b36s23, B2/S
+    fn from_str(s: &str) -> Result<Self, String> {
+        let upper = s.to_ascii_uppercase();
+        let rest = upper.strip_prefix('B').ok_or(format!("expected B<births>/S<survivals>, not {s:?}"))?;
+        let (births, survivals) = rest.split_once('S').ok_or(format!("expected B<births>/S<survivals>, not {s:?}"))?;
+        let sums = |digits: &str| -> Result<u16, String> {
+            digits.chars().try_fold(0u16, |sums, c| match c.to_digit(10) {
+                Some(n) if n <= 8 => Ok(sums | 1 << n),
+                _ => Err(format!("{c:?} is not a neighbor count (0-8) in {s:?}")),
+            })
+        };
+        Ok(LifeRule { births: sums(births.strip_suffix('/').unwrap_or(births))?, survivals: sums(survivals)? })
+    }
+}
+
+impl fmt::Display for LifeRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = |sums: u16| -> String {
+            (0..=8).filter(|n| (sums >> n) & 1 == 1).map(|n| n.to_string()).collect()
+        };
+        f.pad(&format!("B{}/S{}", digits(self.births), digits(self.survivals)))
+    }
+}
+
+/// A torus of cells, row-major, under a Life-like rule
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    cells: Vec<bool>,
+    rule: LifeRule,
+}
+
+impl Grid {
+    /// An all-dead grid; panics on a side of 0
+    pub fn new(width: usize, height: usize, rule: LifeRule) -> Self {
+        assert!(width > 0 && height > 0, "a grid needs a cell");
+        Self { width, height, cells: vec![false; width * height], rule }
+    }
+
+    /// Each cell live with probability `density`, drawn row by row from a
+    /// ChaCha8 stream seeded with `seed`
+    pub fn random(width: usize, height: usize, density: f64, seed: u64, rule: LifeRule) -> Self {
+        assert!((0.0..=1.0).contains(&density), "density must be in [0, 1]");
+        let mut grid = Self::new(width, height, rule);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        grid.cells.iter_mut().for_each(|cell| *cell = rng.random_bool(density));
+        grid
+    }
+
+    /// A pattern in plaintext (`.cells`) format, centered on an otherwise
+    /// dead grid: a line per row, `O` (or `*`) live and `.` dead, and lines
+    /// starting with `!` comments. Patterns bigger than the grid are an
+    /// error
+    pub fn from_plaintext(text: &str, width: usize, height: usize, rule: LifeRule) -> Result<Self, String> {
+        let rows: Vec<&str> = text.lines().map(str::trim_end).filter(|line| !line.starts_with('!')).collect();
+        let pattern_width = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+        if pattern_width > width || rows.len() > height {
+            return Err(format!("a {pattern_width} x {} pattern doesn't fit a {width} x {height} grid", rows.len()));
+        }
+        let mut grid = Self::new(width, height, rule);
+        let (left, top) = ((width - pattern_width) / 2, (height - rows.len()) / 2);
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    'O' | '*' => grid.cells[(top + y) * width + left + x] = true,
+                    '.' => {}
+                    _ => return Err(format!("line {}: {c:?} is neither O nor .", y + 1)),
+                }
+            }
+        }
+        Ok(grid)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn rule(&self) -> LifeRule {
+        self.rule
+    }
+
+    /// Cell (x, y), wrapping around both edges
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.cells[(y % self.height) * self.width + x % self.width]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, live: bool) {
+        let (x, y) = (x % self.width, y % self.height);
+        self.cells[y * self.width + x] = live;
+    }
+
+    /// The rows, top first
+    pub fn rows(&self) -> Vec<Vec<bool>> {
+        self.cells.chunks(self.width).map(<[bool]>::to_vec).collect()
+    }
+
+    pub fn step(&mut self) {
+        let (w, h) = (self.width, self.height);
+        // Live cells in each cell's row of three, itself included
+        let across: Vec<u8> = (0..w * h)
+            .map(|i| {
+                let (row, x) = (i - i % w, i % w);
+                let cell = |x: usize| self.cells[row + x % w] as u8;
+                cell(x + w - 1) + cell(x) + cell(x + 1)
+            })
+            .collect();
+        let next = (0..w * h).map(|i| {
+            let (y, x) = (i / w, i % w);
+            let column = |y: usize| across[(y % h) * w + x] as usize;
+            let live = self.cells[i];
+            self.rule.next(live, column(y + h - 1) + column(y) + column(y + 1) - live as usize)
+        });
+        self.cells = next.collect();
+    }
+
+    /// Live cells
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|&&live| live).count()
+    }
+
+    pub fn density(&self) -> f64 {
+        self.population() as f64 / self.cells.len() as f64
+    }
+
+    /// Shannon entropy in bits of the grid's `k` x `k` blocks, one starting
+    /// at every cell (with wraparound): at most k², 0 for a uniform grid
+    pub fn block_entropy(&self, k: usize) -> f64 {
+        assert!((1..=MAX_BLOCK_SIDE).contains(&k), "blocks must be 1 to {MAX_BLOCK_SIDE} cells on a side");
+        let mut patterns: Vec<u64> = (0..self.cells.len())
+            .map(|i| {
+                let (y, x) = (i / self.width, i % self.width);
+                (0..k * k).fold(0u64, |pattern, j| pattern << 1 | self.get(x + j % k, y + j / k) as u64)
+            })
+            .collect();
+        patterns.sort_unstable();
+        shannon_entropy(&patterns.chunk_by(|a, b| a == b).map(<[u64]>::len).collect::<Vec<_>>())
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (y, row) in self.cells.chunks(self.width).enumerate() {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            row.iter().try_for_each(|&live| write!(f, "{}", if live { '#' } else { ' ' }))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_life_patterns() {
+        let rule: LifeRule = "B3/S23".parse().unwrap();
+        assert_eq!(rule, LifeRule::CONWAY);
+        assert_eq!("b36s23".parse::<LifeRule>().unwrap().to_string(), "B36/S23");
+        assert_eq!("B2/S".parse::<LifeRule>().unwrap().survivals, 0);
+        assert!("B9/S23".parse::<LifeRule>().is_err() && "S23".parse::<LifeRule>().is_err());
+
+        // A blinker flips between a row and a column; a block stays put
+        let mut blinker = Grid::from_plaintext("OOO", 5, 5, rule).unwrap();
+        let start = blinker.clone();
+        blinker.step();
+        assert!((0..3).all(|y| blinker.get(2, 1 + y)) && blinker.population() == 3);
+        blinker.step();
+        assert_eq!(blinker, start);
+        let mut block = Grid::from_plaintext("OO\nOO", 4, 4, rule).unwrap();
+        block.step();
+        assert_eq!(block.population(), 4);
+
+        // A glider comes back in 4 steps moved one cell diagonally, across
+        // the edges after enough of them
+        let glider = Grid::from_plaintext("! glider\n.O.\n..O\nOOO", 8, 8, rule).unwrap();
+        let mut moved = glider.clone();
+        (0..32).for_each(|_| moved.step());
+        assert_eq!(moved, glider);
+        let mut once = glider.clone();
+        (0..4).for_each(|_| once.step());
+        assert!((0..64).all(|i| once.get(i % 8 + 1, i / 8 + 1) == glider.get(i % 8, i / 8)));
+
+        // Entropy: none for an empty grid, near the k² bits of noise for a
+        // random one
+        assert_eq!(Grid::new(10, 10, rule).block_entropy(2), 0.0);
+        let noise = Grid::random(200, 200, 0.5, 1, rule).block_entropy(2);
+        assert!((noise - 4.0).abs() < 0.01, "{noise}");
+        assert!(Grid::from_plaintext("OOOOOO", 5, 5, rule).is_err());
+    }
+}
@@ -0,0 +1,709 @@
+//! Rules linear over GF(2): each new cell the XOR of fixed cells around it
+//!
+//! A two-state rule is linear when f(u ⊕ v) = f(u) ⊕ f(v) for every pair of
+//! neighborhoods, which makes it the XOR of the cells at some offsets, its
+//! taps: rule 90 is x[i-1] ⊕ x[i+1], rule 150 adds x[i], rule 60 is
+//! x[i-1] ⊕ x[i]. An affine rule is one complemented, 1 ⊕ the taps (rules
+//! 105, 165, 195). Eight ECAs are linear and eight affine; a radius-r table
+//! is linear for one choice of taps in each of its 2^(2r+1) subsets.
+//!
+//! Linear runs superpose: the run from u ⊕ v is the XOR of the runs from u
+//! and v. On a ring of n cells a step multiplies the row, as a polynomial
+//! mod x^n - 1, by T(x), the sum of x^-o over the taps o (x^-1 + x for rule
+//! 90). Squaring over GF(2) only spreads the exponents, T(x)^2 = T(x^2), so
+//! T^t is the product of T(x^(2^j)) over the bits j of t: any generation of
+//! any row in about log2(t) passes of a few XORed rotations. An affine run
+//! adds the all-1 row whenever the constant terms it has picked up don't
+//! cancel: every generation if the taps are even in number, every odd one
+//! if they're odd.
+//!
+//! `Circulant` is the same step as a matrix: on a ring every row of it is
+//! the row above rotated a cell, so it is one row of n bits, and products
+//! and powers of circulants are circulant. Repeated squaring reaches T^t in
+//! O(log t) products of n^2/64 word operations, for any circulant affine
+//! map and not just a rule's own step, which suits rings of a few thousand
+//! cells; `advance` is the sparse shortcut for a single rule.
+//!
+//! The same algebra says how a run ends without running it: the row v
+//! returns after p steps from generation t just when x^n - 1 divides
+//! T^t (T^p - 1) v, so the transient and period are a polynomial's
+//! multiplicity and a multiplicative order. `orbit` finds both, periods far
+//! beyond anything a simulation would see repeat.
+
+use crate::evolve::TableRule;
+use crate::preimage::Natural;
+use crate::Automaton;
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::fmt;
+
+/// A linear or affine rule's XOR structure
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Linear {
+    pub radius: usize,
+    /// Offsets of the cells XORed, left to right
+    pub taps: Vec<isize>,
+    /// 1 ⊕ the taps, rather than the taps alone
+    pub affine: bool,
+}
+
+impl Linear {
+    /// The rule's XOR structure, or a neighborhood that has none: its taps
+    /// are the one-cell neighborhoods that map away from the all-0 one
+    pub fn detect(rule: TableRule) -> Result<Linear, String> {
+        let size = 2 * rule.radius + 1;
+        let maps = |w: usize| rule.table >> w & 1 == 1;
+        let affine = maps(0);
+        // Windows are read leftmost cell first, the most significant bit
+        let taps = (0..size).filter(|&j| maps(1 << (size - 1 - j)) != affine);
+        let taps = taps.map(|j| j as isize - rule.radius as isize).collect();
+        let linear = Linear { radius: rule.radius, taps, affine };
+        match (0..rule.entries()).find(|&w| maps(w) != linear.maps(w)) {
+            None => Ok(linear),
+            Some(w) => {
+                let (window, to) = (format!("{w:0size$b}"), maps(w) as u8);
+                Err(format!("{window} maps to {to}, not the {} its cells' own images XOR to", 1 - to))
+            }
+        }
+    }
+
+    /// An elementary rule's XOR structure
+    pub fn elementary(rule: u8) -> Result<Linear, String> {
+        Self::detect(TableRule::new(1, rule as u128))
+    }
+
+    /// The next state of window `w` (leftmost cell most significant)
+    fn maps(&self, w: usize) -> bool {
+        let size = 2 * self.radius + 1;
+        let tapped = |&o: &isize| w >> (size - 1 - (o + self.radius as isize) as usize) & 1 == 1;
+        (self.taps.iter().filter(|o| tapped(o)).count() % 2 == 1) != self.affine
+    }
+
+    /// The linear part of the step as a polynomial in the shift, x^-o for
+    /// each tap o, lowest power first: `x^-1 + x`
+    pub fn polynomial(&self) -> String {
+        let term = |o: isize| match -o {
+            0 => "1".to_string(),
+            1 => "x".to_string(),
+            e => format!("x^{e}"),
+        };
+        let terms: Vec<String> = self.taps.iter().rev().map(|&o| term(o)).collect();
+        if terms.is_empty() { "0".to_string() } else { terms.join(" + ") }
+    }
+
+    /// Generation `t` of `cells` on a ring, without stepping through the
+    /// ones before
+    pub fn advance(&self, cells: &[bool], t: u64) -> Vec<bool> {
+        let n = cells.len();
+        let mut row = cells.to_vec();
+        // 2^j mod n: T(x^(2^j)) reads the cells o * 2^j away
+        let mut power = 1 % n;
+        for j in 0..u64::BITS {
+            if t >> j == 0 {
+                break;
+            }
+            if t >> j & 1 == 1 {
+                let mut next = vec![false; n];
+                for &o in &self.taps {
+                    let shift = (o.rem_euclid(n as isize) as usize * power) % n;
+                    for i in 0..n {
+                        next[i] ^= row[(i + shift) % n];
+                    }
+                }
+                row = next;
+            }
+            power = power * 2 % n;
+        }
+        let ones = if self.taps.len() % 2 == 1 { t % 2 == 1 } else { t >= 1 };
+        if self.affine && ones {
+            row.iter_mut().for_each(|cell| *cell = !*cell);
+        }
+        row
+    }
+
+    /// The transient and period of the run from `cells` on their ring, by
+    /// algebra rather than stepping (a linear rule's only)
+    pub fn orbit(&self, cells: &[bool]) -> Result<Orbit, String> {
+        if self.affine {
+            return Err("an affine rule's orbits aren't worked out here, only a linear one's".to_string());
+        }
+        let n = cells.len();
+        assert!(n > 0, "an empty ring");
+        let ring = Poly::monomials([0, n]);
+        let step = Poly::monomials(self.taps.iter().map(|&o| (-o).rem_euclid(n as isize) as usize)).rem(&ring);
+        let row = Poly::monomials((0..n).filter(|&i| cells[i]));
+        // T^a v = T^b v mod x^n - 1 just when h divides T^a - T^b, for h the
+        // ring's polynomial over its common factor with the row
+        let h = ring.div_rem(&ring.gcd(&row)).0;
+        // h's factors that T shares must be killed off, the transient; on
+        // the rest T is a unit, and the period is its order there
+        let mut unit = h.clone();
+        loop {
+            let common = unit.gcd(&step);
+            if common.is_one() {
+                break;
+            }
+            unit = unit.div_rem(&common).0;
+        }
+        let nilpotent = h.div_rem(&unit).0;
+        let (mut transient, mut power) = (0, Poly::one().rem(&nilpotent));
+        while !power.is_zero() {
+            power = power.mul_mod(&step, &nilpotent);
+            transient += 1;
+        }
+        // x^n - 1 = (x^m - 1)^(2^k) for odd m, whose roots lie in GF(2^s)
+        // for s the order of 2 mod m: the unit group's exponent divides
+        // 2^k (2^s - 1), and the order is what's left after dropping every
+        // prime factor that isn't needed
+        let k = n.trailing_zeros();
+        let m = (n >> k) as u64;
+        let s = (1..=m).find(|&s| pow_mod(2, s, m) == 1 % m).expect("2 is a unit mod odd m");
+        Ok(Orbit { transient, period: order(&step, &unit, k, s), bound: (k, s) })
+    }
+
+    /// `orbit` for the rule's step on a ring of `n` cells as a whole: from
+    /// a single live cell the run takes the longest transient of any row,
+    /// and its period is the one every row's divides
+    pub fn ring(&self, n: usize) -> Result<Orbit, String> {
+        let mut cell = vec![false; n];
+        cell[0] = true;
+        self.orbit(&cell)
+    }
+}
+
+/// How a linear run on a ring ends, worked out over GF(2)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Orbit {
+    /// Steps before the run enters its cycle
+    pub transient: usize,
+    /// The cycle's length, or None when 2^s - 1 can't be factored here
+    pub period: Option<Natural>,
+    /// k and s with the period dividing 2^k (2^s - 1), for a ring of
+    /// 2^k m cells with m odd and s the order of 2 mod m
+    pub bound: (u32, u64),
+}
+
+impl fmt::Display for Linear {
+    /// The new cell's formula: `x[i-1] ⊕ x[i+1]`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cell = |o: isize| match o {
+            0 => "x[i]".to_string(),
+            o => format!("x[i{o:+}]"),
+        };
+        let mut terms: Vec<String> = self.taps.iter().map(|&o| cell(o)).collect();
+        if self.affine {
+            terms.insert(0, "1".to_string());
+        }
+        match terms.is_empty() {
+            true => f.pad("0"),
+            false => f.pad(&terms.join(" ⊕ ")),
+        }
+    }
+}
+
+/// An affine map of a ring of `n` cells that commutes with rotation: each
+/// new cell is the XOR of the cells at the offsets in its row (offset k
+/// set at bit k, reading k cells to the right, wrapping), complemented if
+/// `constant` is set
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Circulant {
+    n: usize,
+    words: Vec<u64>,
+    pub constant: bool,
+}
+
+impl Circulant {
+    /// The map that changes nothing
+    pub fn identity(n: usize) -> Circulant {
+        let mut words = vec![0; n.div_ceil(64)];
+        words[0] = 1;
+        Circulant { n, words, constant: false }
+    }
+
+    /// One step of `linear` on a ring of `n` cells; taps that land on the
+    /// same cell of a small ring cancel, as their XOR does
+    pub fn step(linear: &Linear, n: usize) -> Circulant {
+        let mut words = vec![0; n.div_ceil(64)];
+        for &o in &linear.taps {
+            let k = o.rem_euclid(n as isize) as usize;
+            words[k / 64] ^= 1 << (k % 64);
+        }
+        Circulant { n, words, constant: linear.affine }
+    }
+
+    pub fn width(&self) -> usize {
+        self.n
+    }
+
+    /// Offsets the map reads, nearest the cell itself first
+    pub fn offsets(&self) -> Vec<isize> {
+        let signed = |k: usize| if 2 * k > self.n { k as isize - self.n as isize } else { k as isize };
+        let mut offsets: Vec<isize> = (0..self.n).filter(|&k| self.has(k)).map(signed).collect();
+        offsets.sort_by_key(|&o| (o.abs(), o));
+        offsets
+    }
+
+    fn has(&self, k: usize) -> bool {
+        self.words[k / 64] >> (k % 64) & 1 == 1
+    }
+
+    /// `other` and then this: offsets add, so the row is the cyclic
+    /// convolution of the two, and `other`'s constant goes through this
+    /// map's XOR, flipping every cell once per offset
+    pub fn after(&self, other: &Circulant) -> Circulant {
+        assert_eq!(self.n, other.n, "circulants of different widths");
+        let doubled = doubled(&other.words, self.n);
+        let mut words = vec![0; self.words.len()];
+        // Offset k of this map turns other's offset j into j + k: its row
+        // rotated k places the other way
+        for k in (0..self.n).filter(|&k| self.has(k)) {
+            xor_window(&mut words, &doubled, (self.n - k) % self.n, self.n);
+        }
+        let parity = self.words.iter().map(|w| w.count_ones()).sum::<u32>() % 2 == 1;
+        Circulant { n: self.n, words, constant: self.constant ^ (other.constant && parity) }
+    }
+
+    /// The map applied `t` times, by repeated squaring
+    pub fn pow(&self, mut t: u64) -> Circulant {
+        let (mut power, mut result) = (self.clone(), Circulant::identity(self.n));
+        while t > 0 {
+            if t & 1 == 1 {
+                result = power.after(&result);
+            }
+            t >>= 1;
+            if t > 0 {
+                power = power.after(&power);
+            }
+        }
+        result
+    }
+
+    /// The map applied to a row of `n` cells
+    pub fn apply(&self, cells: &[bool]) -> Vec<bool> {
+        assert_eq!(cells.len(), self.n, "a row of the wrong width");
+        let mut packed = vec![0u64; self.words.len()];
+        for i in (0..self.n).filter(|&i| cells[i]) {
+            packed[i / 64] |= 1 << (i % 64);
+        }
+        let doubled = doubled(&packed, self.n);
+        let mut next = vec![0; self.words.len()];
+        for k in (0..self.n).filter(|&k| self.has(k)) {
+            xor_window(&mut next, &doubled, k, self.n);
+        }
+        (0..self.n).map(|i| (next[i / 64] >> (i % 64) & 1 == 1) != self.constant).collect()
+    }
+}
+
+/// `n` packed bits twice over, so any rotation is a window of `n` of them
+fn doubled(words: &[u64], n: usize) -> Vec<u64> {
+    let mut doubled = vec![0u64; (2 * n).div_ceil(64) + 1];
+    for i in (0..n).filter(|&i| words[i / 64] >> (i % 64) & 1 == 1) {
+        for j in [i, i + n] {
+            doubled[j / 64] |= 1 << (j % 64);
+        }
+    }
+    doubled
+}
+
+/// XOR bits `k..k + n` of `doubled` into the `n` bits of `into`
+fn xor_window(into: &mut [u64], doubled: &[u64], k: usize, n: usize) {
+    let (word, shift) = (k / 64, k % 64);
+    for (j, out) in into.iter_mut().enumerate() {
+        let low = doubled[word + j] >> shift;
+        let high = if shift == 0 { 0 } else { doubled[word + j + 1] << (64 - shift) };
+        *out ^= low | high;
+    }
+    if !n.is_multiple_of(64) {
+        *into.last_mut().unwrap() &= (1 << (n % 64)) - 1;
+    }
+}
+
+/// A polynomial over GF(2), coefficient i at bit i, with no zero words on top
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Poly(Vec<u64>);
+
+impl Poly {
+    fn one() -> Poly {
+        Poly(vec![1])
+    }
+
+    /// The sum of x^e over `exponents`, which cancel in pairs
+    fn monomials(exponents: impl IntoIterator<Item = usize>) -> Poly {
+        let mut words = Vec::new();
+        for e in exponents {
+            if words.len() <= e / 64 {
+                words.resize(e / 64 + 1, 0);
+            }
+            words[e / 64] ^= 1 << (e % 64);
+        }
+        Poly(words).trimmed()
+    }
+
+    fn trimmed(mut self) -> Poly {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+        self
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn is_one(&self) -> bool {
+        self.0 == [1]
+    }
+
+    fn degree(&self) -> Option<usize> {
+        self.0.last().map(|top| 64 * self.0.len() - 1 - top.leading_zeros() as usize)
+    }
+
+    /// This plus `other` times x^shift
+    fn add_shifted(&mut self, other: &Poly, shift: usize) {
+        let (word, bit) = (shift / 64, shift % 64);
+        self.0.resize(self.0.len().max(word + other.0.len() + 1), 0);
+        for (j, &w) in other.0.iter().enumerate() {
+            self.0[word + j] ^= w << bit;
+            if bit > 0 {
+                self.0[word + j + 1] ^= w >> (64 - bit);
+            }
+        }
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    fn mul(&self, other: &Poly) -> Poly {
+        let mut product = Poly(Vec::new());
+        for i in 0..64 * self.0.len() {
+            if self.0[i / 64] >> (i % 64) & 1 == 1 {
+                product.add_shifted(other, i);
+            }
+        }
+        product
+    }
+
+    /// Quotient and remainder by a nonzero `m`
+    fn div_rem(&self, m: &Poly) -> (Poly, Poly) {
+        let top = m.degree().expect("division by zero");
+        let (mut quotient, mut rest) = (Poly(Vec::new()), self.clone());
+        while let Some(d) = rest.degree().filter(|&d| d >= top) {
+            quotient.add_shifted(&Poly::one(), d - top);
+            rest.add_shifted(m, d - top);
+        }
+        (quotient, rest)
+    }
+
+    fn rem(&self, m: &Poly) -> Poly {
+        self.div_rem(m).1
+    }
+
+    fn gcd(&self, other: &Poly) -> Poly {
+        let (mut a, mut b) = (self.clone(), other.clone());
+        while !b.is_zero() {
+            (a, b) = (b.clone(), a.rem(&b));
+        }
+        a
+    }
+
+    fn mul_mod(&self, other: &Poly, m: &Poly) -> Poly {
+        self.mul(other).rem(m)
+    }
+
+    fn pow_mod(&self, mut e: u64, m: &Poly) -> Poly {
+        let (mut power, mut result) = (self.rem(m), Poly::one().rem(m));
+        while e > 0 {
+            if e & 1 == 1 {
+                result = result.mul_mod(&power, m);
+            }
+            e >>= 1;
+            if e > 0 {
+                power = power.mul_mod(&power, m);
+            }
+        }
+        result
+    }
+}
+
+/// The multiplicative order of `step` mod `unit`, a divisor of 2^k (2^s - 1)
+/// found by dropping its prime factors while the power stays 1
+fn order(step: &Poly, unit: &Poly, k: u32, s: u64) -> Option<Natural> {
+    if unit.is_one() {
+        return Some(Natural::one());
+    }
+    let mut exponent: Vec<u64> = vec![2; k as usize];
+    exponent.extend(mersenne_factors(s)?);
+    let one = |exponent: &[u64]| exponent.iter().fold(step.rem(unit), |p, &q| p.pow_mod(q, unit)).is_one();
+    debug_assert!(one(&exponent), "T^(2^k (2^s - 1)) isn't 1 mod the unit part");
+    let mut primes = exponent.clone();
+    primes.dedup();
+    for q in primes {
+        while let Some(i) = exponent.iter().position(|&p| p == q) {
+            let rest: Vec<u64> = [&exponent[..i], &exponent[i + 1..]].concat();
+            if !one(&rest) {
+                break;
+            }
+            exponent = rest;
+        }
+    }
+    let mut period = Natural::one();
+    exponent.iter().for_each(|&q| period.times(q));
+    Some(period)
+}
+
+fn pow_mod(base: u64, mut e: u64, m: u64) -> u64 {
+    let mul = |a: u64, b: u64| (a as u128 * b as u128 % m as u128) as u64;
+    let (mut power, mut result) = (base % m, 1 % m);
+    while e > 0 {
+        if e & 1 == 1 {
+            result = mul(result, power);
+        }
+        power = mul(power, power);
+        e >>= 1;
+    }
+    result
+}
+
+/// The prime factors of 2^s - 1 with multiplicity, if each of its
+/// cyclotomic parts Φ_d(2), over the divisors d of s, fits a u64
+fn mersenne_factors(s: u64) -> Option<Vec<u64>> {
+    let mut primes = Vec::new();
+    for d in (1..=s).filter(|d| s.is_multiple_of(*d)) {
+        factor(cyclotomic_at_two(d)?, &mut primes);
+    }
+    primes.sort_unstable();
+    Some(primes)
+}
+
+/// Φ_d(2), if it fits a u64: x^d - 1 divided by Φ_e for every proper
+/// divisor e of d, evaluated at 2
+fn cyclotomic_at_two(d: u64) -> Option<u64> {
+    // Φ_d(2) is about 2^φ(d), so bigger d never fit
+    let totient = (1..=d).filter(|&j| gcd(j, d) == 1).count();
+    if totient > 64 {
+        return None;
+    }
+    let value = cyclotomic(d).iter().rev().try_fold(0i128, |acc, &c| acc.checked_mul(2)?.checked_add(c as i128))?;
+    u64::try_from(value).ok()
+}
+
+/// Φ_d's integer coefficients, lowest first
+fn cyclotomic(d: u64) -> Vec<i64> {
+    let mut poly = vec![0i64; d as usize + 1];
+    (poly[0], poly[d as usize]) = (-1, 1);
+    for e in (1..d).filter(|e| d.is_multiple_of(*e)) {
+        // Exact division by a monic divisor, from the top down
+        let divisor = cyclotomic(e);
+        let shift = divisor.len() - 1;
+        let mut quotient = vec![0; poly.len() - shift];
+        for i in (0..quotient.len()).rev() {
+            quotient[i] = poly[i + shift];
+            for (j, &c) in divisor.iter().enumerate() {
+                poly[i + j] -= quotient[i] * c;
+            }
+        }
+        poly = quotient;
+    }
+    poly
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b > 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Deterministic Miller-Rabin, exact for every u64 with these bases
+fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    if let Some(&p) = BASES.iter().find(|&&p| n.is_multiple_of(p)) {
+        return n == p;
+    }
+    let (zeros, odd) = ((n - 1).trailing_zeros(), (n - 1) >> (n - 1).trailing_zeros());
+    BASES.iter().all(|&a| {
+        let mut x = pow_mod(a, odd, n);
+        if x == 1 || x == n - 1 {
+            return true;
+        }
+        (1..zeros).any(|_| {
+            x = (x as u128 * x as u128 % n as u128) as u64;
+            x == n - 1
+        })
+    })
+}
+
+/// `n`'s prime factors with multiplicity, by Pollard's rho
+fn factor(n: u64, into: &mut Vec<u64>) {
+    if n == 1 {
+        return;
+    }
+    if is_prime(n) {
+        return into.push(n);
+    }
+    if n.is_multiple_of(2) {
+        into.push(2);
+        return factor(n / 2, into);
+    }
+    for c in 1.. {
+        let f = |x: u64| ((x as u128 * x as u128 + c as u128) % n as u128) as u64;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            (x, y) = (f(x), f(f(y)));
+            d = gcd(x.abs_diff(y), n);
+        }
+        if d != n {
+            factor(d, into);
+            return factor(n / d, into);
+        }
+    }
+}
+
+/// A ring stepped by `rule`, as the analyses step it
+fn automaton(cells: Vec<bool>, rule: TableRule) -> Automaton {
+    match rule.radius {
+        1 => Automaton::from_cells(cells, rule.table as u8),
+        _ => Automaton::from_cells(cells, 0).with_table(rule),
+    }
+}
+
+/// Whether runs of `rule` superpose, checked by simulation: on `samples`
+/// random pairs of rows u and v of `width` cells, every one of `steps`
+/// generations from u ⊕ v is those from u and v XORed (and, for an affine
+/// rule, the run from the all-0 row). The cells compared, or the first
+/// generation that doesn't add up
+pub fn superposes(rule: TableRule, width: usize, steps: usize, samples: usize, seed: u64) -> Result<usize, String> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    for sample in 0..samples {
+        let u: Vec<bool> = (0..width).map(|_| rng.random_bool(0.5)).collect();
+        let v: Vec<bool> = (0..width).map(|_| rng.random_bool(0.5)).collect();
+        let sum = u.iter().zip(&v).map(|(a, b)| a ^ b).collect();
+        let mut runs = [u, v, sum, vec![false; width]].map(|cells| automaton(cells, rule));
+        for step in 1..=steps {
+            runs.iter_mut().for_each(Automaton::step);
+            let [u, v, sum, zero] = runs.each_ref().map(Automaton::cells);
+            if (0..width).any(|i| sum[i] != u[i] ^ v[i] ^ zero[i]) {
+                return Err(format!("pair {sample}: generation {step} from u ⊕ v isn't theirs XORed"));
+            }
+        }
+    }
+    Ok(samples * steps * width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_cycle_from;
+
+    #[test]
+    fn test_linear_rules() {
+        let linear: Vec<u8> = (0..=255).filter(|&rule| Linear::elementary(rule).is_ok_and(|l| !l.affine)).collect();
+        assert_eq!(linear, [0, 60, 90, 102, 150, 170, 204, 240]);
+        let affine: Vec<u8> = (0..=255).filter(|&rule| Linear::elementary(rule).is_ok_and(|l| l.affine)).collect();
+        assert_eq!(affine, [15, 51, 85, 105, 153, 165, 195, 255]);
+        let rule90 = Linear::elementary(90).unwrap();
+        assert_eq!((rule90.to_string(), rule90.polynomial()), ("x[i-1] ⊕ x[i+1]".to_string(), "x^-1 + x".to_string()));
+        assert_eq!(Linear::elementary(105).unwrap().to_string(), "1 ⊕ x[i-1] ⊕ x[i] ⊕ x[i+1]");
+        assert_eq!(Linear::elementary(60).unwrap().polynomial(), "1 + x");
+        assert!(Linear::elementary(110).is_err());
+        // A radius-2 table: x[i-2] ⊕ x[i+1]
+        let table = TableRule::new(2, (0..32u128).filter(|w| (w >> 4 ^ w >> 1) & 1 == 1).map(|w| 1 << w).sum());
+        assert_eq!(Linear::detect(table).unwrap().taps, [-2, 1]);
+
+        // Any generation exactly, checked against stepping, on rings odd,
+        // even and a power of two
+        for rule in [90, 150, 60, 105, 195, 0, 255] {
+            let linear = Linear::elementary(rule).unwrap();
+            for width in [1, 13, 64, 101] {
+                let start = Automaton::random(width, rule, 0.5, width as u64).cells();
+                let mut ca = automaton(start.clone(), TableRule::new(1, rule as u128));
+                for t in 0..=70 {
+                    assert_eq!(linear.advance(&start, t), ca.cells(), "rule {rule} width {width} t {t}");
+                    ca.step();
+                }
+            }
+        }
+        let mut ca = automaton(vec![true; 37], table);
+        (0..50).for_each(|_| ca.step());
+        assert_eq!(Linear::detect(table).unwrap().advance(&[true; 37], 50), ca.cells());
+        // From one cell, rule 90 has 2^(ones in t) live cells until they meet
+        let mut one = vec![false; 1 << 20];
+        one[1 << 19] = true;
+        let t = 0b1011_0110_1001u64;
+        assert_eq!(rule90.advance(&one, t).iter().filter(|&&c| c).count(), 1 << t.count_ones());
+
+        assert_eq!(superposes(TableRule::new(1, 150), 40, 30, 5, 1), Ok(40 * 30 * 5));
+        assert!(superposes(TableRule::new(1, 165), 40, 30, 5, 1).is_ok());
+        assert!(superposes(table, 40, 30, 5, 1).is_ok());
+        assert!(superposes(TableRule::new(1, 30), 40, 30, 5, 1).is_err());
+
+        // The circulant power agrees with the shortcut, on rings around a
+        // word's width and for huge t
+        for rule in [90, 150, 60, 105, 195] {
+            let linear = Linear::elementary(rule).unwrap();
+            for width in [1, 2, 63, 64, 65, 130] {
+                let start = Automaton::random(width, rule, 0.5, 7).cells();
+                let step = Circulant::step(&linear, width);
+                for t in [0, 1, 2, 5, 31, 64, 1000, 123_456_789_012] {
+                    assert_eq!(step.pow(t).apply(&start), linear.advance(&start, t), "rule {rule} width {width} t {t}");
+                }
+            }
+        }
+        // And with stepping itself, for t off the powers of two
+        for rule in [90, 150] {
+            let linear = Linear::elementary(rule).unwrap();
+            for width in [37, 70] {
+                let start = Automaton::random(width, rule, 0.5, 3).cells();
+                let step = Circulant::step(&linear, width);
+                let mut ca = automaton(start.clone(), TableRule::new(1, rule as u128));
+                for t in 1..=157 {
+                    ca.step();
+                    if [3, 7, 12, 100, 157].contains(&t) {
+                        assert_eq!(step.pow(t).apply(&start), ca.cells(), "rule {rule} width {width} t {t}");
+                    }
+                }
+            }
+        }
+        // Rule 90 squared reads two cells each way; 2^k steps only the 2^k-th
+        assert_eq!(Circulant::step(&rule90, 101).pow(2).offsets(), [-2, 2]);
+        assert_eq!(Circulant::step(&rule90, 101).pow(1 << 5).offsets(), [-32, 32]);
+        assert_eq!(Circulant::step(&rule90, 64).pow(64).offsets(), []);
+    }
+
+    #[test]
+    fn test_orbits_match_simulation() {
+        let rule90 = Linear::elementary(90).unwrap();
+        for rule in [90, 150, 60, 102, 170, 204, 0] {
+            let linear = Linear::elementary(rule).unwrap();
+            for width in [1, 2, 3, 5, 8, 12, 16, 17, 21, 24, 31] {
+                for seed in 0..3 {
+                    let start = Automaton::random(width, rule, 0.5, seed).cells();
+                    let simulated = find_cycle_from(automaton(start.clone(), TableRule::new(1, rule as u128)), 1 << 20);
+                    let orbit = linear.orbit(&start).unwrap();
+                    let period = orbit.period.and_then(|p| p.to_u64());
+                    let expected = (simulated.transient, Some(simulated.period as u64));
+                    assert_eq!((orbit.transient, period), expected, "rule {rule} width {width}");
+                }
+            }
+        }
+        // Rule 90 from one cell (Martin, Odlyzko and Wolfram's table): odd
+        // rings lose it in a step and cycle with a period dividing 2^s - 1;
+        // a ring of 2^k cells dies in 2^(k-1)
+        let cycle = |n: usize| rule90.ring(n).unwrap();
+        assert_eq!((cycle(37).transient, cycle(37).period), (1, Some(87381.into())));
+        assert_eq!((cycle(1024).transient, cycle(1024).period), (512, Some(1.into())));
+        // Past simulation's reach, the period still returns the row
+        let start = Automaton::random(101, 150, 0.5, 1).cells();
+        let rule150 = Linear::elementary(150).unwrap();
+        let orbit = rule150.orbit(&start).unwrap();
+        let (t, p) = (orbit.transient as u64, orbit.period.unwrap().to_u64().unwrap());
+        let returns = |p: u64| rule150.advance(&start, t + p) == rule150.advance(&start, t);
+        assert!(returns(p) && [2, 3, 5, 7].iter().all(|q| p % q > 0 || !returns(p / q)));
+        // 2 has order 148 mod 149, and Φ_148(2) is too big to factor here
+        assert_eq!((cycle(149).period, cycle(149).bound), (None, (0, 148)));
+        assert!(Linear::elementary(105).unwrap().orbit(&start).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+//! Provenance for exported files: the parameters that made an artifact,
+//! embedded where its format has room for them
+//!
+//! PNGs (APNGs included) get one `tEXt` chunk per entry right after `IHDR`,
+//! SVGs a `<metadata>` element after the opening tag, netpbm images `#`
+//! comment lines after the magic number, and CSV files `# key: value` lines
+//! before the header (Graphviz files `//` lines, GraphML an XML comment).
+//! `Tagged` does the first three for any writer by looking at the first
+//! bytes written, so the encoders in `render` need not know about it;
+//! formats without room (NPY, JSON) get a sidecar file from the caller
+//! instead.
+
+use crate::render::{write_chunk, PNG_SIGNATURE};
+use std::io::{self, Write};
+
+/// The signature and the IHDR chunk: length, type, 13 bytes of data, CRC
+const PNG_HEAD: usize = 8 + 4 + 4 + 13 + 4;
+
+/// Keys and values in the order they were added
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata(pub Vec<(&'static str, String)>);
+
+impl Metadata {
+    /// The software and version, and the command line that ran it
+    pub fn new(command_line: &str) -> Self {
+        Self(vec![
+            ("software", format!("automata {}", env!("CARGO_PKG_VERSION"))),
+            ("command", command_line.to_string()),
+        ])
+    }
+
+    pub fn with(mut self, key: &'static str, value: impl ToString) -> Self {
+        self.0.push((key, value.to_string()));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// `# key: value` lines to start a CSV file with
+    pub fn csv_header(&self) -> String {
+        self.comments("# ")
+    }
+
+    /// `key: value` lines, each after `prefix`
+    pub fn comments(&self, prefix: &str) -> String {
+        self.0.iter().map(|(key, value)| format!("{prefix}{key}: {}\n", one_line(value))).collect()
+    }
+
+    /// PNG `tEXt` chunk data: keyword, NUL, Latin-1 text
+    fn png_texts(&self) -> Vec<Vec<u8>> {
+        let latin1 = |s: &str| s.chars().map(|c| u8::try_from(c as u32).unwrap_or(b'?')).collect::<Vec<u8>>();
+        self.0
+            .iter()
+            .map(|(key, value)| {
+                let mut data = latin1(key);
+                data.truncate(79);
+                data.push(0);
+                data.extend(latin1(value).into_iter().filter(|&b| b != 0));
+                data
+            })
+            .collect()
+    }
+
+    fn svg_element(&self) -> String {
+        let escape = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;");
+        let lines: String =
+            self.0.iter().map(|(key, value)| format!("{key}: {}\n", escape(&one_line(value)))).collect();
+        format!("<metadata>\n{lines}</metadata>\n")
+    }
+}
+
+fn one_line(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
+
+/// A writer that adds `metadata` to a PNG, SVG or netpbm image passing
+/// through it, and passes anything else through unchanged
+pub struct Tagged<'a, W: Write> {
+    inner: W,
+    metadata: &'a Metadata,
+    /// Bytes held back until the place for the metadata is known
+    head: Vec<u8>,
+    done: bool,
+}
+
+impl<'a, W: Write> Tagged<'a, W> {
+    pub fn new(inner: W, metadata: &'a Metadata) -> Self {
+        let done = metadata.is_empty();
+        Self { inner, metadata, head: Vec::new(), done }
+    }
+
+    /// Write out the head once it is long enough to place the metadata,
+    /// or to tell that this isn't a format with room for it
+    fn place(&mut self) -> io::Result<()> {
+        let head = &self.head;
+        let first_line = head.iter().position(|&b| b == b'\n').map(|i| i + 1);
+        let split = if head.starts_with(PNG_SIGNATURE) {
+            if head.len() < PNG_HEAD {
+                return Ok(());
+            }
+            Some((PNG_HEAD, Embed::Png))
+        } else if PNG_SIGNATURE.starts_with(head) || b"<svg".starts_with(head) || head == b"P" {
+            return Ok(());
+        } else if head.starts_with(b"<svg") || matches!(head[..], [b'P', b'1'..=b'7', ..]) {
+            // The opening tag and the magic number end their lines
+            match first_line {
+                Some(at) if head[0] == b'<' => Some((at, Embed::Svg)),
+                Some(at) => Some((at, Embed::Netpbm)),
+                None => return Ok(()),
+            }
+        } else {
+            None
+        };
+
+        self.done = true;
+        let head = std::mem::take(&mut self.head);
+        let Some((at, embed)) = split else {
+            return self.inner.write_all(&head);
+        };
+        self.inner.write_all(&head[..at])?;
+        match embed {
+            Embed::Png => {
+                for text in self.metadata.png_texts() {
+                    write_chunk(&mut self.inner, b"tEXt", &text)?;
+                }
+            }
+            Embed::Svg => self.inner.write_all(self.metadata.svg_element().as_bytes())?,
+            Embed::Netpbm => {
+                for (key, value) in &self.metadata.0 {
+                    writeln!(self.inner, "# {key}: {}", one_line(value))?;
+                }
+            }
+        }
+        self.inner.write_all(&head[at..])
+    }
+}
+
+enum Embed {
+    Png,
+    Svg,
+    Netpbm,
+}
+
+impl<W: Write> Write for Tagged<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.done {
+            return self.inner.write(buf);
+        }
+        self.head.extend_from_slice(buf);
+        self.place()?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        // Too short to place the metadata: the file is what it is
+        if !self.done {
+            self.done = true;
+            let head = std::mem::take(&mut self.head);
+            self.inner.write_all(&head)?;
+        }
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for Tagged<'_, W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{write_pbm, write_png, write_svg, RenderOptions, SvgOptions};
+    use crate::Automaton;
+
+    fn metadata() -> Metadata {
+        Metadata::new("automata render 30 -o x.png").with("rule", 30).with("width", 11)
+    }
+
+    #[test]
+    fn test_png_gets_text_chunks() {
+        let (mut plain, mut tagged) = (Vec::new(), Vec::new());
+        write_png(&mut plain, Automaton::new(11, 30), 5, &RenderOptions::default()).unwrap();
+        write_png(&mut Tagged::new(&mut tagged, &metadata()), Automaton::new(11, 30), 5, &RenderOptions::default())
+            .unwrap();
+
+        // Walk the chunks: IHDR, then the four texts, then the image as before
+        let mut chunks = Vec::new();
+        let mut at = 8;
+        while at < tagged.len() {
+            let len = u32::from_be_bytes(tagged[at..at + 4].try_into().unwrap()) as usize;
+            chunks.push((tagged[at + 4..at + 8].to_vec(), tagged[at + 8..at + 8 + len].to_vec()));
+            at += 12 + len;
+        }
+        let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| kind.as_slice()).collect();
+        assert_eq!(kinds, [&b"IHDR"[..], b"tEXt", b"tEXt", b"tEXt", b"tEXt", b"IDAT", b"IEND"]);
+        assert_eq!(chunks[3].1, b"rule\x0030");
+        assert_eq!(tagged.len(), plain.len() + chunks[1..5].iter().map(|(_, data)| 12 + data.len()).sum::<usize>());
+        assert_eq!(&tagged[..PNG_HEAD], &plain[..PNG_HEAD]);
+        assert_eq!(&tagged[tagged.len() - 40..], &plain[plain.len() - 40..]);
+    }
+
+    #[test]
+    fn test_svg_netpbm_and_other_bytes() {
+        let mut svg = Vec::new();
+        write_svg(&mut Tagged::new(&mut svg, &metadata()), Automaton::new(11, 30), 3, &SvgOptions::default()).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        let lines: Vec<&str> = svg.lines().collect();
+        assert!(lines[0].starts_with("<svg") && lines[1] == "<metadata>");
+        assert_eq!(lines[3], "command: automata render 30 -o x.png");
+
+        let mut pbm = Vec::new();
+        write_pbm(&mut Tagged::new(&mut pbm, &metadata()), Automaton::new(11, 90), 4, 1).unwrap();
+        assert!(pbm.starts_with(b"P4\n# software: automata "));
+        assert_eq!(crate::spacetime::read_pbm(&pbm).unwrap().len(), 5);
+
+        // Anything else, and anything too short to tell, passes through
+        for bytes in [&b"rule,period\n30,1\n"[..], b"P", b"\x93NUMPY\x01\x00"] {
+            let mut out = Vec::new();
+            Tagged::new(&mut out, &metadata()).write_all(bytes).unwrap();
+            assert_eq!(out, bytes);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Measurements of a simulation already made, for `notebook` scripts that
+//! run a rule once and then measure, draw and report on it in later steps
+//!
+//! A `Simulation` keeps every row of its run. Metrics read those rows
+//! instead of stepping the rule again, and the ones that need the
+//! bestiary's examination (compression, cycle, class) share one, made the
+//! first time any of them is asked for.
+
+use crate::bestiary::{specimen, Specimen};
+use crate::wolfram::WolframRule;
+use crate::Automaton;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Block length of `entropy` without one given
+pub const DEFAULT_BLOCK: usize = 4;
+
+/// Something measured of a run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Metric {
+    /// Fraction of the last row's cells that differ from the background
+    Density,
+    /// Cells of the last row that differ from the background
+    Population,
+    /// Entropy of the last row's k-blocks, in bits
+    Entropy(usize),
+    /// The bestiary's compression ratio of the run's second half
+    Compression,
+    /// Steps before a row came back (up to rotation) and its period
+    Cycle,
+    /// Wolfram class, from the cycle and compression
+    Class,
+}
+
+impl std::str::FromStr for Metric {
+    type Err = String;
+
+    /// `density`, `population`, `entropy` or `entropy:<k>`, `compression`,
+    /// `cycle` or `class`
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.split_once(':') {
+            None if s == "density" => Ok(Metric::Density),
+            None if s == "population" => Ok(Metric::Population),
+            None if s == "entropy" => Ok(Metric::Entropy(DEFAULT_BLOCK)),
+            None if s == "compression" => Ok(Metric::Compression),
+            None if s == "cycle" => Ok(Metric::Cycle),
+            None if s == "class" => Ok(Metric::Class),
+            Some(("entropy", k)) => match k.parse() {
+                Ok(k) if k > 0 => Ok(Metric::Entropy(k)),
+                _ => Err(format!("entropy:{k} needs a block length of 1 or more")),
+            },
+            _ => Err(format!(
+                "unknown metric {s:?}: expected density, population, entropy[:k], compression, cycle or class"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Metric::Density => f.pad("density"),
+            Metric::Population => f.pad("population"),
+            Metric::Entropy(k) => f.pad(&format!("entropy:{k}")),
+            Metric::Compression => f.pad("compression"),
+            Metric::Cycle => f.pad("cycle"),
+            Metric::Class => f.pad("class"),
+        }
+    }
+}
+
+/// A metric's value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Reading {
+    Number(f64),
+    Count(usize),
+    Text(String),
+}
+
+impl fmt::Display for Reading {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reading::Number(x) => f.pad(&format!("{x:.4}")),
+            Reading::Count(n) => f.pad(&n.to_string()),
+            Reading::Text(text) => f.pad(text),
+        }
+    }
+}
+
+/// A run of a two-state rule, every row kept
+#[derive(Debug)]
+pub struct Simulation {
+    pub rule: WolframRule,
+    /// The cells of every generation, the initial row first
+    pub rows: Vec<Vec<bool>>,
+    /// State of the quiescent background the run is measured against
+    pub background: bool,
+    specimen: OnceLock<Specimen>,
+}
+
+impl Simulation {
+    /// `generations` steps of `ca`, whose rule is `rule`
+    pub fn run(rule: WolframRule, mut ca: Automaton, generations: usize) -> Simulation {
+        let mut rows = vec![ca.cells()];
+        for _ in 0..generations {
+            ca.step();
+            rows.push(ca.cells());
+        }
+        Simulation { rule, rows, background: ca.background(), specimen: OnceLock::new() }
+    }
+
+    pub fn width(&self) -> usize {
+        self.rows[0].len()
+    }
+
+    pub fn generations(&self) -> usize {
+        self.rows.len() - 1
+    }
+
+    /// The rows as they are drawn: true where a cell differs from the background
+    pub fn deviations(&self) -> Vec<Vec<bool>> {
+        self.rows.iter().map(|row| row.iter().map(|&cell| cell != self.background).collect()).collect()
+    }
+
+    /// The bestiary's examination of the rows, made once
+    pub fn specimen(&self) -> &Specimen {
+        self.specimen.get_or_init(|| {
+            let rows = self.rows.iter().map(|row| row.iter().map(|&cell| cell as u8).collect()).collect();
+            specimen(self.rule.clone(), rows)
+        })
+    }
+
+    pub fn measure(&self, metric: Metric) -> Result<Reading, String> {
+        let last: Vec<bool> = self.rows[self.rows.len() - 1].iter().map(|&cell| cell != self.background).collect();
+        let population = last.iter().filter(|&&cell| cell).count();
+        Ok(match metric {
+            Metric::Density => Reading::Number(population as f64 / last.len() as f64),
+            Metric::Population => Reading::Count(population),
+            Metric::Entropy(k) => {
+                crate::check_block(k, last.len()).map_err(|e| e.to_string())?;
+                Reading::Number(Automaton::from_cells(last, 0).block_entropy(k))
+            }
+            Metric::Compression => Reading::Number(self.specimen().ratio),
+            Metric::Cycle => Reading::Text(match self.specimen().cycle {
+                Some((transient, period)) => format!("period {period} after {transient}"),
+                None => format!("none in {}", self.generations()),
+            }),
+            Metric::Class => {
+                let class = self.specimen().class;
+                Reading::Text(format!("{class} ({})", class.number()))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulations_measure_their_rows() {
+        assert_eq!("entropy:3".parse(), Ok(Metric::Entropy(3)));
+        assert_eq!("entropy".parse::<Metric>().unwrap().to_string(), "entropy:4");
+        assert!("entropy:0".parse::<Metric>().is_err() && "speed".parse::<Metric>().is_err());
+
+        let rule = |n: u8| WolframRule::general(2, 1, &n.to_string()).unwrap();
+        // Rule 90 from one cell is Pascal's triangle mod 2: generation 16 is two cells
+        let pascal = Simulation::run(rule(90), Automaton::new(101, 90), 16);
+        assert_eq!((pascal.width(), pascal.generations()), (101, 16));
+        assert_eq!(pascal.measure(Metric::Population), Ok(Reading::Count(2)));
+        assert!(pascal.measure(Metric::Entropy(200)).unwrap_err().starts_with("block size 200 is longer than"));
+
+        // Against a live vacuum a single dead cell under rule 255 is gone in a step
+        let vacuum = Simulation::run(rule(255), Automaton::new(31, 255).on_background(true), 3);
+        assert_eq!(vacuum.deviations()[0].iter().filter(|&&c| c).count(), 1);
+        assert_eq!(vacuum.measure(Metric::Density), Ok(Reading::Number(0.0)));
+        assert_eq!(vacuum.measure(Metric::Class).unwrap().to_string(), "uniform (1)");
+
+        // The examination is made once and shared
+        let shift = Simulation::run(rule(170), Automaton::random(40, 170, 0.5, 3), 100);
+        assert_eq!(shift.measure(Metric::Cycle).unwrap().to_string(), "period 1 after 0");
+        assert!(std::ptr::eq(shift.specimen(), shift.specimen()));
+    }
+}
@@ -0,0 +1,512 @@
+//! Parquet files of survey results, for sweeps too big for CSV (needs the
+//! `parquet` feature)
+//!
+//! Without the feature `write_parquet` returns an `Unsupported` error, so
+//! callers can offer `--format parquet` unconditionally.
+//!
+//! A minimal writer over flate2, like the PNG encoder: one row group with one
+//! data page per column, PLAIN-encoded values, GZIP-compressed pages, and
+//! definition levels (RLE) for nullable columns. The footer is Thrift's
+//! compact protocol, written by hand for just the structs a reader needs.
+//! Integers are INT64, floats DOUBLE, flags BOOLEAN, and text UTF8 byte
+//! arrays, so pandas, polars, DuckDB and Arrow read the columns typed.
+
+use std::io::{self, Write};
+
+/// One column's values; `None` is null, allowed only in optional columns
+#[derive(Clone, Debug, PartialEq)]
+pub enum Values {
+    Boolean(Vec<Option<bool>>),
+    Int64(Vec<Option<i64>>),
+    Double(Vec<Option<f64>>),
+    Utf8(Vec<Option<String>>),
+}
+
+impl Values {
+    pub fn len(&self) -> usize {
+        match self {
+            Values::Boolean(v) => v.len(),
+            Values::Int64(v) => v.len(),
+            Values::Double(v) => v.len(),
+            Values::Utf8(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[cfg(feature = "parquet")]
+    fn is_null(&self, row: usize) -> bool {
+        match self {
+            Values::Boolean(v) => v[row].is_none(),
+            Values::Int64(v) => v[row].is_none(),
+            Values::Double(v) => v[row].is_none(),
+            Values::Utf8(v) => v[row].is_none(),
+        }
+    }
+}
+
+macro_rules! values {
+    ($variant:ident: $($t:ty),* => $into:ty) => {
+        $(impl From<Vec<$t>> for Values {
+            fn from(values: Vec<$t>) -> Self {
+                Values::$variant(values.into_iter().map(|x| Some(<$into>::from(x))).collect())
+            }
+        }
+        impl From<Vec<Option<$t>>> for Values {
+            fn from(values: Vec<Option<$t>>) -> Self {
+                Values::$variant(values.into_iter().map(|x| x.map(<$into>::from)).collect())
+            }
+        })*
+    };
+}
+values!(Boolean: bool => bool);
+values!(Int64: u8, u16, u32, i32, i64 => i64);
+values!(Double: f64 => f64);
+values!(Utf8: String, &str => String);
+
+// Counts and hashes don't fit `From<_> for i64`; hashes above i64::MAX wrap, as the bits
+impl From<Vec<usize>> for Values {
+    fn from(values: Vec<usize>) -> Self {
+        Values::Int64(values.into_iter().map(|x| Some(x as i64)).collect())
+    }
+}
+
+impl From<Vec<Option<usize>>> for Values {
+    fn from(values: Vec<Option<usize>>) -> Self {
+        Values::Int64(values.into_iter().map(|x| x.map(|x| x as i64)).collect())
+    }
+}
+
+impl From<Vec<u64>> for Values {
+    fn from(values: Vec<u64>) -> Self {
+        Values::Int64(values.into_iter().map(|x| Some(x as i64)).collect())
+    }
+}
+
+/// A named column of the table
+#[derive(Clone, Debug, PartialEq)]
+pub struct Column {
+    pub name: String,
+    /// Whether the schema allows nulls
+    pub optional: bool,
+    pub values: Values,
+}
+
+impl Column {
+    /// A column with a value in every row
+    pub fn required(name: &str, values: impl Into<Values>) -> Self {
+        Self { name: name.to_string(), optional: false, values: values.into() }
+    }
+
+    /// A column that may hold nulls
+    pub fn optional(name: &str, values: impl Into<Values>) -> Self {
+        Self { name: name.to_string(), optional: true, values: values.into() }
+    }
+}
+
+/// Write `columns` (all the same length) as one Parquet file, with
+/// `metadata` as the footer's key-value pairs
+#[cfg(feature = "parquet")]
+pub fn write_parquet(out: &mut impl Write, columns: &[Column], metadata: &[(&str, String)]) -> io::Result<()> {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    let rows = columns.first().map_or(0, |column| column.values.len());
+    for column in columns {
+        if column.values.len() != rows || (!column.optional && (0..rows).any(|row| column.values.is_null(row))) {
+            let problem = format!("column {} has {} rows or a null where none is allowed", column.name, rows);
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, problem));
+        }
+    }
+
+    out.write_all(MAGIC)?;
+    let mut offset = MAGIC.len() as i64;
+    let mut chunks = Vec::new();
+    for column in columns {
+        let mut page = Vec::new();
+        if column.optional {
+            let levels = definition_levels(&column.values);
+            page.extend((levels.len() as u32).to_le_bytes());
+            page.extend(levels);
+        }
+        plain_values(&column.values, &mut page);
+        let mut compressed = GzEncoder::new(Vec::new(), Compression::default());
+        compressed.write_all(&page)?;
+        let compressed = compressed.finish()?;
+
+        // PageHeader { type: DATA_PAGE, sizes, data_page_header: { values, PLAIN, RLE, RLE } }
+        let mut header = Compact::default();
+        header.i32(1, 0);
+        header.i32(2, page.len() as i32);
+        header.i32(3, compressed.len() as i32);
+        header.begin(5);
+        header.i32(1, rows as i32);
+        header.i32(2, PLAIN);
+        header.i32(3, RLE);
+        header.i32(4, RLE);
+        header.end();
+        header.stop();
+        out.write_all(&header.bytes)?;
+        out.write_all(&compressed)?;
+
+        let size = |body: usize| (header.bytes.len() + body) as i64;
+        chunks.push((offset, size(page.len()), size(compressed.len())));
+        offset += size(compressed.len());
+    }
+
+    // FileMetaData { version, schema, num_rows, row_groups, key_value_metadata, created_by }
+    let mut footer = Compact::default();
+    footer.i32(1, 1);
+    footer.list(2, STRUCT, columns.len() + 1);
+    footer.begin_element();
+    footer.string(4, "schema");
+    footer.i32(5, columns.len() as i32);
+    footer.end();
+    for column in columns {
+        footer.begin_element();
+        footer.i32(1, physical_type(&column.values));
+        footer.i32(3, column.optional as i32);
+        footer.string(4, &column.name);
+        if matches!(column.values, Values::Utf8(_)) {
+            footer.i32(6, 0);
+        }
+        footer.end();
+    }
+    footer.i64(3, rows as i64);
+    footer.list(4, STRUCT, 1);
+    footer.begin_element();
+    footer.list(1, STRUCT, columns.len());
+    for (column, &(start, uncompressed, compressed)) in columns.iter().zip(&chunks) {
+        // ColumnChunk { file_offset, meta_data: ColumnMetaData }
+        footer.begin_element();
+        footer.i64(2, start);
+        footer.begin(3);
+        footer.i32(1, physical_type(&column.values));
+        footer.list(2, I32, 2);
+        footer.varint(zigzag(PLAIN as i64));
+        footer.varint(zigzag(RLE as i64));
+        footer.list(3, BINARY, 1);
+        footer.binary(column.name.as_bytes());
+        footer.i32(4, GZIP);
+        footer.i64(5, rows as i64);
+        footer.i64(6, uncompressed);
+        footer.i64(7, compressed);
+        footer.i64(9, start);
+        footer.end();
+        footer.end();
+    }
+    footer.i64(2, chunks.iter().map(|chunk| chunk.1).sum());
+    footer.i64(3, rows as i64);
+    footer.end();
+    if !metadata.is_empty() {
+        footer.list(5, STRUCT, metadata.len());
+        for (key, value) in metadata {
+            footer.begin_element();
+            footer.string(1, key);
+            footer.string(2, value);
+            footer.end();
+        }
+    }
+    footer.string(6, concat!("automata ", env!("CARGO_PKG_VERSION")));
+    footer.stop();
+
+    out.write_all(&footer.bytes)?;
+    out.write_all(&(footer.bytes.len() as u32).to_le_bytes())?;
+    out.write_all(MAGIC)
+}
+
+#[cfg(not(feature = "parquet"))]
+pub fn write_parquet(_out: &mut impl Write, _columns: &[Column], _metadata: &[(&str, String)]) -> io::Result<()> {
+    let message = "built without the `parquet` feature; rebuild with `--features parquet`";
+    Err(io::Error::new(io::ErrorKind::Unsupported, message))
+}
+
+#[cfg(feature = "parquet")]
+const MAGIC: &[u8] = b"PAR1";
+
+// Encodings, and the GZIP codec, as numbered in parquet.thrift
+#[cfg(feature = "parquet")]
+const PLAIN: i32 = 0;
+#[cfg(feature = "parquet")]
+const RLE: i32 = 3;
+#[cfg(feature = "parquet")]
+const GZIP: i32 = 2;
+
+/// BOOLEAN, INT64, DOUBLE or BYTE_ARRAY
+#[cfg(feature = "parquet")]
+fn physical_type(values: &Values) -> i32 {
+    match values {
+        Values::Boolean(_) => 0,
+        Values::Int64(_) => 2,
+        Values::Double(_) => 5,
+        Values::Utf8(_) => 6,
+    }
+}
+
+/// The non-null values, PLAIN-encoded: little-endian numbers, booleans
+/// packed 8 to a byte from bit 0, and length-prefixed strings
+#[cfg(feature = "parquet")]
+fn plain_values(values: &Values, out: &mut Vec<u8>) {
+    match values {
+        Values::Boolean(v) => {
+            let bits: Vec<bool> = v.iter().flatten().copied().collect();
+            out.extend(bits.chunks(8).map(|byte| byte.iter().rev().fold(0u8, |b, &bit| b << 1 | bit as u8)));
+        }
+        Values::Int64(v) => v.iter().flatten().for_each(|x| out.extend(x.to_le_bytes())),
+        Values::Double(v) => v.iter().flatten().for_each(|x| out.extend(x.to_le_bytes())),
+        Values::Utf8(v) => v.iter().flatten().for_each(|s| {
+            out.extend((s.len() as u32).to_le_bytes());
+            out.extend(s.as_bytes());
+        }),
+    }
+}
+
+/// Definition levels (1 for a value, 0 for null) as RLE runs of bit width 1:
+/// a varint `count << 1`, then the level in one byte
+#[cfg(feature = "parquet")]
+fn definition_levels(values: &Values) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut row = 0;
+    while row < values.len() {
+        let null = values.is_null(row);
+        let run = (row..values.len()).take_while(|&r| values.is_null(r) == null).count();
+        write_varint(&mut out, (run as u64) << 1);
+        out.push(!null as u8);
+        row += run;
+    }
+    out
+}
+
+#[cfg(feature = "parquet")]
+fn write_varint(out: &mut Vec<u8>, mut n: u64) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+#[cfg(feature = "parquet")]
+fn zigzag(n: i64) -> u64 {
+    ((n << 1) ^ (n >> 63)) as u64
+}
+
+// Compact-protocol type codes
+#[cfg(feature = "parquet")]
+const I32: u8 = 5;
+#[cfg(feature = "parquet")]
+const I64: u8 = 6;
+#[cfg(feature = "parquet")]
+const BINARY: u8 = 8;
+#[cfg(feature = "parquet")]
+const LIST: u8 = 9;
+#[cfg(feature = "parquet")]
+const STRUCT: u8 = 12;
+
+/// A Thrift compact-protocol writer: fields carry their id as a delta from
+/// the previous one in the same struct, so nested structs keep a stack
+#[cfg(feature = "parquet")]
+#[derive(Default)]
+struct Compact {
+    bytes: Vec<u8>,
+    last: Vec<i16>,
+    field: i16,
+}
+
+#[cfg(feature = "parquet")]
+impl Compact {
+    fn header(&mut self, id: i16, kind: u8) {
+        match id - self.field {
+            delta @ 1..=15 => self.bytes.push((delta as u8) << 4 | kind),
+            _ => {
+                self.bytes.push(kind);
+                self.varint(zigzag(id as i64));
+            }
+        }
+        self.field = id;
+    }
+
+    fn varint(&mut self, n: u64) {
+        write_varint(&mut self.bytes, n);
+    }
+
+    fn binary(&mut self, bytes: &[u8]) {
+        self.varint(bytes.len() as u64);
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn i32(&mut self, id: i16, n: i32) {
+        self.header(id, I32);
+        self.varint(zigzag(n as i64));
+    }
+
+    fn i64(&mut self, id: i16, n: i64) {
+        self.header(id, I64);
+        self.varint(zigzag(n));
+    }
+
+    fn string(&mut self, id: i16, s: &str) {
+        self.header(id, BINARY);
+        self.binary(s.as_bytes());
+    }
+
+    /// A list field's header; the elements follow
+    fn list(&mut self, id: i16, element: u8, len: usize) {
+        self.header(id, LIST);
+        if len < 15 {
+            self.bytes.push((len as u8) << 4 | element);
+        } else {
+            self.bytes.push(0xf0 | element);
+            self.varint(len as u64);
+        }
+    }
+
+    /// Open a struct field
+    fn begin(&mut self, id: i16) {
+        self.header(id, STRUCT);
+        self.begin_element();
+    }
+
+    /// Open a struct that is a list element
+    fn begin_element(&mut self) {
+        self.last.push(self.field);
+        self.field = 0;
+    }
+
+    fn end(&mut self) {
+        self.stop();
+        self.field = self.last.pop().expect("end matches a begin");
+    }
+
+    fn stop(&mut self) {
+        self.bytes.push(0);
+    }
+}
+
+#[cfg(all(test, feature = "parquet"))]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    /// A compact-protocol value, read back without a schema
+    #[derive(Debug)]
+    enum Thrift {
+        Int(i64),
+        Binary(Vec<u8>),
+        List(Vec<Thrift>),
+        Struct(Vec<(i16, Thrift)>),
+    }
+
+    impl Thrift {
+        fn field(&self, id: i16) -> &Thrift {
+            let Thrift::Struct(fields) = self else { panic!("not a struct") };
+            &fields.iter().find(|(i, _)| *i == id).unwrap_or_else(|| panic!("no field {id}")).1
+        }
+
+        fn int(&self) -> i64 {
+            let Thrift::Int(n) = self else { panic!("not an integer") };
+            *n
+        }
+
+        fn list(&self) -> &[Thrift] {
+            let Thrift::List(items) = self else { panic!("not a list") };
+            items
+        }
+
+        fn text(&self) -> String {
+            let Thrift::Binary(bytes) = self else { panic!("not binary") };
+            String::from_utf8(bytes.clone()).unwrap()
+        }
+    }
+
+    fn read_varint(bytes: &mut &[u8]) -> u64 {
+        let mut n = 0;
+        for shift in (0..).step_by(7) {
+            let byte = bytes[0];
+            *bytes = &bytes[1..];
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte < 0x80 {
+                break;
+            }
+        }
+        n
+    }
+
+    fn read_value(bytes: &mut &[u8], kind: u8) -> Thrift {
+        let unzigzag = |n: u64| (n >> 1) as i64 ^ -((n & 1) as i64);
+        match kind {
+            I32 | I64 => Thrift::Int(unzigzag(read_varint(bytes))),
+            BINARY => {
+                let len = read_varint(bytes) as usize;
+                let (value, rest) = bytes.split_at(len);
+                *bytes = rest;
+                Thrift::Binary(value.to_vec())
+            }
+            LIST => {
+                let head = bytes[0];
+                *bytes = &bytes[1..];
+                let len = if head >> 4 == 15 { read_varint(bytes) as usize } else { (head >> 4) as usize };
+                Thrift::List((0..len).map(|_| read_value(bytes, head & 15)).collect())
+            }
+            STRUCT => {
+                let (mut fields, mut id) = (Vec::new(), 0i16);
+                loop {
+                    let head = bytes[0];
+                    *bytes = &bytes[1..];
+                    if head == 0 {
+                        return Thrift::Struct(fields);
+                    }
+                    id = if head >> 4 == 0 { unzigzag(read_varint(bytes)) as i16 } else { id + (head >> 4) as i16 };
+                    fields.push((id, read_value(bytes, head & 15)));
+                }
+            }
+            _ => panic!("unexpected type {kind}"),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let columns = [
+            Column::required("rule", vec![30u8, 90, 110]),
+            Column::optional("period", vec![None, Some(1usize), Some(3000)]),
+            Column::required("density", vec![0.5, 0.25, f64::MIN_POSITIVE]),
+            Column::required("died", vec![false, true, false]),
+            Column::optional("class", vec![Some("chaotic"), Some("fractal"), None]),
+        ];
+        let mut file = Vec::new();
+        write_parquet(&mut file, &columns, &[("automata.report", "{}".to_string())]).unwrap();
+        assert_eq!((&file[..4], &file[file.len() - 4..]), (&b"PAR1"[..], &b"PAR1"[..]));
+
+        let footer_len = u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap()) as usize;
+        let mut footer = &file[file.len() - 8 - footer_len..file.len() - 8];
+        let meta = read_value(&mut footer, STRUCT);
+        assert!(footer.is_empty());
+        assert_eq!(meta.field(3).int(), 3);
+        let schema = meta.field(2).list();
+        let names: Vec<String> = schema[1..].iter().map(|element| element.field(4).text()).collect();
+        assert_eq!(names, ["rule", "period", "density", "died", "class"]);
+        assert_eq!(meta.field(5).list()[0].field(1).text(), "automata.report");
+
+        // Each chunk's page decompresses to its levels and values
+        let chunks = meta.field(4).list()[0].field(1).list();
+        let page = |i: usize| -> Vec<u8> {
+            let mut bytes = &file[chunks[i].field(3).field(9).int() as usize..];
+            let header = read_value(&mut bytes, STRUCT);
+            assert_eq!(header.field(5).field(1).int(), 3);
+            let mut page = Vec::new();
+            GzDecoder::new(&bytes[..header.field(3).int() as usize]).read_to_end(&mut page).unwrap();
+            assert_eq!(page.len() as i64, header.field(2).int());
+            page
+        };
+        assert_eq!(page(0), [30i64, 90, 110].iter().flat_map(|x| x.to_le_bytes()).collect::<Vec<_>>());
+        // Levels: 4-byte length, a run of one null, a run of two values
+        let levels = [4, 0, 0, 0, 2, 0, 4, 1].into_iter();
+        assert_eq!(page(1), levels.chain(1i64.to_le_bytes()).chain(3000i64.to_le_bytes()).collect::<Vec<u8>>());
+        assert_eq!(page(3), [0b010]);
+        assert_eq!(page(4)[8..], [7, 0, 0, 0, b'c', b'h', b'a', b'o', b't', b'i', b'c', 7, 0, 0, 0, b'f', b'r', b'a',
+            b'c', b't', b'a', b'l']);
+    }
+}
@@ -0,0 +1,212 @@
+//! Generations as musical events, for playing a run live
+//!
+//! Each cell of the ring is a pitch: cell 0 the lowest, counting up the
+//! degrees of a scale and wrapping into the next octave, so a ring of a few
+//! dozen cells spans the keyboard. A cell turning live (relative to the
+//! background) is a note on and turning dead its note off; the first row
+//! sounds every live cell.
+//!
+//! The events go out as OSC 1.0 messages (big-endian, strings and blobs
+//! padded to four bytes, one message per UDP packet) or as raw MIDI bytes,
+//! which on Linux can be written straight to an ALSA rawmidi device such as
+//! `/dev/snd/midiC1D0`. Incoming OSC messages are decoded the same way, so
+//! a controller can switch the rule or the seed mid-performance.
+
+use crate::Automaton;
+
+/// An OSC argument, of the types the format requires every receiver to know
+#[derive(Clone, Debug, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    Str(String),
+    Blob(Vec<u8>),
+}
+
+impl OscArg {
+    fn tag(&self) -> char {
+        match self {
+            OscArg::Int(_) => 'i',
+            OscArg::Float(_) => 'f',
+            OscArg::Str(_) => 's',
+            OscArg::Blob(_) => 'b',
+        }
+    }
+
+    /// The argument as a number, whichever numeric type it was sent as
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            OscArg::Int(i) => Some(i as f64),
+            OscArg::Float(f) => Some(f as f64),
+            _ => None,
+        }
+    }
+}
+
+/// An OSC message: the address, the type tags, then the arguments
+pub fn osc_message(address: &str, args: &[OscArg]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    push_padded(&mut packet, address.as_bytes());
+    let tags: String = std::iter::once(',').chain(args.iter().map(OscArg::tag)).collect();
+    push_padded(&mut packet, tags.as_bytes());
+    for arg in args {
+        match arg {
+            OscArg::Int(i) => packet.extend(i.to_be_bytes()),
+            OscArg::Float(f) => packet.extend(f.to_be_bytes()),
+            OscArg::Str(s) => push_padded(&mut packet, s.as_bytes()),
+            OscArg::Blob(bytes) => {
+                packet.extend((bytes.len() as u32).to_be_bytes());
+                packet.extend(bytes);
+                packet.resize(packet.len().next_multiple_of(4), 0);
+            }
+        }
+    }
+    packet
+}
+
+/// A string's bytes, NUL-terminated and padded with NULs to a multiple of 4
+fn push_padded(packet: &mut Vec<u8>, bytes: &[u8]) {
+    packet.extend(bytes);
+    packet.resize((packet.len() + 1).next_multiple_of(4), 0);
+}
+
+/// The address and arguments of an OSC message; None for a malformed one,
+/// a bundle, or an argument type outside the four basic ones
+pub fn parse_osc_message(packet: &[u8]) -> Option<(String, Vec<OscArg>)> {
+    let mut at = 0;
+    let address = read_padded(packet, &mut at)?;
+    if !address.starts_with('/') {
+        return None;
+    }
+    // Old senders may leave out the type tags along with the arguments
+    let tags = if at < packet.len() { read_padded(packet, &mut at)? } else { ",".to_string() };
+    let mut args = Vec::new();
+    for tag in tags.strip_prefix(',')?.chars() {
+        let word = |at: &mut usize| -> Option<[u8; 4]> {
+            let bytes = packet.get(*at..*at + 4)?.try_into().ok()?;
+            *at += 4;
+            Some(bytes)
+        };
+        args.push(match tag {
+            'i' => OscArg::Int(i32::from_be_bytes(word(&mut at)?)),
+            'f' => OscArg::Float(f32::from_be_bytes(word(&mut at)?)),
+            's' => OscArg::Str(read_padded(packet, &mut at)?),
+            'b' => {
+                let len = u32::from_be_bytes(word(&mut at)?) as usize;
+                let bytes = packet.get(at..at.checked_add(len)?)?.to_vec();
+                at += len.next_multiple_of(4);
+                OscArg::Blob(bytes)
+            }
+            _ => return None,
+        });
+    }
+    Some((address, args))
+}
+
+fn read_padded(packet: &[u8], at: &mut usize) -> Option<String> {
+    let rest = packet.get(*at..)?;
+    let len = rest.iter().position(|&b| b == 0)?;
+    let text = String::from_utf8(rest[..len].to_vec()).ok()?;
+    *at += (len + 1).next_multiple_of(4);
+    Some(text)
+}
+
+/// Scales as semitones above the tonic, within one octave
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Scale {
+    Chromatic,
+    Major,
+    Minor,
+    #[default]
+    Pentatonic,
+}
+
+impl Scale {
+    pub fn intervals(self) -> &'static [u8] {
+        match self {
+            Scale::Chromatic => &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+            Scale::Major => &[0, 2, 4, 5, 7, 9, 11],
+            Scale::Minor => &[0, 2, 3, 5, 7, 8, 10],
+            Scale::Pentatonic => &[0, 2, 4, 7, 9],
+        }
+    }
+}
+
+/// Which MIDI note each cell plays
+#[derive(Clone, Copy, Debug)]
+pub struct NoteMap {
+    /// The note of cell 0, e.g. 48 for the C below middle C
+    pub lowest: u8,
+    pub scale: Scale,
+}
+
+impl NoteMap {
+    /// The note cell `i` plays; None above the top of MIDI's range (127)
+    pub fn note(&self, i: usize) -> Option<u8> {
+        let intervals = self.scale.intervals();
+        let (octave, degree) = (i / intervals.len(), i % intervals.len());
+        let note = self.lowest as usize + 12 * octave + intervals[degree] as usize;
+        u8::try_from(note).ok().filter(|&n| n <= 127)
+    }
+}
+
+/// The cells whose state changed from `before` to `after`, and whether each
+/// is now live; with no `before`, every live cell of `after`
+pub fn toggles<'a>(before: Option<&'a Automaton>, after: &'a Automaton) -> impl Iterator<Item = (usize, bool)> + 'a {
+    (0..after.width()).filter_map(move |i| {
+        let live = after.deviates(i);
+        let was = before.is_some_and(|ca| ca.deviates(i));
+        (live != was).then_some((i, live))
+    })
+}
+
+/// A MIDI note on (velocity > 0) or note off (velocity 0, sent as a proper
+/// note off) on `channel` 0-15
+pub fn midi_note(channel: u8, note: u8, velocity: u8) -> [u8; 3] {
+    let status = if velocity > 0 { 0x90 } else { 0x80 };
+    [status | (channel & 0x0f), note & 0x7f, velocity & 0x7f]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc_encoding_round_trips() {
+        // The OSC 1.0 spec's own example: "/oscillator/4/frequency" 440.0
+        let packet = osc_message("/oscillator/4/frequency", &[OscArg::Float(440.0)]);
+        assert_eq!(packet.len(), 32);
+        assert_eq!(&packet[24..28], b",f\0\0");
+        assert_eq!(&packet[28..], [0x43, 0xdc, 0x00, 0x00]);
+
+        let args =
+            vec![OscArg::Int(-3), OscArg::Str("rule".into()), OscArg::Blob(vec![1, 2, 3, 4, 5]), OscArg::Float(0.5)];
+        let packet = osc_message("/automata/row", &args);
+        assert_eq!(packet.len() % 4, 0);
+        assert_eq!(parse_osc_message(&packet), Some(("/automata/row".to_string(), args)));
+
+        // No type tags at all is an old sender's message without arguments
+        assert_eq!(parse_osc_message(b"/reset\0\0"), Some(("/reset".to_string(), vec![])));
+        assert_eq!(parse_osc_message(b"#bundle\0"), None);
+        assert_eq!(parse_osc_message(&packet[..packet.len() - 4]), None);
+    }
+
+    #[test]
+    fn test_cells_map_to_notes() {
+        let map = NoteMap { lowest: 60, scale: Scale::Pentatonic };
+        let notes: Vec<Option<u8>> = (0..7).map(|i| map.note(i)).collect();
+        assert_eq!(notes, [60, 62, 64, 67, 69, 72, 74].map(Some));
+        assert_eq!(NoteMap { lowest: 120, scale: Scale::Chromatic }.note(8), None);
+
+        // Rule 204 holds its row: only the first row sounds, then nothing changes
+        let row = Automaton::from_cells(vec![true, false, true, false], 204);
+        assert_eq!(toggles(None, &row).collect::<Vec<_>>(), [(0, true), (2, true)]);
+        assert_eq!(toggles(Some(&row), &row).count(), 0);
+        let mut next = Automaton::from_cells(vec![true, false, true, false], 170);
+        next.step();
+        assert_eq!(toggles(Some(&row), &next).collect::<Vec<_>>(), [(0, false), (1, true), (2, false), (3, true)]);
+
+        assert_eq!(midi_note(0, 60, 100), [0x90, 60, 100]);
+        assert_eq!(midi_note(9, 60, 0), [0x89, 60, 0]);
+    }
+}
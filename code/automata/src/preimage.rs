@@ -0,0 +1,721 @@
+//! Preimages of a row by walks on the rule's de Bruijn diagram
+//!
+//! The diagram of a radius-r rule has a node for every 2r-cell window and an
+//! edge for every (2r + 1)-cell one, from its left 2r cells to its right 2r,
+//! labelled with the cell it maps to. A row's preimages are the walks whose
+//! labels spell it: closed walks of its width on a ring, any walk of its
+//! width on an open row (whose preimages are 2r cells longer). Counting them
+//! is a product of the two label matrices, one pass per target cell, so the
+//! cost grows with the width instead of 2^width. Enumeration prunes with the
+//! nodes each position can still finish from, so every branch it takes ends
+//! in a preimage.
+//!
+//! The same diagram decides, after Amoroso and Patt, whether a rule is onto
+//! and one-to-one on bi-infinite rows. It is surjective when no word is an
+//! orphan (a Garden-of-Eden pattern): following labels from the set of all
+//! nodes, subset by subset, never empties it. It is injective when the pair
+//! diagram, whose walks are two rows with one image, has no bi-infinite
+//! walk through an off-diagonal pair, one where the rows differ. On an
+//! n-cell ring it is bijective when no closed walk of n steps there leaves
+//! the diagonal. Injective implies surjective (Moore-Myhill).
+//!
+//! On such a ring every row has exactly one preimage, so the walk to it is
+//! an inverse step, and a run can go backwards: `Inverse`.
+//!
+//! The same questions go to the `sat` solver as well. A preimage is one
+//! variable per cell with, per target cell, a clause against each window
+//! that maps to the wrong state. An orphan of length L is L row variables
+//! and the node sets of the breadth-first search as variables: the first
+//! holds every node, each node a row cell's edge reaches belongs to the
+//! next, and the last is empty. Those clauses only make the sets contain
+//! the nodes reached, which is all it takes for the last to prove it.
+
+use crate::evolve::TableRule;
+use crate::sat::{Lit, Outcome, Solver};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+/// How the preimages of a row meet at its ends
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Boundary {
+    /// A ring: preimages are as wide as the row and wrap around
+    Ring,
+    /// An open row: preimages are 2r cells wider, r more at each end
+    Open,
+}
+
+/// An unbounded count, as little-endian 64-bit limbs
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Natural(Vec<u64>);
+
+impl Natural {
+    pub fn one() -> Self {
+        Natural(vec![1])
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The count, if it fits a u64
+    pub fn to_u64(&self) -> Option<u64> {
+        match self.0[..] {
+            [] => Some(0),
+            [n] => Some(n),
+            _ => None,
+        }
+    }
+
+    /// Bits needed to write the count (0 for zero)
+    pub fn bits(&self) -> u64 {
+        self.0.last().map_or(0, |top| 64 * self.0.len() as u64 - top.leading_zeros() as u64)
+    }
+
+    /// The count multiplied by `factor`
+    pub fn times(&mut self, factor: u64) {
+        let mut carry = 0u128;
+        for limb in self.0.iter_mut() {
+            let product = *limb as u128 * factor as u128 + carry;
+            *limb = product as u64;
+            carry = product >> 64;
+        }
+        if carry > 0 {
+            self.0.push(carry as u64);
+        }
+        if factor == 0 {
+            self.0.clear();
+        }
+    }
+
+    fn add(&mut self, other: &Natural) {
+        if self.0.len() < other.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+        let mut carry = false;
+        for (i, limb) in self.0.iter_mut().enumerate() {
+            let (sum, over) = limb.overflowing_add(other.0.get(i).copied().unwrap_or(0));
+            let (sum, over_carry) = sum.overflowing_add(carry as u64);
+            *limb = sum;
+            carry = over || over_carry;
+            if !carry && i >= other.0.len() {
+                break;
+            }
+        }
+        if carry {
+            self.0.push(1);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+impl From<u64> for Natural {
+    fn from(n: u64) -> Self {
+        Natural(if n == 0 { Vec::new() } else { vec![n] })
+    }
+}
+
+impl fmt::Display for Natural {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Repeated division by 10^19, the largest power of ten in a limb
+        const CHUNK: u64 = 10_000_000_000_000_000_000;
+        let (mut limbs, mut chunks) = (self.0.clone(), Vec::new());
+        while !limbs.is_empty() {
+            let mut remainder = 0u128;
+            for limb in limbs.iter_mut().rev() {
+                let value = (remainder << 64) | *limb as u128;
+                *limb = (value / CHUNK as u128) as u64;
+                remainder = value % CHUNK as u128;
+            }
+            chunks.push(remainder as u64);
+            while limbs.last() == Some(&0) {
+                limbs.pop();
+            }
+        }
+        let digits = match chunks.split_last() {
+            None => "0".to_string(),
+            Some((top, rest)) => {
+                rest.iter().rev().fold(top.to_string(), |digits, chunk| format!("{digits}{chunk:019}"))
+            }
+        };
+        f.pad(&digits)
+    }
+}
+
+/// The cell a de Bruijn node (2r cells) followed by `cell` maps to, and the
+/// node it moves to
+fn edge(rule: TableRule, node: usize, cell: bool) -> (bool, usize) {
+    let window = (node << 1) | cell as usize;
+    ((rule.table >> window) & 1 == 1, window & ((1 << (2 * rule.radius)) - 1))
+}
+
+/// The shortest row with no preimage at all, the first of its length in
+/// dead-before-live order, or `None` for a surjective rule. Breadth-first
+/// over the sets of nodes a row's walks can be at, one bit per node
+pub fn orphan(rule: TableRule) -> Option<Vec<bool>> {
+    let nodes = 1 << (2 * rule.radius);
+    let step = |set: u64, cell: bool| {
+        let edges = (0..nodes).filter(|&node| set >> node & 1 == 1).flat_map(|node| [(node, false), (node, true)]);
+        let labelled = edges.map(|(node, c)| edge(rule, node, c)).filter(|&(label, _)| label == cell);
+        labelled.fold(0u64, |set, (_, to)| set | 1 << to)
+    };
+    let all = u64::MAX >> (64 - nodes);
+    // Each set reached, with the set and the cell it was reached from
+    let mut parents: HashMap<u64, Option<(u64, bool)>> = HashMap::from([(all, None)]);
+    let mut queue = VecDeque::from([all]);
+    while let Some(set) = queue.pop_front() {
+        for cell in [false, true] {
+            let next = step(set, cell);
+            if parents.contains_key(&next) {
+                continue;
+            }
+            parents.insert(next, Some((set, cell)));
+            if next == 0 {
+                let mut row = Vec::new();
+                let mut at = next;
+                while let Some((before, cell)) = parents[&at] {
+                    row.push(cell);
+                    at = before;
+                }
+                row.reverse();
+                return Some(row);
+            }
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+/// The pair diagram: for each pair of nodes (u * nodes + v), the pairs one
+/// step on whose edges carry the same label
+fn pairs(rule: TableRule) -> Vec<Vec<usize>> {
+    let nodes = 1 << (2 * rule.radius);
+    (0..nodes * nodes)
+        .map(|pair| {
+            let (u, v) = (pair / nodes, pair % nodes);
+            let steps = [false, true].into_iter().flat_map(|a| [false, true].into_iter().map(move |b| (a, b)));
+            steps
+                .map(|(a, b)| (edge(rule, u, a), edge(rule, v, b)))
+                .filter(|((x, _), (y, _))| x == y)
+                .map(|((_, u), (_, v))| u * nodes + v)
+                .collect()
+        })
+        .collect()
+}
+
+/// Whether two different bi-infinite rows never share an image
+pub fn injective(rule: TableRule) -> bool {
+    let nodes = 1 << (2 * rule.radius);
+    let next = pairs(rule);
+    let mut back = vec![Vec::new(); next.len()];
+    for (pair, successors) in next.iter().enumerate() {
+        successors.iter().for_each(|&to| back[to].push(pair));
+    }
+    // The pairs with walks going on forever each way: prune the ones with
+    // no step left inside the set until none go
+    let endless = |edges: &[Vec<usize>]| {
+        let mut keep = vec![true; edges.len()];
+        while let Some(dead) = (0..edges.len()).find(|&p| keep[p] && !edges[p].iter().any(|&to| keep[to])) {
+            keep[dead] = false;
+        }
+        keep
+    };
+    let (forward, backward) = (endless(&next), endless(&back));
+    (0..next.len()).all(|pair| pair / nodes == pair % nodes || !(forward[pair] && backward[pair]))
+}
+
+/// Which pairs reach which in some number of steps of the pair diagram, as
+/// rows of bits
+#[derive(Clone)]
+struct Reach {
+    nodes: usize,
+    rows: Vec<Vec<u64>>,
+}
+
+impl Reach {
+    /// One step
+    fn step(rule: TableRule) -> Self {
+        let next = pairs(rule);
+        let words = next.len().div_ceil(64);
+        let rows = next
+            .iter()
+            .map(|successors| {
+                let mut row = vec![0u64; words];
+                successors.iter().for_each(|&to| row[to / 64] |= 1 << (to % 64));
+                row
+            })
+            .collect();
+        Reach { nodes: 1 << (2 * rule.radius), rows }
+    }
+
+    /// These steps, then `other`'s
+    fn then(&self, other: &Reach) -> Reach {
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                let mut out = vec![0u64; row.len()];
+                for k in (0..self.rows.len()).filter(|&k| row[k / 64] >> (k % 64) & 1 == 1) {
+                    out.iter_mut().zip(&other.rows[k]).for_each(|(o, &w)| *o |= w);
+                }
+                out
+            })
+            .collect();
+        Reach { rows, ..*self }
+    }
+
+    /// Whether a walk comes back to an off-diagonal pair it started at
+    fn closes_off_diagonal(&self) -> bool {
+        let nodes = self.nodes;
+        let closes = |pair: usize| self.rows[pair][pair / 64] >> (pair % 64) & 1 == 1;
+        (0..self.rows.len()).any(|pair| pair / nodes != pair % nodes && closes(pair))
+    }
+}
+
+/// Whether the rule permutes the rows of an n-cell ring (n wider than its
+/// window): no closed walk of n steps in the pair diagram starts off the
+/// diagonal. The n-step reach comes by repeated squaring
+pub fn ring_bijective(rule: TableRule, n: usize) -> bool {
+    assert!(n > 2 * rule.radius + 1, "a {n}-cell ring is no wider than the rule's window");
+    let (mut step, mut power, mut t) = (Reach::step(rule), None::<Reach>, n);
+    while t > 0 {
+        if t & 1 == 1 {
+            power = Some(power.map_or_else(|| step.clone(), |p| p.then(&step)));
+        }
+        t >>= 1;
+        if t > 0 {
+            step = step.then(&step);
+        }
+    }
+    !power.unwrap().closes_off_diagonal()
+}
+
+/// What the deciders say of one rule
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Surjectivity {
+    /// The shortest orphan, if the rule isn't surjective
+    pub orphan: Option<Vec<bool>>,
+    pub injective: bool,
+    /// Ring widths the rule permutes, of those checked
+    pub rings: Vec<usize>,
+    /// The ring widths checked: one more than the window up to `max_ring`
+    pub checked: std::ops::RangeInclusive<usize>,
+}
+
+impl Surjectivity {
+    /// Decide `rule` on bi-infinite rows and on rings up to `max_ring` cells
+    pub fn of(rule: TableRule, max_ring: usize) -> Self {
+        // One more step of reach per width
+        let checked = 2 * rule.radius + 2..=max_ring;
+        let step = Reach::step(rule);
+        let mut reach = (1..*checked.start()).fold(step.clone(), |reach, _| reach.then(&step));
+        let rings = checked
+            .clone()
+            .filter(|_| {
+                let permutes = !reach.closes_off_diagonal();
+                reach = reach.then(&step);
+                permutes
+            })
+            .collect();
+        Surjectivity { orphan: orphan(rule), injective: injective(rule), rings, checked }
+    }
+
+    pub fn surjective(&self) -> bool {
+        self.orphan.is_none()
+    }
+
+    /// The ring widths permuted, in words: `all`, `none`, `n not divisible
+    /// by p` when that fits them exactly, or else the list
+    pub fn ring_widths(&self) -> String {
+        let checked = || self.checked.clone();
+        match self.rings.len() {
+            0 => "none".to_string(),
+            n if n == checked().count() => "all".to_string(),
+            _ => match (2..=8).find(|&p| checked().filter(|n| n % p != 0).eq(self.rings.iter().copied())) {
+                Some(p) => format!("n not divisible by {p}"),
+                None => self.rings.iter().map(usize::to_string).collect::<Vec<_>>().join(" "),
+            },
+        }
+    }
+}
+
+/// A SAT search, with the size of the formula it was given
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SatSearch {
+    /// For a model, the row found
+    pub outcome: Outcome,
+    pub vars: usize,
+    pub clauses: usize,
+}
+
+impl SatSearch {
+    /// The first `cells` variables of a model are the row
+    fn new(mut solver: Solver, cells: usize, budget: Option<u64>) -> Self {
+        let outcome = match solver.solve(budget) {
+            Outcome::Sat(model) => Outcome::Sat(model[..cells].to_vec()),
+            outcome => outcome,
+        };
+        SatSearch { outcome, vars: solver.vars(), clauses: solver.clauses() }
+    }
+}
+
+/// A preimage of `target` by SAT instead of walks, within `budget` conflicts
+pub fn sat_preimage(preimages: &Preimages, budget: Option<u64>) -> SatSearch {
+    let (rule, target) = (preimages.rule, preimages.target);
+    let (r, width) = (rule.radius, preimages.width());
+    let mut solver = Solver::new();
+    for _ in 0..width {
+        solver.var();
+    }
+    for (i, &cell) in target.iter().enumerate() {
+        let cells: Vec<usize> = match preimages.boundary {
+            Boundary::Ring => (0..=2 * r).map(|k| (i + width - r + k) % width).collect(),
+            Boundary::Open => (i..=i + 2 * r).collect(),
+        };
+        // Not this window: some cell differs from it
+        for window in (0..rule.entries()).filter(|&w| (rule.table >> w & 1 == 1) != cell) {
+            let clause: Vec<Lit> =
+                cells.iter().enumerate().map(|(k, &x)| Lit::new(x, window >> (2 * r - k) & 1 == 0)).collect();
+            solver.add_clause(&clause);
+        }
+    }
+    SatSearch::new(solver, width, budget)
+}
+
+/// An orphan `length` cells long by SAT, within `budget` conflicts:
+/// unsatisfiable when every row that long has a preimage
+pub fn sat_orphan(rule: TableRule, length: usize, budget: Option<u64>) -> SatSearch {
+    let nodes = 1 << (2 * rule.radius);
+    let mut solver = Solver::new();
+    let row: Vec<usize> = (0..length).map(|_| solver.var()).collect();
+    let sets: Vec<Vec<usize>> = (0..=length).map(|_| (0..nodes).map(|_| solver.var()).collect()).collect();
+    sets[0].iter().for_each(|&node| solver.add_clause(&[Lit::positive(node)]));
+    sets[length].iter().for_each(|&node| solver.add_clause(&[!Lit::positive(node)]));
+    for i in 0..length {
+        for (from, c) in (0..nodes).flat_map(|node| [(node, false), (node, true)]) {
+            // In the set at i, with row cell i the edge's label: its end is in the next
+            let (label, to) = edge(rule, from, c);
+            let reached = [!Lit::positive(sets[i][from]), Lit::new(row[i], !label), Lit::positive(sets[i + 1][to])];
+            solver.add_clause(&reached);
+        }
+    }
+    SatSearch::new(solver, length, budget)
+}
+
+/// The step back of a rule that permutes the rows of a ring
+#[derive(Clone, Copy, Debug)]
+pub struct Inverse {
+    rule: TableRule,
+    width: usize,
+}
+
+impl Inverse {
+    /// The inverse of `rule` on a `width`-cell ring, if it has one there
+    pub fn new(rule: TableRule, width: usize) -> Result<Self, String> {
+        if width <= 2 * rule.radius + 1 {
+            return Err(format!("a {width}-cell ring is no wider than the radius-{} window", rule.radius));
+        }
+        if !ring_bijective(rule, width) {
+            return Err(format!("not injective on a {width}-cell ring (two of its rows share an image)"));
+        }
+        Ok(Inverse { rule, width })
+    }
+
+    /// The row `cells` came from: its one preimage
+    pub fn step(&self, cells: &[bool]) -> Vec<bool> {
+        assert_eq!(cells.len(), self.width, "the inverse is for {}-cell rings", self.width);
+        let preimages = Preimages::new(self.rule, cells, Boundary::Ring).expect("checked when the inverse was made");
+        preimages.enumerate(1).pop().expect("a bijective rule leaves no row without a preimage")
+    }
+}
+
+/// A rule's de Bruijn diagram, read against one target row
+pub struct Preimages<'a> {
+    rule: TableRule,
+    target: &'a [bool],
+    boundary: Boundary,
+}
+
+impl<'a> Preimages<'a> {
+    /// The preimages of `target` under `rule`. A ring must be wider than
+    /// the rule's window, so no cell is read twice by one neighborhood
+    pub fn new(rule: TableRule, target: &'a [bool], boundary: Boundary) -> Result<Self, String> {
+        let window = 2 * rule.radius + 1;
+        match boundary {
+            Boundary::Ring if target.len() <= window => {
+                Err(format!("a ring needs more than {window} cells for a radius-{} rule", rule.radius))
+            }
+            _ if target.is_empty() => Err("the target row has no cells".to_string()),
+            _ => Ok(Preimages { rule, target, boundary }),
+        }
+    }
+
+    /// Nodes of the diagram: one per 2r-cell window
+    pub fn nodes(&self) -> usize {
+        1 << (2 * self.rule.radius)
+    }
+
+    /// Width of each preimage
+    pub fn width(&self) -> usize {
+        match self.boundary {
+            Boundary::Ring => self.target.len(),
+            Boundary::Open => self.target.len() + 2 * self.rule.radius,
+        }
+    }
+
+    fn edge(&self, node: usize, cell: bool) -> (bool, usize) {
+        edge(self.rule, node, cell)
+    }
+
+    /// Walks spelling the target from each node, counted forward from the
+    /// ones `start` marks
+    fn walks(&self, start: impl Fn(usize) -> bool) -> Vec<Natural> {
+        let nodes = self.nodes();
+        let mut counts: Vec<Natural> =
+            (0..nodes).map(|node| if start(node) { Natural::one() } else { Natural::default() }).collect();
+        let mut next = vec![Natural::default(); nodes];
+        for &cell in self.target {
+            next.iter_mut().for_each(Natural::clear);
+            for (node, count) in counts.iter().enumerate().filter(|(_, count)| !count.is_zero()) {
+                for c in [false, true] {
+                    let (label, to) = self.edge(node, c);
+                    if label == cell {
+                        next[to].add(count);
+                    }
+                }
+            }
+            std::mem::swap(&mut counts, &mut next);
+        }
+        counts
+    }
+
+    /// How many preimages the target has (0 for a Garden of Eden)
+    pub fn count(&self) -> Natural {
+        let mut total = Natural::default();
+        match self.boundary {
+            Boundary::Ring => (0..self.nodes()).for_each(|s| total.add(&self.walks(|node| node == s)[s])),
+            Boundary::Open => self.walks(|_| true).iter().for_each(|count| total.add(count)),
+        }
+        total
+    }
+
+    /// For each position, the nodes a walk can still finish from: a bit
+    /// per node, given the nodes it may finish at
+    fn alive(&self, ends: u64) -> Vec<u64> {
+        let mut alive = vec![0u64; self.target.len() + 1];
+        alive[self.target.len()] = ends;
+        for (i, &cell) in self.target.iter().enumerate().rev() {
+            alive[i] = (0..self.nodes())
+                .filter(|&node| {
+                    [false, true].into_iter().any(|c| {
+                        let (label, to) = self.edge(node, c);
+                        label == cell && alive[i + 1] >> to & 1 == 1
+                    })
+                })
+                .fold(0, |mask, node| mask | 1 << node);
+        }
+        alive
+    }
+
+    /// Up to `limit` preimages, each in its walk's order: by starting
+    /// window, then dead before live cell by cell
+    pub fn enumerate(&self, limit: usize) -> Vec<Vec<bool>> {
+        let (mut found, all) = (Vec::new(), u64::MAX >> (64 - self.nodes()));
+        match self.boundary {
+            Boundary::Ring => {
+                for s in 0..self.nodes() {
+                    if found.len() == limit {
+                        break;
+                    }
+                    let alive = self.alive(1 << s);
+                    if alive[0] >> s & 1 == 1 {
+                        self.walk_from(s, &alive, limit, &mut found);
+                    }
+                }
+            }
+            Boundary::Open => {
+                let alive = self.alive(all);
+                for s in (0..self.nodes()).filter(|&s| alive[0] >> s & 1 == 1) {
+                    if found.len() < limit {
+                        self.walk_from(s, &alive, limit, &mut found);
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// Depth-first over the walks from `start` that `alive` allows, kept
+    /// on a stack of nodes (with the next cell to try at each) so wide
+    /// rows don't recurse
+    fn walk_from(&self, start: usize, alive: &[u64], limit: usize, found: &mut Vec<Vec<bool>>) {
+        let n = self.target.len();
+        let (mut path, mut tried) = (vec![start], vec![0u8]);
+        while let Some(&node) = path.last() {
+            let i = path.len() - 1;
+            if i == n {
+                found.push(self.cells(&path));
+                if found.len() == limit {
+                    return;
+                }
+            } else if tried[i] < 2 {
+                let c = tried[i] == 1;
+                tried[i] += 1;
+                let (label, to) = self.edge(node, c);
+                if label == self.target[i] && alive[i + 1] >> to & 1 == 1 {
+                    path.push(to);
+                    tried.push(0);
+                }
+                continue;
+            }
+            path.pop();
+            tried.pop();
+        }
+    }
+
+    /// The preimage a walk spells: its first node's cells, then the cell
+    /// each edge appends (on a ring, the walk's first node straddles
+    /// cell 0, and its last r appended cells wrap onto the first r)
+    fn cells(&self, path: &[usize]) -> Vec<bool> {
+        let (r, start) = (self.rule.radius, path[0]);
+        let appended = path[1..].iter().map(|&node| node & 1 == 1);
+        match self.boundary {
+            Boundary::Ring => {
+                let first = (0..r).map(|j| start >> (r - 1 - j) & 1 == 1);
+                first.chain(appended.take(self.target.len() - r)).collect()
+            }
+            Boundary::Open => (0..2 * r).map(|j| start >> (2 * r - 1 - j) & 1 == 1).chain(appended).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_preimages_match_brute_force() {
+        let bits = |n: usize, w: usize| (0..w).map(|i| n >> i & 1 == 1).collect::<Vec<bool>>();
+        for (radius, table) in [(1, 30), (1, 110), (1, 90), (1, 4), (2, 0x96e8_1f3c)] {
+            let rule = TableRule::new(radius, table);
+            let w = 9;
+            let mut hits = vec![0u64; 1 << w];
+            for x in 0..1 << w {
+                let image = rule.step(&bits(x, w));
+                hits[(0..w).filter(|&i| image[i]).map(|i| 1 << i).sum::<usize>()] += 1;
+            }
+            for y in [0, 1, 0b101101, 0b111111111, 0b10010] {
+                let target = bits(y, w);
+                let preimages = Preimages::new(rule, &target, Boundary::Ring).unwrap();
+                assert_eq!(preimages.count().to_u64(), Some(hits[y]), "rule {table:x} on {y:b}");
+                let found = preimages.enumerate(usize::MAX);
+                assert_eq!(found.len() as u64, hits[y]);
+                assert!(found.iter().all(|x| rule.step(x) == target));
+            }
+        }
+
+        // Open rows: every window of a preimage's image lies inside it
+        let rule = TableRule::new(1, 30);
+        let target = bits(0b1101, 4);
+        let open = Preimages::new(rule, &target, Boundary::Open).unwrap();
+        let found = open.enumerate(usize::MAX);
+        assert_eq!((open.width(), Some(found.len() as u64)), (6, open.count().to_u64()));
+        assert!(found.iter().all(|x| (0..4).all(|i| rule.step(&x[i..i + 3])[1] == target[i])));
+
+        // Counts past 64 bits: every 200-cell row maps to zero under rule 0
+        let zeros = vec![false; 200];
+        let all = Preimages::new(TableRule::new(1, 0), &zeros, Boundary::Ring).unwrap().count();
+        assert_eq!(all.bits(), 201);
+        assert_eq!(all.to_string(), "1606938044258990275541962092341162602522202993782792835301376");
+        assert_eq!(Preimages::new(TableRule::new(1, 0), &[true; 5], Boundary::Ring).unwrap().count().to_u64(), Some(0));
+        assert!(Preimages::new(rule, &[true; 3], Boundary::Ring).is_err());
+    }
+
+    #[test]
+    fn test_decides_surjectivity_and_injectivity() {
+        use crate::basins::in_degrees;
+        let elementary = |rule: u8| TableRule::new(1, rule as u128);
+        let injective: Vec<u8> = (0..=255).filter(|&rule| injective(elementary(rule))).collect();
+        assert_eq!(injective, [15, 51, 85, 170, 204, 240]);
+        let surjective: Vec<u8> = (0..=255).filter(|&rule| orphan(elementary(rule)).is_none()).collect();
+        assert_eq!(surjective.len(), 30);
+        assert!([30, 45, 60, 90, 105, 106, 150, 154].iter().all(|rule| surjective.contains(rule)));
+
+        // An orphan has no preimage, and every shorter row has one
+        for rule in [110, 18, 54, 4] {
+            let row = orphan(elementary(rule)).unwrap();
+            assert_eq!(Preimages::new(elementary(rule), &row, Boundary::Open).unwrap().count().to_u64(), Some(0));
+            let shorter = row.len() - 1;
+            assert!((0..1usize << shorter).all(|y| {
+                let row: Vec<bool> = (0..shorter).map(|i| y >> i & 1 == 1).collect();
+                row.is_empty() || !Preimages::new(elementary(rule), &row, Boundary::Open).unwrap().count().is_zero()
+            }));
+        }
+
+        // Rings permuted, against every state stepped
+        for rule in [15, 30, 60, 90, 105, 150, 170, 204, 110] {
+            for n in 4..=11 {
+                assert_eq!(ring_bijective(elementary(rule), n), in_degrees(rule, n).bijective(), "rule {rule} on {n}");
+            }
+        }
+        assert!(ring_bijective(elementary(150), 40) && !ring_bijective(elementary(150), 42));
+        let decided = |rule| Surjectivity::of(elementary(rule), 30);
+        assert_eq!(decided(150).ring_widths(), "n not divisible by 3");
+        assert_eq!(decided(90).ring_widths(), "none");
+        assert!(decided(170).injective && decided(170).ring_widths() == "all" && decided(30).surjective());
+    }
+
+    #[test]
+    fn test_inverse_steps_back() {
+        // Rule 150 permutes rings whose width isn't a multiple of 3, though no local rule undoes it
+        let rule = TableRule::new(1, 150);
+        let inverse = Inverse::new(rule, 50).unwrap();
+        let start: Vec<bool> = (0..50).map(|i| (i * 7 + i / 3) % 5 < 2).collect();
+        let mut row = start.clone();
+        for _ in 0..20 {
+            let back = inverse.step(&row);
+            assert_eq!(rule.step(&back), row);
+            row = back;
+        }
+        (0..20).for_each(|_| row = rule.step(&row));
+        assert_eq!(row, start);
+        assert!(Inverse::new(rule, 51).is_err() && Inverse::new(TableRule::new(1, 110), 50).is_err());
+        // Rule 15 (x'[i] = !x[i - 1]) undoes as rule 85 (x'[i] = !x[i + 1])
+        let row: Vec<bool> = (0..9).map(|i| i % 4 == 1).collect();
+        assert_eq!(Inverse::new(TableRule::new(1, 15), 9).unwrap().step(&row), TableRule::new(1, 85).step(&row));
+    }
+
+    #[test]
+    fn test_sat_agrees_with_the_diagram() {
+        let elementary = |rule: u8| TableRule::new(1, rule as u128);
+        for rule in [110, 18, 54, 4, 30, 90] {
+            // Surjective rules have none to find
+            let shortest = orphan(elementary(rule)).map_or(usize::MAX, |row| row.len());
+            for length in 1..=shortest.min(7) {
+                let search = sat_orphan(elementary(rule), length, None);
+                match search.outcome {
+                    Outcome::Sat(row) => {
+                        assert_eq!(length, shortest, "rule {rule}");
+                        assert!(Preimages::new(elementary(rule), &row, Boundary::Open).unwrap().count().is_zero());
+                    }
+                    outcome => assert!(outcome == Outcome::Unsat && length < shortest, "rule {rule} length {length}"),
+                }
+            }
+        }
+
+        let rule = TableRule::new(2, 0x96e8_1f3c);
+        for seed in 0..10u64 {
+            let target: Vec<bool> = (0..40).map(|i| (seed * 31 + i * i) % 7 < 3).collect();
+            for boundary in [Boundary::Ring, Boundary::Open] {
+                let preimages = Preimages::new(rule, &target, boundary).unwrap();
+                match sat_preimage(&preimages, None).outcome {
+                    Outcome::Sat(x) if boundary == Boundary::Ring => assert_eq!(rule.step(&x), target),
+                    Outcome::Sat(x) => assert_eq!(rule.step(&x)[2..42], target),
+                    outcome => assert!(outcome == Outcome::Unsat && preimages.count().is_zero()),
+                }
+            }
+        }
+    }
+}
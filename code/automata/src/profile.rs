@@ -0,0 +1,76 @@
+//! Process-wide counters for `--profile`
+//!
+//! The library adds to them where it does work that wall time alone hides:
+//! states held for cycle detection and basin tables, lookups in the GA's
+//! fitness cache and HashLife's memo, and the backend a survey ran on. Each
+//! run adds its total once, when it finishes, so the counters cost nothing
+//! per step; they only grow, and a caller measures a stretch of work by
+//! comparing two `snapshot`s.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+static STATES: AtomicU64 = AtomicU64::new(0);
+static CACHE_LOOKUPS: AtomicU64 = AtomicU64::new(0);
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static BACKEND: Mutex<Option<&'static str>> = Mutex::new(None);
+
+/// Record `n` states stored, e.g. one run's cycle-detection history
+pub fn add_states(n: usize) {
+    STATES.fetch_add(n as u64, Ordering::Relaxed);
+}
+
+/// Record cache lookups and how many of them hit
+pub fn add_cache(lookups: usize, hits: usize) {
+    CACHE_LOOKUPS.fetch_add(lookups as u64, Ordering::Relaxed);
+    CACHE_HITS.fetch_add(hits as u64, Ordering::Relaxed);
+}
+
+/// Record the backend the work ran on, e.g. "gpu"; the last one set wins
+pub fn set_backend(name: &'static str) {
+    *BACKEND.lock().unwrap() = Some(name);
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub states: u64,
+    pub cache_lookups: u64,
+    pub cache_hits: u64,
+    /// None if nothing set one: the CPU
+    pub backend: Option<&'static str>,
+}
+
+/// The counters so far
+pub fn snapshot() -> Counters {
+    Counters {
+        states: STATES.load(Ordering::Relaxed),
+        cache_lookups: CACHE_LOOKUPS.load(Ordering::Relaxed),
+        cache_hits: CACHE_HITS.load(Ordering::Relaxed),
+        backend: *BACKEND.lock().unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_add_their_states() {
+        // Other tests run alongside and may add too, so only a lower bound holds
+        let before = snapshot();
+        let analysis = crate::find_cycle(110, 11, 1000);
+        let after = snapshot();
+        assert!(after.states >= before.states + (analysis.transient + analysis.period) as u64);
+
+        crate::basins::enumerate_basins(30, 8);
+        assert!(snapshot().states >= after.states + 256);
+
+        // A dead ring's blocks are all one block: every lookup after the
+        // first few of each level is a memo hit
+        let before = snapshot();
+        let rule = crate::evolve::TableRule::new(1, 0);
+        crate::hashlife::HashLife::new(rule).advance(&[false; 200], 1 << 30);
+        let after = snapshot();
+        assert!(after.cache_hits >= before.cache_hits + 20 && after.cache_lookups > before.cache_lookups + 20);
+    }
+}
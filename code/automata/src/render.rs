@@ -0,0 +1,1204 @@
+//! Spacetime diagrams as PNG or SVG images
+//!
+//! Rows are simulated and encoded one scanline at a time, so the image size
+//! is limited by the file, not by memory for the diagram; only the deflated
+//! pixels are held, and not even those when they might not fit the memory
+//! budget (see `budget`), in which case they go out as they are made, in a
+//! run of IDAT chunks. Each cell becomes
+//! a `cell_size` square of pixels; with `downscale` d > 1, each pixel instead
+//! averages a d x d block of cells, blending the dead and live colors.
+//! The PNG encoder is the minimal subset: 8-bit RGB, zlib IDAT data.
+//! "Live" is relative to the automaton's background: on a declared live
+//! background, the cells drawn in the live color are the dead ones.
+//!
+//! PPM and PBM are the uncompressed netpbm formats, for piping into other
+//! tools: PPM has the PNG's pixels, PBM one bit per pixel with live black.
+//!
+//! Contact sheets lay out several diagrams in a grid, each labeled with its
+//! rule number in a built-in 3 x 5 pixel digit font.
+//!
+//! With an ether, the background's live cells are drawn in a faint tint so
+//! the particles moving through it stand out; those diagrams are drawn in
+//! memory, since each row's mask needs the whole row. Diffs of two diagrams
+//! are drawn the same way, in red and blue over the faint cells they share,
+//! and so are the rows of multi-color rules, in shades from dead to live,
+//! and composites of two or three rules run side by side, one per color
+//! channel. Coupled map lattices' real-valued cells are shaded continuously.
+//!
+//! Wallpaper tiles are one period of a cycle: the ring already wraps in
+//! space, and the row after the last is the first again, so copies of the
+//! image placed edge to edge continue the diagram in both directions. They
+//! are resampled to any size by area averaging, with the pixel grid lined
+//! up on the tile's edges so no seam appears.
+//!
+//! Plots of state-space trajectories (one point per generation) are drawn
+//! the same way, as connected paths in a framed square.
+//!
+//! Animations are APNG: each frame is a `window`-row slice of the spacetime
+//! ending at the current generation, so the diagram scrolls up as it grows.
+//! Viewers without APNG support show the first frame.
+//!
+//! SVG output is for figures: a background rectangle in the dead color, one
+//! rectangle per horizontal run of live cells (per live cell when cells are
+//! outlined), and optional grid lines and margins.
+
+use crate::ether::Ether;
+use crate::Automaton;
+use std::collections::VecDeque;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::{self, Write};
+
+/// Pixel geometry and colors for `write_png`
+#[derive(Clone, Copy, Debug)]
+pub struct RenderOptions {
+    /// Pixels per cell side (ignored when downscaling)
+    pub cell_size: usize,
+    /// Cells per pixel side; 1 for none
+    pub downscale: usize,
+    pub live: [u8; 3],
+    pub dead: [u8; 3],
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self { cell_size: 1, downscale: 1, live: [0, 0, 0], dead: [255, 255, 255] }
+    }
+}
+
+/// Geometry and styling for `write_svg`; lengths are in SVG user units
+#[derive(Clone, Copy, Debug)]
+pub struct SvgOptions {
+    pub cell_size: f64,
+    /// Blank border around the diagram
+    pub margin: f64,
+    /// Outline width for live cells; 0 for none
+    pub stroke: f64,
+    pub stroke_color: [u8; 3],
+    /// Width of lines between all cells; 0 for none
+    pub grid: f64,
+    pub grid_color: [u8; 3],
+    pub live: [u8; 3],
+    pub dead: [u8; 3],
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            cell_size: 4.0,
+            margin: 0.0,
+            stroke: 0.0,
+            stroke_color: [128, 128, 128],
+            grid: 0.0,
+            grid_color: [200, 200, 200],
+            live: [0, 0, 0],
+            dead: [255, 255, 255],
+        }
+    }
+}
+
+/// Image dimensions in pixels for a diagram of `width` cells and `rows`
+/// rows (partial blocks at the edges are dropped when downscaling)
+pub fn image_size(width: usize, rows: usize, options: &RenderOptions) -> (usize, usize) {
+    if options.downscale > 1 {
+        (width / options.downscale, rows / options.downscale)
+    } else {
+        (width * options.cell_size, rows * options.cell_size)
+    }
+}
+
+/// Simulate `generations` steps from `ca` and write the diagram (initial
+/// row included) to `out` as a PNG
+pub fn write_png(out: &mut impl Write, ca: Automaton, generations: usize, options: &RenderOptions) -> io::Result<()> {
+    let width = ca.width();
+    encode_png(out, spacetime(ca, generations), width, generations + 1, options)
+}
+
+/// `write_png` for rows already in hand, such as a filtered diagram; they
+/// must all have the same width
+pub fn write_png_rows(out: &mut impl Write, rows: &[Vec<bool>], options: &RenderOptions) -> io::Result<()> {
+    let width = rows.first().map_or(0, Vec::len);
+    if rows.iter().any(|row| row.len() != width) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "rows differ in width"));
+    }
+    let cells = rows.iter().map(|row| Automaton::from_cells(row.clone(), 0));
+    encode_png(out, cells, width, rows.len(), options)
+}
+
+/// PNG of the first `count` of `rows`, each `width` cells
+fn encode_png(
+    out: &mut impl Write,
+    rows: impl Iterator<Item = Automaton>,
+    width: usize,
+    count: usize,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let (image_width, image_height) = image_size(width, count, options);
+    if image_width == 0 || image_height == 0 || image_width > u32::MAX as usize || image_height > u32::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("can't make a {image_width} x {image_height} image")));
+    }
+
+    let mut scanline = Vec::with_capacity(1 + 3 * image_width);
+    let scanlines = |pixels: &mut dyn Write| {
+        render_rows(rows, width, count, options, |shades| {
+            scanline.clear();
+            scanline.push(0); // filter type: none
+            shades.for_each(|rgb| scanline.extend_from_slice(&rgb));
+            pixels.write_all(&scanline)
+        })
+    };
+
+    out.write_all(PNG_SIGNATURE)?;
+    write_chunk(out, b"IHDR", &image_header(image_width, image_height))?;
+    // Deflate can't promise to shrink the pixels, so budget for all of them
+    let raw = ((1 + 3 * image_width) as u64).saturating_mul(image_height as u64);
+    if crate::budget::fits(raw) {
+        let mut pixels = ZlibEncoder::new(Vec::new(), Compression::default());
+        scanlines(&mut pixels)?;
+        write_chunk(out, b"IDAT", &pixels.finish()?)?;
+    } else {
+        let how = format!("streamed in IDAT chunks ({} of pixels)", crate::budget::bytes(raw));
+        crate::budget::degrade("PNG pixels", how);
+        let mut pixels = ZlibEncoder::new(IdatChunks { out: &mut *out, buffer: Vec::new() }, Compression::default());
+        scanlines(&mut pixels)?;
+        pixels.finish()?.flush()?;
+    }
+    write_chunk(out, b"IEND", &[])
+}
+
+/// Bytes of deflated pixels `IdatChunks` gathers into each chunk
+const IDAT_CHUNK: usize = 1 << 16;
+
+/// Writes deflated pixels to `out` as consecutive IDAT chunks of about
+/// `IDAT_CHUNK` bytes, which a PNG decoder reads as one stream
+struct IdatChunks<'a, W: Write> {
+    out: &'a mut W,
+    buffer: Vec<u8>,
+}
+
+impl<W: Write> Write for IdatChunks<'_, W> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+        if self.buffer.len() >= IDAT_CHUNK {
+            self.flush()?;
+        }
+        Ok(bytes.len())
+    }
+
+    /// Write what's gathered as a chunk; the last one is written this way
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            write_chunk(self.out, b"IDAT", &self.buffer)?;
+            self.buffer.clear();
+        }
+        self.out.flush()
+    }
+}
+
+/// `write_png` with the cells of `ether` drawn faintly: its live cells take
+/// a tint a fifth of the way from the dead color to the live one
+pub fn write_png_ether(
+    out: &mut impl Write,
+    ca: Automaton,
+    generations: usize,
+    ether: &Ether,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let (live, faint, dead) = (options.live, tint(options, 0.2), options.dead);
+    let width = ca.width();
+    let rows = spacetime(ca, generations).map(|ca| {
+        let mask = ether.mask(&ca.cells());
+        (0..ca.width())
+            .map(|x| match (ca.deviates(x), mask[x]) {
+                (false, _) => dead,
+                (true, true) => faint,
+                (true, false) => live,
+            })
+            .collect()
+    });
+    write_png_colors(out, rows, width, generations + 1, options)
+}
+
+/// Colors for two diagrams' differences: cells live only in the first, red;
+/// only in the second, blue
+pub const DIFF_COLORS: [[u8; 3]; 2] = [[220, 40, 40], [40, 90, 220]];
+
+/// The XOR image of two diagrams of the same width, over the rows they
+/// share: differing cells in `DIFF_COLORS`, cells live in both faintly
+pub fn write_png_diff(
+    out: &mut impl Write,
+    a: &[Vec<bool>],
+    b: &[Vec<bool>],
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let width = a.first().map_or(0, Vec::len);
+    if a.iter().chain(b).any(|row| row.len() != width) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "rows differ in width"));
+    }
+    let (faint, dead) = (tint(options, 0.2), options.dead);
+    let rows = a.iter().zip(b).map(|(x, y)| {
+        x.iter()
+            .zip(y)
+            .map(|cells| match cells {
+                (false, false) => dead,
+                (true, true) => faint,
+                (true, false) => DIFF_COLORS[0],
+                (false, true) => DIFF_COLORS[1],
+            })
+            .collect()
+    });
+    write_png_colors(out, rows, width, a.len().min(b.len()), options)
+}
+
+/// Rows of multi-color cells (0 to `colors` - 1, as a `WolframRule` steps
+/// them), shaded evenly from the dead color for 0 to the live one for the last
+pub fn write_png_states(out: &mut impl Write, rows: &[Vec<u8>], colors: u8, options: &RenderOptions) -> io::Result<()> {
+    let width = rows.first().map_or(0, Vec::len);
+    if colors < 2 || rows.iter().flatten().any(|&c| c >= colors) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("cells must be 0 to {}", colors.max(1) - 1)));
+    }
+    let shades: Vec<[u8; 3]> = (0..colors).map(|c| tint(options, c as f64 / (colors - 1) as f64)).collect();
+    let pixels = rows.iter().map(|row| row.iter().map(|&c| shades[c as usize]).collect());
+    write_png_colors(out, pixels, width, rows.len(), options)
+}
+
+/// Rows of real-valued cells in [0, 1] (a `lattice::Lattice`'s), shaded
+/// continuously from the dead color at 0 to the live one at 1
+pub fn write_png_gray(out: &mut impl Write, rows: &[Vec<f64>], options: &RenderOptions) -> io::Result<()> {
+    let width = rows.first().map_or(0, Vec::len);
+    let pixels = rows.iter().map(|row| row.iter().map(|&x| tint(options, x.clamp(0.0, 1.0))).collect());
+    write_png_colors(out, pixels, width, rows.len(), options)
+}
+
+/// How `write_png_rgb` combines its diagrams, one per channel
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Blend {
+    /// On black, each diagram's live cells light its channel, so overlaps
+    /// add up toward white, like colored lights
+    #[default]
+    Add,
+    /// On white, each diagram's live cells take out its channel, so
+    /// overlaps darken toward black, like inks on paper
+    Subtract,
+}
+
+/// Two or three diagrams of the same width, simulated in lockstep from
+/// `initial` for `generations` steps, as the red, green and (with three)
+/// blue channels of one PNG. The live and dead colors are not used;
+/// downscaled pixels give each channel its diagram's live fraction.
+pub fn write_png_rgb(
+    out: &mut impl Write,
+    initial: &[Automaton],
+    generations: usize,
+    blend: Blend,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let width = initial.first().map_or(0, Automaton::width);
+    if !(2..=3).contains(&initial.len()) || initial.iter().any(|ca| ca.width() != width) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "need two or three diagrams of one width"));
+    }
+    let mut runs: Vec<_> = initial.iter().map(|ca| spacetime(ca.clone(), generations)).collect();
+    let rows = (0..=generations).map(move |_| {
+        let row: Vec<Automaton> = runs.iter_mut().map(|run| run.next().expect("generations + 1 rows")).collect();
+        (0..width)
+            .map(|x| {
+                let live = |c: usize| row.get(c).is_some_and(|ca| ca.deviates(x));
+                let lit: [u8; 3] = std::array::from_fn(|c| if live(c) { 255 } else { 0 });
+                match blend {
+                    Blend::Add => lit,
+                    Blend::Subtract => lit.map(|v| 255 - v),
+                }
+            })
+            .collect()
+    });
+    write_png_colors(out, rows, width, generations + 1, options)
+}
+
+/// The color `t` of the way from the dead color to the live one
+fn tint(options: &RenderOptions, t: f64) -> [u8; 3] {
+    let (from, to) = (options.dead.map(f64::from), options.live.map(f64::from));
+    std::array::from_fn(|c| (from[c] + t * (to[c] - from[c])).round() as u8)
+}
+
+/// A PNG of `count` rows of per-cell colors, `width` cells each, drawn in
+/// memory; downscaled pixels average the colors of their d x d block
+fn write_png_colors(
+    out: &mut impl Write,
+    mut rows: impl Iterator<Item = Vec<[u8; 3]>>,
+    width: usize,
+    count: usize,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let (image_width, image_height) = image_size(width, count, options);
+    if image_width == 0 || image_height == 0 || image_width > u32::MAX as usize || image_height > u32::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("can't make a {image_width} x {image_height} image")));
+    }
+    let (d, size) = if options.downscale > 1 { (options.downscale, 1) } else { (1, options.cell_size) };
+    let mut canvas = Vec::with_capacity(3 * image_width * image_height);
+    let mut sums = vec![[0usize; 3]; image_width];
+    for row in 0..(image_height * d / size) {
+        let colors = rows.next().expect("count rows");
+        if d == 1 {
+            let scanline: Vec<u8> = colors.iter().flat_map(|rgb| std::iter::repeat_n(*rgb, size)).flatten().collect();
+            (0..size).for_each(|_| canvas.extend_from_slice(&scanline));
+            continue;
+        }
+        for (i, sum) in sums.iter_mut().enumerate() {
+            for rgb in &colors[i * d..(i + 1) * d] {
+                (0..3).for_each(|c| sum[c] += rgb[c] as usize);
+            }
+        }
+        if row % d == d - 1 {
+            for sum in &mut sums {
+                canvas.extend(sum.map(|total| (total as f64 / (d * d) as f64).round() as u8));
+                *sum = [0; 3];
+            }
+        }
+    }
+    write_canvas(out, image_width, image_height, &canvas)
+}
+
+/// One period of the cycle that `start` is on, `period` rows from it, as a
+/// tileable PNG: at `size` pixels (width, height) if given, each pixel
+/// blending the dead and live colors by the live share of the cells it
+/// covers, else with the cells drawn as `write_png` does
+pub fn write_png_tile(
+    out: &mut impl Write,
+    start: Automaton,
+    period: usize,
+    size: Option<(usize, usize)>,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    if period == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "a tile needs a period of at least one row"));
+    }
+    let Some((image_width, image_height)) = size else {
+        return write_png(out, start, period - 1, options);
+    };
+    if image_width == 0 || image_height == 0 || image_width > u32::MAX as usize || image_height > u32::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("can't make a {image_width} x {image_height} image")));
+    }
+
+    let width = start.width();
+    let rows: Vec<Vec<bool>> = spacetime(start, period - 1).map(|ca| ca.deviations().collect()).collect();
+    let (columns, lines) = (coverage(width, image_width), coverage(period, image_height));
+    // Each pixel covers `width` x `period` units of cell overlap in all
+    let area = (width * period) as f64;
+    let mut canvas = Vec::with_capacity(3 * image_width * image_height);
+    for line in &lines {
+        for column in &columns {
+            let live: usize = line
+                .iter()
+                .map(|&(y, h)| column.iter().filter(|&&(x, _)| rows[y][x]).map(|&(_, w)| w * h).sum::<usize>())
+                .sum();
+            canvas.extend(tint(options, live as f64 / area));
+        }
+    }
+    write_canvas(out, image_width, image_height, &canvas)
+}
+
+/// For each of `pixels` spans across `cells`, the cells it overlaps and by
+/// how much, in units of 1/`pixels` of a cell: every span totals `cells`
+fn coverage(cells: usize, pixels: usize) -> Vec<Vec<(usize, usize)>> {
+    (0..pixels)
+        .map(|p| {
+            let (from, to) = (p * cells, (p + 1) * cells);
+            (from / pixels..to.div_ceil(pixels))
+                .map(|c| (c, to.min((c + 1) * pixels) - from.max(c * pixels)))
+                .collect()
+        })
+        .collect()
+}
+
+/// Simulate `generations` steps from `ca` and write the diagram as a binary
+/// PPM (netpbm P6): the same pixels as `write_png`, uncompressed
+pub fn write_ppm(out: &mut impl Write, ca: Automaton, generations: usize, options: &RenderOptions) -> io::Result<()> {
+    let (image_width, image_height) = image_size(ca.width(), generations + 1, options);
+    if image_width == 0 || image_height == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("can't make a {image_width} x {image_height} image")));
+    }
+
+    write!(out, "P6\n{image_width} {image_height}\n255\n")?;
+    let mut scanline = Vec::with_capacity(3 * image_width);
+    let width = ca.width();
+    render_rows(spacetime(ca, generations), width, generations + 1, options, |shades| {
+        scanline.clear();
+        shades.for_each(|rgb| scanline.extend_from_slice(&rgb));
+        out.write_all(&scanline)
+    })
+}
+
+/// Simulate `generations` steps from `ca` and write the diagram as a binary
+/// PBM (netpbm P4), live cells black and dead white, each `cell_size` pixels
+/// square. One bit per pixel, so there are no colors or downscaling.
+pub fn write_pbm(out: &mut impl Write, mut ca: Automaton, generations: usize, cell_size: usize) -> io::Result<()> {
+    let size = cell_size.max(1);
+    let (image_width, image_height) = (ca.width() * size, (generations + 1) * size);
+    if image_width == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("can't make a {image_width} x {image_height} image")));
+    }
+
+    write!(out, "P4\n{image_width} {image_height}\n")?;
+    let mut scanline = vec![0u8; image_width.div_ceil(8)];
+    for row in 0..=generations {
+        if row > 0 {
+            ca.step();
+        }
+        // Rows are packed most significant bit first, padded to a whole byte
+        scanline.fill(0);
+        for x in (0..image_width).filter(|x| ca.deviates(x / size)) {
+            scanline[x / 8] |= 0x80 >> (x % 8);
+        }
+        (0..size).try_for_each(|_| out.write_all(&scanline))?;
+    }
+    Ok(())
+}
+
+/// The initial row and the `generations` after it
+fn spacetime(ca: Automaton, generations: usize) -> impl Iterator<Item = Automaton> {
+    std::iter::successors(Some(ca), |ca| {
+        let mut next = ca.clone();
+        next.step();
+        Some(next)
+    })
+    .take(generations + 1)
+}
+
+/// Pass each pixel row for the first `count` of `rows`, each `width` cells,
+/// to `emit` in turn
+fn render_rows(
+    mut rows: impl Iterator<Item = Automaton>,
+    width: usize,
+    count: usize,
+    options: &RenderOptions,
+    mut emit: impl FnMut(&mut dyn Iterator<Item = [u8; 3]>) -> io::Result<()>,
+) -> io::Result<()> {
+    let (image_width, image_height) = image_size(width, count, options);
+    if options.downscale > 1 {
+        // Live cells per block, accumulated over `downscale` rows
+        let d = options.downscale;
+        let blend = |live: usize| -> [u8; 3] {
+            let t = live as f64 / (d * d) as f64;
+            std::array::from_fn(|c| (options.dead[c] as f64 + t * (options.live[c] as f64 - options.dead[c] as f64)).round() as u8)
+        };
+        let mut counts = vec![0usize; image_width];
+        for (row, ca) in rows.by_ref().take(image_height * d).enumerate() {
+            for (i, count) in counts.iter_mut().enumerate() {
+                *count += (i * d..(i + 1) * d).filter(|&j| ca.deviates(j)).count();
+            }
+            if row % d == d - 1 {
+                emit(&mut counts.iter().map(|&live| blend(live)))?;
+                counts.fill(0);
+            }
+        }
+    } else {
+        let size = options.cell_size;
+        for ca in rows.take(count) {
+            for _ in 0..size {
+                let colors = ca.deviations().map(|alive| if alive { options.live } else { options.dead });
+                emit(&mut colors.flat_map(|rgb| std::iter::repeat_n(rgb, size)))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Layout for `write_sheet`, in pixels
+#[derive(Clone, Copy, Debug)]
+pub struct SheetOptions {
+    /// Tiles per row
+    pub columns: usize,
+    /// Space between and around tiles
+    pub gap: usize,
+    /// Pixels per dot of the 3 x 5 label digits; 0 for no labels
+    pub label_scale: usize,
+    /// Color of the gaps and label strips
+    pub background: [u8; 3],
+}
+
+impl Default for SheetOptions {
+    fn default() -> Self {
+        Self { columns: 8, gap: 6, label_scale: 2, background: [224, 224, 224] }
+    }
+}
+
+/// Contact sheet dimensions in pixels for `tiles` diagrams of `width` cells
+/// and `rows` rows
+pub fn sheet_size(tiles: usize, width: usize, rows: usize, sheet: &SheetOptions, options: &RenderOptions) -> (usize, usize) {
+    let (tile_width, tile_height) = image_size(width, rows, options);
+    let columns = sheet.columns.clamp(1, tiles.max(1));
+    let grid_rows = tiles.div_ceil(columns);
+    (columns * (tile_width + sheet.gap) + sheet.gap, grid_rows * (label_height(sheet) + tile_height + sheet.gap) + sheet.gap)
+}
+
+/// Simulate `generations` steps from each of `tiles` and write the diagrams
+/// as one PNG, in rows of `columns`, each labeled above with its rule number
+/// in the live color. The tiles must all have the same width.
+pub fn write_sheet(
+    out: &mut impl Write,
+    tiles: &[Automaton],
+    generations: usize,
+    sheet: &SheetOptions,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let width = tiles.first().map_or(0, Automaton::width);
+    if tiles.iter().any(|ca| ca.width() != width) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "contact sheet tiles differ in width"));
+    }
+    let (tile_width, tile_height) = image_size(width, generations + 1, options);
+    let (image_width, image_height) = sheet_size(tiles.len(), width, generations + 1, sheet, options);
+    if tile_width == 0 || tile_height == 0 || image_width > u32::MAX as usize || image_height > u32::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("can't make a {image_width} x {image_height} image")));
+    }
+
+    // The sheet is small next to a single diagram, so draw it all, then encode
+    let mut canvas: Vec<u8> = sheet.background.repeat(image_width * image_height);
+    let columns = sheet.columns.clamp(1, tiles.len());
+    for (i, ca) in tiles.iter().enumerate() {
+        let left = sheet.gap + (i % columns) * (tile_width + sheet.gap);
+        let top = sheet.gap + (i / columns) * (label_height(sheet) + tile_height + sheet.gap);
+        let tile = Tile { left, top, width: tile_width, label: &ca.rule().to_string() };
+        draw_tile(&mut canvas, image_width, &tile, ca, generations, sheet, options)?;
+    }
+
+    write_canvas(out, image_width, image_height, &canvas)
+}
+
+/// One labeled diagram as a one-tile contact sheet's pixels (see
+/// `sheet_size` for its dimensions), for a frame of `write_apng_frames`.
+/// Label characters other than digits are left as gaps.
+pub fn sheet_frame(
+    ca: &Automaton,
+    label: &str,
+    generations: usize,
+    sheet: &SheetOptions,
+    options: &RenderOptions,
+) -> io::Result<Vec<u8>> {
+    let (tile_width, tile_height) = image_size(ca.width(), generations + 1, options);
+    let (image_width, image_height) = sheet_size(1, ca.width(), generations + 1, sheet, options);
+    if tile_width == 0 || tile_height == 0 || image_width > u32::MAX as usize || image_height > u32::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("can't make a {image_width} x {image_height} image")));
+    }
+    let mut canvas: Vec<u8> = sheet.background.repeat(image_width * image_height);
+    let tile = Tile { left: sheet.gap, top: sheet.gap, width: tile_width, label };
+    draw_tile(&mut canvas, image_width, &tile, ca, generations, sheet, options)?;
+    Ok(canvas)
+}
+
+/// Where a sheet tile goes on its canvas, and what its label says
+struct Tile<'a> {
+    left: usize,
+    top: usize,
+    width: usize,
+    label: &'a str,
+}
+
+/// Draw `ca`'s label, in the live color, and its diagram below it
+fn draw_tile(
+    canvas: &mut [u8],
+    image_width: usize,
+    tile: &Tile,
+    ca: &Automaton,
+    generations: usize,
+    sheet: &SheetOptions,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let Tile { left, top, width: tile_width, label } = *tile;
+    let scale = sheet.label_scale;
+    for (d, digit) in label.bytes().enumerate().filter(|(_, b)| b.is_ascii_digit()) {
+        let glyph = DIGITS[(digit - b'0') as usize];
+        for (row, bits) in glyph.iter().enumerate() {
+            for column in (0..3).filter(|c| bits >> (2 - c) & 1 == 1) {
+                for (dy, dx) in (0..scale).flat_map(|dy| (0..scale).map(move |dx| (dy, dx))) {
+                    let (x, y) = (left + (4 * d + column) * scale + dx, top + row * scale + dy);
+                    if x < left + tile_width {
+                        canvas[3 * (y * image_width + x)..][..3].copy_from_slice(&options.live);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut y = top + label_height(sheet);
+    render_rows(spacetime(ca.clone(), generations), ca.width(), generations + 1, options, |shades| {
+        let start = 3 * (y * image_width + left);
+        for (pixel, rgb) in canvas[start..start + 3 * tile_width].chunks_exact_mut(3).zip(shades) {
+            pixel.copy_from_slice(&rgb);
+        }
+        y += 1;
+        Ok(())
+    })
+}
+
+/// Size and colors for `write_plot`
+#[derive(Clone, Copy, Debug)]
+pub struct PlotOptions {
+    /// Side of the square image, in pixels
+    pub size: usize,
+    /// Pixels between the frame and the image edge
+    pub margin: usize,
+    /// Path color at the first point, fading to `end` at the last
+    pub start: [u8; 3],
+    pub end: [u8; 3],
+    /// Join the points in order; otherwise just the dots
+    pub path: bool,
+    pub frame: [u8; 3],
+    pub background: [u8; 3],
+}
+
+impl Default for PlotOptions {
+    fn default() -> Self {
+        Self {
+            size: 512,
+            margin: 16,
+            start: [150, 190, 255],
+            end: [200, 0, 0],
+            path: true,
+            frame: [160, 160, 160],
+            background: [255, 255, 255],
+        }
+    }
+}
+
+/// Write `points` as a PNG: a dot at each and, with `path`, lines joining
+/// them in order, scaled to fill a framed square. The color fades along the path, so
+/// the direction of time shows and a cycle is a closed loop in one color.
+pub fn write_plot(out: &mut impl Write, points: &[[f64; 2]], options: &PlotOptions) -> io::Result<()> {
+    let mut plot = Plot::new(points.iter(), options)?;
+    let shade = |i: usize| -> [u8; 3] {
+        let t = i as f64 / points.len().saturating_sub(1).max(1) as f64;
+        std::array::from_fn(|c| (options.start[c] as f64 + t * (options.end[c] as f64 - options.start[c] as f64)).round() as u8)
+    };
+    plot.series(points, options.path, shade);
+    write_canvas(out, plot.size, plot.size, &plot.canvas)
+}
+
+/// Write several series as a PNG on shared axes, each joined in order in
+/// its own color (`options.start`/`end` are unused)
+pub fn write_curves(out: &mut impl Write, curves: &[(&[[f64; 2]], [u8; 3])], options: &PlotOptions) -> io::Result<()> {
+    let mut plot = Plot::new(curves.iter().flat_map(|(points, _)| points.iter()), options)?;
+    for &(points, color) in curves {
+        plot.series(points, true, |_| color);
+    }
+    write_canvas(out, plot.size, plot.size, &plot.canvas)
+}
+
+/// A framed square canvas with axes scaled to fit the points it will show
+struct Plot {
+    canvas: Vec<u8>,
+    size: usize,
+    low: usize,
+    high: usize,
+    /// (min, span) of each axis
+    ranges: [(f64, f64); 2],
+}
+
+impl Plot {
+    fn new<'a>(points: impl Iterator<Item = &'a [f64; 2]> + Clone, options: &PlotOptions) -> io::Result<Self> {
+        let size = options.size;
+        if size <= 2 * options.margin + 2 || size > u32::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("can't make a {size} x {size} plot")));
+        }
+        let (low, high) = (options.margin, size - 1 - options.margin);
+        let range = |axis: usize| {
+            let values = points.clone().map(|p| p[axis]);
+            let (min, max) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), v| (a.min(v), b.max(v)));
+            (min, max - min)
+        };
+        let mut plot = Self { canvas: options.background.repeat(size * size), size, low, high, ranges: [range(0), range(1)] };
+        for i in low..=high {
+            for (x, y) in [(i, low), (i, high), (low, i), (high, i)] {
+                plot.put(x as i64, y as i64, options.frame);
+            }
+        }
+        Ok(plot)
+    }
+
+    fn put(&mut self, x: i64, y: i64, rgb: [u8; 3]) {
+        let (x, y) = (x as usize, y as usize);
+        if x < self.size && y < self.size {
+            self.canvas[3 * (y * self.size + x)..][..3].copy_from_slice(&rgb);
+        }
+    }
+
+    /// Scale each axis to the inside of the frame; a constant axis sits in the middle
+    fn pixel(&self, p: &[f64; 2]) -> [i64; 2] {
+        let inner = (self.high - self.low - 4) as f64;
+        let scaled = |axis: usize| {
+            let (min, span) = self.ranges[axis];
+            if span > 0.0 { (p[axis] - min) / span * inner } else { inner / 2.0 }
+        };
+        [(self.low + 2) as i64 + scaled(0).round() as i64, (self.high - 2) as i64 - scaled(1).round() as i64]
+    }
+
+    /// Segments (Bresenham) between consecutive points when `path`, then
+    /// 3 x 3 dots on top; point i and the segment leaving it are `shade(i)`
+    fn series(&mut self, points: &[[f64; 2]], path: bool, shade: impl Fn(usize) -> [u8; 3]) {
+        for (i, pair) in points.windows(2).enumerate().filter(|_| path) {
+            let ([mut x, mut y], [x1, y1]) = (self.pixel(&pair[0]), self.pixel(&pair[1]));
+            let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+            let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+            let mut error = dx + dy;
+            loop {
+                self.put(x, y, shade(i));
+                if (x, y) == (x1, y1) {
+                    break;
+                }
+                let doubled = 2 * error;
+                if doubled >= dy {
+                    error += dy;
+                    x += sx;
+                }
+                if doubled <= dx {
+                    error += dx;
+                    y += sy;
+                }
+            }
+        }
+        for (i, p) in points.iter().enumerate() {
+            let [x, y] = self.pixel(p);
+            for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx, dy))) {
+                self.put(x + dx, y + dy, shade(i));
+            }
+        }
+    }
+}
+
+/// Encode a whole RGB image, row-major, as a PNG
+pub fn write_canvas(out: &mut impl Write, width: usize, height: usize, canvas: &[u8]) -> io::Result<()> {
+    out.write_all(PNG_SIGNATURE)?;
+    write_chunk(out, b"IHDR", &image_header(width, height))?;
+    write_chunk(out, b"IDAT", &deflate_canvas(width, canvas)?)?;
+    write_chunk(out, b"IEND", &[])
+}
+
+/// An RGB image's scanlines, unfiltered and deflated, as IDAT carries them
+fn deflate_canvas(width: usize, canvas: &[u8]) -> io::Result<Vec<u8>> {
+    let mut pixels = ZlibEncoder::new(Vec::new(), Compression::default());
+    for scanline in canvas.chunks_exact(3 * width) {
+        pixels.write_all(&[0])?; // filter type: none
+        pixels.write_all(scanline)?;
+    }
+    pixels.finish()
+}
+
+/// Height of a tile's label strip: the digits plus a dot of space below
+fn label_height(sheet: &SheetOptions) -> usize {
+    6 * sheet.label_scale
+}
+
+/// 3 x 5 pixel digits, one row per entry, most significant bit on the left
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// Simulate `generations` steps from `ca` and write an animated PNG with one
+/// frame per generation, each showing the last `window` rows (dead rows
+/// above the start) and shown for `delay_ms`. Uses `cell_size`, not
+/// `downscale`. The window is cut to `apng_window`'s.
+pub fn write_apng(
+    out: &mut impl Write,
+    mut ca: Automaton,
+    generations: usize,
+    window: usize,
+    delay_ms: u16,
+    options: &RenderOptions,
+) -> io::Result<()> {
+    let (width, size) = (ca.width(), options.cell_size.max(1));
+    let window = apng_window(width, window, options);
+    let (image_width, image_height) = (width * size, window * size);
+    if image_width == 0 || image_height == 0 || image_width > u32::MAX as usize || image_height > u32::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("can't make a {image_width} x {image_height} image")));
+    }
+
+    out.write_all(PNG_SIGNATURE)?;
+    write_chunk(out, b"IHDR", &image_header(image_width, image_height))?;
+    let mut control = Vec::with_capacity(8);
+    control.extend_from_slice(&(generations as u32 + 1).to_be_bytes());
+    control.extend_from_slice(&0u32.to_be_bytes()); // loop forever
+    write_chunk(out, b"acTL", &control)?;
+
+    let mut rows: VecDeque<Vec<bool>> = std::iter::repeat_n(vec![false; width], window).collect();
+    let mut sequence = 0u32;
+    let mut scanline = Vec::with_capacity(1 + 3 * image_width);
+    for generation in 0..=generations {
+        if generation > 0 {
+            ca.step();
+        }
+        rows.pop_front();
+        rows.push_back(ca.deviations().collect());
+
+        let mut pixels = ZlibEncoder::new(Vec::new(), Compression::default());
+        for row in &rows {
+            scanline.clear();
+            scanline.push(0); // filter type: none
+            for &alive in row {
+                let rgb = if alive { options.live } else { options.dead };
+                (0..size).for_each(|_| scanline.extend_from_slice(&rgb));
+            }
+            (0..size).try_for_each(|_| pixels.write_all(&scanline))?;
+        }
+        write_apng_frame(out, &mut sequence, (image_width, image_height), &pixels.finish()?, delay_ms)?;
+    }
+    write_chunk(out, b"IEND", &[])
+}
+
+/// The rows of a `write_apng` window `width` cells wide that fit the memory
+/// budget, with the pixels of a frame: `window`, or fewer (at least one)
+/// under a tight budget
+pub fn apng_window(width: usize, window: usize, options: &RenderOptions) -> usize {
+    let size = options.cell_size.max(1);
+    let row_bytes = width + size * (1 + 3 * width * size);
+    crate::budget::shrink("animation window", window, row_bytes as u64, 1)
+}
+
+/// An animated PNG of whole RGB frames, each `width` x `height` pixels,
+/// row-major and shown for its own delay in milliseconds, looping forever
+pub fn write_apng_frames(out: &mut impl Write, width: usize, height: usize, frames: &[(&[u8], u16)]) -> io::Result<()> {
+    if width == 0 || height == 0 || width > u32::MAX as usize || height > u32::MAX as usize || frames.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("can't animate {width} x {height} images")));
+    }
+    if frames.iter().any(|(frame, _)| frame.len() != 3 * width * height) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "animation frames differ in size"));
+    }
+    out.write_all(PNG_SIGNATURE)?;
+    write_chunk(out, b"IHDR", &image_header(width, height))?;
+    let mut control = Vec::with_capacity(8);
+    control.extend_from_slice(&(frames.len() as u32).to_be_bytes());
+    control.extend_from_slice(&0u32.to_be_bytes()); // loop forever
+    write_chunk(out, b"acTL", &control)?;
+    let mut sequence = 0u32;
+    for &(frame, delay_ms) in frames {
+        write_apng_frame(out, &mut sequence, (width, height), &deflate_canvas(width, frame)?, delay_ms)?;
+    }
+    write_chunk(out, b"IEND", &[])
+}
+
+/// One full-image APNG frame of deflated scanlines, numbered from `sequence`
+fn write_apng_frame(
+    out: &mut impl Write,
+    sequence: &mut u32,
+    (width, height): (usize, usize),
+    pixels: &[u8],
+    delay_ms: u16,
+) -> io::Result<()> {
+    // Frame control: full-image frame, delay_ms / 1000 s, no disposal or blending
+    let first = *sequence == 0;
+    let mut frame = Vec::with_capacity(26);
+    frame.extend_from_slice(&sequence.to_be_bytes());
+    frame.extend_from_slice(&image_header(width, height)[..8]);
+    frame.extend_from_slice(&[0; 8]); // x and y offsets
+    frame.extend_from_slice(&delay_ms.to_be_bytes());
+    frame.extend_from_slice(&1000u16.to_be_bytes());
+    frame.extend_from_slice(&[0, 0]);
+    write_chunk(out, b"fcTL", &frame)?;
+    *sequence += 1;
+
+    // The first frame is the default image; later ones are numbered fdAT chunks
+    if first {
+        write_chunk(out, b"IDAT", pixels)
+    } else {
+        let mut data = Vec::with_capacity(4 + pixels.len());
+        data.extend_from_slice(&sequence.to_be_bytes());
+        data.extend_from_slice(pixels);
+        *sequence += 1;
+        write_chunk(out, b"fdAT", &data)
+    }
+}
+
+pub(crate) const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// IHDR data: dimensions, then 8-bit RGB, deflate, no filter, no interlace
+fn image_header(width: usize, height: usize) -> Vec<u8> {
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    header
+}
+
+/// Simulate `generations` steps from `ca` and write the diagram (initial
+/// row included) to `out` as an SVG document
+pub fn write_svg(out: &mut impl Write, mut ca: Automaton, generations: usize, options: &SvgOptions) -> io::Result<()> {
+    let SvgOptions { cell_size: size, margin, stroke, grid, .. } = *options;
+    let (width, rows) = (ca.width(), generations + 1);
+    let (diagram_width, diagram_height) = (width as f64 * size, rows as f64 * size);
+    let hex = |[r, g, b]: [u8; 3]| format!("#{r:02x}{g:02x}{b:02x}");
+
+    writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+        w = diagram_width + 2.0 * margin, h = diagram_height + 2.0 * margin)?;
+    writeln!(out, r#"<rect x="{margin}" y="{margin}" width="{diagram_width}" height="{diagram_height}" fill="{}"/>"#,
+        hex(options.dead))?;
+
+    // Outlined cells are drawn one by one so each keeps its own border
+    if stroke > 0.0 {
+        writeln!(out, r#"<g fill="{}" stroke="{}" stroke-width="{stroke}">"#, hex(options.live), hex(options.stroke_color))?;
+    } else {
+        writeln!(out, r#"<g fill="{}" shape-rendering="crispEdges">"#, hex(options.live))?;
+    }
+    for row in 0..rows {
+        if row > 0 {
+            ca.step();
+        }
+        let y = margin + row as f64 * size;
+        let mut i = 0;
+        while i < width {
+            if !ca.deviates(i) {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            i += 1;
+            while stroke == 0.0 && i < width && ca.deviates(i) {
+                i += 1;
+            }
+            writeln!(out, r#"<rect x="{}" y="{y}" width="{}" height="{size}"/>"#, margin + start as f64 * size,
+                (i - start) as f64 * size)?;
+        }
+    }
+    writeln!(out, "</g>")?;
+
+    if grid > 0.0 {
+        let mut path = String::new();
+        for i in 0..=width {
+            let x = margin + i as f64 * size;
+            path.push_str(&format!("M{x} {margin}V{}", margin + diagram_height));
+        }
+        for row in 0..=rows {
+            let y = margin + row as f64 * size;
+            path.push_str(&format!("M{margin} {y}H{}", margin + diagram_width));
+        }
+        writeln!(out, r#"<path d="{path}" stroke="{}" stroke-width="{grid}" fill="none"/>"#, hex(options.grid_color))?;
+    }
+    writeln!(out, "</svg>")
+}
+
+/// Length, type, data and CRC of the type and data
+pub(crate) fn write_chunk(out: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    let mut crc = crc32fast::Hasher::new();
+    crc.update(kind);
+    crc.update(data);
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    out.write_all(&crc.finalize().to_be_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    /// (width, height, scanlines without filter bytes) of a PNG from `write_png`
+    fn decode(png: &[u8]) -> (usize, usize, Vec<Vec<u8>>) {
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        let width = u32::from_be_bytes(png[16..20].try_into().unwrap()) as usize;
+        let height = u32::from_be_bytes(png[20..24].try_into().unwrap()) as usize;
+        let idat = 8 + 25;
+        let length = u32::from_be_bytes(png[idat..idat + 4].try_into().unwrap()) as usize;
+        assert_eq!(&png[idat + 4..idat + 8], b"IDAT");
+        let mut raw = Vec::new();
+        ZlibDecoder::new(&png[idat + 8..idat + 8 + length]).read_to_end(&mut raw).unwrap();
+        let rows = raw.chunks(1 + 3 * width).map(|line| line[1..].to_vec()).collect();
+        (width, height, rows)
+    }
+
+    #[test]
+    fn test_png_pixels_match_cells() {
+        let options = RenderOptions { cell_size: 2, ..Default::default() };
+        let mut png = Vec::new();
+        write_png(&mut png, Automaton::new(7, 90), 1, &options).unwrap();
+        let (width, height, rows) = decode(&png);
+        assert_eq!((width, height, rows.len()), (14, 4, 4));
+        // Row 1 of rule 90 is "  # #  ": pixel 4 (cell 2) is live, pixel 6 (cell 3) dead
+        assert_eq!(rows[2][3 * 4..3 * 4 + 3], [0, 0, 0]);
+        assert_eq!(rows[3][3 * 6..3 * 6 + 3], [255, 255, 255]);
+
+        // The same rows given up front make the same image
+        let rows = [Automaton::new(7, 90).cells(), (0..7).map(|i| i == 2 || i == 4).collect()];
+        let mut given = Vec::new();
+        write_png_rows(&mut given, &rows, &options).unwrap();
+        assert_eq!(given, png);
+
+        // 2 x 2 blocks under the identity rule 204: a lone live cell fills half
+        // its block over two rows; the other block stays dead
+        let half = RenderOptions { downscale: 2, ..Default::default() };
+        let mut png = Vec::new();
+        write_png(&mut png, Automaton::from_cells(vec![true, false, false, false], 204), 1, &half).unwrap();
+        let (width, height, rows) = decode(&png);
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(rows[0][..3], [128, 128, 128]);
+        assert_eq!(rows[0][3..], [255, 255, 255]);
+    }
+
+    #[test]
+    fn test_rgb_channels() {
+        // Rule 204 keeps its row, rule 0 clears it and rule 170 shifts it
+        // left: one seed, so the first row is white where live
+        let cells = vec![true, false, true];
+        let initial = [204, 0, 170].map(|rule| Automaton::from_cells(cells.clone(), rule));
+        let mut png = Vec::new();
+        write_png_rgb(&mut png, &initial, 1, Blend::Add, &RenderOptions::default()).unwrap();
+        let (_, _, rows) = decode(&png);
+        assert_eq!(rows[0], [255, 255, 255, 0, 0, 0, 255, 255, 255]);
+        assert_eq!(rows[1], [255, 0, 0, 0, 0, 255, 255, 0, 255]);
+
+        // Inks on white, with two diagrams: blue is never taken out
+        let mut png = Vec::new();
+        write_png_rgb(&mut png, &initial[..2], 1, Blend::Subtract, &RenderOptions::default()).unwrap();
+        assert_eq!(decode(&png).2[1], [0, 255, 255, 255, 255, 255, 0, 255, 255]);
+
+        assert!(write_png_rgb(&mut Vec::new(), &initial[..1], 1, Blend::Add, &RenderOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_tile_resampling() {
+        // Rule 170 shifts a lone cell around the 4-cell ring: period 4
+        let start = Automaton::from_cells(vec![true, false, false, false], 170);
+        let options = RenderOptions::default();
+
+        // Whole pixels per cell are the plain drawing at that size
+        let (mut tile, mut plain) = (Vec::new(), Vec::new());
+        write_png_tile(&mut tile, start.clone(), 4, Some((8, 8)), &options).unwrap();
+        write_png(&mut plain, start.clone(), 3, &RenderOptions { cell_size: 2, ..options }).unwrap();
+        assert_eq!(tile, plain);
+
+        // Halving: every 2 x 2 block of the diagonal holds one live cell
+        let mut tile = Vec::new();
+        write_png_tile(&mut tile, start.clone(), 4, Some((2, 2)), &options).unwrap();
+        assert!(decode(&tile).2.iter().flatten().all(|&v| v == 191));
+
+        // Three cells over two pixels: the middle one is split between them
+        assert_eq!(coverage(3, 2), [vec![(0, 2), (1, 1)], vec![(1, 1), (2, 2)]]);
+        assert!(write_png_tile(&mut Vec::new(), start, 0, None, &options).is_err());
+    }
+
+    #[test]
+    fn test_apng_frames() {
+        let mut png = Vec::new();
+        write_apng(&mut png, Automaton::new(8, 90), 3, 2, 40, &RenderOptions::default()).unwrap();
+
+        // Chunk types in order, skipping the signature
+        let parse = |png: &[u8]| {
+            let mut chunks = Vec::new();
+            let mut at = 8;
+            while at < png.len() {
+                let length = u32::from_be_bytes(png[at..at + 4].try_into().unwrap()) as usize;
+                let kind = String::from_utf8(png[at + 4..at + 8].to_vec()).unwrap();
+                chunks.push((kind, png[at + 8..at + 8 + length].to_vec()));
+                at += 12 + length;
+            }
+            chunks
+        };
+        let chunks = parse(&png);
+        let kinds: Vec<&str> = chunks.iter().map(|(kind, _)| kind.as_str()).collect();
+        assert_eq!(kinds, ["IHDR", "acTL", "fcTL", "IDAT", "fcTL", "fdAT", "fcTL", "fdAT", "fcTL", "fdAT", "IEND"]);
+        assert_eq!(chunks[1].1[..4], 4u32.to_be_bytes());
+
+        // Sequence numbers run 0, 1, 2, ... across fcTL and fdAT
+        let sequence: Vec<u32> = chunks
+            .iter()
+            .filter(|(kind, _)| kind == "fcTL" || kind == "fdAT")
+            .map(|(_, data)| u32::from_be_bytes(data[..4].try_into().unwrap()))
+            .collect();
+        assert_eq!(sequence, (0..7).collect::<Vec<_>>());
+
+        // Whole frames: a labeled diagram apiece, all one size
+        let (sheet, options) = (SheetOptions::default(), RenderOptions::default());
+        let frame = |rule: u8| sheet_frame(&Automaton::new(8, rule), &rule.to_string(), 3, &sheet, &options).unwrap();
+        let frames = [frame(30), frame(90)];
+        let (width, height) = sheet_size(1, 8, 4, &sheet, &options);
+        assert_eq!(frames[0].len(), 3 * width * height);
+        let mut png = Vec::new();
+        write_apng_frames(&mut png, width, height, &[(&frames[0], 100), (&frames[1], 300)]).unwrap();
+        let kinds: Vec<String> = parse(&png).into_iter().map(|(kind, _)| kind).collect();
+        assert_eq!(kinds, ["IHDR", "acTL", "fcTL", "IDAT", "fcTL", "fdAT", "IEND"]);
+        assert!(write_apng_frames(&mut Vec::new(), width, height, &[(&frames[0][3..], 100)]).is_err());
+    }
+
+    #[test]
+    fn test_netpbm_headers_and_bits() {
+        // Rule 90 from one cell in 10: rows "     #    " and "    # #   "
+        let mut pbm = Vec::new();
+        write_pbm(&mut pbm, Automaton::new(10, 90), 1, 1).unwrap();
+        assert_eq!(pbm, [b"P4\n10 2\n".as_slice(), &[0b00000100, 0, 0b00001010, 0]].concat());
+
+        let mut ppm = Vec::new();
+        let options = RenderOptions { cell_size: 2, ..Default::default() };
+        write_ppm(&mut ppm, Automaton::new(10, 90), 1, &options).unwrap();
+        let header = b"P6\n20 4\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(ppm.len(), header.len() + 3 * 20 * 4);
+        assert_eq!(ppm[header.len() + 3 * 10..][..3], [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_sheet_layout() {
+        let tiles: Vec<_> = [1, 90, 110].iter().map(|&rule| Automaton::new(5, rule)).collect();
+        let sheet = SheetOptions { columns: 2, gap: 1, label_scale: 1, background: [100, 100, 100] };
+        let mut png = Vec::new();
+        write_sheet(&mut png, &tiles, 3, &sheet, &RenderOptions::default()).unwrap();
+
+        // Two columns of 5-pixel tiles, two rows of a 6-pixel label over 4 generations
+        let (width, height, pixels) = decode(&png);
+        assert_eq!((width, height), sheet_size(3, 5, 4, &sheet, &RenderOptions::default()));
+        assert_eq!((width, height), (13, 23));
+        let at = |x: usize, y: usize| pixels[y][3 * x..3 * x + 3].to_vec();
+        // The label "1" starts with 0b010, then rule 1's first row has its live center cell
+        assert_eq!((at(1, 1), at(2, 1)), (vec![100; 3], vec![0; 3]));
+        assert_eq!((at(1, 7), at(3, 7)), (vec![255; 3], vec![0; 3]));
+        // The third tile starts the second row; its three-digit label is clipped to the tile
+        assert_eq!((at(2, 12), at(6, 12)), (vec![0; 3], vec![100; 3]));
+    }
+
+    #[test]
+    fn test_plot_fills_the_frame() {
+        let options = PlotOptions { size: 40, margin: 4, ..Default::default() };
+        let mut png = Vec::new();
+        write_plot(&mut png, &[[0.0, 0.0], [1.0, 1.0], [2.0, 0.0]], &options).unwrap();
+        let (width, height, rows) = decode(&png);
+        assert_eq!((width, height), (40, 40));
+        let at = |x: usize, y: usize| rows[y][3 * x..3 * x + 3].to_vec();
+        // First point bottom left in the start color, last bottom right in the end color
+        assert_eq!((at(6, 33), at(33, 33)), (options.start.to_vec(), options.end.to_vec()));
+        assert_eq!((at(4, 20), at(0, 0)), (options.frame.to_vec(), options.background.to_vec()));
+
+        // Curves share their axes: y runs 0..2 over both
+        let (red, blue) = ([255, 0, 0], [0, 0, 255]);
+        let mut png = Vec::new();
+        write_curves(&mut png, &[(&[[0.0, 0.0], [2.0, 0.0]], red), (&[[0.0, 1.0], [2.0, 2.0]], blue)], &options).unwrap();
+        let (_, _, rows) = decode(&png);
+        let at = |x: usize, y: usize| rows[y][3 * x..3 * x + 3].to_vec();
+        assert_eq!((at(6, 33), at(20, 33)), (red.to_vec(), red.to_vec()));
+        assert_eq!((at(6, 19), at(33, 6)), (blue.to_vec(), blue.to_vec()));
+    }
+
+    #[test]
+    fn test_svg_merges_live_runs() {
+        // Rule 254 from one cell: runs of 1, 3 and 5 live cells
+        let options = SvgOptions { cell_size: 2.0, margin: 1.0, ..Default::default() };
+        let mut svg = Vec::new();
+        write_svg(&mut svg, Automaton::new(9, 254), 2, &options).unwrap();
+        let svg = String::from_utf8(svg).unwrap();
+        assert!(svg.starts_with(r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="8""#));
+        assert!(svg.contains(r#"<rect x="7" y="3" width="6" height="2"/>"#));
+        assert_eq!(svg.matches("<rect").count(), 1 + 3);
+
+        // Outlined cells are drawn individually
+        let outlined = SvgOptions { stroke: 0.5, ..options };
+        let mut svg = Vec::new();
+        write_svg(&mut svg, Automaton::new(9, 254), 2, &outlined).unwrap();
+        assert_eq!(String::from_utf8(svg).unwrap().matches("<rect").count(), 1 + 1 + 3 + 5);
+    }
+}
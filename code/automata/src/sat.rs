@@ -0,0 +1,395 @@
+//! A small CDCL SAT solver, for the questions of `preimage` that the de
+//! Bruijn diagram answers slowly: orphans of rules whose node sets are too
+//! many to search breadth-first
+//!
+//! It is MiniSat's loop in miniature: two watched literals per clause, unit
+//! propagation off the trail, first-UIP clause learning with backjumping,
+//! variable activities bumped in conflicts and decayed geometrically, saved
+//! phases, and restarts on the Luby sequence. Learnt clauses are kept (there
+//! is no clause deletion), so a conflict budget bounds the memory too.
+
+use std::ops::Not;
+
+/// A literal: variable v as 2v (positive) or 2v + 1 (negated)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Lit(u32);
+
+impl Lit {
+    pub fn positive(var: usize) -> Self {
+        Lit(2 * var as u32)
+    }
+
+    /// `var`, negated when `value` is false: the literal true when var == value
+    pub fn new(var: usize, value: bool) -> Self {
+        Lit(2 * var as u32 + !value as u32)
+    }
+
+    pub fn var(self) -> usize {
+        (self.0 / 2) as usize
+    }
+
+    fn negated(self) -> bool {
+        self.0 & 1 == 1
+    }
+}
+
+impl Not for Lit {
+    type Output = Lit;
+
+    fn not(self) -> Lit {
+        Lit(self.0 ^ 1)
+    }
+}
+
+/// What a search found
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// A model: each variable's value
+    Sat(Vec<bool>),
+    Unsat,
+    /// The conflict budget ran out first
+    Unknown,
+}
+
+/// Conflicts between restarts, times the Luby sequence
+const RESTART_BASE: u64 = 100;
+
+/// Activity decay per conflict
+const DECAY: f64 = 0.95;
+
+#[derive(Default)]
+pub struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    /// Clauses watching each literal, visited when it becomes false
+    watches: Vec<Vec<usize>>,
+    /// Each variable's value, if assigned
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    /// The clause that forced each variable, `None` for decisions
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    /// Where each decision level starts on the trail
+    starts: Vec<usize>,
+    propagated: usize,
+    activity: Vec<f64>,
+    bump: f64,
+    phases: Vec<bool>,
+    /// Set once an empty clause is derived
+    broken: bool,
+    given: usize,
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Solver { bump: 1.0, ..Default::default() }
+    }
+
+    /// A fresh variable
+    pub fn var(&mut self) -> usize {
+        self.values.push(None);
+        self.levels.push(0);
+        self.reasons.push(None);
+        self.activity.push(0.0);
+        self.phases.push(false);
+        self.watches.extend([Vec::new(), Vec::new()]);
+        self.values.len() - 1
+    }
+
+    pub fn vars(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Clauses added, units and ones already true included
+    pub fn clauses(&self) -> usize {
+        self.given
+    }
+
+    fn value(&self, lit: Lit) -> Option<bool> {
+        self.values[lit.var()].map(|value| value != lit.negated())
+    }
+
+    /// Add a clause before solving; duplicate literals are dropped, and a
+    /// clause with a literal and its negation is always true
+    pub fn add_clause(&mut self, lits: &[Lit]) {
+        self.given += 1;
+        if self.broken {
+            return;
+        }
+        let mut clause: Vec<Lit> = Vec::with_capacity(lits.len());
+        for &lit in lits {
+            match self.value(lit) {
+                Some(true) => return,
+                Some(false) => {}
+                None if clause.contains(&!lit) => return,
+                None if !clause.contains(&lit) => clause.push(lit),
+                None => {}
+            }
+        }
+        match clause.len() {
+            0 => self.broken = true,
+            1 => {
+                self.assign(clause[0], None);
+                self.broken = self.propagate().is_some();
+            }
+            _ => {
+                self.watch(clause);
+            }
+        }
+    }
+
+    fn watch(&mut self, clause: Vec<Lit>) -> usize {
+        let index = self.clauses.len();
+        self.watches[clause[0].0 as usize].push(index);
+        self.watches[clause[1].0 as usize].push(index);
+        self.clauses.push(clause);
+        index
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var();
+        self.values[var] = Some(!lit.negated());
+        self.levels[var] = self.starts.len();
+        self.reasons[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Unit propagation from the trail; a clause left all false, if any
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let falsified = !self.trail[self.propagated];
+            self.propagated += 1;
+            let watching = std::mem::take(&mut self.watches[falsified.0 as usize]);
+            let mut kept = Vec::with_capacity(watching.len());
+            let mut conflict = None;
+            for (i, &index) in watching.iter().enumerate() {
+                if conflict.is_some() {
+                    kept.extend_from_slice(&watching[i..]);
+                    break;
+                }
+                let clause = &mut self.clauses[index];
+                if clause[0] == falsified {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                if self.values[first.var()].map(|value| value != first.negated()) == Some(true) {
+                    kept.push(index);
+                    continue;
+                }
+                let values = &self.values;
+                let free = (2..clause.len())
+                    .find(|&k| values[clause[k].var()].map(|value| value != clause[k].negated()) != Some(false));
+                match free {
+                    Some(k) => {
+                        clause.swap(1, k);
+                        let watched = clause[1].0 as usize;
+                        self.watches[watched].push(index);
+                    }
+                    None => {
+                        kept.push(index);
+                        match self.value(first) {
+                            Some(false) => conflict = Some(index),
+                            _ => self.assign(first, Some(index)),
+                        }
+                    }
+                }
+            }
+            self.watches[falsified.0 as usize] = kept;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    /// First-UIP learning: the learnt clause, its asserting literal first,
+    /// and the level to jump back to
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let level = self.starts.len();
+        let mut seen = vec![false; self.vars()];
+        let mut learnt = vec![Lit(0)];
+        let (mut open, mut index, mut clause) = (0usize, self.trail.len(), conflict);
+        // The conflict's literals all count; a reason's first is the one it forced
+        let mut skip = 0;
+        loop {
+            for k in skip..self.clauses[clause].len() {
+                let lit = self.clauses[clause][k];
+                let var = lit.var();
+                if seen[var] || self.levels[var] == 0 {
+                    continue;
+                }
+                seen[var] = true;
+                self.activity[var] += self.bump;
+                if self.activity[var] > 1e100 {
+                    self.activity.iter_mut().for_each(|a| *a *= 1e-100);
+                    self.bump *= 1e-100;
+                }
+                if self.levels[var] == level {
+                    open += 1;
+                } else {
+                    learnt.push(lit);
+                }
+            }
+            // The next marked literal back along the trail
+            loop {
+                index -= 1;
+                if seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            seen[lit.var()] = false;
+            open -= 1;
+            if open == 0 {
+                learnt[0] = !lit;
+                break;
+            }
+            clause = self.reasons[lit.var()].expect("only decisions lack reasons, and one is left at its level");
+            debug_assert_eq!(self.clauses[clause][0], lit);
+            skip = 1;
+        }
+        // Jump back to the second highest level in the clause, with it watched
+        let back = (1..learnt.len()).max_by_key(|&k| self.levels[learnt[k].var()]);
+        let jump = back.map_or(0, |k| {
+            learnt.swap(1, k);
+            self.levels[learnt[1].var()]
+        });
+        (learnt, jump)
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.starts.len() <= level {
+            return;
+        }
+        for lit in self.trail.drain(self.starts[level]..) {
+            self.phases[lit.var()] = !lit.negated();
+            self.values[lit.var()] = None;
+        }
+        self.starts.truncate(level);
+        self.propagated = self.trail.len();
+    }
+
+    /// The unassigned variable of highest activity
+    fn decide(&self) -> Option<usize> {
+        let free = (0..self.vars()).filter(|&var| self.values[var].is_none());
+        free.max_by(|&a, &b| self.activity[a].total_cmp(&self.activity[b]).then(b.cmp(&a)))
+    }
+
+    /// Search for a model, giving up after `budget` conflicts (`None`:
+    /// never). The solver is left at the root, so more clauses can be added
+    /// and it can be asked again
+    pub fn solve(&mut self, budget: Option<u64>) -> Outcome {
+        if self.broken {
+            return Outcome::Unsat;
+        }
+        let (mut conflicts, mut restart) = (0u64, 1u64);
+        let mut until_restart = RESTART_BASE;
+        let outcome = loop {
+            if let Some(conflict) = self.propagate() {
+                conflicts += 1;
+                if self.starts.is_empty() {
+                    self.broken = true;
+                    break Outcome::Unsat;
+                }
+                let (learnt, jump) = self.analyze(conflict);
+                self.backtrack(jump);
+                let asserting = learnt[0];
+                let reason = (learnt.len() > 1).then(|| self.watch(learnt));
+                self.assign(asserting, reason);
+                self.bump /= DECAY;
+                if budget.is_some_and(|budget| conflicts >= budget) {
+                    break Outcome::Unknown;
+                }
+                until_restart -= 1;
+                if until_restart == 0 {
+                    restart += 1;
+                    until_restart = RESTART_BASE * luby(restart);
+                    self.backtrack(0);
+                }
+                continue;
+            }
+            match self.decide() {
+                None => break Outcome::Sat(self.values.iter().map(|value| value == &Some(true)).collect()),
+                Some(var) => {
+                    self.starts.push(self.trail.len());
+                    self.assign(Lit::new(var, self.phases[var]), None);
+                }
+            }
+        };
+        self.backtrack(0);
+        outcome
+    }
+}
+
+/// The i-th term (from 1) of the Luby sequence: 1 1 2 1 1 2 4 1 1 2 ...
+fn luby(i: u64) -> u64 {
+    let mut k = 1;
+    while (1 << k) - 1 < i {
+        k += 1;
+    }
+    if (1 << k) - 1 == i {
+        1 << (k - 1)
+    } else {
+        luby(i - (1 << (k - 1)) + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solver_decides_small_formulas() {
+        assert_eq!((1..=10).map(luby).collect::<Vec<_>>(), [1, 1, 2, 1, 1, 2, 4, 1, 1, 2]);
+
+        // Pigeonhole: n + 1 pigeons in n holes is unsatisfiable, n in n isn't
+        let pigeons = |pigeons: usize, holes: usize| {
+            let mut solver = Solver::new();
+            let var: Vec<Vec<usize>> = (0..pigeons).map(|_| (0..holes).map(|_| solver.var()).collect()).collect();
+            for row in &var {
+                solver.add_clause(&row.iter().map(|&v| Lit::positive(v)).collect::<Vec<_>>());
+            }
+            for hole in 0..holes {
+                for a in 0..pigeons {
+                    for b in a + 1..pigeons {
+                        solver.add_clause(&[!Lit::positive(var[a][hole]), !Lit::positive(var[b][hole])]);
+                    }
+                }
+            }
+            solver.solve(None)
+        };
+        assert_eq!(pigeons(7, 6), Outcome::Unsat);
+        assert!(matches!(pigeons(6, 6), Outcome::Sat(_)));
+
+        // Random 3-SAT at the threshold, against every assignment tried
+        let mut state = 12345u64;
+        let mut next = || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (state >> 33) as usize
+        };
+        let satisfies = |clauses: &[Vec<Lit>], model: &dyn Fn(usize) -> bool| {
+            clauses.iter().all(|clause| clause.iter().any(|&lit| model(lit.var()) != lit.negated()))
+        };
+        let mut outcomes = [0; 2];
+        for _ in 0..30 {
+            let mut solver = Solver::new();
+            for _ in 0..12 {
+                solver.var();
+            }
+            let clauses: Vec<Vec<Lit>> =
+                (0..52).map(|_| (0..3).map(|_| Lit::new(next() % 12, next() % 2 == 0)).collect()).collect();
+            clauses.iter().for_each(|clause| solver.add_clause(clause));
+            let any = (0..1usize << 12).any(|bits| satisfies(&clauses, &|var| bits >> var & 1 == 1));
+            match solver.solve(None) {
+                Outcome::Sat(model) => assert!(satisfies(&clauses, &|var| model[var])),
+                outcome => assert!(outcome == Outcome::Unsat && !any),
+            }
+            outcomes[any as usize] += 1;
+        }
+        assert!(outcomes[0] > 0 && outcomes[1] > 0, "{outcomes:?}");
+        let mut solver = Solver::new();
+        let x = solver.var();
+        solver.add_clause(&[Lit::positive(x)]);
+        solver.add_clause(&[!Lit::positive(x)]);
+        assert_eq!(solver.solve(None), Outcome::Unsat);
+    }
+}
@@ -0,0 +1,256 @@
+//! Checks of the engines against facts known in closed form, for `selftest`
+//!
+//! Each check runs the code under test the way an analysis would and
+//! compares with what is known without running anything: Pascal's triangle
+//! mod 2 for rule 90 (Lucas' theorem), particle conservation for rule 184,
+//! the identity and the shift, rule 90's periods on odd rings and deaths on
+//! rings of 2^k cells (Martin, Odlyzko and Wolfram, 1984), exact inversion
+//! of Fredkin's second-order rules, the mirror and complement symmetries of
+//! lookup tables, a schedule's composite period and the Life glider. They
+//! cover the packed rows across word boundaries, the table, memory and
+//! schedule steppers, cycle detection and the 2D grid, in well under a
+//! second.
+
+use crate::evolve::TableRule;
+use crate::life::{Grid, LifeRule};
+use crate::{find_cycle_from, Automaton, MemoryRule, Schedule};
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// One fact checked, and how it went
+#[derive(Clone, Debug)]
+pub struct Check {
+    pub name: &'static str,
+    /// The fact, in words
+    pub claim: &'static str,
+    pub passed: bool,
+    /// What was checked, or the first counterexample
+    pub detail: String,
+}
+
+/// A check's body: what it covered, or its first counterexample
+type Outcome = Result<String, String>;
+
+/// A check's name, claim and body
+type Entry = (&'static str, &'static str, fn() -> Outcome);
+
+/// Every check, in order
+pub fn run_checks() -> Vec<Check> {
+    let checks: [Entry; 10] = [
+        ("pascal", "rule 90 from one cell is Pascal's triangle mod 2", pascal),
+        ("conservation", "rule 184 conserves the number of live cells", conservation),
+        ("identity", "rule 204 leaves every row unchanged", identity),
+        ("shift", "rule 170 shifts every row one cell left", shift),
+        ("odd-periods", "rule 90's period on odd n cells divides 2^sord_n(2) - 1, after 1 step at most", odd_periods),
+        ("powers-of-two", "rule 90 kills every row of 2^k cells within 2^(k-1) steps", powers_of_two),
+        ("second-order", "second-order (XOR memory) runs invert exactly", second_order),
+        ("symmetry", "a table's mirror and complement step mirrored and complemented rows", symmetry),
+        ("schedule", "alternating rules 51 and 204 has period 4, where 51 alone has 2", schedule),
+        ("glider", "a Life glider moves one cell diagonally every 4 generations", glider),
+    ];
+    checks
+        .into_iter()
+        .map(|(name, claim, check)| {
+            let (passed, detail) = match check() {
+                Ok(detail) => (true, detail),
+                Err(detail) => (false, detail),
+            };
+            Check { name, claim, passed, detail }
+        })
+        .collect()
+}
+
+/// Random rows of `width` cells under `rule`, one per seed
+fn random_rows(width: usize, rule: u8, seeds: std::ops::Range<u64>) -> impl Iterator<Item = Automaton> {
+    seeds.map(move |seed| Automaton::random(width, rule, 0.5, seed))
+}
+
+fn pascal() -> Outcome {
+    let t_max = 100;
+    let width = 2 * t_max + 3;
+    let center = width / 2;
+    let mut ca = Automaton::new(width, 90);
+    for t in 0..=t_max {
+        for i in 0..width {
+            // Cell center + k holds C(t, (t + k) / 2) mod 2, odd (Lucas)
+            // when the lower index's bits are all among t's
+            let k = i as isize - center as isize;
+            let m = (t as isize + k) / 2;
+            let expected = k.abs() <= t as isize && (t as isize + k) % 2 == 0 && (m as usize & !t) == 0;
+            if ca.get(i) != expected {
+                return Err(format!("row {t}, cell {k:+} from the center is {}", ca.get(i) as u8));
+            }
+        }
+        ca.step();
+    }
+    Ok(format!("{} rows of {width} cells", t_max + 1))
+}
+
+fn conservation() -> Outcome {
+    let widths = [7, 64, 65, 200];
+    for (width, mut ca) in widths.iter().flat_map(|&width| random_rows(width, 184, 0..4).map(move |ca| (width, ca))) {
+        let population = ca.population();
+        for t in 1..=100 {
+            ca.step();
+            if ca.population() != population {
+                return Err(format!("width {width}: {population} live cells became {} at step {t}", ca.population()));
+            }
+        }
+    }
+    Ok(format!("100 steps of 4 rows at each of widths {widths:?}"))
+}
+
+fn identity() -> Outcome {
+    for width in [1, 63, 64, 65, 130] {
+        for start in random_rows(width, 204, 0..4) {
+            let mut ca = start.clone();
+            ca.step();
+            if ca.cells() != start.cells() {
+                return Err(format!("a row of {width} cells changed"));
+            }
+        }
+    }
+    Ok("rows of 1 to 130 cells".to_string())
+}
+
+fn shift() -> Outcome {
+    for width in [2, 64, 65, 130] {
+        for start in random_rows(width, 170, 0..4) {
+            let mut ca = start.clone();
+            ca.step();
+            if let Some(i) = (0..width).find(|&i| ca.get(i) != start.get((i + 1) % width)) {
+                return Err(format!("width {width}: cell {i} isn't the old cell {}", (i + 1) % width));
+            }
+        }
+    }
+    Ok("rows of 2 to 130 cells, across word boundaries".to_string())
+}
+
+/// The least j with 2^j = ±1 mod n
+fn suborder(n: u64) -> u32 {
+    let mut power = 2 % n;
+    let mut j = 1;
+    while power != 1 && power != n - 1 {
+        power = power * 2 % n;
+        j += 1;
+    }
+    j
+}
+
+fn odd_periods() -> Outcome {
+    let widths: Vec<usize> = (3..=25).step_by(2).collect();
+    for &n in &widths {
+        let bound = (1u64 << suborder(n as u64)) - 1;
+        for start in random_rows(n, 90, 0..3) {
+            let cycle = find_cycle_from(start, 1 << 14);
+            let divides = cycle.period > 0 && bound.is_multiple_of(cycle.period as u64);
+            if !divides || cycle.transient > 1 {
+                let (transient, period) = (cycle.transient, cycle.period);
+                return Err(format!("width {n}: transient {transient}, period {period}, not a divisor of {bound}"));
+            }
+        }
+    }
+    Ok(format!("3 rows at each odd width from {} to {}", widths[0], widths[widths.len() - 1]))
+}
+
+fn powers_of_two() -> Outcome {
+    for k in 1..=7 {
+        let width = 1 << k;
+        for mut ca in random_rows(width, 90, 0..4) {
+            (0..1 << (k - 1)).for_each(|_| ca.step());
+            if ca.population() != 0 {
+                return Err(format!("width {width}: {} cells still live after {} steps", ca.population(), 1 << (k - 1)));
+            }
+        }
+    }
+    Ok("4 rows at each width from 2 to 128".to_string())
+}
+
+fn second_order() -> Outcome {
+    let steps = 200;
+    for rule in [30, 90, 110] {
+        // x(t + 1) = f(x(t)) XOR x(t - 1), so x(t - 1) = f(x(t)) XOR x(t + 1)
+        let mut ca = Automaton::random(101, rule, 0.5, rule as u64).with_memory(MemoryRule::Xor, 1);
+        let mut rows = vec![ca.words().to_vec()];
+        for _ in 0..steps {
+            ca.step();
+            rows.push(ca.words().to_vec());
+        }
+        let (mut next, mut current) = (rows[steps].clone(), rows[steps - 1].clone());
+        for t in (0..steps - 1).rev() {
+            let mut stepped = Automaton::from_words(current.clone(), 101, rule);
+            stepped.step();
+            let previous: Vec<u64> = stepped.words().iter().zip(&next).map(|(a, b)| a ^ b).collect();
+            if previous != rows[t] {
+                return Err(format!("rule {rule}: row {t} came back different"));
+            }
+            (next, current) = (current, previous);
+        }
+    }
+    Ok(format!("rules 30, 90 and 110 run {steps} steps forward and back"))
+}
+
+fn symmetry() -> Outcome {
+    let mut rng = ChaCha8Rng::seed_from_u64(5);
+    let width = 97;
+    for radius in [2, 3] {
+        for seed in 0..4 {
+            let table = TableRule::new(radius, rng.random::<u128>());
+            let start = Automaton::random(width, 0, 0.5, seed);
+            let reversed: Vec<bool> = start.cells().into_iter().rev().collect();
+            let inverted: Vec<bool> = start.cells().iter().map(|c| !c).collect();
+            let step = |cells: Vec<bool>, table: TableRule| {
+                let mut ca = Automaton::from_cells(cells, 0).with_table(table);
+                ca.step();
+                ca.cells()
+            };
+            let forward = step(start.cells(), table);
+            let mirrored: Vec<bool> = forward.iter().rev().copied().collect();
+            let complemented: Vec<bool> = forward.iter().map(|c| !c).collect();
+            if step(reversed, table.mirror()) != mirrored {
+                return Err(format!("{table}: its mirror doesn't step the mirrored row"));
+            }
+            if step(inverted, table.complement()) != complemented {
+                return Err(format!("{table}: its complement doesn't step the complemented row"));
+            }
+        }
+    }
+    Ok("4 random tables of radius 2 and of radius 3".to_string())
+}
+
+fn schedule() -> Outcome {
+    let start = Automaton::random(33, 51, 0.5, 9);
+    let composite = find_cycle_from(start.with_schedule(Schedule::new(vec![51, 204], 1)), 100);
+    let alone = find_cycle_from(start, 100);
+    match (composite.transient, composite.period, alone.period) {
+        (0, 4, 2) => Ok("a row of 33 cells".to_string()),
+        (transient, period, alone) => Err(format!("transient {transient}, period {period}; alone, period {alone}")),
+    }
+}
+
+fn glider() -> Outcome {
+    let glider = Grid::from_plaintext(".O.\n..O\nOOO", 12, 10, LifeRule::CONWAY)?;
+    let mut moved = glider.clone();
+    for generation in (4..=120).step_by(4) {
+        (0..4).for_each(|_| moved.step());
+        let shift = generation / 4;
+        let same = (0..12 * 10).all(|i| moved.get(i % 12 + shift, i / 12 + shift) == glider.get(i % 12, i / 12));
+        if !same || moved.population() != 5 {
+            return Err(format!("generation {generation}: not the glider moved {shift} cells"));
+        }
+    }
+    Ok("120 generations on a 12 x 10 torus".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_check_passes() {
+        let failed: Vec<String> =
+            run_checks().into_iter().filter(|c| !c.passed).map(|c| format!("{}: {}", c.name, c.detail)).collect();
+        assert!(failed.is_empty(), "{failed:?}");
+        assert_eq!([3, 5, 7, 9, 11, 13].map(suborder), [1, 2, 3, 3, 5, 6]);
+    }
+}
@@ -0,0 +1,191 @@
+//! Saved `run --live` sessions: the rule, the row on screen and the view,
+//! with bookmarks and notes, in a small TOML file
+//!
+//! ```toml
+//! rule = "110"
+//! start = ", init=random, density=0.5, seed=42"
+//! width = 79
+//! generation = 120
+//! row = "0110..."
+//! background = false
+//! offset = 0
+//! fps = 10.0
+//!
+//! [[bookmark]]
+//! generation = 40
+//! row = "0100..."
+//!
+//! [[note]]
+//! generation = 120
+//! text = "two gliders collide"
+//! ```
+//!
+//! Rows are the cells that differ from the background, leftmost first, as
+//! the screen shows them, so a session restores onto the same background
+//! it was saved over. Noise, memory and schedules are flags, not state: a
+//! restored run takes them from its command line again, and its past rows
+//! are gone, so the screen starts from the saved one.
+
+use crate::toml::{self, Table, Value};
+
+/// A generation to come back to, with its row
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bookmark {
+    pub generation: usize,
+    pub row: Vec<bool>,
+}
+
+/// Text attached to a generation
+#[derive(Clone, Debug, PartialEq)]
+pub struct Note {
+    pub generation: usize,
+    pub text: String,
+}
+
+/// Everything a live run needs to carry on where it was saved
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    /// The rule as the command line gives it: `110` or `table:...`
+    pub rule: String,
+    /// How the first row of the run was made, for its title
+    pub start: String,
+    pub generation: usize,
+    /// The current row's deviations from the background
+    pub row: Vec<bool>,
+    /// State of the quiescent background
+    pub background: bool,
+    /// First column on screen, when the ring is wider than the terminal
+    pub offset: usize,
+    pub fps: f64,
+    pub bookmarks: Vec<Bookmark>,
+    pub notes: Vec<Note>,
+}
+
+impl Session {
+    pub fn to_toml(&self) -> String {
+        let mut text = format!("rule = {}\nstart = {}\n", toml::quote(&self.rule), toml::quote(&self.start));
+        text += &format!("width = {}\ngeneration = {}\nrow = \"{}\"\n", self.row.len(), self.generation, bits(&self.row));
+        text += &format!("background = {}\noffset = {}\nfps = {:?}\n", self.background, self.offset, self.fps);
+        for bookmark in &self.bookmarks {
+            let (generation, row) = (bookmark.generation, bits(&bookmark.row));
+            text += &format!("\n[[bookmark]]\ngeneration = {generation}\nrow = \"{row}\"\n");
+        }
+        for note in &self.notes {
+            text += &format!("\n[[note]]\ngeneration = {}\ntext = {}\n", note.generation, toml::quote(&note.text));
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Session, String> {
+        let doc = toml::parse(text)?;
+        let width = count(&doc, "width")?;
+        let row = row(&doc, width)?;
+        let fps = match doc.get("fps") {
+            Some(Value::Float(fps)) => *fps,
+            Some(Value::Integer(fps)) => *fps as f64,
+            _ => return Err("fps must be a number".to_string()),
+        };
+        let background = match doc.get("background") {
+            Some(Value::Boolean(background)) => *background,
+            _ => return Err("background must be true or false".to_string()),
+        };
+        let bookmarks = tables(&doc, "bookmark")?
+            .iter()
+            .map(|table| Ok(Bookmark { generation: count(table, "generation")?, row: self::row(table, width)? }))
+            .collect::<Result<_, String>>()?;
+        let notes = tables(&doc, "note")?
+            .iter()
+            .map(|table| Ok(Note { generation: count(table, "generation")?, text: string(table, "text")? }))
+            .collect::<Result<_, String>>()?;
+        Ok(Session {
+            rule: string(&doc, "rule")?,
+            start: string(&doc, "start")?,
+            generation: count(&doc, "generation")?,
+            row,
+            background,
+            offset: count(&doc, "offset")?,
+            fps,
+            bookmarks,
+            notes,
+        })
+    }
+}
+
+fn bits(row: &[bool]) -> String {
+    row.iter().map(|&cell| if cell { '1' } else { '0' }).collect()
+}
+
+fn string(table: &Table, key: &str) -> Result<String, String> {
+    match table.get(key) {
+        Some(Value::String(s)) => Ok(s.clone()),
+        _ => Err(format!("{key} must be a string")),
+    }
+}
+
+fn count(table: &Table, key: &str) -> Result<usize, String> {
+    match table.get(key) {
+        Some(&Value::Integer(n)) if n >= 0 => Ok(n as usize),
+        _ => Err(format!("{key} must be a whole number")),
+    }
+}
+
+/// The table's `row`, `width` 0s and 1s
+fn row(table: &Table, width: usize) -> Result<Vec<bool>, String> {
+    let row = string(table, "row")?;
+    if row.len() != width {
+        return Err(format!("a row of {} cells in a session {width} wide", row.len()));
+    }
+    row.chars()
+        .map(|c| match c {
+            '0' => Ok(false),
+            '1' => Ok(true),
+            _ => Err(format!("{c:?} is not a bit in a row")),
+        })
+        .collect()
+}
+
+/// The tables of the `[[key]]` array, none if it is absent
+fn tables<'a>(doc: &'a Table, key: &str) -> Result<Vec<&'a Table>, String> {
+    match doc.get(key) {
+        None => Ok(Vec::new()),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::Table(table) => Ok(table),
+                other => Err(format!("{key} must hold tables, not a {}", other.type_name())),
+            })
+            .collect(),
+        Some(other) => Err(format!("{key} must be [[{key}]] tables, not a {}", other.type_name())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_round_trips() {
+        let session = Session {
+            rule: "table:r=2:0096e81f".to_string(),
+            start: ", init=random, density=0.5, seed=42".to_string(),
+            generation: 120,
+            row: vec![false, true, true, false, true],
+            background: true,
+            offset: 3,
+            fps: 10.0,
+            bookmarks: vec![Bookmark { generation: 40, row: vec![true, false, false, false, false] }],
+            notes: vec![
+                Note { generation: 120, text: "two \"gliders\" collide\\merge".to_string() },
+                Note { generation: 7, text: "ünïcode, tab\there".to_string() },
+            ],
+        };
+        let text = session.to_toml();
+        assert_eq!(Session::parse(&text), Ok(session.clone()));
+        let bare = Session { bookmarks: Vec::new(), notes: Vec::new(), ..session };
+        assert_eq!(Session::parse(&bare.to_toml()), Ok(bare));
+
+        let wider = text.replace("width = 5", "width = 6");
+        assert_eq!(Session::parse(&wider).unwrap_err(), "a row of 5 cells in a session 6 wide");
+        assert!(Session::parse(&text.replace("fps = 10.0", "fps = \"fast\"")).is_err());
+    }
+}
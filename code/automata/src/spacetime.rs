@@ -0,0 +1,223 @@
+//! Spacetime diagrams as files, and the differences between two of them
+//!
+//! NPY (NumPy's array format) is the exchange format for other engines: a
+//! 2-D array with one row per generation, written as `uint8` 0/1 and read
+//! from any boolean or integer dtype, nonzero meaning live. PBM (netpbm P4,
+//! or the P1 text variant) is read one pixel per cell, as `render -f pbm`
+//! writes it with the default cell size.
+//!
+//! A diff compares the rows two diagrams share: the Hamming distance of each
+//! row, and the first row where they part.
+
+use crate::Automaton;
+use std::io::{self, Write};
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Whether `bytes` look like an NPY file
+pub fn is_npy(bytes: &[u8]) -> bool {
+    bytes.starts_with(NPY_MAGIC)
+}
+
+/// Simulate `generations` steps from `ca` and write the diagram as a
+/// (generations + 1) x width `uint8` NPY array of cells relative to the
+/// background
+pub fn write_npy(out: &mut impl Write, mut ca: Automaton, generations: usize) -> io::Result<()> {
+    let shape = format!("({}, {})", generations + 1, ca.width());
+    let mut header = format!("{{'descr': '|u1', 'fortran_order': False, 'shape': {shape}, }}");
+    // Version 1.0: magic, version, 2-byte header length, header padded so the data starts 64-aligned
+    let unpadded = NPY_MAGIC.len() + 4 + header.len() + 1;
+    header.push_str(&" ".repeat(unpadded.next_multiple_of(64) - unpadded));
+    header.push('\n');
+    if header.len() > u16::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "NPY header too long"));
+    }
+    out.write_all(NPY_MAGIC)?;
+    out.write_all(&[1, 0])?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())?;
+    for row in 0..=generations {
+        if row > 0 {
+            ca.step();
+        }
+        out.write_all(&ca.deviations().map(u8::from).collect::<Vec<_>>())?;
+    }
+    Ok(())
+}
+
+/// The rows of a 2-D NPY array of booleans or integers, nonzero being live
+pub fn read_npy(bytes: &[u8]) -> Result<Vec<Vec<bool>>, String> {
+    if !is_npy(bytes) || bytes.len() < 10 {
+        return Err("not an NPY file".into());
+    }
+    let (header_len, start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        2 | 3 if bytes.len() >= 12 => (u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize, 12),
+        version => return Err(format!("unsupported NPY version {version}")),
+    };
+    let header = bytes.get(start..start + header_len).ok_or("truncated NPY header")?;
+    let header = std::str::from_utf8(header).map_err(|_| "NPY header is not text")?;
+    let data = &bytes[start + header_len..];
+
+    // The header is a Python dict literal: pick out the three keys
+    let value = |key: &str| -> Result<&str, String> {
+        let at = header.find(&format!("'{key}'")).ok_or(format!("NPY header has no '{key}'"))?;
+        Ok(header[at + key.len() + 2..].trim_start().trim_start_matches(':').trim_start())
+    };
+    let descr = value("descr")?.trim_start_matches('\'');
+    let descr = &descr[..descr.find('\'').ok_or("bad NPY descr")?];
+    let fortran = value("fortran_order")?.starts_with("True");
+    let shape = value("shape")?;
+    let shape = &shape[1..shape.find(')').ok_or("bad NPY shape")?];
+    let dims: Vec<usize> = shape
+        .split(',')
+        .map(str::trim)
+        .filter(|d| !d.is_empty())
+        .map(|d| d.parse().map_err(|_| "bad NPY shape"))
+        .collect::<Result<_, _>>()?;
+    let [rows, width] = dims[..] else {
+        return Err(format!("spacetime arrays are 2-D (rows, width), not shape ({shape})"));
+    };
+
+    // Bools and integers of any size and byte order: a cell is live if any byte is nonzero
+    let size: usize = match descr.as_bytes() {
+        [b'|' | b'<' | b'>' | b'=', b'b' | b'u' | b'i', size @ ..] => {
+            std::str::from_utf8(size).ok().and_then(|s| s.parse().ok()).ok_or(format!("bad NPY dtype '{descr}'"))?
+        }
+        _ => return Err(format!("NPY dtype '{descr}' is not boolean or integer")),
+    };
+    if data.len() < rows * width * size {
+        return Err(format!("NPY data holds {} bytes, not the {} of shape ({shape})", data.len(), rows * width * size));
+    }
+    let cell = |i: usize| data[i * size..(i + 1) * size].iter().any(|&b| b != 0);
+    Ok((0..rows)
+        .map(|t| (0..width).map(|x| cell(if fortran { x * rows + t } else { t * width + x })).collect())
+        .collect())
+}
+
+/// The rows of a PBM bitmap (P4 or P1), one pixel per cell, black live
+pub fn read_pbm(bytes: &[u8]) -> Result<Vec<Vec<bool>>, String> {
+    let binary = match bytes.get(..2) {
+        Some(b"P4") => true,
+        Some(b"P1") => false,
+        _ => return Err("not a PBM file".into()),
+    };
+    // Width and height, skipping whitespace and # comments; one whitespace byte ends a P4 header
+    let mut at = 2;
+    let mut number = || -> Result<usize, String> {
+        loop {
+            match bytes.get(at) {
+                Some(b'#') => at += bytes[at..].iter().position(|&b| b == b'\n').unwrap_or(bytes.len() - at),
+                Some(b) if b.is_ascii_whitespace() => at += 1,
+                _ => break,
+            }
+        }
+        let digits = bytes[at..].iter().take_while(|b| b.is_ascii_digit()).count();
+        let n = std::str::from_utf8(&bytes[at..at + digits]).unwrap().parse().map_err(|_| "bad PBM header")?;
+        at += digits;
+        Ok(n)
+    };
+    let (width, height) = (number()?, number()?);
+    if width == 0 || height == 0 {
+        return Err("empty PBM".into());
+    }
+
+    if binary {
+        let stride = width.div_ceil(8);
+        let data = bytes.get(at + 1..at + 1 + stride * height).ok_or("truncated PBM data")?;
+        Ok(data.chunks(stride).map(|row| (0..width).map(|x| row[x / 8] & (0x80 >> (x % 8)) != 0).collect()).collect())
+    } else {
+        let pixels: Vec<bool> = bytes[at..].iter().filter(|b| matches!(b, b'0' | b'1')).map(|&b| b == b'1').collect();
+        if pixels.len() < width * height {
+            return Err("truncated PBM data".into());
+        }
+        Ok(pixels.chunks(width).take(height).map(<[bool]>::to_vec).collect())
+    }
+}
+
+/// How two diagrams of the same width differ, over the rows they share
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpacetimeDiff {
+    pub width: usize,
+    /// Rows in each diagram; only the first `hamming.len()` are compared
+    pub rows: (usize, usize),
+    /// Differing cells in each shared row
+    pub hamming: Vec<usize>,
+}
+
+impl SpacetimeDiff {
+    /// The first shared row with a difference
+    pub fn first_divergence(&self) -> Option<usize> {
+        self.hamming.iter().position(|&d| d > 0)
+    }
+
+    /// Differing cells in all
+    pub fn total(&self) -> usize {
+        self.hamming.iter().sum()
+    }
+
+    /// Whether the diagrams are the same, lengths included
+    pub fn identical(&self) -> bool {
+        self.total() == 0 && self.rows.0 == self.rows.1
+    }
+}
+
+/// Row-by-row differences of `a` and `b`, which must have the same width
+pub fn diff_spacetimes(a: &[Vec<bool>], b: &[Vec<bool>]) -> Result<SpacetimeDiff, String> {
+    let width = a.first().map_or(0, Vec::len);
+    if a.iter().chain(b).any(|row| row.len() != width) {
+        return Err(format!("widths differ: the rows must all be {width} cells wide to line up"));
+    }
+    let hamming = a.iter().zip(b).map(|(x, y)| x.iter().zip(y).filter(|(p, q)| p != q).count()).collect();
+    Ok(SpacetimeDiff { width, rows: (a.len(), b.len()), hamming })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_npy_round_trip() {
+        let mut file = Vec::new();
+        write_npy(&mut file, Automaton::new(21, 30), 9).unwrap();
+        let header_len = u16::from_le_bytes([file[8], file[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        assert_eq!(file.len(), 10 + header_len + 10 * 21);
+
+        let rows = read_npy(&file).unwrap();
+        let mut ca = Automaton::new(21, 30);
+        for row in &rows {
+            assert_eq!(row, &ca.cells());
+            ca.step();
+        }
+
+        // Little-endian int32 in Fortran order, as NumPy would write a transposed array
+        let header = "{'descr': '<i4', 'fortran_order': True, 'shape': (2, 3), }\n";
+        let mut file = NPY_MAGIC.to_vec();
+        file.extend([1, 0]);
+        file.extend((header.len() as u16).to_le_bytes());
+        file.extend(header.as_bytes());
+        // Columns (1, 0), (0, 0), (0, 256)
+        [1, 0, 0, 0, 0, 256i32].iter().for_each(|x| file.extend(x.to_le_bytes()));
+        assert_eq!(read_npy(&file).unwrap(), vec![vec![true, false, false], vec![false, false, true]]);
+        assert!(read_npy(&file[..file.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_pbm_and_diff() {
+        let mut p4 = Vec::new();
+        crate::render::write_pbm(&mut p4, Automaton::new(11, 90), 4, 1).unwrap();
+        let a = read_pbm(&p4).unwrap();
+        let p1 = "P1\n# rule 90 with a cell flipped in row 2, and the damage spreading\n11 5\n00000100000\n00001010000\n\
+            00010101000\n00100000100\n01010001010\n";
+        let b = read_pbm(p1.as_bytes()).unwrap();
+        assert_eq!((a.len(), a[0].len()), (5, 11));
+
+        let diff = diff_spacetimes(&a, &b).unwrap();
+        assert_eq!(diff.hamming, [0, 0, 1, 2, 2]);
+        assert_eq!((diff.first_divergence(), diff.total(), diff.identical()), (Some(2), 5, false));
+        assert!(diff_spacetimes(&a, &a).unwrap().identical());
+        assert!(!diff_spacetimes(&a, &a[..3]).unwrap().identical());
+        assert!(diff_spacetimes(&a, &[vec![true; 3]]).is_err());
+    }
+}
@@ -0,0 +1,247 @@
+//! Stochastic mixtures of two rules
+//!
+//! Each step, every cell independently follows rule A with probability p
+//! and rule B otherwise, so p = 1 is A, p = 0 is B, and a sweep over p in
+//! between shows where one behavior gives way to the other: mixing identity
+//! (204) into rule 30, the activity falls from rule 30's one half at p = 0 to
+//! a frozen row at p = 1.
+//!
+//! The order parameters are time averages after a transient, over replicate
+//! runs that share the first row but draw their coin flips from separate
+//! ChaCha8 streams: density, normalized k-block entropy, and activity (the
+//! fraction of cells that change per step, 0 for a frozen row).
+//!
+//! A noisy run (`Automaton::with_noise`) has no cycle to find: a state it
+//! comes back to need not lead where it did before. What's left to count is
+//! recurrence: how many distinct states it visits, how soon it first meets
+//! one again, and how far back its returns reach.
+
+use crate::inference::mean_std;
+use crate::{apply_rule, ring_neighbors, row_key, tail_mask, Automaton};
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Step `ca` once, each cell following its own rule with probability `p`
+/// and rule `other` otherwise
+pub fn step_mixed(ca: &mut Automaton, other: u8, p: f64, rng: &mut ChaCha8Rng) {
+    let (left, right) = ring_neighbors(&ca.words, ca.width);
+    for w in 0..ca.words.len() {
+        let a = apply_rule(ca.rule, left[w], ca.words[w], right[w]);
+        let b = apply_rule(other, left[w], ca.words[w], right[w]);
+        let mask = match p {
+            _ if p >= 1.0 => !0,
+            _ if p <= 0.0 => 0,
+            _ => (0..64).fold(0u64, |mask, bit| mask | (rng.random_bool(p) as u64) << bit),
+        };
+        ca.words[w] = (a & mask) | (b & !mask);
+    }
+    if let Some(last) = ca.words.last_mut() {
+        *last &= tail_mask(ca.width);
+    }
+    ca.key = row_key(&ca.words);
+}
+
+/// Order parameters at one mixing probability: (mean, standard deviation)
+/// over replicates of each run's time average
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MixturePoint {
+    /// Probability of following rule A
+    pub p: f64,
+    /// Fraction of live cells
+    pub density: (f64, f64),
+    /// k-block entropy over its maximum, k bits
+    pub entropy: (f64, f64),
+    /// Fraction of cells changing per step
+    pub activity: (f64, f64),
+}
+
+/// How each point of a sweep is measured
+#[derive(Clone, Copy, Debug)]
+pub struct SweepSettings {
+    /// Unmeasured steps first, so the averages skip the transient
+    pub skip: usize,
+    /// Measured steps
+    pub generations: usize,
+    /// k for the k-block entropy
+    pub block_size: usize,
+    /// Runs per p, each with its own stream of coin flips
+    pub replicates: usize,
+    pub seed: u64,
+}
+
+impl Default for SweepSettings {
+    fn default() -> Self {
+        Self { skip: 100, generations: 200, block_size: 3, replicates: 4, seed: 1 }
+    }
+}
+
+/// Sweep the mixture of `initial`'s rule (A) with rule `other` (B) over
+/// `ps`, one ChaCha8 stream (of `seed`) per run
+pub fn sweep_mixture(initial: &Automaton, other: u8, ps: &[f64], settings: &SweepSettings) -> Vec<MixturePoint> {
+    let SweepSettings { skip, generations, block_size, replicates, seed } = *settings;
+    assert!(generations > 0 && replicates > 0, "need at least one measured generation and replicate");
+    let patterns = 2f64.powi(block_size as i32);
+    crate::diagnostics::check_sampling(&format!("{block_size}-block entropy"), initial.width(), patterns);
+    let runs: Vec<[f64; 3]> = (0..ps.len() * replicates)
+        .into_par_iter()
+        .map(|run| {
+            let p = ps[run / replicates];
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream(run as u64);
+            let mut ca = initial.clone();
+            for _ in 0..skip {
+                step_mixed(&mut ca, other, p, &mut rng);
+            }
+            let mut totals = [0.0; 3];
+            for _ in 0..generations {
+                let before = ca.words.clone();
+                step_mixed(&mut ca, other, p, &mut rng);
+                let changed: u32 = before.iter().zip(&ca.words).map(|(x, y)| (x ^ y).count_ones()).sum();
+                totals[0] += ca.density();
+                totals[1] += ca.block_entropy(block_size) / block_size as f64;
+                totals[2] += changed as f64 / ca.width as f64;
+            }
+            totals.map(|total| total / generations as f64)
+        })
+        .collect();
+
+    ps.iter()
+        .zip(runs.chunks(replicates))
+        .map(|(&p, runs)| {
+            let stat = |i: usize| mean_std(&runs.iter().map(|run| run[i]).collect::<Vec<_>>());
+            MixturePoint { p, density: stat(0), entropy: stat(1), activity: stat(2) }
+        })
+        .collect()
+}
+
+/// How a run revisits its states, exactly (not up to rotation)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Recurrence {
+    /// Steps run
+    pub steps: usize,
+    /// Distinct states met, the first row included
+    pub distinct: usize,
+    /// The first step to meet a state seen before, and how many steps back
+    /// it was seen: the transient plus period, and the period, without noise
+    pub first: Option<(usize, usize)>,
+    /// Steps that met a state seen before
+    pub returns: usize,
+    /// Mean steps back to the last visit, over the returns
+    pub mean_return: Option<f64>,
+    /// Visits to the state met most often
+    pub most_visits: usize,
+    /// Mean density over every row, the first included
+    pub mean_density: f64,
+}
+
+/// Step `ca` `steps` times, remembering every state it meets, or, if they
+/// don't fit the memory budget, every state's 64-bit key: a noisy run
+/// can't be stepped again to tell two states with one key apart, but
+/// among a few billion states they are unlikely to meet
+pub fn recurrences(ca: Automaton, steps: usize) -> Recurrence {
+    let words = ca.state().len() as u64;
+    let states = (steps as u64 + 1).saturating_mul(8 * words + crate::budget::STATE_OVERHEAD);
+    if crate::budget::fits(states) {
+        return count_recurrences(ca, steps, Automaton::state);
+    }
+    let needed = crate::budget::bytes(states);
+    crate::budget::degrade("recurrences", format!("64-bit keys only, unconfirmed (every state would need {needed})"));
+    count_recurrences(ca, steps, Automaton::state_key)
+}
+
+/// `recurrences`, telling states apart by `key`
+fn count_recurrences<K: Hash + Eq>(mut ca: Automaton, steps: usize, key: impl Fn(&Automaton) -> K) -> Recurrence {
+    // Each state's last visit and number of visits
+    let mut seen: HashMap<K, (usize, usize)> = HashMap::from([(key(&ca), (0, 1))]);
+    let (mut first, mut returns, mut gaps, mut density) = (None, 0, 0, ca.density());
+    for t in 1..=steps {
+        ca.step();
+        density += ca.density();
+        match seen.entry(key(&ca)) {
+            Entry::Occupied(mut entry) => {
+                let (last, visits) = entry.get_mut();
+                first.get_or_insert((t, t - *last));
+                (returns, gaps) = (returns + 1, gaps + t - *last);
+                (*last, *visits) = (t, *visits + 1);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((t, 1));
+            }
+        }
+    }
+    crate::profile::add_states(seen.len());
+    Recurrence {
+        steps,
+        distinct: seen.len(),
+        first,
+        returns,
+        mean_return: (returns > 0).then(|| gaps as f64 / returns as f64),
+        most_visits: seen.values().map(|&(_, visits)| visits).max().unwrap(),
+        mean_density: density / (steps + 1) as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pure_mixtures_are_the_rules() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let start = Automaton::random(130, 30, 0.5, 9);
+        for (p, rule) in [(1.0, 30), (0.0, 110)] {
+            let (mut mixed, mut pure) = (start.clone(), start.with_rule(rule));
+            for _ in 0..20 {
+                step_mixed(&mut mixed, 110, p, &mut rng);
+                pure.step();
+            }
+            assert_eq!(mixed.words(), pure.words(), "p = {p}");
+        }
+
+        // All-dead (rule 0) against all-live (rule 255): one step leaves 1 - p of the cells live
+        let mut ca = Automaton::new(10_000, 0);
+        step_mixed(&mut ca, 255, 0.3, &mut rng);
+        assert!((ca.density() - 0.7).abs() < 0.02, "density {}", ca.density());
+    }
+
+    #[test]
+    fn test_sweep_from_frozen_to_chaotic() {
+        let settings = SweepSettings { skip: 50, generations: 50, replicates: 2, ..SweepSettings::default() };
+        let points = sweep_mixture(&Automaton::random(200, 204, 0.5, 3), 30, &[0.0, 1.0], &settings);
+        let (chaotic, frozen) = (points[0], points[1]);
+        assert_eq!((frozen.p, frozen.activity), (1.0, (0.0, 0.0)));
+        assert!(chaotic.activity.0 > 0.3 && chaotic.entropy.0 > 0.9, "{chaotic:?}");
+
+        // Seeded: the same sweep again is identical
+        let start = Automaton::random(200, 204, 0.5, 3);
+        assert_eq!(sweep_mixture(&start, 30, &[0.5], &settings), sweep_mixture(&start, 30, &[0.5], &settings));
+    }
+
+    #[test]
+    fn test_noise_breaks_cycles_into_recurrences() {
+        // Without noise the first return is the cycle
+        let start = Automaton::random(12, 110, 0.5, 4);
+        let cycle = crate::find_cycle_from(start.clone(), 10_000);
+        let exact = recurrences(start.clone(), 200);
+        assert_eq!(exact.first, Some((cycle.transient + cycle.period, cycle.period)));
+        assert_eq!(exact.distinct, cycle.transient + cycle.period);
+
+        // Full noise complements the identity's row every step; some flips it
+        let mut flipped = Automaton::from_cells(vec![true, false, false], 204).with_noise(1.0, 1);
+        flipped.step();
+        assert_eq!(flipped.cells(), [false, true, true]);
+        let mut ca = Automaton::new(10_000, 0).with_noise(0.3, 2);
+        ca.step();
+        assert!((ca.density() - 0.3).abs() < 0.02, "density {}", ca.density());
+        assert_eq!((ca.noise(), Automaton::new(9, 0).with_noise(0.0, 2).noise()), (0.3, 0.0));
+
+        // The same seed repeats the run, and noise keeps it off the cycle
+        let noisy = |seed| recurrences(start.with_noise(0.01, seed), 2000);
+        assert_eq!(noisy(7), noisy(7));
+        assert!(noisy(7).distinct > exact.distinct);
+    }
+}
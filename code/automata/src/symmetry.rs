@@ -0,0 +1,138 @@
+//! The 88 classes of elementary rules under mirror and complement symmetry
+//!
+//! Reflecting a rule left to right (reading each neighborhood backwards) or
+//! conjugating it (swapping 0 and 1 in and out) gives a rule whose every
+//! run is the original's, reflected or with live and dead swapped, so its
+//! cycles, entropies and complexity are the same. The two symmetries
+//! commute and are involutions, so together they split the 256 rules into
+//! classes of 1, 2 or 4: 88 in all, each named by its smallest rule.
+//!
+//! `Rule` carries the same functions as methods; the surveys report every
+//! rule's canonical one beside it, so equivalent rules group by that
+//! column. `TableRule` and `WolframRule` have their own `mirror`,
+//! `complement` and `canonical` for the bigger spaces.
+
+use crate::evolve::TableRule;
+use std::fmt;
+
+/// An elementary rule by number, with its symmetries
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rule(pub u8);
+
+impl Rule {
+    pub fn mirror(self) -> Rule {
+        Rule(mirror(self.0))
+    }
+
+    pub fn complement(self) -> Rule {
+        Rule(complement(self.0))
+    }
+
+    /// The class's smallest rule
+    pub fn canonical(self) -> Rule {
+        Rule(representative(self.0))
+    }
+
+    pub fn is_canonical(self) -> bool {
+        self.canonical() == self
+    }
+
+    pub fn class(self) -> Class {
+        class(self.0)
+    }
+}
+
+impl From<u8> for Rule {
+    fn from(rule: u8) -> Self {
+        Rule(rule)
+    }
+}
+
+impl From<Rule> for u8 {
+    fn from(rule: Rule) -> Self {
+        rule.0
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// A rule, as its mirror, complement and both see it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Class {
+    /// The smallest rule in the class
+    pub representative: u8,
+    pub mirror: u8,
+    pub complement: u8,
+    /// The mirror's complement
+    pub both: u8,
+    /// The distinct rules, smallest first
+    pub members: Vec<u8>,
+}
+
+/// The left-right reflection: neighborhood (l, c, r) maps where (r, c, l) did
+pub fn mirror(rule: u8) -> u8 {
+    TableRule::new(1, rule as u128).mirror().table as u8
+}
+
+/// The 0/1 conjugate: neighborhood n maps to the opposite of !n's
+pub fn complement(rule: u8) -> u8 {
+    TableRule::new(1, rule as u128).complement().table as u8
+}
+
+/// The class of `rule`, with its images taken from the representative
+pub fn class(rule: u8) -> Class {
+    let representative = representative(rule);
+    let (mirror, complement) = (mirror(representative), complement(representative));
+    let both = self::complement(mirror);
+    let mut members = vec![representative, mirror, complement, both];
+    members.sort_unstable();
+    members.dedup();
+    Class { representative, mirror, complement, both, members }
+}
+
+/// The smallest rule equivalent to `rule`
+pub fn representative(rule: u8) -> u8 {
+    TableRule::new(1, rule as u128).canonical(true).table as u8
+}
+
+/// The representatives, smallest first: 88 of them
+pub fn representatives() -> Vec<u8> {
+    (0..=255u8).filter(|&rule| representative(rule) == rule).collect()
+}
+
+/// Every class, by representative
+pub fn classes() -> Vec<Class> {
+    representatives().into_iter().map(class).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_88_classes() {
+        let classes = classes();
+        assert_eq!(classes.len(), 88);
+        // The classes partition the rules
+        let mut members: Vec<u8> = classes.iter().flat_map(|c| c.members.clone()).collect();
+        members.sort_unstable();
+        assert_eq!(members, (0..=255).collect::<Vec<u8>>());
+        let sizes = |n| classes.iter().filter(|c| c.members.len() == n).count();
+        assert_eq!((sizes(1), sizes(2), sizes(4)), (8, 36, 44));
+
+        assert_eq!(class(86).members, [30, 86, 135, 149]);
+        assert_eq!((mirror(110), complement(110), class(110).both), (124, 137, 193));
+        assert_eq!(class(165).members, [90, 165]);
+        assert!((0..=255u8).all(|rule| mirror(mirror(rule)) == rule && complement(complement(rule)) == rule));
+        assert_eq!(representatives()[..6], [0, 1, 2, 3, 4, 5]);
+
+        let rule = Rule(110);
+        assert_eq!((rule.mirror(), rule.complement(), rule.mirror().complement()), (Rule(124), Rule(137), Rule(193)));
+        assert_eq!((Rule(149).canonical(), Rule(30).is_canonical(), Rule(86).is_canonical()), (Rule(30), true, false));
+        assert_eq!(Rule::from(90).class().members, [90, 165]);
+    }
+}
@@ -0,0 +1,452 @@
+//! A reader for the subset of TOML that experiment files use
+//!
+//! Key/value pairs, `[table]` and `[[array of tables]]` headers one level
+//! deep, and `#` comments. Values are basic ("...", with escapes) and literal
+//! ('...') strings, integers, floats, booleans, arrays (which may span
+//! lines) and inline tables. Dotted keys, multi-line strings and dates are
+//! not supported and are reported as errors, with the line number.
+//!
+//! `quote` writes a string back in the same syntax.
+//!
+//! Tables keep their keys in file order, so whatever is built from them
+//! (command lines, say) comes out in the order it was written.
+
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Integer(_) => "integer",
+            Value::Float(_) => "float",
+            Value::Boolean(_) => "boolean",
+            Value::Array(_) => "array",
+            Value::Table(_) => "table",
+        }
+    }
+}
+
+/// Values that stand for themselves on a command line: strings bare, the
+/// rest as TOML writes them, arrays comma-separated
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::String(s) => write!(f, "{s}"),
+            Value::Integer(n) => write!(f, "{n}"),
+            Value::Float(x) => write!(f, "{x}"),
+            Value::Boolean(b) => write!(f, "{b}"),
+            Value::Array(items) => {
+                let items: Vec<String> = items.iter().map(Value::to_string).collect();
+                write!(f, "{}", items.join(","))
+            }
+            Value::Table(table) => {
+                let pairs: Vec<String> = table.0.iter().map(|(k, v)| format!("{k}={v}")).collect();
+                write!(f, "{{{}}}", pairs.join(","))
+            }
+        }
+    }
+}
+
+/// Keys and values in file order; keys are unique
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Table(pub Vec<(String, Value)>);
+
+impl Table {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    fn insert(&mut self, key: String, value: Value) -> Result<(), String> {
+        if self.get(&key).is_some() {
+            return Err(format!("duplicate key {key:?}"));
+        }
+        self.0.push((key, value));
+        Ok(())
+    }
+}
+
+/// Parse a document into its root table
+pub fn parse(text: &str) -> Result<Table, String> {
+    let mut parser = Parser { chars: text.chars().collect(), at: 0, line: 1 };
+    parser.document().map_err(|e| format!("line {}: {e}", parser.line))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    at: usize,
+    line: usize,
+}
+
+/// Where the key/value pairs after a header go
+enum Target {
+    Root,
+    Table(String),
+    /// The last table of an array of tables
+    Array(String),
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.at).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.at += 1;
+        self.line += (c == '\n') as usize;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) { Ok(()) } else { Err(format!("expected {c:?}, found {}", self.found())) }
+    }
+
+    fn found(&self) -> String {
+        self.peek().map_or("the end of the file".to_string(), |c| format!("{c:?}"))
+    }
+
+    /// Spaces and tabs, and a comment running to the end of the line
+    fn blank(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.bump();
+        }
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.bump();
+            }
+        }
+    }
+
+    /// Blanks, comments and newlines, as between array items
+    fn space(&mut self) {
+        loop {
+            self.blank();
+            if !(self.eat('\n') || self.eat('\r')) {
+                break;
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.blank();
+        self.eat('\r');
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            }
+            _ => Err(format!("expected the end of the line, found {}", self.found())),
+        }
+    }
+
+    fn document(&mut self) -> Result<Table, String> {
+        let mut root = Table::default();
+        let mut target = Target::Root;
+        loop {
+            self.space();
+            let Some(c) = self.peek() else {
+                return Ok(root);
+            };
+            if c == '[' {
+                self.bump();
+                let array = self.eat('[');
+                self.blank();
+                let name = self.key()?;
+                self.blank();
+                if self.peek() == Some('.') {
+                    return Err("nested tables ([a.b]) are not supported".into());
+                }
+                self.expect(']')?;
+                if array {
+                    self.expect(']')?;
+                }
+                target = if array {
+                    match root.0.iter_mut().find(|(k, _)| *k == name) {
+                        Some((_, Value::Array(tables))) if tables.iter().all(|t| matches!(t, Value::Table(_))) => {
+                            tables.push(Value::Table(Table::default()))
+                        }
+                        Some(_) => return Err(format!("{name:?} is already defined, not as an array of tables")),
+                        None => root.insert(name.clone(), Value::Array(vec![Value::Table(Table::default())]))?,
+                    }
+                    Target::Array(name)
+                } else {
+                    root.insert(name.clone(), Value::Table(Table::default()))?;
+                    Target::Table(name)
+                };
+                self.end_of_line()?;
+                continue;
+            }
+
+            let key = self.key()?;
+            self.blank();
+            if self.peek() == Some('.') {
+                return Err("dotted keys are not supported".into());
+            }
+            self.expect('=')?;
+            self.blank();
+            let value = self.value()?;
+            let table = match &target {
+                Target::Root => &mut root,
+                Target::Table(name) | Target::Array(name) => match root.0.iter_mut().find(|(k, _)| k == name) {
+                    Some((_, Value::Table(table))) => table,
+                    Some((_, Value::Array(tables))) => match tables.last_mut() {
+                        Some(Value::Table(table)) => table,
+                        _ => unreachable!("arrays of tables hold tables"),
+                    },
+                    _ => unreachable!("headers define their table"),
+                },
+            };
+            // Before the newline, so errors name the key's line
+            table.insert(key, value)?;
+            self.end_of_line()?;
+        }
+    }
+
+    fn key(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let start = self.at;
+                while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                    self.bump();
+                }
+                if self.at == start {
+                    return Err(format!("expected a key, found {}", self.found()));
+                }
+                Ok(self.chars[start..self.at].iter().collect())
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => {
+                self.bump();
+                let mut items = Vec::new();
+                loop {
+                    self.space();
+                    if self.eat(']') {
+                        return Ok(Value::Array(items));
+                    }
+                    items.push(self.value()?);
+                    self.space();
+                    if !self.eat(',') {
+                        self.space();
+                        self.expect(']')?;
+                        return Ok(Value::Array(items));
+                    }
+                }
+            }
+            Some('{') => {
+                self.bump();
+                let mut table = Table::default();
+                self.blank();
+                if self.eat('}') {
+                    return Ok(Value::Table(table));
+                }
+                loop {
+                    self.blank();
+                    let key = self.key()?;
+                    self.blank();
+                    self.expect('=')?;
+                    self.blank();
+                    let value = self.value()?;
+                    table.insert(key, value)?;
+                    self.blank();
+                    if self.eat('}') {
+                        return Ok(Value::Table(table));
+                    }
+                    self.expect(',')?;
+                }
+            }
+            _ => {
+                let start = self.at;
+                while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.' | '_')) {
+                    self.bump();
+                }
+                let word: String = self.chars[start..self.at].iter().collect();
+                scalar(&word).ok_or_else(|| match word.as_str() {
+                    "" => format!("expected a value, found {}", self.found()),
+                    _ => format!("{word:?} is not a string, number or boolean (strings need quotes)"),
+                })
+            }
+        }
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let c = match self.peek() {
+                None | Some('\n') => return Err("unterminated string".into()),
+                Some(c) => c,
+            };
+            self.bump();
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let escaped = match self.bump() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some(u @ ('u' | 'U')) => {
+                            let len = if u == 'u' { 4 } else { 8 };
+                            let digits: String = (0..len).filter_map(|_| self.bump()).collect();
+                            u32::from_str_radix(&digits, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or(format!("bad unicode escape \\{u}{digits}"))?
+                        }
+                        other => return Err(format!("bad escape \\{}", other.unwrap_or(' '))),
+                    };
+                    s.push(escaped);
+                }
+                c => s.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        self.expect('\'')?;
+        let mut s = String::new();
+        loop {
+            match self.peek() {
+                None | Some('\n') => return Err("unterminated string".into()),
+                Some('\'') => {
+                    self.bump();
+                    return Ok(s);
+                }
+                Some(c) => {
+                    self.bump();
+                    s.push(c);
+                }
+            }
+        }
+    }
+}
+
+/// A boolean, integer or float written bare
+fn scalar(word: &str) -> Option<Value> {
+    match word {
+        "true" => return Some(Value::Boolean(true)),
+        "false" => return Some(Value::Boolean(false)),
+        _ => {}
+    }
+    if !word.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-') {
+        return None;
+    }
+    let digits = word.replace('_', "");
+    let integer = match digits.trim_start_matches(['+', '-']).get(..2) {
+        Some("0x") => i64::from_str_radix(&digits.replacen("0x", "", 1), 16).ok(),
+        Some("0b") => i64::from_str_radix(&digits.replacen("0b", "", 1), 2).ok(),
+        _ => digits.parse().ok(),
+    };
+    integer.map(Value::Integer).or_else(|| digits.parse().ok().filter(|x: &f64| x.is_finite()).map(Value::Float))
+}
+
+/// `s` as a basic string, quoted and escaped for `parse` to read back
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_experiment() {
+        let text = r#"
+# Rule 30 and friends
+rules = [30, 110,
+    "k=2,r=1,code=10",  # trailing comma next
+]
+width = 1_001
+density = 0.25
+quiet = false
+name = 'C:\runs'
+
+[init]
+kind = "random"
+seed = -7
+
+[[analysis]]
+command = "cycle"
+
+[[analysis]]
+command = "render"
+out = "rule{rule}.png"
+colors = { live = "ff0000", dead = "ffffff" }
+"#;
+        let doc = parse(text).unwrap();
+        let code = Value::String("k=2,r=1,code=10".into());
+        let rules = Value::Array(vec![Value::Integer(30), Value::Integer(110), code]);
+        assert_eq!(doc.get("rules"), Some(&rules));
+        assert_eq!(doc.get("width"), Some(&Value::Integer(1001)));
+        assert_eq!(doc.get("density"), Some(&Value::Float(0.25)));
+        assert_eq!(doc.get("quiet"), Some(&Value::Boolean(false)));
+        assert_eq!(doc.get("name"), Some(&Value::String("C:\\runs".into())));
+        let Some(Value::Table(init)) = doc.get("init") else { panic!("no [init]") };
+        assert_eq!(init.get("seed"), Some(&Value::Integer(-7)));
+        let Some(Value::Array(analyses)) = doc.get("analysis") else { panic!("no [[analysis]]") };
+        assert_eq!(analyses.len(), 2);
+        let Value::Table(render) = &analyses[1] else { panic!() };
+        let keys: Vec<&str> = render.0.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keys, ["command", "out", "colors"]);
+        assert_eq!(render.get("colors").unwrap().to_string(), "{live=ff0000,dead=ffffff}");
+        assert_eq!(rules.to_string(), "30,110,k=2,r=1,code=10");
+    }
+
+    #[test]
+    fn test_errors_name_the_line() {
+        for (text, line, message) in [
+            ("a = 1\na = 2\n", 2, "duplicate key"),
+            ("a = 1\nb = two\n", 2, "strings need quotes"),
+            ("\n\n[x.y]\n", 3, "nested tables"),
+            ("a.b = 1\n", 1, "dotted keys"),
+            ("s = \"open\n", 1, "unterminated"),
+            ("a = [1, 2\n", 2, "expected ']'"),
+            ("a = 1 2\n", 1, "end of the line"),
+            ("t = 1\n[[t]]\n", 2, "not as an array of tables"),
+        ] {
+            let error = parse(text).unwrap_err();
+            assert!(error.starts_with(&format!("line {line}:")) && error.contains(message), "{text:?}: {error}");
+        }
+    }
+}
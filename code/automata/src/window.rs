@@ -0,0 +1,131 @@
+//! Which stretch of a run a thumbnail shows, when it can't show all of it
+//!
+//! The first rows of a diagram are mostly the initial condition: a random
+//! row dissolving, or a single cell that hasn't spread yet. A window can
+//! instead start where the run settled (its transient over), be the one
+//! most typical of the run (median entropy), or straddle an event: the
+//! generation where the rows' entropy shifts most, such as a collapse to
+//! uniform or a pattern taking over.
+//!
+//! Entropy here is of adjacent pairs of colors around the ring, as a
+//! fraction of its most (2 log2 k), which sees stripes and textures that
+//! single-cell frequencies miss.
+
+use std::fmt;
+
+/// How a window is chosen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Pick {
+    /// Around the event if there is one, else where the run settled
+    #[default]
+    Auto,
+    /// The first rows, initial row included
+    Start,
+    /// From the end of the transient, or the last rows if no cycle was seen
+    Settled,
+    /// The window whose mean entropy is the median of all windows'
+    Median,
+    /// Centered on the event, or the first rows if there is none
+    Event,
+}
+
+impl fmt::Display for Pick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Pick::Auto => "auto",
+            Pick::Start => "start",
+            Pick::Settled => "settled",
+            Pick::Median => "median",
+            Pick::Event => "event",
+        };
+        f.pad(name)
+    }
+}
+
+/// Smallest shift in mean entropy, either side of a generation, that counts
+/// as an event
+pub const EVENT_SHIFT: f64 = 0.1;
+
+/// Entropy of a row's adjacent color pairs (cyclically), in [0, 1]
+pub fn pair_entropy(row: &[u8], colors: u8) -> f64 {
+    let k = colors as usize;
+    if k < 2 || row.is_empty() {
+        return 0.0;
+    }
+    let mut counts = vec![0; k * k];
+    for (i, &c) in row.iter().enumerate() {
+        counts[c as usize * k + row[(i + 1) % row.len()] as usize] += 1;
+    }
+    crate::shannon_entropy(&counts) / (2.0 * (k as f64).log2())
+}
+
+/// The generation where mean entropy changes most between the `span` rows
+/// before it and the `span` rows from it on, if by `EVENT_SHIFT` or more
+pub fn find_event(entropies: &[f64], span: usize) -> Option<usize> {
+    let span = span.max(1);
+    let prefix: Vec<f64> = std::iter::once(0.0)
+        .chain(entropies.iter().scan(0.0, |sum, e| {
+            *sum += e;
+            Some(*sum)
+        }))
+        .collect();
+    let mean = |a: usize, b: usize| (prefix[b] - prefix[a]) / (b - a) as f64;
+    (span..=entropies.len().saturating_sub(span))
+        .map(|t| (t, (mean(t, t + span) - mean(t - span, t)).abs()))
+        .filter(|&(_, shift)| shift >= EVENT_SHIFT)
+        .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+        .map(|(t, _)| t)
+}
+
+/// The first row of the `height`-row window of `rows` that `pick` chooses,
+/// given the run's transient (when a row was first seen again) if known
+pub fn pick_window(rows: &[Vec<u8>], colors: u8, transient: Option<usize>, height: usize, pick: Pick) -> usize {
+    let last = rows.len().saturating_sub(height);
+    if last == 0 {
+        return 0;
+    }
+    let entropies: Vec<f64> = rows.iter().map(|row| pair_entropy(row, colors)).collect();
+    let settled = transient.unwrap_or(last);
+    let around = |t: usize| t.saturating_sub(height / 2);
+    let start = match pick {
+        Pick::Start => 0,
+        Pick::Settled => settled,
+        Pick::Event => find_event(&entropies, height / 2).map_or(0, around),
+        Pick::Auto => find_event(&entropies, height / 2).map_or(settled, around),
+        Pick::Median => {
+            let window_mean = |s: usize| entropies[s..s + height].iter().sum::<f64>() / height as f64;
+            let mut means: Vec<(f64, usize)> = (0..=last).map(|s| (window_mean(s), s)).collect();
+            means.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+            means[last / 2].1
+        }
+    };
+    start.min(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_find_the_event() {
+        assert_eq!(pair_entropy(&[1, 1, 1, 1], 2), 0.0);
+        assert_eq!(pair_entropy(&[0, 0, 1, 1], 2), 1.0);
+
+        // Twenty noisy rows, then a collapse to uniform
+        let noise = crate::wolfram::random_row(2, 64, 1);
+        let rows: Vec<Vec<u8>> = (0..60).map(|t| if t < 20 { noise.clone() } else { vec![0; 64] }).collect();
+        let entropies: Vec<f64> = rows.iter().map(|row| pair_entropy(row, 2)).collect();
+        assert_eq!(find_event(&entropies, 5), Some(20));
+        assert_eq!(find_event(&[0.5; 30], 5), None);
+
+        assert_eq!(pick_window(&rows, 2, Some(21), 10, Pick::Event), 15);
+        assert_eq!(pick_window(&rows, 2, Some(21), 10, Pick::Auto), 15);
+        assert_eq!(pick_window(&rows, 2, Some(21), 10, Pick::Settled), 21);
+        assert_eq!(pick_window(&rows, 2, None, 10, Pick::Settled), 50);
+        assert_eq!(pick_window(&rows, 2, None, 10, Pick::Start), 0);
+        // Most windows are all uniform, so the median one is too
+        assert!(pick_window(&rows, 2, None, 10, Pick::Median) >= 20);
+        // A window as tall as the run is the run
+        assert_eq!(pick_window(&rows, 2, None, 60, Pick::Settled), 0);
+    }
+}
@@ -0,0 +1,479 @@
+//! Rules given as Wolfram codes: k colors, radius r, and a number
+//!
+//! A general rule's number, written in base k, lists the next state of every
+//! (2r + 1)-cell neighborhood: digit v (least significant first) is the next
+//! state of the neighborhood that reads as v in base k, leftmost cell most
+//! significant. For k = 2, r = 1 that is the elementary rule number. A
+//! totalistic code does the same for the sum of the neighborhood, 0 to
+//! (k - 1)(2r + 1), so k = 3, r = 1 codes run 0 to 3^7 - 1 = 2186 (NKS's
+//! code 1635, code 777, ...). An outer-totalistic code lists the next state
+//! for every pair of the center's color and its 2r neighbors' sum, digit
+//! k * sum + center, as Life does in two dimensions: two-color ones can be
+//! written the same way, as births and survivals (B1/S12 is born with one
+//! neighbor of the two, and survives with one or two).
+//!
+//! Codes are parsed digit by digit, so numbers too big for a machine word
+//! (a k = 2, r = 3 rule has 128 bits) are fine. Two-color rules up to radius
+//! 3 convert to a `TableRule`, and r = 1 ones to an elementary rule number.
+
+use crate::evolve::{TableRule, MAX_RADIUS};
+use rand::{RngExt, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::fmt;
+use std::str::FromStr;
+
+/// Most colors a rule may have (one digit each)
+pub const MAX_COLORS: u8 = 10;
+
+/// Most neighborhoods a general rule's table may list
+const MAX_ENTRIES: usize = 1 << 16;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WolframRule {
+    pub colors: u8,
+    pub radius: usize,
+    /// What the table is indexed by: see `Family`
+    pub family: Family,
+    /// Next state for each neighborhood value, sum, or center and sum
+    pub table: Vec<u8>,
+}
+
+impl WolframRule {
+    /// The general rule numbered `code` (decimal digits)
+    pub fn general(colors: u8, radius: usize, code: &str) -> Result<Self, String> {
+        Self::check(colors, radius)?;
+        let entries = (colors as usize)
+            .checked_pow(2 * radius as u32 + 1)
+            .filter(|&n| n <= MAX_ENTRIES)
+            .ok_or(format!("k={colors},r={radius} has too many neighborhoods for a general rule; try a totalistic code"))?;
+        let table = base_digits(code, colors, entries)?;
+        Ok(Self { colors, radius, family: Family::General, table })
+    }
+
+    /// The totalistic rule with code `code` (decimal digits)
+    pub fn totalistic(colors: u8, radius: usize, code: &str) -> Result<Self, String> {
+        Self::check(colors, radius)?;
+        let table = base_digits(code, colors, (colors as usize - 1) * (2 * radius + 1) + 1)?;
+        Ok(Self { colors, radius, family: Family::Totalistic, table })
+    }
+
+    /// The outer-totalistic rule with code `code` (decimal digits)
+    pub fn outer_totalistic(colors: u8, radius: usize, code: &str) -> Result<Self, String> {
+        Self::check(colors, radius)?;
+        let table = base_digits(code, colors, colors as usize * ((colors as usize - 1) * 2 * radius + 1))?;
+        Ok(Self { colors, radius, family: Family::OuterTotalistic, table })
+    }
+
+    /// The two-color outer-totalistic rule born with a neighbor sum in
+    /// `births` and surviving with one in `survivals`
+    pub fn life(radius: usize, births: &[usize], survivals: &[usize]) -> Result<Self, String> {
+        let mut rule = Self::outer_totalistic(2, radius, "0")?;
+        for (center, sums) in [births, survivals].into_iter().enumerate() {
+            for &sum in sums {
+                if sum > 2 * radius {
+                    return Err(format!("{sum} is more neighbors than radius {radius} has ({})", 2 * radius));
+                }
+                rule.table[2 * sum + center] = 1;
+            }
+        }
+        Ok(rule)
+    }
+
+    /// Births and survivals (B1/S12), for a two-color outer-totalistic rule
+    pub fn life_notation(&self) -> Option<String> {
+        if self.family != Family::OuterTotalistic || self.colors != 2 {
+            return None;
+        }
+        let sums = |center: usize| -> String {
+            (0..=2 * self.radius).filter(|sum| self.table[2 * sum + center] == 1).map(|sum| sum.to_string()).collect()
+        };
+        Some(format!("B{}/S{}", sums(0), sums(1)))
+    }
+
+    /// A rule drawn uniformly from the family's codes with `colors` colors
+    /// and radius `radius`, one table entry at a time
+    pub fn random(colors: u8, radius: usize, family: Family, rng: &mut ChaCha8Rng) -> Result<Self, String> {
+        let zero = family.rule(colors, radius, "0")?;
+        let table = zero.table.iter().map(|_| rng.random_range(0..colors)).collect();
+        Ok(Self { table, ..zero })
+    }
+
+    fn check(colors: u8, radius: usize) -> Result<(), String> {
+        if !(2..=MAX_COLORS).contains(&colors) {
+            return Err(format!("k must be 2..={MAX_COLORS}, not {colors}"));
+        }
+        if !(1..=MAX_RADIUS).contains(&radius) {
+            return Err(format!("r must be 1..={MAX_RADIUS}, not {radius}"));
+        }
+        Ok(())
+    }
+
+    /// The rule's number, in decimal
+    pub fn code(&self) -> String {
+        // Horner's rule on a little-endian vector of decimal digits
+        let mut decimal = vec![0u32];
+        for &digit in self.table.iter().rev() {
+            let mut carry = digit as u32;
+            for d in decimal.iter_mut() {
+                let value = *d * self.colors as u32 + carry;
+                *d = value % 10;
+                carry = value / 10;
+            }
+            while carry > 0 {
+                decimal.push(carry % 10);
+                carry /= 10;
+            }
+        }
+        decimal.iter().rev().map(|&d| char::from_digit(d, 10).unwrap()).collect()
+    }
+
+    /// The next row of a ring
+    pub fn step(&self, cells: &[u8]) -> Vec<u8> {
+        let (n, r, k) = (cells.len(), self.radius, self.colors as usize);
+        (0..n)
+            .map(|i| {
+                let neighborhood = (0..=2 * r).map(|j| cells[(i + n * r + j - r) % n] as usize);
+                let index = match self.family {
+                    Family::General => neighborhood.fold(0, |v, c| v * k + c),
+                    Family::Totalistic => neighborhood.sum(),
+                    Family::OuterTotalistic => {
+                        let center = cells[i] as usize;
+                        k * (neighborhood.sum::<usize>() - center) + center
+                    }
+                };
+                self.table[index]
+            })
+            .collect()
+    }
+
+    /// The left-right reflection: each neighborhood maps where its reverse
+    /// did. Totalistic and outer-totalistic rules are their own mirrors
+    pub fn mirror(&self) -> WolframRule {
+        if self.family != Family::General {
+            return self.clone();
+        }
+        let (k, n) = (self.colors as usize, 2 * self.radius + 1);
+        let reverse = |w: usize| (0..n).fold((0, w), |(r, w), _| (r * k + w % k, w / k)).0;
+        let table = (0..self.table.len()).map(|w| self.table[reverse(w)]).collect();
+        WolframRule { table, ..self.clone() }
+    }
+
+    /// The color conjugate: every color c read and written as k - 1 - c
+    pub fn complement(&self) -> WolframRule {
+        let (k, n) = (self.colors as usize, 2 * self.radius + 1);
+        let top = self.colors - 1;
+        let entries = self.table.len();
+        let table = (0..entries)
+            .map(|index| {
+                let conjugate = match self.family {
+                    // Every digit flipped
+                    Family::General => entries - 1 - index,
+                    Family::Totalistic => (k - 1) * n - index,
+                    Family::OuterTotalistic => {
+                        let (sum, center) = (index / k, index % k);
+                        k * ((k - 1) * (n - 1) - sum) + (k - 1 - center)
+                    }
+                };
+                top - self.table[conjugate]
+            })
+            .collect();
+        WolframRule { table, ..self.clone() }
+    }
+
+    /// The smallest code among the rule, its mirror, its complement and
+    /// both, in the same family: one name for rules that behave alike
+    pub fn canonical(&self) -> WolframRule {
+        let mirror = self.mirror();
+        let candidates = [self.complement(), mirror.complement(), mirror, self.clone()];
+        // Tables are least significant digit first
+        candidates.into_iter().min_by(|a, b| a.table.iter().rev().cmp(b.table.iter().rev())).unwrap()
+    }
+
+    /// The same rule as a general one, listing every neighborhood: None if
+    /// there are too many to list
+    pub fn to_general(&self) -> Option<WolframRule> {
+        let (k, n) = (self.colors as usize, 2 * self.radius + 1);
+        let entries = k.checked_pow(n as u32).filter(|&entries| entries <= MAX_ENTRIES)?;
+        let table = (0..entries)
+            .map(|w| {
+                let cells = (0..n).map(|j| w / k.pow((n - 1 - j) as u32) % k);
+                let index = match self.family {
+                    Family::General => w,
+                    Family::Totalistic => cells.sum(),
+                    Family::OuterTotalistic => {
+                        let center = w / k.pow(self.radius as u32) % k;
+                        k * (cells.sum::<usize>() - center) + center
+                    }
+                };
+                self.table[index]
+            })
+            .collect();
+        Some(WolframRule { family: Family::General, table, ..*self })
+    }
+
+    /// The same rule as a two-color lookup table
+    pub fn table_rule(&self) -> Option<TableRule> {
+        if self.colors != 2 {
+            return None;
+        }
+        let entries = 1 << (2 * self.radius + 1);
+        let table = (0..entries).fold(0u128, |t, w: usize| {
+            let center = w >> self.radius & 1;
+            let index = match self.family {
+                Family::General => w,
+                Family::Totalistic => w.count_ones() as usize,
+                Family::OuterTotalistic => 2 * (w.count_ones() as usize - center) + center,
+            };
+            t | (self.table[index] as u128) << w
+        });
+        Some(TableRule::new(self.radius, table))
+    }
+
+    /// The elementary rule number, for two colors and radius 1
+    pub fn elementary(&self) -> Option<u8> {
+        self.table_rule().filter(|rule| rule.radius == 1).map(|rule| rule.table as u8)
+    }
+}
+
+/// The kinds of code: general rules list the next state of every
+/// neighborhood, totalistic ones of every neighborhood sum, so they are
+/// mirror-symmetric and far fewer (k^((k-1)(2r+1)+1) rather than k^(k^(2r+1))),
+/// and outer-totalistic ones of every center color and sum of the rest
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Family {
+    #[default]
+    General,
+    Totalistic,
+    OuterTotalistic,
+}
+
+impl Family {
+    /// The family's rule with number `code` (decimal digits)
+    pub fn rule(self, colors: u8, radius: usize, code: &str) -> Result<WolframRule, String> {
+        match self {
+            Family::General => WolframRule::general(colors, radius, code),
+            Family::Totalistic => WolframRule::totalistic(colors, radius, code),
+            Family::OuterTotalistic => WolframRule::outer_totalistic(colors, radius, code),
+        }
+    }
+
+    /// How many codes the family has for `colors` and `radius`: None when
+    /// the count overflows a u64
+    pub fn size(self, colors: u8, radius: usize) -> Result<Option<u64>, String> {
+        let entries = self.rule(colors, radius, "0")?.table.len();
+        Ok(u32::try_from(entries).ok().and_then(|entries| (colors as u64).checked_pow(entries)))
+    }
+}
+
+impl fmt::Display for Family {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            Family::General => "general",
+            Family::Totalistic => "totalistic",
+            Family::OuterTotalistic => "outer-totalistic",
+        })
+    }
+}
+
+impl FromStr for WolframRule {
+    type Err = String;
+
+    /// `k=<colors>,r=<radius>,code=<n>` (totalistic), `k=..,r=..,rule=<n>`
+    /// (general; k and r default to 2 and 1), `k=..,r=..,outer=<n>`
+    /// (outer-totalistic), `totalistic:<n>` for NKS's 3-color radius-1 codes
+    /// (`totalistic:<n>,k=..,r=..` for others), births and survivals as
+    /// `B<sums>/S<sums>[,r=..]`, or an elementary rule number, any of them
+    /// optionally after `wolfram:`
+    fn from_str(s: &str) -> Result<Self, String> {
+        let s = s.strip_prefix("wolfram:").unwrap_or(s);
+        if s.bytes().all(|b| b.is_ascii_digit()) && !s.is_empty() {
+            return Self::general(2, 1, s);
+        }
+        if let Some(rest) = s.strip_prefix('B') {
+            let (notation, radius) = rest.split_once(",r=").unwrap_or((rest, "1"));
+            let radius = radius.parse().map_err(|_| format!("bad r {radius:?}"))?;
+            let (births, survivals) = notation.split_once("/S").ok_or(format!("expected B<sums>/S<sums>, not {s:?}"))?;
+            let sums = |digits: &str| -> Result<Vec<usize>, String> {
+                let sum = |c: char| c.to_digit(10).map(|d| d as usize).ok_or(format!("{c:?} is not a sum"));
+                digits.chars().map(sum).collect()
+            };
+            return Self::life(radius, &sums(births)?, &sums(survivals)?);
+        }
+        let (mut colors, mut radius, mut code, mut family) = (None, None, None, Family::General);
+        let fields = match s.strip_prefix("totalistic:") {
+            Some(rest) => {
+                family = Family::Totalistic;
+                let (n, rest) = rest.split_once(',').unwrap_or((rest, ""));
+                code = Some(n);
+                (colors, radius) = (Some(3), Some(1));
+                rest
+            }
+            None => s,
+        };
+        for field in fields.split(',').filter(|f| !f.is_empty()) {
+            let (key, value) = field.split_once('=').ok_or(format!("expected key=value, not {field:?}"))?;
+            let bad = || format!("bad {key} {value:?}");
+            match key {
+                "k" => colors = Some(value.parse().map_err(|_| bad())?),
+                "r" => radius = Some(value.parse().map_err(|_| bad())?),
+                "code" => (code, family) = (Some(value), Family::Totalistic),
+                "outer" => (code, family) = (Some(value), Family::OuterTotalistic),
+                "rule" if family == Family::General => code = Some(value),
+                _ => return Err(format!("unknown key {key:?}: expected k, r, code, outer or rule")),
+            }
+        }
+        let code = code.ok_or("no rule number: give code=<n> (totalistic), outer=<n> or rule=<n> (general)")?;
+        family.rule(colors.unwrap_or(2), radius.unwrap_or(1), code)
+    }
+}
+
+impl fmt::Display for WolframRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.family {
+            Family::General => "rule",
+            Family::Totalistic => "code",
+            Family::OuterTotalistic => "outer",
+        };
+        write!(f, "k={},r={},{kind}={}", self.colors, self.radius, self.code())
+    }
+}
+
+/// A row of `width` cells of uniformly random colors, from a ChaCha8 stream
+/// seeded with `seed`
+pub fn random_row(colors: u8, width: usize, seed: u64) -> Vec<u8> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    (0..width).map(|_| rng.random_range(0..colors)).collect()
+}
+
+/// The `count` lowest base-`base` digits of a decimal number, least
+/// significant first; the number must have no more
+fn base_digits(decimal: &str, base: u8, count: usize) -> Result<Vec<u8>, String> {
+    if decimal.is_empty() || !decimal.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!("bad rule number {decimal:?}"));
+    }
+    let mut number: Vec<u8> = decimal.bytes().map(|b| b - b'0').collect();
+    let mut digits = Vec::with_capacity(count);
+    for _ in 0..count {
+        // Long division, most significant decimal digit first
+        let mut remainder = 0u32;
+        for d in number.iter_mut() {
+            let value = remainder * 10 + *d as u32;
+            *d = (value / base as u32) as u8;
+            remainder = value % base as u32;
+        }
+        digits.push(remainder as u8);
+    }
+    if number.iter().any(|&d| d != 0) {
+        return Err(format!("{decimal} is too big: {count} base-{base} digits is the most this rule has"));
+    }
+    Ok(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Automaton;
+
+    #[test]
+    fn test_parse_and_print() {
+        let rule: WolframRule = "k=3,r=1,code=1635".parse().unwrap();
+        assert_eq!(rule.family, Family::Totalistic);
+        // 1635 = 2020120 in base 3
+        assert_eq!(rule.table, [0, 2, 1, 0, 2, 0, 2]);
+        assert_eq!(rule.to_string(), "k=3,r=1,code=1635");
+        assert_eq!("totalistic:1635".parse::<WolframRule>().unwrap(), rule);
+        assert_eq!("totalistic:777".parse::<WolframRule>().unwrap().code(), "777");
+
+        assert_eq!("k=2,r=1,rule=110".parse::<WolframRule>().unwrap().elementary(), Some(110));
+        assert_eq!("110".parse::<WolframRule>().unwrap().to_string(), "k=2,r=1,rule=110");
+        let big = "340282366920938463463374607431768211455";
+        assert_eq!(format!("k=2,r=3,rule={big}").parse::<WolframRule>().unwrap().code(), big);
+
+        // NKS's 2187 three-color codes; the 2^32 binary radius-2 rules; too many to count
+        assert_eq!(Family::Totalistic.size(3, 1), Ok(Some(2187)));
+        assert_eq!(Family::General.size(2, 2), Ok(Some(1 << 32)));
+        assert_eq!(Family::General.size(2, 3), Ok(None));
+        assert_eq!(Family::Totalistic.rule(3, 1, "1635"), Ok(rule.clone()));
+
+        // Outer-totalistic codes: digit 2 * sum + center, so B1/S12 sets digits 2, 3 and 5
+        let life: WolframRule = "B1/S12".parse().unwrap();
+        assert_eq!((life.family, life.table.clone()), (Family::OuterTotalistic, vec![0, 0, 1, 1, 0, 1]));
+        assert_eq!(life.to_string(), "k=2,r=1,outer=44");
+        assert_eq!("k=2,r=1,outer=44".parse::<WolframRule>().unwrap().life_notation().as_deref(), Some("B1/S12"));
+        assert_eq!("B36/S125,r=3".parse::<WolframRule>().unwrap().radius, 3);
+        assert_eq!(Family::OuterTotalistic.size(2, 2), Ok(Some(1 << 10)));
+        assert_eq!(Family::OuterTotalistic.size(3, 1), Ok(Some(3u64.pow(15))));
+
+        let bad = ["totalistic:2187", "256", "k=2,r=1", "k=1,rule=0", "r=4,code=1", "k=2,x=1,rule=3", "rule=-1"];
+        // Three neighbors is more than radius 1 has
+        for bad in bad.into_iter().chain(["B3/S23"]) {
+            assert!(bad.parse::<WolframRule>().is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn test_step_matches_automaton() {
+        // Totalistic code 10 for k = 2, r = 1: live when one or three cells are, which is rule 150
+        let rule: WolframRule = "k=2,r=1,code=10".parse().unwrap();
+        assert_eq!(rule.elementary(), Some(150));
+        for number in [30, 110, 150] {
+            let rule = WolframRule::general(2, 1, &number.to_string()).unwrap();
+            let mut ca = Automaton::new(31, number);
+            let mut cells: Vec<u8> = ca.cells().into_iter().map(u8::from).collect();
+            for _ in 0..20 {
+                ca.step();
+                cells = rule.step(&cells);
+                assert_eq!(cells, ca.cells().into_iter().map(u8::from).collect::<Vec<_>>(), "rule {number}");
+            }
+        }
+
+        // B1/S12: born beside exactly one live cell, surviving beside one or two, is rule 218
+        assert_eq!("B1/S12".parse::<WolframRule>().unwrap().elementary(), Some(218));
+        let rule: WolframRule = "B2/S,r=2".parse().unwrap();
+        let mut cells = vec![0; 17];
+        (cells[3], cells[5]) = (1, 1);
+        let live = cells.iter().map(|&c| c == 1).collect();
+        let mut ca = Automaton::from_cells(live, 0).with_table(rule.table_rule().unwrap());
+        for _ in 0..10 {
+            ca.step();
+            cells = rule.step(&cells);
+            assert_eq!(cells, ca.cells().into_iter().map(u8::from).collect::<Vec<_>>());
+        }
+
+        // Code 1635 from one cell of color 1
+        let rule: WolframRule = "totalistic:1635".parse().unwrap();
+        assert_eq!(rule.step(&[0, 0, 1, 0, 0]), [0, 2, 2, 2, 0]);
+        assert_eq!(rule.step(&[0, 2, 2, 2, 0]), [1, 2, 2, 2, 1]);
+    }
+
+    #[test]
+    fn test_symmetries() {
+        // As the elementary rules' and the lookup tables' own
+        for number in 0..=255u8 {
+            let rule = WolframRule::general(2, 1, &number.to_string()).unwrap();
+            assert_eq!(rule.mirror().elementary(), Some(crate::symmetry::mirror(number)));
+            assert_eq!(rule.complement().elementary(), Some(crate::symmetry::complement(number)));
+            assert_eq!(rule.canonical().elementary(), Some(crate::symmetry::representative(number)));
+        }
+        for code in [0, 44, 333, 1000] {
+            let rule = WolframRule::outer_totalistic(2, 2, &code.to_string()).unwrap();
+            let table = rule.table_rule().unwrap();
+            assert_eq!(rule.complement().table_rule(), Some(table.complement()));
+            // The smallest code, which needn't be the smallest table
+            assert!(table.equivalents(true).contains(&rule.canonical().table_rule().unwrap()));
+            assert_eq!(rule.canonical().family, Family::OuterTotalistic);
+        }
+
+        // Three colors: the complement steps the conjugate row to the
+        // conjugate, the mirror the reversed row to the reverse
+        let row = random_row(3, 23, 5);
+        let conjugate = |row: &[u8]| row.iter().map(|&c| 2 - c).collect::<Vec<_>>();
+        let reverse = |row: &[u8]| row.iter().rev().copied().collect::<Vec<_>>();
+        for spec in ["totalistic:1635", "k=3,r=1,outer=11589501", "k=3,r=1,rule=7479532539765"] {
+            let rule: WolframRule = spec.parse().unwrap();
+            assert_eq!(rule.complement().step(&conjugate(&row)), conjugate(&rule.step(&row)), "{spec}");
+            assert_eq!(rule.mirror().step(&reverse(&row)), reverse(&rule.step(&row)), "{spec}");
+            assert_eq!(rule.canonical().canonical(), rule.canonical());
+        }
+    }
+}
@@ -0,0 +1,127 @@
+//! The `automata` library compiled for the browser
+//!
+//! A thin wasm-bindgen wrapper, like the CLI in `src/main.rs`: an `Explorer`
+//! owns one `Automaton` and hands rows to JavaScript as byte arrays, which
+//! `www/main.js` paints onto a canvas. Analyses call the same library
+//! functions as the commands, so a rule behaves identically in both.
+
+use automata::{check_block, compression_ratio_from, find_cycle_from, Automaton};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct Explorer {
+    initial: Automaton,
+    ca: Automaton,
+    generation: usize,
+}
+
+#[wasm_bindgen]
+impl Explorer {
+    /// `init` is `single` (one live cell in the center) or `random` (each
+    /// cell live with probability `density`, seeded as in the CLI's `--seed`)
+    #[wasm_bindgen(constructor)]
+    pub fn new(rule: u8, width: usize, init: &str, density: f64, seed: u64) -> Result<Explorer, JsError> {
+        if width == 0 {
+            return Err(JsError::new("width must be at least 1"));
+        }
+        let initial = match init {
+            "single" => Automaton::new(width, rule),
+            "random" if (0.0..=1.0).contains(&density) => Automaton::random(width, rule, density, seed),
+            "random" => return Err(JsError::new("density must be in [0, 1]")),
+            _ => return Err(JsError::new(&format!("unknown init '{init}' (expected single or random)"))),
+        };
+        Ok(Self { ca: initial.clone(), initial, generation: 0 })
+    }
+
+    pub fn rule(&self) -> u8 {
+        self.ca.rule()
+    }
+
+    pub fn width(&self) -> usize {
+        self.ca.width()
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Advance `n` generations
+    pub fn step(&mut self, n: usize) {
+        for _ in 0..n {
+            self.ca.step();
+        }
+        self.generation += n;
+    }
+
+    /// The current row, one byte (0 or 1) per cell
+    pub fn cells(&self) -> Vec<u8> {
+        self.ca.iter().map(u8::from).collect()
+    }
+
+    /// Flip cell `i` of the initial row and start over from it
+    pub fn toggle(&mut self, i: usize) {
+        let mut cells = self.initial.cells();
+        if let Some(cell) = cells.get_mut(i) {
+            *cell = !*cell;
+        }
+        self.initial = Automaton::from_cells(cells, self.initial.rule());
+        self.restart();
+    }
+
+    /// Switch to `rule` and start over from the initial row
+    pub fn set_rule(&mut self, rule: u8) {
+        self.initial = self.initial.with_rule(rule);
+        self.restart();
+    }
+
+    pub fn restart(&mut self) {
+        self.ca = self.initial.clone();
+        self.generation = 0;
+    }
+
+    pub fn density(&self) -> f64 {
+        self.ca.density()
+    }
+
+    /// k-block entropy of the current row, in bits, for 1 <= k <= width
+    pub fn block_entropy(&self, k: usize) -> Result<f64, JsError> {
+        check_block(k, self.ca.width())?;
+        Ok(self.ca.block_entropy(k))
+    }
+
+    /// `[transient, period]` from the current row as `find_cycle_from`
+    /// reports it (period 0: no cycle within `max_steps`)
+    pub fn cycle(&self, max_steps: usize) -> Vec<u32> {
+        let analysis = find_cycle_from(self.ca.clone(), max_steps);
+        vec![analysis.transient as u32, analysis.period as u32]
+    }
+
+    /// Compressed/raw size of the next `generations` rows, as `compress` reports it
+    pub fn compression_ratio(&self, generations: usize) -> f64 {
+        compression_ratio_from(self.ca.clone(), generations).2
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explorer_matches_automaton() {
+        let mut explorer = Explorer::new(90, 7, "single", 0.5, 0).unwrap_or_else(|_| unreachable!());
+        explorer.step(1);
+        assert_eq!(explorer.cells(), [0, 0, 1, 0, 1, 0, 0]);
+
+        // Editing the initial row restarts from it
+        explorer.toggle(0);
+        assert_eq!((explorer.generation(), explorer.cells()), (0, vec![1, 0, 0, 1, 0, 0, 0]));
+        explorer.set_rule(204);
+        explorer.step(5);
+        assert_eq!(explorer.cells(), [1, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(explorer.cycle(10), [0, 1]);
+        // Two live cells of seven, each as a 1-block
+        let entropy = explorer.block_entropy(1).unwrap_or_else(|_| unreachable!());
+        assert_eq!(entropy, explorer.ca.block_entropy(1));
+        assert!(entropy > 0.86 && entropy < 0.87);
+    }
+}
@@ -0,0 +1,126 @@
+// Canvas viewer for the wasm build: one pixel per cell, the initial row at
+// the top until the run fills the canvas, then scrolling a row per generation
+
+import init, { Explorer } from "./pkg/automata_wasm.js";
+
+const ROWS = 300;
+const SCALE = 2;
+const LIVE = [0, 0, 0];
+const DEAD = [255, 255, 255];
+
+const $ = (id) => document.getElementById(id);
+const canvas = $("canvas");
+const context = canvas.getContext("2d");
+
+let explorer = null;
+let image = null;
+let filled = 0;
+let paused = false;
+let lastStep = 0;
+
+function start() {
+  const width = Number($("width").value);
+  explorer?.free();
+  try {
+    explorer = new Explorer(Number($("rule").value), width, $("init").value, Number($("density").value),
+      BigInt($("seed").value));
+  } catch (e) {
+    $("stats").textContent = e.message;
+    explorer = null;
+    return;
+  }
+  canvas.width = width;
+  canvas.height = ROWS;
+  canvas.style.width = `${width * SCALE}px`;
+  canvas.style.height = `${ROWS * SCALE}px`;
+  image = context.createImageData(width, ROWS);
+  filled = 0;
+  restart();
+}
+
+// Clear the canvas and draw the explorer's initial row
+function restart() {
+  explorer.restart();
+  image.data.fill(0);
+  filled = 0;
+  drawRow();
+}
+
+// Paint the current row below the last, scrolling once the canvas is full
+function drawRow() {
+  const width = explorer.width();
+  const rowBytes = width * 4;
+  if (filled === ROWS) {
+    image.data.copyWithin(0, rowBytes);
+    filled -= 1;
+  }
+  const cells = explorer.cells();
+  const offset = filled * rowBytes;
+  for (let i = 0; i < width; i++) {
+    const [r, g, b] = cells[i] ? LIVE : DEAD;
+    image.data.set([r, g, b, 255], offset + 4 * i);
+  }
+  filled += 1;
+  context.putImageData(image, 0, 0);
+  showStats();
+}
+
+function showStats() {
+  const k = Math.min(3, explorer.width());
+  $("stats").textContent = `rule ${explorer.rule()} | gen ${explorer.generation()} | density `
+    + `${explorer.density().toFixed(3)} | ${k}-block entropy ${explorer.block_entropy(k).toFixed(3)} bits`;
+}
+
+function step() {
+  explorer.step(1);
+  drawRow();
+}
+
+function frame(time) {
+  const interval = 1000 / Number($("speed").value);
+  if (explorer && !paused && time - lastStep >= interval) {
+    // Catch up after a slow frame, but never more than a screenful
+    const due = Math.min(ROWS, Math.floor((time - lastStep) / interval));
+    for (let i = 0; i < due; i++) step();
+    lastStep = time;
+  }
+  requestAnimationFrame(frame);
+}
+
+function setRule(rule) {
+  $("rule").value = (rule + 256) % 256;
+  explorer.set_rule(Number($("rule").value));
+  restart();
+}
+
+await init();
+
+$("start").onclick = start;
+$("rule").onchange = () => explorer ? setRule(Number($("rule").value)) : start();
+for (const id of ["width", "init", "density", "seed"]) $(id).onchange = start;
+$("pause").onclick = () => {
+  paused = !paused;
+  $("pause").textContent = paused ? "Run" : "Pause";
+};
+$("step").onclick = () => explorer && step();
+$("cycle").onclick = () => {
+  if (!explorer) return;
+  const [transient, period] = explorer.cycle(10000);
+  $("stats").textContent += period ? ` | from here: transient ${transient}, period ${period}`
+    : " | no cycle within 10000 steps";
+};
+canvas.onclick = (event) => {
+  if (!explorer) return;
+  const column = Math.floor((event.offsetX / canvas.clientWidth) * explorer.width());
+  explorer.toggle(column);
+  restart();
+};
+document.onkeydown = (event) => {
+  if (!explorer || event.target.tagName === "INPUT") return;
+  if (event.key === "ArrowUp" || event.key === "ArrowRight") setRule(explorer.rule() + 1);
+  if (event.key === "ArrowDown" || event.key === "ArrowLeft") setRule(explorer.rule() - 1);
+  if (event.key === " ") $("pause").click();
+};
+
+start();
+requestAnimationFrame(frame);